@@ -0,0 +1,18 @@
+//! Generates the gRPC server and client from `proto/ess/v1/ess.proto` when
+//! the `grpc` feature is on; other builds have nothing to generate.
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "grpc")]
+    compile_protos();
+}
+
+#[cfg(feature = "grpc")]
+fn compile_protos() {
+    // A vendored protoc, so the feature builds without one installed.
+    let protoc = protoc_bin_vendored::protoc_bin_path().expect("locate the vendored protoc");
+    std::env::set_var("PROTOC", protoc);
+    tonic_build::configure()
+        .compile_protos(&["proto/ess/v1/ess.proto"], &["proto"])
+        .expect("compile proto/ess/v1/ess.proto");
+}
@@ -16,11 +16,17 @@ chmod +x "${BIN_DIR}/ess"
 
 echo "Installed: ${BIN_DIR}/ess"
 
-CONFIG_DIR="${HOME}/.ess"
+if [[ "$(uname -s)" == "Darwin" ]]; then
+  CONFIG_DIR="${HOME}/Library/Application Support/ess"
+else
+  CONFIG_DIR="${XDG_CONFIG_HOME:-${HOME}/.config}/ess"
+fi
 CONFIG_FILE="${CONFIG_DIR}/config.toml"
-mkdir -p "${CONFIG_DIR}"
 
-if [[ ! -f "${CONFIG_FILE}" ]]; then
+if [[ -f "${HOME}/.ess/config.toml" && ! -L "${HOME}/.ess" ]]; then
+  echo "Found ${HOME}/.ess; ess moves it to ${CONFIG_DIR} and the XDG data directory on its next run"
+elif [[ ! -f "${CONFIG_FILE}" ]]; then
+  mkdir -p "${CONFIG_DIR}"
   cat > "${CONFIG_FILE}" <<'TOML'
 [general]
 default_scope = "all"
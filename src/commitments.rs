@@ -0,0 +1,609 @@
+//! Promises made in sent mail, such as "I'll send the deck by Friday".
+//!
+//! After each sync, emails sent from an account address are read once: a
+//! set of patterns finds sentences where the sender commits to doing
+//! something, and a due date is taken from phrases like `by Friday`,
+//! `tomorrow`, `end of the week` or `March 6`, counted from the day the
+//! email was sent. An optional model endpoint, set under `[commitments]`
+//! in `config.toml`, gets each email with what the patterns found and may
+//! add commitments of its own. Results land in the `followups` table for
+//! `ess commitments`.
+//!
+//! ```toml
+//! [commitments]
+//! model_url = "http://127.0.0.1:8000/commitments"
+//! ```
+
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Days, Local, Months, NaiveDate, Weekday};
+use regex::Regex;
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use thiserror::Error;
+
+use crate::config::Config;
+use crate::db::followups::{FoundFollowup, ScannedEmail};
+use crate::db::models::Email;
+use crate::db::reply_signals::own_text;
+use crate::db::Database;
+
+pub const RULE_SOURCE: &str = "rule";
+pub const MODEL_SOURCE: &str = "model";
+
+/// Emails scanned per database transaction.
+const BATCH_SIZE: usize = 500;
+/// Characters of the body sent to the model.
+const MAX_MODEL_BODY_CHARS: usize = 4_000;
+/// Longest stored commitment sentence, in characters.
+const MAX_TEXT_CHARS: usize = 300;
+pub const DEFAULT_MODEL_TIMEOUT_SECS: u64 = 10;
+
+/// "I'll", "I will", "I'm going to", "let me", "we'll" and so on, a few
+/// softening words, then a verb of doing something for someone.
+static COMMITMENT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?ix)
+        \b(?: i \s* (?:'|’)ll | i \s+ will | i \s+ shall | i \s* (?:'|’)m \s+ going \s+ to
+            | i \s+ am \s+ going \s+ to | let \s+ me | we \s* (?:'|’)ll | we \s+ will )
+        \s+
+        (?: (?: also | then | just | definitely | certainly | try \s+ to | make \s+ sure \s+ to
+              | be \s+ able \s+ to | need \s+ to | aim \s+ to ) \s+ )*
+        (?: send | share | get \s+ back | follow \s+ up | review | look \s+ into | check
+          | reply | respond | update | circle \s+ back | call | schedule | confirm | prepare
+          | draft | finish | deliver | forward | write | book | set \s+ up | put \s+ together
+          | submit | provide | introduce | ping | sort \s+ out | take \s+ care \s+ of | have
+          | get | loop )
+        \b",
+    )
+    .expect("valid commitment pattern")
+});
+
+static ISO_DATE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(\d{4})-(\d{2})-(\d{2})\b").expect("valid date pattern"));
+
+static IN_DAYS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\bin\s+(\d{1,2}|two|three|four|five)\s+(?:business\s+|working\s+)?days?\b")
+        .expect("valid offset pattern")
+});
+
+static WEEKDAY: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)\b(?:by|on|before|until|till|this|next)\s+(?:(next|this)\s+)?(monday|tuesday|wednesday|thursday|friday|saturday|sunday|mon|tue|tues|wed|thu|thur|thurs|fri|sat|sun)\b",
+    )
+    .expect("valid weekday pattern")
+});
+
+static MONTH_DAY: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)\b(?:(jan|feb|mar|apr|may|jun|jul|aug|sep|sept|oct|nov|dec)[a-z]*\.?\s+(\d{1,2})(?:st|nd|rd|th)?|(\d{1,2})(?:st|nd|rd|th)?\s+(?:of\s+)?(jan|feb|mar|apr|may|jun|jul|aug|sep|sept|oct|nov|dec)[a-z]*)\b",
+    )
+    .expect("valid month pattern")
+});
+
+#[derive(Debug, Error)]
+pub enum CommitmentModelError {
+    #[error("commitment model request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("commitment model returned {status}: {body}")]
+    Api { status: u16, body: String },
+}
+
+/// A promise found in an email.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Commitment {
+    pub text: String,
+    #[serde(default)]
+    pub due: Option<NaiveDate>,
+}
+
+/// Finds commitments beyond what the patterns find, e.g. a local LLM.
+#[async_trait(?Send)]
+pub trait CommitmentModel {
+    /// Extra commitments in `email`, given the ones the patterns found.
+    async fn commitments(
+        &self,
+        email: &Email,
+        found: &[Commitment],
+    ) -> Result<Vec<Commitment>, CommitmentModelError>;
+}
+
+/// The patterns and, when configured, the model.
+#[derive(Default)]
+pub struct CommitmentExtractor {
+    model: Option<Box<dyn CommitmentModel>>,
+}
+
+impl std::fmt::Debug for CommitmentExtractor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CommitmentExtractor")
+            .field("model", &self.model.is_some())
+            .finish()
+    }
+}
+
+/// What [`extract_pending`] did.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CommitmentReport {
+    /// Sent emails scanned.
+    pub emails: usize,
+    /// Commitments found in them.
+    pub found: usize,
+    /// Emails scanned with the patterns alone because the model failed; they
+    /// are scanned again on the next run.
+    pub model_skipped: usize,
+}
+
+impl CommitmentExtractor {
+    /// The extractor `[commitments]` in `config.toml` describes.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let model = config
+            .commitments
+            .model_url
+            .as_deref()
+            .map(|url| -> Result<Box<dyn CommitmentModel>> {
+                Ok(Box::new(HttpCommitmentModel::new(
+                    url,
+                    Duration::from_secs(
+                        config
+                            .commitments
+                            .model_timeout_secs
+                            .unwrap_or(DEFAULT_MODEL_TIMEOUT_SECS),
+                    ),
+                )?))
+            })
+            .transpose()?;
+        Ok(Self { model })
+    }
+
+    /// Replaces the model endpoint, or adds one.
+    pub fn with_model(mut self, model: Box<dyn CommitmentModel>) -> Self {
+        self.model = Some(model);
+        self
+    }
+}
+
+/// Commitments the patterns find in the sender's own text of `email`, due
+/// dates counted from the day it was sent.
+pub fn find_commitments(email: &Email) -> Vec<Commitment> {
+    let sent = DateTime::parse_from_rfc3339(email.sent_at.as_deref().unwrap_or(&email.received_at))
+        .or_else(|_| DateTime::parse_from_rfc3339(&email.received_at))
+        .map(|sent| sent.with_timezone(&Local).date_naive())
+        .unwrap_or_else(|_| Local::now().date_naive());
+    let mut found: Vec<Commitment> = Vec::new();
+    for sentence in sentences(&own_text(email)) {
+        if sentence.ends_with('?') || !COMMITMENT.is_match(&sentence) {
+            continue;
+        }
+        let text: String = sentence.chars().take(MAX_TEXT_CHARS).collect();
+        if !found.iter().any(|existing| existing.text == text) {
+            found.push(Commitment {
+                due: due_date(&sentence, sent),
+                text,
+            });
+        }
+    }
+    found
+}
+
+/// Sentences with whitespace collapsed; a blank line ends one too.
+fn sentences(text: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    for paragraph in text.split("\n\n") {
+        let paragraph = paragraph.split_whitespace().collect::<Vec<_>>().join(" ");
+        let mut start = 0;
+        let chars: Vec<(usize, char)> = paragraph.char_indices().collect();
+        for (position, &(index, ch)) in chars.iter().enumerate() {
+            let ends = matches!(ch, '.' | '!' | '?')
+                && chars
+                    .get(position + 1)
+                    .is_none_or(|&(_, next)| next.is_whitespace());
+            if ends {
+                let end = index + ch.len_utf8();
+                sentences.push(paragraph[start..end].trim().to_string());
+                start = end;
+            }
+        }
+        sentences.push(paragraph[start..].trim().to_string());
+    }
+    sentences.retain(|sentence| !sentence.is_empty());
+    sentences
+}
+
+/// The first deadline phrase in `sentence`, resolved against `sent`.
+fn due_date(sentence: &str, sent: NaiveDate) -> Option<NaiveDate> {
+    let lower = sentence.to_lowercase();
+    let has = |phrases: &[&str]| phrases.iter().any(|phrase| contains_words(&lower, phrase));
+
+    if let Some(captures) = ISO_DATE.captures(&lower) {
+        return NaiveDate::from_ymd_opt(
+            captures[1].parse().ok()?,
+            captures[2].parse().ok()?,
+            captures[3].parse().ok()?,
+        );
+    }
+    if let Some(captures) = MONTH_DAY.captures(&lower) {
+        let (month, day) = match (captures.get(1), captures.get(2)) {
+            (Some(month), Some(day)) => (month.as_str(), day.as_str()),
+            _ => (captures.get(4)?.as_str(), captures.get(3)?.as_str()),
+        };
+        let month = month_number(month)?;
+        let day: u32 = day.parse().ok()?;
+        let this_year = NaiveDate::from_ymd_opt(sent.year(), month, day)?;
+        return if this_year < sent {
+            NaiveDate::from_ymd_opt(sent.year() + 1, month, day)
+        } else {
+            Some(this_year)
+        };
+    }
+    if let Some(captures) = WEEKDAY.captures(&lower) {
+        let next = next_weekday(sent, parse_weekday(&captures[2])?);
+        let says_next = captures[0].split_whitespace().any(|word| word == "next");
+        // "next Friday" written on a Monday is the Friday of the week after.
+        return if says_next && next.iso_week() == sent.iso_week() {
+            next.checked_add_days(Days::new(7))
+        } else {
+            Some(next)
+        };
+    }
+    if let Some(captures) = IN_DAYS.captures(&lower) {
+        let days = match &captures[1] {
+            "two" => 2,
+            "three" => 3,
+            "four" => 4,
+            "five" => 5,
+            digits => digits.parse().ok()?,
+        };
+        return sent.checked_add_days(Days::new(days));
+    }
+    if has(&[
+        "today",
+        "tonight",
+        "end of day",
+        "end of the day",
+        "eod",
+        "cob",
+        "this afternoon",
+        "this evening",
+    ]) {
+        return Some(sent);
+    }
+    if has(&["tomorrow"]) {
+        return sent.succ_opt();
+    }
+    if has(&["end of the week", "end of week", "eow", "this week"]) {
+        return Some(next_weekday(sent - chrono::Duration::days(1), Weekday::Fri));
+    }
+    if has(&["next week"]) {
+        return next_weekday(sent, Weekday::Mon).checked_add_days(Days::new(4));
+    }
+    if has(&["end of the month", "end of month", "eom", "this month"]) {
+        let first = NaiveDate::from_ymd_opt(sent.year(), sent.month(), 1)?;
+        return first.checked_add_months(Months::new(1))?.pred_opt();
+    }
+    None
+}
+
+/// Whether `phrase` appears in `text` on word boundaries.
+fn contains_words(text: &str, phrase: &str) -> bool {
+    text.match_indices(phrase).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + phrase.len()..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
+
+/// The first `weekday` after `day`.
+fn next_weekday(day: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let ahead = (7 + weekday.num_days_from_monday() - day.weekday().num_days_from_monday()) % 7;
+    day + chrono::Duration::days(if ahead == 0 { 7 } else { i64::from(ahead) })
+}
+
+fn parse_weekday(value: &str) -> Option<Weekday> {
+    Some(match &value[..3] {
+        "mon" => Weekday::Mon,
+        "tue" => Weekday::Tue,
+        "wed" => Weekday::Wed,
+        "thu" => Weekday::Thu,
+        "fri" => Weekday::Fri,
+        "sat" => Weekday::Sat,
+        "sun" => Weekday::Sun,
+        _ => return None,
+    })
+}
+
+fn month_number(value: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    MONTHS
+        .iter()
+        .position(|month| value.starts_with(month))
+        .map(|index| index as u32 + 1)
+}
+
+/// Scans every sent email not scanned yet, in batches. After the first
+/// model failure the rest are scanned by the patterns alone and left for
+/// the next run to complete.
+pub async fn extract_pending(
+    db: &Database,
+    extractor: &CommitmentExtractor,
+) -> Result<CommitmentReport> {
+    db.prune_followups()?;
+    let mut report = CommitmentReport::default();
+    let mut model_failed = false;
+    let mut after: Option<String> = None;
+    loop {
+        let batch =
+            db.sent_emails_to_scan(after.as_deref(), BATCH_SIZE, extractor.model.is_some())?;
+        let Some(last) = batch.last() else {
+            break;
+        };
+        after = Some(last.id.clone());
+
+        let mut scanned = Vec::with_capacity(batch.len());
+        for email in &batch {
+            let rule_found = find_commitments(email);
+            let mut found: Vec<FoundFollowup> = rule_found
+                .iter()
+                .map(|commitment| FoundFollowup {
+                    text: commitment.text.clone(),
+                    due_date: commitment.due,
+                    source: RULE_SOURCE.to_string(),
+                })
+                .collect();
+            let mut complete = true;
+            match extractor.model.as_deref() {
+                Some(_) if model_failed => {
+                    complete = false;
+                    report.model_skipped += 1;
+                }
+                Some(model) => match model.commitments(email, &rule_found).await {
+                    Ok(extra) => {
+                        for commitment in extra {
+                            let text: String = commitment
+                                .text
+                                .trim()
+                                .chars()
+                                .take(MAX_TEXT_CHARS)
+                                .collect();
+                            if !text.is_empty() && !found.iter().any(|known| known.text == text) {
+                                found.push(FoundFollowup {
+                                    text,
+                                    due_date: commitment.due,
+                                    source: MODEL_SOURCE.to_string(),
+                                });
+                            }
+                        }
+                    }
+                    Err(error) => {
+                        tracing::warn!(
+                            "commitment model failed on {}: {error}; using patterns only",
+                            email.id
+                        );
+                        model_failed = true;
+                        complete = false;
+                        report.model_skipped += 1;
+                    }
+                },
+                None => {}
+            }
+            report.emails += 1;
+            report.found += found.len();
+            scanned.push(ScannedEmail {
+                email_id: email.id.clone(),
+                found,
+                complete,
+            });
+        }
+        db.store_commitment_scans(&scanned)?;
+    }
+    Ok(report)
+}
+
+/// `POST {url}` with the email as JSON, answered by
+/// `{"commitments": [{"text": ..., "due": "YYYY-MM-DD"}]}`.
+#[derive(Debug, Clone)]
+pub struct HttpCommitmentModel {
+    client: Client,
+    url: Url,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelResponse {
+    #[serde(default)]
+    commitments: Vec<Commitment>,
+}
+
+impl HttpCommitmentModel {
+    pub fn new(url: &str, timeout: Duration) -> Result<Self> {
+        Ok(Self {
+            client: Client::builder()
+                .timeout(timeout)
+                .build()
+                .context("build commitment model client")?,
+            url: Url::parse(url)
+                .with_context(|| format!("invalid commitment model URL '{url}'"))?,
+        })
+    }
+}
+
+#[async_trait(?Send)]
+impl CommitmentModel for HttpCommitmentModel {
+    async fn commitments(
+        &self,
+        email: &Email,
+        found: &[Commitment],
+    ) -> Result<Vec<Commitment>, CommitmentModelError> {
+        let body: String = own_text(email).chars().take(MAX_MODEL_BODY_CHARS).collect();
+        let response = self
+            .client
+            .post(self.url.clone())
+            .json(&json!({
+                "id": email.id,
+                "subject": email.subject,
+                "to": email.to_addresses,
+                "sent_at": email.sent_at.as_deref().unwrap_or(&email.received_at),
+                "body": body,
+                "commitments": found,
+            }))
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(CommitmentModelError::Api {
+                status: status.as_u16(),
+                body: body.chars().take(500).collect(),
+            });
+        }
+        Ok(response.json::<ModelResponse>().await?.commitments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use chrono::NaiveDate;
+
+    use super::{
+        due_date, extract_pending, find_commitments, Commitment, CommitmentExtractor,
+        CommitmentModel, CommitmentModelError,
+    };
+    use crate::db::followups::COMMITMENT_KIND;
+    use crate::db::models::{Account, AccountType, Email};
+    use crate::db::Database;
+
+    fn email(id: &str, from: &str, body: &str) -> Email {
+        Email {
+            account_id: Some("work".to_string()),
+            subject: Some("Q3 deck".to_string()),
+            from_address: Some(from.to_string()),
+            to_addresses: vec!["bob@example.com".to_string()],
+            body_text: Some(body.to_string()),
+            received_at: "2026-03-02T12:00:00Z".to_string(),
+            sent_at: Some("2026-03-02T12:00:00Z".to_string()),
+            folder: Some("sentitems".to_string()),
+            ..Email::fixture(id)
+        }
+    }
+
+    fn day(value: &str) -> NaiveDate {
+        value.parse().expect("date")
+    }
+
+    struct Fails;
+
+    #[async_trait(?Send)]
+    impl CommitmentModel for Fails {
+        async fn commitments(
+            &self,
+            _: &Email,
+            _: &[Commitment],
+        ) -> Result<Vec<Commitment>, CommitmentModelError> {
+            Err(CommitmentModelError::Api {
+                status: 503,
+                body: "down".to_string(),
+            })
+        }
+    }
+
+    #[test]
+    fn patterns_find_promises_and_their_due_dates() {
+        let found = find_commitments(&email(
+            "a",
+            "me@example.com",
+            "Thanks Bob! I'll send the deck by Friday. Can you check it?\n\
+             Will you join? We will review the numbers\nnext week.\n\n\
+             On Sun, Mar 1, 2026 Bob <bob@example.com> wrote:\nI'll call you tomorrow.",
+        ));
+        let texts: Vec<_> = found.iter().map(|found| found.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            [
+                "I'll send the deck by Friday.",
+                "We will review the numbers next week."
+            ]
+        );
+
+        // 2026-03-02 is a Monday.
+        let monday = day("2026-03-02");
+        assert_eq!(due_date("by Friday", monday), Some(day("2026-03-06")));
+        assert_eq!(due_date("by next Friday", monday), Some(day("2026-03-13")));
+        assert_eq!(due_date("on Monday", monday), Some(day("2026-03-09")));
+        assert_eq!(due_date("tomorrow", monday), Some(day("2026-03-03")));
+        assert_eq!(due_date("by end of week", monday), Some(day("2026-03-06")));
+        assert_eq!(due_date("next week", monday), Some(day("2026-03-13")));
+        assert_eq!(due_date("in 3 days", monday), Some(day("2026-03-05")));
+        assert_eq!(
+            due_date("by the end of the month", monday),
+            Some(day("2026-03-31"))
+        );
+        assert_eq!(
+            due_date("by 12th of April", monday),
+            Some(day("2026-04-12"))
+        );
+        assert_eq!(due_date("by Jan 5", monday), Some(day("2027-01-05")));
+        assert_eq!(due_date("by 2026-04-01", monday), Some(day("2026-04-01")));
+        assert_eq!(due_date("when I can", monday), None);
+        assert_eq!(due_date("by eowyn", monday), None);
+    }
+
+    #[tokio::test]
+    async fn sent_mail_is_scanned_once_and_done_state_kept() {
+        let path =
+            std::env::temp_dir().join(format!("ess-commitments-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        db.insert_account(&Account {
+            account_id: "work".to_string(),
+            email_address: "me@example.com".to_string(),
+            display_name: None,
+            tenant_id: None,
+            account_type: AccountType::Professional,
+            enabled: true,
+            last_sync: None,
+            config: None,
+        })
+        .expect("insert account");
+        db.insert_email(&email(
+            "a",
+            "Me@Example.com",
+            "I'll send the deck by Friday.",
+        ))
+        .expect("insert a");
+        db.insert_email(&email("b", "bob@example.com", "I will call you tomorrow."))
+            .expect("insert b");
+
+        let failing = CommitmentExtractor::default().with_model(Box::new(Fails));
+        let report = extract_pending(&db, &failing).await.expect("extract");
+        assert_eq!(
+            (report.emails, report.found, report.model_skipped),
+            (1, 1, 1)
+        );
+        let open = db.followups(COMMITMENT_KIND, true).expect("followups");
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].due_date, Some(day("2026-03-06")));
+        assert_eq!(open[0].to_addresses, ["bob@example.com"]);
+        assert!(db.complete_followup(open[0].id).expect("complete"));
+        assert!(!db.complete_followup(999).expect("missing"));
+
+        let report = extract_pending(&db, &CommitmentExtractor::default())
+            .await
+            .expect("extract again");
+        assert_eq!(report.emails, 0);
+        let report = extract_pending(&db, &failing).await.expect("retry");
+        assert_eq!((report.emails, report.found), (1, 1));
+        assert!(db
+            .followups(COMMITMENT_KIND, true)
+            .expect("open")
+            .is_empty());
+        assert_eq!(db.followups(COMMITMENT_KIND, false).expect("all").len(), 1);
+
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,521 @@
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use super::{Config, WorkHours, COMMANDS_WITH_DEFAULTS, KNOWN_KEYS};
+use crate::env::{self, EnvValueKind};
+
+const VALID_SCOPES: &[&str] = &["all", "pro", "professional", "personal"];
+const VALID_ACCOUNT_TYPES: &[&str] = &["professional", "personal"];
+const SECRET_KEY_MARKERS: &[&str] = &["secret", "token", "password", "key"];
+const MASK: &str = "********";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Ok,
+    Warn,
+    Error,
+}
+
+impl std::fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            Self::Ok => "ok",
+            Self::Warn => "warn",
+            Self::Error => "error",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CheckItem {
+    pub area: String,
+    pub subject: String,
+    pub status: CheckStatus,
+    pub message: String,
+}
+
+/// Result of `ess config check`: individual findings plus the effective
+/// configuration with secrets masked.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ConfigCheckReport {
+    pub checks: Vec<CheckItem>,
+    pub effective: Map<String, Value>,
+}
+
+impl ConfigCheckReport {
+    pub fn push(
+        &mut self,
+        area: &str,
+        subject: impl Into<String>,
+        status: CheckStatus,
+        message: impl Into<String>,
+    ) {
+        self.checks.push(CheckItem {
+            area: area.to_string(),
+            subject: subject.into(),
+            status,
+            message: message.into(),
+        });
+    }
+
+    pub fn worst_status(&self) -> CheckStatus {
+        self.checks
+            .iter()
+            .map(|check| check.status)
+            .max()
+            .unwrap_or(CheckStatus::Ok)
+    }
+}
+
+/// Validates the config file at `path` and returns the parsed config when it
+/// could be read. Unknown sections and keys are warnings; invalid values are
+/// errors.
+pub fn check_config_file(path: &Path, report: &mut ConfigCheckReport) -> Option<Config> {
+    let subject = path.display().to_string();
+    let raw = match std::fs::read_to_string(path) {
+        Ok(raw) => raw,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            report.push(
+                "config",
+                subject,
+                CheckStatus::Warn,
+                "config file not found; built-in defaults apply",
+            );
+            return Some(Config::default());
+        }
+        Err(error) => {
+            report.push(
+                "config",
+                subject,
+                CheckStatus::Error,
+                format!("unreadable: {error}"),
+            );
+            return None;
+        }
+    };
+
+    let table = match raw.parse::<toml::Table>() {
+        Ok(table) => table,
+        Err(error) => {
+            report.push(
+                "config",
+                subject,
+                CheckStatus::Error,
+                format!("invalid TOML: {error}"),
+            );
+            return None;
+        }
+    };
+    for issue in unknown_keys(&table) {
+        report.push("config", &subject, CheckStatus::Warn, issue);
+    }
+
+    let config = match Config::parse(&raw) {
+        Ok(config) => config,
+        Err(error) => {
+            report.push("config", subject, CheckStatus::Error, error.to_string());
+            return None;
+        }
+    };
+
+    let mut valid = true;
+    let mut scopes = vec![(
+        "general.default_scope".to_string(),
+        config.general.default_scope.as_ref(),
+    )];
+    for command in COMMANDS_WITH_DEFAULTS {
+        let Some(defaults) = config.command_defaults(command) else {
+            continue;
+        };
+        scopes.push((format!("defaults.{command}.scope"), defaults.scope.as_ref()));
+        scopes.push((
+            format!("defaults.{command}.work_hours_scope"),
+            defaults.work_hours_scope.as_ref(),
+        ));
+        if defaults.limit == Some(0) {
+            valid = false;
+            report.push(
+                "config",
+                format!("defaults.{command}.limit"),
+                CheckStatus::Error,
+                "limit must be greater than zero",
+            );
+        }
+    }
+    for (key, scope) in scopes {
+        let Some(scope) = scope else {
+            continue;
+        };
+        if !VALID_SCOPES.contains(&scope.trim().to_ascii_lowercase().as_str()) {
+            valid = false;
+            report.push(
+                "config",
+                key,
+                CheckStatus::Error,
+                format!(
+                    "unknown scope '{scope}' (expected one of {})",
+                    VALID_SCOPES.join(", ")
+                ),
+            );
+        }
+    }
+    if let Err(error) = config.index_settings() {
+        valid = false;
+        report.push("config", "index", CheckStatus::Error, error.to_string());
+    }
+    if let Err(error) = config.sync_interval() {
+        valid = false;
+        report.push("config", "sync", CheckStatus::Error, error.to_string());
+    }
+    if let Err(error) = config.sync_schedule() {
+        valid = false;
+        report.push("config", "sync", CheckStatus::Error, error.to_string());
+    }
+    if let Err(error) = config.store_cap() {
+        valid = false;
+        report.push("config", "storage", CheckStatus::Error, error.to_string());
+    }
+    if let Err(error) = config.remote_url() {
+        valid = false;
+        report.push("config", "remote", CheckStatus::Error, error.to_string());
+    }
+    if let Some(hours) = &config.general.work_hours {
+        if let Err(error) = WorkHours::parse(hours) {
+            valid = false;
+            report.push(
+                "config",
+                "general.work_hours",
+                CheckStatus::Error,
+                error.to_string(),
+            );
+        }
+    }
+    for (name, entry) in &config.accounts {
+        let subject = format!("accounts.{name}");
+        if entry.email.is_none() && entry.account_id.is_none() {
+            valid = false;
+            report.push(
+                "config",
+                &subject,
+                CheckStatus::Error,
+                "needs at least one of 'email' or 'account_id'",
+            );
+        }
+        match entry.account_type.as_deref() {
+            Some(kind) if VALID_ACCOUNT_TYPES.contains(&kind) => {}
+            Some(kind) => {
+                valid = false;
+                report.push(
+                    "config",
+                    &subject,
+                    CheckStatus::Error,
+                    format!("unknown type '{kind}' (expected professional or personal)"),
+                );
+            }
+            None => report.push(
+                "config",
+                &subject,
+                CheckStatus::Warn,
+                "no 'type' set; account scope filtering will not apply",
+            ),
+        }
+    }
+    if valid {
+        report.push("config", subject, CheckStatus::Ok, "parsed successfully");
+    }
+    Some(config)
+}
+
+fn unknown_keys(table: &toml::Table) -> Vec<String> {
+    let mut issues = Vec::new();
+    for (section, value) in table {
+        match section.as_str() {
+            "general" => issues.extend(unknown_in_section(
+                "general",
+                value,
+                allowed_keys("general"),
+            )),
+            "index" => issues.extend(unknown_in_section("index", value, allowed_keys("index"))),
+            "storage" => issues.extend(unknown_in_section(
+                "storage",
+                value,
+                allowed_keys("storage"),
+            )),
+            "sync" => issues.extend(unknown_in_section("sync", value, allowed_keys("sync"))),
+            "needs_reply" => issues.extend(unknown_in_section(
+                "needs_reply",
+                value,
+                allowed_keys("needs_reply"),
+            )),
+            "commitments" => issues.extend(unknown_in_section(
+                "commitments",
+                value,
+                allowed_keys("commitments"),
+            )),
+            "defaults" => {
+                if let Some(commands) = value.as_table() {
+                    for (command, entry) in commands {
+                        if COMMANDS_WITH_DEFAULTS.contains(&command.as_str()) {
+                            issues.extend(unknown_in_section(
+                                &format!("defaults.{command}"),
+                                entry,
+                                allowed_keys("defaults.*"),
+                            ));
+                        } else {
+                            issues.push(format!("unknown section [defaults.{command}]"));
+                        }
+                    }
+                }
+            }
+            "accounts" => {
+                if let Some(accounts) = value.as_table() {
+                    for (name, entry) in accounts {
+                        issues.extend(unknown_in_section(
+                            &format!("accounts.{name}"),
+                            entry,
+                            allowed_keys("accounts.*"),
+                        ));
+                    }
+                }
+            }
+            other => issues.push(format!("unknown section [{other}]")),
+        }
+    }
+    issues
+}
+
+fn allowed_keys(section: &str) -> &'static [&'static str] {
+    KNOWN_KEYS
+        .iter()
+        .find(|(name, _)| *name == section)
+        .map(|(_, keys)| *keys)
+        .unwrap_or(&[])
+}
+
+fn unknown_in_section(section: &str, value: &toml::Value, allowed: &[&str]) -> Vec<String> {
+    let Some(table) = value.as_table() else {
+        return vec![format!("[{section}] should be a table")];
+    };
+    table
+        .keys()
+        .filter(|key| !allowed.contains(&key.as_str()))
+        .map(|key| format!("unknown key '{key}' in [{section}]"))
+        .collect()
+}
+
+/// Reports whether `path` exists and, for files holding mail or secrets,
+/// whether it is readable by other users.
+pub fn check_path(label: &str, path: &Path, private: bool, report: &mut ConfigCheckReport) {
+    let subject = format!("{label} ({})", path.display());
+    let metadata = match std::fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            report.push("paths", subject, CheckStatus::Warn, "does not exist yet");
+            return;
+        }
+        Err(error) => {
+            report.push(
+                "paths",
+                subject,
+                CheckStatus::Error,
+                format!("cannot stat: {error}"),
+            );
+            return;
+        }
+    };
+
+    if metadata.permissions().readonly() {
+        report.push("paths", subject, CheckStatus::Error, "not writable");
+        return;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = metadata.permissions().mode() & 0o777;
+        if private && mode & 0o077 != 0 {
+            report.push(
+                "paths",
+                subject,
+                CheckStatus::Warn,
+                format!("permissions {mode:o} allow access by other users; consider chmod 600/700"),
+            );
+            return;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = private;
+
+    report.push("paths", subject, CheckStatus::Ok, "present and writable");
+}
+
+/// Validates the `ESS_*` variables that are set and returns them, masked,
+/// for the effective-configuration dump. Values are checked against the
+/// shapes recorded in [`crate::env::REGISTRY`]; unregistered `ESS_*` names
+/// are most likely typos.
+pub fn check_env(report: &mut ConfigCheckReport) -> Map<String, Value> {
+    let mut vars: Vec<(String, String)> = std::env::vars()
+        .filter(|(name, _)| name.starts_with("ESS_"))
+        .collect();
+    vars.sort();
+
+    let mut effective = Map::new();
+    for (name, value) in vars {
+        let trimmed = value.trim();
+        let registered = env::lookup(&name);
+        match registered {
+            Some(var) => match env::validate(var, trimmed) {
+                Ok(()) if var.kind == EnvValueKind::HexKey => {
+                    report.push("env", &name, CheckStatus::Ok, "valid 256-bit key")
+                }
+                Ok(()) => {}
+                Err(message) => report.push("env", &name, CheckStatus::Error, message),
+            },
+            None => report.push(
+                "env",
+                &name,
+                CheckStatus::Warn,
+                "not read by ess; run `ess env` for the supported variables",
+            ),
+        }
+        let secret = registered.map_or_else(|| is_secret_key(&name), |var| var.secret);
+        let shown = if secret {
+            mask_secret(trimmed)
+        } else {
+            trimmed.to_string()
+        };
+        effective.insert(name, Value::String(shown));
+    }
+
+    if !effective.contains_key(env::TOKEN_CACHE_KEY) {
+        report.push(
+            "env",
+            env::TOKEN_CACHE_KEY,
+            CheckStatus::Warn,
+            "not set; access tokens are not cached between runs",
+        );
+    }
+    effective
+}
+
+/// True for config keys whose values must never be printed.
+pub fn is_secret_key(key: &str) -> bool {
+    let lower = key.to_ascii_lowercase();
+    SECRET_KEY_MARKERS
+        .iter()
+        .any(|marker| lower.contains(marker))
+}
+
+/// Masks a secret, keeping only the last four characters of long values so
+/// users can tell which credential is configured.
+pub fn mask_secret(value: &str) -> String {
+    let chars = value.chars().count();
+    if chars <= 8 {
+        return MASK.to_string();
+    }
+    let tail: String = value.chars().skip(chars - 4).collect();
+    format!("{MASK}{tail}")
+}
+
+/// Recursively masks secret-looking keys in a JSON value.
+pub fn mask_json(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| {
+                    let masked = match value {
+                        Value::String(secret) if is_secret_key(key) => {
+                            Value::String(mask_secret(secret))
+                        }
+                        other => mask_json(other),
+                    };
+                    (key.clone(), masked)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(mask_json).collect()),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{check_config_file, mask_json, mask_secret, CheckStatus, ConfigCheckReport};
+
+    fn write_config(contents: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("ess-config-{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(&path, contents).expect("write config");
+        path
+    }
+
+    #[test]
+    fn flags_unknown_keys_and_invalid_values() {
+        let path = write_config(
+            r#"
+[general]
+default_scope = "everything"
+defualt_limit = 5
+
+[defaults.search]
+work_hours_scope = "office"
+
+[defaults.serach]
+limit = 5
+
+[accounts.work]
+email = "you@company.com"
+type = "corporate"
+
+[mystery]
+"#,
+        );
+        let mut report = ConfigCheckReport::default();
+        let config = check_config_file(&path, &mut report);
+        std::fs::remove_file(&path).ok();
+
+        assert!(config.is_some());
+        assert_eq!(report.worst_status(), CheckStatus::Error);
+        let messages: Vec<&str> = report.checks.iter().map(|c| c.message.as_str()).collect();
+        assert!(messages.iter().any(|m| m.contains("defualt_limit")));
+        assert!(messages.iter().any(|m| m.contains("[mystery]")));
+        assert!(messages.iter().any(|m| m.contains("[defaults.serach]")));
+        assert!(messages
+            .iter()
+            .any(|m| m.contains("unknown scope 'office'")));
+        assert!(messages
+            .iter()
+            .any(|m| m.contains("unknown scope 'everything'")));
+        assert!(messages
+            .iter()
+            .any(|m| m.contains("unknown type 'corporate'")));
+    }
+
+    #[test]
+    fn valid_config_passes() {
+        let path = write_config("[general]\ndefault_scope = \"pro\"\n");
+        let mut report = ConfigCheckReport::default();
+        check_config_file(&path, &mut report);
+        std::fs::remove_file(&path).ok();
+        assert_eq!(report.worst_status(), CheckStatus::Ok);
+    }
+
+    #[test]
+    fn masks_secret_values() {
+        assert_eq!(mask_secret("short"), "********");
+        assert_eq!(mask_secret("abcdefghijkl"), "********ijkl");
+
+        let masked = mask_json(&json!({
+            "connector": "gmail_api",
+            "client_secret": "super-secret-value",
+            "nested": {"refresh_token": "1//abcdefgh"}
+        }));
+        assert_eq!(masked["connector"], "gmail_api");
+        assert_eq!(masked["client_secret"], "********alue");
+        assert_eq!(masked["nested"]["refresh_token"], "********efgh");
+    }
+}
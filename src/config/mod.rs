@@ -0,0 +1,835 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{Datelike, NaiveDateTime, NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::db::EvictionPolicy;
+use crate::indexer::schema::parse_language_setting;
+use crate::indexer::{
+    IndexSettings, MergePolicySetting, DEFAULT_DELETED_RATIO_BEFORE_MERGE,
+    DEFAULT_MAX_DOCS_BEFORE_MERGE, DEFAULT_MIN_MERGE_SEGMENTS, MIN_WRITER_MEMORY_BYTES,
+};
+use crate::paths;
+
+pub mod check;
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("filesystem: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("parse config: {0}")]
+    Parse(#[from] toml::de::Error),
+
+    #[error("{0}")]
+    Config(String),
+}
+
+/// Contents of `$XDG_CONFIG_HOME/ess/config.toml`. Every section is optional so a missing
+/// or partial file behaves like the installer template.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub general: GeneralConfig,
+    pub defaults: DefaultsConfig,
+    pub index: IndexConfig,
+    pub storage: StorageConfig,
+    pub sync: SyncConfig,
+    pub needs_reply: NeedsReplyConfig,
+    pub commitments: CommitmentsConfig,
+    pub remote: RemoteConfig,
+    pub accounts: BTreeMap<String, AccountEntry>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GeneralConfig {
+    pub default_scope: Option<String>,
+    /// Local weekday hours such as `"09:00-17:30"` during which each
+    /// command's `work_hours_scope` applies.
+    pub work_hours: Option<String>,
+}
+
+/// `[defaults.<command>]` blocks, applied when the flag is not given.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DefaultsConfig {
+    pub search: CommandDefaults,
+    pub list: CommandDefaults,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommandDefaults {
+    pub limit: Option<usize>,
+    pub scope: Option<String>,
+    pub work_hours_scope: Option<String>,
+}
+
+/// `[index]`: search index writer memory, merge policy and text analysis.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IndexConfig {
+    pub writer_memory_mb: Option<usize>,
+    /// `log` (the default) or `none`.
+    pub merge_policy: Option<String>,
+    pub min_merge_segments: Option<usize>,
+    pub max_docs_before_merge: Option<usize>,
+    /// Share of deleted documents (0 to 1] above which a segment is
+    /// rewritten on its own.
+    pub deleted_ratio_before_merge: Option<f32>,
+    /// `standard` (the default) or `cjk`; changing it rebuilds the index.
+    pub analyzer: Option<String>,
+    /// Stemming language (default `english`) or `none`.
+    pub stemming: Option<String>,
+    /// Stopword list to drop (default `none`).
+    pub stopwords: Option<String>,
+}
+
+/// `[storage]`: what sync keeps besides the parsed messages.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    /// Also fetch and store each new message's raw RFC 822 source
+    /// (compressed, under `$XDG_DATA_HOME/ess/raw`), and keep each
+    /// imported archive file's JSON in the email's metadata; off by default.
+    pub raw_messages: Option<bool>,
+    /// Keep `~/.ess/ess.db`, `~/.ess/config.toml` and the other old paths
+    /// as symlinks to their new locations, for scripts written against the
+    /// old layout; off by default.
+    pub legacy_symlink: Option<bool>,
+    /// Cap on the database, index and raw store together, in megabytes.
+    /// Past it, sync drops the bodies of old emails; unset means no cap.
+    pub max_size_mb: Option<u64>,
+    /// Which bodies go first past the cap: `priority` (the default) or
+    /// `oldest`.
+    pub eviction: Option<String>,
+}
+
+/// The store size cap from `[storage]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StoreCap {
+    pub max_bytes: u64,
+    pub policy: EvictionPolicy,
+}
+
+/// `[sync]`: scheduling of `ess sync --watch`, re-read on reload.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncConfig {
+    /// Seconds between the end of one watch cycle and the start of the
+    /// next; 60 by default.
+    pub interval_secs: Option<u64>,
+    /// Local hours such as `"22:00-07:00"`, every day, during which watch
+    /// cycles leave accounts alone. `[accounts.<name>] quiet_hours`
+    /// overrides it per account.
+    pub quiet_hours: Option<String>,
+    /// Seconds between syncs of an account during its quiet hours; unset,
+    /// the account is not synced until they end.
+    pub quiet_interval_secs: Option<u64>,
+    /// On battery, watch cycles put off full syncs and raw message
+    /// downloads; true by default.
+    pub defer_on_battery: Option<bool>,
+    /// The same on a metered connection; true by default.
+    pub defer_on_metered: Option<bool>,
+}
+
+/// Default pause between `ess sync --watch` cycles.
+pub const DEFAULT_SYNC_INTERVAL_SECS: u64 = 60;
+
+/// `[needs_reply]`: when `ess list --needs-reply` starts listing an email.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NeedsReplyConfig {
+    /// Days an email may go unanswered before it is listed; 2 by default,
+    /// 0 lists it at once.
+    pub after_days: Option<u32>,
+}
+
+/// `[commitments]`: the optional model `ess commitments` asks besides its
+/// patterns.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommitmentsConfig {
+    pub model_url: Option<String>,
+    /// 10 by default.
+    pub model_timeout_secs: Option<u64>,
+}
+
+/// `[remote]`: a server running `ess sync --watch --listen` that this
+/// machine's commands are sent to instead of a local store.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RemoteConfig {
+    /// Base URL such as `https://mail.example.com:8788`.
+    pub url: Option<String>,
+}
+
+/// Default wait before `ess list --needs-reply` lists an email.
+pub const DEFAULT_NEEDS_REPLY_AFTER_DAYS: u32 = 2;
+
+/// A daily window, Monday to Friday. An end before the start wraps past
+/// midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorkHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl WorkHours {
+    pub fn parse(raw: &str) -> Result<Self, ConfigError> {
+        let (start, end) = parse_hours("work_hours", raw, "09:00-17:30")?;
+        Ok(Self { start, end })
+    }
+
+    pub fn contains(&self, at: NaiveDateTime) -> bool {
+        if matches!(at.weekday(), Weekday::Sat | Weekday::Sun) {
+            return false;
+        }
+        hours_contain(self.start, self.end, at.time())
+    }
+}
+
+/// A daily window, every day of the week, in which `ess sync --watch`
+/// leaves an account alone. An end before the start wraps past midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    /// `None` for `"off"`, which an account uses to opt out of
+    /// `sync.quiet_hours`.
+    pub fn parse(raw: &str) -> Result<Option<Self>, ConfigError> {
+        if raw.trim().eq_ignore_ascii_case("off") {
+            return Ok(None);
+        }
+        let (start, end) = parse_hours("quiet_hours", raw, "22:00-07:00")?;
+        Ok(Some(Self { start, end }))
+    }
+
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        hours_contain(self.start, self.end, time)
+    }
+}
+
+/// `HH:MM-HH:MM`.
+fn parse_hours(key: &str, raw: &str, example: &str) -> Result<(NaiveTime, NaiveTime), ConfigError> {
+    let invalid = || ConfigError::Config(format!("invalid {key} '{raw}', expected e.g. {example}"));
+    let (start, end) = raw.split_once('-').ok_or_else(invalid)?;
+    let time = |value: &str| NaiveTime::parse_from_str(value.trim(), "%H:%M");
+    Ok((
+        time(start).map_err(|_| invalid())?,
+        time(end).map_err(|_| invalid())?,
+    ))
+}
+
+fn hours_contain(start: NaiveTime, end: NaiveTime, time: NaiveTime) -> bool {
+    if start <= end {
+        start <= time && time < end
+    } else {
+        time >= start || time < end
+    }
+}
+
+/// When `ess sync --watch` syncs each account, from `[sync]` and the
+/// `[accounts.<name>]` blocks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncSchedule {
+    quiet_hours: Option<QuietHours>,
+    accounts: Vec<AccountSchedule>,
+    quiet_interval: Option<Duration>,
+    defer_on_battery: bool,
+    defer_on_metered: bool,
+}
+
+impl Default for SyncSchedule {
+    fn default() -> Self {
+        Self {
+            quiet_hours: None,
+            accounts: Vec::new(),
+            quiet_interval: None,
+            defer_on_battery: true,
+            defer_on_metered: true,
+        }
+    }
+}
+
+/// What an `[accounts.<name>]` block overrides; `None` keeps `[sync]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AccountSchedule {
+    /// The account id and address, lowercase.
+    keys: Vec<String>,
+    quiet_hours: Option<Option<QuietHours>>,
+    defer_on_battery: Option<bool>,
+    defer_on_metered: Option<bool>,
+}
+
+impl SyncSchedule {
+    fn account(&self, account_id: &str, email: &str) -> Option<&AccountSchedule> {
+        let email = email.to_ascii_lowercase();
+        self.accounts.iter().find(|account| {
+            account
+                .keys
+                .iter()
+                .any(|key| key.eq_ignore_ascii_case(account_id) || *key == email)
+        })
+    }
+
+    /// The quiet hours of the account with this id and address.
+    pub fn quiet_hours(&self, account_id: &str, email: &str) -> Option<QuietHours> {
+        self.account(account_id, email)
+            .and_then(|account| account.quiet_hours)
+            .unwrap_or(self.quiet_hours)
+    }
+
+    /// Whether watch cycles put off the account's full syncs and raw
+    /// message downloads while on battery or a metered connection.
+    pub fn defers(&self, account_id: &str, email: &str, on_battery: bool, metered: bool) -> bool {
+        let account = self.account(account_id, email);
+        let on_battery_defers = account
+            .and_then(|account| account.defer_on_battery)
+            .unwrap_or(self.defer_on_battery);
+        let metered_defers = account
+            .and_then(|account| account.defer_on_metered)
+            .unwrap_or(self.defer_on_metered);
+        (on_battery && on_battery_defers) || (metered && metered_defers)
+    }
+
+    /// Whether a watch cycle at local time `now` syncs the account, which
+    /// the watch loop last synced `since_last_sync` ago (`None`: not yet).
+    pub fn is_due(
+        &self,
+        account_id: &str,
+        email: &str,
+        now: NaiveTime,
+        since_last_sync: Option<Duration>,
+    ) -> bool {
+        match self.quiet_hours(account_id, email) {
+            Some(hours) if hours.contains(now) => self
+                .quiet_interval
+                .is_some_and(|interval| since_last_sync.is_none_or(|elapsed| elapsed >= interval)),
+            _ => true,
+        }
+    }
+}
+
+/// An `[accounts.<name>]` block.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccountEntry {
+    pub account_id: Option<String>,
+    pub email: Option<String>,
+    #[serde(rename = "type")]
+    pub account_type: Option<String>,
+    pub tenant_id: Option<String>,
+    /// Replaces `sync.quiet_hours` for this account; `"off"` for none.
+    pub quiet_hours: Option<String>,
+    /// Replaces `sync.defer_on_battery` for this account.
+    pub defer_on_battery: Option<bool>,
+    /// Replaces `sync.defer_on_metered` for this account.
+    pub defer_on_metered: Option<bool>,
+}
+
+/// Commands that read a `[defaults.<command>]` block.
+pub const COMMANDS_WITH_DEFAULTS: &[&str] = &["search", "list"];
+
+/// Keys each known section accepts; anything else is reported by
+/// `ess config check` as a likely typo.
+pub(crate) const KNOWN_KEYS: &[(&str, &[&str])] = &[
+    ("general", &["default_scope", "work_hours"]),
+    ("defaults.*", &["limit", "scope", "work_hours_scope"]),
+    (
+        "index",
+        &[
+            "writer_memory_mb",
+            "merge_policy",
+            "min_merge_segments",
+            "max_docs_before_merge",
+            "deleted_ratio_before_merge",
+            "analyzer",
+            "stemming",
+            "stopwords",
+        ],
+    ),
+    (
+        "storage",
+        &["raw_messages", "legacy_symlink", "max_size_mb", "eviction"],
+    ),
+    (
+        "sync",
+        &[
+            "interval_secs",
+            "quiet_hours",
+            "quiet_interval_secs",
+            "defer_on_battery",
+            "defer_on_metered",
+        ],
+    ),
+    ("needs_reply", &["after_days"]),
+    ("commitments", &["model_url", "model_timeout_secs"]),
+    ("remote", &["url"]),
+    (
+        "accounts.*",
+        &[
+            "account_id",
+            "email",
+            "type",
+            "tenant_id",
+            "quiet_hours",
+            "defer_on_battery",
+            "defer_on_metered",
+        ],
+    ),
+];
+
+impl Config {
+    pub fn default_path() -> Result<PathBuf, ConfigError> {
+        let dir = paths::config_dir().map_err(|e| ConfigError::Config(e.to_string()))?;
+        Ok(dir.join("config.toml"))
+    }
+
+    pub fn parse(raw: &str) -> Result<Self, ConfigError> {
+        Ok(toml::from_str(raw)?)
+    }
+
+    /// Loads the config file at `path`; a missing file yields the defaults.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        match std::fs::read_to_string(path) {
+            Ok(raw) => Self::parse(&raw),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn command_defaults(&self, command: &str) -> Option<&CommandDefaults> {
+        match command {
+            "search" => Some(&self.defaults.search),
+            "list" => Some(&self.defaults.list),
+            _ => None,
+        }
+    }
+
+    /// The `--limit` a command uses when the flag is not given, if configured.
+    pub fn default_limit(&self, command: &str) -> Option<usize> {
+        self.command_defaults(command)?.limit
+    }
+
+    /// The `--scope` a command uses when the flag is not given: its
+    /// `work_hours_scope` inside `general.work_hours`, else its own `scope`,
+    /// else `general.default_scope`.
+    pub fn default_scope(
+        &self,
+        command: &str,
+        now: NaiveDateTime,
+    ) -> Result<Option<&str>, ConfigError> {
+        let defaults = self.command_defaults(command);
+        if let (Some(scope), Some(hours)) = (
+            defaults.and_then(|defaults| defaults.work_hours_scope.as_deref()),
+            self.general.work_hours.as_deref(),
+        ) {
+            if WorkHours::parse(hours)?.contains(now) {
+                return Ok(Some(scope));
+            }
+        }
+        Ok(defaults
+            .and_then(|defaults| defaults.scope.as_deref())
+            .or(self.general.default_scope.as_deref()))
+    }
+
+    /// Index writer settings, with Tantivy's defaults for anything unset.
+    pub fn index_settings(&self) -> Result<IndexSettings, ConfigError> {
+        let index = &self.index;
+        let mut settings = IndexSettings::default();
+        if let Some(megabytes) = index.writer_memory_mb {
+            settings.writer_memory_bytes = megabytes.saturating_mul(1_000_000);
+            if settings.writer_memory_bytes < MIN_WRITER_MEMORY_BYTES {
+                return Err(ConfigError::Config(format!(
+                    "index.writer_memory_mb must be at least {}",
+                    MIN_WRITER_MEMORY_BYTES / 1_000_000
+                )));
+            }
+        }
+
+        let tuned = index.min_merge_segments.is_some()
+            || index.max_docs_before_merge.is_some()
+            || index.deleted_ratio_before_merge.is_some();
+        settings.merge_policy = match index.merge_policy.as_deref().map(str::trim) {
+            None | Some("log") => {
+                let ratio = index
+                    .deleted_ratio_before_merge
+                    .unwrap_or(DEFAULT_DELETED_RATIO_BEFORE_MERGE);
+                if !(ratio > 0.0 && ratio <= 1.0) {
+                    return Err(ConfigError::Config(format!(
+                        "index.deleted_ratio_before_merge must be in (0, 1], got {ratio}"
+                    )));
+                }
+                MergePolicySetting::Log {
+                    min_num_segments: index
+                        .min_merge_segments
+                        .unwrap_or(DEFAULT_MIN_MERGE_SEGMENTS)
+                        .max(2),
+                    max_docs_before_merge: index
+                        .max_docs_before_merge
+                        .unwrap_or(DEFAULT_MAX_DOCS_BEFORE_MERGE),
+                    del_docs_ratio_before_merge: ratio,
+                }
+            }
+            Some("none") if tuned => {
+                return Err(ConfigError::Config(
+                    "index merge settings have no effect with merge_policy = \"none\"".to_string(),
+                ))
+            }
+            Some("none") => MergePolicySetting::None,
+            Some(other) => {
+                return Err(ConfigError::Config(format!(
+                    "unknown index.merge_policy '{other}' (expected log or none)"
+                )))
+            }
+        };
+        if let Some(analyzer) = &index.analyzer {
+            settings.analysis.analyzer = analyzer.parse().map_err(ConfigError::Config)?;
+        }
+        if let Some(stemming) = &index.stemming {
+            settings.analysis.stemming =
+                parse_language_setting("stemming", stemming).map_err(ConfigError::Config)?;
+        }
+        if let Some(stopwords) = &index.stopwords {
+            settings.analysis.stopwords =
+                parse_language_setting("stopwords", stopwords).map_err(ConfigError::Config)?;
+        }
+        Ok(settings)
+    }
+
+    /// The store size cap, if `storage.max_size_mb` is set.
+    pub fn store_cap(&self) -> Result<Option<StoreCap>, ConfigError> {
+        let policy = match self.storage.eviction.as_deref() {
+            None => EvictionPolicy::default(),
+            Some(raw) => EvictionPolicy::parse(raw).ok_or_else(|| {
+                ConfigError::Config(format!(
+                    "unknown storage.eviction '{raw}' (expected priority or oldest)"
+                ))
+            })?,
+        };
+        match self.storage.max_size_mb {
+            None => Ok(None),
+            Some(0) => Err(ConfigError::Config(
+                "storage.max_size_mb must be at least 1".to_string(),
+            )),
+            Some(megabytes) => Ok(Some(StoreCap {
+                max_bytes: megabytes.saturating_mul(1_000_000),
+                policy,
+            })),
+        }
+    }
+
+    /// The `[remote] url` commands are sent to, if set.
+    pub fn remote_url(&self) -> Result<Option<&str>, ConfigError> {
+        let Some(url) = self.remote.url.as_deref().map(str::trim) else {
+            return Ok(None);
+        };
+        match reqwest::Url::parse(url) {
+            Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => Ok(Some(url)),
+            _ => Err(ConfigError::Config(format!(
+                "remote.url must be an http or https URL, got '{url}'"
+            ))),
+        }
+    }
+
+    /// Pause between `ess sync --watch` cycles.
+    pub fn sync_interval(&self) -> Result<Duration, ConfigError> {
+        match self.sync.interval_secs {
+            Some(0) => Err(ConfigError::Config(
+                "sync.interval_secs must be at least 1".to_string(),
+            )),
+            secs => Ok(Duration::from_secs(
+                secs.unwrap_or(DEFAULT_SYNC_INTERVAL_SECS),
+            )),
+        }
+    }
+
+    /// Quiet hours and deferral of the watch loop, globally and per
+    /// account.
+    pub fn sync_schedule(&self) -> Result<SyncSchedule, ConfigError> {
+        let quiet_hours = match &self.sync.quiet_hours {
+            Some(raw) => QuietHours::parse(raw)?,
+            None => None,
+        };
+        let quiet_interval = match self.sync.quiet_interval_secs {
+            Some(0) => {
+                return Err(ConfigError::Config(
+                    "sync.quiet_interval_secs must be at least 1".to_string(),
+                ))
+            }
+            secs => secs.map(Duration::from_secs),
+        };
+        let mut accounts = Vec::new();
+        for entry in self.accounts.values() {
+            let keys = [entry.account_id.as_deref(), entry.email.as_deref()]
+                .into_iter()
+                .flatten()
+                .map(str::to_ascii_lowercase)
+                .collect();
+            accounts.push(AccountSchedule {
+                keys,
+                quiet_hours: entry
+                    .quiet_hours
+                    .as_deref()
+                    .map(QuietHours::parse)
+                    .transpose()?,
+                defer_on_battery: entry.defer_on_battery,
+                defer_on_metered: entry.defer_on_metered,
+            });
+        }
+        Ok(SyncSchedule {
+            quiet_hours,
+            accounts,
+            quiet_interval,
+            defer_on_battery: self.sync.defer_on_battery.unwrap_or(true),
+            defer_on_metered: self.sync.defer_on_metered.unwrap_or(true),
+        })
+    }
+
+    /// How long an email may go unanswered before `ess list --needs-reply`
+    /// lists it.
+    pub fn needs_reply_wait(&self) -> chrono::Duration {
+        chrono::Duration::days(i64::from(
+            self.needs_reply
+                .after_days
+                .unwrap_or(DEFAULT_NEEDS_REPLY_AFTER_DAYS),
+        ))
+    }
+
+    /// Loads the config from the default location.
+    pub fn load_default() -> Result<Self, ConfigError> {
+        Self::load(&Self::default_path()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::{NaiveDateTime, NaiveTime};
+
+    use super::{Config, SyncSchedule};
+    use crate::db::EvictionPolicy;
+    use crate::indexer::MergePolicySetting;
+
+    #[test]
+    fn parses_installer_template() {
+        let config = Config::parse(
+            r#"
+[general]
+default_scope = "all"
+
+[accounts]
+[accounts.work]
+account_id = "you@company.com"
+email = "you@company.com"
+type = "professional"
+"#,
+        )
+        .expect("parse template");
+
+        assert_eq!(config.general.default_scope.as_deref(), Some("all"));
+        let work = &config.accounts["work"];
+        assert_eq!(work.account_type.as_deref(), Some("professional"));
+        assert_eq!(work.tenant_id, None);
+    }
+
+    #[test]
+    fn command_defaults_follow_work_hours() {
+        let config = Config::parse(
+            r#"
+[general]
+default_scope = "personal"
+work_hours = "09:00-17:30"
+
+[defaults.search]
+limit = 50
+scope = "all"
+work_hours_scope = "pro"
+
+[defaults.list]
+limit = 200
+"#,
+        )
+        .expect("parse config");
+        let at = |raw: &str| {
+            NaiveDateTime::parse_from_str(raw, "%Y-%m-%d %H:%M").expect("parse timestamp")
+        };
+
+        // 2026-03-02 is a Monday, 2026-03-07 a Saturday.
+        let scope = |command: &str, now: &str| {
+            config
+                .default_scope(command, at(now))
+                .expect("resolve scope")
+                .map(str::to_string)
+        };
+        assert_eq!(scope("search", "2026-03-02 10:00").as_deref(), Some("pro"));
+        assert_eq!(scope("search", "2026-03-02 17:30").as_deref(), Some("all"));
+        assert_eq!(scope("search", "2026-03-07 10:00").as_deref(), Some("all"));
+        assert_eq!(
+            scope("list", "2026-03-02 10:00").as_deref(),
+            Some("personal")
+        );
+        assert_eq!(config.default_limit("search"), Some(50));
+        assert_eq!(config.default_limit("list"), Some(200));
+        assert_eq!(config.default_limit("starred"), None);
+    }
+
+    #[test]
+    fn sync_interval_defaults_to_a_minute() {
+        let interval = |raw: &str| Config::parse(raw).expect("parse config").sync_interval();
+        assert_eq!(interval("").expect("default"), Duration::from_secs(60));
+        assert_eq!(
+            interval("[sync]\ninterval_secs = 300").expect("configured"),
+            Duration::from_secs(300)
+        );
+        assert!(interval("[sync]\ninterval_secs = 0").is_err());
+    }
+
+    #[test]
+    fn quiet_hours_skip_or_slow_down_accounts() {
+        let config = Config::parse(
+            r#"
+[sync]
+quiet_hours = "22:00-07:00"
+quiet_interval_secs = 1800
+
+[accounts.work]
+email = "Me@Company.com"
+quiet_hours = "18:00-08:30"
+
+[accounts.home]
+account_id = "home"
+quiet_hours = "off"
+"#,
+        )
+        .expect("parse config");
+        let schedule = config.sync_schedule().expect("schedule");
+        let at = |raw: &str| NaiveTime::parse_from_str(raw, "%H:%M").expect("parse time");
+        let minutes = |minutes: u64| Some(Duration::from_secs(minutes * 60));
+
+        assert!(schedule.is_due("other", "other@example.com", at("21:59"), minutes(1)));
+        assert!(!schedule.is_due("other", "other@example.com", at("23:00"), minutes(29)));
+        assert!(schedule.is_due("other", "other@example.com", at("06:00"), minutes(30)));
+        assert!(schedule.is_due("other", "other@example.com", at("23:00"), None));
+
+        assert!(!schedule.is_due("work-id", "me@company.com", at("08:00"), minutes(5)));
+        assert!(schedule.is_due("work-id", "me@company.com", at("08:30"), minutes(5)));
+        assert!(schedule.is_due("home", "me@home.example", at("23:00"), minutes(1)));
+
+        let skipping = Config::parse("[sync]\nquiet_hours = \"22:00-07:00\"")
+            .expect("parse config")
+            .sync_schedule()
+            .expect("schedule");
+        assert!(!skipping.is_due("other", "other@example.com", at("23:00"), None));
+        assert!(Config::parse("[sync]\nquiet_hours = \"late\"")
+            .expect("parse config")
+            .sync_schedule()
+            .is_err());
+    }
+
+    #[test]
+    fn battery_and_metered_deferral_is_overridden_per_account() {
+        let config = Config::parse(
+            r#"
+[sync]
+quiet_hours = "22:00-07:00"
+defer_on_metered = false
+
+[accounts.home]
+account_id = "home"
+defer_on_battery = false
+
+[accounts.phone]
+account_id = "phone"
+defer_on_metered = true
+"#,
+        )
+        .expect("parse config");
+        let schedule = config.sync_schedule().expect("schedule");
+
+        assert!(schedule.defers("work", "me@company.com", true, false));
+        assert!(!schedule.defers("work", "me@company.com", false, true));
+        assert!(!schedule.defers("home", "me@home.example", true, false));
+        assert!(schedule.defers("phone", "me@phone.example", false, true));
+        assert!(!schedule.defers("phone", "me@phone.example", false, false));
+        // A block that sets no quiet hours keeps the global ones.
+        assert!(schedule.quiet_hours("home", "me@home.example").is_some());
+
+        assert!(SyncSchedule::default().defers("work", "me@company.com", false, true));
+    }
+
+    #[test]
+    fn index_settings_validate_merge_policy() {
+        let settings =
+            Config::parse("[index]\nwriter_memory_mb = 100\ndeleted_ratio_before_merge = 0.2\n")
+                .expect("parse config")
+                .index_settings()
+                .expect("index settings");
+        assert_eq!(settings.writer_memory_bytes, 100_000_000);
+        assert!(matches!(
+            settings.merge_policy,
+            MergePolicySetting::Log { del_docs_ratio_before_merge, .. }
+                if del_docs_ratio_before_merge == 0.2
+        ));
+
+        for raw in [
+            "[index]\nwriter_memory_mb = 4\n",
+            "[index]\nmerge_policy = \"tiered\"\n",
+            "[index]\ndeleted_ratio_before_merge = 0.0\n",
+            "[index]\nmerge_policy = \"none\"\nmin_merge_segments = 4\n",
+            "[index]\nanalyzer = \"icu\"\n",
+            "[index]\nstemming = \"klingon\"\n",
+        ] {
+            let config = Config::parse(raw).expect("parse config");
+            assert!(config.index_settings().is_err(), "{raw}");
+        }
+    }
+
+    #[test]
+    fn store_cap_reads_size_and_policy() {
+        let cap = Config::parse("[storage]\nmax_size_mb = 500\neviction = \"oldest\"\n")
+            .expect("parse config")
+            .store_cap()
+            .expect("store cap")
+            .expect("cap set");
+        assert_eq!(cap.max_bytes, 500_000_000);
+        assert_eq!(cap.policy, EvictionPolicy::Oldest);
+        assert_eq!(Config::default().store_cap().expect("no cap"), None);
+
+        for raw in [
+            "[storage]\nmax_size_mb = 0\n",
+            "[storage]\nmax_size_mb = 10\neviction = \"largest\"\n",
+        ] {
+            let config = Config::parse(raw).expect("parse config");
+            assert!(config.store_cap().is_err(), "{raw}");
+        }
+    }
+
+    #[test]
+    fn remote_url_must_be_http() {
+        let config = Config::parse("[remote]\nurl = \"https://mail.example.com:8788/\"\n")
+            .expect("parse config");
+        assert_eq!(
+            config.remote_url().expect("remote url"),
+            Some("https://mail.example.com:8788/")
+        );
+        assert_eq!(Config::default().remote_url().expect("no remote"), None);
+        let config = Config::parse("[remote]\nurl = \"mail.example.com\"\n").expect("parse");
+        assert!(config.remote_url().is_err());
+    }
+
+    #[test]
+    fn missing_file_yields_defaults() {
+        let path = std::env::temp_dir().join(format!("ess-missing-{}.toml", uuid::Uuid::new_v4()));
+        assert_eq!(Config::load(&path).unwrap(), Config::default());
+    }
+}
@@ -0,0 +1,163 @@
+//! Whether the machine runs on battery or over a metered connection, so
+//! `ess sync --watch` can put off the syncs that download the most.
+//!
+//! Battery power is read from `/sys/class/power_supply` on Linux and from
+//! `pmset` on macOS; a metered connection from NetworkManager on the D-Bus
+//! system bus. Elsewhere, or when the tool or bus is missing, a condition
+//! reads as absent. `ESS_ON_BATTERY` and `ESS_METERED` override detection.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::env;
+
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+/// How long to wait on the system bus before treating the connection as
+/// unmetered.
+const METERED_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct SyncConditions {
+    pub on_battery: bool,
+    pub metered: bool,
+}
+
+impl SyncConditions {
+    pub async fn detect() -> Self {
+        let metered = match flag_override(env::METERED) {
+            Some(metered) => metered,
+            None => metered().await,
+        };
+        Self {
+            on_battery: flag_override(env::ON_BATTERY).unwrap_or_else(on_battery),
+            metered,
+        }
+    }
+
+    /// `"on battery"`, `"on a metered connection"`, both, or `None`.
+    pub fn describe(&self) -> Option<String> {
+        match (self.on_battery, self.metered) {
+            (false, false) => None,
+            (true, false) => Some("on battery".to_string()),
+            (false, true) => Some("on a metered connection".to_string()),
+            (true, true) => Some("on battery and a metered connection".to_string()),
+        }
+    }
+}
+
+/// `1`, `true`, `yes` or `on`, and their opposites.
+pub fn parse_flag(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+fn flag_override(name: &str) -> Option<bool> {
+    env::var(name).as_deref().and_then(parse_flag)
+}
+
+fn on_battery() -> bool {
+    if cfg!(target_os = "macos") {
+        command_output("pmset", &["-g", "batt"]).is_some_and(|output| pmset_on_battery(&output))
+    } else {
+        power_supplies_on_battery(Path::new(POWER_SUPPLY_DIR))
+    }
+}
+
+async fn metered() -> bool {
+    tokio::time::timeout(METERED_TIMEOUT, network_manager_state())
+        .await
+        .ok()
+        .and_then(Result::ok)
+        .is_some_and(network_manager_metered)
+}
+
+/// NetworkManager's `Metered` property, read once without caching.
+async fn network_manager_state() -> zbus::Result<u32> {
+    let connection = zbus::Connection::system().await?;
+    let proxy: zbus::Proxy = zbus::proxy::Builder::new(&connection)
+        .destination("org.freedesktop.NetworkManager")?
+        .path("/org/freedesktop/NetworkManager")?
+        .interface("org.freedesktop.NetworkManager")?
+        .cache_properties(zbus::proxy::CacheProperties::No)
+        .build()
+        .await?;
+    proxy.get_property("Metered").await
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// A battery is discharging. A laptop on its charger reports `Charging`,
+/// `Full` or `Not charging`.
+fn power_supplies_on_battery(dir: &Path) -> bool {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return false;
+    };
+    entries.flatten().any(|entry| {
+        let read = |name: &str| {
+            fs::read_to_string(entry.path().join(name))
+                .map(|value| value.trim().to_string())
+                .unwrap_or_default()
+        };
+        read("type") == "Battery" && read("status") == "Discharging"
+    })
+}
+
+/// `Now drawing from 'Battery Power'` on the first line of `pmset -g batt`.
+fn pmset_on_battery(output: &str) -> bool {
+    output
+        .lines()
+        .next()
+        .is_some_and(|line| line.contains("'Battery Power'"))
+}
+
+/// NetworkManager's `NMMetered`: 1 (yes) or 3 (guessed yes).
+fn network_manager_metered(state: u32) -> bool {
+    matches!(state, 1 | 3)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{network_manager_metered, pmset_on_battery, power_supplies_on_battery};
+
+    #[test]
+    fn reads_battery_and_metered_state() {
+        let dir = std::env::temp_dir().join(format!("ess-power-{}", uuid::Uuid::new_v4()));
+        for (name, kind, status) in [("AC", "Mains", None), ("BAT0", "Battery", Some("Full"))] {
+            fs::create_dir_all(dir.join(name)).expect("create supply");
+            fs::write(dir.join(name).join("type"), format!("{kind}\n")).expect("write type");
+            if let Some(status) = status {
+                fs::write(dir.join(name).join("status"), status).expect("write status");
+            }
+        }
+        assert!(!power_supplies_on_battery(&dir));
+        fs::write(dir.join("BAT0").join("status"), "Discharging\n").expect("write status");
+        assert!(power_supplies_on_battery(&dir));
+        assert!(!power_supplies_on_battery(&dir.join("missing")));
+        let _ = fs::remove_dir_all(&dir);
+
+        assert!(pmset_on_battery(
+            "Now drawing from 'Battery Power'\n -InternalBattery-0 (id=1)\t81%; discharging"
+        ));
+        assert!(!pmset_on_battery("Now drawing from 'AC Power'\n"));
+
+        assert!(network_manager_metered(1));
+        assert!(network_manager_metered(3));
+        assert!(!network_manager_metered(0));
+        assert!(!network_manager_metered(4));
+    }
+}
@@ -0,0 +1,112 @@
+use std::time::Duration;
+
+use reqwest::Url;
+use tokio::net::TcpStream;
+
+use crate::env;
+
+/// How long a single TCP probe may take before the host counts as unreachable.
+pub const DEFAULT_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Returned when none of the remote endpoints a command needs can be reached.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "offline: could not reach {} (local commands such as search, list and show still work)",
+    .unreachable.join(", ")
+)]
+pub struct OfflineError {
+    pub unreachable: Vec<String>,
+}
+
+/// Fails fast with [`OfflineError`] when none of `endpoints` accepts a TCP
+/// connection. Reaching any one endpoint counts as online; per-account
+/// failures are left for the connectors to report. When an HTTP proxy is
+/// configured the direct probe is skipped because it would not reflect the
+/// route reqwest actually takes.
+pub async fn ensure_online(endpoints: &[String], timeout: Duration) -> Result<(), OfflineError> {
+    if proxy_configured() {
+        return Ok(());
+    }
+    probe_endpoints(endpoints, timeout).await
+}
+
+async fn probe_endpoints(endpoints: &[String], timeout: Duration) -> Result<(), OfflineError> {
+    let mut targets: Vec<(String, u16)> = endpoints
+        .iter()
+        .filter_map(|endpoint| endpoint_host_port(endpoint))
+        .collect();
+    targets.sort();
+    targets.dedup();
+    if targets.is_empty() {
+        return Ok(());
+    }
+
+    let mut unreachable = Vec::new();
+    for (host, port) in targets {
+        if probe(&host, port, timeout).await {
+            return Ok(());
+        }
+        unreachable.push(format!("{host}:{port}"));
+    }
+    Err(OfflineError { unreachable })
+}
+
+async fn probe(host: &str, port: u16, timeout: Duration) -> bool {
+    matches!(
+        tokio::time::timeout(timeout, TcpStream::connect((host, port))).await,
+        Ok(Ok(_))
+    )
+}
+
+fn proxy_configured() -> bool {
+    env::PROXY_VARS.iter().any(|name| env::var(name).is_some())
+}
+
+fn endpoint_host_port(endpoint: &str) -> Option<(String, u16)> {
+    let url = Url::parse(endpoint).ok()?;
+    let host = url.host_str()?.to_string();
+    let port = url.port_or_known_default()?;
+    Some((host, port))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{endpoint_host_port, probe_endpoints};
+
+    #[test]
+    fn extracts_host_and_default_port() {
+        assert_eq!(
+            endpoint_host_port("https://graph.microsoft.com/v1.0"),
+            Some(("graph.microsoft.com".to_string(), 443))
+        );
+        assert_eq!(
+            endpoint_host_port("http://127.0.0.1:8080/mock"),
+            Some(("127.0.0.1".to_string(), 8080))
+        );
+        assert_eq!(endpoint_host_port("not a url"), None);
+    }
+
+    #[tokio::test]
+    async fn reports_offline_only_when_every_endpoint_is_unreachable() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind listener");
+        let open_port = listener.local_addr().expect("local addr").port();
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").expect("bind listener");
+        let closed_port = closed.local_addr().expect("local addr").port();
+        drop(closed);
+
+        let timeout = Duration::from_secs(1);
+        let closed_endpoint = format!("http://127.0.0.1:{closed_port}");
+        let open_endpoint = format!("http://127.0.0.1:{open_port}");
+
+        let error = probe_endpoints(std::slice::from_ref(&closed_endpoint), timeout)
+            .await
+            .expect_err("closed port should be offline");
+        assert_eq!(error.unreachable, vec![format!("127.0.0.1:{closed_port}")]);
+
+        probe_endpoints(&[closed_endpoint, open_endpoint], timeout)
+            .await
+            .expect("one reachable endpoint means online");
+    }
+}
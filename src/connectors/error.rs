@@ -0,0 +1,164 @@
+use std::fmt;
+
+use reqwest::StatusCode;
+use serde::Serialize;
+
+/// A connector failure classified by what a caller can do about it.
+///
+/// The message keeps the connector's own wording; the variant carries the
+/// decision retry and fallback logic used to make by matching on text.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ConnectorError {
+    /// Credentials were rejected or lack a required scope (401/403).
+    #[error("{message}")]
+    Auth { message: String },
+    /// The message, folder or sync cursor no longer exists (404/410).
+    #[error("{message}")]
+    NotFound { message: String },
+    /// The provider kept throttling after every retry was spent (429).
+    #[error("{message}")]
+    RateLimited { message: String },
+    /// Network failures, timeouts and 5xx responses; a later attempt may
+    /// succeed.
+    #[error("{message}")]
+    Transient { message: String },
+    /// Anything retrying will not fix: bad requests, undecodable payloads,
+    /// local storage failures.
+    #[error("{message}")]
+    Permanent { message: String },
+}
+
+impl ConnectorError {
+    /// Maps an HTTP status from a provider response onto a variant.
+    pub fn from_status(status: StatusCode, message: impl Into<String>) -> Self {
+        let message = message.into();
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Self::Auth { message },
+            StatusCode::NOT_FOUND | StatusCode::GONE => Self::NotFound { message },
+            StatusCode::TOO_MANY_REQUESTS => Self::RateLimited { message },
+            StatusCode::REQUEST_TIMEOUT => Self::Transient { message },
+            status if status.is_server_error() => Self::Transient { message },
+            _ => Self::Permanent { message },
+        }
+    }
+
+    pub fn rate_limited(message: impl Into<String>) -> Self {
+        Self::RateLimited {
+            message: message.into(),
+        }
+    }
+
+    pub fn transient(message: impl Into<String>) -> Self {
+        Self::Transient {
+            message: message.into(),
+        }
+    }
+
+    pub fn permanent(message: impl Into<String>) -> Self {
+        Self::Permanent {
+            message: message.into(),
+        }
+    }
+
+    /// Classifies an error bubbled up through `anyhow`. A `ConnectorError`
+    /// anywhere in the chain decides the variant; otherwise transport errors
+    /// count as transient and everything else as permanent. The message is
+    /// the outermost context, matching what the connectors used to report.
+    pub fn classify(error: &anyhow::Error) -> Self {
+        let message = error.to_string();
+        for cause in error.chain() {
+            if let Some(typed) = cause.downcast_ref::<ConnectorError>() {
+                return typed.with_message(message);
+            }
+            if cause.downcast_ref::<reqwest::Error>().is_some() {
+                return Self::Transient { message };
+            }
+        }
+        Self::Permanent { message }
+    }
+
+    /// Prefixes the message with where the failure happened, keeping the
+    /// variant.
+    pub fn context(self, context: impl fmt::Display) -> Self {
+        let message = format!("{context}: {}", self.message());
+        self.with_message(message)
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Auth { .. } => "auth",
+            Self::NotFound { .. } => "not_found",
+            Self::RateLimited { .. } => "rate_limited",
+            Self::Transient { .. } => "transient",
+            Self::Permanent { .. } => "permanent",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Self::Auth { message }
+            | Self::NotFound { message }
+            | Self::RateLimited { message }
+            | Self::Transient { message }
+            | Self::Permanent { message } => message,
+        }
+    }
+
+    /// True when repeating the same request later may succeed.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::RateLimited { .. } | Self::Transient { .. })
+    }
+
+    fn with_message(&self, message: String) -> Self {
+        match self {
+            Self::Auth { .. } => Self::Auth { message },
+            Self::NotFound { .. } => Self::NotFound { message },
+            Self::RateLimited { .. } => Self::RateLimited { message },
+            Self::Transient { .. } => Self::Transient { message },
+            Self::Permanent { .. } => Self::Permanent { message },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{anyhow, Context};
+    use reqwest::StatusCode;
+
+    use super::ConnectorError;
+
+    #[test]
+    fn status_codes_map_to_variants() {
+        let kind = |status| ConnectorError::from_status(status, "x").kind();
+        assert_eq!(kind(StatusCode::UNAUTHORIZED), "auth");
+        assert_eq!(kind(StatusCode::FORBIDDEN), "auth");
+        assert_eq!(kind(StatusCode::NOT_FOUND), "not_found");
+        assert_eq!(kind(StatusCode::GONE), "not_found");
+        assert_eq!(kind(StatusCode::TOO_MANY_REQUESTS), "rate_limited");
+        assert_eq!(kind(StatusCode::SERVICE_UNAVAILABLE), "transient");
+        assert_eq!(kind(StatusCode::BAD_REQUEST), "permanent");
+    }
+
+    #[test]
+    fn classify_finds_typed_error_under_context() {
+        let error = Err::<(), _>(ConnectorError::from_status(
+            StatusCode::NOT_FOUND,
+            "gmail api request failed: status=404",
+        ))
+        .context("list gmail history")
+        .unwrap_err();
+
+        let classified = ConnectorError::classify(&error);
+        assert!(matches!(classified, ConnectorError::NotFound { .. }));
+        assert_eq!(classified.message(), "list gmail history");
+        assert!(!classified.is_retryable());
+
+        let untyped = ConnectorError::classify(&anyhow!("mentions 404 but is local"));
+        assert!(matches!(untyped, ConnectorError::Permanent { .. }));
+        assert_eq!(
+            untyped.context("id=abc").to_string(),
+            "id=abc: mentions 404 but is local"
+        );
+    }
+}
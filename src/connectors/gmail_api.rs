@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 use std::time::Duration as StdDuration;
 
@@ -7,29 +7,41 @@ use async_trait::async_trait;
 use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use base64::Engine;
 use chrono::{DateTime, Duration, TimeZone, Utc};
-use reqwest::{Client, StatusCode};
-use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM};
-use ring::rand::{SecureRandom, SystemRandom};
+use reqwest::{Client, RequestBuilder, StatusCode};
 use serde::{Deserialize, Serialize};
 use tokio::time::sleep;
 use tracing::warn;
 
-use crate::connectors::{EmailConnector, ImportReport, SyncReport};
-use crate::db::models::{Account, Email};
-use crate::db::Database;
+use crate::connectors::progress::{ProgressSink, StageProgress, SyncProgress};
+use crate::connectors::quarantine::{quarantine_unparsed, UnparsedMessage};
+use crate::connectors::shutdown::ShutdownSignal;
+use crate::connectors::usage::UsageMeter;
+use crate::connectors::{
+    raw_fetches_due, ConnectorError, EmailConnector, FlagUpdate, ImportReport, MessageAction,
+    MovedMessage, SyncReport,
+};
+use crate::db::mailing_lists::LIST_HEADERS_KEY;
+use crate::db::meetings::{CalendarEvent, CALENDAR_EVENT_KEY};
+use crate::db::models::{Account, Attachment, Email};
+use crate::db::quarantine::QuarantinedMessage;
+use crate::db::thread_links::ThreadHeaders;
+use crate::db::{Database, Label, ListHeaders, UsageCounts};
+use crate::env;
 use crate::indexer::EmailIndex;
+use crate::raw::RawStore;
+use crate::secrets::{self, TokenCacheKeys, TokenRefreshPolicy};
 
 const GMAIL_API_BASE: &str = "https://gmail.googleapis.com/gmail/v1";
 const GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
-const CACHE_SKEW_SECONDS: i64 = 60;
 const DEFAULT_PAGE_SIZE: usize = 100;
 const MAX_RATE_LIMIT_RETRIES: usize = 5;
-const TOKEN_CACHE_ENCRYPTION_KEY_ENV: &str = "ESS_TOKEN_CACHE_KEY";
-const TOKEN_CACHE_KEY_BYTES: usize = 32;
-const TOKEN_CACHE_NONCE_BYTES: usize = 12;
-const TOKEN_CACHE_ENVELOPE_VERSION: u8 = 1;
 const REDACTED_BODY_MAX_LEN: usize = 200;
-const BATCH_SIZE: usize = 25;
+/// Messages per batch request for an account without a tuned size.
+const DEFAULT_BATCH_SIZE: usize = 25;
+const MIN_BATCH_SIZE: usize = 5;
+/// Gmail's limit on sub-requests per batch.
+const MAX_BATCH_SIZE: usize = 100;
+const BATCH_SIZE_STEP: usize = 5;
 const MAX_BATCH_RETRIES: usize = 3;
 const BATCH_ENDPOINT: &str = "https://www.googleapis.com/batch/gmail/v1";
 
@@ -54,6 +66,11 @@ const SYSTEM_LABELS: &[&str] = &[
 #[derive(Debug, Clone)]
 pub struct GmailApiConnector {
     client: Client,
+    progress: ProgressSink,
+    shutdown: ShutdownSignal,
+    raw: Option<RawStore>,
+    defer_raw: bool,
+    usage: UsageMeter,
 }
 
 impl Default for GmailApiConnector {
@@ -66,9 +83,48 @@ impl GmailApiConnector {
     pub fn new() -> Self {
         Self {
             client: Client::new(),
+            progress: ProgressSink::default(),
+            shutdown: ShutdownSignal::default(),
+            raw: None,
+            defer_raw: false,
+            usage: UsageMeter::default(),
         }
     }
 
+    /// Stop at the next batch or page boundary once `shutdown` is requested.
+    pub fn with_shutdown(mut self, shutdown: ShutdownSignal) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Route sync progress events to `progress` instead of stderr.
+    pub fn with_progress(mut self, progress: ProgressSink) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Also fetch each new message with `format=raw` and keep its source
+    /// in `raw`.
+    pub fn with_raw_store(mut self, raw: RawStore) -> Self {
+        self.raw = Some(raw);
+        self
+    }
+
+    /// Put off fetching raw sources, e.g. on a metered connection; see
+    /// [`raw_fetches_due`].
+    pub fn with_raw_fetches_deferred(mut self, defer: bool) -> Self {
+        self.defer_raw = defer;
+        self
+    }
+
+    fn notice(&self, account: &Account, message: String) {
+        self.progress.emit(SyncProgress::Notice {
+            account_id: account.account_id.clone(),
+            folder: None,
+            message,
+        });
+    }
+
     async fn get_access_token(&self, db: &Database, account: &Account) -> Result<String> {
         if let Some(cached) = self.cached_token(db, account)? {
             return Ok(cached.access_token);
@@ -88,6 +144,23 @@ impl GmailApiConnector {
         format!("gmail_history_id:{}", account.account_id)
     }
 
+    fn batch_size_key(account: &Account) -> String {
+        format!("gmail_batch_size:{}", account.account_id)
+    }
+
+    fn load_batch_sizer(&self, db: &Database, account: &Account) -> Result<BatchSizer> {
+        let stored = db
+            .get_sync_state(&Self::batch_size_key(account))?
+            .and_then(|state| state.value)
+            .and_then(|value| value.trim().parse().ok());
+        Ok(BatchSizer::new(stored.unwrap_or(DEFAULT_BATCH_SIZE)))
+    }
+
+    fn store_batch_sizer(&self, db: &Database, account: &Account, sizer: BatchSizer) -> Result<()> {
+        db.set_sync_state(&Self::batch_size_key(account), &sizer.size().to_string())
+            .context("persist gmail batch size")
+    }
+
     fn cached_token(&self, db: &Database, account: &Account) -> Result<Option<CachedAccessToken>> {
         let cache_key = Self::token_cache_key(account);
         let Some(state) = db.get_sync_state(&cache_key)? else {
@@ -98,13 +171,18 @@ impl GmailApiConnector {
             return Ok(None);
         };
 
-        let Some(encryption_key) = Self::token_cache_encryption_key()? else {
+        let Some(keys) = TokenCacheKeys::from_env()? else {
             Self::clear_sync_state(db, &cache_key)?;
             return Ok(None);
         };
 
-        let cached = match decrypt_cached_access_token(&raw, &encryption_key) {
-            Ok(token) => token,
+        let cached = match decrypt_cached_access_token(&raw, &keys) {
+            Ok((token, needs_reseal)) => {
+                if needs_reseal {
+                    self.store_token(db, account, &token)?;
+                }
+                token
+            }
             Err(decrypt_error) => {
                 if let Ok(legacy_token) = serde_json::from_str::<CachedAccessToken>(&raw) {
                     self.store_token(db, account, &legacy_token)?;
@@ -120,7 +198,7 @@ impl GmailApiConnector {
             }
         };
 
-        if cached.is_expired() {
+        if TokenRefreshPolicy::from_env()?.needs_refresh(cached.expires_at, Utc::now()) {
             Self::clear_sync_state(db, &cache_key)?;
             return Ok(None);
         }
@@ -134,30 +212,17 @@ impl GmailApiConnector {
         account: &Account,
         token: &CachedAccessToken,
     ) -> Result<()> {
-        let Some(encryption_key) = Self::token_cache_encryption_key()? else {
+        let Some(keys) = TokenCacheKeys::from_env()? else {
             return Ok(());
         };
 
         let key = Self::token_cache_key(account);
-        let value = encrypt_cached_access_token(token, &encryption_key)
-            .context("encrypt cached gmail token")?;
+        let value =
+            encrypt_cached_access_token(token, &keys).context("encrypt cached gmail token")?;
         db.set_sync_state(&key, &value)
             .context("write gmail token to sync_state")
     }
 
-    fn token_cache_encryption_key() -> Result<Option<[u8; TOKEN_CACHE_KEY_BYTES]>> {
-        let raw = std::env::var(TOKEN_CACHE_ENCRYPTION_KEY_ENV)
-            .ok()
-            .map(|value| value.trim().to_string())
-            .filter(|value| !value.is_empty());
-
-        raw.map(|value| parse_token_cache_key_hex(&value))
-            .transpose()
-            .with_context(|| {
-                format!("{TOKEN_CACHE_ENCRYPTION_KEY_ENV} must be 64 hex characters (32 bytes)")
-            })
-    }
-
     fn clear_sync_state(db: &Database, key: &str) -> Result<()> {
         db.conn()
             .execute("DELETE FROM sync_state WHERE key = ?", [key])
@@ -181,10 +246,8 @@ impl GmailApiConnector {
     }
 
     async fn fetch_token(&self, credentials: &GmailCredentials) -> Result<CachedAccessToken> {
-        let token_url = std::env::var("ESS_GMAIL_TOKEN_URL")
-            .ok()
-            .filter(|value| !value.trim().is_empty())
-            .unwrap_or_else(|| GOOGLE_TOKEN_URL.to_string());
+        let token_url =
+            env::var(env::GMAIL_TOKEN_URL).unwrap_or_else(|| GOOGLE_TOKEN_URL.to_string());
 
         let response = self
             .client
@@ -198,21 +261,28 @@ impl GmailApiConnector {
             .send()
             .await
             .with_context(|| format!("request gmail oauth token from {token_url}"))?;
+        self.usage.request();
 
         let status = response.status();
         let body = response.text().await.context("read gmail token response")?;
+        self.usage.received(body.len());
         if !status.is_success() {
-            return Err(anyhow!(
-                "gmail oauth token request failed: status={} body={}",
+            return Err(ConnectorError::from_status(
                 status,
-                redact_response_body(&body)
-            ));
+                format!(
+                    "gmail oauth token request failed: status={} body={}",
+                    status,
+                    redact_response_body(&body)
+                ),
+            )
+            .into());
         }
 
         let payload: OAuthTokenResponse =
             serde_json::from_str(&body).context("decode gmail token JSON response")?;
-        let expires_at = Utc::now()
-            + Duration::seconds((payload.expires_in as i64).saturating_sub(CACHE_SKEW_SECONDS));
+        // Skew and the refresh window are applied when the cached token is
+        // read, so changing them takes effect without discarding the cache.
+        let expires_at = Utc::now() + Duration::seconds(payload.expires_in as i64);
 
         Ok(CachedAccessToken {
             access_token: payload.access_token,
@@ -232,6 +302,7 @@ impl GmailApiConnector {
                 .send()
                 .await
                 .with_context(|| format!("gmail api request: {url}"))?;
+            self.usage.request();
 
             if response.status() == StatusCode::TOO_MANY_REQUESTS {
                 if attempt == MAX_RATE_LIMIT_RETRIES {
@@ -239,10 +310,12 @@ impl GmailApiConnector {
                         .text()
                         .await
                         .context("read gmail 429 response body")?;
-                    return Err(anyhow!(
+                    self.usage.received(body.len());
+                    return Err(ConnectorError::rate_limited(format!(
                         "gmail api request exhausted retries: {}",
                         redact_response_body(&body)
-                    ));
+                    ))
+                    .into());
                 }
 
                 let retry_after_seconds = response
@@ -262,22 +335,27 @@ impl GmailApiConnector {
                 .text()
                 .await
                 .context("read gmail api response body")?;
+            self.usage.received(body.len());
             if !status.is_success() {
-                return Err(anyhow!(
-                    "gmail api request failed: status={} body={}",
+                return Err(ConnectorError::from_status(
                     status,
-                    redact_response_body(&body)
-                ));
+                    format!(
+                        "gmail api request failed: status={} body={}",
+                        status,
+                        redact_response_body(&body)
+                    ),
+                )
+                .into());
             }
 
             return Ok(body);
         }
 
-        Err(anyhow!("gmail api request failed without response"))
+        Err(ConnectorError::transient("gmail api request failed without response").into())
     }
 
     async fn get_profile(&self, token: &str) -> Result<GmailProfile> {
-        let url = format!("{GMAIL_API_BASE}/users/me/profile");
+        let url = format!("{}/users/me/profile", gmail_api_base());
         let body = self.fetch_with_retry(token, &url).await?;
         serde_json::from_str(&body).context("decode gmail profile")
     }
@@ -287,7 +365,10 @@ impl GmailApiConnector {
         token: &str,
         page_token: Option<&str>,
     ) -> Result<GmailMessageList> {
-        let mut url = format!("{GMAIL_API_BASE}/users/me/messages?maxResults={DEFAULT_PAGE_SIZE}");
+        let mut url = format!(
+            "{}/users/me/messages?maxResults={DEFAULT_PAGE_SIZE}",
+            gmail_api_base()
+        );
         if let Some(pt) = page_token {
             url.push_str(&format!("&pageToken={pt}"));
         }
@@ -295,12 +376,180 @@ impl GmailApiConnector {
         serde_json::from_str(&body).context("decode gmail message list")
     }
 
+    async fn list_labels(&self, token: &str) -> Result<Vec<GmailLabel>> {
+        let url = format!("{}/users/me/labels", gmail_api_base());
+        let body = self.fetch_with_retry(token, &url).await?;
+        let list: GmailLabelList =
+            serde_json::from_str(&body).context("decode gmail label list")?;
+        Ok(list.labels.unwrap_or_default())
+    }
+
+    /// Stores the account's current label listing, so user labels can be
+    /// shown by name and `ess labels` can list them.
+    async fn refresh_labels(&self, db: &Database, account: &Account) -> Result<()> {
+        let token = self.get_access_token(db, account).await?;
+        let labels = self.list_labels(&token).await?;
+        db.replace_labels(
+            &account.account_id,
+            &map_gmail_labels(&account.account_id, &labels),
+        )
+        .context("store gmail labels")
+    }
+
     async fn get_message(&self, token: &str, message_id: &str) -> Result<GmailMessage> {
-        let url = format!("{GMAIL_API_BASE}/users/me/messages/{message_id}?format=full");
+        let url = format!(
+            "{}/users/me/messages/{message_id}?format=full",
+            gmail_api_base()
+        );
         let body = self.fetch_with_retry(token, &url).await?;
         serde_json::from_str(&body).context("decode gmail message")
     }
 
+    async fn get_raw_message(&self, token: &str, message_id: &str) -> Result<Vec<u8>> {
+        let url = format!(
+            "{}/users/me/messages/{message_id}?format=raw",
+            gmail_api_base()
+        );
+        let body = self.fetch_with_retry(token, &url).await?;
+        let message: GmailRawMessage =
+            serde_json::from_str(&body).context("decode gmail raw message")?;
+        URL_SAFE_NO_PAD
+            .decode(message.raw.trim_end_matches('='))
+            .context("base64url decode gmail raw message")
+    }
+
+    /// Stores the RFC 822 source of the given messages when raw storage is
+    /// on. Messages already stored are skipped; failures are reported but
+    /// do not stop the sync.
+    async fn store_raw_messages(
+        &self,
+        db: &Database,
+        account: &Account,
+        message_ids: &[String],
+        report: &mut SyncReport,
+    ) {
+        let Some(raw) = &self.raw else {
+            return;
+        };
+        let message_ids = match raw_fetches_due(db, account, message_ids, self.defer_raw) {
+            Ok(message_ids) => message_ids,
+            Err(error) => {
+                report
+                    .errors
+                    .push(ConnectorError::classify(&error).context("deferred raw ids"));
+                return;
+            }
+        };
+        for message_id in &message_ids {
+            if raw.contains(message_id) {
+                continue;
+            }
+            let stored = match self.get_access_token(db, account).await {
+                Ok(token) => match self.get_raw_message(&token, message_id).await {
+                    Ok(message) => raw
+                        .put(message_id, &message)
+                        .context("write raw message file"),
+                    Err(error) => Err(error),
+                },
+                Err(error) => Err(error),
+            };
+            if let Err(error) = stored {
+                report
+                    .errors
+                    .push(ConnectorError::classify(&error).context(format!("raw id={message_id}")));
+            }
+        }
+    }
+
+    fn delete_raw_message(&self, message_id: &str) {
+        if let Some(raw) = &self.raw {
+            if let Err(error) = raw.delete(message_id) {
+                warn!("failed to delete raw gmail message {message_id}: {error}");
+            }
+        }
+    }
+
+    /// Sends a mutating request, retrying on throttling. A 403 usually means
+    /// the refresh token was granted without the `gmail.modify` scope.
+    async fn send_write_with_retry(&self, request: RequestBuilder, action: &str) -> Result<String> {
+        let mut backoff_seconds = 1u64;
+
+        for attempt in 0..=MAX_RATE_LIMIT_RETRIES {
+            let response = request
+                .try_clone()
+                .ok_or_else(|| anyhow!("gmail {action} request cannot be retried"))?
+                .send()
+                .await
+                .with_context(|| format!("gmail api request: {action}"))?;
+            self.usage.request();
+
+            if response.status() == StatusCode::TOO_MANY_REQUESTS
+                && attempt < MAX_RATE_LIMIT_RETRIES
+            {
+                let retry_after_seconds = response
+                    .headers()
+                    .get("retry-after")
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .unwrap_or(backoff_seconds);
+
+                sleep(StdDuration::from_secs(retry_after_seconds)).await;
+                backoff_seconds = (backoff_seconds * 2).min(32);
+                continue;
+            }
+
+            let status = response.status();
+            let body = response
+                .text()
+                .await
+                .context("read gmail api response body")?;
+            self.usage.received(body.len());
+            if status == StatusCode::FORBIDDEN {
+                return Err(ConnectorError::from_status(
+                    status,
+                    format!(
+                        "gmail {action} forbidden: the refresh token needs the gmail.modify scope (body={})",
+                        redact_response_body(&body)
+                    ),
+                )
+                .into());
+            }
+            if !status.is_success() {
+                return Err(ConnectorError::from_status(
+                    status,
+                    format!(
+                        "gmail {action} failed: status={} body={}",
+                        status,
+                        redact_response_body(&body)
+                    ),
+                )
+                .into());
+            }
+            return Ok(body);
+        }
+
+        Err(ConnectorError::transient(format!("gmail {action} failed without response")).into())
+    }
+
+    async fn modify_labels(
+        &self,
+        token: &str,
+        message_id: &str,
+        changes: &GmailLabelChanges,
+    ) -> Result<GmailMessageLabels> {
+        let url = format!("{}/users/me/messages/{message_id}/modify", gmail_api_base());
+        let request = self.client.post(&url).bearer_auth(token).json(changes);
+        let body = self.send_write_with_retry(request, "modify labels").await?;
+        serde_json::from_str(&body).context("decode gmail modify response")
+    }
+
+    async fn trash_message(&self, token: &str, message_id: &str) -> Result<GmailMessageLabels> {
+        let url = format!("{}/users/me/messages/{message_id}/trash", gmail_api_base());
+        let request = self.client.post(&url).bearer_auth(token);
+        let body = self.send_write_with_retry(request, "trash message").await?;
+        serde_json::from_str(&body).context("decode gmail trash response")
+    }
+
     async fn list_history(
         &self,
         token: &str,
@@ -308,7 +557,8 @@ impl GmailApiConnector {
         page_token: Option<&str>,
     ) -> Result<GmailHistoryList> {
         let mut url = format!(
-            "{GMAIL_API_BASE}/users/me/history?startHistoryId={start_history_id}&maxResults={DEFAULT_PAGE_SIZE}"
+            "{}/users/me/history?startHistoryId={start_history_id}&maxResults={DEFAULT_PAGE_SIZE}",
+            gmail_api_base()
         );
         if let Some(pt) = page_token {
             url.push_str(&format!("&pageToken={pt}"));
@@ -322,33 +572,38 @@ impl GmailApiConnector {
         &self,
         db: &Database,
         account: &Account,
+        expected_total: Option<u64>,
     ) -> Result<Vec<String>> {
         let mut all_ids = Vec::new();
         let mut page_token: Option<String> = None;
-        let mut page_number = 0u64;
+        let empty_report = SyncReport::default();
+        let mut stage = StageProgress::start(
+            &self.progress,
+            &account.account_id,
+            "enumerate_ids",
+            None,
+            expected_total,
+        );
 
         loop {
             let token = self.get_access_token(db, account).await?;
             let list = self.list_message_ids(&token, page_token.as_deref()).await?;
             let messages = list.messages.unwrap_or_default();
             let page_size = messages.len();
-            page_number += 1;
 
             for stub in messages {
                 all_ids.push(stub.id);
             }
 
-            eprintln!(
-                "gmail enumerate {}: page {} ({} ids), {} total so far",
-                account.account_id, page_number, page_size, all_ids.len(),
-            );
+            stage.page(page_size, &empty_report);
 
             page_token = list.next_page_token;
-            if page_token.is_none() {
+            if page_token.is_none() || self.shutdown.is_requested() {
                 break;
             }
         }
 
+        stage.finish(&empty_report);
         Ok(all_ids)
     }
 
@@ -360,15 +615,19 @@ impl GmailApiConnector {
         ids: &[String],
         report: &mut SyncReport,
     ) -> BatchParseResult {
-        let empty = BatchParseResult {
+        let mut empty = BatchParseResult {
             messages: Vec::new(),
             retryable_ids: ids.to_vec(),
+            unparsed: Vec::new(),
+            throttled: false,
         };
 
         if ids.is_empty() {
             return BatchParseResult {
                 messages: Vec::new(),
                 retryable_ids: Vec::new(),
+                unparsed: Vec::new(),
+                throttled: false,
             };
         }
 
@@ -389,12 +648,12 @@ impl GmailApiConnector {
         let content_type = format!("multipart/mixed; boundary={boundary}");
 
         let mut backoff_seconds = 1u64;
-        let mut last_error = String::new();
+        let mut last_error = ConnectorError::transient("batch request failed without response");
 
         for attempt in 0..=MAX_RATE_LIMIT_RETRIES {
             let response = match self
                 .client
-                .post(BATCH_ENDPOINT)
+                .post(gmail_batch_url())
                 .bearer_auth(token)
                 .header("content-type", &content_type)
                 .body(body.clone())
@@ -403,7 +662,7 @@ impl GmailApiConnector {
             {
                 Ok(r) => r,
                 Err(e) => {
-                    last_error = format!("batch request error: {e}");
+                    last_error = ConnectorError::transient(format!("batch request error: {e}"));
                     if attempt < MAX_RATE_LIMIT_RETRIES {
                         sleep(StdDuration::from_secs(backoff_seconds)).await;
                         backoff_seconds = (backoff_seconds * 2).min(32);
@@ -413,8 +672,10 @@ impl GmailApiConnector {
                     return empty;
                 }
             };
+            self.usage.request();
 
             if response.status() == StatusCode::TOO_MANY_REQUESTS {
+                empty.throttled = true;
                 let retry_after = response
                     .headers()
                     .get("retry-after")
@@ -436,23 +697,30 @@ impl GmailApiConnector {
             let response_body = match response.text().await {
                 Ok(b) => b,
                 Err(e) => {
-                    report
-                        .errors
-                        .push(format!("batch response read error: {e}"));
+                    report.errors.push(ConnectorError::transient(format!(
+                        "batch response read error: {e}"
+                    )));
                     return empty;
                 }
             };
+            self.usage.received(response_body.len());
 
             if !status.is_success() {
-                report.errors.push(format!(
-                    "batch request failed: status={} body={}",
+                report.errors.push(ConnectorError::from_status(
                     status,
-                    redact_response_body(&response_body)
+                    format!(
+                        "batch request failed: status={} body={}",
+                        status,
+                        redact_response_body(&response_body)
+                    ),
                 ));
                 return empty;
             }
 
-            return parse_batch_response(&response_body, &response_content_type, ids, report);
+            let mut result =
+                parse_batch_response(&response_body, &response_content_type, ids, report);
+            result.throttled |= empty.throttled;
+            return result;
         }
 
         report.errors.push(last_error);
@@ -473,16 +741,19 @@ impl GmailApiConnector {
         let new_history_id = profile.history_id;
 
         // 2. Enumerate all message IDs from the API (lightweight, IDs only)
-        eprintln!(
-            "gmail sync {}: enumerating all message IDs...",
-            account.account_id
-        );
-        let all_api_ids = self.enumerate_all_message_ids(db, account).await?;
-        eprintln!(
-            "gmail sync {}: {} message IDs found in mailbox",
-            account.account_id,
-            all_api_ids.len()
+        let all_api_ids = self
+            .enumerate_all_message_ids(db, account, profile.messages_total)
+            .await?;
+        self.notice(
+            account,
+            format!("{} message IDs found in mailbox", all_api_ids.len()),
         );
+        if self.shutdown.is_requested() {
+            // Nothing fetched yet and the historyId watermark is not stored,
+            // so the next run starts the full sync over.
+            report.interrupted = true;
+            return Ok(report);
+        }
 
         // 3. Diff against DB to find missing IDs
         let existing_ids = db
@@ -492,11 +763,13 @@ impl GmailApiConnector {
             .iter()
             .filter(|id| !existing_ids.contains(*id))
             .collect();
-        eprintln!(
-            "gmail sync {}: {} already in DB, {} to fetch",
-            account.account_id,
-            existing_ids.len(),
-            missing_ids.len()
+        self.notice(
+            account,
+            format!(
+                "{} already in DB, {} to fetch",
+                existing_ids.len(),
+                missing_ids.len()
+            ),
         );
 
         if missing_ids.is_empty() {
@@ -507,12 +780,19 @@ impl GmailApiConnector {
         // 4. Batch-fetch missing messages (newest first, already in API order)
         //    Retries 429-throttled IDs with backoff (up to MAX_BATCH_RETRIES rounds)
         let total_missing = missing_ids.len();
-        let mut ids_to_fetch: Vec<String> = missing_ids
-            .into_iter()
-            .cloned()
-            .collect();
-
-        let mut fetched_total = 0usize;
+        let mut ids_to_fetch: Vec<String> = missing_ids.into_iter().cloned().collect();
+
+        let mut sizer = self.load_batch_sizer(db, account)?;
+        let label_names = db
+            .label_names(&account.account_id)
+            .context("load gmail label names")?;
+        let mut stage = StageProgress::start(
+            &self.progress,
+            &account.account_id,
+            "fetch_messages",
+            None,
+            Some(total_missing as u64),
+        );
         for retry_round in 0..=MAX_BATCH_RETRIES {
             if ids_to_fetch.is_empty() {
                 break;
@@ -520,73 +800,99 @@ impl GmailApiConnector {
 
             if retry_round > 0 {
                 let backoff = StdDuration::from_secs(2u64.pow(retry_round as u32));
-                eprintln!(
-                    "gmail sync {}: retry round {} for {} throttled IDs (backoff {:?})",
-                    account.account_id,
-                    retry_round,
-                    ids_to_fetch.len(),
-                    backoff,
+                self.notice(
+                    account,
+                    format!(
+                        "retry round {} for {} throttled IDs (backoff {:?})",
+                        retry_round,
+                        ids_to_fetch.len(),
+                        backoff,
+                    ),
                 );
                 sleep(backoff).await;
             }
 
-            let chunks: Vec<Vec<String>> = ids_to_fetch
-                .chunks(BATCH_SIZE)
-                .map(|chunk| chunk.to_vec())
-                .collect();
-            let num_chunks = chunks.len();
             let mut next_round_retries = Vec::new();
+            let mut remaining = ids_to_fetch.as_slice();
+            let mut batch_idx = 0;
 
-            for (batch_idx, chunk) in chunks.into_iter().enumerate() {
+            while !remaining.is_empty() {
+                let (chunk, rest) = remaining.split_at(sizer.size().min(remaining.len()));
+                remaining = rest;
                 let token = self.get_access_token(db, account).await?;
-                let batch_result = self
-                    .batch_get_messages(&token, &chunk, &mut report)
-                    .await;
+                let batch_result = self.batch_get_messages(&token, chunk, &mut report).await;
+                sizer.record(batch_result.throttled);
 
+                let mut added = Vec::new();
                 for message in &batch_result.messages {
-                    match self.apply_message_buffered(db, indexer, account, message) {
-                        Ok(ApplyResult::Added) => report.emails_added += 1,
+                    match self.apply_message_buffered(db, indexer, account, &label_names, message) {
+                        Ok(ApplyResult::Added) => {
+                            report.emails_added += 1;
+                            added.push(message.id.clone());
+                        }
                         Ok(ApplyResult::Updated) => report.emails_updated += 1,
                         Err(error) => {
-                            report
-                                .errors
-                                .push(format!("id={}: {error}", message.id));
+                            report.errors.push(
+                                ConnectorError::classify(&error)
+                                    .context(format!("id={}", message.id)),
+                            );
                         }
                     }
                 }
 
+                quarantine_unparsed(
+                    db,
+                    account,
+                    self.name(),
+                    None,
+                    &batch_result.unparsed,
+                    &mut report,
+                );
                 next_round_retries.extend(batch_result.retryable_ids);
+                self.store_raw_messages(db, account, &added, &mut report)
+                    .await;
 
                 // Commit index after each batch instead of per-email
                 if let Err(e) = indexer.commit() {
-                    report
-                        .errors
-                        .push(format!("index commit batch {}: {e}", batch_idx + 1));
+                    report.errors.push(ConnectorError::permanent(format!(
+                        "index commit batch {}: {e}",
+                        batch_idx + 1
+                    )));
                 }
 
-                fetched_total += batch_result.messages.len();
-                eprintln!(
-                    "gmail sync {}: batch {}/{} done, {} fetched / {} total missing",
-                    account.account_id,
-                    batch_idx + 1,
-                    num_chunks,
-                    fetched_total,
-                    total_missing,
-                );
+                stage.page(batch_result.messages.len(), &report);
+                batch_idx += 1;
+
+                if self.shutdown.is_requested() {
+                    self.store_batch_sizer(db, account, sizer)?;
+                    // Leave the historyId watermark unset: the next run diffs
+                    // the mailbox against SQLite and fetches only what is missing.
+                    self.notice(
+                        account,
+                        format!("interrupted after batch {batch_idx}; remaining messages will be fetched next run"),
+                    );
+                    report.interrupted = true;
+                    stage.finish(&report);
+                    return Ok(report);
+                }
             }
 
             ids_to_fetch = next_round_retries;
         }
 
         if !ids_to_fetch.is_empty() {
-            eprintln!(
-                "gmail sync {}: {} IDs still throttled after {} retries",
-                account.account_id,
-                ids_to_fetch.len(),
-                MAX_BATCH_RETRIES,
+            self.notice(
+                account,
+                format!(
+                    "{} IDs still throttled after {} retries",
+                    ids_to_fetch.len(),
+                    MAX_BATCH_RETRIES,
+                ),
             );
         }
+        stage.finish(&report);
 
+        self.store_batch_sizer(db, account, sizer)?;
         self.store_history_id(db, account, &new_history_id)?;
         Ok(report)
     }
@@ -602,6 +908,8 @@ impl GmailApiConnector {
         let mut seen_message_ids = HashSet::new();
 
         let mut page_token: Option<String> = None;
+        let mut stage =
+            StageProgress::start(&self.progress, &account.account_id, "history", None, None);
         let newest_history_id = loop {
             let token = self.get_access_token(db, account).await?;
 
@@ -611,8 +919,9 @@ impl GmailApiConnector {
             {
                 Ok(list) => list,
                 Err(error) => {
-                    let error_str = format!("{error}");
-                    if error_str.contains("404") || error_str.contains("historyId") {
+                    // Gmail answers 404 once startHistoryId is older than
+                    // its retention window.
+                    if let ConnectorError::NotFound { .. } = ConnectorError::classify(&error) {
                         warn!(
                             "gmail history expired for account {}, falling back to full sync",
                             account.account_id
@@ -624,24 +933,35 @@ impl GmailApiConnector {
             };
 
             let current_history_id = history_list.history_id.clone();
+            let records = history_list.history.unwrap_or_default();
+            let page_size = records.len();
 
             self.apply_history_records(
                 db,
                 indexer,
                 account,
-                history_list.history.unwrap_or_default(),
+                records,
                 &mut seen_message_ids,
                 &mut report,
             )
             .await;
 
+            stage.page(page_size, &report);
+
             page_token = history_list.next_page_token;
             if page_token.is_none() {
                 break current_history_id;
             }
+            if self.shutdown.is_requested() {
+                // Keep the previous watermark; replaying history is idempotent.
+                report.interrupted = true;
+                stage.finish(&report);
+                return Ok(report);
+            }
         };
 
         self.store_history_id(db, account, &newest_history_id)?;
+        stage.finish(&report);
         Ok(report)
     }
 
@@ -654,6 +974,16 @@ impl GmailApiConnector {
         seen_message_ids: &mut HashSet<String>,
         report: &mut SyncReport,
     ) {
+        let mut state_changes = 0usize;
+        let label_names = match db.label_names(&account.account_id) {
+            Ok(names) => names,
+            Err(error) => {
+                report.errors.push(ConnectorError::permanent(format!(
+                    "load gmail label names: {error}"
+                )));
+                HashMap::new()
+            }
+        };
         for record in records {
             let mut message_ids = Vec::new();
             if let Some(added) = &record.messages_added {
@@ -668,17 +998,31 @@ impl GmailApiConnector {
                         .conn()
                         .execute("DELETE FROM emails WHERE id = ?", [id.as_str()]);
                     let _ = indexer.delete_email(id);
+                    self.delete_raw_message(id);
                     report.emails_updated += 1;
                 }
             }
-            if let Some(label_added) = &record.labels_added {
-                for entry in label_added {
-                    message_ids.push(entry.message.id.clone());
+            // A label change carries the message's new label set, which is
+            // all a stored email needs; only unknown messages are fetched.
+            let label_events = record
+                .labels_added
+                .iter()
+                .chain(record.labels_removed.iter())
+                .flatten();
+            for entry in label_events {
+                let stub = &entry.message;
+                if message_ids.contains(&stub.id) {
+                    continue;
                 }
-            }
-            if let Some(label_removed) = &record.labels_removed {
-                for entry in label_removed {
-                    message_ids.push(entry.message.id.clone());
+                match self.apply_label_change(db, indexer, account, &label_names, stub) {
+                    Ok(true) => {
+                        state_changes += 1;
+                        report.emails_updated += 1;
+                    }
+                    Ok(false) => message_ids.push(stub.id.clone()),
+                    Err(error) => report.errors.push(
+                        ConnectorError::classify(&error).context(format!("labels id={}", stub.id)),
+                    ),
                 }
             }
 
@@ -689,34 +1033,89 @@ impl GmailApiConnector {
                 let token = match self.get_access_token(db, account).await {
                     Ok(t) => t,
                     Err(e) => {
-                        report
-                            .errors
-                            .push(format!("token refresh for id={msg_id}: {e}"));
+                        report.errors.push(
+                            ConnectorError::classify(&e)
+                                .context(format!("token refresh for id={msg_id}")),
+                        );
                         continue;
                     }
                 };
                 match self.get_message(&token, &msg_id).await {
-                    Ok(message) => match self.apply_message(db, indexer, account, &message) {
-                        Ok(ApplyResult::Added) => report.emails_added += 1,
-                        Ok(ApplyResult::Updated) => report.emails_updated += 1,
-                        Err(error) => {
-                            report.errors.push(format!("id={msg_id}: {error}"));
+                    Ok(message) => {
+                        match self.apply_message(db, indexer, account, &label_names, &message) {
+                            Ok(ApplyResult::Added) => {
+                                report.emails_added += 1;
+                                self.store_raw_messages(
+                                    db,
+                                    account,
+                                    std::slice::from_ref(&msg_id),
+                                    report,
+                                )
+                                .await;
+                            }
+                            Ok(ApplyResult::Updated) => report.emails_updated += 1,
+                            Err(error) => {
+                                report.errors.push(
+                                    ConnectorError::classify(&error)
+                                        .context(format!("id={msg_id}")),
+                                );
+                            }
                         }
-                    },
-                    Err(error) => {
-                        if format!("{error}").contains("404") {
+                    }
+                    Err(error) => match ConnectorError::classify(&error) {
+                        ConnectorError::NotFound { .. } => {
                             let _ = db
                                 .conn()
                                 .execute("DELETE FROM emails WHERE id = ?", [msg_id.as_str()]);
                             let _ = indexer.delete_email(&msg_id);
+                            self.delete_raw_message(&msg_id);
                             report.emails_updated += 1;
-                        } else {
-                            report.errors.push(format!("fetch id={msg_id}: {error}"));
                         }
-                    }
+                        error => report
+                            .errors
+                            .push(error.context(format!("fetch id={msg_id}"))),
+                    },
                 }
             }
         }
+
+        if state_changes > 0 {
+            if let Err(e) = indexer.commit() {
+                report.errors.push(ConnectorError::permanent(format!(
+                    "index commit after label changes: {e}"
+                )));
+            }
+        }
+    }
+
+    /// Applies a history label event to the stored copy of the message.
+    /// Returns `false` when the event has no label set or the message is not
+    /// stored yet, in which case it must be fetched.
+    fn apply_label_change(
+        &self,
+        db: &Database,
+        indexer: &mut EmailIndex,
+        account: &Account,
+        label_names: &HashMap<String, String>,
+        stub: &GmailMessageStub,
+    ) -> Result<bool> {
+        let Some(label_ids) = stub.label_ids.as_deref() else {
+            return Ok(false);
+        };
+        let Some(mut email) = db
+            .get_email(&stub.id)
+            .with_context(|| format!("load stored email {}", stub.id))?
+        else {
+            return Ok(false);
+        };
+        apply_label_state(&mut email, label_ids);
+        resolve_label_names(&mut email, label_names);
+        db.update_email_flags(&email)
+            .with_context(|| format!("update labels of gmail email {}", email.id))?;
+        indexer
+            .update_email(&email, &account.account_type.to_string())
+            .with_context(|| format!("reindex gmail email {}", email.id))?;
+        Ok(true)
     }
 
     fn apply_message(
@@ -724,16 +1123,28 @@ impl GmailApiConnector {
         db: &Database,
         indexer: &mut EmailIndex,
         account: &Account,
+        label_names: &HashMap<String, String>,
         message: &GmailMessage,
     ) -> Result<ApplyResult> {
-        let email = map_gmail_message_to_email(message, account)?;
+        let mut email = map_gmail_message_to_email(message, account)?;
+        resolve_label_names(&mut email, label_names);
         let existed = db
             .get_email(&email.id)
             .with_context(|| format!("check existing email {}", email.id))?
             .is_some();
 
+        for copy in db
+            .merge_stored_copies(&email)
+            .with_context(|| format!("merge stored copies of gmail email {}", email.id))?
+        {
+            indexer
+                .delete_email_buffered(&copy)
+                .with_context(|| format!("unindex replaced copy {copy}"))?;
+        }
         db.insert_email(&email)
             .with_context(|| format!("upsert gmail email {}", email.id))?;
+        db.replace_attachments(&email.id, &payload_attachments(&email.id, &message.payload))
+            .with_context(|| format!("store attachments of gmail email {}", email.id))?;
         indexer
             .add_email(&email, &account.account_type.to_string())
             .with_context(|| format!("index gmail email {}", email.id))?;
@@ -746,22 +1157,60 @@ impl GmailApiConnector {
         }
     }
 
+    /// Stores a message quarantined by an earlier sync, decoding the kept
+    /// payload again. Returns false when the email is already stored. The
+    /// index write is buffered.
+    pub(crate) fn apply_quarantined(
+        &self,
+        db: &Database,
+        indexer: &mut EmailIndex,
+        account: &Account,
+        entry: &QuarantinedMessage,
+    ) -> Result<bool> {
+        let message: GmailMessage =
+            serde_json::from_str(&entry.payload).context("decode gmail message")?;
+        if db
+            .get_email(&message.id)
+            .with_context(|| format!("check existing email {}", message.id))?
+            .is_some()
+        {
+            return Ok(false);
+        }
+        let label_names = db
+            .label_names(&account.account_id)
+            .context("load gmail label names")?;
+        self.apply_message_buffered(db, indexer, account, &label_names, &message)?;
+        Ok(true)
+    }
+
     /// Like apply_message but buffers the index write (no commit per email).
     fn apply_message_buffered(
         &self,
         db: &Database,
         indexer: &mut EmailIndex,
         account: &Account,
+        label_names: &HashMap<String, String>,
         message: &GmailMessage,
     ) -> Result<ApplyResult> {
-        let email = map_gmail_message_to_email(message, account)?;
+        let mut email = map_gmail_message_to_email(message, account)?;
+        resolve_label_names(&mut email, label_names);
         let existed = db
             .get_email(&email.id)
             .with_context(|| format!("check existing email {}", email.id))?
             .is_some();
 
+        for copy in db
+            .merge_stored_copies(&email)
+            .with_context(|| format!("merge stored copies of gmail email {}", email.id))?
+        {
+            indexer
+                .delete_email_buffered(&copy)
+                .with_context(|| format!("unindex replaced copy {copy}"))?;
+        }
         db.insert_email(&email)
             .with_context(|| format!("upsert gmail email {}", email.id))?;
+        db.replace_attachments(&email.id, &payload_attachments(&email.id, &message.payload))
+            .with_context(|| format!("store attachments of gmail email {}", email.id))?;
         indexer
             .add_email_buffered(&email, &account.account_type.to_string())
             .with_context(|| format!("index gmail email {}", email.id))?;
@@ -784,28 +1233,23 @@ struct GmailCredentials {
 
 impl GmailCredentials {
     fn resolve(account: &Account) -> Result<Self> {
-        let client_id = std::env::var("ESS_GMAIL_CLIENT_ID")
-            .ok()
-            .filter(|value| !value.trim().is_empty())
-            .or_else(|| config_string(account, "client_id"))
+        let client_id = secrets::credential(env::GMAIL_CLIENT_ID, account, "client_id")
             .ok_or_else(|| {
-                anyhow!("missing gmail client id (ESS_GMAIL_CLIENT_ID/account.config)")
+                anyhow!("missing gmail client id (ESS_GMAIL_CLIENT_ID/keychain/account.config)")
             })?;
 
-        let client_secret = std::env::var("ESS_GMAIL_CLIENT_SECRET")
-            .ok()
-            .filter(|value| !value.trim().is_empty())
-            .or_else(|| config_string(account, "client_secret"))
+        let client_secret = secrets::credential(env::GMAIL_CLIENT_SECRET, account, "client_secret")
             .ok_or_else(|| {
-                anyhow!("missing gmail client secret (ESS_GMAIL_CLIENT_SECRET/account.config)")
+                anyhow!(
+                    "missing gmail client secret (ESS_GMAIL_CLIENT_SECRET/keychain/account.config)"
+                )
             })?;
 
-        let refresh_token = std::env::var("ESS_GMAIL_REFRESH_TOKEN")
-            .ok()
-            .filter(|value| !value.trim().is_empty())
-            .or_else(|| config_string(account, "refresh_token"))
+        let refresh_token = secrets::credential(env::GMAIL_REFRESH_TOKEN, account, "refresh_token")
             .ok_or_else(|| {
-                anyhow!("missing gmail refresh token (ESS_GMAIL_REFRESH_TOKEN/account.config)")
+                anyhow!(
+                    "missing gmail refresh token (ESS_GMAIL_REFRESH_TOKEN/keychain/account.config)"
+                )
             })?;
 
         Ok(Self {
@@ -816,15 +1260,44 @@ impl GmailCredentials {
     }
 }
 
-fn config_string(account: &Account, key: &str) -> Option<String> {
-    account
-        .config
-        .as_ref()
-        .and_then(|config| config.get(key))
-        .and_then(|value| value.as_str())
-        .map(str::trim)
-        .filter(|value| !value.is_empty())
-        .map(str::to_string)
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+struct GmailLabelChanges {
+    #[serde(rename = "addLabelIds", skip_serializing_if = "Vec::is_empty")]
+    add_label_ids: Vec<&'static str>,
+    #[serde(rename = "removeLabelIds", skip_serializing_if = "Vec::is_empty")]
+    remove_label_ids: Vec<&'static str>,
+}
+
+/// Message resource returned by `modify` and `trash`.
+#[derive(Debug, Deserialize)]
+struct GmailMessageLabels {
+    id: String,
+    #[serde(rename = "labelIds", default)]
+    label_ids: Vec<String>,
+}
+
+/// Read state is the absence of `UNREAD`; flagged maps to `STARRED`.
+fn flag_label_changes(update: FlagUpdate) -> GmailLabelChanges {
+    let mut changes = GmailLabelChanges::default();
+    match update.read {
+        Some(true) => changes.remove_label_ids.push("UNREAD"),
+        Some(false) => changes.add_label_ids.push("UNREAD"),
+        None => {}
+    }
+    match update.flagged {
+        Some(true) => changes.add_label_ids.push("STARRED"),
+        Some(false) => changes.remove_label_ids.push("STARRED"),
+        None => {}
+    }
+    changes
+}
+
+pub(crate) fn gmail_api_base() -> String {
+    env::var(env::GMAIL_API_BASE).unwrap_or_else(|| GMAIL_API_BASE.to_string())
+}
+
+pub(crate) fn gmail_batch_url() -> String {
+    env::var(env::GMAIL_BATCH_URL).unwrap_or_else(|| BATCH_ENDPOINT.to_string())
 }
 
 fn redact_response_body(body: &str) -> String {
@@ -841,14 +1314,47 @@ fn redact_response_body(body: &str) -> String {
 }
 
 /// Result of parsing a batch response: successfully parsed messages and retryable IDs.
-struct BatchParseResult {
-    messages: Vec<GmailMessage>,
-    retryable_ids: Vec<String>,
+pub(crate) struct BatchParseResult {
+    pub(crate) messages: Vec<GmailMessage>,
+    pub(crate) retryable_ids: Vec<String>,
+    /// Messages Gmail returned that did not decode, for the quarantine.
+    pub(crate) unparsed: Vec<UnparsedMessage>,
+    /// Gmail answered the batch or one of its sub-requests with 429.
+    pub(crate) throttled: bool,
+}
+
+/// Messages per batch request, tuned per account: the size grows by
+/// `BATCH_SIZE_STEP` after each batch without a 429 and halves after one
+/// with throttling, so a full sync settles near the largest size the
+/// account's quota allows. It is stored in `sync_state` between syncs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BatchSizer {
+    size: usize,
+}
+
+impl BatchSizer {
+    fn new(size: usize) -> Self {
+        Self {
+            size: size.clamp(MIN_BATCH_SIZE, MAX_BATCH_SIZE),
+        }
+    }
+
+    fn size(self) -> usize {
+        self.size
+    }
+
+    fn record(&mut self, throttled: bool) {
+        self.size = if throttled {
+            (self.size / 2).max(MIN_BATCH_SIZE)
+        } else {
+            (self.size + BATCH_SIZE_STEP).min(MAX_BATCH_SIZE)
+        };
+    }
 }
 
 /// Parse a Gmail batch API multipart/mixed response into individual GmailMessages.
 /// Sub-requests that return 429 are collected as retryable IDs rather than errors.
-fn parse_batch_response(
+pub(crate) fn parse_batch_response(
     body: &str,
     content_type: &str,
     sent_ids: &[String],
@@ -857,6 +1363,8 @@ fn parse_batch_response(
     let mut result = BatchParseResult {
         messages: Vec::new(),
         retryable_ids: Vec::new(),
+        unparsed: Vec::new(),
+        throttled: false,
     };
 
     // Extract boundary from content-type header
@@ -870,9 +1378,9 @@ fn parse_batch_response(
         .next();
 
     let Some(boundary) = boundary else {
-        report
-            .errors
-            .push("batch response missing boundary in content-type".to_string());
+        report.errors.push(ConnectorError::transient(
+            "batch response missing boundary in content-type",
+        ));
         // All IDs are retryable if we can't parse the response at all
         result.retryable_ids = sent_ids.to_vec();
         return result;
@@ -905,6 +1413,11 @@ fn parse_batch_response(
         let status_region = &normalized[http_pos..];
         let status_line_end = status_region.find('\n').unwrap_or(status_region.len());
         let status_line = &status_region[..status_line_end];
+        let status = status_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|code| code.parse::<u16>().ok())
+            .and_then(|code| StatusCode::from_u16(code).ok());
 
         // Find JSON body: first '{' after the HTTP status line
         let after_status = &normalized[http_pos + status_line_end..];
@@ -922,7 +1435,8 @@ fn parse_batch_response(
         }
 
         // Handle rate limiting (429) — retryable
-        if status_line.contains("429") {
+        if status == Some(StatusCode::TOO_MANY_REQUESTS) {
+            result.throttled = true;
             if let Some(id) = &current_id {
                 result.retryable_ids.push(id.clone());
                 seen_ids.insert(id.clone());
@@ -930,15 +1444,19 @@ fn parse_batch_response(
             continue;
         }
 
-        // Handle other non-200 — permanent error
-        if !status_line.contains("200") {
+        // Handle other non-200 — classified by status, not retried here
+        if status != Some(StatusCode::OK) {
             if let Some(id) = &current_id {
                 seen_ids.insert(id.clone());
             }
-            report.errors.push(format!(
+            let message = format!(
                 "batch sub-request failed: {}",
                 redact_response_body(json_body)
-            ));
+            );
+            report.errors.push(match status {
+                Some(status) => ConnectorError::from_status(status, message),
+                None => ConnectorError::permanent(message),
+            });
             continue;
         }
 
@@ -952,10 +1470,14 @@ fn parse_batch_response(
                 if let Some(id) = &current_id {
                     seen_ids.insert(id.clone());
                 }
-                report.errors.push(format!(
-                    "batch response parse error: {e} body={}",
-                    redact_response_body(json_body)
-                ));
+                result.unparsed.push(UnparsedMessage {
+                    message_id: serde_json::from_str::<serde_json::Value>(json_body)
+                        .ok()
+                        .and_then(|value| value.get("id")?.as_str().map(str::to_string))
+                        .or(current_id),
+                    payload: json_body.to_string(),
+                    error: format!("batch response parse error: {e}"),
+                });
             }
         }
     }
@@ -1004,7 +1526,10 @@ fn find_json_object_end(s: &str) -> Option<usize> {
     None
 }
 
-fn map_gmail_message_to_email(message: &GmailMessage, account: &Account) -> Result<Email> {
+pub(crate) fn map_gmail_message_to_email(
+    message: &GmailMessage,
+    account: &Account,
+) -> Result<Email> {
     let id = message.id.clone();
 
     let subject = extract_header(&message.payload, "Subject");
@@ -1041,19 +1566,40 @@ fn map_gmail_message_to_email(message: &GmailMessage, account: &Account) -> Resu
             .map(|dt| dt.with_timezone(&Utc).to_rfc3339())
     });
 
-    let label_ids = message.label_ids.as_deref().unwrap_or_default();
-    let is_read = Some(!label_ids.iter().any(|l| l == "UNREAD"));
     let has_attachments = Some(payload_has_attachments(&message.payload));
-    let folder = Some(map_labels_to_folder(label_ids));
-    let categories = extract_user_labels(label_ids);
     let web_link = Some(format!(
         "https://mail.google.com/mail/u/0/#inbox/{}",
         message.id
     ));
+    let mut metadata = serde_json::json!({
+        "connector": "gmail_api",
+        "source": "gmail_sync"
+    });
+    let headers = || {
+        message
+            .payload
+            .headers
+            .as_deref()
+            .unwrap_or_default()
+            .iter()
+            .map(|header| (header.name.as_str(), header.value.as_str()))
+    };
+    if let Some(list_headers) = ListHeaders::from_headers(headers()) {
+        metadata[LIST_HEADERS_KEY] = serde_json::to_value(list_headers)?;
+    }
+    let thread_headers = ThreadHeaders::from_headers(headers());
+    if let Some(event) = find_calendar_part(&message.payload)
+        .as_deref()
+        .and_then(CalendarEvent::parse_ics)
+    {
+        metadata[CALENDAR_EVENT_KEY] = serde_json::to_value(event)?;
+    }
 
-    Ok(Email {
+    let mut email = Email {
         id,
         internet_message_id,
+        in_reply_to: thread_headers.in_reply_to,
+        references: thread_headers.references,
         conversation_id: Some(message.thread_id.clone()),
         account_id: Some(account.account_id.clone()),
         subject,
@@ -1068,21 +1614,27 @@ fn map_gmail_message_to_email(message: &GmailMessage, account: &Account) -> Resu
         received_at,
         sent_at,
         importance: Some(importance.unwrap_or_else(|| "normal".to_string())),
-        is_read,
+        is_read: None,
         has_attachments,
-        folder,
-        categories,
-        flag_status: if label_ids.iter().any(|l| l == "STARRED") {
-            Some("flagged".to_string())
-        } else {
-            None
-        },
+        folder: None,
+        categories: Vec::new(),
+        flag_status: None,
         web_link,
-        metadata: Some(serde_json::json!({
-            "connector": "gmail_api",
-            "source": "gmail_sync"
-        })),
-    })
+        metadata: Some(metadata),
+    };
+    apply_label_state(&mut email, message.label_ids.as_deref().unwrap_or_default());
+    Ok(email)
+}
+
+/// Derives read state, star, folder and user labels from a label set.
+fn apply_label_state(email: &mut Email, label_ids: &[String]) {
+    email.is_read = Some(!label_ids.iter().any(|l| l == "UNREAD"));
+    email.folder = Some(map_labels_to_folder(label_ids));
+    email.categories = extract_user_labels(label_ids);
+    email.flag_status = label_ids
+        .iter()
+        .any(|l| l == "STARRED")
+        .then(|| "flagged".to_string());
 }
 
 fn extract_header(payload: &GmailPayload, name: &str) -> Option<String> {
@@ -1095,7 +1647,7 @@ fn extract_header(payload: &GmailPayload, name: &str) -> Option<String> {
         .map(|h| h.value.clone())
 }
 
-fn parse_from_header(raw: Option<&str>) -> (Option<String>, Option<String>) {
+pub(crate) fn parse_from_header(raw: Option<&str>) -> (Option<String>, Option<String>) {
     let Some(raw) = raw else {
         return (None, None);
     };
@@ -1106,7 +1658,7 @@ fn parse_from_header(raw: Option<&str>) -> (Option<String>, Option<String>) {
 
     // Pattern: "Display Name <email@example.com>"
     if let Some(angle_start) = raw.rfind('<') {
-        if let Some(angle_end) = raw.rfind('>') {
+        if let Some(angle_end) = raw[angle_start..].find('>').map(|end| angle_start + end) {
             let address = raw[angle_start + 1..angle_end].trim().to_string();
             let name_part = raw[..angle_start].trim();
             // Strip surrounding quotes from display name
@@ -1129,7 +1681,7 @@ fn parse_from_header(raw: Option<&str>) -> (Option<String>, Option<String>) {
     (Some(raw.to_string()), None)
 }
 
-fn parse_address_list(raw: Option<&str>) -> Vec<String> {
+pub(crate) fn parse_address_list(raw: Option<&str>) -> Vec<String> {
     let Some(raw) = raw else {
         return Vec::new();
     };
@@ -1168,7 +1720,7 @@ fn extract_email_from_entry(entry: &str) -> Option<String> {
     }
 
     if let Some(start) = entry.rfind('<') {
-        if let Some(end) = entry.rfind('>') {
+        if let Some(end) = entry[start..].find('>').map(|end| start + end) {
             let addr = entry[start + 1..end].trim();
             if !addr.is_empty() {
                 return Some(addr.to_string());
@@ -1244,6 +1796,28 @@ fn collect_body_parts(
     }
 }
 
+/// The first inline `text/calendar` part, as sent with meeting invites.
+/// Invites only attached as an `.ics` file are not fetched.
+fn find_calendar_part(payload: &GmailPayload) -> Option<String> {
+    let is_calendar = payload
+        .mime_type
+        .as_deref()
+        .is_some_and(|mime_type| mime_type.eq_ignore_ascii_case("text/calendar"));
+    if is_calendar {
+        if let Some(data) = payload.body.as_ref().and_then(|body| body.data.as_deref()) {
+            if let Ok(decoded) = decode_body_data(data) {
+                return Some(decoded);
+            }
+        }
+    }
+    payload
+        .parts
+        .as_deref()
+        .unwrap_or_default()
+        .iter()
+        .find_map(find_calendar_part)
+}
+
 fn decode_body_data(data: &str) -> Result<String> {
     let bytes = URL_SAFE_NO_PAD
         .decode(data)
@@ -1267,6 +1841,50 @@ fn payload_has_attachments(payload: &GmailPayload) -> bool {
     false
 }
 
+/// Every part with a file name, in payload order. Parts without an
+/// `attachmentId` (small files inlined in the body) are keyed by position.
+fn payload_attachments(email_id: &str, payload: &GmailPayload) -> Vec<Attachment> {
+    fn collect(
+        email_id: &str,
+        payload: &GmailPayload,
+        path: &str,
+        attachments: &mut Vec<Attachment>,
+    ) {
+        if let Some(name) = payload.filename.as_deref().filter(|name| !name.is_empty()) {
+            let key = payload
+                .body
+                .as_ref()
+                .and_then(|body| body.attachment_id.clone())
+                .unwrap_or_else(|| format!("part{path}"));
+            let disposition = payload.headers.as_deref().and_then(|headers| {
+                headers
+                    .iter()
+                    .find(|header| header.name.eq_ignore_ascii_case("Content-Disposition"))
+                    .map(|header| header.value.trim().to_ascii_lowercase())
+            });
+            attachments.push(Attachment {
+                id: format!("{email_id}:{key}"),
+                email_id: email_id.to_string(),
+                name: Some(name.to_string()),
+                content_type: payload.mime_type.clone(),
+                size_bytes: payload
+                    .body
+                    .as_ref()
+                    .and_then(|body| body.size)
+                    .and_then(|size| i64::try_from(size).ok()),
+                is_inline: disposition.map(|value| value.starts_with("inline")),
+            });
+        }
+        for (position, part) in payload.parts.iter().flatten().enumerate() {
+            collect(email_id, part, &format!("{path}.{position}"), attachments);
+        }
+    }
+
+    let mut attachments = Vec::new();
+    collect(email_id, payload, "", &mut attachments);
+    attachments
+}
+
 fn map_labels_to_folder(label_ids: &[String]) -> String {
     if label_ids.iter().any(|l| l == "INBOX") {
         "inbox".to_string()
@@ -1283,6 +1901,52 @@ fn map_labels_to_folder(label_ids: &[String]) -> String {
     }
 }
 
+/// Replaces user label ids such as `Label_42` in `categories` with their
+/// names; ids without a stored label are kept as they are.
+fn resolve_label_names(email: &mut Email, label_names: &HashMap<String, String>) {
+    for category in &mut email.categories {
+        if let Some(name) = label_names.get(category.as_str()) {
+            category.clone_from(name);
+        }
+    }
+}
+
+/// Gmail labels as stored rows. A label's parent is the label named by the
+/// part of its name before the last `/`, when the account has one.
+fn map_gmail_labels(account_id: &str, labels: &[GmailLabel]) -> Vec<Label> {
+    let names = labels
+        .iter()
+        .map(|label| label.name.as_str())
+        .collect::<HashSet<_>>();
+    labels
+        .iter()
+        .map(|label| Label {
+            account_id: account_id.to_string(),
+            label_id: label.id.clone(),
+            name: label.name.clone(),
+            label_type: label
+                .label_type
+                .as_deref()
+                .unwrap_or("user")
+                .to_ascii_lowercase(),
+            parent: label
+                .name
+                .rsplit_once('/')
+                .map(|(parent, _)| parent)
+                .filter(|parent| names.contains(parent))
+                .map(str::to_string),
+            background_color: label
+                .color
+                .as_ref()
+                .and_then(|color| color.background_color.clone()),
+            text_color: label
+                .color
+                .as_ref()
+                .and_then(|color| color.text_color.clone()),
+        })
+        .collect()
+}
+
 fn extract_user_labels(label_ids: &[String]) -> Vec<String> {
     label_ids
         .iter()
@@ -1354,130 +2018,20 @@ struct CachedAccessToken {
     expires_at: DateTime<Utc>,
 }
 
-impl CachedAccessToken {
-    fn is_expired(&self) -> bool {
-        self.expires_at <= Utc::now()
-    }
-}
-
-// --- Encryption (shared pattern with graph_api) ---
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct EncryptedTokenEnvelope {
-    version: u8,
-    nonce_hex: String,
-    ciphertext_hex: String,
-}
-
-fn encrypt_cached_access_token(
-    token: &CachedAccessToken,
-    encryption_key: &[u8; TOKEN_CACHE_KEY_BYTES],
-) -> Result<String> {
-    let mut plaintext = serde_json::to_vec(token).context("serialize token payload")?;
-
-    let unbound_key = UnboundKey::new(&AES_256_GCM, encryption_key)
-        .map_err(|_| anyhow!("construct AES-256-GCM key"))?;
-    let key = LessSafeKey::new(unbound_key);
-
-    let mut nonce_bytes = [0u8; TOKEN_CACHE_NONCE_BYTES];
-    SystemRandom::new()
-        .fill(&mut nonce_bytes)
-        .map_err(|_| anyhow!("generate random nonce for token cache encryption"))?;
-
-    key.seal_in_place_append_tag(
-        Nonce::assume_unique_for_key(nonce_bytes),
-        Aad::empty(),
-        &mut plaintext,
-    )
-    .map_err(|_| anyhow!("encrypt gmail token cache"))?;
-
-    let envelope = EncryptedTokenEnvelope {
-        version: TOKEN_CACHE_ENVELOPE_VERSION,
-        nonce_hex: hex_encode(&nonce_bytes),
-        ciphertext_hex: hex_encode(&plaintext),
-    };
-
-    serde_json::to_string(&envelope).context("serialize encrypted token envelope")
+fn encrypt_cached_access_token(token: &CachedAccessToken, keys: &TokenCacheKeys) -> Result<String> {
+    let plaintext = serde_json::to_vec(token).context("serialize token payload")?;
+    Ok(keys.seal(&plaintext)?)
 }
 
+/// Returns the token and whether it was sealed with the previous rotation key.
 fn decrypt_cached_access_token(
     raw: &str,
-    encryption_key: &[u8; TOKEN_CACHE_KEY_BYTES],
-) -> Result<CachedAccessToken> {
-    let envelope: EncryptedTokenEnvelope =
-        serde_json::from_str(raw).context("parse encrypted token envelope")?;
-
-    if envelope.version != TOKEN_CACHE_ENVELOPE_VERSION {
-        return Err(anyhow!(
-            "unsupported token envelope version {}",
-            envelope.version
-        ));
-    }
-
-    let nonce_vec = hex_decode(&envelope.nonce_hex).context("decode envelope nonce")?;
-    let nonce_bytes: [u8; TOKEN_CACHE_NONCE_BYTES] = nonce_vec
-        .try_into()
-        .map_err(|_| anyhow!("invalid nonce length in token envelope"))?;
-    let mut ciphertext =
-        hex_decode(&envelope.ciphertext_hex).context("decode envelope ciphertext")?;
-
-    let unbound_key = UnboundKey::new(&AES_256_GCM, encryption_key)
-        .map_err(|_| anyhow!("construct AES-256-GCM key"))?;
-    let key = LessSafeKey::new(unbound_key);
-
-    let plaintext = key
-        .open_in_place(
-            Nonce::assume_unique_for_key(nonce_bytes),
-            Aad::empty(),
-            &mut ciphertext,
-        )
-        .map_err(|_| anyhow!("decrypt gmail token cache"))?;
-
-    serde_json::from_slice(plaintext).context("parse decrypted token payload")
-}
-
-fn parse_token_cache_key_hex(raw: &str) -> Result<[u8; TOKEN_CACHE_KEY_BYTES]> {
-    let decoded = hex_decode(raw).context("decode token cache key hex")?;
-    decoded
-        .try_into()
-        .map_err(|_| anyhow!("token cache key must be 32 bytes"))
-}
-
-fn hex_encode(bytes: &[u8]) -> String {
-    const HEX: &[u8; 16] = b"0123456789abcdef";
-    let mut out = String::with_capacity(bytes.len() * 2);
-    for byte in bytes {
-        out.push(HEX[(byte >> 4) as usize] as char);
-        out.push(HEX[(byte & 0x0f) as usize] as char);
-    }
-    out
-}
-
-fn hex_decode(raw: &str) -> Result<Vec<u8>> {
-    let value = raw.trim();
-    if !value.len().is_multiple_of(2) {
-        return Err(anyhow!("hex string length must be even"));
-    }
-
-    let mut out = Vec::with_capacity(value.len() / 2);
-    let bytes = value.as_bytes();
-    let mut idx = 0usize;
-    while idx < bytes.len() {
-        let hi = decode_hex_nibble(bytes[idx]).ok_or_else(|| anyhow!("invalid hex digit"))?;
-        let lo = decode_hex_nibble(bytes[idx + 1]).ok_or_else(|| anyhow!("invalid hex digit"))?;
-        out.push((hi << 4) | lo);
-        idx += 2;
-    }
-    Ok(out)
-}
-
-fn decode_hex_nibble(byte: u8) -> Option<u8> {
-    match byte {
-        b'0'..=b'9' => Some(byte - b'0'),
-        b'a'..=b'f' => Some(byte - b'a' + 10),
-        b'A'..=b'F' => Some(byte - b'A' + 10),
-        _ => None,
-    }
+    keys: &TokenCacheKeys,
+) -> Result<(CachedAccessToken, bool)> {
+    let unsealed = keys.open(raw)?;
+    let token =
+        serde_json::from_slice(&unsealed.plaintext).context("parse decrypted token payload")?;
+    Ok((token, unsealed.needs_reseal))
 }
 
 // --- Gmail API response types ---
@@ -1491,6 +2045,8 @@ struct GmailProfile {
     email_address: String,
     #[serde(rename = "historyId")]
     history_id: String,
+    #[serde(rename = "messagesTotal", default)]
+    messages_total: Option<u64>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -1503,12 +2059,43 @@ struct GmailMessageList {
     result_size_estimate: Option<u64>,
 }
 
+#[derive(Debug, Deserialize)]
+struct GmailLabelList {
+    labels: Option<Vec<GmailLabel>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GmailLabel {
+    id: String,
+    name: String,
+    #[serde(rename = "type")]
+    label_type: Option<String>,
+    color: Option<GmailLabelColor>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GmailLabelColor {
+    #[serde(rename = "textColor")]
+    text_color: Option<String>,
+    #[serde(rename = "backgroundColor")]
+    background_color: Option<String>,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 #[allow(dead_code)]
 struct GmailMessageStub {
     id: String,
     #[serde(rename = "threadId")]
     thread_id: String,
+    /// The message's full label set after the change, on label events.
+    #[serde(rename = "labelIds")]
+    label_ids: Option<Vec<String>>,
+}
+
+/// A message fetched with `format=raw`: the RFC 822 source, base64url.
+#[derive(Debug, Deserialize)]
+struct GmailRawMessage {
+    raw: String,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -1605,6 +2192,68 @@ impl EmailConnector for GmailApiConnector {
         "gmail_api"
     }
 
+    fn remote_endpoints(&self) -> Vec<String> {
+        vec![gmail_api_base()]
+    }
+
+    fn needs_full_sync(&self, db: &Database, account: &Account) -> Result<bool> {
+        Ok(self.load_history_id(db, account)?.is_none())
+    }
+
+    fn take_usage(&self) -> UsageCounts {
+        self.usage.take()
+    }
+
+    fn check_credentials(&self, account: &Account) -> Result<()> {
+        GmailCredentials::resolve(account).map(|_| ())
+    }
+
+    fn supports_flag_updates(&self) -> bool {
+        true
+    }
+
+    async fn update_flags(
+        &self,
+        db: &Database,
+        account: &Account,
+        email: &Email,
+        update: FlagUpdate,
+    ) -> Result<()> {
+        let token = self.get_access_token(db, account).await?;
+        self.modify_labels(&token, &email.id, &flag_label_changes(update))
+            .await
+            .map(|_| ())
+    }
+
+    fn supports_message_actions(&self) -> bool {
+        true
+    }
+
+    async fn apply_message_action(
+        &self,
+        db: &Database,
+        account: &Account,
+        email: &Email,
+        action: MessageAction,
+    ) -> Result<MovedMessage> {
+        let token = self.get_access_token(db, account).await?;
+        let labels = match action {
+            MessageAction::Archive => {
+                let changes = GmailLabelChanges {
+                    remove_label_ids: vec!["INBOX"],
+                    ..GmailLabelChanges::default()
+                };
+                self.modify_labels(&token, &email.id, &changes).await?
+            }
+            MessageAction::Delete => self.trash_message(&token, &email.id).await?,
+        };
+
+        Ok(MovedMessage {
+            folder: map_labels_to_folder(&labels.label_ids),
+            id: labels.id,
+        })
+    }
+
     async fn sync(
         &self,
         db: &Database,
@@ -1617,13 +2266,22 @@ impl EmailConnector for GmailApiConnector {
         db.insert_account(account)
             .context("upsert account before gmail sync")?;
 
+        // A failed listing keeps the labels stored by an earlier sync.
+        let labels_refreshed = self.refresh_labels(db, account).await;
+
         let saved_history_id = self.load_history_id(db, account)?;
 
-        if let Some(history_id) = saved_history_id {
-            self.sync_delta(db, indexer, account, &history_id).await
+        let mut report = if let Some(history_id) = saved_history_id {
+            self.sync_delta(db, indexer, account, &history_id).await?
         } else {
-            self.sync_full(db, indexer, account).await
+            self.sync_full(db, indexer, account).await?
+        };
+        if let Err(error) = labels_refreshed {
+            report
+                .errors
+                .push(ConnectorError::classify(&error).context("list labels"));
         }
+        Ok(report)
     }
 
     async fn import(
@@ -1639,17 +2297,21 @@ impl EmailConnector for GmailApiConnector {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     use chrono::Duration;
     use serde_json::json;
     use uuid::Uuid;
 
     use super::{
-        map_gmail_message_to_email, CachedAccessToken, GmailApiConnector, GmailCredentials,
-        GmailMessage, OAuthTokenResponse, TOKEN_CACHE_ENCRYPTION_KEY_ENV,
+        flag_label_changes, map_gmail_message_to_email, payload_attachments, CachedAccessToken,
+        GmailApiConnector, GmailCredentials, GmailHistoryRecord, GmailMessage, OAuthTokenResponse,
     };
-    use crate::connectors::TOKEN_ENV_LOCK;
+    use crate::connectors::{FlagUpdate, TOKEN_ENV_LOCK};
     use crate::db::models::{Account, AccountType};
-    use crate::db::Database;
+    use crate::db::{CalendarEvent, Database, ListHeaders};
+    use crate::env;
+    use crate::indexer::{EmailIndex, SearchFilters};
 
     const TEST_TOKEN_CACHE_KEY_HEX: &str =
         "00112233445566778899aabbccddeeff00112233445566778899aabbccddeeff";
@@ -1658,14 +2320,14 @@ mod tests {
 
     impl TokenCacheKeyGuard {
         fn set() -> Self {
-            std::env::set_var(TOKEN_CACHE_ENCRYPTION_KEY_ENV, TEST_TOKEN_CACHE_KEY_HEX);
+            std::env::set_var(env::TOKEN_CACHE_KEY, TEST_TOKEN_CACHE_KEY_HEX);
             Self
         }
     }
 
     impl Drop for TokenCacheKeyGuard {
         fn drop(&mut self) {
-            std::env::remove_var(TOKEN_CACHE_ENCRYPTION_KEY_ENV);
+            std::env::remove_var(env::TOKEN_CACHE_KEY);
         }
     }
 
@@ -1739,7 +2401,7 @@ mod tests {
     #[test]
     fn gmail_token_cache_not_persisted_without_encryption_key() {
         let _lock = TOKEN_ENV_LOCK.lock().expect("lock env mutation");
-        std::env::remove_var(TOKEN_CACHE_ENCRYPTION_KEY_ENV);
+        std::env::remove_var(env::TOKEN_CACHE_KEY);
 
         let connector = GmailApiConnector::new();
         let account = account();
@@ -1789,7 +2451,9 @@ mod tests {
                     { "name": "Cc", "value": "cc@example.com" },
                     { "name": "Message-ID", "value": "<msg-1@mail.gmail.com>" },
                     { "name": "Date", "value": "Wed, 01 Jan 2026 12:00:00 +0000" },
-                    { "name": "Importance", "value": "high" }
+                    { "name": "Importance", "value": "high" },
+                    { "name": "List-Id", "value": "Team <team.example.com>" },
+                    { "name": "In-Reply-To", "value": "<parent@mail.gmail.com>" }
                 ],
                 "body": { "size": 0 },
                 "parts": [
@@ -1808,6 +2472,14 @@ mod tests {
                             "size": 30,
                             "data": "PHA-SGVsbG8gPGI-dGVhbTwvYj4hPC9wPg"
                         }
+                    },
+                    {
+                        "mimeType": "text/calendar",
+                        "headers": [],
+                        "body": {
+                            "size": 171,
+                            "data": "QkVHSU46VkNBTEVOREFSDQpCRUdJTjpWRVZFTlQNClVJRDpyZXZpZXctMQ0KU1VNTUFSWTpRdWFydGVybHkgUmV2aWV3DQpEVFNUQVJUOjIwMjYwMTA1VDE1MDAwMFoNCkxPQ0FUSU9OOmh0dHBzOi8vbWVldC5nb29nbGUuY29tL2FiYy1kZWZnLWhpag0KRU5EOlZFVkVOVA0KRU5EOlZDQUxFTkRBUg0K"
+                        }
                     }
                 ]
             },
@@ -1844,6 +2516,94 @@ mod tests {
         assert_eq!(mapped.folder.as_deref(), Some("inbox"));
         assert_eq!(mapped.categories, vec!["Label_42"]);
         assert!(mapped.web_link.as_deref().unwrap().contains("18e1234abcd"));
+        assert_eq!(
+            ListHeaders::of(&mapped).and_then(|headers| headers.list_id),
+            Some("Team <team.example.com>".to_string())
+        );
+        assert_eq!(
+            mapped.in_reply_to.as_deref(),
+            Some("<parent@mail.gmail.com>")
+        );
+        let event = CalendarEvent::of(&mapped).expect("calendar event");
+        assert_eq!(event.starts_at, "2026-01-05T15:00:00Z");
+        assert_eq!(
+            event.join_url.as_deref(),
+            Some("https://meet.google.com/abc-defg-hij")
+        );
+    }
+
+    #[test]
+    fn history_label_events_update_stored_emails_in_place() {
+        let connector = GmailApiConnector::new();
+        let account = account();
+        let db_path = temp_db_path();
+        let index_path = std::env::temp_dir().join(format!("ess-gmail-index-{}", Uuid::new_v4()));
+        let db = Database::open(&db_path).expect("open db");
+        let mut index = EmailIndex::open(&index_path).expect("open index");
+        db.insert_account(&account).expect("insert account");
+
+        let message: GmailMessage = serde_json::from_value(json!({
+            "id": "msg-labels",
+            "threadId": "thread-labels",
+            "labelIds": ["INBOX", "UNREAD", "Label_42"],
+            "payload": {
+                "mimeType": "text/plain",
+                "headers": [{ "name": "Subject", "value": "Invoice" }],
+                "body": { "size": 5, "data": "SGVsbG8" }
+            },
+            "internalDate": "1735732800000"
+        }))
+        .expect("deserialize gmail message");
+        let email = map_gmail_message_to_email(&message, &account).expect("map message");
+        db.insert_email(&email).expect("insert email");
+        index.add_email(&email, "personal").expect("index email");
+
+        let record: GmailHistoryRecord = serde_json::from_value(json!({
+            "id": "900",
+            "labelsRemoved": [{
+                "message": {
+                    "id": "msg-labels",
+                    "threadId": "thread-labels",
+                    "labelIds": ["INBOX", "STARRED"]
+                },
+                "labelIds": ["UNREAD", "Label_42"]
+            }]
+        }))
+        .expect("deserialize history record");
+        let stub = &record.labels_removed.as_ref().expect("label events")[0].message;
+        assert!(connector
+            .apply_label_change(&db, &mut index, &account, &HashMap::new(), stub)
+            .expect("apply label change"));
+        index.commit().expect("commit");
+
+        let stored = db
+            .get_email("msg-labels")
+            .expect("get email")
+            .expect("email exists");
+        assert_eq!(stored.is_read, Some(true));
+        assert_eq!(stored.flag_status.as_deref(), Some("flagged"));
+        assert!(stored.categories.is_empty());
+        assert_eq!(stored.body_text.as_deref(), Some("Hello"));
+        let unread = index
+            .search(
+                "",
+                &SearchFilters {
+                    unread_only: true,
+                    ..SearchFilters::default()
+                },
+                10,
+            )
+            .expect("search unread");
+        assert!(unread.is_empty());
+
+        let mut unknown = stub.clone();
+        unknown.id = "msg-elsewhere".to_string();
+        assert!(!connector
+            .apply_label_change(&db, &mut index, &account, &HashMap::new(), &unknown)
+            .expect("unknown message"));
+
+        let _ = std::fs::remove_file(db_path);
+        let _ = std::fs::remove_dir_all(index_path);
     }
 
     #[test]
@@ -1917,6 +2677,16 @@ mod tests {
         let mapped = map_gmail_message_to_email(&message, &account).expect("map gmail message");
 
         assert_eq!(mapped.has_attachments, Some(true));
+
+        let attachments = payload_attachments(&mapped.id, &message.payload);
+        assert_eq!(attachments.len(), 1);
+        assert_eq!(attachments[0].id, "msg-attach:att-1");
+        assert_eq!(attachments[0].name.as_deref(), Some("report.pdf"));
+        assert_eq!(
+            attachments[0].content_type.as_deref(),
+            Some("application/pdf")
+        );
+        assert_eq!(attachments[0].size_bytes, Some(50000));
     }
 
     #[test]
@@ -1971,6 +2741,27 @@ mod tests {
         assert!(empty.is_empty());
     }
 
+    #[test]
+    fn gmail_address_headers_with_stray_angle_brackets() {
+        use super::{parse_address_list, parse_from_header};
+
+        assert_eq!(
+            parse_from_header(Some("><")),
+            (Some("><".to_string()), None)
+        );
+        assert_eq!(
+            parse_from_header(Some("a> Bob <bob@example.com>")),
+            (
+                Some("a> Bob".to_string()),
+                Some("bob@example.com".to_string())
+            )
+        );
+        assert_eq!(
+            parse_address_list(Some("x>y@example.com<, carol@example.com")),
+            vec!["x>y@example.com<", "carol@example.com"]
+        );
+    }
+
     #[test]
     fn gmail_html_entity_decode() {
         use super::html_entity_decode;
@@ -1987,6 +2778,134 @@ mod tests {
         assert_eq!(key, "gmail_history_id:acc-gmail");
     }
 
+    #[test]
+    fn flag_update_maps_to_label_changes() {
+        let changes = flag_label_changes(FlagUpdate {
+            read: Some(true),
+            flagged: Some(true),
+        });
+        assert_eq!(
+            serde_json::to_value(&changes).expect("serialize"),
+            serde_json::json!({ "addLabelIds": ["STARRED"], "removeLabelIds": ["UNREAD"] })
+        );
+    }
+
+    #[test]
+    fn trash_response_maps_to_trash_folder() {
+        use super::{map_labels_to_folder, GmailMessageLabels};
+        let labels: GmailMessageLabels =
+            serde_json::from_str(r#"{"id":"m1","threadId":"t1","labelIds":["TRASH","UNREAD"]}"#)
+                .expect("decode trash response");
+        assert_eq!(labels.id, "m1");
+        assert_eq!(map_labels_to_folder(&labels.label_ids), "trash");
+    }
+
+    #[test]
+    fn batch_sub_request_errors_are_classified_by_status() {
+        use super::parse_batch_response;
+        use crate::connectors::{ConnectorError, SyncReport};
+
+        let body = "--b\r\nContent-Type: application/http\r\n\r\nHTTP/1.1 404 Not Found\r\n\r\n{\"error\":{\"code\":404}}\r\n\
+                    --b\r\nContent-Type: application/http\r\n\r\nHTTP/1.1 429 Too Many Requests\r\n\r\n{\"error\":{\"code\":429}}\r\n\
+                    --b--";
+        let ids = vec!["gone".to_string(), "throttled".to_string()];
+        let mut report = SyncReport::default();
+        let result = parse_batch_response(body, "multipart/mixed; boundary=b", &ids, &mut report);
+
+        assert!(result.messages.is_empty());
+        assert_eq!(result.retryable_ids, vec!["throttled".to_string()]);
+        assert!(result.throttled);
+        assert_eq!(report.errors.len(), 1);
+        assert!(matches!(report.errors[0], ConnectorError::NotFound { .. }));
+    }
+
+    #[test]
+    fn gmail_labels_map_to_named_nested_rows() {
+        use super::{map_gmail_labels, resolve_label_names, GmailLabelList};
+
+        let list: GmailLabelList = serde_json::from_value(json!({
+            "labels": [
+                {"id": "INBOX", "name": "INBOX", "type": "system"},
+                {"id": "Label_7", "name": "Work", "type": "user"},
+                {
+                    "id": "Label_42",
+                    "name": "Work/Clients",
+                    "type": "user",
+                    "color": {"textColor": "#ffffff", "backgroundColor": "#16a765"}
+                },
+                {"id": "Label_9", "name": "Travel/2026", "type": "user"}
+            ]
+        }))
+        .expect("decode label list");
+        let labels = map_gmail_labels("acc", &list.labels.expect("labels"));
+
+        assert_eq!(labels[0].label_type, "system");
+        assert_eq!(labels[1].parent, None);
+        assert_eq!(labels[2].parent.as_deref(), Some("Work"));
+        assert_eq!(labels[2].background_color.as_deref(), Some("#16a765"));
+        assert_eq!(labels[2].text_color.as_deref(), Some("#ffffff"));
+        // No `Travel` label exists, so `Travel/2026` is top-level.
+        assert_eq!(labels[3].parent, None);
+
+        let names = labels
+            .iter()
+            .map(|label| (label.label_id.clone(), label.name.clone()))
+            .collect::<HashMap<_, _>>();
+        let mut email = map_gmail_message_to_email(
+            &serde_json::from_value::<GmailMessage>(json!({
+                "id": "m1",
+                "threadId": "t1",
+                "labelIds": ["INBOX", "Label_42", "Label_404"],
+                "payload": {"mimeType": "text/plain", "headers": []},
+                "internalDate": "1767225600000"
+            }))
+            .expect("decode message"),
+            &account(),
+        )
+        .expect("map message");
+        resolve_label_names(&mut email, &names);
+        assert_eq!(email.categories, vec!["Work/Clients", "Label_404"]);
+    }
+
+    #[test]
+    fn batch_size_grows_until_throttled_and_is_stored_per_account() {
+        use super::{BatchSizer, MAX_BATCH_SIZE, MIN_BATCH_SIZE};
+
+        let mut sizer = BatchSizer::new(25);
+        sizer.record(false);
+        assert_eq!(sizer.size(), 30);
+        sizer.record(true);
+        assert_eq!(sizer.size(), 15);
+        for _ in 0..5 {
+            sizer.record(true);
+        }
+        assert_eq!(sizer.size(), MIN_BATCH_SIZE);
+        assert_eq!(BatchSizer::new(1000).size(), MAX_BATCH_SIZE);
+
+        let db_path = temp_db_path();
+        let db = Database::open(&db_path).expect("open db");
+        let connector = GmailApiConnector::new();
+        let account = account();
+        assert_eq!(
+            connector
+                .load_batch_sizer(&db, &account)
+                .expect("load default")
+                .size(),
+            25
+        );
+        connector
+            .store_batch_sizer(&db, &account, BatchSizer::new(60))
+            .expect("store size");
+        assert_eq!(
+            connector
+                .load_batch_sizer(&db, &account)
+                .expect("load stored")
+                .size(),
+            60
+        );
+        let _ = std::fs::remove_file(db_path);
+    }
+
     #[test]
     fn gmail_base64url_decode() {
         use super::decode_body_data;
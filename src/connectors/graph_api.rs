@@ -1,35 +1,53 @@
 use std::collections::HashSet;
 use std::path::Path;
-use std::time::Duration as StdDuration;
+use std::time::{Duration as StdDuration, Instant};
 
 use anyhow::{anyhow, bail, Context, Result};
 use async_trait::async_trait;
-use chrono::{DateTime, Duration, Utc};
-use reqwest::{Client, StatusCode, Url};
-use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM};
-use ring::rand::{SecureRandom, SystemRandom};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use reqwest::{Client, RequestBuilder, StatusCode, Url};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use tokio::time::sleep;
 use tracing::warn;
 
-use crate::connectors::{EmailConnector, ImportReport, SyncReport};
-use crate::db::models::{Account, Email};
-use crate::db::Database;
+use crate::connectors::progress::{ProgressSink, StageProgress, SyncProgress};
+use crate::connectors::quarantine::{quarantine_unparsed, UnparsedMessage};
+use crate::connectors::shutdown::ShutdownSignal;
+use crate::connectors::usage::UsageMeter;
+use crate::connectors::{
+    raw_fetches_due, ConnectorError, EmailConnector, FlagUpdate, FolderSyncReport, ImportReport,
+    MessageAction, MovedMessage, SyncReport,
+};
+use crate::db::mailing_lists::LIST_HEADERS_KEY;
+use crate::db::models::{Account, Attachment, Email};
+use crate::db::quarantine::QuarantinedMessage;
+use crate::db::thread_links::ThreadHeaders;
+use crate::db::{Database, FolderDiscovery, FolderSyncRun, Label, ListHeaders, UsageCounts};
+use crate::env;
 use crate::indexer::EmailIndex;
+use crate::raw::RawStore;
+use crate::secrets::{self, TokenCacheKeys, TokenRefreshPolicy};
 
 const GRAPH_SCOPE: &str = "https://graph.microsoft.com/.default";
 const GRAPH_API_BASE: &str = "https://graph.microsoft.com/v1.0";
-const CACHE_SKEW_SECONDS: i64 = 60;
 const DEFAULT_DELTA_PAGE_SIZE: usize = 200;
 const FULL_SYNC_PAGE_SIZE: usize = 250;
 const MAX_RATE_LIMIT_RETRIES: usize = 5;
-const TOKEN_CACHE_ENCRYPTION_KEY_ENV: &str = "ESS_TOKEN_CACHE_KEY";
-const TOKEN_CACHE_KEY_BYTES: usize = 32;
-const TOKEN_CACHE_NONCE_BYTES: usize = 12;
-const TOKEN_CACHE_ENVELOPE_VERSION: u8 = 1;
 
 const REDACTED_BODY_MAX_LEN: usize = 200;
 
+/// Failed syncs in a row after which a folder's delta link is taken for
+/// stale and the folder enumerated again.
+const STALE_DELTA_FAILURES: i64 = 3;
+/// Messages Graph counts in a folder beyond those stored that make an
+/// empty delta round suspicious; at least this many, or 1% of the folder.
+const STALE_DELTA_MIN_GAP: i64 = 10;
+/// How often a folder's count discrepancy may reset its delta link, so a
+/// gap a full enumeration cannot close (duplicates, quarantined messages)
+/// does not enumerate the folder on every sync.
+const STALE_DELTA_REPAIR_INTERVAL_HOURS: i64 = 24;
+
 /// A folder discovered at runtime via the Graph API mailFolders endpoint.
 #[derive(Debug, Clone)]
 struct DiscoveredFolder {
@@ -39,6 +57,8 @@ struct DiscoveredFolder {
     display_name: String,
     /// Normalised label stored in ESS `emails.folder` column.
     ess_label: String,
+    /// Graph's `totalItemCount` at discovery.
+    total_items: Option<i64>,
 }
 
 /// Normalise a Graph API folder display name into an ESS folder label.
@@ -96,18 +116,45 @@ struct GraphMailFolder {
     #[serde(rename = "displayName")]
     display_name: String,
     #[serde(rename = "parentFolderId")]
-    #[allow(dead_code)]
     parent_folder_id: Option<String>,
     #[serde(rename = "childFolderCount")]
     child_folder_count: Option<i32>,
     #[serde(rename = "totalItemCount")]
-    #[allow(dead_code)]
     total_item_count: Option<i32>,
     #[serde(rename = "isHidden")]
-    #[allow(dead_code)]
     is_hidden: Option<bool>,
 }
 
+impl GraphMailFolder {
+    /// What `ess folders --discovered` shows for the folder. An excluded
+    /// folder has a `reason` and no label.
+    fn discovery(
+        &self,
+        account: &Account,
+        path: String,
+        label: Option<&str>,
+        reason: Option<&str>,
+        discovered_at: &str,
+    ) -> FolderDiscovery {
+        FolderDiscovery {
+            account_id: account.account_id.clone(),
+            folder_id: self.id.clone(),
+            parent_folder_id: self.parent_folder_id.clone(),
+            path,
+            label: label.map(str::to_string),
+            included: reason.is_none(),
+            reason: reason.map(str::to_string),
+            total_items: self.total_item_count.map(i64::from),
+            hidden: self.is_hidden.unwrap_or(false),
+            discovered_at: discovered_at.to_string(),
+            stored_emails: 0,
+        }
+    }
+}
+
+const SYSTEM_FOLDER_REASON: &str = "system folder without user mail; subfolders not listed";
+const SEARCH_FOLDER_REASON: &str = "search folders repeat mail stored in other folders";
+
 #[derive(Debug, Clone, Deserialize)]
 struct GraphMailFolderPage {
     value: Vec<GraphMailFolder>,
@@ -115,15 +162,65 @@ struct GraphMailFolderPage {
     next_link: Option<String>,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+struct GraphMasterCategoryList {
+    value: Vec<GraphMasterCategory>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct GraphMasterCategory {
+    id: String,
+    #[serde(rename = "displayName")]
+    display_name: String,
+    color: Option<String>,
+}
+
+/// Outlook's names for the `preset0`..`preset24` category colors.
+const CATEGORY_COLORS: [&str; 25] = [
+    "Red",
+    "Orange",
+    "Brown",
+    "Yellow",
+    "Green",
+    "Teal",
+    "Olive",
+    "Blue",
+    "Purple",
+    "Cranberry",
+    "Steel",
+    "DarkSteel",
+    "Gray",
+    "DarkGray",
+    "Black",
+    "DarkRed",
+    "DarkOrange",
+    "DarkBrown",
+    "DarkYellow",
+    "DarkGreen",
+    "DarkTeal",
+    "DarkOlive",
+    "DarkBlue",
+    "DarkPurple",
+    "DarkCranberry",
+];
+
 const MESSAGE_SELECT_FIELDS: &str = concat!(
     "id,subject,from,toRecipients,ccRecipients,bccRecipients,receivedDateTime,sentDateTime,",
     "body,bodyPreview,importance,isRead,hasAttachments,conversationId,internetMessageId,",
-    "categories,flag,webLink"
+    "categories,flag,webLink,internetMessageHeaders"
 );
 
+/// Attachment metadata for the full listing; delta queries cannot `$expand`.
+const MESSAGE_EXPAND_ATTACHMENTS: &str = "attachments($select=id,name,contentType,size,isInline)";
+
 #[derive(Debug, Clone)]
 pub struct GraphApiConnector {
     client: Client,
+    progress: ProgressSink,
+    shutdown: ShutdownSignal,
+    raw: Option<RawStore>,
+    defer_raw: bool,
+    usage: UsageMeter,
 }
 
 impl Default for GraphApiConnector {
@@ -136,9 +233,63 @@ impl GraphApiConnector {
     pub fn new() -> Self {
         Self {
             client: Client::new(),
+            progress: ProgressSink::default(),
+            shutdown: ShutdownSignal::default(),
+            raw: None,
+            defer_raw: false,
+            usage: UsageMeter::default(),
         }
     }
 
+    /// Stop at the next page boundary once `shutdown` is requested.
+    pub fn with_shutdown(mut self, shutdown: ShutdownSignal) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Route sync progress events to `progress` instead of stderr.
+    pub fn with_progress(mut self, progress: ProgressSink) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Also fetch each new message's MIME source from `$value` and keep it
+    /// in `raw`.
+    pub fn with_raw_store(mut self, raw: RawStore) -> Self {
+        self.raw = Some(raw);
+        self
+    }
+
+    /// Put off fetching raw sources, e.g. on a metered connection; see
+    /// [`raw_fetches_due`].
+    pub fn with_raw_fetches_deferred(mut self, defer: bool) -> Self {
+        self.defer_raw = defer;
+        self
+    }
+
+    fn folder_stage(
+        &self,
+        account: &Account,
+        stage: &str,
+        folder: &DiscoveredFolder,
+    ) -> StageProgress {
+        StageProgress::start(
+            &self.progress,
+            &account.account_id,
+            stage,
+            Some(&folder.ess_label),
+            None,
+        )
+    }
+
+    fn notice(&self, account: &Account, folder: Option<&DiscoveredFolder>, message: String) {
+        self.progress.emit(SyncProgress::Notice {
+            account_id: account.account_id.clone(),
+            folder: folder.map(|folder| folder.ess_label.clone()),
+            message,
+        });
+    }
+
     async fn get_access_token(&self, db: &Database, account: &Account) -> Result<String> {
         if let Some(cached) = self.cached_token(db, account)? {
             return Ok(cached.access_token);
@@ -158,6 +309,10 @@ impl GraphApiConnector {
         format!("graph_delta_link:{}:{}", account.account_id, folder_id)
     }
 
+    fn delta_repair_key(account: &Account, folder_id: &str) -> String {
+        format!("graph_delta_repair:{}:{}", account.account_id, folder_id)
+    }
+
     /// Legacy key format using well-known graph_name (pre-dynamic-discovery).
     fn legacy_wellknown_delta_link_key(account: &Account, graph_name: &str) -> String {
         format!("graph_delta_link:{}:{}", account.account_id, graph_name)
@@ -179,14 +334,19 @@ impl GraphApiConnector {
             return Ok(None);
         };
 
-        let Some(encryption_key) = Self::token_cache_encryption_key()? else {
+        let Some(keys) = TokenCacheKeys::from_env()? else {
             // Security default: if encryption is not configured, do not keep token data at rest.
             Self::clear_sync_state(db, &cache_key)?;
             return Ok(None);
         };
 
-        let cached = match decrypt_cached_access_token(&raw, &encryption_key) {
-            Ok(token) => token,
+        let cached = match decrypt_cached_access_token(&raw, &keys) {
+            Ok((token, needs_reseal)) => {
+                if needs_reseal {
+                    self.store_token(db, account, &token)?;
+                }
+                token
+            }
             Err(decrypt_error) => {
                 if let Ok(legacy_token) = serde_json::from_str::<CachedAccessToken>(&raw) {
                     // One-time migration for pre-encryption plaintext cache entries.
@@ -203,7 +363,7 @@ impl GraphApiConnector {
             }
         };
 
-        if cached.is_expired() {
+        if TokenRefreshPolicy::from_env()?.needs_refresh(cached.expires_at, Utc::now()) {
             Self::clear_sync_state(db, &cache_key)?;
             return Ok(None);
         }
@@ -217,30 +377,17 @@ impl GraphApiConnector {
         account: &Account,
         token: &CachedAccessToken,
     ) -> Result<()> {
-        let Some(encryption_key) = Self::token_cache_encryption_key()? else {
+        let Some(keys) = TokenCacheKeys::from_env()? else {
             return Ok(());
         };
 
         let key = Self::token_cache_key(account);
-        let value = encrypt_cached_access_token(token, &encryption_key)
-            .context("encrypt cached graph token")?;
+        let value =
+            encrypt_cached_access_token(token, &keys).context("encrypt cached graph token")?;
         db.set_sync_state(&key, &value)
             .context("write graph token to sync_state")
     }
 
-    fn token_cache_encryption_key() -> Result<Option<[u8; TOKEN_CACHE_KEY_BYTES]>> {
-        let raw = std::env::var(TOKEN_CACHE_ENCRYPTION_KEY_ENV)
-            .ok()
-            .map(|value| value.trim().to_string())
-            .filter(|value| !value.is_empty());
-
-        raw.map(|value| parse_token_cache_key_hex(&value))
-            .transpose()
-            .with_context(|| {
-                format!("{TOKEN_CACHE_ENCRYPTION_KEY_ENV} must be 64 hex characters (32 bytes)")
-            })
-    }
-
     fn clear_sync_state(db: &Database, key: &str) -> Result<()> {
         db.conn()
             .execute("DELETE FROM sync_state WHERE key = ?", [key])
@@ -316,11 +463,84 @@ impl GraphApiConnector {
             .context("persist graph delta link")
     }
 
+    /// Why the folder's delta link should be given up before this sync:
+    /// the last [`STALE_DELTA_FAILURES`] syncs of the folder all failed.
+    fn failing_delta_link(
+        db: &Database,
+        account: &Account,
+        folder: &DiscoveredFolder,
+    ) -> Result<Option<String>> {
+        let key = Self::delta_link_key(account, &folder.folder_id);
+        if db
+            .get_sync_state(&key)?
+            .and_then(|state| state.value)
+            .is_none()
+        {
+            return Ok(None);
+        }
+        let failures = db
+            .folder_sync_state(&account.account_id, &folder.folder_id)?
+            .map_or(0, |state| state.consecutive_failures);
+        Ok((failures >= STALE_DELTA_FAILURES)
+            .then(|| format!("the last {failures} syncs of the folder failed")))
+    }
+
+    /// Why the folder's delta link should be given up after a delta round
+    /// that changed nothing: Graph counts clearly more messages in the
+    /// folder than are stored for it, and the link was not reset for that
+    /// within [`STALE_DELTA_REPAIR_INTERVAL_HOURS`].
+    fn lagging_delta_link(
+        db: &Database,
+        account: &Account,
+        folder: &DiscoveredFolder,
+    ) -> Result<Option<String>> {
+        let Some(total) = folder.total_items else {
+            return Ok(None);
+        };
+        let stored = db.count_folder_emails(&account.account_id, &folder.ess_label)?;
+        if !is_suspicious_count_gap(total, stored) {
+            return Ok(None);
+        }
+        let last_repair = db
+            .get_sync_state(&Self::delta_repair_key(account, &folder.folder_id))?
+            .and_then(|state| state.updated_at)
+            .and_then(|at| DateTime::parse_from_rfc3339(&at).ok());
+        if last_repair.is_some_and(|at| {
+            Utc::now().signed_duration_since(at)
+                < Duration::hours(STALE_DELTA_REPAIR_INTERVAL_HOURS)
+        }) {
+            return Ok(None);
+        }
+        Ok(Some(format!(
+            "delta round was empty but Graph counts {total} messages and {stored} are stored"
+        )))
+    }
+
+    /// Drops a stale delta link, so the folder is enumerated in full
+    /// instead of staying behind for good, and logs why.
+    fn reset_stale_delta_link(
+        &self,
+        db: &Database,
+        account: &Account,
+        folder: &DiscoveredFolder,
+        reason: &str,
+    ) -> Result<()> {
+        warn!(
+            "graph delta link of account {} folder {} looks stale ({reason}); scheduling a full enumeration",
+            account.account_id, folder.ess_label
+        );
+        self.notice(
+            account,
+            Some(folder),
+            format!("delta link looks stale ({reason}); scheduling a full enumeration"),
+        );
+        Self::clear_sync_state(db, &Self::delta_link_key(account, &folder.folder_id))?;
+        db.set_sync_state(&Self::delta_repair_key(account, &folder.folder_id), reason)
+            .context("record graph delta link repair")
+    }
+
     fn initial_delta_url(&self, account: &Account, folder: &DiscoveredFolder) -> Result<String> {
-        let base = std::env::var("ESS_GRAPH_API_BASE")
-            .ok()
-            .filter(|value| !value.trim().is_empty())
-            .unwrap_or_else(|| GRAPH_API_BASE.to_string());
+        let base = graph_api_base();
 
         let endpoint = format!(
             "{base}/users/{}/mailFolders/{}/messages/delta",
@@ -335,15 +555,12 @@ impl GraphApiConnector {
     }
 
     async fn fetch_token(&self, credentials: &GraphCredentials) -> Result<CachedAccessToken> {
-        let token_url = std::env::var("ESS_GRAPH_TOKEN_URL")
-            .ok()
-            .filter(|value| !value.trim().is_empty())
-            .unwrap_or_else(|| {
-                format!(
-                    "https://login.microsoftonline.com/{}/oauth2/v2.0/token",
-                    credentials.tenant_id
-                )
-            });
+        let token_url = env::var(env::GRAPH_TOKEN_URL).unwrap_or_else(|| {
+            format!(
+                "https://login.microsoftonline.com/{}/oauth2/v2.0/token",
+                credentials.tenant_id
+            )
+        });
 
         let response = self
             .client
@@ -357,21 +574,28 @@ impl GraphApiConnector {
             .send()
             .await
             .with_context(|| format!("request graph oauth token from {token_url}"))?;
+        self.usage.request();
 
         let status = response.status();
         let body = response.text().await.context("read graph token response")?;
+        self.usage.received(body.len());
         if !status.is_success() {
-            return Err(anyhow!(
-                "graph oauth token request failed: status={} body={}",
+            return Err(ConnectorError::from_status(
                 status,
-                redact_response_body(&body)
-            ));
+                format!(
+                    "graph oauth token request failed: status={} body={}",
+                    status,
+                    redact_response_body(&body)
+                ),
+            )
+            .into());
         }
 
         let payload: OAuthTokenResponse =
             serde_json::from_str(&body).context("decode graph token JSON response")?;
-        let expires_at = Utc::now()
-            + Duration::seconds((payload.expires_in as i64).saturating_sub(CACHE_SKEW_SECONDS));
+        // Skew and the refresh window are applied when the cached token is
+        // read, so changing them takes effect without discarding the cache.
+        let expires_at = Utc::now() + Duration::seconds(payload.expires_in as i64);
 
         Ok(CachedAccessToken {
             access_token: payload.access_token,
@@ -391,6 +615,7 @@ impl GraphApiConnector {
                 .send()
                 .await
                 .context("request graph delta page")?;
+            self.usage.request();
 
             if response.status() == StatusCode::TOO_MANY_REQUESTS {
                 if attempt == MAX_RATE_LIMIT_RETRIES {
@@ -398,10 +623,12 @@ impl GraphApiConnector {
                         .text()
                         .await
                         .context("read graph 429 response body")?;
-                    return Err(anyhow!(
+                    self.usage.received(body.len());
+                    return Err(ConnectorError::rate_limited(format!(
                         "graph delta request exhausted retries: {}",
                         redact_response_body(&body)
-                    ));
+                    ))
+                    .into());
                 }
 
                 let retry_after_seconds = response
@@ -421,12 +648,17 @@ impl GraphApiConnector {
                 .text()
                 .await
                 .context("read graph delta response body")?;
+            self.usage.received(body.len());
             if !status.is_success() {
-                return Err(anyhow!(
-                    "graph delta request failed: status={} body={}",
+                return Err(ConnectorError::from_status(
                     status,
-                    redact_response_body(&body)
-                ));
+                    format!(
+                        "graph delta request failed: status={} body={}",
+                        status,
+                        redact_response_body(&body)
+                    ),
+                )
+                .into());
             }
 
             let page: GraphDeltaPage =
@@ -434,7 +666,7 @@ impl GraphApiConnector {
             return Ok(page);
         }
 
-        Err(anyhow!("graph delta request failed without response"))
+        Err(ConnectorError::transient("graph delta request failed without response").into())
     }
 
     async fn fetch_folder_page_with_retry(
@@ -453,6 +685,7 @@ impl GraphApiConnector {
                 .send()
                 .await
                 .context("request graph mailFolders page")?;
+            self.usage.request();
 
             if response.status() == StatusCode::TOO_MANY_REQUESTS {
                 if attempt == MAX_RATE_LIMIT_RETRIES {
@@ -460,10 +693,12 @@ impl GraphApiConnector {
                         .text()
                         .await
                         .context("read graph 429 response body")?;
-                    return Err(anyhow!(
+                    self.usage.received(body.len());
+                    return Err(ConnectorError::rate_limited(format!(
                         "graph mailFolders request exhausted retries: {}",
                         redact_response_body(&body)
-                    ));
+                    ))
+                    .into());
                 }
 
                 let retry_after_seconds = response
@@ -483,12 +718,17 @@ impl GraphApiConnector {
                 .text()
                 .await
                 .context("read graph mailFolders response body")?;
+            self.usage.received(body.len());
             if !status.is_success() {
-                return Err(anyhow!(
-                    "graph mailFolders request failed: status={} body={}",
+                return Err(ConnectorError::from_status(
                     status,
-                    redact_response_body(&body)
-                ));
+                    format!(
+                        "graph mailFolders request failed: status={} body={}",
+                        status,
+                        redact_response_body(&body)
+                    ),
+                )
+                .into());
             }
 
             let page: GraphMailFolderPage =
@@ -496,7 +736,209 @@ impl GraphApiConnector {
             return Ok(page);
         }
 
-        Err(anyhow!("graph mailFolders request failed without response"))
+        Err(ConnectorError::transient("graph mailFolders request failed without response").into())
+    }
+
+    async fn fetch_master_categories_with_retry(
+        &self,
+        token: &str,
+        url: &str,
+    ) -> Result<Vec<GraphMasterCategory>> {
+        let mut backoff_seconds = 1u64;
+
+        for attempt in 0..=MAX_RATE_LIMIT_RETRIES {
+            let response = self
+                .client
+                .get(url)
+                .bearer_auth(token)
+                .header("accept", "application/json")
+                .send()
+                .await
+                .context("request graph masterCategories")?;
+            self.usage.request();
+
+            if response.status() == StatusCode::TOO_MANY_REQUESTS {
+                if attempt == MAX_RATE_LIMIT_RETRIES {
+                    let body = response
+                        .text()
+                        .await
+                        .context("read graph 429 response body")?;
+                    self.usage.received(body.len());
+                    return Err(ConnectorError::rate_limited(format!(
+                        "graph masterCategories request exhausted retries: {}",
+                        redact_response_body(&body)
+                    ))
+                    .into());
+                }
+
+                let retry_after_seconds = response
+                    .headers()
+                    .get("retry-after")
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .unwrap_or(backoff_seconds);
+
+                sleep(StdDuration::from_secs(retry_after_seconds)).await;
+                backoff_seconds = (backoff_seconds * 2).min(32);
+                continue;
+            }
+
+            let status = response.status();
+            let body = response
+                .text()
+                .await
+                .context("read graph masterCategories response body")?;
+            self.usage.received(body.len());
+            if !status.is_success() {
+                return Err(ConnectorError::from_status(
+                    status,
+                    format!(
+                        "graph masterCategories request failed: status={} body={}",
+                        status,
+                        redact_response_body(&body)
+                    ),
+                )
+                .into());
+            }
+
+            let list: GraphMasterCategoryList =
+                serde_json::from_str(&body).context("decode graph masterCategories JSON")?;
+            return Ok(list.value);
+        }
+
+        Err(
+            ConnectorError::transient("graph masterCategories request failed without response")
+                .into(),
+        )
+    }
+
+    /// The message's MIME source, as `GET /messages/{id}/$value` returns it.
+    async fn fetch_mime_with_retry(&self, token: &str, url: &str) -> Result<Vec<u8>> {
+        let mut backoff_seconds = 1u64;
+
+        for attempt in 0..=MAX_RATE_LIMIT_RETRIES {
+            let response = self
+                .client
+                .get(url)
+                .bearer_auth(token)
+                .send()
+                .await
+                .context("request graph message MIME content")?;
+            self.usage.request();
+
+            if response.status() == StatusCode::TOO_MANY_REQUESTS {
+                if attempt == MAX_RATE_LIMIT_RETRIES {
+                    let body = response
+                        .text()
+                        .await
+                        .context("read graph 429 response body")?;
+                    self.usage.received(body.len());
+                    return Err(ConnectorError::rate_limited(format!(
+                        "graph MIME content request exhausted retries: {}",
+                        redact_response_body(&body)
+                    ))
+                    .into());
+                }
+
+                let retry_after_seconds = response
+                    .headers()
+                    .get("retry-after")
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .unwrap_or(backoff_seconds);
+
+                sleep(StdDuration::from_secs(retry_after_seconds)).await;
+                backoff_seconds = (backoff_seconds * 2).min(32);
+                continue;
+            }
+
+            let status = response.status();
+            if !status.is_success() {
+                let body = response
+                    .text()
+                    .await
+                    .context("read graph MIME content error body")?;
+                self.usage.received(body.len());
+                return Err(ConnectorError::from_status(
+                    status,
+                    format!(
+                        "graph MIME content request failed: status={} body={}",
+                        status,
+                        redact_response_body(&body)
+                    ),
+                )
+                .into());
+            }
+            let body = response.bytes().await.context("read graph MIME content")?;
+            self.usage.received(body.len());
+            return Ok(body.to_vec());
+        }
+
+        Err(ConnectorError::transient("graph MIME content request failed without response").into())
+    }
+
+    /// Stores the MIME source of the given messages when raw storage is
+    /// on. Messages already stored are skipped; failures are reported but
+    /// do not stop the sync.
+    async fn store_raw_messages(
+        &self,
+        db: &Database,
+        account: &Account,
+        message_ids: &[String],
+        report: &mut SyncReport,
+    ) {
+        let Some(raw) = &self.raw else {
+            return;
+        };
+        let message_ids = match raw_fetches_due(db, account, message_ids, self.defer_raw) {
+            Ok(message_ids) => message_ids,
+            Err(error) => {
+                report
+                    .errors
+                    .push(ConnectorError::classify(&error).context("deferred raw ids"));
+                return;
+            }
+        };
+        for message_id in &message_ids {
+            if raw.contains(message_id) {
+                continue;
+            }
+            let url = format!("{}/$value", Self::message_url(account, message_id));
+            let stored = match self.get_access_token(db, account).await {
+                Ok(token) => match self.fetch_mime_with_retry(&token, &url).await {
+                    Ok(message) => raw
+                        .put(message_id, &message)
+                        .context("write raw message file"),
+                    Err(error) => Err(error),
+                },
+                Err(error) => Err(error),
+            };
+            if let Err(error) = stored {
+                report
+                    .errors
+                    .push(ConnectorError::classify(&error).context(format!("raw id={message_id}")));
+            }
+        }
+    }
+
+    /// Stores the mailbox's Outlook master categories, so `ess labels` can
+    /// list them with their colors. Reading them needs the
+    /// `MailboxSettings.Read` permission.
+    async fn refresh_master_categories(&self, db: &Database, account: &Account) -> Result<()> {
+        let token = self.get_access_token(db, account).await?;
+        let url = format!(
+            "{}/users/{}/outlook/masterCategories",
+            graph_api_base(),
+            account.email_address
+        );
+        let categories = self
+            .fetch_master_categories_with_retry(&token, &url)
+            .await?;
+        db.replace_labels(
+            &account.account_id,
+            &map_master_categories(&account.account_id, &categories),
+        )
+        .context("store graph master categories")
     }
 
     async fn discover_folders(
@@ -505,13 +947,12 @@ impl GraphApiConnector {
         account: &Account,
     ) -> Result<Vec<DiscoveredFolder>> {
         let token = self.get_access_token(db, account).await?;
-        let base = std::env::var("ESS_GRAPH_API_BASE")
-            .ok()
-            .filter(|value| !value.trim().is_empty())
-            .unwrap_or_else(|| GRAPH_API_BASE.to_string());
+        let base = graph_api_base();
 
         let mut folders = Vec::new();
         let mut pending_parents: Vec<(String, String)> = Vec::new(); // (folder_id, display_name)
+        let discovered_at = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+        let mut discoveries = Vec::new();
 
         // Fetch top-level folders
         let mut url = format!(
@@ -522,19 +963,42 @@ impl GraphApiConnector {
         loop {
             let page = self.fetch_folder_page_with_retry(&token, &url).await?;
             for folder in &page.value {
+                let path = folder.display_name.clone();
                 if is_excluded_folder(&folder.display_name) {
+                    discoveries.push(folder.discovery(
+                        account,
+                        path,
+                        None,
+                        Some(SYSTEM_FOLDER_REASON),
+                        &discovered_at,
+                    ));
                     continue;
                 }
                 // Skip search folders (virtual folders that would create duplicates)
                 if folder.display_name.eq_ignore_ascii_case("searchfolders") {
+                    discoveries.push(folder.discovery(
+                        account,
+                        path,
+                        None,
+                        Some(SEARCH_FOLDER_REASON),
+                        &discovered_at,
+                    ));
                     continue;
                 }
 
                 let ess_label = normalize_folder_label(&folder.display_name);
+                discoveries.push(folder.discovery(
+                    account,
+                    path,
+                    Some(&ess_label),
+                    None,
+                    &discovered_at,
+                ));
                 folders.push(DiscoveredFolder {
                     folder_id: folder.id.clone(),
                     display_name: folder.display_name.clone(),
                     ess_label,
+                    total_items: folder.total_item_count.map(i64::from),
                 });
 
                 if folder.child_folder_count.unwrap_or(0) > 0 {
@@ -560,7 +1024,15 @@ impl GraphApiConnector {
                     .fetch_folder_page_with_retry(&token, &child_url)
                     .await?;
                 for child in &page.value {
+                    let path = format!("{}/{}", parent_name, child.display_name);
                     if is_excluded_folder(&child.display_name) {
+                        discoveries.push(child.discovery(
+                            account,
+                            path,
+                            None,
+                            Some(SYSTEM_FOLDER_REASON),
+                            &discovered_at,
+                        ));
                         continue;
                     }
 
@@ -569,10 +1041,18 @@ impl GraphApiConnector {
                         normalize_folder_label(&parent_name),
                         child.display_name.trim().to_lowercase()
                     );
+                    discoveries.push(child.discovery(
+                        account,
+                        path,
+                        Some(&ess_label),
+                        None,
+                        &discovered_at,
+                    ));
                     folders.push(DiscoveredFolder {
                         folder_id: child.id.clone(),
                         display_name: format!("{}/{}", parent_name, child.display_name),
                         ess_label,
+                        total_items: child.total_item_count.map(i64::from),
                     });
 
                     if child.child_folder_count.unwrap_or(0) > 0 {
@@ -590,13 +1070,19 @@ impl GraphApiConnector {
             }
         }
 
-        eprintln!(
-            "graph: discovered {} folders for {}",
-            folders.len(),
-            account.account_id
+        db.replace_discovered_folders(&account.account_id, &discoveries)
+            .context("store discovered folders")?;
+        self.notice(
+            account,
+            None,
+            format!("discovered {} folders", folders.len()),
         );
         for f in &folders {
-            eprintln!("  {} → label={}", f.display_name, f.ess_label);
+            self.notice(
+                account,
+                None,
+                format!("  {} → label={}", f.display_name, f.ess_label),
+            );
         }
 
         Ok(folders)
@@ -618,6 +1104,7 @@ impl GraphApiConnector {
                 .send()
                 .await
                 .context("request graph messages page")?;
+            self.usage.request();
 
             if response.status() == StatusCode::TOO_MANY_REQUESTS {
                 if attempt == MAX_RATE_LIMIT_RETRIES {
@@ -625,10 +1112,12 @@ impl GraphApiConnector {
                         .text()
                         .await
                         .context("read graph 429 response body")?;
-                    return Err(anyhow!(
+                    self.usage.received(body.len());
+                    return Err(ConnectorError::rate_limited(format!(
                         "graph messages request exhausted retries: {}",
                         redact_response_body(&body)
-                    ));
+                    ))
+                    .into());
                 }
 
                 let retry_after_seconds = response
@@ -648,12 +1137,17 @@ impl GraphApiConnector {
                 .text()
                 .await
                 .context("read graph messages response body")?;
+            self.usage.received(body.len());
             if !status.is_success() {
-                return Err(anyhow!(
-                    "graph messages request failed: status={} body={}",
+                return Err(ConnectorError::from_status(
                     status,
-                    redact_response_body(&body)
-                ));
+                    format!(
+                        "graph messages request failed: status={} body={}",
+                        status,
+                        redact_response_body(&body)
+                    ),
+                )
+                .into());
             }
 
             let page: GraphMessagesPage =
@@ -661,7 +1155,77 @@ impl GraphApiConnector {
             return Ok(page);
         }
 
-        Err(anyhow!("graph messages request failed without response"))
+        Err(ConnectorError::transient("graph messages request failed without response").into())
+    }
+
+    /// Sends a mutating request, retrying on throttling. A 403 usually means
+    /// the app registration lacks the `Mail.ReadWrite` application permission.
+    async fn send_write_with_retry(&self, request: RequestBuilder, action: &str) -> Result<String> {
+        let mut backoff_seconds = 1u64;
+
+        for attempt in 0..=MAX_RATE_LIMIT_RETRIES {
+            let response = request
+                .try_clone()
+                .ok_or_else(|| anyhow!("graph {action} request cannot be retried"))?
+                .send()
+                .await
+                .with_context(|| format!("request graph {action}"))?;
+            self.usage.request();
+
+            if response.status() == StatusCode::TOO_MANY_REQUESTS
+                && attempt < MAX_RATE_LIMIT_RETRIES
+            {
+                let retry_after_seconds = response
+                    .headers()
+                    .get("retry-after")
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .unwrap_or(backoff_seconds);
+
+                sleep(StdDuration::from_secs(retry_after_seconds)).await;
+                backoff_seconds = (backoff_seconds * 2).min(32);
+                continue;
+            }
+
+            let status = response.status();
+            let body = response
+                .text()
+                .await
+                .with_context(|| format!("read graph {action} response body"))?;
+            self.usage.received(body.len());
+            if status == StatusCode::FORBIDDEN {
+                return Err(ConnectorError::from_status(
+                    status,
+                    format!(
+                        "graph {action} forbidden: grant the app registration Mail.ReadWrite (body={})",
+                        redact_response_body(&body)
+                    ),
+                )
+                .into());
+            }
+            if !status.is_success() {
+                return Err(ConnectorError::from_status(
+                    status,
+                    format!(
+                        "graph {action} failed: status={} body={}",
+                        status,
+                        redact_response_body(&body)
+                    ),
+                )
+                .into());
+            }
+            return Ok(body);
+        }
+
+        Err(ConnectorError::transient(format!("graph {action} failed without response")).into())
+    }
+
+    fn message_url(account: &Account, message_id: &str) -> String {
+        format!(
+            "{}/users/{}/messages/{message_id}",
+            graph_api_base(),
+            account.email_address
+        )
     }
 
     /// Full enumeration of all messages in a folder via the plain /messages
@@ -676,10 +1240,7 @@ impl GraphApiConnector {
     ) -> Result<SyncReport> {
         let mut report = SyncReport::default();
 
-        let base = std::env::var("ESS_GRAPH_API_BASE")
-            .ok()
-            .filter(|value| !value.trim().is_empty())
-            .unwrap_or_else(|| GRAPH_API_BASE.to_string());
+        let base = graph_api_base();
 
         let endpoint = format!(
             "{base}/users/{}/mailFolders/{}/messages",
@@ -690,45 +1251,48 @@ impl GraphApiConnector {
         url.query_pairs_mut()
             .append_pair("$top", &FULL_SYNC_PAGE_SIZE.to_string())
             .append_pair("$select", MESSAGE_SELECT_FIELDS)
+            .append_pair("$expand", MESSAGE_EXPAND_ATTACHMENTS)
             .append_pair("$orderby", "receivedDateTime desc");
         let mut next_url = url.to_string();
         let mut page_number = 0u64;
+        let mut stage = self.folder_stage(account, "full_enumerate", folder);
 
         let mut consecutive_errors = 0u32;
         const MAX_CONSECUTIVE_PAGE_ERRORS: u32 = 3;
 
         loop {
             let token = self.get_access_token(db, account).await?;
-            let page = match self
-                .fetch_messages_page_with_retry(&token, &next_url)
-                .await
-            {
+            let page = match self.fetch_messages_page_with_retry(&token, &next_url).await {
                 Ok(page) => {
                     consecutive_errors = 0;
                     page
                 }
                 Err(error) => {
                     consecutive_errors += 1;
-                    report.errors.push(format!(
-                        "folder={} page_fetch_error: {error}",
-                        folder.ess_label
-                    ));
-                    eprintln!(
-                        "graph full-sync {} folder={}: page fetch error ({}/{}): {error}",
-                        account.account_id,
-                        folder.ess_label,
-                        consecutive_errors,
-                        MAX_CONSECUTIVE_PAGE_ERRORS,
+                    let error = ConnectorError::classify(&error)
+                        .context(format!("folder={} page_fetch_error", folder.ess_label));
+                    let retryable = error.is_retryable();
+                    report.errors.push(error.clone());
+                    self.notice(
+                        account,
+                        Some(folder),
+                        format!(
+                            "page fetch error ({consecutive_errors}/{MAX_CONSECUTIVE_PAGE_ERRORS}): {error}"
+                        ),
                     );
                     if consecutive_errors >= MAX_CONSECUTIVE_PAGE_ERRORS {
-                        eprintln!(
-                            "graph full-sync {} folder={}: aborting after {} consecutive page errors",
-                            account.account_id,
-                            folder.ess_label,
-                            consecutive_errors,
+                        self.notice(
+                            account,
+                            Some(folder),
+                            format!("aborting after {consecutive_errors} consecutive page errors"),
                         );
                         break;
                     }
+                    if retryable {
+                        // Transient failures refetch the same page.
+                        sleep(StdDuration::from_secs(1 << consecutive_errors)).await;
+                        continue;
+                    }
                     // The nextLink URL is opaque — we can't skip a page. If
                     // we fail to parse the current page we have no nextLink
                     // to advance to, so we must stop.
@@ -737,18 +1301,30 @@ impl GraphApiConnector {
             };
 
             page_number += 1;
-            let page_size = page.value.len();
+            let page_size = page.value.messages.len() + page.value.unparsed.len();
+            quarantine_unparsed(
+                db,
+                account,
+                self.name(),
+                Some(&folder.ess_label),
+                &page.value.unparsed,
+                &mut report,
+            );
 
-            for message in &page.value {
+            let mut added = Vec::new();
+            for message in &page.value.messages {
                 match self.apply_message_buffered(db, indexer, account, folder, message) {
-                    Ok(ApplyResult::Added) => report.emails_added += 1,
+                    Ok(ApplyResult::Added) => {
+                        report.emails_added += 1;
+                        added.extend(message.id.clone());
+                    }
                     Ok(ApplyResult::Updated | ApplyResult::Deleted) => report.emails_updated += 1,
                     Err(error) => {
                         let message_id = message.id.as_deref().unwrap_or("<missing-id>");
-                        report.errors.push(format!(
-                            "folder={} id={message_id}: {error}",
-                            folder.ess_label
-                        ));
+                        report.errors.push(
+                            ConnectorError::classify(&error)
+                                .context(format!("folder={} id={message_id}", folder.ess_label)),
+                        );
                     }
                 }
             }
@@ -756,18 +1332,23 @@ impl GraphApiConnector {
             indexer
                 .commit()
                 .with_context(|| format!("commit index after page {page_number}"))?;
+            self.store_raw_messages(db, account, &added, &mut report)
+                .await;
 
-            eprintln!(
-                "graph full-sync {} folder={} ({}): page {} ({} messages), added={} updated={} errors={}",
-                account.account_id,
-                folder.ess_label,
-                folder.display_name,
-                page_number,
-                page_size,
-                report.emails_added,
-                report.emails_updated,
-                report.errors.len(),
-            );
+            stage.page(page_size, &report);
+
+            if self.shutdown.is_requested() && page.next_link.is_some() {
+                // No delta baseline exists yet, so the next run simply
+                // re-enumerates this folder; stored messages are upserted.
+                self.notice(
+                    account,
+                    Some(folder),
+                    format!("interrupted after page {page_number}; folder will be re-enumerated"),
+                );
+                report.interrupted = true;
+                stage.finish(&report);
+                return Ok(report);
+            }
 
             match page.next_link {
                 Some(url) => next_url = url,
@@ -778,9 +1359,10 @@ impl GraphApiConnector {
         // After full enumeration, obtain a delta baseline token for future
         // incremental syncs. This re-enumerates messages (treated as upserts)
         // but the goal is to capture the deltaLink.
-        eprintln!(
-            "graph full-sync {} folder={}: obtaining delta baseline",
-            account.account_id, folder.ess_label
+        self.notice(
+            account,
+            Some(folder),
+            "obtaining delta baseline".to_string(),
         );
         let delta_url = self.initial_delta_url(account, folder)?;
         let mut next_delta_url = delta_url;
@@ -793,10 +1375,12 @@ impl GraphApiConnector {
                 .await?;
 
             // Process messages as upserts (mostly no-ops since we just enumerated)
-            for message in &page.value {
+            for message in &page.value.messages {
                 let _ = self.apply_message_buffered(db, indexer, account, folder, message);
             }
-            indexer.commit().context("commit index during delta baseline")?;
+            indexer
+                .commit()
+                .context("commit index during delta baseline")?;
 
             if let Some(delta_link) = page.delta_link {
                 newest_delta_link = Some(delta_link);
@@ -810,15 +1394,36 @@ impl GraphApiConnector {
 
         if let Some(delta_link) = newest_delta_link {
             self.store_delta_link(db, account, folder, &delta_link)?;
-            eprintln!(
-                "graph full-sync {} folder={}: delta baseline saved",
-                account.account_id, folder.ess_label
-            );
+            self.notice(account, Some(folder), "delta baseline saved".to_string());
         }
 
+        stage.finish(&report);
         Ok(report)
     }
 
+    /// Stores a message quarantined by an earlier sync, decoding the kept
+    /// payload again into the folder it was synced from. Returns false when
+    /// the email is already stored. The index write is buffered.
+    pub(crate) fn apply_quarantined(
+        &self,
+        db: &Database,
+        indexer: &mut EmailIndex,
+        account: &Account,
+        entry: &QuarantinedMessage,
+    ) -> Result<bool> {
+        let message: GraphMessage =
+            serde_json::from_str(&entry.payload).context("decode graph message")?;
+        let label = entry.source.clone().unwrap_or_else(|| "inbox".to_string());
+        let folder = DiscoveredFolder {
+            folder_id: label.clone(),
+            display_name: label.clone(),
+            ess_label: label,
+            total_items: None,
+        };
+        let applied = self.apply_message_buffered(db, indexer, account, &folder, &message)?;
+        Ok(matches!(applied, ApplyResult::Added))
+    }
+
     fn apply_message_buffered(
         &self,
         db: &Database,
@@ -838,17 +1443,53 @@ impl GraphApiConnector {
             indexer
                 .delete_email(id)
                 .with_context(|| format!("delete removed email from index {id}"))?;
+            if let Some(raw) = &self.raw {
+                raw.delete(id)
+                    .with_context(|| format!("delete raw message of removed email {id}"))?;
+            }
             return Ok(ApplyResult::Deleted);
         }
 
+        if let (true, Some(id)) = (message.is_state_only(), message.id.as_deref()) {
+            if let Some(mut email) = db
+                .get_email(id)
+                .with_context(|| format!("load stored email {id}"))?
+            {
+                message.apply_state_to(&mut email, folder);
+                db.update_email_flags(&email)
+                    .with_context(|| format!("update state of graph email {id}"))?;
+                indexer
+                    .update_email(&email, &account.account_type.to_string())
+                    .with_context(|| format!("reindex graph email {id}"))?;
+                return Ok(ApplyResult::Updated);
+            }
+        }
+
         let email = map_graph_message_to_email(message, account, folder)?;
         let existed = db
             .get_email(&email.id)
             .with_context(|| format!("check existing email {}", email.id))?
             .is_some();
+        let attachments = match message.attachments.as_deref() {
+            Some(attachments) => map_graph_attachments(&email.id, attachments),
+            // Delta pages carry no attachments; keep the ones stored earlier.
+            None => db
+                .get_attachments(&email.id)
+                .with_context(|| format!("load attachments of graph email {}", email.id))?,
+        };
 
+        for copy in db
+            .merge_stored_copies(&email)
+            .with_context(|| format!("merge stored copies of graph email {}", email.id))?
+        {
+            indexer
+                .delete_email_buffered(&copy)
+                .with_context(|| format!("unindex replaced copy {copy}"))?;
+        }
         db.insert_email(&email)
             .with_context(|| format!("upsert graph email {}", email.id))?;
+        db.replace_attachments(&email.id, &attachments)
+            .with_context(|| format!("store attachments of graph email {}", email.id))?;
         indexer
             .add_email_buffered(&email, &account.account_type.to_string())
             .with_context(|| format!("index graph email {}", email.id))?;
@@ -883,18 +1524,54 @@ impl GraphApiConnector {
         let mut next_url = existing_delta_link.unwrap();
         let mut newest_delta_link: Option<String> = None;
         let mut page_number = 0u64;
+        let mut stage = self.folder_stage(account, "delta", folder);
 
         loop {
             // Refresh token per page to avoid expiry during long syncs
             let token = self.get_access_token(db, account).await?;
 
-            let page = self.fetch_delta_page_with_retry(&token, &next_url).await?;
+            let page = match self.fetch_delta_page_with_retry(&token, &next_url).await {
+                Ok(page) => page,
+                Err(error) => match ConnectorError::classify(&error) {
+                    // Graph answers 410 Gone (or 404) once a delta token has
+                    // expired; the folder has to be enumerated again.
+                    ConnectorError::NotFound { message } => {
+                        self.notice(
+                            account,
+                            Some(folder),
+                            format!("delta link expired ({message}); re-enumerating folder"),
+                        );
+                        let key = Self::delta_link_key(account, &folder.folder_id);
+                        Self::clear_sync_state(db, &key)?;
+                        let mut enumerated = self
+                            .full_enumerate_folder(db, indexer, account, folder)
+                            .await?;
+                        enumerated.emails_added += report.emails_added;
+                        enumerated.emails_updated += report.emails_updated;
+                        enumerated.errors.splice(0..0, report.errors);
+                        return Ok(enumerated);
+                    }
+                    _ => return Err(error),
+                },
+            };
             page_number += 1;
-            let page_size = page.value.len();
+            let page_size = page.value.messages.len() + page.value.unparsed.len();
+            quarantine_unparsed(
+                db,
+                account,
+                self.name(),
+                Some(&folder.ess_label),
+                &page.value.unparsed,
+                &mut report,
+            );
 
-            for message in &page.value {
+            let mut added = Vec::new();
+            for message in &page.value.messages {
                 match self.apply_message_buffered(db, indexer, account, folder, message) {
-                    Ok(ApplyResult::Added) => report.emails_added += 1,
+                    Ok(ApplyResult::Added) => {
+                        report.emails_added += 1;
+                        added.extend(message.id.clone());
+                    }
                     Ok(ApplyResult::Updated | ApplyResult::Deleted) => report.emails_updated += 1,
                     Err(error) => {
                         let message_id = message.id.as_deref().unwrap_or("<missing-id>");
@@ -903,10 +1580,12 @@ impl GraphApiConnector {
                             .as_ref()
                             .and_then(|removed| removed.reason.as_deref())
                             .unwrap_or("-");
-                        report.errors.push(format!(
-                            "folder={} id={message_id} removed_reason={removed_reason}: {error}",
-                            folder.ess_label
-                        ));
+                        report
+                            .errors
+                            .push(ConnectorError::classify(&error).context(format!(
+                                "folder={} id={message_id} removed_reason={removed_reason}",
+                                folder.ess_label
+                            )));
                     }
                 }
             }
@@ -915,24 +1594,29 @@ impl GraphApiConnector {
             indexer
                 .commit()
                 .with_context(|| format!("commit index after page {page_number}"))?;
+            self.store_raw_messages(db, account, &added, &mut report)
+                .await;
 
-            eprintln!(
-                "graph sync {} folder={} ({}): page {} ({} messages), added={} updated={} errors={}",
-                account.account_id,
-                folder.ess_label,
-                folder.display_name,
-                page_number,
-                page_size,
-                report.emails_added,
-                report.emails_updated,
-                report.errors.len(),
-            );
+            stage.page(page_size, &report);
 
             if let Some(delta_link) = page.delta_link {
                 newest_delta_link = Some(delta_link);
             }
 
             if let Some(url) = page.next_link {
+                if self.shutdown.is_requested() {
+                    // A delta nextLink resumes the same round, so persist it
+                    // as the folder cursor and pick up from here next time.
+                    self.store_delta_link(db, account, folder, &url)?;
+                    self.notice(
+                        account,
+                        Some(folder),
+                        format!("interrupted after page {page_number}; cursor saved"),
+                    );
+                    report.interrupted = true;
+                    stage.finish(&report);
+                    return Ok(report);
+                }
                 next_url = url;
                 continue;
             }
@@ -941,8 +1625,14 @@ impl GraphApiConnector {
 
         if let Some(delta_link) = newest_delta_link {
             self.store_delta_link(db, account, folder, &delta_link)?;
+            if report.emails_added + report.emails_updated == 0 && report.errors.is_empty() {
+                if let Some(reason) = Self::lagging_delta_link(db, account, folder)? {
+                    self.reset_stale_delta_link(db, account, folder, &reason)?;
+                }
+            }
         }
 
+        stage.finish(&report);
         Ok(report)
     }
 }
@@ -956,25 +1646,19 @@ struct GraphCredentials {
 
 impl GraphCredentials {
     fn resolve(account: &Account) -> Result<Self> {
-        let tenant_id = std::env::var("ESS_TENANT_ID")
-            .ok()
-            .filter(|value| !value.trim().is_empty())
+        let tenant_id = env::var(env::TENANT_ID)
             .or_else(|| account.tenant_id.clone())
-            .or_else(|| config_string(account, "tenant_id"))
+            .or_else(|| secrets::config_string(account, "tenant_id"))
             .ok_or_else(|| anyhow!("missing graph tenant id (ESS_TENANT_ID/account.tenant_id)"))?;
 
-        let client_id = std::env::var("ESS_CLIENT_ID")
-            .ok()
-            .filter(|value| !value.trim().is_empty())
-            .or_else(|| config_string(account, "client_id"))
-            .ok_or_else(|| anyhow!("missing graph client id (ESS_CLIENT_ID/account.config)"))?;
+        let client_id =
+            secrets::credential(env::CLIENT_ID, account, "client_id").ok_or_else(|| {
+                anyhow!("missing graph client id (ESS_CLIENT_ID/keychain/account.config)")
+            })?;
 
-        let client_secret = std::env::var("ESS_CLIENT_SECRET")
-            .ok()
-            .filter(|value| !value.trim().is_empty())
-            .or_else(|| config_string(account, "client_secret"))
+        let client_secret = secrets::credential(env::CLIENT_SECRET, account, "client_secret")
             .ok_or_else(|| {
-                anyhow!("missing graph client secret (ESS_CLIENT_SECRET/account.config)")
+                anyhow!("missing graph client secret (ESS_CLIENT_SECRET/keychain/account.config)")
             })?;
 
         Ok(Self {
@@ -985,6 +1669,63 @@ impl GraphCredentials {
     }
 }
 
+/// Whether Graph counting `total` messages in a folder of which `stored`
+/// are stored is more than duplicates and quarantined messages explain.
+fn is_suspicious_count_gap(total: i64, stored: i64) -> bool {
+    total - stored >= STALE_DELTA_MIN_GAP.max(total / 100)
+}
+
+/// Master categories as stored label rows, with `presetN` colors named.
+fn map_master_categories(account_id: &str, categories: &[GraphMasterCategory]) -> Vec<Label> {
+    categories
+        .iter()
+        .map(|category| Label {
+            account_id: account_id.to_string(),
+            label_id: category.id.clone(),
+            name: category.display_name.clone(),
+            label_type: "category".to_string(),
+            parent: None,
+            background_color: category
+                .color
+                .as_deref()
+                .and_then(|color| color.strip_prefix("preset"))
+                .and_then(|index| index.parse::<usize>().ok())
+                .and_then(|index| CATEGORY_COLORS.get(index))
+                .map(|color| color.to_string()),
+            text_color: None,
+        })
+        .collect()
+}
+
+pub(crate) fn graph_api_base() -> String {
+    env::var(env::GRAPH_API_BASE).unwrap_or_else(|| GRAPH_API_BASE.to_string())
+}
+
+/// PATCH body for a read/flag change on a Graph message.
+fn graph_flag_patch(update: FlagUpdate) -> serde_json::Value {
+    let mut patch = serde_json::Map::new();
+    if let Some(read) = update.read {
+        patch.insert("isRead".to_string(), read.into());
+    }
+    if let Some(flagged) = update.flagged {
+        let status = if flagged { "flagged" } else { "notFlagged" };
+        patch.insert(
+            "flag".to_string(),
+            serde_json::json!({ "flagStatus": status }),
+        );
+    }
+    serde_json::Value::Object(patch)
+}
+
+/// Well-known Graph folder a [`MessageAction`] moves to, and the ESS label
+/// sync assigns to it. Deleting moves to Deleted Items rather than purging.
+fn move_destination(action: MessageAction) -> (&'static str, &'static str) {
+    match action {
+        MessageAction::Archive => ("archive", "archive"),
+        MessageAction::Delete => ("deleteditems", "trash"),
+    }
+}
+
 fn redact_response_body(body: &str) -> String {
     let trimmed = body.trim();
     if trimmed.len() <= REDACTED_BODY_MAX_LEN {
@@ -998,15 +1739,25 @@ fn redact_response_body(body: &str) -> String {
     }
 }
 
-fn config_string(account: &Account, key: &str) -> Option<String> {
-    account
-        .config
-        .as_ref()
-        .and_then(|config| config.get(key))
-        .and_then(|value| value.as_str())
-        .map(str::trim)
-        .filter(|value| !value.is_empty())
-        .map(str::to_string)
+fn map_graph_attachments(email_id: &str, attachments: &[GraphAttachment]) -> Vec<Attachment> {
+    attachments
+        .iter()
+        .enumerate()
+        .map(|(position, attachment)| Attachment {
+            id: format!(
+                "{email_id}:{}",
+                attachment
+                    .id
+                    .clone()
+                    .unwrap_or_else(|| position.to_string())
+            ),
+            email_id: email_id.to_string(),
+            name: attachment.name.clone(),
+            content_type: attachment.content_type.clone(),
+            size_bytes: attachment.size,
+            is_inline: attachment.is_inline,
+        })
+        .collect()
 }
 
 fn map_graph_message_to_email(
@@ -1059,9 +1810,28 @@ fn map_graph_message_to_email(
         .or_else(|| message.sent_date_time.clone())
         .unwrap_or_else(|| Utc::now().to_rfc3339());
 
+    let mut metadata = serde_json::json!({
+        "connector": "graph_api",
+        "source": "graph_delta_sync"
+    });
+    let headers = || {
+        message
+            .internet_message_headers
+            .as_deref()
+            .unwrap_or_default()
+            .iter()
+            .map(|header| (header.name.as_str(), header.value.as_str()))
+    };
+    if let Some(list_headers) = ListHeaders::from_headers(headers()) {
+        metadata[LIST_HEADERS_KEY] = serde_json::to_value(list_headers)?;
+    }
+    let thread_headers = ThreadHeaders::from_headers(headers());
+
     Ok(Email {
         id,
         internet_message_id: message.internet_message_id.clone(),
+        in_reply_to: thread_headers.in_reply_to,
+        references: thread_headers.references,
         conversation_id: message.conversation_id.clone(),
         account_id: Some(account.account_id.clone()),
         subject: message.subject.clone(),
@@ -1085,10 +1855,7 @@ fn map_graph_message_to_email(
             .as_ref()
             .and_then(|flag| flag.flag_status.clone()),
         web_link: message.web_link.clone(),
-        metadata: Some(serde_json::json!({
-            "connector": "graph_api",
-            "source": "graph_delta_sync"
-        })),
+        metadata: Some(metadata),
     })
 }
 
@@ -1188,133 +1955,25 @@ struct CachedAccessToken {
     expires_at: DateTime<Utc>,
 }
 
-impl CachedAccessToken {
-    fn is_expired(&self) -> bool {
-        self.expires_at <= Utc::now()
-    }
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct EncryptedTokenEnvelope {
-    version: u8,
-    nonce_hex: String,
-    ciphertext_hex: String,
-}
-
-fn encrypt_cached_access_token(
-    token: &CachedAccessToken,
-    encryption_key: &[u8; TOKEN_CACHE_KEY_BYTES],
-) -> Result<String> {
-    let mut plaintext = serde_json::to_vec(token).context("serialize token payload")?;
-
-    let unbound_key = UnboundKey::new(&AES_256_GCM, encryption_key)
-        .map_err(|_| anyhow!("construct AES-256-GCM key"))?;
-    let key = LessSafeKey::new(unbound_key);
-
-    let mut nonce_bytes = [0u8; TOKEN_CACHE_NONCE_BYTES];
-    SystemRandom::new()
-        .fill(&mut nonce_bytes)
-        .map_err(|_| anyhow!("generate random nonce for token cache encryption"))?;
-
-    key.seal_in_place_append_tag(
-        Nonce::assume_unique_for_key(nonce_bytes),
-        Aad::empty(),
-        &mut plaintext,
-    )
-    .map_err(|_| anyhow!("encrypt graph token cache"))?;
-
-    let envelope = EncryptedTokenEnvelope {
-        version: TOKEN_CACHE_ENVELOPE_VERSION,
-        nonce_hex: hex_encode(&nonce_bytes),
-        ciphertext_hex: hex_encode(&plaintext),
-    };
-
-    serde_json::to_string(&envelope).context("serialize encrypted token envelope")
+fn encrypt_cached_access_token(token: &CachedAccessToken, keys: &TokenCacheKeys) -> Result<String> {
+    let plaintext = serde_json::to_vec(token).context("serialize token payload")?;
+    Ok(keys.seal(&plaintext)?)
 }
 
+/// Returns the token and whether it was sealed with the previous rotation key.
 fn decrypt_cached_access_token(
     raw: &str,
-    encryption_key: &[u8; TOKEN_CACHE_KEY_BYTES],
-) -> Result<CachedAccessToken> {
-    let envelope: EncryptedTokenEnvelope =
-        serde_json::from_str(raw).context("parse encrypted token envelope")?;
-
-    if envelope.version != TOKEN_CACHE_ENVELOPE_VERSION {
-        return Err(anyhow!(
-            "unsupported token envelope version {}",
-            envelope.version
-        ));
-    }
-
-    let nonce_vec = hex_decode(&envelope.nonce_hex).context("decode envelope nonce")?;
-    let nonce_bytes: [u8; TOKEN_CACHE_NONCE_BYTES] = nonce_vec
-        .try_into()
-        .map_err(|_| anyhow!("invalid nonce length in token envelope"))?;
-    let mut ciphertext =
-        hex_decode(&envelope.ciphertext_hex).context("decode envelope ciphertext")?;
-
-    let unbound_key = UnboundKey::new(&AES_256_GCM, encryption_key)
-        .map_err(|_| anyhow!("construct AES-256-GCM key"))?;
-    let key = LessSafeKey::new(unbound_key);
-
-    let plaintext = key
-        .open_in_place(
-            Nonce::assume_unique_for_key(nonce_bytes),
-            Aad::empty(),
-            &mut ciphertext,
-        )
-        .map_err(|_| anyhow!("decrypt graph token cache"))?;
-
-    serde_json::from_slice(plaintext).context("parse decrypted token payload")
-}
-
-fn parse_token_cache_key_hex(raw: &str) -> Result<[u8; TOKEN_CACHE_KEY_BYTES]> {
-    let decoded = hex_decode(raw).context("decode token cache key hex")?;
-    decoded
-        .try_into()
-        .map_err(|_| anyhow!("token cache key must be 32 bytes"))
-}
-
-fn hex_encode(bytes: &[u8]) -> String {
-    const HEX: &[u8; 16] = b"0123456789abcdef";
-    let mut out = String::with_capacity(bytes.len() * 2);
-    for byte in bytes {
-        out.push(HEX[(byte >> 4) as usize] as char);
-        out.push(HEX[(byte & 0x0f) as usize] as char);
-    }
-    out
-}
-
-fn hex_decode(raw: &str) -> Result<Vec<u8>> {
-    let value = raw.trim();
-    if !value.len().is_multiple_of(2) {
-        return Err(anyhow!("hex string length must be even"));
-    }
-
-    let mut out = Vec::with_capacity(value.len() / 2);
-    let bytes = value.as_bytes();
-    let mut idx = 0usize;
-    while idx < bytes.len() {
-        let hi = decode_hex_nibble(bytes[idx]).ok_or_else(|| anyhow!("invalid hex digit"))?;
-        let lo = decode_hex_nibble(bytes[idx + 1]).ok_or_else(|| anyhow!("invalid hex digit"))?;
-        out.push((hi << 4) | lo);
-        idx += 2;
-    }
-    Ok(out)
-}
-
-fn decode_hex_nibble(byte: u8) -> Option<u8> {
-    match byte {
-        b'0'..=b'9' => Some(byte - b'0'),
-        b'a'..=b'f' => Some(byte - b'a' + 10),
-        b'A'..=b'F' => Some(byte - b'A' + 10),
-        _ => None,
-    }
+    keys: &TokenCacheKeys,
+) -> Result<(CachedAccessToken, bool)> {
+    let unsealed = keys.open(raw)?;
+    let token =
+        serde_json::from_slice(&unsealed.plaintext).context("parse decrypted token payload")?;
+    Ok((token, unsealed.needs_reseal))
 }
 
 #[derive(Debug, Clone, Deserialize)]
 struct GraphDeltaPage {
-    value: Vec<GraphMessage>,
+    value: GraphMessageList,
     #[serde(rename = "@odata.nextLink")]
     next_link: Option<String>,
     #[serde(rename = "@odata.deltaLink")]
@@ -1324,11 +1983,39 @@ struct GraphDeltaPage {
 /// Response page from the plain `/messages` list endpoint (no deltaLink).
 #[derive(Debug, Clone, Deserialize)]
 struct GraphMessagesPage {
-    value: Vec<GraphMessage>,
+    value: GraphMessageList,
     #[serde(rename = "@odata.nextLink")]
     next_link: Option<String>,
 }
 
+/// A page's `value` array, decoded message by message so one malformed
+/// message ends up in the quarantine instead of failing the whole page.
+#[derive(Debug, Clone, Default)]
+struct GraphMessageList {
+    messages: Vec<GraphMessage>,
+    unparsed: Vec<UnparsedMessage>,
+}
+
+impl<'de> Deserialize<'de> for GraphMessageList {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let mut list = Self::default();
+        for value in Vec::<Value>::deserialize(deserializer)? {
+            match GraphMessage::deserialize(&value) {
+                Ok(message) => list.messages.push(message),
+                Err(error) => list.unparsed.push(UnparsedMessage {
+                    message_id: value.get("id").and_then(Value::as_str).map(str::to_string),
+                    payload: value.to_string(),
+                    error: format!("decode graph message: {error}"),
+                }),
+            }
+        }
+        Ok(list)
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 struct GraphMessage {
     id: Option<String>,
@@ -1362,6 +2049,36 @@ struct GraphMessage {
     sent_date_time: Option<String>,
     #[serde(rename = "@removed")]
     removed: Option<GraphRemoved>,
+    attachments: Option<Vec<GraphAttachment>>,
+    #[serde(rename = "internetMessageHeaders")]
+    internet_message_headers: Option<Vec<GraphHeader>>,
+}
+
+impl GraphMessage {
+    /// A delta item that only reports changed state (read, flag,
+    /// categories) without the message content.
+    fn is_state_only(&self) -> bool {
+        self.subject.is_none()
+            && self.body.is_none()
+            && self.body_preview.is_none()
+            && self.from.is_none()
+            && self.received_date_time.is_none()
+    }
+
+    /// Copies the state this item reports onto a stored email, keeping
+    /// whatever it leaves out.
+    fn apply_state_to(&self, email: &mut Email, folder: &DiscoveredFolder) {
+        if let Some(is_read) = self.is_read {
+            email.is_read = Some(is_read);
+        }
+        if let Some(flag) = &self.flag {
+            email.flag_status = flag.flag_status.clone();
+        }
+        if let Some(categories) = &self.categories {
+            email.categories = categories.clone();
+        }
+        email.folder = Some(folder.ess_label.clone());
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -1410,12 +2127,29 @@ struct GraphBody {
     content: Option<String>,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+struct GraphHeader {
+    name: String,
+    value: String,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 struct GraphFlag {
     #[serde(rename = "flagStatus")]
     flag_status: Option<String>,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+struct GraphAttachment {
+    id: Option<String>,
+    name: Option<String>,
+    #[serde(rename = "contentType")]
+    content_type: Option<String>,
+    size: Option<i64>,
+    #[serde(rename = "isInline")]
+    is_inline: Option<bool>,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 struct GraphRemoved {
     reason: Option<String>,
@@ -1427,6 +2161,79 @@ impl EmailConnector for GraphApiConnector {
         "graph_api"
     }
 
+    fn remote_endpoints(&self) -> Vec<String> {
+        vec![graph_api_base()]
+    }
+
+    /// No folder has a delta link yet. A folder discovered later is
+    /// enumerated on its own.
+    fn needs_full_sync(&self, db: &Database, account: &Account) -> Result<bool> {
+        let prefix = Self::legacy_delta_link_key(account);
+        let has_delta_link = db.list_sync_state(&prefix)?.into_iter().any(|state| {
+            (state.key == prefix || state.key.starts_with(&format!("{prefix}:")))
+                && state.value.is_some_and(|value| !value.trim().is_empty())
+        });
+        Ok(!has_delta_link)
+    }
+
+    fn take_usage(&self) -> UsageCounts {
+        self.usage.take()
+    }
+
+    fn check_credentials(&self, account: &Account) -> Result<()> {
+        GraphCredentials::resolve(account).map(|_| ())
+    }
+
+    fn supports_flag_updates(&self) -> bool {
+        true
+    }
+
+    async fn update_flags(
+        &self,
+        db: &Database,
+        account: &Account,
+        email: &Email,
+        update: FlagUpdate,
+    ) -> Result<()> {
+        let token = self.get_access_token(db, account).await?;
+        let request = self
+            .client
+            .patch(Self::message_url(account, &email.id))
+            .bearer_auth(&token)
+            .json(&graph_flag_patch(update));
+        self.send_write_with_retry(request, "message update")
+            .await
+            .map(|_| ())
+    }
+
+    fn supports_message_actions(&self) -> bool {
+        true
+    }
+
+    async fn apply_message_action(
+        &self,
+        db: &Database,
+        account: &Account,
+        email: &Email,
+        action: MessageAction,
+    ) -> Result<MovedMessage> {
+        let (destination_id, folder) = move_destination(action);
+        let token = self.get_access_token(db, account).await?;
+        let request = self
+            .client
+            .post(format!("{}/move", Self::message_url(account, &email.id)))
+            .bearer_auth(&token)
+            .json(&serde_json::json!({ "destinationId": destination_id }));
+        let body = self.send_write_with_retry(request, "message move").await?;
+        let moved: GraphMessage =
+            serde_json::from_str(&body).context("decode graph move response JSON")?;
+
+        Ok(MovedMessage {
+            id: moved.id.unwrap_or_else(|| email.id.clone()),
+            folder: folder.to_string(),
+        })
+    }
+
     async fn sync(
         &self,
         db: &Database,
@@ -1438,27 +2245,74 @@ impl EmailConnector for GraphApiConnector {
         db.insert_account(account)
             .context("upsert account before graph sync")?;
 
+        // Categories are optional: without MailboxSettings.Read the ones
+        // stored earlier (if any) are kept and the sync goes on.
+        if let Err(error) = self.refresh_master_categories(db, account).await {
+            match ConnectorError::classify(&error) {
+                ConnectorError::Auth { .. } => warn!(
+                    "graph master categories not readable for account {} (needs MailboxSettings.Read)",
+                    account.account_id
+                ),
+                error => report.errors.push(error.context("master categories")),
+            }
+        }
+
         let folders = self.discover_folders(db, account).await?;
 
         for folder in &folders {
-            eprintln!(
-                "graph sync {} starting folder={} ({})",
-                account.account_id, folder.ess_label, folder.display_name
-            );
-
-            match self.sync_folder(db, indexer, account, folder).await {
+            if self.shutdown.is_requested() {
+                report.interrupted = true;
+                break;
+            }
+            let started = Instant::now();
+            let mut summary = FolderSyncReport {
+                folder: folder.ess_label.clone(),
+                display_name: folder.display_name.clone(),
+                ..FolderSyncReport::default()
+            };
+            if let Some(reason) = Self::failing_delta_link(db, account, folder)? {
+                self.reset_stale_delta_link(db, account, folder, &reason)?;
+            }
+            let mut completed = false;
+            let last_error = match self.sync_folder(db, indexer, account, folder).await {
                 Ok(folder_report) => {
+                    completed = !folder_report.interrupted;
+                    summary.emails_added = folder_report.emails_added;
+                    summary.emails_updated = folder_report.emails_updated;
+                    summary.errors = folder_report.errors.len();
+                    summary.interrupted = folder_report.interrupted;
+                    let last_error = folder_report.errors.last().map(ToString::to_string);
                     report.emails_added += folder_report.emails_added;
                     report.emails_updated += folder_report.emails_updated;
                     report.errors.extend(folder_report.errors);
+                    report.interrupted |= folder_report.interrupted;
+                    last_error
                 }
                 Err(error) => {
-                    report.errors.push(format!(
-                        "folder={} ({}): {}",
-                        folder.ess_label, folder.display_name, error
-                    ));
+                    summary.errors = 1;
+                    let error = ConnectorError::classify(&error);
+                    let last_error = error.to_string();
+                    report.errors.push(error.context(format!(
+                        "folder={} ({})",
+                        folder.ess_label, folder.display_name
+                    )));
+                    Some(last_error)
                 }
-            }
+            };
+            db.record_folder_sync(&FolderSyncRun {
+                account_id: &account.account_id,
+                folder_id: &folder.folder_id,
+                folder: &folder.ess_label,
+                display_name: &folder.display_name,
+                emails_added: summary.emails_added,
+                emails_updated: summary.emails_updated,
+                completed,
+                error: last_error.as_deref(),
+                at: &Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            })
+            .context("record folder sync state")?;
+            summary.duration_ms = u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX);
+            report.folders.push(summary);
         }
 
         Ok(report)
@@ -1482,13 +2336,15 @@ mod tests {
     use uuid::Uuid;
 
     use super::{
-        is_excluded_folder, legacy_delta_key_name, map_graph_message_to_email,
-        normalize_folder_label, CachedAccessToken, DiscoveredFolder, GraphApiConnector,
-        GraphCredentials, GraphMessage, OAuthTokenResponse, TOKEN_CACHE_ENCRYPTION_KEY_ENV,
+        graph_flag_patch, is_excluded_folder, is_suspicious_count_gap, legacy_delta_key_name,
+        map_graph_message_to_email, move_destination, normalize_folder_label, CachedAccessToken,
+        DiscoveredFolder, GraphApiConnector, GraphCredentials, GraphMessage, OAuthTokenResponse,
+        STALE_DELTA_FAILURES,
     };
-    use crate::connectors::TOKEN_ENV_LOCK;
+    use crate::connectors::{FlagUpdate, MessageAction, TOKEN_ENV_LOCK};
     use crate::db::models::{Account, AccountType};
-    use crate::db::Database;
+    use crate::db::{Database, FolderSyncRun};
+    use crate::env;
 
     const TEST_TOKEN_CACHE_KEY_HEX: &str =
         "00112233445566778899aabbccddeeff00112233445566778899aabbccddeeff";
@@ -1497,14 +2353,14 @@ mod tests {
 
     impl TokenCacheKeyGuard {
         fn set() -> Self {
-            std::env::set_var(TOKEN_CACHE_ENCRYPTION_KEY_ENV, TEST_TOKEN_CACHE_KEY_HEX);
+            std::env::set_var(env::TOKEN_CACHE_KEY, TEST_TOKEN_CACHE_KEY_HEX);
             Self
         }
     }
 
     impl Drop for TokenCacheKeyGuard {
         fn drop(&mut self) {
-            std::env::remove_var(TOKEN_CACHE_ENCRYPTION_KEY_ENV);
+            std::env::remove_var(env::TOKEN_CACHE_KEY);
         }
     }
 
@@ -1514,9 +2370,13 @@ mod tests {
 
     fn test_folder(display_name: &str) -> DiscoveredFolder {
         DiscoveredFolder {
-            folder_id: format!("folder-id-{}", display_name.to_lowercase().replace(' ', "-")),
+            folder_id: format!(
+                "folder-id-{}",
+                display_name.to_lowercase().replace(' ', "-")
+            ),
             display_name: display_name.to_string(),
             ess_label: normalize_folder_label(display_name),
+            total_items: None,
         }
     }
 
@@ -1584,7 +2444,7 @@ mod tests {
     #[test]
     fn token_cache_is_not_persisted_without_encryption_key() {
         let _lock = TOKEN_ENV_LOCK.lock().expect("lock env mutation");
-        std::env::remove_var(TOKEN_CACHE_ENCRYPTION_KEY_ENV);
+        std::env::remove_var(env::TOKEN_CACHE_KEY);
 
         let connector = GraphApiConnector::new();
         let account = account();
@@ -1659,6 +2519,39 @@ mod tests {
         assert_eq!(mapped.folder.as_deref(), Some("inbox"));
     }
 
+    #[test]
+    fn state_only_delta_items_keep_stored_content() {
+        let account = account();
+        let inbox = test_folder("Inbox");
+        let full: GraphMessage = serde_json::from_value(json!({
+            "id": "msg-1",
+            "subject": "Quarterly Review",
+            "body": { "contentType": "text", "content": "Numbers attached" },
+            "isRead": false,
+            "categories": ["work"],
+            "receivedDateTime": "2026-01-01T12:00:00Z"
+        }))
+        .expect("deserialize graph message");
+        assert!(!full.is_state_only());
+        let mut stored =
+            map_graph_message_to_email(&full, &account, &inbox).expect("map graph message");
+
+        let change: GraphMessage = serde_json::from_value(json!({
+            "id": "msg-1",
+            "isRead": true,
+            "flag": { "flagStatus": "flagged" }
+        }))
+        .expect("deserialize state change");
+        assert!(change.is_state_only());
+        change.apply_state_to(&mut stored, &inbox);
+
+        assert_eq!(stored.is_read, Some(true));
+        assert_eq!(stored.flag_status.as_deref(), Some("flagged"));
+        assert_eq!(stored.categories, vec!["work"]);
+        assert_eq!(stored.subject.as_deref(), Some("Quarterly Review"));
+        assert_eq!(stored.body_text.as_deref(), Some("Numbers attached"));
+    }
+
     #[test]
     fn initial_delta_url_is_account_and_folder_scoped() {
         let connector = GraphApiConnector::new();
@@ -1696,8 +2589,11 @@ mod tests {
 
         // Store a delta link under the legacy (un-scoped) key.
         let legacy_key = GraphApiConnector::legacy_delta_link_key(&account);
-        db.set_sync_state(&legacy_key, "https://graph.microsoft.com/v1.0/delta-link-old")
-            .expect("seed legacy delta link");
+        db.set_sync_state(
+            &legacy_key,
+            "https://graph.microsoft.com/v1.0/delta-link-old",
+        )
+        .expect("seed legacy delta link");
 
         let inbox = test_folder("Inbox");
         let loaded = connector
@@ -1725,6 +2621,63 @@ mod tests {
         let _ = std::fs::remove_file(db_path);
     }
 
+    #[test]
+    fn stale_delta_links_are_reset_after_failures_or_an_unexplained_gap() {
+        let connector = GraphApiConnector::new();
+        let account = account();
+        let db_path = temp_db_path();
+        let db = Database::open(&db_path).expect("open db");
+        let inbox = DiscoveredFolder {
+            total_items: Some(500),
+            ..test_folder("Inbox")
+        };
+        let key = GraphApiConnector::delta_link_key(&account, &inbox.folder_id);
+        db.set_sync_state(&key, "https://graph.microsoft.com/v1.0/delta-link")
+            .expect("seed delta link");
+
+        let failed = FolderSyncRun {
+            account_id: &account.account_id,
+            folder_id: &inbox.folder_id,
+            folder: &inbox.ess_label,
+            display_name: &inbox.display_name,
+            emails_added: 0,
+            emails_updated: 0,
+            completed: false,
+            error: Some("bad request"),
+            at: "2026-03-01T00:00:00Z",
+        };
+        for failures in 1..=STALE_DELTA_FAILURES {
+            assert!(
+                GraphApiConnector::failing_delta_link(&db, &account, &inbox)
+                    .expect("check failures")
+                    .is_none(),
+                "reset after {} failures",
+                failures - 1
+            );
+            db.record_folder_sync(&failed).expect("record failure");
+        }
+        let reason = GraphApiConnector::failing_delta_link(&db, &account, &inbox)
+            .expect("check failures")
+            .expect("stale after repeated failures");
+        assert!(GraphApiConnector::lagging_delta_link(&db, &account, &inbox)
+            .expect("check gap")
+            .is_some_and(|gap| gap.contains("500 messages and 0 are stored")));
+
+        connector
+            .reset_stale_delta_link(&db, &account, &inbox, &reason)
+            .expect("reset");
+        assert!(db.get_sync_state(&key).expect("read cursor").is_none());
+        // A reset just now holds off the next one for the same gap.
+        assert!(GraphApiConnector::lagging_delta_link(&db, &account, &inbox)
+            .expect("check gap")
+            .is_none());
+
+        assert!(is_suspicious_count_gap(500, 480));
+        assert!(!is_suspicious_count_gap(500, 495));
+        assert!(!is_suspicious_count_gap(10_000, 9_950));
+        let _ = std::fs::remove_file(db_path);
+    }
+
     #[test]
     fn legacy_wellknown_delta_link_is_migrated() {
         let connector = GraphApiConnector::new();
@@ -1746,10 +2699,7 @@ mod tests {
             .load_delta_link(&db, &account, &sent)
             .expect("load delta link")
             .expect("delta link exists");
-        assert_eq!(
-            loaded,
-            "https://graph.microsoft.com/v1.0/delta-link-sent"
-        );
+        assert_eq!(loaded, "https://graph.microsoft.com/v1.0/delta-link-sent");
 
         // The new folder-ID key should hold the value.
         let new_key = GraphApiConnector::delta_link_key(&account, &sent.folder_id);
@@ -1773,6 +2723,37 @@ mod tests {
         let _ = std::fs::remove_file(db_path);
     }
 
+    #[test]
+    fn flag_patch_only_includes_requested_fields() {
+        let patch = graph_flag_patch(FlagUpdate {
+            read: Some(true),
+            flagged: None,
+        });
+        assert_eq!(patch, serde_json::json!({ "isRead": true }));
+
+        let patch = graph_flag_patch(FlagUpdate {
+            read: None,
+            flagged: Some(false),
+        });
+        assert_eq!(
+            patch,
+            serde_json::json!({ "flag": { "flagStatus": "notFlagged" } })
+        );
+    }
+
+    #[test]
+    fn move_destinations_match_synced_folder_labels() {
+        for action in [MessageAction::Archive, MessageAction::Delete] {
+            let (destination, label) = move_destination(action);
+            let display_name = match destination {
+                "archive" => "Archive",
+                _ => "Deleted Items",
+            };
+            assert_eq!(legacy_delta_key_name(display_name), Some(destination));
+            assert_eq!(normalize_folder_label(display_name), label);
+        }
+    }
+
     #[test]
     fn folder_mapping_uses_normalized_label() {
         let account = account();
@@ -1804,9 +2785,15 @@ mod tests {
         assert_eq!(normalize_folder_label("Deleted Items"), "trash");
         assert_eq!(normalize_folder_label("Junk Email"), "spam");
         assert_eq!(normalize_folder_label("Outbox"), "outbox");
-        assert_eq!(normalize_folder_label("Conversation History"), "conversation_history");
+        assert_eq!(
+            normalize_folder_label("Conversation History"),
+            "conversation_history"
+        );
         // Custom folders pass through as lowercase
-        assert_eq!(normalize_folder_label("My Custom Folder"), "my custom folder");
+        assert_eq!(
+            normalize_folder_label("My Custom Folder"),
+            "my custom folder"
+        );
         assert_eq!(normalize_folder_label("Blocked"), "blocked");
         assert_eq!(normalize_folder_label("Later"), "later");
     }
@@ -1835,4 +2822,25 @@ mod tests {
         assert_eq!(legacy_delta_key_name("Custom Folder"), None);
         assert_eq!(legacy_delta_key_name("Outbox"), None);
     }
+
+    #[test]
+    fn master_categories_map_to_named_colors() {
+        use super::{map_master_categories, GraphMasterCategoryList};
+
+        let list: GraphMasterCategoryList = serde_json::from_value(json!({
+            "value": [
+                {"id": "c1", "displayName": "Red category", "color": "preset0"},
+                {"id": "c2", "displayName": "Board", "color": "preset20"},
+                {"id": "c3", "displayName": "Plain", "color": "none"}
+            ]
+        }))
+        .expect("decode master categories");
+        let labels = map_master_categories("acc", &list.value);
+
+        assert_eq!(labels[0].background_color.as_deref(), Some("Red"));
+        assert_eq!(labels[1].name, "Board");
+        assert_eq!(labels[1].label_type, "category");
+        assert_eq!(labels[1].background_color.as_deref(), Some("DarkTeal"));
+        assert_eq!(labels[2].background_color, None);
+    }
 }
@@ -9,16 +9,27 @@ use serde_json::{json, Value};
 
 use crate::connectors::{EmailConnector, ImportReport, SyncReport};
 use crate::db::models::Account;
-use crate::db::models::Email;
+use crate::db::models::{Attachment, Email};
+use crate::db::quarantine::{QuarantineRecord, QuarantinedMessage};
+use crate::db::thread_links::ThreadHeaders;
 use crate::db::Database;
 use crate::indexer::EmailIndex;
 
 #[derive(Debug, Default, Clone)]
-pub struct JsonArchiveConnector;
+pub struct JsonArchiveConnector {
+    keep_payloads: bool,
+}
 
 impl JsonArchiveConnector {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Keeps each file's JSON in `metadata.archive_payload`, so `ess
+    /// reprocess` can map it again after the mapping improves.
+    pub fn with_payloads(mut self, keep: bool) -> Self {
+        self.keep_payloads = keep;
+        self
     }
 }
 
@@ -53,11 +64,53 @@ impl EmailConnector for JsonArchiveConnector {
         for file_path in files {
             report.files_processed += 1;
 
-            match import_file(db, indexer, account, &file_path) {
+            let raw = match std::fs::read_to_string(&file_path) {
+                Ok(raw) => raw,
+                Err(error) => {
+                    report.errors.push(format!(
+                        "{}: read JSON archive file: {error}",
+                        file_path.display()
+                    ));
+                    continue;
+                }
+            };
+            // A file that does not decode or map is quarantined whole, so a
+            // large import never drops one silently.
+            let (payload, email) = match decode_archive_file(&raw, account, &file_path) {
+                Ok(decoded) => decoded,
+                Err(error) => {
+                    let source = file_path.display().to_string();
+                    let quarantined = db.quarantine_message(&QuarantineRecord {
+                        account_id: &account.account_id,
+                        connector: self.name(),
+                        message_id: None,
+                        source: Some(&source),
+                        payload: &raw,
+                        error: &format!("{error:#}"),
+                    });
+                    report.errors.push(match quarantined {
+                        Ok(id) => {
+                            report.quarantined += 1;
+                            format!("{source}: {error} (quarantined as #{id})")
+                        }
+                        Err(quarantine_error) => {
+                            format!("{source}: {error} (not quarantined: {quarantine_error})")
+                        }
+                    });
+                    continue;
+                }
+            };
+
+            match store_archive_email(db, indexer, account, payload, email, self.keep_payloads) {
                 Ok(imported) => {
                     if imported {
                         report.emails_imported += 1;
                     }
+                    db.release_quarantined_source(
+                        &account.account_id,
+                        self.name(),
+                        &file_path.display().to_string(),
+                    )?;
                 }
                 Err(error) => {
                     report
@@ -103,24 +156,48 @@ fn collect_json_files(path: &Path) -> Result<Vec<PathBuf>> {
     Ok(files)
 }
 
-fn import_file(
+/// Imports a file quarantined by an earlier import, from the contents kept
+/// with it. Returns false when the email is already stored.
+pub fn import_quarantined(
     db: &Database,
     indexer: &mut EmailIndex,
     account: &Account,
-    file_path: &Path,
+    entry: &QuarantinedMessage,
+    keep_payload: bool,
 ) -> Result<bool> {
-    let raw = std::fs::read_to_string(file_path)
-        .with_context(|| format!("read JSON archive file {}", file_path.display()))?;
-    let payload: Value = serde_json::from_str(&raw)
-        .with_context(|| format!("parse JSON archive file {}", file_path.display()))?;
+    let file_path = PathBuf::from(entry.source.as_deref().unwrap_or_default());
+    let (payload, email) = decode_archive_file(&entry.payload, account, &file_path)?;
+    store_archive_email(db, indexer, account, payload, email, keep_payload)
+}
 
+fn decode_archive_file(raw: &str, account: &Account, file_path: &Path) -> Result<(Value, Email)> {
+    let payload: Value = serde_json::from_str(raw)
+        .with_context(|| format!("parse JSON archive file {}", file_path.display()))?;
     let email = map_archive_payload(&payload, account, file_path)?;
-    if db.get_email(&email.id)?.is_some() {
+    Ok((payload, email))
+}
+
+fn store_archive_email(
+    db: &Database,
+    indexer: &mut EmailIndex,
+    account: &Account,
+    payload: Value,
+    mut email: Email,
+    keep_payload: bool,
+) -> Result<bool> {
+    // A copy synced from the provider, or imported before under another
+    // id, is kept as it is.
+    if db.get_email(&email.id)?.is_some() || !db.stored_copies_of(&email)?.is_empty() {
         return Ok(false);
     }
 
+    if keep_payload {
+        email.set_meta("archive_payload", payload.clone());
+    }
     db.insert_email(&email)
         .with_context(|| format!("insert imported email {}", email.id))?;
+    db.replace_attachments(&email.id, &map_archive_attachments(&payload, &email.id))
+        .with_context(|| format!("store attachments of imported email {}", email.id))?;
     indexer
         .add_email(&email, &account.account_type.to_string())
         .with_context(|| format!("index imported email {}", email.id))?;
@@ -130,6 +207,28 @@ fn import_file(
 }
 
 fn map_archive_payload(payload: &Value, account: &Account, source_path: &Path) -> Result<Email> {
+    let mut email = map_archive_record(payload, &account.account_id)?;
+    let metadata = json!({
+        "source_file": source_path.file_name().and_then(|name| name.to_str()).unwrap_or_default(),
+        "archive_connector": "json_archive",
+    });
+    email.metadata = Some(metadata);
+    Ok(email)
+}
+
+/// Maps a payload kept by [`JsonArchiveConnector::with_payloads`] again,
+/// for `ess reprocess`. The stored email's id, account and metadata are
+/// kept.
+pub fn remap_archive_payload(email: &Email, payload: &Value) -> Result<Email> {
+    let mut remapped =
+        map_archive_record(payload, email.account_id.as_deref().unwrap_or_default())?;
+    remapped.id = email.id.clone();
+    remapped.account_id = email.account_id.clone();
+    remapped.metadata = email.metadata.clone();
+    Ok(remapped)
+}
+
+pub(crate) fn map_archive_record(payload: &Value, account_id: &str) -> Result<Email> {
     let record = payload.get("email").unwrap_or(payload);
 
     let id = get_str(record, &["id"])
@@ -179,6 +278,14 @@ fn map_archive_payload(payload: &Value, account: &Account, source_path: &Path) -
         .or_else(|| get_str(record, &["internetMessageId"]))
         .or_else(|| get_str(payload, &["internetMessageId"]));
 
+    let thread_headers = ThreadHeaders::from_headers(
+        headers
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+            .filter_map(|(name, value)| Some((name.as_str(), value.as_str()?))),
+    );
+
     let conversation_id = get_str(record, &["conversationId"])
         .or_else(|| get_str(payload, &["conversationId"]))
         .or_else(|| {
@@ -204,16 +311,13 @@ fn map_archive_payload(payload: &Value, account: &Account, source_path: &Path) -
         })
         .unwrap_or_default();
 
-    let metadata = Some(json!({
-        "source_file": source_path.file_name().and_then(|name| name.to_str()).unwrap_or_default(),
-        "archive_connector": "json_archive",
-    }));
-
     Ok(Email {
         id,
         internet_message_id,
+        in_reply_to: thread_headers.in_reply_to,
+        references: thread_headers.references,
         conversation_id,
-        account_id: Some(account.account_id.clone()),
+        account_id: Some(account_id.to_string()),
         subject,
         from_address,
         from_name,
@@ -232,10 +336,98 @@ fn map_archive_payload(payload: &Value, account: &Account, source_path: &Path) -
         categories,
         flag_status: None,
         web_link: get_str(record, &["webLink"]).or_else(|| get_str(payload, &["webLink"])),
-        metadata,
+        metadata: None,
     })
 }
 
+/// Renders a stored email in the archive format `import` reads, so exported
+/// emails can be imported again. Prefers the plain-text body over HTML.
+pub fn archive_payload(email: &Email) -> Value {
+    let recipients = |addresses: &[String]| {
+        addresses
+            .iter()
+            .map(|address| json!({ "address": address }))
+            .collect::<Vec<_>>()
+    };
+    let body = match (&email.body_text, &email.body_html) {
+        (Some(text), _) => json!({ "contentType": "text", "content": text }),
+        (None, Some(html)) => json!({ "contentType": "html", "content": html }),
+        (None, None) => Value::Null,
+    };
+
+    let mut payload = json!({
+        "id": email.id,
+        "subject": email.subject,
+        "receivedDateTime": email.received_at,
+        "sentDateTime": email.sent_at,
+        "from": { "name": email.from_name, "address": email.from_address },
+        "toRecipients": recipients(&email.to_addresses),
+        "ccRecipients": recipients(&email.cc_addresses),
+        "bccRecipients": recipients(&email.bcc_addresses),
+        "body": body,
+        "bodyPreview": email.body_preview,
+        "importance": email.importance,
+        "isRead": email.is_read,
+        "hasAttachments": email.has_attachments,
+        "folder": email.folder,
+        "conversationId": email.conversation_id,
+        "internetMessageId": email.internet_message_id,
+        "categories": email.categories,
+        "webLink": email.web_link,
+        "headers": thread_headers_payload(email),
+    });
+    if let Some(fields) = payload.as_object_mut() {
+        fields.retain(|_, value| !value.is_null());
+    }
+    payload
+}
+
+/// `In-Reply-To` and `References` as a `headers` object, which
+/// [`map_archive_record`] reads back; `null` when the email has neither.
+fn thread_headers_payload(email: &Email) -> Value {
+    let mut headers = serde_json::Map::new();
+    if let Some(in_reply_to) = &email.in_reply_to {
+        headers.insert("In-Reply-To".to_string(), json!(in_reply_to));
+    }
+    if !email.references.is_empty() {
+        headers.insert("References".to_string(), json!(email.references.join(" ")));
+    }
+    if headers.is_empty() {
+        Value::Null
+    } else {
+        Value::Object(headers)
+    }
+}
+
+/// Graph-style `attachments` entries (`name`, `contentType`, `size`,
+/// `isInline`); entries without a name are skipped.
+fn map_archive_attachments(payload: &Value, email_id: &str) -> Vec<Attachment> {
+    let record = payload.get("email").unwrap_or(payload);
+    field(record, payload, &["attachments"])
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .filter_map(|(position, value)| {
+            let name = get_str(value, &["name", "filename", "fileName"])?;
+            Some(Attachment {
+                id: format!(
+                    "{email_id}:{}",
+                    get_str(value, &["id"]).unwrap_or_else(|| position.to_string())
+                ),
+                email_id: email_id.to_string(),
+                name: Some(name),
+                content_type: get_str(value, &["contentType", "mimeType"]),
+                size_bytes: ["size", "sizeBytes"]
+                    .iter()
+                    .find_map(|key| value.get(*key))
+                    .and_then(Value::as_i64),
+                is_inline: get_bool(value, &["isInline"]),
+            })
+        })
+        .collect()
+}
+
 fn update_contact_stats(db: &Database, email: &Email) -> Result<()> {
     let mut unique_addresses: HashSet<String> = HashSet::new();
 
@@ -454,7 +646,7 @@ fn parse_first_email_from_header(value: &str) -> Option<String> {
     }
 }
 
-fn parse_addresses_from_header(value: &str) -> Vec<String> {
+pub(crate) fn parse_addresses_from_header(value: &str) -> Vec<String> {
     let email_pattern = Regex::new(r"(?i)<([^>]+@[^>]+)>").expect("compile email header regex");
     let mut addresses: Vec<String> = email_pattern
         .captures_iter(value)
@@ -485,10 +677,10 @@ mod tests {
     use uuid::Uuid;
 
     use crate::connectors::EmailConnector;
-    use crate::db::models::{Account, AccountType};
+    use crate::db::models::{Account, AccountType, Email};
     use crate::indexer::SearchFilters;
 
-    use super::{map_archive_payload, JsonArchiveConnector};
+    use super::{archive_payload, map_archive_payload, JsonArchiveConnector};
 
     fn temp_root() -> PathBuf {
         let root = std::env::temp_dir().join(format!("ess-json-archive-test-{}", Uuid::new_v4()));
@@ -540,6 +732,44 @@ mod tests {
         assert_eq!(email.from_address.as_deref(), Some("alice@example.com"));
     }
 
+    #[test]
+    fn archive_payload_round_trips_through_import_mapping() {
+        let account = sample_account();
+        let original = map_archive_payload(
+            &json!({
+                "id": "msg-2",
+                "subject": "Vendor shortlist",
+                "receivedDateTime": "2026-01-02T09:00:00Z",
+                "from": { "name": "Alice", "address": "alice@example.com" },
+                "toRecipients": [{ "address": "bob@example.com" }],
+                "ccRecipients": [{ "address": "carol@example.com" }],
+                "body": { "contentType": "text", "content": "Three vendors made the cut." },
+                "conversationId": "conv-7",
+                "isRead": true,
+                "categories": ["Research"]
+            }),
+            &account,
+            Path::new("original.json"),
+        )
+        .expect("map original payload");
+
+        let exported = archive_payload(&original);
+        assert!(exported.get("webLink").is_none());
+        let reimported = map_archive_payload(&exported, &account, Path::new("exported.json"))
+            .expect("map exported payload");
+
+        assert_eq!(
+            Email {
+                metadata: None,
+                ..reimported
+            },
+            Email {
+                metadata: None,
+                ..original
+            }
+        );
+    }
+
     #[tokio::test]
     async fn import_directory_dedupes_and_indexes_emails() -> Result<()> {
         let root = temp_root();
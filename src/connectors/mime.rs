@@ -0,0 +1,267 @@
+//! Maps a stored RFC 822 source (see [`crate::raw`]) onto an email.
+//!
+//! Used by `ess reprocess`. Headers and bodies come from the source; every
+//! field the source does not carry keeps its stored value, so provider-only
+//! data such as the received time, read state and preview survives.
+
+use anyhow::{anyhow, Result};
+use chrono::{TimeZone, Utc};
+use mail_parser::{Address, HeaderValue, MessageParser, MimeHeaders, PartType};
+use ring::digest::{digest, SHA256};
+
+use crate::db::models::Email;
+
+/// The email with its header and body fields re-derived from `source`.
+pub fn apply_mime(email: &Email, source: &[u8]) -> Result<Email> {
+    let message = MessageParser::default()
+        .parse(source)
+        .ok_or_else(|| anyhow!("source is not an RFC 822 message"))?;
+    let mut remapped = email.clone();
+
+    if let Some(subject) = message.subject() {
+        remapped.subject = Some(subject.to_string());
+    }
+    if let Some(from) = message.from().and_then(Address::first) {
+        remapped.from_name = from.name().map(str::to_string);
+        remapped.from_address = from.address().map(str::to_string);
+    }
+    for (header, addresses) in [
+        (message.to(), &mut remapped.to_addresses),
+        (message.cc(), &mut remapped.cc_addresses),
+        (message.bcc(), &mut remapped.bcc_addresses),
+    ] {
+        if let Some(header) = header {
+            *addresses = header
+                .iter()
+                .filter_map(|addr| addr.address().map(str::to_string))
+                .collect();
+        }
+    }
+    if let Some(message_id) = message.message_id() {
+        remapped.internet_message_id = Some(format!("<{message_id}>"));
+    }
+    let message_ids = |value: &HeaderValue| -> Vec<String> {
+        value
+            .as_text_list()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|id| format!("<{id}>"))
+            .collect()
+    };
+    remapped.in_reply_to = message_ids(message.in_reply_to()).pop();
+    remapped.references = message_ids(message.references());
+    if let Some(sent) = message
+        .date()
+        .and_then(|date| Utc.timestamp_opt(date.to_timestamp(), 0).single())
+    {
+        remapped.sent_at = Some(sent.to_rfc3339());
+    }
+
+    let html = message
+        .html_body
+        .first()
+        .and_then(|part| message.part(*part))
+        .and_then(|part| match &part.body {
+            PartType::Html(html) => Some(html.to_string()),
+            _ => None,
+        });
+    let text = message
+        .text_body
+        .first()
+        .and_then(|part| message.part(*part))
+        .and_then(|part| match &part.body {
+            PartType::Text(text) => Some(text.to_string()),
+            _ => None,
+        })
+        .or_else(|| html.as_deref().map(html_to_text));
+    if text.is_some() || html.is_some() {
+        remapped.body_text = text.filter(|text| !text.trim().is_empty());
+        remapped.body_html = html.filter(|html| !html.trim().is_empty());
+    }
+    Ok(remapped)
+}
+
+/// A named attachment of a stored source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttachmentDigest {
+    pub name: String,
+    /// Decoded size of the contents in bytes.
+    pub size: usize,
+    /// SHA-256 (hex) of the contents.
+    pub sha256: String,
+}
+
+/// Each named attachment in `source`, in message order. Used by
+/// `ess attachments versions` to tell apart files sent under the same name.
+pub fn attachment_digests(source: &[u8]) -> Result<Vec<AttachmentDigest>> {
+    let message = MessageParser::default()
+        .parse(source)
+        .ok_or_else(|| anyhow!("source is not an RFC 822 message"))?;
+    Ok(message
+        .attachments()
+        .filter_map(|part| {
+            let name = part.attachment_name()?.to_string();
+            let contents = part.contents();
+            let sha256 = digest(&SHA256, contents)
+                .as_ref()
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect();
+            Some(AttachmentDigest {
+                name,
+                size: contents.len(),
+                sha256,
+            })
+        })
+        .collect())
+}
+
+/// The hash for each stored copy of one message's attachment, given the
+/// copies' provider sizes and the source's digests for that name.
+///
+/// The provider lists attachments in its own order, so copies pair with
+/// digests by size. A lone copy takes a lone digest whatever the sizes say.
+/// A copy stays unhashed when no digest has its size, or when digests of
+/// that size disagree on the contents.
+pub fn match_digests(sizes: &[Option<i64>], digests: &[AttachmentDigest]) -> Vec<Option<String>> {
+    if let ([_], [only]) = (sizes, digests) {
+        return vec![Some(only.sha256.clone())];
+    }
+    sizes
+        .iter()
+        .map(|size| {
+            let size = usize::try_from((*size)?).ok()?;
+            let mut same_size = digests.iter().filter(|found| found.size == size);
+            let first = same_size.next()?;
+            same_size
+                .all(|other| other.sha256 == first.sha256)
+                .then(|| first.sha256.clone())
+        })
+        .collect()
+}
+
+fn html_to_text(html: &str) -> String {
+    std::panic::catch_unwind(|| {
+        html2text::from_read(html.as_bytes(), 120)
+            .trim()
+            .to_string()
+    })
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_mime, attachment_digests, match_digests, AttachmentDigest};
+    use crate::db::models::Email;
+
+    #[test]
+    fn takes_headers_and_bodies_from_the_source() {
+        let stored = Email {
+            conversation_id: Some("t1".to_string()),
+            account_id: Some("acc".to_string()),
+            subject: Some("=?UTF-8?B?Q2Fmw6k=?=".to_string()),
+            bcc_addresses: vec!["hidden@example.com".to_string()],
+            body_text: Some("garbled".to_string()),
+            body_preview: Some("provider preview".to_string()),
+            received_at: "2026-02-01T10:00:05Z".to_string(),
+            is_read: Some(true),
+            folder: Some("inbox".to_string()),
+            ..Email::fixture("m1")
+        };
+        let source = b"From: \"Alice Smith\" <alice@example.com>\r\n\
+            To: me@example.com, Bob <bob@example.com>\r\n\
+            Subject: =?UTF-8?B?Q2Fmw6k=?=\r\n\
+            Date: Sun, 01 Feb 2026 10:00:00 +0000\r\n\
+            Message-ID: <abc@example.com>\r\n\
+            MIME-Version: 1.0\r\n\
+            Content-Type: multipart/alternative; boundary=b\r\n\r\n\
+            --b\r\nContent-Type: text/plain; charset=utf-8\r\n\
+            Content-Transfer-Encoding: quoted-printable\r\n\r\n\
+            Caf=C3=A9 at noon?\r\n\
+            --b\r\nContent-Type: text/html; charset=utf-8\r\n\r\n\
+            <p>Caf\xc3\xa9 at noon?</p>\r\n--b--\r\n";
+
+        let remapped = apply_mime(&stored, source).expect("parse source");
+        assert_eq!(remapped.subject.as_deref(), Some("Café"));
+        assert_eq!(remapped.from_name.as_deref(), Some("Alice Smith"));
+        assert_eq!(remapped.from_address.as_deref(), Some("alice@example.com"));
+        assert_eq!(
+            remapped.to_addresses,
+            ["me@example.com".to_string(), "bob@example.com".to_string()]
+        );
+        assert_eq!(remapped.bcc_addresses, stored.bcc_addresses);
+        assert_eq!(
+            remapped.internet_message_id.as_deref(),
+            Some("<abc@example.com>")
+        );
+        assert_eq!(
+            remapped.sent_at.as_deref(),
+            Some("2026-02-01T10:00:00+00:00")
+        );
+        assert_eq!(remapped.body_text.as_deref(), Some("Café at noon?"));
+        assert_eq!(remapped.body_html.as_deref(), Some("<p>Café at noon?</p>"));
+        assert_eq!(remapped.body_preview, stored.body_preview);
+        assert_eq!(remapped.received_at, stored.received_at);
+        assert_eq!(remapped.is_read, Some(true));
+    }
+
+    #[test]
+    fn hashes_decoded_attachment_contents() {
+        let source = b"From: alice@example.com\r\n\
+            Subject: Contract\r\n\
+            MIME-Version: 1.0\r\n\
+            Content-Type: multipart/mixed; boundary=b\r\n\r\n\
+            --b\r\nContent-Type: text/plain\r\n\r\nSee attached.\r\n\
+            --b\r\nContent-Type: application/octet-stream\r\n\
+            Content-Disposition: attachment; filename=\"contract.docx\"\r\n\
+            Content-Transfer-Encoding: base64\r\n\r\n\
+            aGVsbG8=\r\n--b--\r\n";
+
+        let digests = attachment_digests(source).expect("parse source");
+        assert_eq!(
+            digests,
+            [AttachmentDigest {
+                name: "contract.docx".to_string(),
+                size: 5,
+                // SHA-256 of "hello".
+                sha256: "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+                    .to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn pairs_copies_with_digests_by_size() {
+        let digest = |size: usize, sha256: &str| AttachmentDigest {
+            name: "report.pdf".to_string(),
+            size,
+            sha256: sha256.to_string(),
+        };
+        // Message order differs from the order the copies are listed in.
+        let digests = [digest(20, "big"), digest(10, "small")];
+        assert_eq!(
+            match_digests(&[Some(10), Some(20)], &digests),
+            [Some("small".to_string()), Some("big".to_string())]
+        );
+        assert_eq!(
+            match_digests(&[Some(10), Some(30), None], &digests),
+            [Some("small".to_string()), None, None]
+        );
+
+        // Same size, different contents: no way to tell which is which.
+        let clash = [digest(10, "a"), digest(10, "b")];
+        assert_eq!(match_digests(&[Some(10), Some(10)], &clash), [None, None]);
+        let twins = [digest(10, "a"), digest(10, "a")];
+        assert_eq!(
+            match_digests(&[Some(10), Some(10)], &twins),
+            [Some("a".to_string()), Some("a".to_string())]
+        );
+
+        // A lone copy keeps its digest even when the provider counts bytes
+        // differently.
+        assert_eq!(
+            match_digests(&[Some(12)], &[digest(10, "only")]),
+            [Some("only".to_string())]
+        );
+    }
+}
@@ -1,39 +1,191 @@
 use std::path::Path;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use async_trait::async_trait;
 use serde::Serialize;
 
-use crate::db::models::Account;
+use crate::db::models::{Account, Email};
+use crate::db::usage::UsageCounts;
 use crate::db::Database;
 use crate::indexer::EmailIndex;
 
+pub mod conditions;
+pub mod connectivity;
+pub mod error;
 pub mod gmail_api;
 pub mod graph_api;
 pub mod json_archive;
+pub mod mime;
+pub mod progress;
+pub mod quarantine;
+pub mod recording;
+pub mod reprocess;
+pub mod shutdown;
+pub mod usage;
 
+pub use connectivity::OfflineError;
+pub use error::ConnectorError;
 pub use gmail_api::GmailApiConnector;
 pub use graph_api::GraphApiConnector;
 pub use json_archive::JsonArchiveConnector;
+pub use progress::{ProgressReporter, ProgressSink, SyncProgress};
+pub use shutdown::ShutdownSignal;
 
 #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
 pub struct SyncReport {
     pub emails_added: usize,
     pub emails_updated: usize,
-    pub errors: Vec<String>,
+    pub errors: Vec<ConnectorError>,
+    /// True when the sync stopped early because a shutdown was requested.
+    pub interrupted: bool,
+    /// Per-folder breakdown, for connectors that sync folder by folder.
+    pub folders: Vec<FolderSyncReport>,
+}
+
+/// Counts for one folder of a sync. `errors` is the number of entries the
+/// folder contributed to [`SyncReport::errors`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct FolderSyncReport {
+    pub folder: String,
+    pub display_name: String,
+    pub emails_added: usize,
+    pub emails_updated: usize,
+    pub errors: usize,
+    pub interrupted: bool,
+    pub duration_ms: u64,
 }
 
 #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
 pub struct ImportReport {
     pub files_processed: usize,
     pub emails_imported: usize,
+    /// Files that failed to decode or map, kept for `ess quarantine`.
+    pub quarantined: usize,
     pub errors: Vec<String>,
 }
 
+/// A read/flag change requested by `ess mark`. `None` leaves that state
+/// untouched.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct FlagUpdate {
+    pub read: Option<bool>,
+    pub flagged: Option<bool>,
+}
+
+impl FlagUpdate {
+    pub fn is_empty(&self) -> bool {
+        self.read.is_none() && self.flagged.is_none()
+    }
+
+    /// Applies the change to a stored email using the same representation the
+    /// connectors produce on sync (`flag_status` of `flagged`/`notFlagged`).
+    pub fn apply_to(&self, email: &mut Email) {
+        if let Some(read) = self.read {
+            email.is_read = Some(read);
+        }
+        match self.flagged {
+            Some(true) => email.flag_status = Some("flagged".to_string()),
+            Some(false) if email.flag_status.is_some() => {
+                email.flag_status = Some("notFlagged".to_string())
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A provider-side move requested by `ess archive` / `ess delete`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageAction {
+    Archive,
+    /// Moves the message to the provider's trash; nothing is purged.
+    Delete,
+}
+
+impl MessageAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Archive => "archive",
+            Self::Delete => "delete",
+        }
+    }
+}
+
+/// Where a message ended up after a [`MessageAction`], in the same terms the
+/// connector records on sync. Graph assigns a new id when a message moves.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MovedMessage {
+    pub id: String,
+    pub folder: String,
+}
+
 #[async_trait(?Send)]
 pub trait EmailConnector: Send + Sync {
     fn name(&self) -> &str;
 
+    /// Base URLs this connector talks to during sync. Used by the pre-sync
+    /// connectivity probe; local-only connectors return nothing.
+    fn remote_endpoints(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Verifies that the credentials needed to sync `account` are available
+    /// locally, without contacting the provider.
+    fn check_credentials(&self, _account: &Account) -> Result<()> {
+        Ok(())
+    }
+
+    /// Whether [`EmailConnector::update_flags`] pushes changes to the
+    /// provider. Connectors without write access only change local state.
+    fn supports_flag_updates(&self) -> bool {
+        false
+    }
+
+    /// Pushes a read/flag change for `email` to the provider. Needs write
+    /// scopes on the account credentials.
+    async fn update_flags(
+        &self,
+        _db: &Database,
+        _account: &Account,
+        _email: &Email,
+        _update: FlagUpdate,
+    ) -> Result<()> {
+        bail!("{} connector does not support flag updates", self.name())
+    }
+
+    /// Whether [`EmailConnector::apply_message_action`] is available.
+    fn supports_message_actions(&self) -> bool {
+        false
+    }
+
+    /// Archives or trashes `email` at the provider. Needs write scopes on the
+    /// account credentials.
+    async fn apply_message_action(
+        &self,
+        _db: &Database,
+        _account: &Account,
+        _email: &Email,
+        action: MessageAction,
+    ) -> Result<MovedMessage> {
+        bail!(
+            "{} connector does not support {} on the provider",
+            self.name(),
+            action.as_str()
+        )
+    }
+
+    /// Whether the next [`EmailConnector::sync`] of `account` lists the
+    /// whole mailbox rather than the changes since a stored cursor.
+    fn needs_full_sync(&self, _db: &Database, _account: &Account) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// API requests made and bytes downloaded since the last call, for
+    /// `sync_usage`. Local-only connectors count nothing.
+    fn take_usage(&self) -> UsageCounts {
+        UsageCounts::default()
+    }
+
     async fn sync(
         &self,
         db: &Database,
@@ -83,6 +235,43 @@ impl Default for ConnectorRegistry {
     }
 }
 
+/// The emails whose raw source a sync fetches now, out of `message_ids`,
+/// the ones it added. While `defer` is set they are remembered under
+/// `raw_deferred:<account>` in `sync_state` instead; the first sync without
+/// it fetches the remembered ones that are still stored as well.
+pub(crate) fn raw_fetches_due(
+    db: &Database,
+    account: &Account,
+    message_ids: &[String],
+    defer: bool,
+) -> Result<Vec<String>> {
+    let key = format!("raw_deferred:{}", account.account_id);
+    let mut deferred: Vec<String> = db
+        .get_sync_state(&key)?
+        .and_then(|state| state.value)
+        .and_then(|value| serde_json::from_str(&value).ok())
+        .unwrap_or_default();
+    if defer {
+        if !message_ids.is_empty() {
+            deferred.extend(message_ids.iter().cloned());
+            db.set_sync_state(&key, &serde_json::to_string(&deferred)?)?;
+        }
+        return Ok(Vec::new());
+    }
+    if deferred.is_empty() {
+        return Ok(message_ids.to_vec());
+    }
+    db.delete_sync_state(&key)?;
+    let mut due = Vec::with_capacity(deferred.len() + message_ids.len());
+    for id in deferred {
+        if db.get_email(&id)?.is_some() {
+            due.push(id);
+        }
+    }
+    due.extend(message_ids.iter().cloned());
+    Ok(due)
+}
+
 /// Shared mutex for tests that mutate environment variables (token cache key).
 /// Both `graph_api::tests` and `gmail_api::tests` must acquire this before
 /// touching `ESS_TOKEN_CACHE_KEY` to avoid poisoning each other's state.
@@ -95,8 +284,11 @@ mod tests {
     use anyhow::Result;
     use async_trait::async_trait;
 
-    use super::{ConnectorRegistry, EmailConnector, ImportReport, SyncReport};
-    use crate::db::models::Account;
+    use super::{
+        raw_fetches_due, ConnectorError, ConnectorRegistry, EmailConnector, FlagUpdate,
+        FolderSyncReport, ImportReport, SyncReport,
+    };
+    use crate::db::models::{Account, AccountType, Email};
     use crate::db::Database;
     use crate::indexer::EmailIndex;
 
@@ -134,12 +326,106 @@ mod tests {
         assert_eq!(ImportReport::default().files_processed, 0);
     }
 
+    #[test]
+    fn sync_report_serializes_typed_errors_and_folders() {
+        let report = SyncReport {
+            emails_added: 2,
+            errors: vec![ConnectorError::transient("folder=inbox: timed out")],
+            folders: vec![FolderSyncReport {
+                folder: "inbox".to_string(),
+                display_name: "Inbox".to_string(),
+                emails_added: 2,
+                errors: 1,
+                duration_ms: 40,
+                ..FolderSyncReport::default()
+            }],
+            ..SyncReport::default()
+        };
+
+        let value = serde_json::to_value(&report).expect("serialize sync report");
+        assert_eq!(value["errors"][0]["kind"], "transient");
+        assert_eq!(value["errors"][0]["message"], "folder=inbox: timed out");
+        assert_eq!(value["folders"][0]["folder"], "inbox");
+        assert_eq!(value["folders"][0]["duration_ms"], 40);
+    }
+
     #[test]
     fn connector_trait_is_object_safe() {
         let connector: Box<dyn EmailConnector> = Box::new(DummyConnector);
         assert_eq!(connector.name(), "dummy");
     }
 
+    #[test]
+    fn flag_update_mirrors_sync_representation() {
+        let mut email = Email {
+            is_read: Some(false),
+            ..Email::fixture("msg-1")
+        };
+
+        FlagUpdate {
+            read: None,
+            flagged: Some(false),
+        }
+        .apply_to(&mut email);
+        assert_eq!(email.flag_status, None);
+
+        FlagUpdate {
+            read: Some(true),
+            flagged: Some(true),
+        }
+        .apply_to(&mut email);
+        assert_eq!(email.is_read, Some(true));
+        assert_eq!(email.flag_status.as_deref(), Some("flagged"));
+
+        FlagUpdate {
+            read: None,
+            flagged: Some(false),
+        }
+        .apply_to(&mut email);
+        assert_eq!(email.flag_status.as_deref(), Some("notFlagged"));
+        assert!(FlagUpdate::default().is_empty());
+    }
+
+    #[test]
+    fn raw_fetches_wait_while_deferred() {
+        let path = std::env::temp_dir().join(format!("ess-raw-defer-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        let account = Account {
+            account_id: "work".to_string(),
+            email_address: "me@example.com".to_string(),
+            display_name: None,
+            tenant_id: None,
+            account_type: AccountType::Professional,
+            enabled: true,
+            last_sync: None,
+            config: None,
+        };
+        db.insert_account(&account).expect("insert account");
+        let mut email: Email = serde_json::from_value(serde_json::json!({
+            "id": "m1", "internet_message_id": null, "conversation_id": null,
+            "account_id": "work", "subject": null, "from_address": null, "from_name": null,
+            "to_addresses": [], "cc_addresses": [], "bcc_addresses": [], "body_text": null,
+            "body_html": null, "body_preview": null, "received_at": "2026-01-01T00:00:00Z",
+            "sent_at": null, "importance": null, "is_read": null, "has_attachments": null,
+            "folder": null, "categories": [], "flag_status": null, "web_link": null,
+            "metadata": null
+        }))
+        .expect("email");
+        db.insert_email(&email).expect("insert m1");
+        email.id = "m2".to_string();
+        db.insert_email(&email).expect("insert m2");
+        let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+
+        let due = raw_fetches_due(&db, &account, &ids(&["m1", "deleted"]), true).expect("defer");
+        assert!(due.is_empty());
+        let due = raw_fetches_due(&db, &account, &ids(&["m2"]), false).expect("fetch");
+        assert_eq!(due, ["m1", "m2"]);
+        let due = raw_fetches_due(&db, &account, &[], false).expect("fetch again");
+        assert!(due.is_empty());
+
+        let _ = std::fs::remove_file(path);
+    }
+
     #[test]
     fn registry_registers_and_finds_connectors() {
         let mut registry = ConnectorRegistry::new();
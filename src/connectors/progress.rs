@@ -0,0 +1,294 @@
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use super::SyncReport;
+
+/// Structured progress events emitted by connectors while syncing.
+///
+/// A sync is a sequence of stages (folder discovery, per-folder enumeration,
+/// Gmail ID listing, batch fetches, ...). Each stage reports pages as they are
+/// committed; `total` is set when the connector knows how much work remains so
+/// renderers can draw a bounded bar and estimate time to completion.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SyncProgress {
+    StageStarted {
+        account_id: String,
+        stage: String,
+        folder: Option<String>,
+        total: Option<u64>,
+    },
+    PageCompleted {
+        account_id: String,
+        stage: String,
+        folder: Option<String>,
+        page: u64,
+        page_size: usize,
+        processed: u64,
+        total: Option<u64>,
+        added: usize,
+        updated: usize,
+        errors: usize,
+    },
+    StageFinished {
+        account_id: String,
+        stage: String,
+        folder: Option<String>,
+        added: usize,
+        updated: usize,
+        errors: usize,
+    },
+    Notice {
+        account_id: String,
+        folder: Option<String>,
+        message: String,
+    },
+}
+
+impl SyncProgress {
+    pub fn account_id(&self) -> &str {
+        match self {
+            Self::StageStarted { account_id, .. }
+            | Self::PageCompleted { account_id, .. }
+            | Self::StageFinished { account_id, .. }
+            | Self::Notice { account_id, .. } => account_id,
+        }
+    }
+}
+
+/// Receives [`SyncProgress`] events. Closures implement this directly, so a
+/// channel sender can be adapted with `move |event| { let _ = tx.send(event.clone()); }`.
+pub trait ProgressReporter: Send + Sync {
+    fn report(&self, event: &SyncProgress);
+}
+
+impl<F> ProgressReporter for F
+where
+    F: Fn(&SyncProgress) + Send + Sync,
+{
+    fn report(&self, event: &SyncProgress) {
+        self(event)
+    }
+}
+
+/// Cloneable handle connectors hold to publish progress. Defaults to
+/// [`StderrProgress`].
+#[derive(Clone)]
+pub struct ProgressSink(Arc<dyn ProgressReporter>);
+
+impl ProgressSink {
+    pub fn new(reporter: impl ProgressReporter + 'static) -> Self {
+        Self(Arc::new(reporter))
+    }
+
+    pub fn emit(&self, event: SyncProgress) {
+        self.0.report(&event);
+    }
+}
+
+impl Default for ProgressSink {
+    fn default() -> Self {
+        Self::new(StderrProgress)
+    }
+}
+
+impl std::fmt::Debug for ProgressSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ProgressSink")
+    }
+}
+
+/// Tracks one stage of a sync and emits its start, page and finish events.
+#[derive(Debug)]
+pub struct StageProgress {
+    sink: ProgressSink,
+    account_id: String,
+    stage: String,
+    folder: Option<String>,
+    total: Option<u64>,
+    page: u64,
+    processed: u64,
+}
+
+impl StageProgress {
+    pub fn start(
+        sink: &ProgressSink,
+        account_id: &str,
+        stage: &str,
+        folder: Option<&str>,
+        total: Option<u64>,
+    ) -> Self {
+        let progress = Self {
+            sink: sink.clone(),
+            account_id: account_id.to_string(),
+            stage: stage.to_string(),
+            folder: folder.map(str::to_string),
+            total,
+            page: 0,
+            processed: 0,
+        };
+        progress.sink.emit(SyncProgress::StageStarted {
+            account_id: progress.account_id.clone(),
+            stage: progress.stage.clone(),
+            folder: progress.folder.clone(),
+            total,
+        });
+        progress
+    }
+
+    /// Records a committed page of `page_size` items; counts come from the
+    /// stage's running report.
+    pub fn page(&mut self, page_size: usize, report: &SyncReport) {
+        self.page += 1;
+        self.processed += page_size as u64;
+        self.sink.emit(SyncProgress::PageCompleted {
+            account_id: self.account_id.clone(),
+            stage: self.stage.clone(),
+            folder: self.folder.clone(),
+            page: self.page,
+            page_size,
+            processed: self.processed,
+            total: self.total,
+            added: report.emails_added,
+            updated: report.emails_updated,
+            errors: report.errors.len(),
+        });
+    }
+
+    pub fn finish(self, report: &SyncReport) {
+        self.sink.emit(SyncProgress::StageFinished {
+            account_id: self.account_id,
+            stage: self.stage,
+            folder: self.folder,
+            added: report.emails_added,
+            updated: report.emails_updated,
+            errors: report.errors.len(),
+        });
+    }
+}
+
+/// Default reporter: one plain-text line per event on stderr.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StderrProgress;
+
+impl ProgressReporter for StderrProgress {
+    fn report(&self, event: &SyncProgress) {
+        eprintln!("{}", describe(event));
+    }
+}
+
+/// Discards every event.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoProgress;
+
+impl ProgressReporter for NoProgress {
+    fn report(&self, _event: &SyncProgress) {}
+}
+
+/// Human-readable single-line rendering of an event.
+pub fn describe(event: &SyncProgress) -> String {
+    match event {
+        SyncProgress::StageStarted {
+            account_id,
+            stage,
+            folder,
+            total,
+        } => {
+            let total = total.map(|total| format!(" ({total} items)")).unwrap_or_default();
+            format!("sync {account_id}{}: {stage} started{total}", folder_suffix(folder))
+        }
+        SyncProgress::PageCompleted {
+            account_id,
+            stage,
+            folder,
+            page,
+            page_size,
+            processed,
+            total,
+            added,
+            updated,
+            errors,
+        } => {
+            let processed = match total {
+                Some(total) => format!("{processed}/{total}"),
+                None => processed.to_string(),
+            };
+            format!(
+                "sync {account_id}{}: {stage} page {page} ({page_size} items, {processed} processed), added={added} updated={updated} errors={errors}",
+                folder_suffix(folder)
+            )
+        }
+        SyncProgress::StageFinished {
+            account_id,
+            stage,
+            folder,
+            added,
+            updated,
+            errors,
+        } => format!(
+            "sync {account_id}{}: {stage} finished, added={added} updated={updated} errors={errors}",
+            folder_suffix(folder)
+        ),
+        SyncProgress::Notice {
+            account_id,
+            folder,
+            message,
+        } => format!("sync {account_id}{}: {message}", folder_suffix(folder)),
+    }
+}
+
+fn folder_suffix(folder: &Option<String>) -> String {
+    folder
+        .as_deref()
+        .map(|folder| format!(" folder={folder}"))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::{describe, ProgressSink, SyncProgress};
+
+    #[test]
+    fn closures_receive_events() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        let progress = ProgressSink::new(move |event: &SyncProgress| {
+            sink.lock().unwrap().push(event.account_id().to_string());
+        });
+
+        progress.emit(SyncProgress::Notice {
+            account_id: "user@example.com".to_string(),
+            folder: None,
+            message: "hello".to_string(),
+        });
+
+        assert_eq!(*seen.lock().unwrap(), vec!["user@example.com".to_string()]);
+    }
+
+    #[test]
+    fn events_serialize_with_tag_and_render_as_text() {
+        let event = SyncProgress::PageCompleted {
+            account_id: "user@example.com".to_string(),
+            stage: "fetch_messages".to_string(),
+            folder: Some("inbox".to_string()),
+            page: 2,
+            page_size: 25,
+            processed: 50,
+            total: Some(100),
+            added: 48,
+            updated: 2,
+            errors: 0,
+        };
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["event"], "page_completed");
+        assert_eq!(json["processed"], 50);
+
+        let text = describe(&event);
+        assert!(text.contains("folder=inbox"));
+        assert!(text.contains("50/100 processed"));
+    }
+}
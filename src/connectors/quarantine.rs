@@ -0,0 +1,204 @@
+//! Messages a connector fetched but could not decode or map. Instead of
+//! ending up as one line in a sync or import report, each is stored with
+//! its payload and error in the `quarantine` table (see
+//! [`crate::db::quarantine`]); `ess quarantine retry` feeds them back
+//! through the connector once the parser handles them.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+
+use crate::connectors::{
+    json_archive, ConnectorError, GmailApiConnector, GraphApiConnector, SyncReport,
+};
+use crate::db::models::Account;
+use crate::db::quarantine::{QuarantineRecord, QuarantinedMessage};
+use crate::db::Database;
+use crate::indexer::EmailIndex;
+
+/// A message whose payload did not decode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnparsedMessage {
+    pub message_id: Option<String>,
+    pub payload: String,
+    pub error: String,
+}
+
+/// Quarantines messages that did not decode during a sync, reporting each
+/// as a permanent error that names its quarantine entry.
+pub fn quarantine_unparsed(
+    db: &Database,
+    account: &Account,
+    connector: &str,
+    source: Option<&str>,
+    unparsed: &[UnparsedMessage],
+    report: &mut SyncReport,
+) {
+    for message in unparsed {
+        let quarantined = db.quarantine_message(&QuarantineRecord {
+            account_id: &account.account_id,
+            connector,
+            message_id: message.message_id.as_deref(),
+            source,
+            payload: &message.payload,
+            error: &message.error,
+        });
+        let outcome = match quarantined {
+            Ok(id) => format!("quarantined as #{id}"),
+            Err(error) => format!("not quarantined: {error}"),
+        };
+        report.errors.push(ConnectorError::permanent(format!(
+            "id={} {} ({outcome})",
+            message.message_id.as_deref().unwrap_or("<unknown>"),
+            message.error
+        )));
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RetryReport {
+    /// Entries stored as emails and released from quarantine.
+    pub released: Vec<i64>,
+    /// Entries whose email was already stored; released as well.
+    pub already_stored: Vec<i64>,
+    /// Entries that failed again, with the new error.
+    pub failed: Vec<RetryFailure>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RetryFailure {
+    pub id: i64,
+    pub error: String,
+}
+
+/// Runs quarantined messages through their connector's decoding and
+/// mapping again. Entries that now map are stored, indexed and released;
+/// the rest keep their payload with the new error and a bumped retry count.
+pub fn retry(
+    db: &Database,
+    index: &mut EmailIndex,
+    entries: &[QuarantinedMessage],
+    keep_payloads: bool,
+) -> Result<RetryReport> {
+    let mut report = RetryReport::default();
+    let mut accounts = HashMap::<String, Option<Account>>::new();
+    for entry in entries {
+        if !accounts.contains_key(&entry.account_id) {
+            let account = db.get_account(&entry.account_id)?;
+            accounts.insert(entry.account_id.clone(), account);
+        }
+        let outcome = match &accounts[&entry.account_id] {
+            Some(account) => retry_entry(db, index, account, entry, keep_payloads),
+            None => Err(anyhow!("account {} no longer exists", entry.account_id)),
+        };
+        match outcome {
+            Ok(stored) => {
+                db.release_quarantined(entry.id)
+                    .with_context(|| format!("release quarantined message #{}", entry.id))?;
+                if stored {
+                    report.released.push(entry.id);
+                } else {
+                    report.already_stored.push(entry.id);
+                }
+            }
+            Err(error) => {
+                let error = format!("{error:#}");
+                db.record_quarantine_retry(entry.id, &error)
+                    .with_context(|| {
+                        format!("record retry of quarantined message #{}", entry.id)
+                    })?;
+                report.failed.push(RetryFailure {
+                    id: entry.id,
+                    error,
+                });
+            }
+        }
+    }
+    index
+        .commit()
+        .context("commit retried messages to the index")?;
+    Ok(report)
+}
+
+/// Whether the email was newly stored.
+fn retry_entry(
+    db: &Database,
+    index: &mut EmailIndex,
+    account: &Account,
+    entry: &QuarantinedMessage,
+    keep_payloads: bool,
+) -> Result<bool> {
+    match entry.connector.as_str() {
+        "json_archive" => {
+            json_archive::import_quarantined(db, index, account, entry, keep_payloads)
+        }
+        "graph_api" => GraphApiConnector::new().apply_quarantined(db, index, account, entry),
+        "gmail_api" => GmailApiConnector::new().apply_quarantined(db, index, account, entry),
+        other => Err(anyhow!("no connector named {other}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::retry;
+    use crate::db::models::{Account, AccountType};
+    use crate::db::quarantine::QuarantineRecord;
+    use crate::db::Database;
+    use crate::indexer::EmailIndex;
+
+    #[test]
+    fn retry_releases_entries_that_map_and_keeps_the_rest() {
+        let root = std::env::temp_dir().join(format!("ess-quarantine-{}", uuid::Uuid::new_v4()));
+        let db = Database::open(&root.join("ess.db")).expect("open db");
+        let mut index = EmailIndex::open(&root.join("index")).expect("open index");
+        db.insert_account(&Account {
+            account_id: "acc".to_string(),
+            email_address: "me@example.com".to_string(),
+            display_name: None,
+            tenant_id: None,
+            account_type: AccountType::Personal,
+            enabled: true,
+            last_sync: None,
+            config: None,
+        })
+        .expect("insert account");
+
+        let record = QuarantineRecord {
+            account_id: "acc",
+            connector: "gmail_api",
+            message_id: Some("m1"),
+            source: None,
+            payload: r#"{"id": "m1", "threadId": "t1", "labelIds": ["INBOX"], "snippet": "hi",
+                "internalDate": "1767261600000",
+                "payload": {"mimeType": "text/plain", "headers": [{"name": "Subject", "value": "Fixed"}]}}"#,
+            error: "decode gmail message: missing field",
+        };
+        let fixed = db.quarantine_message(&record).expect("quarantine");
+        let broken = db
+            .quarantine_message(&QuarantineRecord {
+                connector: "json_archive",
+                message_id: None,
+                source: Some("broken.json"),
+                payload: "{\"subject\": \"no id\"}",
+                error: "missing id/graph_id",
+                ..record
+            })
+            .expect("quarantine");
+
+        let entries = db.quarantined(None, None).expect("list");
+        let report = retry(&db, &mut index, &entries, false).expect("retry");
+        assert_eq!(report.released, vec![fixed]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].id, broken);
+        assert!(report.failed[0].error.contains("missing id/graph_id"));
+
+        let stored = db.get_email("m1").expect("get email").expect("email");
+        assert_eq!(stored.subject.as_deref(), Some("Fixed"));
+        let remaining = db.quarantined(None, None).expect("list");
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].retry_count, 1);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,421 @@
+//! Recorded HTTP fixtures for the Graph and Gmail connectors.
+//!
+//! `ess sync --record <dir>` runs the sync through [`FixtureRecorder`], a
+//! loopback proxy in front of the provider APIs that writes every exchange
+//! to an [`HttpFixture`] file. The integration tests in
+//! `tests/connector_http_fixtures.rs` replay such files from a mock server,
+//! so connector behaviour against real responses is covered offline.
+//!
+//! Paths are recorded under a prefix naming the API (`/graph`, `/gmail`,
+//! `/gmail-batch`), and absolute API URLs inside response bodies (Graph's
+//! `@odata.nextLink` and `@odata.deltaLink`) as `{{server}}/<prefix>/...`,
+//! so a replaying server at any address stands in for all of them. OAuth
+//! token requests bypass the proxy and are never recorded, and no request
+//! headers are kept. Response bodies are kept as sent and hold real mail:
+//! trim and anonymise a recording before committing it.
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Context, Result};
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{HeaderMap, Method, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::Router;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+use crate::connectors::{gmail_api, graph_api};
+use crate::env;
+
+/// Stands for the replaying server's base URL in recorded bodies.
+pub const SERVER_PLACEHOLDER: &str = "{{server}}";
+
+/// Request headers passed on to the provider.
+const FORWARDED_REQUEST_HEADERS: [&str; 4] = ["authorization", "accept", "content-type", "prefer"];
+/// Response headers the connectors read, and so the ones recorded.
+const RECORDED_RESPONSE_HEADERS: [&str; 2] = ["content-type", "retry-after"];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HttpFixture {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    /// In the order the connector made the requests.
+    pub exchanges: Vec<RecordedExchange>,
+}
+
+/// One request and the response it got. A replayed request matches an
+/// exchange when the method and path are equal and it carries every query
+/// parameter the exchange lists, so hand-written fixtures can leave out the
+/// parameters that do not tell pages apart.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedExchange {
+    pub method: String,
+    /// Request path, starting with the API prefix.
+    pub path: String,
+    /// Query string as sent, without the leading `?`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// JSON bodies as JSON; anything else, such as a batch response, as a
+    /// string.
+    #[serde(default)]
+    pub body: Value,
+}
+
+impl HttpFixture {
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("read HTTP fixture {}", path.display()))?;
+        serde_json::from_str(&raw)
+            .with_context(|| format!("decode HTTP fixture {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut raw = serde_json::to_string_pretty(self).context("encode HTTP fixture")?;
+        raw.push('\n');
+        std::fs::write(path, raw).with_context(|| format!("write HTTP fixture {}", path.display()))
+    }
+}
+
+impl RecordedExchange {
+    /// Decoded query parameters.
+    pub fn query_pairs(&self) -> Vec<(String, String)> {
+        let query = self.query.as_deref().unwrap_or_default();
+        reqwest::Url::parse(&format!("http://fixture/?{query}"))
+            .map(|url| url.query_pairs().into_owned().collect())
+            .unwrap_or_default()
+    }
+
+    /// The response body as a server at `server` sends it.
+    pub fn body_for(&self, server: &str) -> String {
+        let body = match &self.body {
+            Value::Null => String::new(),
+            Value::String(text) => text.clone(),
+            json => json.to_string(),
+        };
+        body.replace(SERVER_PLACEHOLDER, server)
+    }
+}
+
+/// A provider API the recorder proxies, and the variable that points the
+/// connector at it.
+#[derive(Debug, Clone)]
+pub struct Upstream {
+    pub prefix: &'static str,
+    pub env: &'static str,
+    pub base: String,
+}
+
+impl Upstream {
+    /// The Graph and Gmail APIs, at their configured base URLs.
+    pub fn providers() -> Vec<Self> {
+        vec![
+            Self {
+                prefix: "graph",
+                env: env::GRAPH_API_BASE,
+                base: graph_api::graph_api_base(),
+            },
+            Self {
+                prefix: "gmail",
+                env: env::GMAIL_API_BASE,
+                base: gmail_api::gmail_api_base(),
+            },
+            Self {
+                prefix: "gmail-batch",
+                env: env::GMAIL_BATCH_URL,
+                base: gmail_api::gmail_batch_url(),
+            },
+        ]
+    }
+}
+
+struct RecorderState {
+    client: Client,
+    url: String,
+    upstreams: Vec<Upstream>,
+    exchanges: Mutex<Vec<RecordedExchange>>,
+}
+
+pub struct FixtureRecorder {
+    state: Arc<RecorderState>,
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<Result<()>>,
+    /// Variables replaced by [`FixtureRecorder::redirect_connectors`], with
+    /// the values to put back.
+    redirected: Vec<(&'static str, Option<std::ffi::OsString>)>,
+}
+
+impl FixtureRecorder {
+    /// Starts the proxy on a loopback port.
+    pub async fn start(upstreams: Vec<Upstream>) -> Result<Self> {
+        let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .await
+            .context("bind fixture recorder")?;
+        let url = format!(
+            "http://{}",
+            listener
+                .local_addr()
+                .context("read fixture recorder address")?
+        );
+        let state = Arc::new(RecorderState {
+            client: Client::new(),
+            url,
+            upstreams,
+            exchanges: Mutex::new(Vec::new()),
+        });
+        let router = Router::new()
+            .fallback(forward)
+            .with_state(Arc::clone(&state));
+        let (stop, stopped) = oneshot::channel::<()>();
+        let task = tokio::spawn(async move {
+            axum::serve(listener, router)
+                .with_graceful_shutdown(async {
+                    let _ = stopped.await;
+                })
+                .await
+                .context("serve fixture recorder")
+        });
+        Ok(Self {
+            state,
+            stop,
+            task,
+            redirected: Vec::new(),
+        })
+    }
+
+    /// Base URL the proxy serves `upstream` under.
+    pub fn url(&self, upstream: &str) -> String {
+        format!("{}/{upstream}", self.state.url)
+    }
+
+    /// Points the connectors at the proxy through their base URL variables,
+    /// until [`FixtureRecorder::finish`].
+    pub fn redirect_connectors(mut self) -> Self {
+        for upstream in &self.state.upstreams {
+            self.redirected
+                .push((upstream.env, std::env::var_os(upstream.env)));
+            std::env::set_var(upstream.env, self.url(upstream.prefix));
+        }
+        self
+    }
+
+    /// Stops the proxy and returns what it recorded.
+    pub async fn finish(self) -> Result<HttpFixture> {
+        for (name, previous) in &self.redirected {
+            match previous {
+                Some(value) => std::env::set_var(name, value),
+                None => std::env::remove_var(name),
+            }
+        }
+        let _ = self.stop.send(());
+        self.task
+            .await
+            .map_err(|error| anyhow!("fixture recorder stopped: {error}"))??;
+        let exchanges = std::mem::take(&mut *lock(&self.state.exchanges));
+        Ok(HttpFixture {
+            description: String::new(),
+            exchanges,
+        })
+    }
+}
+
+async fn forward(
+    State(state): State<Arc<RecorderState>>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    match forward_exchange(&state, method, &uri, &headers, body).await {
+        Ok(response) => response,
+        Err(error) => (StatusCode::BAD_GATEWAY, format!("{error:#}")).into_response(),
+    }
+}
+
+async fn forward_exchange(
+    state: &RecorderState,
+    method: Method,
+    uri: &Uri,
+    headers: &HeaderMap,
+    body: Bytes,
+) -> Result<Response> {
+    let path = uri.path();
+    let (prefix, rest) = path
+        .trim_start_matches('/')
+        .split_once('/')
+        .map_or((path.trim_start_matches('/'), ""), |(prefix, rest)| {
+            (prefix, rest)
+        });
+    let upstream = state
+        .upstreams
+        .iter()
+        .find(|upstream| upstream.prefix == prefix)
+        .ok_or_else(|| anyhow!("no upstream API under /{prefix}"))?;
+    let mut target = upstream.base.clone();
+    if !rest.is_empty() {
+        target.push('/');
+        target.push_str(rest);
+    }
+    if let Some(query) = uri.query() {
+        target.push('?');
+        target.push_str(query);
+    }
+
+    let mut request = state.client.request(method.clone(), &target).body(body);
+    for name in FORWARDED_REQUEST_HEADERS {
+        if let Some(value) = headers.get(name) {
+            request = request.header(name, value);
+        }
+    }
+    let response = request
+        .send()
+        .await
+        .with_context(|| format!("forward {method} {target}"))?;
+    let status = response.status();
+    let mut recorded_headers = BTreeMap::new();
+    for name in RECORDED_RESPONSE_HEADERS {
+        if let Some(value) = response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+        {
+            recorded_headers.insert(name.to_string(), value.to_string());
+        }
+    }
+    let bytes = response
+        .bytes()
+        .await
+        .with_context(|| format!("read response of {method} {target}"))?;
+
+    // Links back into an API have to lead to the proxy as well.
+    let (live, recorded) = match String::from_utf8(bytes.to_vec()) {
+        Ok(mut text) => {
+            for upstream in &state.upstreams {
+                text = text.replace(
+                    &upstream.base,
+                    &format!("{}/{}", state.url, upstream.prefix),
+                );
+            }
+            let recorded = text.replace(&state.url, SERVER_PLACEHOLDER);
+            (Bytes::from(text), recorded)
+        }
+        Err(_) => (bytes.clone(), String::from_utf8_lossy(&bytes).into_owned()),
+    };
+    let is_json = recorded_headers
+        .get("content-type")
+        .is_some_and(|content_type| content_type.contains("json"));
+    let body = match serde_json::from_str::<Value>(&recorded) {
+        Ok(json) if is_json => json,
+        _ => Value::String(recorded),
+    };
+    lock(&state.exchanges).push(RecordedExchange {
+        method: method.to_string(),
+        path: path.to_string(),
+        query: uri.query().map(str::to_string),
+        status: status.as_u16(),
+        headers: recorded_headers.clone(),
+        body,
+    });
+
+    let mut reply = (status, live).into_response();
+    for name in RECORDED_RESPONSE_HEADERS {
+        if let Some(value) = recorded_headers
+            .get(name)
+            .and_then(|value| value.parse().ok())
+        {
+            reply.headers_mut().insert(name, value);
+        }
+    }
+    Ok(reply)
+}
+
+fn lock(
+    exchanges: &Mutex<Vec<RecordedExchange>>,
+) -> std::sync::MutexGuard<'_, Vec<RecordedExchange>> {
+    exchanges
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::routing::get;
+    use axum::{Json, Router};
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+
+    use super::{FixtureRecorder, Upstream, SERVER_PLACEHOLDER};
+    use crate::env;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn records_exchanges_and_rewrites_api_links() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let upstream = format!("http://{}/v1.0", listener.local_addr().expect("addr"));
+        let next_link = format!("{upstream}/users/me/messages?$skiptoken=2");
+        let router = Router::new().route(
+            "/v1.0/users/me/messages",
+            get(move || async move { Json(json!({"value": [], "@odata.nextLink": next_link})) }),
+        );
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let recorder = FixtureRecorder::start(vec![Upstream {
+            prefix: "graph",
+            env: env::GRAPH_API_BASE,
+            base: upstream,
+        }])
+        .await
+        .expect("start recorder");
+        let client = reqwest::Client::builder()
+            .no_proxy()
+            .build()
+            .expect("client");
+        let live: Value = client
+            .get(format!(
+                "{}/users/me/messages?$top=2",
+                recorder.url("graph")
+            ))
+            .bearer_auth("token")
+            .send()
+            .await
+            .expect("request")
+            .json()
+            .await
+            .expect("body");
+        assert_eq!(
+            live["@odata.nextLink"],
+            format!("{}/users/me/messages?$skiptoken=2", recorder.url("graph"))
+        );
+        let missing = client
+            .get(format!("{}/nowhere", recorder.url("other")))
+            .send()
+            .await
+            .expect("request");
+        assert_eq!(missing.status(), 502);
+
+        let fixture = recorder.finish().await.expect("finish");
+        assert_eq!(fixture.exchanges.len(), 1);
+        let exchange = &fixture.exchanges[0];
+        assert_eq!(exchange.path, "/graph/users/me/messages");
+        assert_eq!(
+            exchange.query_pairs(),
+            [("$top".to_string(), "2".to_string())]
+        );
+        assert_eq!(
+            exchange.body["@odata.nextLink"],
+            format!("{SERVER_PLACEHOLDER}/graph/users/me/messages?$skiptoken=2")
+        );
+        assert!(exchange
+            .body_for("http://mock")
+            .contains("http://mock/graph/users/me/messages?$skiptoken=2"));
+    }
+}
@@ -0,0 +1,250 @@
+//! `ess reprocess`: re-derives stored emails from the payloads kept for
+//! them, so parser fixes reach mail that was synced or imported earlier
+//! without fetching it from the provider again.
+//!
+//! An email's raw RFC 822 source (kept with `[storage] raw_messages`, see
+//! [`crate::raw`]) is parsed by [`mime::apply_mime`]. Without one, the JSON
+//! that `ess import` kept in `metadata.archive_payload` is mapped again by
+//! the JSON archive connector. Emails with neither are skipped. The thread,
+//! read state, flag, folder and categories always keep their stored values:
+//! they follow the provider and `ess mark`, while a payload only records
+//! them as they were when it was fetched. The received time is kept too, as
+//! emails are walked in received order.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::connectors::{json_archive, mime};
+use crate::db::models::Email;
+use crate::db::{Database, EmailKeyset, EmailSearchFilters};
+use crate::indexer::EmailIndex;
+use crate::raw::RawStore;
+
+/// Emails read from SQLite, and index documents committed, per batch.
+const BATCH_SIZE: usize = 500;
+
+#[derive(Debug, Clone, Default)]
+pub struct ReprocessOptions {
+    pub account: Option<String>,
+    /// Received on or after this day.
+    pub since: Option<NaiveDate>,
+    /// Received on or before this day.
+    pub until: Option<NaiveDate>,
+    /// Count what would change without writing anything.
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ReprocessReport {
+    pub examined: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    /// Emails with no stored payload to map again.
+    pub skipped: usize,
+    pub errors: Vec<String>,
+    pub dry_run: bool,
+}
+
+pub fn reprocess(
+    db: &Database,
+    index: &mut EmailIndex,
+    raw: &RawStore,
+    options: &ReprocessOptions,
+) -> Result<ReprocessReport> {
+    let mut report = ReprocessReport {
+        dry_run: options.dry_run,
+        ..ReprocessReport::default()
+    };
+    let mut account_types = HashMap::<String, String>::new();
+    let mut after = None;
+    loop {
+        let batch = db.search_emails(EmailSearchFilters {
+            query: None,
+            account_id: options.account.clone(),
+            account_type: None,
+            folder: None,
+            from_address: None,
+            from_domain: None,
+            since: options.since,
+            until: options.until,
+            tag: None,
+            needs_reply_before: None,
+            limit: BATCH_SIZE,
+            offset: 0,
+            after: after.take(),
+        })?;
+        let Some(last) = batch.last() else {
+            break;
+        };
+        after = Some(EmailKeyset::of(last));
+        let exhausted = batch.len() < BATCH_SIZE;
+
+        let mut indexed = false;
+        for email in &batch {
+            report.examined += 1;
+            let remapped = match remap(email, raw) {
+                Ok(Some(remapped)) => remapped,
+                Ok(None) => {
+                    report.skipped += 1;
+                    continue;
+                }
+                Err(error) => {
+                    report.errors.push(format!("{}: {error:#}", email.id));
+                    continue;
+                }
+            };
+            if remapped == *email {
+                report.unchanged += 1;
+                continue;
+            }
+            report.updated += 1;
+            if options.dry_run {
+                continue;
+            }
+            db.update_email_content(&remapped)
+                .with_context(|| format!("update reprocessed email {}", email.id))?;
+            let account_id = remapped.account_id.clone().unwrap_or_default();
+            if !account_types.contains_key(&account_id) {
+                let account_type = db
+                    .get_account(&account_id)?
+                    .map(|account| account.account_type.to_string())
+                    .unwrap_or_default();
+                account_types.insert(account_id.clone(), account_type);
+            }
+            index
+                .update_email(&remapped, &account_types[&account_id])
+                .with_context(|| format!("index reprocessed email {}", email.id))?;
+            indexed = true;
+        }
+        if indexed {
+            index
+                .commit()
+                .context("commit reprocessed emails to the index")?;
+        }
+        if exhausted {
+            break;
+        }
+    }
+    Ok(report)
+}
+
+/// The email as mapped from its stored payload, or `None` without one.
+fn remap(email: &Email, raw: &RawStore) -> Result<Option<Email>> {
+    let mut remapped = if let Some(source) = raw
+        .get(&email.id)
+        .with_context(|| format!("read raw message {}", raw.path(&email.id).display()))?
+    {
+        mime::apply_mime(email, &source)?
+    } else if let Some(payload) = email.meta("archive_payload") {
+        json_archive::remap_archive_payload(email, payload)?
+    } else {
+        return Ok(None);
+    };
+    remapped.conversation_id = email.conversation_id.clone();
+    remapped.received_at = email.received_at.clone();
+    remapped.is_read = email.is_read;
+    remapped.flag_status = email.flag_status.clone();
+    remapped.folder = email.folder.clone();
+    remapped.categories = email.categories.clone();
+    Ok(Some(remapped))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{reprocess, ReprocessOptions};
+    use crate::db::models::{Account, AccountType, Email};
+    use crate::db::Database;
+    use crate::indexer::{EmailIndex, SearchFilters};
+    use crate::raw::RawStore;
+
+    fn email(id: &str) -> Email {
+        Email {
+            conversation_id: Some("t1".to_string()),
+            account_id: Some("acc".to_string()),
+            subject: Some("=?UTF-8?Q?Caf=C3=A9?=".to_string()),
+            from_address: Some("alice@example.com".to_string()),
+            received_at: "2026-02-01T10:00:00Z".to_string(),
+            is_read: Some(true),
+            folder: Some("archive".to_string()),
+            ..Email::fixture(id)
+        }
+    }
+
+    #[test]
+    fn remaps_raw_sources_and_archive_payloads_in_place() {
+        let root = std::env::temp_dir().join(format!("ess-reprocess-{}", uuid::Uuid::new_v4()));
+        let db = Database::open(&root.join("ess.db")).expect("open db");
+        let mut index = EmailIndex::open(&root.join("index")).expect("open index");
+        let raw = RawStore::new(root.join("raw"));
+        db.insert_account(&Account {
+            account_id: "acc".to_string(),
+            email_address: "me@example.com".to_string(),
+            display_name: None,
+            tenant_id: None,
+            account_type: AccountType::Professional,
+            enabled: true,
+            last_sync: None,
+            config: None,
+        })
+        .expect("insert account");
+
+        let synced = email("m1");
+        raw.put(
+            "m1",
+            b"From: Alice <alice@example.com>\r\nSubject: =?UTF-8?Q?Caf=C3=A9?=\r\n\r\nSee you there\r\n",
+        )
+        .expect("store raw message");
+        let mut imported = email("m2");
+        imported.set_meta(
+            "archive_payload",
+            json!({"id": "m2", "subject": "Café", "isRead": false, "folder": "inbox"}),
+        );
+        for email in [&synced, &imported, &email("m3")] {
+            db.insert_email(email).expect("insert email");
+            index.add_email(email, "professional").expect("index email");
+        }
+
+        let options = ReprocessOptions {
+            dry_run: true,
+            ..ReprocessOptions::default()
+        };
+        let report = reprocess(&db, &mut index, &raw, &options).expect("dry run");
+        assert_eq!((report.examined, report.updated, report.skipped), (3, 2, 1));
+        let stored = db.get_email("m1").expect("get email").expect("email");
+        assert_eq!(stored.subject, synced.subject);
+
+        let report =
+            reprocess(&db, &mut index, &raw, &ReprocessOptions::default()).expect("reprocess");
+        assert_eq!((report.updated, report.unchanged), (2, 0));
+        assert!(report.errors.is_empty());
+        let stored = db.get_email("m1").expect("get email").expect("email");
+        assert_eq!(stored.subject.as_deref(), Some("Café"));
+        assert_eq!(stored.from_name.as_deref(), Some("Alice"));
+        assert_eq!(stored.body_text.as_deref(), Some("See you there\r\n"));
+        let stored = db.get_email("m2").expect("get email").expect("email");
+        assert_eq!(stored.subject.as_deref(), Some("Café"));
+        assert_eq!(stored.is_read, Some(true));
+        assert_eq!(stored.folder.as_deref(), Some("archive"));
+        assert_eq!(
+            index
+                .search("see you there", &SearchFilters::default(), 10)
+                .expect("search")
+                .len(),
+            1
+        );
+
+        let report = reprocess(&db, &mut index, &raw, &ReprocessOptions::default())
+            .expect("reprocess again");
+        assert_eq!(
+            (report.updated, report.unchanged, report.skipped),
+            (0, 2, 1)
+        );
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
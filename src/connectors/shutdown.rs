@@ -0,0 +1,64 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::Notify;
+
+/// Cooperative stop request shared between the CLI signal handler and the
+/// connectors. Connectors poll [`ShutdownSignal::is_requested`] at page
+/// boundaries so an interrupted sync always leaves the index committed and the
+/// latest resumable cursor persisted.
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownSignal {
+    requested: Arc<AtomicBool>,
+    notify: Arc<Notify>,
+}
+
+impl ShutdownSignal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once a shutdown has been requested.
+    pub async fn requested(&self) {
+        loop {
+            let notified = self.notify.notified();
+            if self.is_requested() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::ShutdownSignal;
+
+    #[tokio::test]
+    async fn clones_observe_the_same_request() {
+        let signal = ShutdownSignal::new();
+        let observer = signal.clone();
+        assert!(!observer.is_requested());
+
+        let waiter = tokio::spawn(async move { observer.requested().await });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        signal.request();
+
+        tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .expect("waiter should wake after request")
+            .expect("waiter task should not panic");
+        assert!(signal.is_requested());
+    }
+}
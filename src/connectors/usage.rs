@@ -0,0 +1,40 @@
+//! Counts of the API requests a connector makes and the bytes it
+//! downloads, taken after each sync or provider write and added to
+//! `sync_usage`.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::db::usage::UsageCounts;
+
+/// Clones count into the same totals.
+#[derive(Debug, Clone, Default)]
+pub struct UsageMeter {
+    counts: Arc<Counts>,
+}
+
+#[derive(Debug, Default)]
+struct Counts {
+    requests: AtomicU64,
+    bytes: AtomicU64,
+}
+
+impl UsageMeter {
+    /// One request sent, whatever its response.
+    pub fn request(&self) {
+        self.counts.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A response body read, error bodies included.
+    pub fn received(&self, bytes: usize) {
+        self.counts.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// The counts since the last call.
+    pub fn take(&self) -> UsageCounts {
+        UsageCounts {
+            requests: self.counts.requests.swap(0, Ordering::Relaxed),
+            bytes: self.counts.bytes.swap(0, Ordering::Relaxed),
+        }
+    }
+}
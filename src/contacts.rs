@@ -0,0 +1,612 @@
+//! Local contact enrichment for `ess contacts enrich --local`, and address
+//! completion for `ess complete-address`.
+//!
+//! Contacts are collected from message headers, so they start out as bare
+//! addresses. Enrichment fills in what the stored mail already says about
+//! them: the display name each contact most often sends under, and the
+//! company and phone number from the signatures of their newest messages.
+//! Fields that already hold a value are never overwritten.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::Serialize;
+
+use crate::db::models::Contact;
+use crate::db::{ContactDetails, Database};
+
+/// Newest messages per contact whose signatures are read.
+const SIGNATURE_SAMPLE: usize = 5;
+/// Lines after the sign-off or `--` that count as the signature.
+const MAX_SIGNATURE_LINES: usize = 8;
+/// Longest company name taken from a signature, in words.
+const MAX_COMPANY_WORDS: usize = 6;
+/// Most-written-to contacts read for a completion before ranking.
+const COMPLETION_CANDIDATES: usize = 200;
+/// Days after which the recency boost of a contact halves.
+const RECENCY_HALF_LIFE_DAYS: f64 = 30.0;
+
+const VALEDICTIONS: &[&str] = &[
+    "all the best",
+    "best",
+    "best regards",
+    "best wishes",
+    "cheers",
+    "kind regards",
+    "many thanks",
+    "regards",
+    "sincerely",
+    "talk soon",
+    "thank you",
+    "thanks",
+    "thanks again",
+    "warm regards",
+    "warmly",
+    "yours sincerely",
+];
+
+const COMPANY_SUFFIXES: &[&str] = &[
+    "ag",
+    "b.v.",
+    "bv",
+    "co",
+    "co.",
+    "corp",
+    "corp.",
+    "corporation",
+    "gmbh",
+    "group",
+    "holdings",
+    "inc",
+    "inc.",
+    "labs",
+    "limited",
+    "llc",
+    "llp",
+    "ltd",
+    "ltd.",
+    "plc",
+    "pty",
+    "s.a.",
+    "srl",
+];
+
+const TITLE_WORDS: &[&str] = &[
+    "accountant",
+    "administrator",
+    "advisor",
+    "analyst",
+    "architect",
+    "assistant",
+    "associate",
+    "attorney",
+    "ceo",
+    "cfo",
+    "co-founder",
+    "consultant",
+    "controller",
+    "coo",
+    "coordinator",
+    "counsel",
+    "cto",
+    "designer",
+    "developer",
+    "director",
+    "engineer",
+    "executive",
+    "founder",
+    "head",
+    "lead",
+    "manager",
+    "officer",
+    "partner",
+    "president",
+    "recruiter",
+    "representative",
+    "specialist",
+    "vp",
+];
+
+static PHONE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\+?\(?\d[\d ().\-]{5,}\d").expect("compile phone regex"));
+static DATE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b\d{4}[-/.]\d{1,2}[-/.]\d{1,2}\b|\b\d{1,2}[-/.]\d{1,2}[-/.]\d{2,4}\b")
+        .expect("compile date regex")
+});
+
+/// What [`enrich_local`] filled in.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct EnrichReport {
+    /// Contacts with at least one field filled.
+    pub contacts: usize,
+    pub names: usize,
+    pub companies: usize,
+    pub phones: usize,
+}
+
+/// Company and phone number read from one signature.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SignatureDetails {
+    pub company: Option<String>,
+    pub phone: Option<String>,
+}
+
+/// Fills empty display names, companies and phone numbers of every contact
+/// from the mail they sent.
+pub fn enrich_local(db: &Database) -> Result<EnrichReport> {
+    let contacts = db
+        .get_contacts(None)?
+        .into_iter()
+        .map(|contact| (contact.email_address.clone(), contact))
+        .collect::<HashMap<_, _>>();
+    let empty =
+        |value: &Option<String>| value.as_deref().is_none_or(|value| value.trim().is_empty());
+    let mut found: HashMap<String, ContactDetails> = HashMap::new();
+
+    for (address, name) in db.sender_display_names()? {
+        let Some(contact) = contacts.get(&address) else {
+            continue;
+        };
+        if let (true, Some(name)) = (empty(&contact.display_name), clean_display_name(&name)) {
+            details(&mut found, &address).display_name = Some(name);
+        }
+    }
+
+    db.for_each_sender_bodies(SIGNATURE_SAMPLE, |address, bodies| {
+        let Some(contact) = contacts.get(address) else {
+            return;
+        };
+        let (needs_company, needs_phone) = (empty(&contact.company), empty(&contact.phone));
+        if !needs_company && !needs_phone {
+            return;
+        }
+        let name = contact.display_name.clone().or_else(|| {
+            found
+                .get(address)
+                .and_then(|found| found.display_name.clone())
+        });
+        let signatures = bodies
+            .iter()
+            .map(|body| signature_details(&signature_lines(body), name.as_deref()))
+            .collect::<Vec<_>>();
+        let company = needs_company
+            .then(|| most_common(signatures.iter().filter_map(|sig| sig.company.as_deref())))
+            .flatten();
+        let phone = needs_phone
+            .then(|| most_common(signatures.iter().filter_map(|sig| sig.phone.as_deref())))
+            .flatten();
+        if company.is_some() || phone.is_some() {
+            let entry = details(&mut found, address);
+            entry.company = company;
+            entry.phone = phone;
+        }
+    })?;
+
+    let found = found.into_values().collect::<Vec<_>>();
+    db.store_contact_details(&found)?;
+    Ok(EnrichReport {
+        contacts: found.len(),
+        names: found.iter().filter(|c| c.display_name.is_some()).count(),
+        companies: found.iter().filter(|c| c.company.is_some()).count(),
+        phones: found.iter().filter(|c| c.phone.is_some()).count(),
+    })
+}
+
+fn details<'a>(
+    found: &'a mut HashMap<String, ContactDetails>,
+    address: &str,
+) -> &'a mut ContactDetails {
+    found
+        .entry(address.to_string())
+        .or_insert_with(|| ContactDetails {
+            email_address: address.to_string(),
+            ..ContactDetails::default()
+        })
+}
+
+/// The value seen most often; ties go to the one seen first, i.e. in the
+/// newest message.
+fn most_common<'a>(values: impl Iterator<Item = &'a str>) -> Option<String> {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for value in values {
+        match counts.iter_mut().find(|(seen, _)| *seen == value) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((value, 1)),
+        }
+    }
+    let best = counts.iter().map(|(_, count)| *count).max()?;
+    counts
+        .into_iter()
+        .find(|(_, count)| *count == best)
+        .map(|(value, _)| value.to_string())
+}
+
+/// The name without surrounding quotes, or `None` when nothing is left.
+fn clean_display_name(name: &str) -> Option<String> {
+    let name = name
+        .trim()
+        .trim_matches(|ch| ch == '"' || ch == '\'')
+        .trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// The signature at the end of the sender's own text: the lines after a
+/// `--` separator, or else after the last sign-off such as "Best," or
+/// "Thanks,". Quoted replies and forwarded messages are cut off first.
+/// Empty when neither marker is found.
+pub fn signature_lines(body: &str) -> Vec<&str> {
+    let own = body
+        .lines()
+        .map(str::trim)
+        .take_while(|line| !is_reply_marker(line))
+        .filter(|line| !line.starts_with('>'))
+        .collect::<Vec<_>>();
+    let start = own
+        .iter()
+        .position(|line| *line == "--")
+        .or_else(|| own.iter().rposition(|line| is_valediction(line)));
+    match start {
+        Some(start) => own[start + 1..]
+            .iter()
+            .copied()
+            .filter(|line| !line.is_empty())
+            .take(MAX_SIGNATURE_LINES)
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+fn is_reply_marker(line: &str) -> bool {
+    let lower = line.to_lowercase();
+    lower.starts_with("-----original message")
+        || lower.starts_with("---------- forwarded message")
+        || lower.starts_with("from: ")
+        || lower.starts_with("sent from my ")
+        || (lower.starts_with("on ") && lower.ends_with("wrote:"))
+}
+
+fn is_valediction(line: &str) -> bool {
+    let sign_off = line.trim_end_matches([',', '!', '.']).trim().to_lowercase();
+    VALEDICTIONS.contains(&sign_off.as_str())
+}
+
+/// The company and phone number in a signature's lines. `name` is the
+/// sender's display name, whose line is skipped.
+pub fn signature_details(lines: &[&str], name: Option<&str>) -> SignatureDetails {
+    let mut details = SignatureDetails::default();
+    for line in lines {
+        if name.is_some_and(|name| line.eq_ignore_ascii_case(name)) {
+            continue;
+        }
+        if details.phone.is_none() {
+            details.phone = phone_in(line);
+        }
+        if details.company.is_none() {
+            details.company = company_in(line);
+        }
+    }
+    details
+}
+
+/// A phone number of 7 to 15 digits, as written. Fax lines and dates are
+/// skipped.
+fn phone_in(line: &str) -> Option<String> {
+    if line.to_lowercase().contains("fax") {
+        return None;
+    }
+    PHONE
+        .find_iter(line)
+        .map(|found| found.as_str().trim())
+        .find(|candidate| {
+            let digits = candidate.chars().filter(char::is_ascii_digit).count();
+            (7..=15).contains(&digits) && !DATE.is_match(candidate)
+        })
+        .map(str::to_string)
+}
+
+/// A company named on the line: a part ending in a legal suffix
+/// ("Northwind Traders Ltd"), or what follows a job title ("Head of
+/// Finance | Northwind", "CFO at Northwind", "Director, Northwind").
+fn company_in(line: &str) -> Option<String> {
+    let lower = line.to_lowercase();
+    if line.contains('@') || lower.contains("http") || lower.contains("www.") {
+        return None;
+    }
+    let plausible = |part: &str| {
+        let words = part.split_whitespace().count();
+        (1..=MAX_COMPANY_WORDS).contains(&words)
+            && !part.chars().any(|ch| ch.is_ascii_digit())
+            && !has_title(part)
+    };
+
+    let parts = line
+        .split(['|', '•', '·'])
+        .map(|part| part.trim().trim_end_matches(','))
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>();
+    if let Some(part) = parts.iter().find(|part| {
+        part.split_whitespace()
+            .last()
+            .is_some_and(|word| COMPANY_SUFFIXES.contains(&word.to_lowercase().as_str()))
+            && part.split_whitespace().count() > 1
+            && plausible(part)
+    }) {
+        return Some(part.to_string());
+    }
+    if let [title, .., company] = parts.as_slice() {
+        if has_title(title) && plausible(company) {
+            return Some(company.to_string());
+        }
+    }
+    for separator in [" at ", ", "] {
+        if let Some((title, company)) = line.split_once(separator) {
+            let company = company.trim();
+            if has_title(title) && plausible(company) {
+                return Some(company.to_string());
+            }
+        }
+    }
+    None
+}
+
+fn has_title(text: &str) -> bool {
+    text.split(|ch: char| !ch.is_alphanumeric() && ch != '-')
+        .any(|word| TITLE_WORDS.contains(&word.to_lowercase().as_str()))
+}
+
+/// A contact offered for a partly typed address.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AddressMatch {
+    pub email_address: String,
+    pub display_name: Option<String>,
+    /// `Name <address>`, ready for a To or Cc line.
+    pub formatted: String,
+    pub message_count: i64,
+    pub last_seen: Option<String>,
+    pub score: f64,
+}
+
+/// Up to `limit` contacts whose address or name contains `fragment`, best
+/// first. An empty fragment ranks every contact.
+pub fn complete_address(db: &Database, fragment: &str, limit: usize) -> Result<Vec<AddressMatch>> {
+    let fragment = fragment.trim();
+    let candidates = db.contacts_matching(fragment, COMPLETION_CANDIDATES.max(limit))?;
+    Ok(rank_addresses(candidates, fragment, Utc::now(), limit))
+}
+
+/// Orders contacts by how well they match times how often and how recently
+/// they were seen. An address that starts with the fragment outranks one
+/// where a word of the name or address does, which outranks one that merely
+/// contains it; the message count counts logarithmically, and a contact seen
+/// today gets up to twice the weight of one not seen in months.
+fn rank_addresses(
+    contacts: Vec<Contact>,
+    fragment: &str,
+    now: DateTime<Utc>,
+    limit: usize,
+) -> Vec<AddressMatch> {
+    let fragment = fragment.to_lowercase();
+    let mut matches = contacts
+        .into_iter()
+        .filter_map(|contact| {
+            let quality = match_quality(&contact, &fragment)?;
+            let frequency = (1.0 + contact.message_count.max(0) as f64).ln();
+            let recency = contact
+                .last_seen
+                .as_deref()
+                .and_then(|seen| DateTime::parse_from_rfc3339(seen).ok())
+                .map_or(0.0, |seen| {
+                    let days =
+                        (now - seen.with_timezone(&Utc)).num_seconds().max(0) as f64 / 86_400.0;
+                    0.5f64.powf(days / RECENCY_HALF_LIFE_DAYS)
+                });
+            let display_name = contact.display_name.filter(|name| {
+                !name.trim().is_empty() && !name.eq_ignore_ascii_case(&contact.email_address)
+            });
+            Some(AddressMatch {
+                formatted: format_address(display_name.as_deref(), &contact.email_address),
+                email_address: contact.email_address,
+                display_name,
+                message_count: contact.message_count,
+                last_seen: contact.last_seen,
+                score: quality * frequency.max(0.1) * (1.0 + recency),
+            })
+        })
+        .collect::<Vec<_>>();
+    matches.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then(b.message_count.cmp(&a.message_count))
+            .then_with(|| a.email_address.cmp(&b.email_address))
+    });
+    matches.truncate(limit);
+    matches
+}
+
+/// 4 when the address starts with the fragment, 2 when a word of the name
+/// or address does, 1 when either merely contains it.
+fn match_quality(contact: &Contact, fragment: &str) -> Option<f64> {
+    let address = contact.email_address.to_lowercase();
+    let name = contact
+        .display_name
+        .as_deref()
+        .unwrap_or_default()
+        .to_lowercase();
+    if address.starts_with(fragment) {
+        return Some(4.0);
+    }
+    let mut words = name
+        .split_whitespace()
+        .chain(address.split(['.', '_', '-', '+', '@']));
+    if words.any(|word| {
+        word.trim_matches(|ch: char| !ch.is_alphanumeric())
+            .starts_with(fragment)
+    }) {
+        return Some(2.0);
+    }
+    (address.contains(fragment) || name.contains(fragment)).then_some(1.0)
+}
+
+/// `Name <address>`, quoting the name when it holds characters that would
+/// otherwise split or break the header.
+fn format_address(name: Option<&str>, address: &str) -> String {
+    match name.map(str::trim) {
+        None => address.to_string(),
+        Some(name)
+            if name.contains([',', ';', ':', '<', '>', '@', '"', '(', ')', '[', ']', '\\']) =>
+        {
+            format!(
+                "\"{}\" <{address}>",
+                name.replace('\\', "\\\\").replace('"', "\\\"")
+            )
+        }
+        Some(name) => format!("{name} <{address}>"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Utc};
+
+    use super::{enrich_local, rank_addresses, signature_details, signature_lines, EnrichReport};
+    use crate::db::models::{Contact, Email};
+    use crate::db::Database;
+
+    #[test]
+    fn signatures_yield_company_and_phone() {
+        let body = "Hi Sam,\n\nNumbers attached.\n\nBest regards,\nAlice Smith\n\
+                    Head of Finance | Northwind Traders\nMobile: +1 (555) 010-0199\n\
+                    Sent on 2026-02-01\n\nOn Mon, Feb 2, 2026 at 9:00 AM Sam wrote:\n\
+                    > Thanks,\n> Sam\n> Globex Inc.";
+        let lines = signature_lines(body);
+        assert_eq!(lines[0], "Alice Smith");
+        assert!(!lines.iter().any(|line| line.contains("Globex")));
+        let details = signature_details(&lines, Some("Alice Smith"));
+        assert_eq!(details.company.as_deref(), Some("Northwind Traders"));
+        assert_eq!(details.phone.as_deref(), Some("+1 (555) 010-0199"));
+
+        let dashed = signature_details(
+            &signature_lines("See you there.\n--\nBob\nAcme Widgets Ltd\nt. 020 7946 0000"),
+            None,
+        );
+        assert_eq!(dashed.company.as_deref(), Some("Acme Widgets Ltd"));
+        assert_eq!(dashed.phone.as_deref(), Some("020 7946 0000"));
+        assert_eq!(
+            signature_details(&signature_lines("CFO at Initech"), None),
+            Default::default(),
+            "no sign-off, no signature"
+        );
+        assert_eq!(
+            signature_details(&["Carol", "CFO at Initech"], None)
+                .company
+                .as_deref(),
+            Some("Initech")
+        );
+    }
+
+    #[test]
+    fn enrichment_fills_only_empty_fields() {
+        let path = std::env::temp_dir().join(format!("ess-enrich-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        db.insert_email(&Email {
+            subject: Some("Q3 numbers".to_string()),
+            from_address: Some("alice@northwind.example".to_string()),
+            from_name: Some("\"Alice Smith\"".to_string()),
+            to_addresses: vec!["sam@example.com".to_string()],
+            body_text: Some(
+                "Attached.\n\nThanks,\nAlice\nNorthwind Traders Inc.\n+44 20 7946 0000".to_string(),
+            ),
+            received_at: "2026-02-01T10:00:00Z".to_string(),
+            ..Email::fixture("m1")
+        })
+        .expect("insert email");
+        for address in ["alice@northwind.example", "sam@example.com"] {
+            db.update_contact_stats(address).expect("add contact");
+        }
+
+        let report = enrich_local(&db).expect("enrich");
+        assert_eq!(
+            report,
+            EnrichReport {
+                contacts: 1,
+                names: 1,
+                companies: 1,
+                phones: 1,
+            }
+        );
+        let alice = db.get_contacts(Some("alice")).expect("contacts").remove(0);
+        assert_eq!(alice.display_name.as_deref(), Some("Alice Smith"));
+        assert_eq!(alice.company.as_deref(), Some("Northwind Traders Inc."));
+        assert_eq!(alice.phone.as_deref(), Some("+44 20 7946 0000"));
+        assert_eq!(
+            enrich_local(&db).expect("enrich again"),
+            EnrichReport::default()
+        );
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn completions_weigh_match_frequency_and_recency() {
+        let now = "2026-03-01T00:00:00Z"
+            .parse::<DateTime<Utc>>()
+            .expect("now");
+        let contact = |address: &str, name: Option<&str>, message_count, last_seen: &str| Contact {
+            email_address: address.to_string(),
+            display_name: name.map(str::to_string),
+            company: None,
+            phone: None,
+            attio_person_id: None,
+            attio_company_id: None,
+            message_count,
+            first_seen: None,
+            last_seen: Some(last_seen.to_string()),
+        };
+        let contacts = vec![
+            contact("malice@example.com", None, 50, "2026-02-28T00:00:00Z"),
+            contact(
+                "bob@example.com",
+                Some("Alistair Bob"),
+                2,
+                "2026-02-28T00:00:00Z",
+            ),
+            contact(
+                "alicia@example.org",
+                Some("Smith, Alicia"),
+                3,
+                "2025-06-01T00:00:00Z",
+            ),
+            contact(
+                "alice@example.com",
+                Some("Alice Smith"),
+                3,
+                "2026-02-28T00:00:00Z",
+            ),
+        ];
+        let ranked = rank_addresses(contacts.clone(), "Ali", now, 10);
+        let order = ranked
+            .iter()
+            .map(|found| found.email_address.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            order,
+            [
+                "alice@example.com",
+                "malice@example.com",
+                "alicia@example.org",
+                "bob@example.com"
+            ],
+            "a recent prefix match first; many recent messages outweigh a better but stale match"
+        );
+        assert_eq!(ranked[0].formatted, "Alice Smith <alice@example.com>");
+        assert_eq!(ranked[1].formatted, "malice@example.com");
+        assert_eq!(
+            ranked[2].formatted,
+            "\"Smith, Alicia\" <alicia@example.org>"
+        );
+        assert_eq!(rank_addresses(contacts, "ali", now, 1).len(), 1);
+    }
+}
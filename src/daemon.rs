@@ -0,0 +1,561 @@
+//! The socket a running `ess sync --watch` serves CLI commands on.
+//!
+//! `ess search` and `ess list` first try `$XDG_DATA_HOME/ess/ess.sock`.
+//! When a daemon answers, it runs the command with the database and index
+//! it already holds open and sends back the output, so the client never
+//! opens them and never waits on the sync's locks. Without a daemon the
+//! command runs in-process as before; `--connect` makes that an error and
+//! `--no-connect` skips the socket.
+//!
+//! Each connection carries one request line and one response line, both
+//! JSON.
+//!
+//! `ess sync --watch --listen <addr>` also takes the same requests over
+//! HTTP (`POST /v1/cli`), so a server can keep the mail store while laptops
+//! with `[remote] url` in their config run these commands against it. Off
+//! loopback every request needs `Authorization: Bearer <token>`, with the
+//! token from `ESS_REMOTE_TOKEN` or the `remote-token` keychain entry;
+//! without one, requests from a non-local `Origin` are refused. Bodies must
+//! be sent as `application/json`.
+//! `GET /openapi.json` describes the endpoint and needs no token.
+
+use std::io;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::Router;
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpListener;
+
+use crate::env;
+use crate::mcp::http::{has_bearer_token, is_json_request, is_local_origin};
+use crate::output::schema::{openapi_document, OPENAPI_PATH};
+use crate::output::TableStyle;
+use crate::paths;
+use crate::secrets::keychain::{Keychain, SecretStore};
+
+pub const SOCKET_FILE: &str = "ess.sock";
+pub const HTTP_PATH: &str = "/v1/cli";
+/// Keychain entry holding the token when `ESS_REMOTE_TOKEN` is unset.
+pub const TOKEN_ENTRY: &str = "remote-token";
+/// How long a client waits for the server's answer without `--timeout`.
+pub const REMOTE_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long a client waits to connect, at most.
+const REMOTE_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a client waits for the local daemon without `--timeout` before
+/// running the command itself.
+pub const SOCKET_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// `$XDG_DATA_HOME/ess/ess.sock`.
+pub fn socket_path() -> io::Result<PathBuf> {
+    Ok(paths::data_dir()?.join(SOCKET_FILE))
+}
+
+/// A command line for the daemon to run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientRequest {
+    /// The client's version; a daemon of another version declines, since it
+    /// may parse the arguments differently.
+    pub version: String,
+    /// The arguments after `ess`.
+    pub args: Vec<String>,
+    /// How the client's terminal wants tables drawn.
+    pub style: TableStyle,
+}
+
+impl ClientRequest {
+    pub fn new(args: Vec<String>, style: TableStyle) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            args,
+            style,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ClientResponse {
+    /// The command's standard output.
+    Done { output: String },
+    /// What the command wrote before it failed, and the error it failed
+    /// with.
+    Failed { output: String, error: String },
+    /// The daemon does not run this command; the client runs it itself.
+    Declined { reason: String },
+}
+
+/// `ESS_REMOTE_TOKEN`, else the keychain's `remote-token` entry.
+pub fn remote_token() -> Option<String> {
+    env::var(env::REMOTE_TOKEN).or_else(|| Keychain.get(TOKEN_ENTRY))
+}
+
+/// Listens for HTTP clients on `addr`. Refuses to serve off loopback
+/// without a token.
+pub async fn bind_http(addr: SocketAddr, token: Option<&str>) -> Result<TcpListener> {
+    if token.is_none() && !addr.ip().is_loopback() {
+        return Err(anyhow!(
+            "serving ess clients on {addr} needs a token (--token or {}); without one, bind to a loopback address",
+            env::REMOTE_TOKEN
+        ));
+    }
+    TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("listen for ess clients on {addr}"))
+}
+
+/// Answers `POST /v1/cli` until the task is dropped.
+pub async fn serve_http<F, Fut>(listener: TcpListener, token: Option<String>, handler: F)
+where
+    F: Fn(ClientRequest) -> Fut + Clone + Send + Sync + 'static,
+    Fut: std::future::Future<Output = ClientResponse> + Send,
+{
+    let token = Arc::new(token);
+    let router = Router::new()
+        .route(
+            HTTP_PATH,
+            post(move |headers: HeaderMap, body: String| async move {
+                answer_http(token.as_deref(), &headers, &body, handler).await
+            }),
+        )
+        .route(
+            OPENAPI_PATH,
+            get(|| async { axum::Json(openapi_document()) }),
+        );
+    if let Err(error) = axum::serve(listener, router).await {
+        tracing::warn!("serving ess clients over HTTP: {error}");
+    }
+}
+
+async fn answer_http<F, Fut>(
+    token: Option<&str>,
+    headers: &HeaderMap,
+    body: &str,
+    handler: F,
+) -> Response
+where
+    F: Fn(ClientRequest) -> Fut,
+    Fut: std::future::Future<Output = ClientResponse>,
+{
+    match token {
+        Some(token) => {
+            if !has_bearer_token(headers, token) {
+                return (
+                    StatusCode::UNAUTHORIZED,
+                    [(header::WWW_AUTHENTICATE, "Bearer")],
+                    "missing or wrong bearer token",
+                )
+                    .into_response();
+            }
+        }
+        // Without a token only the origin keeps a web page in the user's
+        // browser from driving the server, as in `ess mcp --http`.
+        None => {
+            let origin = headers
+                .get(header::ORIGIN)
+                .and_then(|value| value.to_str().ok());
+            if origin.is_some_and(|origin| !is_local_origin(origin)) {
+                return (StatusCode::FORBIDDEN, "cross-origin request refused").into_response();
+            }
+        }
+    }
+    if !is_json_request(headers) {
+        return (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "expected Content-Type: application/json",
+        )
+            .into_response();
+    }
+    let response = match serde_json::from_str::<ClientRequest>(body) {
+        Ok(request) => handler(request).await,
+        Err(error) => ClientResponse::Declined {
+            reason: format!("invalid request: {error}"),
+        },
+    };
+    axum::Json(response).into_response()
+}
+
+/// Sends `request` to the server at `url` (the base URL, without
+/// `/v1/cli`), giving up when it has not answered within `timeout`.
+pub async fn request_http(
+    url: &str,
+    token: Option<&str>,
+    timeout: Duration,
+    request: &ClientRequest,
+) -> Result<ClientResponse> {
+    let endpoint = format!("{}{HTTP_PATH}", url.trim_end_matches('/'));
+    let client = reqwest::Client::builder()
+        .connect_timeout(REMOTE_CONNECT_TIMEOUT.min(timeout))
+        .timeout(timeout)
+        .build()
+        .context("build the HTTP client")?;
+    let mut builder = client.post(&endpoint).json(request);
+    if let Some(token) = token {
+        builder = builder.bearer_auth(token);
+    }
+    let response = match builder.send().await {
+        Ok(response) => response,
+        Err(error) if error.is_timeout() => {
+            return Err(anyhow!(
+                "the ess server at {url} did not answer within {}s",
+                timeout.as_secs()
+            ))
+        }
+        Err(error) => {
+            return Err(error).with_context(|| format!("reach the ess server at {url}"));
+        }
+    };
+    if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+        return Err(anyhow!(
+            "the ess server at {url} refused the token; set {} or the `{TOKEN_ENTRY}` keychain entry",
+            env::REMOTE_TOKEN
+        ));
+    }
+    let response = response
+        .error_for_status()
+        .with_context(|| format!("ask the ess server at {url}"))?;
+    response
+        .json()
+        .await
+        .with_context(|| format!("read the answer of the ess server at {url}"))
+}
+
+#[cfg(unix)]
+pub use self::unix::{bind, is_serving, request, serve, SocketListener};
+
+#[cfg(unix)]
+mod unix {
+    use std::future::Future;
+    use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::UnixStream;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+    use tokio::net::UnixListener;
+
+    use super::{ClientRequest, ClientResponse};
+
+    /// A bound socket, removed again when dropped.
+    pub struct SocketListener {
+        listener: UnixListener,
+        path: PathBuf,
+    }
+
+    impl Drop for SocketListener {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    /// Whether a daemon answers at `path`; a socket left behind by one that
+    /// exited does not.
+    pub fn is_serving(path: &Path) -> bool {
+        UnixStream::connect(path).is_ok()
+    }
+
+    /// Listens on `path`, readable by the current user only. Returns `None`
+    /// when another daemon already answers there; a socket left behind by
+    /// one that exited is replaced.
+    pub fn bind(path: &Path) -> io::Result<Option<SocketListener>> {
+        if is_serving(path) {
+            return Ok(None);
+        }
+        match std::fs::remove_file(path) {
+            Ok(()) => {}
+            Err(error) if error.kind() == ErrorKind::NotFound => {}
+            Err(error) => return Err(error),
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        Ok(Some(SocketListener {
+            listener,
+            path: path.to_path_buf(),
+        }))
+    }
+
+    /// Answers connections until the task is dropped, each on its own task.
+    pub async fn serve<F, Fut>(socket: &SocketListener, handler: F)
+    where
+        F: Fn(ClientRequest) -> Fut + Clone + Send + 'static,
+        Fut: Future<Output = ClientResponse> + Send,
+    {
+        loop {
+            let stream = match socket.listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(error) => {
+                    tracing::warn!("accept on {}: {error}", socket.path.display());
+                    continue;
+                }
+            };
+            let handler = handler.clone();
+            tokio::spawn(async move {
+                let (reader, mut writer) = stream.into_split();
+                let mut line = String::new();
+                if tokio::io::BufReader::new(reader)
+                    .read_line(&mut line)
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+                let response = match serde_json::from_str::<ClientRequest>(&line) {
+                    Ok(request) => handler(request).await,
+                    Err(error) => ClientResponse::Declined {
+                        reason: format!("invalid request: {error}"),
+                    },
+                };
+                let Ok(mut encoded) = serde_json::to_vec(&response) else {
+                    return;
+                };
+                encoded.push(b'\n');
+                // The client may have gone away; nothing is left to tell.
+                let _ = writer.write_all(&encoded).await;
+            });
+        }
+    }
+
+    /// Sends `request` to the daemon at `path`. `None` when no daemon is
+    /// listening there; a daemon that has not answered within `timeout`,
+    /// say one stuck on a lock, is a `TimedOut` error.
+    pub fn request(
+        path: &Path,
+        request: &ClientRequest,
+        timeout: Duration,
+    ) -> io::Result<Option<ClientResponse>> {
+        let mut stream = match UnixStream::connect(path) {
+            Ok(stream) => stream,
+            Err(error)
+                if matches!(
+                    error.kind(),
+                    ErrorKind::NotFound | ErrorKind::ConnectionRefused
+                ) =>
+            {
+                return Ok(None)
+            }
+            Err(error) => return Err(error),
+        };
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        let mut encoded = serde_json::to_vec(request)?;
+        encoded.push(b'\n');
+        let mut line = String::new();
+        stream
+            .write_all(&encoded)
+            .and_then(|()| BufReader::new(stream).read_line(&mut line))
+            .map_err(|error| match error.kind() {
+                ErrorKind::WouldBlock | ErrorKind::TimedOut => io::Error::new(
+                    ErrorKind::TimedOut,
+                    format!("no answer within {}s", timeout.as_secs()),
+                ),
+                _ => error,
+            })?;
+        Ok(Some(serde_json::from_str(&line)?))
+    }
+}
+
+#[cfg(test)]
+mod http_tests {
+    use std::time::Duration;
+
+    use super::{
+        bind_http, request_http, serve_http, ClientRequest, ClientResponse, REMOTE_TIMEOUT,
+    };
+    use crate::output::TableStyle;
+
+    #[tokio::test]
+    async fn requests_are_answered_over_http_with_the_token() {
+        let addr = "127.0.0.1:0".parse().expect("addr");
+        let listener = bind_http(addr, Some("s3cret")).await.expect("bind");
+        let url = format!("http://{}", listener.local_addr().expect("local addr"));
+        let server = tokio::spawn(serve_http(
+            listener,
+            Some("s3cret".to_string()),
+            |request: ClientRequest| async move {
+                ClientResponse::Done {
+                    output: request.args.join(" "),
+                }
+            },
+        ));
+
+        let sent = ClientRequest::new(vec!["list".into(), "--unread".into()], TableStyle::plain());
+        assert_eq!(
+            request_http(&url, Some("s3cret"), REMOTE_TIMEOUT, &sent)
+                .await
+                .expect("request"),
+            ClientResponse::Done {
+                output: "list --unread".to_string()
+            }
+        );
+        let refused = request_http(&url, Some("wrong"), REMOTE_TIMEOUT, &sent)
+            .await
+            .expect_err("wrong token");
+        assert!(refused.to_string().contains("refused the token"));
+        let refused = request_http(&url, None, REMOTE_TIMEOUT, &sent)
+            .await
+            .expect_err("no token");
+        assert!(refused.to_string().contains("refused the token"));
+
+        let openapi: serde_json::Value = reqwest::get(format!("{url}/openapi.json"))
+            .await
+            .expect("openapi without token")
+            .json()
+            .await
+            .expect("openapi document");
+        assert!(openapi["paths"]["/v1/cli"]["post"].is_object());
+
+        let public = "0.0.0.0:0".parse().expect("addr");
+        assert!(bind_http(public, None).await.is_err());
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn tokenless_server_refuses_browser_requests() {
+        let addr = "127.0.0.1:0".parse().expect("addr");
+        let listener = bind_http(addr, None).await.expect("bind");
+        let url = format!("http://{}", listener.local_addr().expect("local addr"));
+        let server = tokio::spawn(serve_http(
+            listener,
+            None,
+            |_request: ClientRequest| async move {
+                ClientResponse::Done {
+                    output: String::new(),
+                }
+            },
+        ));
+        let sent = ClientRequest::new(vec!["list".into()], TableStyle::plain());
+        let body = serde_json::to_string(&sent).expect("encode");
+        let client = reqwest::Client::new();
+        let post = |content_type: &'static str, origin: Option<&'static str>| {
+            let mut builder = client
+                .post(format!("{url}/v1/cli"))
+                .header("Content-Type", content_type)
+                .body(body.clone());
+            if let Some(origin) = origin {
+                builder = builder.header("Origin", origin);
+            }
+            async move { builder.send().await.expect("send").status().as_u16() }
+        };
+
+        assert_eq!(post("application/json", None).await, 200);
+        assert_eq!(
+            post("application/json", Some("http://localhost:3000")).await,
+            200
+        );
+        assert_eq!(
+            post("application/json", Some("https://evil.example")).await,
+            403
+        );
+        assert_eq!(post("text/plain", None).await, 415);
+        assert_eq!(post("text/plain", Some("https://evil.example")).await, 403);
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn requests_to_a_silent_server_time_out() {
+        // Accepts connections but never answers them.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind");
+        let url = format!("http://{}", listener.local_addr().expect("local addr"));
+        let server = tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                held.push(stream);
+            }
+        });
+
+        let sent = ClientRequest::new(vec!["list".into()], TableStyle::plain());
+        let error = request_http(&url, None, Duration::from_millis(200), &sent)
+            .await
+            .expect_err("no answer");
+        assert!(error.to_string().contains("did not answer"), "{error}");
+        server.abort();
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::time::Duration;
+
+    use super::{bind, request, serve, ClientRequest, ClientResponse, SOCKET_TIMEOUT};
+    use crate::output::TableStyle;
+
+    #[tokio::test]
+    async fn requests_are_answered_over_the_socket() {
+        let path = std::env::temp_dir().join(format!("ess-{}.sock", uuid::Uuid::new_v4()));
+        assert_eq!(
+            request(
+                &path,
+                &ClientRequest::new(vec![], TableStyle::plain()),
+                SOCKET_TIMEOUT
+            )
+            .expect("no daemon"),
+            None
+        );
+
+        let socket = bind(&path).expect("bind").expect("socket is free");
+        assert!(bind(&path).expect("bind again").is_none());
+        let server = tokio::spawn(async move {
+            serve(&socket, |request: ClientRequest| async move {
+                ClientResponse::Done {
+                    output: format!("{}\n", request.args.join(" ")),
+                }
+            })
+            .await
+        });
+
+        let sent = ClientRequest::new(vec!["search".into(), "budget".into()], TableStyle::plain());
+        let client_path = path.clone();
+        let response =
+            tokio::task::spawn_blocking(move || request(&client_path, &sent, SOCKET_TIMEOUT))
+                .await
+                .expect("client task")
+                .expect("request");
+        assert_eq!(
+            response,
+            Some(ClientResponse::Done {
+                output: "search budget\n".to_string()
+            })
+        );
+
+        server.abort();
+        let _ = server.await;
+        assert!(!path.exists(), "the socket is removed with its listener");
+    }
+
+    #[tokio::test]
+    async fn a_stuck_daemon_times_out() {
+        let path = std::env::temp_dir().join(format!("ess-{}.sock", uuid::Uuid::new_v4()));
+        let socket = bind(&path).expect("bind").expect("socket is free");
+        let server = tokio::spawn(async move {
+            serve(&socket, |_request: ClientRequest| async move {
+                std::future::pending::<ClientResponse>().await
+            })
+            .await
+        });
+
+        let sent = ClientRequest::new(vec!["list".into()], TableStyle::plain());
+        let client_path = path.clone();
+        let error = tokio::task::spawn_blocking(move || {
+            request(&client_path, &sent, Duration::from_millis(200))
+        })
+        .await
+        .expect("client task")
+        .expect_err("no answer");
+        assert_eq!(error.kind(), std::io::ErrorKind::TimedOut);
+
+        server.abort();
+        let _ = server.await;
+    }
+}
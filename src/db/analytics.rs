@@ -0,0 +1,425 @@
+//! Aggregates for `ess analytics`. Mail sent from an account address counts
+//! as sent, everything else as received; periods and hours are local time.
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::db::{Database, DbError, SqlWhereClause};
+
+/// Whether `e` was sent from one of the accounts.
+const OUTGOING: &str = "lower(trim(e.from_address)) IN (SELECT lower(email_address) FROM accounts)";
+const PARENT_OUTGOING: &str =
+    "lower(trim(parent.from_address)) IN (SELECT lower(email_address) FROM accounts)";
+
+/// Upper bounds, in minutes, of the response time buckets; the last bucket
+/// has none.
+const RESPONSE_BUCKETS: &[(&str, Option<f64>)] = &[
+    ("< 1h", Some(60.0)),
+    ("1-4h", Some(240.0)),
+    ("4-24h", Some(1_440.0)),
+    ("1-3d", Some(4_320.0)),
+    ("3-7d", Some(10_080.0)),
+    ("> 7d", None),
+];
+
+#[derive(Debug, Clone, Default)]
+pub struct AnalyticsFilters {
+    pub account_id: Option<String>,
+    /// Received on or after this day.
+    pub since: Option<NaiveDate>,
+    /// Received on or before this day.
+    pub until: Option<NaiveDate>,
+}
+
+impl AnalyticsFilters {
+    fn predicate(&self) -> SqlWhereClause {
+        let mut conditions = Vec::new();
+        if let Some(account_id) = &self.account_id {
+            conditions.push(SqlWhereClause::new(
+                "e.account_id = ?",
+                [account_id.clone()],
+            ));
+        }
+        if let Some(since) = self.since {
+            conditions.push(SqlWhereClause::new(
+                "DATE(e.received_at) >= DATE(?)",
+                [since.to_string()],
+            ));
+        }
+        if let Some(until) = self.until {
+            conditions.push(SqlWhereClause::new(
+                "DATE(e.received_at) <= DATE(?)",
+                [until.to_string()],
+            ));
+        }
+        SqlWhereClause::all(conditions)
+    }
+}
+
+/// How `ess analytics` groups volume over time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnalyticsInterval {
+    /// Weeks starting on Monday, labelled by that day.
+    Week,
+    /// Calendar months, labelled `YYYY-MM`.
+    #[default]
+    Month,
+}
+
+impl AnalyticsInterval {
+    fn period_sql(self) -> &'static str {
+        match self {
+            Self::Week => "date(e.received_at, 'localtime', 'weekday 0', '-6 days')",
+            Self::Month => "strftime('%Y-%m', e.received_at, 'localtime')",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Analytics {
+    pub interval: AnalyticsInterval,
+    pub volume: Vec<VolumePeriod>,
+    pub top_senders: Vec<TopSender>,
+    pub response_times: ResponseTimes,
+    /// All 24 hours, midnight first.
+    pub busiest_hours: Vec<HourCount>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VolumePeriod {
+    pub period: String,
+    pub received: i64,
+    pub sent: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TopSender {
+    pub address: String,
+    pub name: Option<String>,
+    pub messages: i64,
+}
+
+/// How long replies from the accounts took. A reply is a sent email whose
+/// `In-Reply-To` names a received message of its account or, without one,
+/// whose previous message in the same conversation was received.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ResponseTimes {
+    pub replies: usize,
+    pub median_minutes: Option<f64>,
+    pub buckets: Vec<ResponseBucket>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ResponseBucket {
+    pub label: String,
+    pub replies: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HourCount {
+    pub hour: u32,
+    pub received: i64,
+    pub sent: i64,
+}
+
+impl ResponseTimes {
+    fn from_minutes(mut minutes: Vec<f64>) -> Self {
+        minutes.sort_by(f64::total_cmp);
+        let median_minutes = match minutes.len() {
+            0 => None,
+            len if len.is_multiple_of(2) => Some((minutes[len / 2 - 1] + minutes[len / 2]) / 2.0),
+            len => Some(minutes[len / 2]),
+        };
+        let mut lower = 0.0;
+        let buckets = RESPONSE_BUCKETS
+            .iter()
+            .map(|&(label, upper)| {
+                let replies = minutes
+                    .iter()
+                    .filter(|&&value| value >= lower && upper.is_none_or(|upper| value < upper))
+                    .count();
+                lower = upper.unwrap_or(f64::INFINITY);
+                ResponseBucket {
+                    label: label.to_string(),
+                    replies,
+                }
+            })
+            .collect();
+        Self {
+            replies: minutes.len(),
+            median_minutes,
+            buckets,
+        }
+    }
+}
+
+impl Database {
+    /// Volume per `interval`, the `top` senders of received mail, reply
+    /// times and mail per hour of the day, over the emails `filters` match.
+    pub fn analytics(
+        &self,
+        filters: &AnalyticsFilters,
+        interval: AnalyticsInterval,
+        top: usize,
+    ) -> Result<Analytics, DbError> {
+        let predicate = filters.predicate();
+
+        let sql = format!(
+            r#"
+            SELECT {period} AS period,
+                   SUM(NOT ({OUTGOING})), SUM({OUTGOING})
+            FROM emails e
+            WHERE {where_clause}
+            GROUP BY period
+            ORDER BY period
+            "#,
+            period = interval.period_sql(),
+            where_clause = predicate.clause
+        );
+        let volume = self
+            .conn
+            .prepare(&sql)?
+            .query_map(predicate.bind(&[]).as_slice(), |row| {
+                Ok(VolumePeriod {
+                    period: row.get(0)?,
+                    received: row.get(1)?,
+                    sent: row.get(2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let sql = format!(
+            r#"
+            SELECT lower(trim(e.from_address)) AS address, MAX(NULLIF(trim(e.from_name), '')),
+                   COUNT(*) AS messages
+            FROM emails e
+            WHERE {where_clause} AND trim(COALESCE(e.from_address, '')) != ''
+              AND NOT ({OUTGOING})
+            GROUP BY address
+            ORDER BY messages DESC, address
+            LIMIT ?
+            "#,
+            where_clause = predicate.clause
+        );
+        let top = top as i64;
+        let top_senders = self
+            .conn
+            .prepare(&sql)?
+            .query_map(predicate.bind(&[&top]).as_slice(), |row| {
+                Ok(TopSender {
+                    address: row.get(0)?,
+                    name: row.get(1)?,
+                    messages: row.get(2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        // The previous message is looked up before filtering, so a reply in
+        // range still pairs with a message received before `since`.
+        let sql = format!(
+            r#"
+            WITH ordered AS (
+                SELECT e.id, {OUTGOING} AS outgoing,
+                       unixepoch(e.received_at) - unixepoch(LAG(e.received_at) OVER conversation)
+                           AS gap_secs,
+                       LAG({OUTGOING}) OVER conversation AS previous_outgoing
+                FROM emails e
+                WHERE e.conversation_id IS NOT NULL
+                WINDOW conversation AS (
+                    PARTITION BY e.account_id, e.conversation_id ORDER BY e.received_at, e.id
+                )
+            ),
+            paired AS (
+                SELECT o.id,
+                       CASE WHEN parent.id IS NULL THEN o.gap_secs
+                            ELSE unixepoch(e.received_at) - unixepoch(parent.received_at) END
+                           AS gap_secs,
+                       CASE WHEN parent.id IS NULL THEN o.previous_outgoing
+                            ELSE {PARENT_OUTGOING} END AS previous_outgoing
+                FROM ordered o
+                JOIN emails e ON e.id = o.id
+                LEFT JOIN emails parent
+                    ON parent.account_id = e.account_id
+                   AND parent.internet_message_id = e.in_reply_to
+                WHERE o.outgoing
+            )
+            SELECT p.gap_secs / 60.0
+            FROM paired p
+            JOIN emails e ON e.id = p.id
+            WHERE p.previous_outgoing = 0 AND p.gap_secs >= 0
+              AND {where_clause}
+            "#,
+            where_clause = predicate.clause
+        );
+        let minutes = self
+            .conn
+            .prepare(&sql)?
+            .query_map(predicate.bind(&[]).as_slice(), |row| row.get::<_, f64>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let sql = format!(
+            r#"
+            SELECT CAST(strftime('%H', e.received_at, 'localtime') AS INTEGER) AS hour,
+                   SUM(NOT ({OUTGOING})), SUM({OUTGOING})
+            FROM emails e
+            WHERE {where_clause}
+            GROUP BY hour
+            "#,
+            where_clause = predicate.clause
+        );
+        let mut busiest_hours: Vec<HourCount> = (0..24)
+            .map(|hour| HourCount {
+                hour,
+                received: 0,
+                sent: 0,
+            })
+            .collect();
+        let mut stmt = self.conn.prepare(&sql)?;
+        let mut rows = stmt.query(predicate.bind(&[]).as_slice())?;
+        while let Some(row) = rows.next()? {
+            let Some(hour) = row.get::<_, Option<u32>>(0)? else {
+                continue;
+            };
+            if let Some(slot) = busiest_hours.get_mut(hour as usize) {
+                slot.received = row.get(1)?;
+                slot.sent = row.get(2)?;
+            }
+        }
+
+        Ok(Analytics {
+            interval,
+            volume,
+            top_senders,
+            response_times: ResponseTimes::from_minutes(minutes),
+            busiest_hours,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AnalyticsFilters, AnalyticsInterval};
+    use crate::db::models::{Account, AccountType, Email};
+    use crate::db::Database;
+
+    fn email(
+        id: &str,
+        from: &str,
+        conversation: &str,
+        parent: Option<&str>,
+        received_at: &str,
+    ) -> Email {
+        Email {
+            internet_message_id: Some(format!("<{id}@example.com>")),
+            in_reply_to: parent.map(|parent| format!("<{parent}@example.com>")),
+            conversation_id: Some(conversation.to_string()),
+            account_id: Some("work".to_string()),
+            subject: Some("Budget".to_string()),
+            from_address: Some(from.to_string()),
+            received_at: received_at.to_string(),
+            ..Email::fixture(id)
+        }
+    }
+
+    #[test]
+    fn analytics_pair_replies_with_the_message_before_them() {
+        let path = std::env::temp_dir().join(format!("ess-analytics-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        db.insert_account(&Account {
+            account_id: "work".to_string(),
+            email_address: "me@example.com".to_string(),
+            display_name: None,
+            tenant_id: None,
+            account_type: AccountType::Professional,
+            enabled: true,
+            last_sync: None,
+            config: None,
+        })
+        .expect("insert account");
+        // b3 answers b1 by its In-Reply-To rather than b2 before it.
+        for (id, from, conversation, parent, received_at) in [
+            (
+                "a1",
+                "alice@example.com",
+                "c1",
+                None,
+                "2026-01-10T12:00:00Z",
+            ),
+            ("a2", "me@example.com", "c1", None, "2026-01-10T12:30:00Z"),
+            ("a3", "me@example.com", "c1", None, "2026-01-10T13:00:00Z"),
+            ("b1", "bob@example.com", "c2", None, "2026-02-10T12:00:00Z"),
+            ("b2", "Bob@Example.com", "c2", None, "2026-02-11T12:00:00Z"),
+            (
+                "b3",
+                "me@example.com",
+                "c2",
+                Some("b1"),
+                "2026-02-13T12:00:00Z",
+            ),
+            (
+                "c1",
+                "alice@example.com",
+                "c3",
+                None,
+                "2026-02-20T12:00:00Z",
+            ),
+        ] {
+            db.insert_email(&email(id, from, conversation, parent, received_at))
+                .expect("insert email");
+        }
+
+        let all = db
+            .analytics(&AnalyticsFilters::default(), AnalyticsInterval::Month, 10)
+            .expect("analytics");
+        let volume: Vec<_> = all
+            .volume
+            .iter()
+            .map(|period| (period.period.as_str(), period.received, period.sent))
+            .collect();
+        assert_eq!(volume, [("2026-01", 1, 2), ("2026-02", 3, 1)]);
+        let senders: Vec<_> = all
+            .top_senders
+            .iter()
+            .map(|sender| (sender.address.as_str(), sender.messages))
+            .collect();
+        assert_eq!(senders, [("alice@example.com", 2), ("bob@example.com", 2)]);
+        assert_eq!(all.response_times.replies, 2);
+        assert_eq!(all.response_times.median_minutes, Some(2_175.0));
+        let buckets: Vec<_> = all
+            .response_times
+            .buckets
+            .iter()
+            .map(|bucket| bucket.replies)
+            .collect();
+        assert_eq!(buckets, [1, 0, 0, 0, 1, 0]);
+        assert_eq!(all.busiest_hours.len(), 24);
+        assert_eq!(
+            all.busiest_hours
+                .iter()
+                .map(|hour| hour.received + hour.sent)
+                .sum::<i64>(),
+            7
+        );
+
+        let february = db
+            .analytics(
+                &AnalyticsFilters {
+                    since: "2026-02-12".parse().ok(),
+                    ..AnalyticsFilters::default()
+                },
+                AnalyticsInterval::Week,
+                1,
+            )
+            .expect("filtered analytics");
+        assert_eq!(february.response_times.replies, 1);
+        assert_eq!(february.top_senders.len(), 1);
+        assert_eq!(february.top_senders[0].address, "alice@example.com");
+        assert!(february
+            .volume
+            .iter()
+            .all(|period| period.period.as_str() >= "2026-02-09"));
+
+        let _ = std::fs::remove_file(path);
+    }
+}
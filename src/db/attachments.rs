@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+
+use rusqlite::params;
+use serde::Serialize;
+
+use crate::db::models::Attachment;
+use crate::db::sql::SqlWhereClause;
+use crate::db::{Database, DbError};
+
+/// `--attachment-type` shorthands: names, then file extensions, then
+/// fragments of the MIME type. Any other value is taken as an extension
+/// (`xlsx`) or, with a `/`, a MIME type fragment (`image/png`).
+const ATTACHMENT_KINDS: &[(&[&str], &[&str], &[&str])] = &[
+    (&["pdf"], &["pdf"], &["application/pdf"]),
+    (
+        &["spreadsheet", "sheet", "excel"],
+        &["xls", "xlsx", "xlsm", "csv", "ods", "numbers"],
+        &["spreadsheet", "ms-excel", "text/csv"],
+    ),
+    (
+        &["document", "doc", "word"],
+        &["doc", "docx", "odt", "rtf", "pages"],
+        &["wordprocessing", "msword", "opendocument.text"],
+    ),
+    (
+        &["presentation", "slides", "powerpoint"],
+        &["ppt", "pptx", "odp", "key"],
+        &["presentation", "powerpoint"],
+    ),
+    (
+        &["image", "photo"],
+        &["png", "jpg", "jpeg", "gif", "heic", "webp"],
+        &["image/"],
+    ),
+    (
+        &["archive", "zip"],
+        &["zip", "gz", "tgz", "tar", "7z", "rar"],
+        &["zip", "x-tar", "gzip", "x-7z", "rar"],
+    ),
+];
+
+/// SQL condition on `attachments` rows for a name glob (`*budget*.xlsx`,
+/// case-insensitive) and/or a type, with its parameters. `None` when
+/// neither is set.
+pub fn attachment_predicate(name_glob: Option<&str>, kind: Option<&str>) -> Option<SqlWhereClause> {
+    let mut conditions = Vec::new();
+
+    if let Some(glob) = name_glob.map(str::trim).filter(|value| !value.is_empty()) {
+        conditions.push(SqlWhereClause::new(
+            "LOWER(name) GLOB ?",
+            [glob.to_lowercase()],
+        ));
+    }
+
+    if let Some(kind) = kind
+        .map(|value| value.trim().trim_start_matches('.').to_lowercase())
+        .filter(|value| !value.is_empty())
+    {
+        let (extensions, mime_types) = match ATTACHMENT_KINDS
+            .iter()
+            .find(|(names, _, _)| names.contains(&kind.as_str()))
+        {
+            Some((_, extensions, mime_types)) => (
+                extensions.iter().map(|value| value.to_string()).collect(),
+                mime_types.iter().map(|value| value.to_string()).collect(),
+            ),
+            None if kind.contains('/') => (Vec::new(), vec![kind]),
+            None => (vec![kind], Vec::new()),
+        };
+        let alternatives = extensions
+            .into_iter()
+            .map(|extension: String| {
+                SqlWhereClause::new("LOWER(name) LIKE ?", [format!("%.{extension}")])
+            })
+            .chain(mime_types.into_iter().map(|mime_type: String| {
+                SqlWhereClause::new("LOWER(content_type) LIKE ?", [format!("%{mime_type}%")])
+            }));
+        conditions.push(SqlWhereClause::any(alternatives));
+    }
+
+    (!conditions.is_empty()).then(|| SqlWhereClause::all(conditions))
+}
+
+/// One copy of a file sent in a thread, as listed by
+/// `ess attachments versions`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AttachmentVersion {
+    /// Copies with the same contents share a version; versions count up in
+    /// the order their contents were first sent.
+    pub version: usize,
+    /// An earlier message of the thread already carried these contents,
+    /// e.g. when a reply keeps the attachment.
+    pub resent: bool,
+    pub attachment_id: String,
+    pub email_id: String,
+    pub name: Option<String>,
+    pub size_bytes: Option<i64>,
+    /// SHA-256 of the contents, known once read from the stored source.
+    pub sha256: Option<String>,
+    pub sent_at: String,
+    pub from_address: Option<String>,
+}
+
+/// Numbers the copies, oldest first. Contents compare by hash, or by size
+/// while either hash is unknown; a copy with neither is a new version.
+fn number_versions(copies: &mut [AttachmentVersion]) {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut sizes: HashMap<i64, usize> = HashMap::new();
+    let mut latest = 0;
+    for copy in copies.iter_mut() {
+        let earlier = match (&copy.sha256, copy.size_bytes) {
+            (Some(hash), _) => seen.get(hash).copied(),
+            (None, Some(size)) => sizes.get(&size).copied(),
+            (None, None) => None,
+        };
+        match earlier {
+            Some(version) => {
+                copy.version = version;
+                copy.resent = true;
+            }
+            None => {
+                latest += 1;
+                copy.version = latest;
+            }
+        }
+        if let Some(hash) = &copy.sha256 {
+            seen.entry(hash.clone()).or_insert(copy.version);
+        }
+        if let Some(size) = copy.size_bytes {
+            // A hashed copy only stands in for one of the same size whose
+            // hash is unknown.
+            sizes.entry(size).or_insert(copy.version);
+        }
+    }
+}
+
+impl Database {
+    /// Every copy of the attachment named `name` (case-insensitive) in a
+    /// conversation, oldest first, numbered into versions.
+    pub fn attachment_versions(
+        &self,
+        conversation_id: &str,
+        name: &str,
+    ) -> Result<Vec<AttachmentVersion>, DbError> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT a.id, a.email_id, a.name, a.size_bytes, h.sha256,
+                   COALESCE(e.sent_at, e.received_at), e.from_address
+            FROM attachments a
+            JOIN emails e ON e.id = a.email_id
+            LEFT JOIN attachment_hashes h ON h.attachment_id = a.id
+            WHERE e.conversation_id = ?1 AND LOWER(a.name) = LOWER(?2)
+            ORDER BY datetime(COALESCE(e.sent_at, e.received_at)), a.email_id, a.id
+            "#,
+        )?;
+        let mut copies = stmt
+            .query_map(params![conversation_id, name.trim()], |row| {
+                Ok(AttachmentVersion {
+                    version: 0,
+                    resent: false,
+                    attachment_id: row.get(0)?,
+                    email_id: row.get(1)?,
+                    name: row.get(2)?,
+                    size_bytes: row.get(3)?,
+                    sha256: row.get(4)?,
+                    sent_at: row.get(5)?,
+                    from_address: row.get(6)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        number_versions(&mut copies);
+        Ok(copies)
+    }
+
+    /// Records the SHA-256 of an attachment's contents. The hash goes with
+    /// the attachment record, so a sync that stores the email again drops
+    /// it until the source is read again.
+    pub fn store_attachment_hash(&self, attachment_id: &str, sha256: &str) -> Result<(), DbError> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO attachment_hashes (attachment_id, sha256) VALUES (?, ?)",
+            params![attachment_id, sha256],
+        )?;
+        Ok(())
+    }
+
+    /// Replaces the attachment records of an email. `insert_email` replaces
+    /// the email row, which drops them, so connectors call this after it.
+    pub fn replace_attachments(
+        &self,
+        email_id: &str,
+        attachments: &[Attachment],
+    ) -> Result<(), DbError> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM attachments WHERE email_id = ?", [email_id])?;
+        {
+            let mut stmt = tx.prepare(
+                r#"
+                INSERT OR REPLACE INTO attachments (id, email_id, name, content_type, size_bytes, is_inline)
+                VALUES (?, ?, ?, ?, ?, ?)
+                "#,
+            )?;
+            for attachment in attachments {
+                stmt.execute(params![
+                    attachment.id,
+                    email_id,
+                    attachment.name,
+                    attachment.content_type,
+                    attachment.size_bytes,
+                    attachment.is_inline,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn get_attachments(&self, email_id: &str) -> Result<Vec<Attachment>, DbError> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id, email_id, name, content_type, size_bytes, is_inline
+            FROM attachments WHERE email_id = ? ORDER BY name, id
+            "#,
+        )?;
+        let attachments = stmt
+            .query_map([email_id], Attachment::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(attachments)
+    }
+
+    /// Whether the email has an attachment matching the name glob and type
+    /// (see `attachment_predicate`); `true` when neither is set.
+    pub fn has_matching_attachment(
+        &self,
+        email_id: &str,
+        name_glob: Option<&str>,
+        kind: Option<&str>,
+    ) -> Result<bool, DbError> {
+        let Some(predicate) = attachment_predicate(name_glob, kind) else {
+            return Ok(true);
+        };
+        let predicate = SqlWhereClause::all([
+            SqlWhereClause::new("email_id = ?", [email_id.to_string()]),
+            predicate,
+        ]);
+        let sql = format!(
+            "SELECT EXISTS(SELECT 1 FROM attachments WHERE {})",
+            predicate.clause
+        );
+        let found = self
+            .conn
+            .query_row(&sql, predicate.bind(&[]).as_slice(), |row| row.get(0))?;
+        Ok(found)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{number_versions, AttachmentVersion};
+    use crate::db::models::{Account, AccountType, Attachment, Email};
+    use crate::db::Database;
+
+    fn attachment(id: &str, name: &str, content_type: &str) -> Attachment {
+        Attachment {
+            id: id.to_string(),
+            email_id: "e1".to_string(),
+            name: Some(name.to_string()),
+            content_type: Some(content_type.to_string()),
+            size_bytes: Some(1024),
+            is_inline: Some(false),
+        }
+    }
+
+    #[test]
+    fn matches_attachments_by_name_glob_and_type() {
+        let path = std::env::temp_dir().join(format!("ess-attach-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        db.insert_account(&Account {
+            account_id: "acc".to_string(),
+            email_address: "me@example.com".to_string(),
+            display_name: None,
+            tenant_id: None,
+            account_type: AccountType::Professional,
+            enabled: true,
+            last_sync: None,
+            config: None,
+        })
+        .expect("insert account");
+        db.insert_email(&Email {
+            account_id: Some("acc".to_string()),
+            subject: Some("Numbers".to_string()),
+            from_address: Some("finance@example.com".to_string()),
+            received_at: "2026-02-01T10:00:00Z".to_string(),
+            has_attachments: Some(true),
+            ..Email::fixture("e1")
+        })
+        .expect("insert email");
+        db.replace_attachments(
+            "e1",
+            &[
+                attachment("a1", "Q3 Budget.XLSX", "application/octet-stream"),
+                attachment("a2", "logo", "image/png"),
+            ],
+        )
+        .expect("store attachments");
+
+        let matches = |name: Option<&str>, kind: Option<&str>| {
+            db.has_matching_attachment("e1", name, kind)
+                .expect("match attachments")
+        };
+        assert!(matches(Some("*budget*"), None));
+        assert!(matches(None, Some("spreadsheet")));
+        assert!(matches(None, Some(".xlsx")));
+        assert!(matches(None, Some("image")));
+        assert!(matches(Some("*budget*"), Some("xlsx")));
+        assert!(!matches(None, Some("pdf")));
+        assert!(!matches(Some("logo"), Some("spreadsheet")));
+        assert_eq!(db.get_attachments("e1").expect("list").len(), 2);
+
+        db.replace_attachments("e1", &[])
+            .expect("clear attachments");
+        assert!(!matches(Some("*budget*"), None));
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn copies_are_numbered_by_their_contents() {
+        let copy = |email_id: &str, size: Option<i64>, sha256: Option<&str>| AttachmentVersion {
+            version: 0,
+            resent: false,
+            attachment_id: format!("{email_id}:a"),
+            email_id: email_id.to_string(),
+            name: Some("contract.docx".to_string()),
+            size_bytes: size,
+            sha256: sha256.map(str::to_string),
+            sent_at: "2026-02-01T10:00:00Z".to_string(),
+            from_address: None,
+        };
+        let mut copies = [
+            copy("draft", Some(100), Some("aa")),
+            copy("reply", Some(100), Some("aa")),
+            copy("redline", Some(100), Some("bb")),
+            copy("unhashed", Some(100), None),
+            copy("final", Some(120), None),
+            copy("unknown", None, None),
+        ];
+        number_versions(&mut copies);
+        let numbered: Vec<_> = copies
+            .iter()
+            .map(|copy| (copy.version, copy.resent))
+            .collect();
+        assert_eq!(
+            numbered,
+            [
+                (1, false),
+                (1, true),
+                (2, false),
+                (1, true),
+                (3, false),
+                (4, false)
+            ]
+        );
+    }
+}
@@ -0,0 +1,182 @@
+//! The change feed: the latest local change of each email, star,
+//! collection and collection member, kept by the triggers of schema v25.
+//! `ess replicate` ships the changes made since its last run to a peer,
+//! which keeps whichever side changed an entry last.
+
+use rusqlite::{params, OptionalExtension};
+
+use crate::db::{Database, DbError};
+
+/// When entries stored before the change feed existed count as changed.
+pub const EPOCH: &str = "1970-01-01T00:00:00.000Z";
+
+/// What a change is to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeEntity {
+    Email,
+    Star,
+    Collection,
+    /// Keyed by `["<collection>", "<email_id>"]`.
+    CollectionEmail,
+}
+
+impl ChangeEntity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Email => "email",
+            Self::Star => "star",
+            Self::Collection => "collection",
+            Self::CollectionEmail => "collection_email",
+        }
+    }
+
+    fn parse(raw: &str) -> Option<Self> {
+        match raw {
+            "email" => Some(Self::Email),
+            "star" => Some(Self::Star),
+            "collection" => Some(Self::Collection),
+            "collection_email" => Some(Self::CollectionEmail),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub seq: i64,
+    pub entity: ChangeEntity,
+    pub key: String,
+    /// RFC 3339 UTC with milliseconds, so timestamps compare as strings.
+    pub changed_at: String,
+}
+
+impl Database {
+    /// Up to `limit` changes recorded after `seq`, oldest first.
+    pub fn changes_since(&self, seq: i64, limit: usize) -> Result<Vec<Change>, DbError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT seq, entity, key, changed_at FROM changes WHERE seq > ? ORDER BY seq LIMIT ?",
+        )?;
+        let rows = stmt
+            .query_map(params![seq, limit as i64], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<(i64, String, String, String)>>>()?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(seq, entity, key, changed_at)| {
+                Some(Change {
+                    seq,
+                    entity: ChangeEntity::parse(&entity)?,
+                    key,
+                    changed_at,
+                })
+            })
+            .collect())
+    }
+
+    /// The sequence number of the newest change, or 0 before the first.
+    pub fn latest_change_seq(&self) -> Result<i64, DbError> {
+        Ok(self
+            .conn
+            .query_row("SELECT COALESCE(MAX(seq), 0) FROM changes", [], |row| {
+                row.get(0)
+            })?)
+    }
+
+    /// When `key` last changed here, or `None` if it never did.
+    pub fn last_changed_at(
+        &self,
+        entity: ChangeEntity,
+        key: &str,
+    ) -> Result<Option<String>, DbError> {
+        let changed_at = self
+            .conn
+            .query_row(
+                "SELECT changed_at FROM changes WHERE entity = ?1 AND key = ?2",
+                params![entity.as_str(), key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(changed_at)
+    }
+
+    /// Records `key` as changed at `changed_at`, replacing what the triggers
+    /// recorded, so a change applied from a peer keeps the time it was made
+    /// there.
+    pub fn stamp_change(
+        &self,
+        entity: ChangeEntity,
+        key: &str,
+        changed_at: &str,
+    ) -> Result<(), DbError> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO changes (entity, key, changed_at) VALUES (?1, ?2, ?3)",
+            params![entity.as_str(), key, changed_at],
+        )?;
+        Ok(())
+    }
+
+    pub fn has_collection(&self, name: &str) -> Result<bool, DbError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT 1 FROM collections WHERE name = ?")?;
+        Ok(stmt.exists([name])?)
+    }
+
+    pub fn collection_has_email(&self, name: &str, email_id: &str) -> Result<bool, DbError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT 1 FROM collection_emails WHERE collection = ?1 AND email_id = ?2")?;
+        Ok(stmt.exists(params![name, email_id])?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChangeEntity, EPOCH};
+    use crate::db::Database;
+
+    #[test]
+    fn triggers_keep_the_latest_change_of_each_entry() {
+        let path = std::env::temp_dir().join(format!("ess-changes-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+
+        db.star_email("a").expect("star");
+        db.add_to_collection("trip", &["a".to_string()])
+            .expect("collect");
+        db.unstar_email("a").expect("unstar");
+        let changes = db.changes_since(0, 100).expect("changes");
+        let entries: Vec<_> = changes
+            .iter()
+            .map(|change| (change.entity, change.key.as_str()))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                (ChangeEntity::Collection, "trip"),
+                (ChangeEntity::CollectionEmail, r#"["trip","a"]"#),
+                (ChangeEntity::Star, "a"),
+            ]
+        );
+        assert_eq!(db.changes_since(0, 2).expect("changes").len(), 2);
+        assert!(db
+            .changes_since(changes[2].seq, 100)
+            .expect("changes")
+            .is_empty());
+
+        db.stamp_change(ChangeEntity::Star, "a", EPOCH)
+            .expect("stamp");
+        assert_eq!(
+            db.last_changed_at(ChangeEntity::Star, "a")
+                .expect("changed at")
+                .as_deref(),
+            Some(EPOCH)
+        );
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,202 @@
+use rusqlite::{params, OptionalExtension};
+use serde::Serialize;
+
+use crate::db::models::Email;
+use crate::db::{Database, DbError};
+
+/// A named set of emails and how many it holds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CollectionSummary {
+    pub name: String,
+    pub emails: i64,
+    pub created_at: String,
+    /// When an email was last added; `None` for an empty collection.
+    pub last_added_at: Option<String>,
+}
+
+impl Database {
+    /// Adds emails to the collection `name`, creating it first if needed.
+    /// Returns how many were not in it already.
+    pub fn add_to_collection(&self, name: &str, email_ids: &[String]) -> Result<usize, DbError> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT OR IGNORE INTO collections (name) VALUES (?)",
+            [name],
+        )?;
+        let mut added = 0;
+        for email_id in email_ids {
+            added += tx.execute(
+                "INSERT OR IGNORE INTO collection_emails (collection, email_id) VALUES (?1, ?2)",
+                params![name, email_id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(added)
+    }
+
+    /// Removes emails from a collection; the collection itself stays, even
+    /// when empty. Returns how many were removed.
+    pub fn remove_from_collection(
+        &self,
+        name: &str,
+        email_ids: &[String],
+    ) -> Result<usize, DbError> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut removed = 0;
+        for email_id in email_ids {
+            removed += tx.execute(
+                "DELETE FROM collection_emails WHERE collection = ?1 AND email_id = ?2",
+                params![name, email_id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(removed)
+    }
+
+    /// Deletes a collection and its membership list (not the emails).
+    pub fn delete_collection(&self, name: &str) -> Result<bool, DbError> {
+        let deleted = self
+            .conn
+            .execute("DELETE FROM collections WHERE name = ?", [name])?;
+        Ok(deleted > 0)
+    }
+
+    pub fn list_collections(&self) -> Result<Vec<CollectionSummary>, DbError> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT c.name, COUNT(m.email_id), c.created_at, MAX(m.added_at)
+            FROM collections c
+            LEFT JOIN collection_emails m ON m.collection = c.name
+            GROUP BY c.name
+            ORDER BY c.name
+            "#,
+        )?;
+        let collections = stmt
+            .query_map([], |row| {
+                Ok(CollectionSummary {
+                    name: row.get(0)?,
+                    emails: row.get(1)?,
+                    created_at: row.get(2)?,
+                    last_added_at: row.get(3)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(collections)
+    }
+
+    /// Emails in a collection, newest first, or `None` when there is no
+    /// collection called `name`.
+    pub fn collection_emails(&self, name: &str) -> Result<Option<Vec<Email>>, DbError> {
+        let exists = self
+            .conn
+            .query_row("SELECT 1 FROM collections WHERE name = ?", [name], |_| {
+                Ok(())
+            })
+            .optional()?
+            .is_some();
+        if !exists {
+            return Ok(None);
+        }
+
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT e.id, e.internet_message_id, e.conversation_id, e.account_id, e.subject,
+                   e.from_address, e.from_name, e.to_addresses, e.cc_addresses, e.bcc_addresses,
+                   e.body_text, e.body_html, e.body_preview, e.received_at, e.sent_at,
+                   e.importance, e.is_read, e.has_attachments, e.folder, e.categories,
+                   e.flag_status, e.web_link, e.metadata
+            FROM collection_emails m
+            JOIN emails e ON e.id = m.email_id
+            WHERE m.collection = ?
+            ORDER BY e.received_at DESC, e.id DESC
+            "#,
+        )?;
+        let emails = stmt
+            .query_map([name], Email::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(Some(emails))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::models::{Account, AccountType, Email};
+    use crate::db::Database;
+
+    fn email(id: &str, received_at: &str) -> Email {
+        Email {
+            account_id: Some("acc".to_string()),
+            subject: Some("Vendor research".to_string()),
+            from_address: Some("alice@example.com".to_string()),
+            received_at: received_at.to_string(),
+            is_read: Some(true),
+            has_attachments: Some(false),
+            folder: Some("inbox".to_string()),
+            ..Email::fixture(id)
+        }
+    }
+
+    #[test]
+    fn collections_track_membership() {
+        let path =
+            std::env::temp_dir().join(format!("ess-collections-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        db.insert_account(&Account {
+            account_id: "acc".to_string(),
+            email_address: "me@example.com".to_string(),
+            display_name: None,
+            tenant_id: None,
+            account_type: AccountType::Professional,
+            enabled: true,
+            last_sync: None,
+            config: None,
+        })
+        .expect("insert account");
+        db.insert_email(&email("old", "2026-01-01T10:00:00Z"))
+            .expect("insert email");
+        db.insert_email(&email("new", "2026-02-01T10:00:00Z"))
+            .expect("insert email");
+        let ids = |values: &[&str]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            db.add_to_collection("vendors", &ids(&["old", "new"]))
+                .expect("add"),
+            2
+        );
+        assert_eq!(
+            db.add_to_collection("vendors", &ids(&["new"]))
+                .expect("add"),
+            0
+        );
+        assert_eq!(db.collection_emails("missing").expect("missing"), None);
+        let members = db
+            .collection_emails("vendors")
+            .expect("members")
+            .expect("collection exists")
+            .into_iter()
+            .map(|email| email.id)
+            .collect::<Vec<_>>();
+        assert_eq!(members, vec!["new", "old"]);
+
+        assert_eq!(
+            db.remove_from_collection("vendors", &ids(&["old", "other"]))
+                .expect("remove"),
+            1
+        );
+        let summary = db.list_collections().expect("list");
+        assert_eq!(summary.len(), 1);
+        assert_eq!(summary[0].emails, 1);
+
+        assert!(db.delete_collection("vendors").expect("delete"));
+        assert!(db.list_collections().expect("list").is_empty());
+        let orphans: i64 = db
+            .conn()
+            .query_row("SELECT COUNT(*) FROM collection_emails", [], |row| {
+                row.get(0)
+            })
+            .expect("count members");
+        assert_eq!(orphans, 0);
+
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::BTreeMap;
+
+use rusqlite::params;
+
+use crate::db::models::Contact;
+use crate::db::{Database, DbError};
+
+/// Details found for a contact by `ess contacts enrich`; `None` leaves the
+/// stored value alone.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContactDetails {
+    pub email_address: String,
+    pub display_name: Option<String>,
+    pub company: Option<String>,
+    pub phone: Option<String>,
+}
+
+impl Database {
+    /// The display name each contact most often sends under, by lowercased
+    /// address. Ties go to the name used most recently; names that merely
+    /// repeat the address are ignored.
+    pub fn sender_display_names(&self) -> Result<BTreeMap<String, String>, DbError> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT lower(trim(e.from_address)) AS address, trim(e.from_name) AS name,
+                   COUNT(*) AS uses, MAX(e.received_at) AS latest
+            FROM emails e
+            JOIN contacts c ON c.email_address = lower(trim(e.from_address))
+            WHERE trim(e.from_name) != ''
+              AND lower(trim(e.from_name)) != lower(trim(e.from_address))
+            GROUP BY address, name
+            ORDER BY address, uses DESC, latest DESC
+            "#,
+        )?;
+        let mut names = BTreeMap::new();
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        for row in rows {
+            let (address, name) = row?;
+            names.entry(address).or_insert(name);
+        }
+        Ok(names)
+    }
+
+    /// Calls `visit` once per contact that has sent mail, with the plain
+    /// text bodies of up to `per_sender` of their newest messages. Rows are
+    /// streamed, so only one sender's bodies are held at a time.
+    pub fn for_each_sender_bodies(
+        &self,
+        per_sender: usize,
+        mut visit: impl FnMut(&str, &[String]),
+    ) -> Result<(), DbError> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT address, body FROM (
+                SELECT lower(trim(e.from_address)) AS address, e.body_text AS body,
+                       ROW_NUMBER() OVER (
+                           PARTITION BY lower(trim(e.from_address))
+                           ORDER BY e.received_at DESC
+                       ) AS position
+                FROM emails e
+                JOIN contacts c ON c.email_address = lower(trim(e.from_address))
+                WHERE trim(COALESCE(e.body_text, '')) != ''
+            )
+            WHERE position <= ?1
+            ORDER BY address, position
+            "#,
+        )?;
+        let mut rows = stmt.query(params![per_sender as i64])?;
+        let mut current: Option<String> = None;
+        let mut bodies: Vec<String> = Vec::new();
+        while let Some(row) = rows.next()? {
+            let address: String = row.get(0)?;
+            if current.as_deref() != Some(address.as_str()) {
+                if let Some(previous) = current.take() {
+                    visit(&previous, &bodies);
+                }
+                bodies.clear();
+                current = Some(address);
+            }
+            bodies.push(row.get(1)?);
+        }
+        if let Some(last) = current {
+            visit(&last, &bodies);
+        }
+        Ok(())
+    }
+
+    /// Up to `limit` contacts whose address or display name contains
+    /// `fragment`, ignoring case, most messages first.
+    pub fn contacts_matching(&self, fragment: &str, limit: usize) -> Result<Vec<Contact>, DbError> {
+        let escaped = fragment
+            .to_lowercase()
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT email_address, display_name, company, phone, attio_person_id, attio_company_id,
+                   message_count, first_seen, last_seen
+            FROM contacts
+            WHERE lower(email_address) LIKE ?1 ESCAPE '\'
+               OR lower(COALESCE(display_name, '')) LIKE ?1 ESCAPE '\'
+            ORDER BY message_count DESC, email_address ASC
+            LIMIT ?2
+            "#,
+        )?;
+        let contacts = stmt
+            .query_map(
+                params![format!("%{escaped}%"), limit as i64],
+                Contact::from_row,
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(contacts)
+    }
+
+    /// Fills in the given details, in one transaction. Only fields that are
+    /// still empty are written, so names and companies set by hand or by
+    /// another source are kept.
+    pub fn store_contact_details(&self, details: &[ContactDetails]) -> Result<usize, DbError> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut updated = 0;
+        for contact in details {
+            updated += tx.execute(
+                r#"
+                UPDATE contacts SET
+                    display_name = CASE WHEN trim(COALESCE(display_name, '')) = ''
+                        THEN COALESCE(?2, display_name) ELSE display_name END,
+                    company = CASE WHEN trim(COALESCE(company, '')) = ''
+                        THEN COALESCE(?3, company) ELSE company END,
+                    phone = CASE WHEN trim(COALESCE(phone, '')) = ''
+                        THEN COALESCE(?4, phone) ELSE phone END
+                WHERE email_address = ?1
+                "#,
+                params![
+                    contact.email_address,
+                    contact.display_name,
+                    contact.company,
+                    contact.phone,
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(updated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ContactDetails;
+    use crate::db::models::Email;
+    use crate::db::Database;
+
+    fn email(id: &str, from_name: &str, received_at: &str, body: &str) -> Email {
+        Email {
+            subject: Some("Budget".to_string()),
+            from_address: Some("Alice@Example.com".to_string()),
+            from_name: Some(from_name.to_string()),
+            body_text: Some(body.to_string()),
+            received_at: received_at.to_string(),
+            ..Email::fixture(id)
+        }
+    }
+
+    #[test]
+    fn sender_names_and_bodies_feed_empty_contact_fields() {
+        let path = std::env::temp_dir().join(format!("ess-contacts-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        for (id, name, received_at) in [
+            ("m1", "Alice Smith", "2026-02-01T10:00:00Z"),
+            ("m2", "alice@example.com", "2026-02-02T10:00:00Z"),
+            ("m3", "Alice Smith", "2026-02-03T10:00:00Z"),
+            ("m4", "A. Smith", "2026-02-04T10:00:00Z"),
+        ] {
+            db.insert_email(&email(id, name, received_at, &format!("body {id}")))
+                .expect("insert email");
+        }
+        db.update_contact_stats("alice@example.com")
+            .expect("add contact");
+
+        assert_eq!(db.contacts_matching("ALICE@", 5).expect("match").len(), 1);
+        assert!(db.contacts_matching("%", 5).expect("wildcard").is_empty());
+
+        let names = db.sender_display_names().expect("names");
+        assert_eq!(names["alice@example.com"], "Alice Smith");
+
+        let mut seen = Vec::new();
+        db.for_each_sender_bodies(2, |address, bodies| {
+            seen.push((address.to_string(), bodies.to_vec()))
+        })
+        .expect("bodies");
+        assert_eq!(
+            seen,
+            [(
+                "alice@example.com".to_string(),
+                vec!["body m4".to_string(), "body m3".to_string()]
+            )]
+        );
+
+        let details = ContactDetails {
+            email_address: "alice@example.com".to_string(),
+            display_name: Some("Alice Smith".to_string()),
+            company: Some("Acme Inc.".to_string()),
+            phone: None,
+        };
+        assert_eq!(
+            db.store_contact_details(std::slice::from_ref(&details))
+                .expect("store"),
+            1
+        );
+        db.store_contact_details(&[ContactDetails {
+            company: Some("Other Ltd".to_string()),
+            phone: Some("+1 555 0100".to_string()),
+            ..details
+        }])
+        .expect("store again");
+        let contact = db.get_contacts(None).expect("contacts").remove(0);
+        assert_eq!(contact.display_name.as_deref(), Some("Alice Smith"));
+        assert_eq!(contact.company.as_deref(), Some("Acme Inc."));
+        assert_eq!(contact.phone.as_deref(), Some("+1 555 0100"));
+
+        let _ = std::fs::remove_file(path);
+    }
+}
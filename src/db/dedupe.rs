@@ -0,0 +1,454 @@
+//! Copies of one message stored under different ids, e.g. once by `ess
+//! import` and again by a sync. Within an account's folder, emails are the
+//! same message when their Message-IDs match, or, for emails without one,
+//! when sender, subject, date and body are identical. A message in two
+//! folders, like one sent to yourself in Inbox and Sent Items, is two emails.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+
+use crate::db::models::Email;
+use crate::db::{Database, DbError};
+
+/// Keeps new syncs from storing a second copy of a message in a folder.
+/// Created by migration v30 when no duplicates are stored, otherwise by
+/// `ess dedupe --apply`.
+pub(crate) const UNIQUE_MESSAGE_ID_INDEX: &str = r#"
+    CREATE UNIQUE INDEX IF NOT EXISTS idx_emails_account_folder_message_id
+        ON emails(account_id, COALESCE(folder, ''), internet_message_id)
+        WHERE account_id IS NOT NULL AND internet_message_id IS NOT NULL
+            AND internet_message_id != ''
+"#;
+
+/// Copies of one message: the one kept and the ones to remove.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DuplicateGroup {
+    pub account_id: Option<String>,
+    pub folder: Option<String>,
+    /// The normalized Message-ID, or `content:<hash>` for emails without one.
+    pub key: String,
+    pub subject: Option<String>,
+    pub keep: String,
+    pub remove: Vec<String>,
+}
+
+/// What decides which copy is kept, compared field by field.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Richness {
+    attachments: i64,
+    has_html: bool,
+    body_chars: i64,
+    /// Stored by a sync rather than by `ess import`.
+    synced: bool,
+    has_web_link: bool,
+    has_folder: bool,
+    has_conversation: bool,
+}
+
+struct Candidate {
+    id: String,
+    account_id: Option<String>,
+    folder: Option<String>,
+    key: String,
+    subject: Option<String>,
+    richness: Richness,
+}
+
+/// Message-IDs compare without angle brackets, whitespace or case.
+pub fn normalize_message_id(message_id: &str) -> String {
+    message_id
+        .trim()
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+        .trim()
+        .to_lowercase()
+}
+
+fn content_key(
+    from_address: Option<&str>,
+    subject: Option<&str>,
+    date: &str,
+    body: Option<&str>,
+) -> String {
+    let mut hasher = DefaultHasher::new();
+    from_address
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase()
+        .hash(&mut hasher);
+    subject.unwrap_or_default().trim().hash(&mut hasher);
+    date.hash(&mut hasher);
+    body.unwrap_or_default().trim().hash(&mut hasher);
+    format!("content:{:016x}", hasher.finish())
+}
+
+impl Database {
+    /// Every group of emails that are copies of one message, with the
+    /// richest copy to keep: the one with the most attachments, then an
+    /// HTML body, the longest text body, a sync rather than an import as
+    /// its source, a web link, a folder and a conversation.
+    pub fn find_duplicates(&self) -> Result<Vec<DuplicateGroup>, DbError> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT e.id, e.account_id, e.internet_message_id, e.from_address, e.subject,
+                   COALESCE(e.sent_at, e.received_at),
+                   CASE WHEN trim(COALESCE(e.internet_message_id, '')) = ''
+                        THEN e.body_text END,
+                   (SELECT COUNT(*) FROM attachments a WHERE a.email_id = e.id),
+                   trim(COALESCE(e.body_html, '')) != '',
+                   length(COALESCE(e.body_text, '')),
+                   json_extract(e.metadata, '$.archive_connector') IS NULL,
+                   e.web_link IS NOT NULL, e.folder IS NOT NULL, e.conversation_id IS NOT NULL,
+                   e.folder
+            FROM emails e
+            "#,
+        )?;
+        let candidates = stmt
+            .query_map([], |row| {
+                let message_id: Option<String> = row.get(2)?;
+                let subject: Option<String> = row.get(4)?;
+                let key = match message_id.as_deref().map(normalize_message_id) {
+                    Some(message_id) if !message_id.is_empty() => message_id,
+                    _ => content_key(
+                        row.get::<_, Option<String>>(3)?.as_deref(),
+                        subject.as_deref(),
+                        &row.get::<_, String>(5)?,
+                        row.get::<_, Option<String>>(6)?.as_deref(),
+                    ),
+                };
+                Ok(Candidate {
+                    id: row.get(0)?,
+                    account_id: row.get(1)?,
+                    folder: row.get(14)?,
+                    key,
+                    subject,
+                    richness: Richness {
+                        attachments: row.get(7)?,
+                        has_html: row.get(8)?,
+                        body_chars: row.get(9)?,
+                        synced: row.get(10)?,
+                        has_web_link: row.get(11)?,
+                        has_folder: row.get(12)?,
+                        has_conversation: row.get(13)?,
+                    },
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        type GroupKey = (Option<String>, String, String);
+        let mut groups: BTreeMap<GroupKey, Vec<Candidate>> = BTreeMap::new();
+        for candidate in candidates {
+            groups
+                .entry((
+                    candidate.account_id.clone(),
+                    candidate.folder.clone().unwrap_or_default(),
+                    candidate.key.clone(),
+                ))
+                .or_default()
+                .push(candidate);
+        }
+        Ok(groups
+            .into_values()
+            .filter(|copies| copies.len() > 1)
+            .map(|mut copies| {
+                // Richest first; among equals, the smallest id.
+                copies.sort_by(|a, b| b.richness.cmp(&a.richness).then_with(|| a.id.cmp(&b.id)));
+                let kept = copies.remove(0);
+                DuplicateGroup {
+                    account_id: kept.account_id,
+                    folder: kept.folder,
+                    key: kept.key,
+                    subject: kept.subject,
+                    keep: kept.id,
+                    remove: copies.into_iter().map(|copy| copy.id).collect(),
+                }
+            })
+            .collect())
+    }
+
+    /// Removes the extra copies of each group in one transaction, moving
+    /// their stars, collection memberships and commitments to the copy
+    /// kept, then adds the unique Message-ID index. Returns the number of
+    /// emails removed.
+    pub fn remove_duplicates(&self, groups: &[DuplicateGroup]) -> Result<usize, DbError> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut removed = 0;
+        for group in groups {
+            for id in &group.remove {
+                removed += merge_copy(&tx, &group.keep, id)?;
+            }
+        }
+        tx.execute_batch(UNIQUE_MESSAGE_ID_INDEX)?;
+        tx.commit()?;
+        Ok(removed)
+    }
+
+    /// Whether the unique Message-ID index exists yet.
+    pub fn has_unique_message_ids(&self) -> Result<bool, DbError> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT 1 FROM sqlite_master WHERE type = 'index' AND name = 'idx_emails_account_folder_message_id'",
+                [],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some())
+    }
+
+    /// Ids of other stored emails in `email`'s account and folder with its
+    /// exact Message-ID.
+    pub fn stored_copies_of(&self, email: &Email) -> Result<Vec<String>, DbError> {
+        stored_copies(&self.conn, email)
+    }
+
+    /// Folds the other stored copies of `email` into it before it is
+    /// stored, for a provider that hands a message a new id: their stars,
+    /// collection memberships and commitments move to `email.id`, as in
+    /// `remove_duplicates`, and the copies are deleted. Returns their ids
+    /// so the caller can drop them from the search index. Opens no
+    /// transaction, so it can run inside the caller's.
+    pub fn merge_stored_copies(&self, email: &Email) -> Result<Vec<String>, DbError> {
+        let copies = stored_copies(&self.conn, email)?;
+        for copy in &copies {
+            merge_copy(&self.conn, &email.id, copy)?;
+        }
+        Ok(copies)
+    }
+
+    /// Stores `email`, a stored email the provider moved under a new id,
+    /// and carries the local state of its old id `from` over: its stars,
+    /// collection memberships, commitments, tags and attachment records
+    /// move to `email.id`, then `from` is deleted. Other stored copies in
+    /// the new folder must be folded in with `merge_stored_copies` first.
+    pub fn move_email(&self, from: &str, email: &Email) -> Result<(), DbError> {
+        let tx = self.conn.unchecked_transaction()?;
+        self.insert_email(email)?;
+        for sql in [
+            "UPDATE attachments SET email_id = ?1 WHERE email_id = ?2",
+            "UPDATE OR IGNORE tags SET email_id = ?1 WHERE email_id = ?2",
+        ] {
+            tx.execute(sql, params![email.id, from])?;
+        }
+        merge_copy(&tx, &email.id, from)?;
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+/// Moves the local state of email `from` to `keep`, then deletes `from`.
+fn merge_copy(conn: &Connection, keep: &str, from: &str) -> Result<usize, DbError> {
+    for sql in [
+        "UPDATE OR IGNORE stars SET email_id = ?1 WHERE email_id = ?2",
+        "UPDATE OR IGNORE collection_emails SET email_id = ?1 WHERE email_id = ?2",
+        "UPDATE followups SET email_id = ?1 WHERE email_id = ?2",
+    ] {
+        conn.execute(sql, params![keep, from])?;
+    }
+    delete_email_rows(conn, from)
+}
+
+pub(crate) fn stored_copies(conn: &Connection, email: &Email) -> Result<Vec<String>, DbError> {
+    let (Some(account_id), Some(message_id)) = (
+        email.account_id.as_deref(),
+        email
+            .internet_message_id
+            .as_deref()
+            .filter(|id| !id.is_empty()),
+    ) else {
+        return Ok(Vec::new());
+    };
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT id FROM emails
+        WHERE account_id = ?1 AND internet_message_id = ?2 AND id != ?3
+          AND COALESCE(folder, '') = COALESCE(?4, '')
+        "#,
+    )?;
+    let ids = stmt
+        .query_map(
+            params![account_id, message_id, email.id, email.folder],
+            |row| row.get(0),
+        )?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(ids)
+}
+
+/// Deletes an email and the rows that refer to it without a cascade.
+pub(crate) fn delete_email_rows(conn: &Connection, id: &str) -> Result<usize, DbError> {
+    let deleted = conn.execute("DELETE FROM emails WHERE id = ?", [id])?;
+    conn.execute("DELETE FROM embeddings WHERE email_id = ?", [id])?;
+    conn.execute("DELETE FROM stars WHERE email_id = ?", [id])?;
+    conn.execute("DELETE FROM collection_emails WHERE email_id = ?", [id])?;
+    Ok(deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::models::{Account, AccountType, Attachment, Email};
+    use crate::db::Database;
+
+    fn email(id: &str, message_id: Option<&str>, body_html: Option<&str>) -> Email {
+        Email {
+            internet_message_id: message_id.map(str::to_string),
+            account_id: Some("work".to_string()),
+            subject: Some("Budget".to_string()),
+            from_address: Some("alice@example.com".to_string()),
+            body_text: Some("Numbers attached.".to_string()),
+            body_html: body_html.map(str::to_string),
+            received_at: "2026-02-01T10:00:00Z".to_string(),
+            ..Email::fixture(id)
+        }
+    }
+
+    #[test]
+    fn duplicates_keep_the_richest_copy_and_its_local_state() {
+        let path = std::env::temp_dir().join(format!("ess-dedupe-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        db.insert_account(&Account {
+            account_id: "work".to_string(),
+            email_address: "me@example.com".to_string(),
+            display_name: None,
+            tenant_id: None,
+            account_type: AccountType::Professional,
+            enabled: true,
+            last_sync: None,
+            config: None,
+        })
+        .expect("insert account");
+        db.conn
+            .execute("DROP INDEX idx_emails_account_folder_message_id", [])
+            .expect("drop index");
+        assert!(!db.has_unique_message_ids().expect("index"));
+
+        db.insert_email(&email("import-1", Some("<ABC@example.com>"), None))
+            .expect("insert import");
+        db.insert_email(&email(
+            "sync-1",
+            Some("abc@example.com"),
+            Some("<p>Numbers</p>"),
+        ))
+        .expect("insert sync");
+        db.insert_email(&email("plain-1", None, None))
+            .expect("insert plain");
+        db.insert_email(&email("plain-2", None, None))
+            .expect("insert plain copy");
+        db.insert_email(&email("other", Some("other@example.com"), None))
+            .expect("insert other");
+        db.star_email("import-1").expect("star");
+
+        let groups = db.find_duplicates().expect("find");
+        let summary: Vec<_> = groups
+            .iter()
+            .map(|group| (group.keep.as_str(), group.remove.clone()))
+            .collect();
+        assert_eq!(summary.len(), 2);
+        assert!(summary.contains(&("sync-1", vec!["import-1".to_string()])));
+        assert!(summary.contains(&("plain-1", vec!["plain-2".to_string()])));
+
+        assert_eq!(db.remove_duplicates(&groups).expect("remove"), 2);
+        assert!(db.has_unique_message_ids().expect("index"));
+        assert!(db.starred_at("sync-1").expect("starred").is_some());
+        assert!(db.find_duplicates().expect("find again").is_empty());
+
+        // A later copy under a new id cannot sit beside the stored one; it
+        // takes over the stored one's star once merged.
+        let resynced = email("sync-2", Some("abc@example.com"), None);
+        assert!(db.insert_email(&resynced).is_err());
+        assert_eq!(
+            db.merge_stored_copies(&resynced).expect("merge"),
+            ["sync-1"]
+        );
+        db.insert_email(&resynced).expect("insert resynced");
+        assert!(db.get_email("sync-1").expect("get").is_none());
+        assert!(db.get_email("sync-2").expect("get").is_some());
+        assert!(db.starred_at("sync-2").expect("starred").is_some());
+
+        // The same message in another folder is another email.
+        let sent = Email {
+            folder: Some("Sent Items".to_string()),
+            ..email("sent-1", Some("abc@example.com"), None)
+        };
+        assert!(db.merge_stored_copies(&sent).expect("merge").is_empty());
+        db.insert_email(&sent).expect("insert sent copy");
+        assert!(db.get_email("sync-2").expect("get").is_some());
+        assert!(db.starred_at("sync-2").expect("starred").is_some());
+        assert!(db.find_duplicates().expect("find again").is_empty());
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn archived_emails_keep_their_local_state() {
+        let path = std::env::temp_dir().join(format!("ess-move-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        db.insert_account(&Account {
+            account_id: "work".to_string(),
+            email_address: "me@example.com".to_string(),
+            display_name: None,
+            tenant_id: None,
+            account_type: AccountType::Professional,
+            enabled: true,
+            last_sync: None,
+            config: None,
+        })
+        .expect("insert account");
+        let mut stored = email("inbox-1", Some("abc@example.com"), None);
+        db.insert_email(&stored).expect("insert");
+        db.replace_attachments(
+            "inbox-1",
+            &[Attachment {
+                id: "att-1".to_string(),
+                email_id: "inbox-1".to_string(),
+                name: Some("budget.xlsx".to_string()),
+                content_type: None,
+                size_bytes: Some(2048),
+                is_inline: Some(false),
+            }],
+        )
+        .expect("attachments");
+        db.store_attachment_hash("att-1", "abc123").expect("hash");
+        db.star_email("inbox-1").expect("star");
+        db.add_to_collection("budget", &["inbox-1".to_string()])
+            .expect("collect");
+
+        // Gmail keeps the id: the row is updated in place.
+        stored.folder = Some("Archive".to_string());
+        db.update_email_flags(&stored).expect("archive in place");
+        assert!(db.starred_at("inbox-1").expect("starred").is_some());
+        assert!(db
+            .collection_has_email("budget", "inbox-1")
+            .expect("member"));
+        assert_eq!(db.get_attachments("inbox-1").expect("attachments").len(), 1);
+
+        // Graph hands out a new id: the new row takes over the old one's state.
+        let moved = Email {
+            folder: Some("Deleted Items".to_string()),
+            ..email("deleted-1", Some("abc@example.com"), None)
+        };
+        assert!(db.merge_stored_copies(&moved).expect("merge").is_empty());
+        db.move_email("inbox-1", &moved).expect("move");
+        assert!(db.get_email("inbox-1").expect("get").is_none());
+        assert!(db.starred_at("inbox-1").expect("starred").is_none());
+        assert!(db.starred_at("deleted-1").expect("starred").is_some());
+        assert!(db
+            .collection_has_email("budget", "deleted-1")
+            .expect("member"));
+        let attachments = db.get_attachments("deleted-1").expect("attachments");
+        assert_eq!(attachments.len(), 1);
+        assert_eq!(attachments[0].id, "att-1");
+        let hashes: i64 = db
+            .conn
+            .query_row("SELECT COUNT(*) FROM attachment_hashes", [], |row| {
+                row.get(0)
+            })
+            .expect("hashes");
+        assert_eq!(hashes, 1);
+
+        let _ = std::fs::remove_file(path);
+    }
+}
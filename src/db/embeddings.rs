@@ -0,0 +1,169 @@
+use rusqlite::{params, params_from_iter};
+
+use crate::db::{Database, DbError};
+
+impl Database {
+    /// Stores the vector for one email under `model`, replacing any earlier
+    /// one.
+    pub fn upsert_embedding(
+        &self,
+        email_id: &str,
+        model: &str,
+        vector: &[f32],
+    ) -> Result<(), DbError> {
+        self.conn.execute(
+            r#"
+            INSERT OR REPLACE INTO embeddings (email_id, model, dimensions, vector)
+            VALUES (?1, ?2, ?3, ?4)
+            "#,
+            params![email_id, model, vector.len() as i64, encode_vector(vector)],
+        )?;
+        Ok(())
+    }
+
+    /// Emails that have no vector for `model` yet, newest first.
+    pub fn email_ids_without_embedding(
+        &self,
+        model: &str,
+        limit: usize,
+    ) -> Result<Vec<String>, DbError> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id FROM emails
+            WHERE id NOT IN (SELECT email_id FROM embeddings WHERE model = ?1)
+            ORDER BY received_at DESC, id DESC
+            LIMIT ?2
+            "#,
+        )?;
+        let ids = stmt
+            .query_map(params![model, limit as i64], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(ids)
+    }
+
+    /// Stored vectors for `model` whose email exists, limited to emails
+    /// matching `clause` (a `WHERE` fragment over `emails` with `?`
+    /// placeholders for `params`).
+    pub fn embeddings_matching(
+        &self,
+        model: &str,
+        clause: &str,
+        params: &[String],
+    ) -> Result<Vec<(String, Vec<f32>)>, DbError> {
+        let sql = format!(
+            r#"
+            SELECT email_id, vector FROM embeddings
+            WHERE model = ? AND email_id IN (SELECT id FROM emails WHERE {clause})
+            "#
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map(
+                params_from_iter(std::iter::once(model).chain(params.iter().map(String::as_str))),
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        decode_vector(&row.get::<_, Vec<u8>>(1)?),
+                    ))
+                },
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    pub fn count_embeddings(&self, model: &str) -> Result<i64, DbError> {
+        let count = self.conn.query_row(
+            "SELECT COUNT(*) FROM embeddings WHERE model = ?",
+            [model],
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+
+    pub fn clear_embeddings(&self, model: &str) -> Result<usize, DbError> {
+        let deleted = self
+            .conn
+            .execute("DELETE FROM embeddings WHERE model = ?", [model])?;
+        Ok(deleted)
+    }
+}
+
+fn encode_vector(vector: &[f32]) -> Vec<u8> {
+    vector
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+fn decode_vector(raw: &[u8]) -> Vec<f32> {
+    raw.chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::models::{Account, AccountType, Email};
+    use crate::db::Database;
+
+    fn email(id: &str, received_at: &str) -> Email {
+        Email {
+            account_id: Some("acc".to_string()),
+            subject: Some("Budget".to_string()),
+            from_address: Some("alice@example.com".to_string()),
+            received_at: received_at.to_string(),
+            is_read: Some(true),
+            has_attachments: Some(false),
+            folder: Some("inbox".to_string()),
+            ..Email::fixture(id)
+        }
+    }
+
+    #[test]
+    fn embeddings_round_trip_and_survive_resync() {
+        let path = std::env::temp_dir().join(format!("ess-embeddings-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        db.insert_account(&Account {
+            account_id: "acc".to_string(),
+            email_address: "me@example.com".to_string(),
+            display_name: None,
+            tenant_id: None,
+            account_type: AccountType::Professional,
+            enabled: true,
+            last_sync: None,
+            config: None,
+        })
+        .expect("insert account");
+        db.insert_email(&email("old", "2026-01-01T10:00:00Z"))
+            .expect("insert email");
+        db.insert_email(&email("new", "2026-02-01T10:00:00Z"))
+            .expect("insert email");
+
+        assert_eq!(
+            db.email_ids_without_embedding("m", 10).expect("missing"),
+            vec!["new", "old"]
+        );
+        db.upsert_embedding("new", "m", &[0.5, -1.25, 3.0])
+            .expect("store vector");
+        db.insert_email(&email("new", "2026-02-01T10:00:00Z"))
+            .expect("resync email");
+        assert_eq!(
+            db.email_ids_without_embedding("m", 10).expect("missing"),
+            vec!["old"]
+        );
+        assert_eq!(
+            db.email_ids_without_embedding("other", 1).expect("missing"),
+            vec!["new"]
+        );
+
+        let rows = db
+            .embeddings_matching("m", "received_at >= ?", &["2026-01-15".to_string()])
+            .expect("matching vectors");
+        assert_eq!(rows, vec![("new".to_string(), vec![0.5, -1.25, 3.0])]);
+
+        db.delete_email("new").expect("delete email");
+        assert_eq!(db.count_embeddings("m").expect("count"), 0);
+
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,220 @@
+//! Encryption at rest with SQLCipher, available in builds with the
+//! `sqlcipher` feature.
+//!
+//! The key is `ESS_DB_KEY`, else the `database-key` entry of service `ess`
+//! in the OS keychain (see [`crate::secrets::keychain`]).
+//! [`Database::open`](super::Database::open) tells an encrypted file from a
+//! plain one by its header, so only encrypted databases need a key;
+//! `ess db encrypt` and `ess db decrypt` convert between the two.
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use rusqlite::Connection;
+
+use crate::db::DbError;
+use crate::env;
+use crate::secrets::keychain::{Keychain, SecretStore, SERVICE as KEYCHAIN_SERVICE};
+
+/// The first 16 bytes of every unencrypted SQLite database.
+const PLAINTEXT_HEADER: &[u8; 16] = b"SQLite format 3\0";
+const KEYCHAIN_ACCOUNT: &str = "database-key";
+
+/// Whether the database at `path` is encrypted. A missing or empty file
+/// is not.
+pub fn is_encrypted(path: &Path) -> Result<bool, DbError> {
+    let mut header = Vec::with_capacity(PLAINTEXT_HEADER.len());
+    match File::open(path) {
+        Ok(file) => {
+            file.take(PLAINTEXT_HEADER.len() as u64)
+                .read_to_end(&mut header)?;
+        }
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(error) => return Err(error.into()),
+    }
+    Ok(!header.is_empty() && header != PLAINTEXT_HEADER)
+}
+
+/// `ESS_DB_KEY`, else the key stored in the OS keychain.
+pub fn database_key() -> Option<String> {
+    env::var(env::DB_KEY).or_else(|| Keychain.get(KEYCHAIN_ACCOUNT))
+}
+
+fn require_sqlcipher() -> Result<(), DbError> {
+    if cfg!(feature = "sqlcipher") {
+        Ok(())
+    } else {
+        Err(DbError::Config(
+            "this build of ess has no SQLCipher support; rebuild with `--features sqlcipher`"
+                .to_string(),
+        ))
+    }
+}
+
+/// The configured key; `why` leads the error when there is none.
+fn require_key(why: &str) -> Result<String, DbError> {
+    database_key().ok_or_else(|| {
+        DbError::Config(format!(
+            "{why}; set {} or store the key in the OS keychain \
+             (service `{KEYCHAIN_SERVICE}`, account `{KEYCHAIN_ACCOUNT}`)",
+            env::DB_KEY
+        ))
+    })
+}
+
+/// A passphrase as an SQL string literal.
+fn quote_key(key: &str) -> String {
+    format!("'{}'", key.replace('\'', "''"))
+}
+
+/// Opens `path`, keying the connection when the file is encrypted, or
+/// when it is new and `ESS_DB_KEY` is set in a SQLCipher build.
+pub(crate) fn open_connection(path: &Path) -> Result<Connection, DbError> {
+    let key = if is_encrypted(path)? {
+        require_sqlcipher()?;
+        Some(require_key(&format!("{} is encrypted", path.display()))?)
+    } else if cfg!(feature = "sqlcipher") && !path.exists() {
+        env::var(env::DB_KEY)
+    } else {
+        None
+    };
+
+    match key {
+        Some(key) => keyed_connection(path, &key),
+        None => Ok(Connection::open(path)?),
+    }
+}
+
+/// Opens `path` under `key`, checking the key fits.
+fn keyed_connection(path: &Path, key: &str) -> Result<Connection, DbError> {
+    let conn = Connection::open(path)?;
+    conn.execute_batch(&format!("PRAGMA key = {}", quote_key(key)))?;
+    // The key is only checked on first read.
+    conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |_| Ok(()))
+        .map_err(|_| {
+            DbError::Config(format!(
+                "cannot decrypt {}: wrong database key",
+                path.display()
+            ))
+        })?;
+    Ok(conn)
+}
+
+/// Whether `ess db encrypt` encrypted the database or found it already
+/// encrypted; likewise for `ess db decrypt`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conversion {
+    Converted,
+    Unchanged,
+}
+
+/// Rewrites the database at `path` encrypted under the configured key.
+pub fn encrypt_database(path: &Path) -> Result<Conversion, DbError> {
+    require_sqlcipher()?;
+    if is_encrypted(path)? {
+        return Ok(Conversion::Unchanged);
+    }
+    let key = require_key("no database key")?;
+    let conn = Connection::open(path)?;
+    export(&conn, path, &quote_key(&key))?;
+    Ok(Conversion::Converted)
+}
+
+/// Rewrites the encrypted database at `path` as a plain SQLite file.
+pub fn decrypt_database(path: &Path) -> Result<Conversion, DbError> {
+    require_sqlcipher()?;
+    if !is_encrypted(path)? {
+        return Ok(Conversion::Unchanged);
+    }
+    let key = require_key(&format!("{} is encrypted", path.display()))?;
+    let conn = keyed_connection(path, &key)?;
+    export(&conn, path, "''")?;
+    Ok(Conversion::Converted)
+}
+
+/// Copies everything `conn` holds into a sibling file keyed with `key`
+/// (`''` for none) with `sqlcipher_export`, then puts the copy in place of
+/// `path`.
+fn export(conn: &Connection, path: &Path, key: &str) -> Result<(), DbError> {
+    let staging = staging_path(path);
+    let _ = fs::remove_file(&staging);
+    let staged = staging.to_string_lossy().replace('\'', "''");
+    let result = conn.execute_batch(&format!(
+        "ATTACH DATABASE '{staged}' AS converted KEY {key};
+         SELECT sqlcipher_export('converted');
+         DETACH DATABASE converted;"
+    ));
+    if let Err(error) = result {
+        let _ = fs::remove_file(&staging);
+        return Err(error.into());
+    }
+    fs::rename(&staging, path)?;
+    Ok(())
+}
+
+fn staging_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".converting");
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "sqlcipher")]
+    use super::{export, keyed_connection};
+    use super::{is_encrypted, quote_key};
+    use crate::db::Database;
+
+    #[test]
+    fn encrypted_files_are_told_apart_by_header() {
+        let dir = std::env::temp_dir().join(format!("ess-cipher-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("create dir");
+        let plain = dir.join("plain.db");
+        assert!(!is_encrypted(&plain).expect("missing file"));
+        Database::open(&plain).expect("open db");
+        assert!(!is_encrypted(&plain).expect("plain file"));
+
+        let encrypted = dir.join("encrypted.db");
+        std::fs::write(&encrypted, [0x5a_u8; 64]).expect("write file");
+        assert!(is_encrypted(&encrypted).expect("encrypted file"));
+        if !cfg!(feature = "sqlcipher") {
+            let error = Database::open(&encrypted).err().expect("no sqlcipher");
+            assert!(error.to_string().contains("--features sqlcipher"));
+        }
+
+        assert_eq!(quote_key("it's"), "'it''s'");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(feature = "sqlcipher")]
+    #[test]
+    fn encrypts_and_decrypts_in_place() {
+        let dir = std::env::temp_dir().join(format!("ess-cipher-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("create dir");
+        let path = dir.join("ess.db");
+        let count = |conn: &rusqlite::Connection| -> i64 {
+            conn.query_row("SELECT COUNT(*) FROM emails", [], |row| row.get(0))
+                .expect("count emails")
+        };
+        let db = Database::open(&path).expect("open db");
+        db.insert_email(&crate::db::models::Email::fixture("m1"))
+            .expect("insert email");
+        drop(db);
+
+        let conn = rusqlite::Connection::open(&path).expect("open plain");
+        export(&conn, &path, &quote_key("secret")).expect("encrypt");
+        drop(conn);
+        assert!(is_encrypted(&path).expect("encrypted file"));
+        assert!(keyed_connection(&path, "wrong").is_err());
+        let conn = keyed_connection(&path, "secret").expect("open with key");
+        assert_eq!(count(&conn), 1);
+
+        export(&conn, &path, "''").expect("decrypt");
+        drop(conn);
+        assert!(!is_encrypted(&path).expect("plain file"));
+        let conn = rusqlite::Connection::open(&path).expect("reopen plain");
+        assert_eq!(count(&conn), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,381 @@
+use rusqlite::{params, OptionalExtension, Row};
+use serde::Serialize;
+
+use crate::db::{Database, DbError};
+
+/// A mail folder as the last sync of a Graph account found it, and
+/// whether that sync took its mail.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FolderDiscovery {
+    pub account_id: String,
+    pub folder_id: String,
+    pub parent_folder_id: Option<String>,
+    /// Display names from the top-level folder down, e.g. `Inbox/Clients`.
+    pub path: String,
+    /// The `emails.folder` its mail is stored under; `None` when excluded.
+    pub label: Option<String>,
+    pub included: bool,
+    /// Why an excluded folder is skipped.
+    pub reason: Option<String>,
+    /// Messages in the folder, as the provider counts them.
+    pub total_items: Option<i64>,
+    pub hidden: bool,
+    pub discovered_at: String,
+    /// Emails stored under `label` for the account.
+    pub stored_emails: i64,
+}
+
+/// One folder's syncs so far: what they took in total, when the last one
+/// ran and how it ended.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FolderSyncState {
+    pub account_id: String,
+    pub folder_id: String,
+    /// The `emails.folder` its mail is stored under.
+    pub folder: String,
+    pub display_name: String,
+    pub emails_added: i64,
+    pub emails_updated: i64,
+    pub last_run_at: String,
+    /// When a sync of the folder last ran to the end of its delta round.
+    pub last_delta_at: Option<String>,
+    /// What went wrong in the last sync, `None` when nothing did.
+    pub last_error: Option<String>,
+    pub last_error_at: Option<String>,
+    /// Syncs of the folder that failed since the last one that completed.
+    pub consecutive_failures: i64,
+}
+
+impl FolderSyncState {
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            account_id: row.get(0)?,
+            folder_id: row.get(1)?,
+            folder: row.get(2)?,
+            display_name: row.get(3)?,
+            emails_added: row.get(4)?,
+            emails_updated: row.get(5)?,
+            last_run_at: row.get(6)?,
+            last_delta_at: row.get(7)?,
+            last_error: row.get(8)?,
+            last_error_at: row.get(9)?,
+            consecutive_failures: row.get(10)?,
+        })
+    }
+}
+
+/// The outcome of syncing one folder, as [`Database::record_folder_sync`]
+/// adds it to the folder's [`FolderSyncState`].
+#[derive(Debug, Clone, Copy)]
+pub struct FolderSyncRun<'a> {
+    pub account_id: &'a str,
+    pub folder_id: &'a str,
+    pub folder: &'a str,
+    pub display_name: &'a str,
+    pub emails_added: usize,
+    pub emails_updated: usize,
+    /// Whether the run reached the end of the folder instead of failing or
+    /// being interrupted.
+    pub completed: bool,
+    pub error: Option<&'a str>,
+    pub at: &'a str,
+}
+
+impl Database {
+    /// Adds a sync run to its folder's state.
+    pub fn record_folder_sync(&self, run: &FolderSyncRun<'_>) -> Result<(), DbError> {
+        self.conn.execute(
+            r#"
+            INSERT INTO folder_sync_state (
+                account_id, folder_id, folder, display_name, emails_added, emails_updated,
+                last_run_at, last_delta_at, last_error, last_error_at, consecutive_failures
+            ) VALUES (
+                ?1, ?2, ?3, ?4, ?5, ?6, ?7, CASE WHEN ?8 THEN ?7 END, ?9,
+                CASE WHEN ?9 IS NOT NULL THEN ?7 END, CASE WHEN ?8 OR ?9 IS NULL THEN 0 ELSE 1 END
+            )
+            ON CONFLICT(account_id, folder_id) DO UPDATE SET
+                folder = excluded.folder,
+                display_name = excluded.display_name,
+                emails_added = emails_added + excluded.emails_added,
+                emails_updated = emails_updated + excluded.emails_updated,
+                last_run_at = excluded.last_run_at,
+                last_delta_at = COALESCE(excluded.last_delta_at, last_delta_at),
+                last_error = excluded.last_error,
+                last_error_at = excluded.last_error_at,
+                consecutive_failures = CASE
+                    WHEN excluded.last_delta_at IS NOT NULL THEN 0
+                    WHEN excluded.last_error IS NOT NULL THEN consecutive_failures + 1
+                    ELSE consecutive_failures
+                END
+            "#,
+            params![
+                run.account_id,
+                run.folder_id,
+                run.folder,
+                run.display_name,
+                run.emails_added as i64,
+                run.emails_updated as i64,
+                run.at,
+                run.completed,
+                run.error,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Sync state of the folders of one account or all of them, by account
+    /// and folder.
+    pub fn folder_sync_states(
+        &self,
+        account_id: Option<&str>,
+    ) -> Result<Vec<FolderSyncState>, DbError> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT account_id, folder_id, folder, display_name, emails_added, emails_updated,
+                   last_run_at, last_delta_at, last_error, last_error_at, consecutive_failures
+            FROM folder_sync_state
+            WHERE ?1 IS NULL OR account_id = ?1
+            ORDER BY account_id, folder COLLATE NOCASE
+            "#,
+        )?;
+        let states = stmt
+            .query_map([account_id], FolderSyncState::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(states)
+    }
+
+    pub fn folder_sync_state(
+        &self,
+        account_id: &str,
+        folder_id: &str,
+    ) -> Result<Option<FolderSyncState>, DbError> {
+        let state = self
+            .conn
+            .query_row(
+                r#"
+                SELECT account_id, folder_id, folder, display_name, emails_added, emails_updated,
+                       last_run_at, last_delta_at, last_error, last_error_at, consecutive_failures
+                FROM folder_sync_state
+                WHERE account_id = ?1 AND folder_id = ?2
+                "#,
+                params![account_id, folder_id],
+                FolderSyncState::from_row,
+            )
+            .optional()?;
+        Ok(state)
+    }
+
+    /// Emails of an account stored under `folder`.
+    pub fn count_folder_emails(&self, account_id: &str, folder: &str) -> Result<i64, DbError> {
+        let count = self.conn.query_row(
+            "SELECT COUNT(*) FROM emails WHERE account_id = ?1 AND folder = ?2",
+            params![account_id, folder],
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+
+    /// Replaces the folders recorded for an account with a fresh discovery.
+    /// `stored_emails` is ignored.
+    pub fn replace_discovered_folders(
+        &self,
+        account_id: &str,
+        folders: &[FolderDiscovery],
+    ) -> Result<(), DbError> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "DELETE FROM discovered_folders WHERE account_id = ?",
+            [account_id],
+        )?;
+        for folder in folders {
+            tx.execute(
+                r#"
+                INSERT OR REPLACE INTO discovered_folders (
+                    account_id, folder_id, parent_folder_id, path, label, included, reason,
+                    total_items, hidden, discovered_at
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                "#,
+                params![
+                    account_id,
+                    folder.folder_id,
+                    folder.parent_folder_id,
+                    folder.path,
+                    folder.label,
+                    folder.included,
+                    folder.reason,
+                    folder.total_items,
+                    folder.hidden,
+                    folder.discovered_at,
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Recorded folders of one account or all of them, in path order, with
+    /// the number of emails stored under each label.
+    pub fn discovered_folders(
+        &self,
+        account_id: Option<&str>,
+    ) -> Result<Vec<FolderDiscovery>, DbError> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT d.account_id, d.folder_id, d.parent_folder_id, d.path, d.label, d.included,
+                   d.reason, d.total_items, d.hidden, d.discovered_at,
+                   (SELECT COUNT(*) FROM emails e
+                    WHERE e.account_id = d.account_id AND e.folder = d.label)
+            FROM discovered_folders d
+            WHERE ?1 IS NULL OR d.account_id = ?1
+            ORDER BY d.account_id, d.path COLLATE NOCASE
+            "#,
+        )?;
+        let folders = stmt
+            .query_map([account_id], |row| {
+                Ok(FolderDiscovery {
+                    account_id: row.get(0)?,
+                    folder_id: row.get(1)?,
+                    parent_folder_id: row.get(2)?,
+                    path: row.get(3)?,
+                    label: row.get(4)?,
+                    included: row.get(5)?,
+                    reason: row.get(6)?,
+                    total_items: row.get(7)?,
+                    hidden: row.get(8)?,
+                    discovered_at: row.get(9)?,
+                    stored_emails: row.get(10)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(folders)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FolderDiscovery, FolderSyncRun};
+    use crate::db::models::{Account, AccountType, Email};
+    use crate::db::Database;
+
+    fn folder(id: &str, path: &str, label: Option<&str>, reason: Option<&str>) -> FolderDiscovery {
+        FolderDiscovery {
+            account_id: "acc".to_string(),
+            folder_id: id.to_string(),
+            parent_folder_id: None,
+            path: path.to_string(),
+            label: label.map(str::to_string),
+            included: reason.is_none(),
+            reason: reason.map(str::to_string),
+            total_items: Some(3),
+            hidden: false,
+            discovered_at: "2026-03-01T00:00:00Z".to_string(),
+            stored_emails: 0,
+        }
+    }
+
+    #[test]
+    fn replace_keeps_the_latest_discovery_with_stored_counts() {
+        let path = std::env::temp_dir().join(format!("ess-folders-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        db.replace_discovered_folders("acc", &[folder("old", "Old", Some("old"), None)])
+            .expect("store");
+        db.replace_discovered_folders(
+            "acc",
+            &[
+                folder("f1", "Inbox", Some("inbox"), None),
+                folder("f2", "Sync Issues", None, Some("system folder")),
+            ],
+        )
+        .expect("replace");
+        db.insert_account(&Account {
+            account_id: "acc".to_string(),
+            email_address: "acc@company.com".to_string(),
+            display_name: None,
+            tenant_id: None,
+            account_type: AccountType::Professional,
+            enabled: true,
+            last_sync: None,
+            config: None,
+        })
+        .expect("insert account");
+        let email: Email = serde_json::from_value(serde_json::json!({
+            "id": "m1",
+            "account_id": "acc",
+            "folder": "inbox",
+            "to_addresses": [],
+            "cc_addresses": [],
+            "bcc_addresses": [],
+            "received_at": "2026-03-01T00:00:00Z",
+            "categories": [],
+        }))
+        .expect("email");
+        db.insert_email(&email).expect("insert");
+
+        let folders = db.discovered_folders(Some("acc")).expect("load");
+        assert_eq!(
+            folders.iter().map(|f| f.path.as_str()).collect::<Vec<_>>(),
+            ["Inbox", "Sync Issues"]
+        );
+        assert_eq!(folders[1].reason.as_deref(), Some("system folder"));
+        assert_eq!(folders[0].stored_emails, 1);
+        assert!(db
+            .discovered_folders(Some("other"))
+            .expect("load")
+            .is_empty());
+        assert_eq!(db.discovered_folders(None).expect("load").len(), 2);
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn folder_sync_state_adds_up_runs_and_keeps_the_last_error() {
+        let path = std::env::temp_dir().join(format!("ess-folders-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        let run = FolderSyncRun {
+            account_id: "acc",
+            folder_id: "f1",
+            folder: "inbox",
+            display_name: "Inbox",
+            emails_added: 5,
+            emails_updated: 1,
+            completed: true,
+            error: None,
+            at: "2026-03-01T00:00:00Z",
+        };
+        db.record_folder_sync(&run).expect("first run");
+        db.record_folder_sync(&FolderSyncRun {
+            emails_added: 2,
+            emails_updated: 0,
+            completed: false,
+            error: Some("timed out"),
+            at: "2026-03-02T00:00:00Z",
+            ..run
+        })
+        .expect("failed run");
+
+        let states = db.folder_sync_states(Some("acc")).expect("load");
+        assert_eq!(states.len(), 1);
+        let state = &states[0];
+        assert_eq!((state.emails_added, state.emails_updated), (7, 1));
+        assert_eq!(state.last_run_at, "2026-03-02T00:00:00Z");
+        assert_eq!(state.last_delta_at.as_deref(), Some("2026-03-01T00:00:00Z"));
+        assert_eq!(state.last_error.as_deref(), Some("timed out"));
+        assert_eq!(state.last_error_at.as_deref(), Some("2026-03-02T00:00:00Z"));
+        assert_eq!(state.consecutive_failures, 1);
+
+        db.record_folder_sync(&FolderSyncRun {
+            at: "2026-03-03T00:00:00Z",
+            ..run
+        })
+        .expect("clean run");
+        let state = db
+            .folder_sync_state("acc", "f1")
+            .expect("load")
+            .expect("state");
+        assert_eq!(state.last_delta_at.as_deref(), Some("2026-03-03T00:00:00Z"));
+        assert_eq!(state.last_error, None);
+        assert_eq!(state.consecutive_failures, 0);
+
+        let _ = std::fs::remove_file(path);
+    }
+}
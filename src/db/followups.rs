@@ -0,0 +1,204 @@
+use chrono::{NaiveDate, SecondsFormat, Utc};
+use rusqlite::{params, OptionalExtension};
+use serde::Serialize;
+
+use crate::db::models::Email;
+use crate::db::{Database, DbError};
+
+/// `followups.kind` of a promise found in sent mail by `ess commitments`.
+pub const COMMITMENT_KIND: &str = "commitment";
+
+/// Something to follow up on, with the email it came from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Followup {
+    pub id: i64,
+    pub kind: String,
+    pub email_id: String,
+    /// The sentence it was found in.
+    pub text: String,
+    pub due_date: Option<NaiveDate>,
+    /// `rule` or `model`.
+    pub source: String,
+    pub created_at: String,
+    pub done_at: Option<String>,
+    pub subject: Option<String>,
+    pub to_addresses: Vec<String>,
+    pub sent_at: String,
+}
+
+/// A commitment to store for a scanned email.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundFollowup {
+    pub text: String,
+    pub due_date: Option<NaiveDate>,
+    pub source: String,
+}
+
+/// The outcome of scanning one email.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScannedEmail {
+    pub email_id: String,
+    pub found: Vec<FoundFollowup>,
+    /// False when the model failed and only the rules ran; the email is
+    /// scanned again while a model is configured.
+    pub complete: bool,
+}
+
+impl Database {
+    /// Up to `limit` emails sent from an account address that have not been
+    /// scanned for commitments, in id order after `after_id`. With
+    /// `retry_partial`, emails scanned without the model count as not
+    /// scanned.
+    pub fn sent_emails_to_scan(
+        &self,
+        after_id: Option<&str>,
+        limit: usize,
+        retry_partial: bool,
+    ) -> Result<Vec<Email>, DbError> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT e.id, e.internet_message_id, e.conversation_id, e.account_id, e.subject,
+                   e.from_address, e.from_name, e.to_addresses, e.cc_addresses, e.bcc_addresses,
+                   e.body_text, e.body_html, e.body_preview, e.received_at, e.sent_at,
+                   e.importance, e.is_read, e.has_attachments, e.folder, e.categories,
+                   e.flag_status, e.web_link, e.metadata
+            FROM emails e
+            LEFT JOIN followup_scans s ON s.email_id = e.id
+            WHERE (s.email_id IS NULL OR (?3 AND s.complete = 0))
+              AND lower(trim(e.from_address)) IN (SELECT lower(email_address) FROM accounts)
+              AND e.id > ?1
+            ORDER BY e.id
+            LIMIT ?2
+            "#,
+        )?;
+        let emails = stmt
+            .query_map(
+                params![after_id.unwrap_or(""), limit as i64, retry_partial],
+                Email::from_row,
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(emails)
+    }
+
+    /// Stores what each scan found, in one transaction. Commitments found
+    /// again keep their id and done state; open ones no longer found are
+    /// dropped.
+    pub fn store_commitment_scans(&self, scanned: &[ScannedEmail]) -> Result<(), DbError> {
+        let now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+        let tx = self.conn.unchecked_transaction()?;
+        for email in scanned {
+            let mut kept = Vec::new();
+            for found in &email.found {
+                let existing: Option<i64> = tx
+                    .query_row(
+                        "SELECT id FROM followups WHERE email_id = ?1 AND kind = ?2 AND text = ?3",
+                        params![email.email_id, COMMITMENT_KIND, found.text],
+                        |row| row.get(0),
+                    )
+                    .optional()?;
+                let id = match existing {
+                    Some(id) => {
+                        tx.execute(
+                            "UPDATE followups SET due_date = ?2, source = ?3 WHERE id = ?1",
+                            params![id, found.due_date.map(|day| day.to_string()), found.source],
+                        )?;
+                        id
+                    }
+                    None => {
+                        tx.execute(
+                            r#"
+                            INSERT INTO followups
+                                (email_id, kind, text, due_date, source, created_at)
+                            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                            "#,
+                            params![
+                                email.email_id,
+                                COMMITMENT_KIND,
+                                found.text,
+                                found.due_date.map(|day| day.to_string()),
+                                found.source,
+                                now
+                            ],
+                        )?;
+                        tx.last_insert_rowid()
+                    }
+                };
+                kept.push(id);
+            }
+            let stale = tx
+                .prepare(
+                    "SELECT id FROM followups WHERE email_id = ?1 AND kind = ?2 AND done_at IS NULL",
+                )?
+                .query_map(params![email.email_id, COMMITMENT_KIND], |row| {
+                    row.get::<_, i64>(0)
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            for id in stale.into_iter().filter(|id| !kept.contains(id)) {
+                tx.execute("DELETE FROM followups WHERE id = ?", [id])?;
+            }
+            tx.execute(
+                "INSERT OR REPLACE INTO followup_scans (email_id, complete, scanned_at) \
+                 VALUES (?1, ?2, ?3)",
+                params![email.email_id, email.complete, now],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Drops follow-ups whose email has been deleted. They are kept apart
+    /// from `emails` so that storing an email again does not lose them.
+    pub fn prune_followups(&self) -> Result<usize, DbError> {
+        Ok(self.conn.execute(
+            "DELETE FROM followups WHERE email_id NOT IN (SELECT id FROM emails)",
+            [],
+        )?)
+    }
+
+    /// Follow-ups of `kind`, earliest due first and undated ones last;
+    /// `open_only` leaves out those marked done.
+    pub fn followups(&self, kind: &str, open_only: bool) -> Result<Vec<Followup>, DbError> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT f.id, f.kind, f.email_id, f.text, f.due_date, f.source, f.created_at,
+                   f.done_at, e.subject, e.to_addresses, COALESCE(e.sent_at, e.received_at)
+            FROM followups f
+            JOIN emails e ON e.id = f.email_id
+            WHERE f.kind = ?1 AND (?2 = 0 OR f.done_at IS NULL)
+            ORDER BY f.due_date IS NULL, f.due_date, e.received_at DESC, f.id
+            "#,
+        )?;
+        let followups = stmt
+            .query_map(params![kind, open_only], |row| {
+                let due_date: Option<String> = row.get(4)?;
+                let to_addresses: Option<String> = row.get(9)?;
+                Ok(Followup {
+                    id: row.get(0)?,
+                    kind: row.get(1)?,
+                    email_id: row.get(2)?,
+                    text: row.get(3)?,
+                    due_date: due_date.and_then(|day| day.parse().ok()),
+                    source: row.get(5)?,
+                    created_at: row.get(6)?,
+                    done_at: row.get(7)?,
+                    subject: row.get(8)?,
+                    to_addresses: to_addresses
+                        .and_then(|raw| serde_json::from_str(&raw).ok())
+                        .unwrap_or_default(),
+                    sent_at: row.get(10)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(followups)
+    }
+
+    /// Marks a follow-up done. False when there is no follow-up `id`.
+    pub fn complete_followup(&self, id: i64) -> Result<bool, DbError> {
+        let now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+        let updated = self.conn.execute(
+            "UPDATE followups SET done_at = COALESCE(done_at, ?2) WHERE id = ?1",
+            params![id, now],
+        )?;
+        Ok(updated > 0)
+    }
+}
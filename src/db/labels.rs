@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+use rusqlite::params;
+use serde::Serialize;
+
+use crate::db::{Database, DbError};
+
+/// A provider label (a Gmail label or an Outlook master category) with its
+/// display name, nesting and colors. Gmail nests labels by name:
+/// `Work/Clients` sits under `Work`; Outlook categories are flat.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Label {
+    pub account_id: String,
+    pub label_id: String,
+    pub name: String,
+    /// `system` or `user`, as the provider reports it.
+    pub label_type: String,
+    /// Name of the enclosing label, when one exists.
+    pub parent: Option<String>,
+    pub background_color: Option<String>,
+    pub text_color: Option<String>,
+}
+
+impl Database {
+    /// Replaces the stored labels of an account with a fresh listing.
+    pub fn replace_labels(&self, account_id: &str, labels: &[Label]) -> Result<(), DbError> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM labels WHERE account_id = ?", [account_id])?;
+        for label in labels {
+            tx.execute(
+                r#"
+                INSERT OR REPLACE INTO labels (
+                    account_id, label_id, name, label_type, parent, background_color, text_color
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                "#,
+                params![
+                    account_id,
+                    label.label_id,
+                    label.name,
+                    label.label_type,
+                    label.parent,
+                    label.background_color,
+                    label.text_color,
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Labels of an account, ordered by name so nested labels follow their
+    /// parent.
+    pub fn labels(&self, account_id: &str) -> Result<Vec<Label>, DbError> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT account_id, label_id, name, label_type, parent, background_color, text_color
+            FROM labels
+            WHERE account_id = ?
+            ORDER BY label_type DESC, name COLLATE NOCASE
+            "#,
+        )?;
+        let labels = stmt
+            .query_map([account_id], |row| {
+                Ok(Label {
+                    account_id: row.get(0)?,
+                    label_id: row.get(1)?,
+                    name: row.get(2)?,
+                    label_type: row.get(3)?,
+                    parent: row.get(4)?,
+                    background_color: row.get(5)?,
+                    text_color: row.get(6)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(labels)
+    }
+
+    /// Display names of an account's labels, keyed by label id.
+    pub fn label_names(&self, account_id: &str) -> Result<HashMap<String, String>, DbError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT label_id, name FROM labels WHERE account_id = ?")?;
+        let names = stmt
+            .query_map([account_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<HashMap<_, _>>>()?;
+        Ok(names)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Label;
+    use crate::db::Database;
+
+    fn label(id: &str, name: &str, parent: Option<&str>) -> Label {
+        Label {
+            account_id: "acc".to_string(),
+            label_id: id.to_string(),
+            name: name.to_string(),
+            label_type: "user".to_string(),
+            parent: parent.map(str::to_string),
+            background_color: Some("#16a765".to_string()),
+            text_color: Some("#ffffff".to_string()),
+        }
+    }
+
+    #[test]
+    fn replace_labels_swaps_the_account_listing() {
+        let path = std::env::temp_dir().join(format!("ess-labels-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+
+        db.replace_labels("acc", &[label("Label_1", "Old", None)])
+            .expect("store labels");
+        db.replace_labels(
+            "acc",
+            &[
+                label("Label_42", "work/Clients", Some("work")),
+                label("Label_7", "work", None),
+            ],
+        )
+        .expect("replace labels");
+
+        let labels = db.labels("acc").expect("load labels");
+        assert_eq!(
+            labels.iter().map(|l| l.name.as_str()).collect::<Vec<_>>(),
+            vec!["work", "work/Clients"]
+        );
+        assert_eq!(labels[1].parent.as_deref(), Some("work"));
+        let names = db.label_names("acc").expect("load names");
+        assert_eq!(names.len(), 2);
+        assert_eq!(names["Label_42"], "work/Clients");
+        assert!(db.labels("other").expect("load labels").is_empty());
+
+        let _ = std::fs::remove_file(path);
+    }
+}
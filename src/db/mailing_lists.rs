@@ -0,0 +1,347 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+use crate::db::models::Email;
+use crate::db::{Database, DbError};
+
+/// The metadata key connectors keep an email's [`ListHeaders`] under.
+pub const LIST_HEADERS_KEY: &str = "list_headers";
+
+/// The RFC 2369/2919 list headers of a message, as sent.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListHeaders {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub list_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub list_unsubscribe: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub list_unsubscribe_post: Option<String>,
+}
+
+impl ListHeaders {
+    /// Picks the list headers out of a message's `(name, value)` pairs.
+    /// `None` when it has neither `List-Id` nor `List-Unsubscribe`.
+    pub fn from_headers<'a>(headers: impl IntoIterator<Item = (&'a str, &'a str)>) -> Option<Self> {
+        let mut found = Self::default();
+        for (name, value) in headers {
+            let slot = if name.eq_ignore_ascii_case("List-Id") {
+                &mut found.list_id
+            } else if name.eq_ignore_ascii_case("List-Unsubscribe") {
+                &mut found.list_unsubscribe
+            } else if name.eq_ignore_ascii_case("List-Unsubscribe-Post") {
+                &mut found.list_unsubscribe_post
+            } else {
+                continue;
+            };
+            if slot.is_none() && !value.trim().is_empty() {
+                *slot = Some(value.trim().to_string());
+            }
+        }
+        (found.list_id.is_some() || found.list_unsubscribe.is_some()).then_some(found)
+    }
+
+    /// The headers a connector stored in `email.metadata`.
+    pub fn of(email: &Email) -> Option<Self> {
+        let value = email.metadata.as_ref()?.get(LIST_HEADERS_KEY)?;
+        serde_json::from_value(value.clone()).ok()
+    }
+}
+
+/// One row of `mailing_lists`: an email and the list it came through.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ListMembership {
+    /// The `List-Id` identifier, or the sender's address when the message
+    /// only carries `List-Unsubscribe`.
+    list_key: String,
+    name: Option<String>,
+    unsubscribe_url: Option<String>,
+    unsubscribe_mailto: Option<String>,
+    one_click: bool,
+}
+
+impl ListMembership {
+    fn parse(
+        headers: &ListHeaders,
+        from_address: Option<&str>,
+        from_name: Option<&str>,
+    ) -> Option<Self> {
+        let (list_id, list_name) = match headers.list_id.as_deref() {
+            Some(raw) => match raw.rsplit_once('<') {
+                Some((name, rest)) => (
+                    rest.split('>')
+                        .next()
+                        .unwrap_or_default()
+                        .trim()
+                        .to_string(),
+                    Some(name.trim().trim_matches('"').trim().to_string()),
+                ),
+                None => (raw.trim().to_string(), None),
+            },
+            None => (String::new(), None),
+        };
+        let list_key = if list_id.is_empty() {
+            from_address?.trim().to_lowercase()
+        } else {
+            list_id.to_lowercase()
+        };
+        if list_key.is_empty() {
+            return None;
+        }
+
+        let links = headers
+            .list_unsubscribe
+            .as_deref()
+            .unwrap_or_default()
+            .split('<')
+            .skip(1)
+            .filter_map(|part| part.split_once('>').map(|(link, _)| link.trim()))
+            .collect::<Vec<_>>();
+        let unsubscribe_url = links
+            .iter()
+            .find(|link| {
+                let lower = link.to_ascii_lowercase();
+                lower.starts_with("https://") || lower.starts_with("http://")
+            })
+            .map(|link| link.to_string());
+        let unsubscribe_mailto = links
+            .iter()
+            .find(|link| link.to_ascii_lowercase().starts_with("mailto:"))
+            .map(|link| link.to_string());
+        let one_click = unsubscribe_url.is_some()
+            && headers
+                .list_unsubscribe_post
+                .as_deref()
+                .is_some_and(|post| {
+                    post.to_ascii_lowercase()
+                        .contains("list-unsubscribe=one-click")
+                });
+
+        Some(Self {
+            list_key,
+            name: list_name.filter(|name| !name.is_empty()).or_else(|| {
+                from_name
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string)
+            }),
+            unsubscribe_url,
+            unsubscribe_mailto,
+            one_click,
+        })
+    }
+}
+
+/// A list the user receives, as shown by `ess lists`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MailingList {
+    pub list_id: String,
+    pub name: Option<String>,
+    pub messages: i64,
+    /// Messages received in the 30 days before the listing.
+    pub recent_messages: i64,
+    pub last_received: String,
+    /// The unsubscribe links of the newest message.
+    pub unsubscribe_url: Option<String>,
+    pub unsubscribe_mailto: Option<String>,
+    /// The sender accepts an RFC 8058 one-click `POST` to `unsubscribe_url`.
+    pub one_click: bool,
+}
+
+/// Records which list `email` came through, or forgets it when the stored
+/// version no longer carries list headers.
+pub(crate) fn store_list_membership(
+    conn: &Connection,
+    email: &Email,
+) -> Result<(), rusqlite::Error> {
+    let membership = ListHeaders::of(email).and_then(|headers| {
+        ListMembership::parse(
+            &headers,
+            email.from_address.as_deref(),
+            email.from_name.as_deref(),
+        )
+    });
+    match membership {
+        Some(membership) => insert_membership(conn, &email.id, &membership),
+        None => {
+            conn.execute("DELETE FROM mailing_lists WHERE email_id = ?", [&email.id])?;
+            Ok(())
+        }
+    }
+}
+
+fn insert_membership(
+    conn: &Connection,
+    email_id: &str,
+    membership: &ListMembership,
+) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        r#"
+        INSERT OR REPLACE INTO mailing_lists
+            (email_id, list_key, name, unsubscribe_url, unsubscribe_mailto, one_click)
+        VALUES (?, ?, ?, ?, ?, ?)
+        "#,
+        params![
+            email_id,
+            membership.list_key,
+            membership.name,
+            membership.unsubscribe_url,
+            membership.unsubscribe_mailto,
+            membership.one_click,
+        ],
+    )?;
+    Ok(())
+}
+
+/// Records list membership for emails stored with list headers before the
+/// `mailing_lists` table existed.
+pub(crate) fn backfill_list_memberships(conn: &Connection) -> Result<usize, rusqlite::Error> {
+    let rows = {
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, from_address, from_name, json_extract(metadata, '$.list_headers')
+            FROM emails
+            WHERE json_valid(metadata) AND json_extract(metadata, '$.list_headers') IS NOT NULL
+            "#,
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, String>(3)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        rows
+    };
+    let mut stored = 0;
+    for (id, from_address, from_name, headers) in rows {
+        let Ok(headers) = serde_json::from_str::<ListHeaders>(&headers) else {
+            continue;
+        };
+        let Some(membership) =
+            ListMembership::parse(&headers, from_address.as_deref(), from_name.as_deref())
+        else {
+            continue;
+        };
+        insert_membership(conn, &id, &membership)?;
+        stored += 1;
+    }
+    Ok(stored)
+}
+
+impl Database {
+    /// Every list mail has arrived through, most messages first. `since`
+    /// (RFC 3339) bounds `recent_messages`.
+    pub fn mailing_lists(&self, since: &str) -> Result<Vec<MailingList>, DbError> {
+        // SQLite takes the bare columns from the row that has the MAX().
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT m.list_key, m.name, COUNT(*),
+                   SUM(datetime(e.received_at) >= datetime(?1)),
+                   MAX(e.received_at), m.unsubscribe_url, m.unsubscribe_mailto, m.one_click
+            FROM mailing_lists m
+            JOIN emails e ON e.id = m.email_id
+            GROUP BY m.list_key
+            ORDER BY COUNT(*) DESC, m.list_key ASC
+            "#,
+        )?;
+        let lists = stmt
+            .query_map([since], |row| {
+                Ok(MailingList {
+                    list_id: row.get(0)?,
+                    name: row.get(1)?,
+                    messages: row.get(2)?,
+                    recent_messages: row.get(3)?,
+                    last_received: row.get(4)?,
+                    unsubscribe_url: row.get(5)?,
+                    unsubscribe_mailto: row.get(6)?,
+                    one_click: row.get(7)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(lists)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ListHeaders, LIST_HEADERS_KEY};
+    use crate::db::models::Email;
+    use crate::db::Database;
+
+    fn email(id: &str, received_at: &str, headers: &[(&str, &str)]) -> Email {
+        Email {
+            subject: Some("Weekly digest".to_string()),
+            from_address: Some("News@Shop.example".to_string()),
+            from_name: Some("Shop".to_string()),
+            body_text: Some("Deals".to_string()),
+            received_at: received_at.to_string(),
+            metadata: ListHeaders::from_headers(headers.iter().copied())
+                .map(|found| serde_json::json!({ LIST_HEADERS_KEY: found })),
+            ..Email::fixture(id)
+        }
+    }
+
+    #[test]
+    fn list_headers_group_mail_by_list() {
+        assert_eq!(
+            ListHeaders::from_headers([("Subject", "Hi"), ("List-Post", "<mailto:x@example.com>")]),
+            None
+        );
+
+        let path = std::env::temp_dir().join(format!("ess-lists-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        let rust = [
+            ("List-Id", "\"Rust users\" <users.rust-lang.example>"),
+            ("List-Unsubscribe", "<mailto:leave@rust-lang.example>"),
+        ];
+        db.insert_email(&email("r1", "2026-01-01T10:00:00Z", &rust))
+            .expect("insert r1");
+        db.insert_email(&email("r2", "2026-02-01T10:00:00Z", &rust))
+            .expect("insert r2");
+        db.insert_email(&email(
+            "s1",
+            "2026-02-02T10:00:00Z",
+            &[
+                (
+                    "list-unsubscribe",
+                    "<mailto:u@shop.example?subject=stop>, <https://shop.example/u/1>",
+                ),
+                ("List-Unsubscribe-Post", "List-Unsubscribe=One-Click"),
+            ],
+        ))
+        .expect("insert s1");
+        db.insert_email(&email("plain", "2026-02-03T10:00:00Z", &[]))
+            .expect("insert plain");
+
+        let lists = db.mailing_lists("2026-01-15T00:00:00Z").expect("lists");
+        assert_eq!(lists.len(), 2);
+        assert_eq!(lists[0].list_id, "users.rust-lang.example");
+        assert_eq!(lists[0].name.as_deref(), Some("Rust users"));
+        assert_eq!((lists[0].messages, lists[0].recent_messages), (2, 1));
+        assert_eq!(lists[0].last_received, "2026-02-01T10:00:00Z");
+        assert_eq!(
+            lists[0].unsubscribe_mailto.as_deref(),
+            Some("mailto:leave@rust-lang.example")
+        );
+        assert_eq!(lists[1].list_id, "news@shop.example");
+        assert_eq!(lists[1].name.as_deref(), Some("Shop"));
+        assert_eq!(
+            lists[1].unsubscribe_url.as_deref(),
+            Some("https://shop.example/u/1")
+        );
+        assert!(lists[1].one_click);
+
+        // Stored again without list headers, the message leaves the list.
+        db.insert_email(&email("s1", "2026-02-02T10:00:00Z", &[]))
+            .expect("replace s1");
+        db.delete_email("r1").expect("delete r1");
+        let lists = db.mailing_lists("2026-01-15T00:00:00Z").expect("lists");
+        assert_eq!(lists.len(), 1);
+        assert_eq!(lists[0].messages, 1);
+
+        let _ = std::fs::remove_file(path);
+    }
+}
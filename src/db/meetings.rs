@@ -0,0 +1,472 @@
+//! Zoom, Teams and Google Meet links found in stored mail, with the
+//! calendar invite that came with them, for `ess meetings`.
+
+use std::sync::LazyLock;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use regex::Regex;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::db::models::Email;
+use crate::db::{Database, DbError};
+
+/// The metadata key connectors keep an email's [`CalendarEvent`] under.
+pub const CALENDAR_EVENT_KEY: &str = "calendar_event";
+
+/// Longest stored context, in characters.
+const MAX_CONTEXT_CHARS: usize = 200;
+
+static MEETING_URL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?i)https://(?:[a-z0-9-]+\.)?(?:zoom\.us/(?:j|my|w|s)/|teams\.microsoft\.com/l/meetup-join/|teams\.live\.com/meet/|meet\.google\.com/[a-z]{3}-[a-z]{4}-[a-z]{3})[^\s<>"'\])]*"#,
+    )
+    .expect("valid meeting link pattern")
+});
+
+/// The first event of a `text/calendar` invite.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CalendarEvent {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    /// RFC 3339, UTC.
+    pub starts_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ends_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    /// A meeting link from the invite's location or description.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub join_url: Option<String>,
+    /// `METHOD:CANCEL`, or the event's `STATUS:CANCELLED`.
+    #[serde(default)]
+    pub cancelled: bool,
+}
+
+impl CalendarEvent {
+    /// Reads the first `VEVENT` of an iCalendar document. Times with a
+    /// `TZID` are taken as local time; `None` without a usable `DTSTART`.
+    pub fn parse_ics(ics: &str) -> Option<Self> {
+        let mut unfolded: Vec<String> = Vec::new();
+        for line in ics.lines() {
+            match line.strip_prefix([' ', '\t']) {
+                Some(rest) if !unfolded.is_empty() => {
+                    unfolded.last_mut().expect("checked").push_str(rest)
+                }
+                _ => unfolded.push(line.trim_end_matches('\r').to_string()),
+            }
+        }
+
+        let mut event = Self::default();
+        let mut in_event = false;
+        let mut starts_at = None;
+        let mut description = String::new();
+        for line in &unfolded {
+            let Some((name, value)) = line.split_once(':') else {
+                continue;
+            };
+            let (property, parameters) = name.split_once(';').unwrap_or((name, ""));
+            let property = property.to_ascii_uppercase();
+            match property.as_str() {
+                "METHOD" if value.eq_ignore_ascii_case("CANCEL") => event.cancelled = true,
+                "BEGIN" if value.eq_ignore_ascii_case("VEVENT") => in_event = true,
+                "END" if value.eq_ignore_ascii_case("VEVENT") => break,
+                _ if !in_event => {}
+                "UID" => event.uid = Some(value.to_string()),
+                "SUMMARY" => event.summary = Some(unescape(value)),
+                "LOCATION" => event.location = Some(unescape(value)),
+                "DESCRIPTION" => description = unescape(value),
+                "STATUS" if value.eq_ignore_ascii_case("CANCELLED") => event.cancelled = true,
+                "DTSTART" => starts_at = parse_ics_time(value, parameters),
+                "DTEND" => event.ends_at = parse_ics_time(value, parameters),
+                name if name.starts_with("X-") && name.contains("MEETINGURL") => {
+                    event.join_url = Some(value.to_string());
+                }
+                _ => {}
+            }
+        }
+        event.starts_at = starts_at?;
+        if event.join_url.is_none() {
+            event.join_url = [event.location.as_deref().unwrap_or_default(), &description]
+                .iter()
+                .find_map(|text| meeting_urls(text).next().map(|(url, _)| url));
+        }
+        Some(event)
+    }
+
+    /// The invite a connector stored in `email.metadata`.
+    pub fn of(email: &Email) -> Option<Self> {
+        let value = email.metadata.as_ref()?.get(CALENDAR_EVENT_KEY)?;
+        serde_json::from_value(value.clone()).ok()
+    }
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("\\n", "\n")
+        .replace("\\N", "\n")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+}
+
+/// `20260303T100000Z`, `20260303T100000` (with or without a `TZID`,
+/// local time) or an all-day `20260303`.
+fn parse_ics_time(value: &str, parameters: &str) -> Option<String> {
+    let value = value.trim();
+    let utc = if let Some(utc) = value.strip_suffix(['Z', 'z']) {
+        NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S")
+            .ok()?
+            .and_utc()
+    } else {
+        let naive = if parameters.to_ascii_uppercase().contains("VALUE=DATE") || value.len() == 8 {
+            NaiveDate::parse_from_str(value, "%Y%m%d")
+                .ok()?
+                .and_hms_opt(0, 0, 0)?
+        } else {
+            NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?
+        };
+        Local
+            .from_local_datetime(&naive)
+            .earliest()?
+            .with_timezone(&Utc)
+    };
+    Some(utc.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+/// Meeting links in `text` with the byte offset each starts at.
+fn meeting_urls(text: &str) -> impl Iterator<Item = (String, usize)> + '_ {
+    MEETING_URL.find_iter(text).map(|found| {
+        let url = found
+            .as_str()
+            .trim_end_matches(['.', ',', ';', ':', '!', '?'])
+            .replace("&amp;", "&");
+        (url, found.start())
+    })
+}
+
+fn provider(url: &str) -> &'static str {
+    let lower = url.to_ascii_lowercase();
+    if lower.contains("zoom.us/") {
+        "zoom"
+    } else if lower.contains("meet.google.com/") {
+        "meet"
+    } else {
+        "teams"
+    }
+}
+
+/// The line the link is on without the link, or the line before it when
+/// the link stands alone.
+fn context_of(text: &str, offset: usize) -> Option<String> {
+    let start = text[..offset].rfind('\n').map_or(0, |newline| newline + 1);
+    let end = text[offset..]
+        .find('\n')
+        .map_or(text.len(), |newline| offset + newline);
+    let trimmed = |line: &str| {
+        line.trim()
+            .trim_end_matches([':', '<', '>', '(', ')'])
+            .trim()
+            .to_string()
+    };
+    let mut context = trimmed(&MEETING_URL.replace_all(&text[start..end], ""));
+    if context.is_empty() {
+        context = text[..start]
+            .lines()
+            .rev()
+            .map(trimmed)
+            .find(|line| !line.is_empty())?;
+    }
+    Some(context.chars().take(MAX_CONTEXT_CHARS).collect())
+}
+
+/// A meeting link found in an email.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FoundLink {
+    url: String,
+    provider: &'static str,
+    context: Option<String>,
+}
+
+fn find_links(email: &Email, event: Option<&CalendarEvent>) -> Vec<FoundLink> {
+    let mut links: Vec<FoundLink> = Vec::new();
+    let mut add = |url: String, context: Option<String>| {
+        if !links.iter().any(|link| link.url == url) {
+            links.push(FoundLink {
+                provider: provider(&url),
+                url,
+                context,
+            });
+        }
+    };
+    if let Some(join_url) = event.and_then(|event| event.join_url.clone()) {
+        add(join_url, event.and_then(|event| event.location.clone()));
+    }
+    let text = email.body_text.as_deref().unwrap_or_default();
+    for (url, offset) in meeting_urls(text) {
+        add(url, context_of(text, offset));
+    }
+    // Links hidden behind "Join meeting" buttons only appear in the HTML.
+    for (url, _) in meeting_urls(email.body_html.as_deref().unwrap_or_default()) {
+        add(url, None);
+    }
+    links
+}
+
+/// Records the meeting links in `email`, replacing those stored for an
+/// earlier version of it. The invite is read from the stored row, which
+/// `update_email_content` leaves the metadata of alone.
+pub(crate) fn store_meeting_links(conn: &Connection, email: &Email) -> Result<(), rusqlite::Error> {
+    conn.execute("DELETE FROM meeting_links WHERE email_id = ?", [&email.id])?;
+    let event: Option<CalendarEvent> = conn
+        .query_row(
+            "SELECT json_extract(metadata, '$.calendar_event') FROM emails WHERE id = ?",
+            [&email.id],
+            |row| row.get::<_, Option<String>>(0),
+        )
+        .optional()?
+        .flatten()
+        .and_then(|event| serde_json::from_str(&event).ok());
+    for link in find_links(email, event.as_ref()) {
+        conn.execute(
+            r#"
+            INSERT OR REPLACE INTO meeting_links
+                (email_id, url, provider, context, event_uid, title, starts_at, ends_at, cancelled)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+            params![
+                email.id,
+                link.url,
+                link.provider,
+                link.context,
+                event.as_ref().and_then(|event| event.uid.as_deref()),
+                event.as_ref().and_then(|event| event.summary.as_deref()),
+                event.as_ref().map(|event| event.starts_at.as_str()),
+                event.as_ref().and_then(|event| event.ends_at.as_deref()),
+                event.as_ref().is_some_and(|event| event.cancelled),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// Records meeting links for emails stored before the `meeting_links`
+/// table existed.
+pub(crate) fn backfill_meeting_links(conn: &Connection) -> Result<usize, rusqlite::Error> {
+    let emails = {
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT * FROM emails
+            WHERE body_text LIKE '%zoom.us/%' OR body_html LIKE '%zoom.us/%'
+               OR body_text LIKE '%teams.microsoft.com/l/meetup-join/%'
+               OR body_html LIKE '%teams.microsoft.com/l/meetup-join/%'
+               OR body_text LIKE '%teams.live.com/meet/%' OR body_html LIKE '%teams.live.com/meet/%'
+               OR body_text LIKE '%meet.google.com/%' OR body_html LIKE '%meet.google.com/%'
+               OR json_extract(metadata, '$.calendar_event') IS NOT NULL
+            "#,
+        )?;
+        let emails = stmt
+            .query_map([], Email::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        emails
+    };
+    for email in &emails {
+        store_meeting_links(conn, email)?;
+    }
+    Ok(emails.len())
+}
+
+/// A meeting link and where it came from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MeetingLink {
+    pub email_id: String,
+    pub url: String,
+    /// `zoom`, `teams` or `meet`.
+    pub provider: String,
+    /// The text around the link in the email.
+    pub context: Option<String>,
+    /// The invite's summary, else the email subject.
+    pub title: Option<String>,
+    /// From the calendar invite, when the email carried one.
+    pub starts_at: Option<String>,
+    pub ends_at: Option<String>,
+    pub from_address: Option<String>,
+    pub received_at: String,
+}
+
+impl Database {
+    /// Meetings starting in `[from, to)` (RFC 3339), earliest first,
+    /// followed by links without an invite from mail received in the same
+    /// window. Cancelled invites are left out, and an invite updated by a
+    /// later email is only listed as of that email.
+    pub fn meeting_links(&self, from: &str, to: &str) -> Result<Vec<MeetingLink>, DbError> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT m.email_id, m.url, m.provider, m.context, COALESCE(m.title, e.subject),
+                   m.starts_at, m.ends_at, e.from_address, e.received_at
+            FROM meeting_links m
+            JOIN emails e ON e.id = m.email_id
+            WHERE (
+                    m.starts_at IS NOT NULL
+                    AND datetime(m.starts_at) >= datetime(?1) AND datetime(m.starts_at) < datetime(?2)
+                    AND m.cancelled = 0
+                    AND NOT EXISTS (
+                        SELECT 1 FROM meeting_links later
+                        JOIN emails later_email ON later_email.id = later.email_id
+                        WHERE later.event_uid = m.event_uid
+                          AND later_email.received_at > e.received_at
+                    )
+                  )
+               OR (
+                    m.starts_at IS NULL
+                    AND datetime(e.received_at) >= datetime(?1) AND datetime(e.received_at) < datetime(?2)
+                  )
+            ORDER BY m.starts_at IS NULL, m.starts_at, e.received_at DESC, m.url
+            "#,
+        )?;
+        let links = stmt
+            .query_map(params![from, to], |row| {
+                Ok(MeetingLink {
+                    email_id: row.get(0)?,
+                    url: row.get(1)?,
+                    provider: row.get(2)?,
+                    context: row.get(3)?,
+                    title: row.get(4)?,
+                    starts_at: row.get(5)?,
+                    ends_at: row.get(6)?,
+                    from_address: row.get(7)?,
+                    received_at: row.get(8)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        // One row per meeting: an invite repeated in several emails of a
+        // thread shares its UID and start.
+        let mut seen = std::collections::HashSet::new();
+        Ok(links
+            .into_iter()
+            .filter(|link| seen.insert((link.url.clone(), link.starts_at.clone())))
+            .collect())
+    }
+}
+
+/// The `[start, end)` of `days` local days from `first`, as RFC 3339 for
+/// [`Database::meeting_links`].
+pub fn local_days(first: NaiveDate, days: u64) -> Option<(String, String)> {
+    let bound = |day: NaiveDate| -> Option<String> {
+        let start: DateTime<Local> = Local
+            .from_local_datetime(&day.and_hms_opt(0, 0, 0)?)
+            .earliest()?;
+        Some(
+            start
+                .with_timezone(&Utc)
+                .to_rfc3339_opts(SecondsFormat::Secs, true),
+        )
+    };
+    Some((
+        bound(first)?,
+        bound(first.checked_add_days(chrono::Days::new(days))?)?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CalendarEvent, CALENDAR_EVENT_KEY};
+    use crate::db::models::Email;
+    use crate::db::Database;
+
+    const INVITE: &str = "BEGIN:VCALENDAR\r\nMETHOD:REQUEST\r\nBEGIN:VEVENT\r\n\
+        UID:abc-123\r\nSUMMARY:Budget review\\, Q3\r\nDTSTART:20260303T100000Z\r\n\
+        DTEND:20260303T103000Z\r\nLOCATION:https://meet.google.com/abc-defg-hij\r\n\
+        DESCRIPTION:Join with Google Meet: https://meet.google.com/abc-defg-hij\\n\r\n \
+        More options\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+
+    fn email(id: &str, body: &str, event: Option<&CalendarEvent>) -> Email {
+        Email {
+            subject: Some("Catch up".to_string()),
+            from_address: Some("alice@example.com".to_string()),
+            body_text: Some(body.to_string()),
+            body_html: Some(
+                r#"<a href="https://us02web.zoom.us/j/123?pwd=x&amp;from=addon">Join</a>"#
+                    .to_string(),
+            ),
+            received_at: "2026-03-02T09:00:00Z".to_string(),
+            metadata: event.map(|event| {
+                serde_json::json!({ CALENDAR_EVENT_KEY: serde_json::to_value(event).unwrap() })
+            }),
+            ..Email::fixture(id)
+        }
+    }
+
+    #[test]
+    fn invites_and_body_links_pair_up_by_day() {
+        let event = CalendarEvent::parse_ics(INVITE).expect("parse invite");
+        assert_eq!(event.uid.as_deref(), Some("abc-123"));
+        assert_eq!(event.summary.as_deref(), Some("Budget review, Q3"));
+        assert_eq!(event.starts_at, "2026-03-03T10:00:00Z");
+        assert_eq!(
+            event.join_url.as_deref(),
+            Some("https://meet.google.com/abc-defg-hij")
+        );
+        assert!(!event.cancelled);
+        assert!(CalendarEvent::parse_ics("BEGIN:VEVENT\nSUMMARY:x\nEND:VEVENT").is_none());
+
+        let path = std::env::temp_dir().join(format!("ess-meetings-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        db.insert_email(&email("invite", "See you there.", Some(&event)))
+            .expect("insert invite");
+        db.insert_email(&email(
+            "adhoc",
+            "Quick call?\nJoin here:\nhttps://teams.microsoft.com/l/meetup-join/19%3ameeting_x.\nThanks",
+            None,
+        ))
+        .expect("insert ad hoc");
+
+        let day = db
+            .meeting_links("2026-03-03T00:00:00Z", "2026-03-04T00:00:00Z")
+            .expect("links");
+        let found: Vec<_> = day
+            .iter()
+            .map(|link| (link.provider.as_str(), link.title.as_deref()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("meet", Some("Budget review, Q3")),
+                ("zoom", Some("Budget review, Q3"))
+            ]
+        );
+        assert_eq!(day[1].url, "https://us02web.zoom.us/j/123?pwd=x&from=addon");
+
+        let received = db
+            .meeting_links("2026-03-02T00:00:00Z", "2026-03-03T00:00:00Z")
+            .expect("received links");
+        let teams = received
+            .iter()
+            .find(|link| link.provider == "teams")
+            .expect("teams link");
+        assert_eq!(
+            teams.url,
+            "https://teams.microsoft.com/l/meetup-join/19%3ameeting_x"
+        );
+        assert_eq!(teams.context.as_deref(), Some("Join here"));
+        assert_eq!(teams.starts_at, None);
+
+        let cancelled = CalendarEvent {
+            cancelled: true,
+            ..event.clone()
+        };
+        let mut update = email("update", "Cancelled.", Some(&cancelled));
+        update.received_at = "2026-03-02T18:00:00Z".to_string();
+        update.body_html = None;
+        db.insert_email(&update).expect("insert cancellation");
+        assert_eq!(CalendarEvent::of(&update), Some(cancelled));
+        assert!(db
+            .meeting_links("2026-03-03T00:00:00Z", "2026-03-04T00:00:00Z")
+            .expect("links")
+            .is_empty());
+
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,166 @@
+//! Custom key-value metadata on emails.
+//!
+//! `emails.metadata` is a JSON object. Connectors record where a message
+//! came from (`connector`, `source`) and rules can add their own keys.
+//! The accessors here read and write single keys. `meta:key=value` in
+//! search queries filters on them. Keys in [`INDEXED_META_KEYS`] are also
+//! indexed as exact terms, so filtering on them never scans SQLite.
+
+use rusqlite::params;
+use serde_json::{Map, Value};
+
+use crate::db::models::Email;
+use crate::db::{Database, DbError};
+
+/// Metadata keys indexed as `key=value` keyword terms.
+pub const INDEXED_META_KEYS: &[&str] = &["connector", "source"];
+
+/// Letters, digits, `_`, `-` and `.`; anything else would need escaping in
+/// a JSON path.
+pub fn is_meta_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|ch| ch.is_alphanumeric() || matches!(ch, '_' | '-' | '.'))
+}
+
+/// The SQLite JSON path of a top-level key, quoted so `.` stays part of
+/// the key.
+pub fn meta_path(key: &str) -> String {
+    format!("$.\"{key}\"")
+}
+
+/// How a metadata value compares in `meta:` queries and index terms:
+/// strings as they are, anything else as compact JSON (`42`, `true`).
+pub fn meta_value_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+impl Email {
+    pub fn meta(&self, key: &str) -> Option<&Value> {
+        self.metadata.as_ref()?.get(key)
+    }
+
+    pub fn meta_str(&self, key: &str) -> Option<&str> {
+        self.meta(key)?.as_str()
+    }
+
+    pub fn meta_bool(&self, key: &str) -> Option<bool> {
+        self.meta(key)?.as_bool()
+    }
+
+    pub fn meta_i64(&self, key: &str) -> Option<i64> {
+        self.meta(key)?.as_i64()
+    }
+
+    /// The value in the form `meta:` queries compare against.
+    pub fn meta_text(&self, key: &str) -> Option<String> {
+        self.meta(key).map(meta_value_text)
+    }
+
+    /// Sets one key. Missing metadata, or metadata that is not an object,
+    /// is replaced by an object first.
+    pub fn set_meta(&mut self, key: &str, value: impl Into<Value>) {
+        let metadata = self
+            .metadata
+            .get_or_insert_with(|| Value::Object(Map::new()));
+        if !metadata.is_object() {
+            *metadata = Value::Object(Map::new());
+        }
+        if let Value::Object(map) = metadata {
+            map.insert(key.to_string(), value.into());
+        }
+    }
+}
+
+impl Database {
+    /// Sets one metadata key of a stored email in place, keeping the other
+    /// keys. Returns false when there is no such email. The search index
+    /// is not touched; callers changing an indexed key re-index the email.
+    pub fn set_email_meta(
+        &self,
+        email_id: &str,
+        key: &str,
+        value: &Value,
+    ) -> Result<bool, DbError> {
+        if !is_meta_key(key) {
+            return Err(DbError::Config(format!("invalid metadata key '{key}'")));
+        }
+        let updated = self.conn.execute(
+            r#"
+            UPDATE emails
+            SET metadata = json_set(
+                CASE json_valid(metadata) AND json_type(metadata) = 'object'
+                    WHEN 1 THEN metadata ELSE '{}' END,
+                ?, json(?))
+            WHERE id = ?
+            "#,
+            params![meta_path(key), serde_json::to_string(value)?, email_id],
+        )?;
+        Ok(updated > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{is_meta_key, meta_value_text};
+    use crate::db::models::Email;
+    use crate::db::Database;
+
+    fn email() -> Email {
+        Email {
+            subject: Some("Budget".to_string()),
+            received_at: "2026-02-01T10:00:00Z".to_string(),
+            ..Email::fixture("e1")
+        }
+    }
+
+    #[test]
+    fn typed_accessors_read_and_write_single_keys() {
+        let mut email = email();
+        assert_eq!(email.meta("source"), None);
+        email.set_meta("source", "graph_delta_sync");
+        email.set_meta("priority", 3);
+        email.set_meta("triaged", true);
+        assert_eq!(email.meta_str("source"), Some("graph_delta_sync"));
+        assert_eq!(email.meta_i64("priority"), Some(3));
+        assert_eq!(email.meta_bool("triaged"), Some(true));
+        assert_eq!(email.meta_str("priority"), None);
+        assert_eq!(email.meta_text("priority").as_deref(), Some("3"));
+        assert_eq!(meta_value_text(&json!("a b")), "a b");
+
+        assert!(is_meta_key("rule.id"));
+        assert!(!is_meta_key("a\"b"));
+        assert!(!is_meta_key(""));
+    }
+
+    #[test]
+    fn set_email_meta_keeps_other_keys() {
+        let path = std::env::temp_dir().join(format!("ess-meta-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        let mut stored = email();
+        stored.set_meta("connector", "graph_api");
+        db.insert_email(&stored).expect("insert email");
+
+        assert!(db
+            .set_email_meta("e1", "rule", &json!("newsletters"))
+            .expect("set meta"));
+        assert!(!db
+            .set_email_meta("missing", "rule", &json!("x"))
+            .expect("set meta on missing email"));
+        assert!(db.set_email_meta("e1", "a\"b", &json!(1)).is_err());
+
+        let loaded = db.get_email("e1").expect("get email").expect("email");
+        assert_eq!(
+            loaded.metadata,
+            Some(json!({"connector": "graph_api", "rule": "newsletters"}))
+        );
+
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -1,10 +1,16 @@
 use anyhow::{anyhow, Context, Result};
 use rusqlite::{params, Connection, OptionalExtension};
 
+use crate::db::dedupe;
+use crate::db::mailing_lists::backfill_list_memberships;
+use crate::db::meetings::backfill_meeting_links;
+use crate::db::previews::backfill_previews;
+use crate::db::reply_signals::backfill_reply_signals;
 use crate::db::schema;
+use crate::db::thread_links::{backfill_thread_links, ThreadHeaders};
 
 const SCHEMA_VERSION_KEY: &str = "schema_version";
-const LATEST_SCHEMA_VERSION: u32 = 1;
+const LATEST_SCHEMA_VERSION: u32 = 30;
 
 pub fn migrate(conn: &Connection) -> Result<()> {
     ensure_sync_state_table(conn)?;
@@ -19,6 +25,93 @@ pub fn migrate(conn: &Connection) -> Result<()> {
     if current_version < 1 {
         apply_v1(conn)?;
     }
+    if current_version < 2 {
+        apply_v2(conn)?;
+    }
+    if current_version < 3 {
+        apply_v3(conn)?;
+    }
+    if current_version < 4 {
+        apply_v4(conn)?;
+    }
+    if current_version < 5 {
+        apply_v5(conn)?;
+    }
+    if current_version < 6 {
+        apply_v6(conn)?;
+    }
+    if current_version < 7 {
+        apply_v7(conn)?;
+    }
+    if current_version < 8 {
+        apply_v8(conn)?;
+    }
+    if current_version < 9 {
+        apply_v9(conn)?;
+    }
+    if current_version < 10 {
+        apply_v10(conn)?;
+    }
+    if current_version < 11 {
+        apply_v11(conn)?;
+    }
+    if current_version < 12 {
+        apply_v12(conn)?;
+    }
+    if current_version < 13 {
+        apply_v13(conn)?;
+    }
+    if current_version < 14 {
+        apply_v14(conn)?;
+    }
+    if current_version < 15 {
+        apply_v15(conn)?;
+    }
+    if current_version < 16 {
+        apply_v16(conn)?;
+    }
+    if current_version < 17 {
+        apply_v17(conn)?;
+    }
+    if current_version < 18 {
+        apply_v18(conn)?;
+    }
+    if current_version < 19 {
+        apply_v19(conn)?;
+    }
+    if current_version < 20 {
+        apply_v20(conn)?;
+    }
+    if current_version < 21 {
+        apply_v21(conn)?;
+    }
+    if current_version < 22 {
+        apply_v22(conn)?;
+    }
+    if current_version < 23 {
+        apply_v23(conn)?;
+    }
+    if current_version < 24 {
+        apply_v24(conn)?;
+    }
+    if current_version < 25 {
+        apply_v25(conn)?;
+    }
+    if current_version < 26 {
+        apply_v26(conn)?;
+    }
+    if current_version < 27 {
+        apply_v27(conn)?;
+    }
+    if current_version < 28 {
+        apply_v28(conn)?;
+    }
+    if current_version < 29 {
+        apply_v29(conn)?;
+    }
+    if current_version < 30 {
+        apply_v30(conn)?;
+    }
 
     Ok(())
 }
@@ -78,6 +171,717 @@ fn apply_v1(conn: &Connection) -> Result<()> {
     Ok(())
 }
 
+/// v2: `stats_snapshots`, the history behind `ess stats --trend`.
+fn apply_v2(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS stats_snapshots (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            captured_at TEXT NOT NULL,
+            total_accounts INTEGER NOT NULL,
+            total_emails INTEGER NOT NULL,
+            total_contacts INTEGER NOT NULL,
+            index_doc_count INTEGER NOT NULL,
+            index_size_bytes INTEGER NOT NULL
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_stats_snapshots_captured_at
+            ON stats_snapshots(captured_at);
+        "#,
+    )
+    .context("apply schema migration v2")?;
+    set_schema_version(conn, 2)?;
+    Ok(())
+}
+
+/// v3: `thread_mappings`, local corrections made with `ess thread merge` and
+/// `ess thread split`. `kind` is `thread` (every message of provider thread
+/// `source_id` belongs to `thread_id`) or `email` (message `source_id` does).
+fn apply_v3(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS thread_mappings (
+            kind TEXT NOT NULL CHECK (kind IN ('thread', 'email')),
+            source_id TEXT NOT NULL,
+            thread_id TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+            PRIMARY KEY (kind, source_id)
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_thread_mappings_thread_id
+            ON thread_mappings(thread_id);
+        "#,
+    )
+    .context("apply schema migration v3")?;
+    set_schema_version(conn, 3)?;
+    Ok(())
+}
+
+/// v4: `notes`, free-text annotations attached to a conversation.
+fn apply_v4(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS notes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            conversation_id TEXT NOT NULL,
+            body TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_notes_conversation_id
+            ON notes(conversation_id);
+        "#,
+    )
+    .context("apply schema migration v4")?;
+    set_schema_version(conn, 4)?;
+    Ok(())
+}
+
+/// v5: `embeddings`, one vector per email and embedding model for
+/// `ess search --semantic`. Vectors are little-endian `f32`s. There is no
+/// foreign key: `INSERT OR REPLACE` re-syncs would cascade and throw away
+/// vectors that are costly to recompute.
+fn apply_v5(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS embeddings (
+            email_id TEXT NOT NULL,
+            model TEXT NOT NULL,
+            dimensions INTEGER NOT NULL,
+            vector BLOB NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+            PRIMARY KEY (email_id, model)
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_embeddings_model
+            ON embeddings(model);
+        "#,
+    )
+    .context("apply schema migration v5")?;
+    set_schema_version(conn, 5)?;
+    Ok(())
+}
+
+/// v6: `stars`, emails bookmarked locally with `ess star`. Kept apart from
+/// `emails.flag_status` so provider syncs never touch them.
+fn apply_v6(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS stars (
+            email_id TEXT PRIMARY KEY,
+            starred_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+        );
+        "#,
+    )
+    .context("apply schema migration v6")?;
+    set_schema_version(conn, 6)?;
+    Ok(())
+}
+
+/// v7: `collections` and their members, named sets of emails curated with
+/// `ess collections`. Members reference emails by id only, like `stars`.
+fn apply_v7(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS collections (
+            name TEXT PRIMARY KEY,
+            created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+        );
+
+        CREATE TABLE IF NOT EXISTS collection_emails (
+            collection TEXT NOT NULL REFERENCES collections(name) ON DELETE CASCADE,
+            email_id TEXT NOT NULL,
+            added_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+            PRIMARY KEY (collection, email_id)
+        );
+        "#,
+    )
+    .context("apply schema migration v7")?;
+    set_schema_version(conn, 7)?;
+    Ok(())
+}
+
+/// v8: `emails.from_domain`, the sender's lowercased domain, so domain
+/// filters and facets use an index instead of `LIKE` over `from_address`.
+/// New rows get it from `insert_email`; existing rows are backfilled here.
+fn apply_v8(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        ALTER TABLE emails ADD COLUMN from_domain TEXT;
+
+        UPDATE emails
+        SET from_domain = NULLIF(LOWER(TRIM(SUBSTR(from_address, INSTR(from_address, '@') + 1))), '')
+        WHERE INSTR(from_address, '@') > 0;
+
+        CREATE INDEX IF NOT EXISTS idx_emails_from_domain ON emails(from_domain);
+        "#,
+    )
+    .context("apply schema migration v8")?;
+    set_schema_version(conn, 8)?;
+    Ok(())
+}
+
+/// v9: index `attachments` by email, now that syncs fill the table and
+/// search filters on attachment names and types.
+fn apply_v9(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE INDEX IF NOT EXISTS idx_attachments_email_id ON attachments(email_id);
+        "#,
+    )
+    .context("apply schema migration v9")?;
+    set_schema_version(conn, 9)?;
+    Ok(())
+}
+
+/// v10: `previews`, a cleaned title and plain-text excerpt per email for
+/// list and search output. New rows get one from `insert_email`; existing
+/// rows are backfilled here.
+fn apply_v10(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS previews (
+            email_id TEXT PRIMARY KEY REFERENCES emails(id) ON DELETE CASCADE,
+            title TEXT NOT NULL,
+            text TEXT NOT NULL
+        );
+        "#,
+    )
+    .context("apply schema migration v10")?;
+    backfill_previews(conn).context("backfill email previews")?;
+    set_schema_version(conn, 10)?;
+    Ok(())
+}
+
+/// v11: `labels`, the provider's label listing per account (Gmail label
+/// names, nesting and colors), refreshed on every sync.
+fn apply_v11(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS labels (
+            account_id TEXT NOT NULL,
+            label_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            label_type TEXT NOT NULL,
+            parent TEXT,
+            background_color TEXT,
+            text_color TEXT,
+            PRIMARY KEY (account_id, label_id)
+        );
+        "#,
+    )
+    .context("apply schema migration v11")?;
+    set_schema_version(conn, 11)?;
+    Ok(())
+}
+
+/// v12: `quarantine`, messages a connector fetched but could not decode or
+/// map, kept with the error so `ess quarantine retry` can try them again.
+fn apply_v12(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS quarantine (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            account_id TEXT NOT NULL,
+            connector TEXT NOT NULL,
+            message_id TEXT,
+            source TEXT,
+            payload TEXT NOT NULL,
+            error TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            retry_count INTEGER NOT NULL DEFAULT 0,
+            last_retry_at TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_quarantine_account ON quarantine(account_id);
+        "#,
+    )
+    .context("apply schema migration v12")?;
+    set_schema_version(conn, 12)?;
+    Ok(())
+}
+
+/// v13: `summaries`, one LLM-written summary per conversation from
+/// `ess summarize`, with the message count it covers.
+fn apply_v13(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS summaries (
+            conversation_id TEXT PRIMARY KEY,
+            summary TEXT NOT NULL,
+            model TEXT NOT NULL,
+            message_count INTEGER NOT NULL,
+            last_received_at TEXT,
+            created_at TEXT NOT NULL
+        );
+        "#,
+    )
+    .context("apply schema migration v13")?;
+    set_schema_version(conn, 13)?;
+    Ok(())
+}
+
+/// v14: `tags` assigned by the rules in `rules.toml`, and `tagged_emails`,
+/// which records the rules each email was last tagged under so a changed
+/// rules file or a re-synced email is tagged again.
+fn apply_v14(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS tags (
+            email_id TEXT NOT NULL REFERENCES emails(id) ON DELETE CASCADE,
+            tag TEXT NOT NULL,
+            source TEXT NOT NULL,
+            PRIMARY KEY (email_id, tag)
+        );
+        CREATE INDEX IF NOT EXISTS idx_tags_tag ON tags(tag);
+
+        CREATE TABLE IF NOT EXISTS tagged_emails (
+            email_id TEXT PRIMARY KEY REFERENCES emails(id) ON DELETE CASCADE,
+            rules_hash TEXT NOT NULL,
+            tagged_at TEXT NOT NULL
+        );
+        "#,
+    )
+    .context("apply schema migration v14")?;
+    set_schema_version(conn, 14)?;
+    Ok(())
+}
+
+/// v15: `contacts.phone`, filled from signatures by `ess contacts enrich`.
+fn apply_v15(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE contacts ADD COLUMN phone TEXT;")
+        .context("apply schema migration v15")?;
+    set_schema_version(conn, 15)?;
+    Ok(())
+}
+
+/// v16: `reply_signals`, what `ess list --needs-reply` reads from each
+/// email's body and sender, backfilled for emails already stored.
+fn apply_v16(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS reply_signals (
+            email_id TEXT PRIMARY KEY REFERENCES emails(id) ON DELETE CASCADE,
+            asks_question INTEGER NOT NULL,
+            automated INTEGER NOT NULL
+        );
+        "#,
+    )
+    .context("apply schema migration v16")?;
+    backfill_reply_signals(conn).context("backfill reply signals")?;
+    set_schema_version(conn, 16)?;
+    Ok(())
+}
+
+/// v17: `mailing_lists`, the list each email arrived through (from the
+/// `List-Id` and `List-Unsubscribe` headers connectors keep in its
+/// metadata), for `ess lists`.
+fn apply_v17(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS mailing_lists (
+            email_id TEXT PRIMARY KEY REFERENCES emails(id) ON DELETE CASCADE,
+            list_key TEXT NOT NULL,
+            name TEXT,
+            unsubscribe_url TEXT,
+            unsubscribe_mailto TEXT,
+            one_click INTEGER NOT NULL DEFAULT 0
+        );
+        CREATE INDEX IF NOT EXISTS idx_mailing_lists_list_key ON mailing_lists(list_key);
+        "#,
+    )
+    .context("apply schema migration v17")?;
+    backfill_list_memberships(conn).context("backfill mailing lists")?;
+    set_schema_version(conn, 17)?;
+    Ok(())
+}
+
+/// v18: `followups`, commitments found in sent mail by `ess commitments`,
+/// and `followup_scans`, which sent emails have been read for them.
+/// Follow-ups carry no foreign key so that an email stored again keeps
+/// them; the scan record goes with the email so it is read again.
+fn apply_v18(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS followups (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            email_id TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            text TEXT NOT NULL,
+            due_date TEXT,
+            source TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            done_at TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_followups_email_id ON followups(email_id);
+        CREATE TABLE IF NOT EXISTS followup_scans (
+            email_id TEXT PRIMARY KEY REFERENCES emails(id) ON DELETE CASCADE,
+            complete INTEGER NOT NULL,
+            scanned_at TEXT NOT NULL
+        );
+        "#,
+    )
+    .context("apply schema migration v18")?;
+    set_schema_version(conn, 18)?;
+    Ok(())
+}
+
+/// v19: the unique Message-ID index (see `dedupe::UNIQUE_MESSAGE_ID_INDEX`).
+/// A database that already holds copies of a message gets it from `ess
+/// dedupe --apply` instead, since creating it would fail.
+fn apply_v19(conn: &Connection) -> Result<()> {
+    let duplicated: bool = conn
+        .query_row(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM emails
+                WHERE account_id IS NOT NULL AND internet_message_id IS NOT NULL
+                  AND internet_message_id != ''
+                GROUP BY account_id, internet_message_id
+                HAVING COUNT(*) > 1
+            )
+            "#,
+            [],
+            |row| row.get(0),
+        )
+        .context("check for duplicate message ids")?;
+    if !duplicated {
+        conn.execute_batch(dedupe::UNIQUE_MESSAGE_ID_INDEX)
+            .context("apply schema migration v19")?;
+    }
+    set_schema_version(conn, 19)?;
+    Ok(())
+}
+
+/// v20: `meeting_links`, the Zoom, Teams and Meet links in each email with
+/// the calendar invite it carried, for `ess meetings`.
+fn apply_v20(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS meeting_links (
+            email_id TEXT NOT NULL REFERENCES emails(id) ON DELETE CASCADE,
+            url TEXT NOT NULL,
+            provider TEXT NOT NULL,
+            context TEXT,
+            event_uid TEXT,
+            title TEXT,
+            starts_at TEXT,
+            ends_at TEXT,
+            cancelled INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (email_id, url)
+        );
+        CREATE INDEX IF NOT EXISTS idx_meeting_links_starts_at ON meeting_links(starts_at);
+        CREATE INDEX IF NOT EXISTS idx_meeting_links_event_uid ON meeting_links(event_uid);
+        "#,
+    )
+    .context("apply schema migration v20")?;
+    backfill_meeting_links(conn).context("backfill meeting links")?;
+    set_schema_version(conn, 20)?;
+    Ok(())
+}
+
+/// v21: `attachment_hashes`, the SHA-256 of attachment contents read from
+/// stored sources by `ess attachments versions`.
+fn apply_v21(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS attachment_hashes (
+            attachment_id TEXT PRIMARY KEY REFERENCES attachments(id) ON DELETE CASCADE,
+            sha256 TEXT NOT NULL
+        );
+        "#,
+    )
+    .context("apply schema migration v21")?;
+    set_schema_version(conn, 21)?;
+    Ok(())
+}
+
+/// v22: `thread_links`, the Message-IDs each email has or refers to in
+/// `In-Reply-To` and `References`, which stitch conversations of different
+/// accounts into one thread for `ess thread`. Filled by v23, once those
+/// headers have columns.
+fn apply_v22(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS thread_links (
+            email_id TEXT NOT NULL REFERENCES emails(id) ON DELETE CASCADE,
+            message_id TEXT NOT NULL,
+            is_own INTEGER NOT NULL,
+            PRIMARY KEY (email_id, message_id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_thread_links_message_id ON thread_links(message_id);
+        "#,
+    )
+    .context("apply schema migration v22")?;
+    set_schema_version(conn, 22)?;
+    Ok(())
+}
+
+/// v23: `emails.in_reply_to` and `emails.reference_ids` (a JSON array),
+/// moved out of the `thread_headers` metadata key v22 kept them under.
+fn apply_v23(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        ALTER TABLE emails ADD COLUMN in_reply_to TEXT;
+        ALTER TABLE emails ADD COLUMN reference_ids TEXT;
+        CREATE INDEX IF NOT EXISTS idx_emails_in_reply_to ON emails(in_reply_to);
+        "#,
+    )
+    .context("apply schema migration v23")?;
+
+    let stored = {
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, json_extract(metadata, '$.thread_headers.in_reply_to'),
+                   json_extract(metadata, '$.thread_headers.references')
+            FROM emails
+            WHERE json_extract(metadata, '$.thread_headers') IS NOT NULL
+            "#,
+        )?;
+        let stored = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        stored
+    };
+    for (id, in_reply_to, references) in stored {
+        let headers = ThreadHeaders::from_headers(
+            [("In-Reply-To", in_reply_to), ("References", references)]
+                .iter()
+                .filter_map(|(name, value)| Some((*name, value.as_deref()?))),
+        );
+        conn.execute(
+            r#"
+            UPDATE emails
+            SET in_reply_to = ?, reference_ids = ?,
+                metadata = json_remove(metadata, '$.thread_headers')
+            WHERE id = ?
+            "#,
+            params![
+                headers.in_reply_to,
+                serde_json::to_string(&headers.references)?,
+                id
+            ],
+        )
+        .context("move thread headers out of metadata")?;
+    }
+    backfill_thread_links(conn).context("backfill thread links")?;
+    set_schema_version(conn, 23)?;
+    Ok(())
+}
+
+/// v24: `sync_usage`, API requests and bytes downloaded per account and
+/// day.
+fn apply_v24(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS sync_usage (
+            account_id TEXT NOT NULL,
+            day TEXT NOT NULL,
+            requests INTEGER NOT NULL DEFAULT 0,
+            bytes INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (account_id, day)
+        );
+        "#,
+    )
+    .context("apply schema migration v24")?;
+    set_schema_version(conn, 24)?;
+    Ok(())
+}
+
+/// v25: `changes`, the change feed `ess replicate` ships to other machines:
+/// the latest change of each email, star, collection and collection member,
+/// recorded by triggers. Body-only updates, such as `ess trim`, are left
+/// out. What is already stored is entered as changed at the epoch, so it
+/// ships once and loses to any real change.
+fn apply_v25(conn: &Connection) -> Result<()> {
+    let mut sql = String::from(
+        r#"
+        CREATE TABLE IF NOT EXISTS changes (
+            seq INTEGER PRIMARY KEY AUTOINCREMENT,
+            entity TEXT NOT NULL,
+            key TEXT NOT NULL,
+            changed_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+        );
+        CREATE UNIQUE INDEX IF NOT EXISTS idx_changes_entity_key ON changes(entity, key);
+        "#,
+    );
+    let tracked = [
+        (
+            "email",
+            "emails",
+            "id",
+            Some("is_read, flag_status, folder, categories, conversation_id, subject, received_at"),
+        ),
+        ("star", "stars", "email_id", None),
+        ("collection", "collections", "name", None),
+        (
+            "collection_email",
+            "collection_emails",
+            "json_array({row}.collection, {row}.email_id)",
+            None,
+        ),
+    ];
+    for (entity, table, key, updated_columns) in tracked {
+        let key_of = |row: &str| {
+            if key.contains("{row}") {
+                key.replace("{row}", row)
+            } else {
+                format!("{row}.{key}")
+            }
+        };
+        let mut events = vec![
+            ("insert", "INSERT".to_string(), "NEW"),
+            ("delete", "DELETE".to_string(), "OLD"),
+        ];
+        if let Some(columns) = updated_columns {
+            events.push(("update", format!("UPDATE OF {columns}"), "NEW"));
+        }
+        for (name, event, row) in events {
+            sql.push_str(&format!(
+                r#"
+        CREATE TRIGGER IF NOT EXISTS changes_{table}_{name} AFTER {event} ON {table}
+        BEGIN
+            INSERT OR REPLACE INTO changes (entity, key) VALUES ('{entity}', {key});
+        END;
+                "#,
+                key = key_of(row),
+            ));
+        }
+        sql.push_str(&format!(
+            "INSERT OR IGNORE INTO changes (entity, key, changed_at) \
+             SELECT '{entity}', {key}, '{EPOCH}' FROM {table} ORDER BY rowid;\n",
+            key = key_of(table),
+            EPOCH = crate::db::changes::EPOCH,
+        ));
+    }
+    conn.execute_batch(&sql)
+        .context("apply schema migration v25")?;
+    set_schema_version(conn, 25)?;
+    Ok(())
+}
+
+/// v26: `discovered_folders`, the mail folders of each Graph account as its
+/// last sync found them, with whether each one is synced and why not.
+fn apply_v26(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS discovered_folders (
+            account_id TEXT NOT NULL,
+            folder_id TEXT NOT NULL,
+            parent_folder_id TEXT,
+            path TEXT NOT NULL,
+            label TEXT,
+            included BOOLEAN NOT NULL,
+            reason TEXT,
+            total_items INTEGER,
+            hidden BOOLEAN NOT NULL DEFAULT false,
+            discovered_at TEXT NOT NULL,
+            PRIMARY KEY (account_id, folder_id)
+        );
+        "#,
+    )
+    .context("apply schema migration v26")?;
+    set_schema_version(conn, 26)?;
+    Ok(())
+}
+
+/// v27: `folder_sync_state`, what each folder's syncs have taken so far and
+/// how the last one went.
+fn apply_v27(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS folder_sync_state (
+            account_id TEXT NOT NULL,
+            folder_id TEXT NOT NULL,
+            folder TEXT NOT NULL,
+            display_name TEXT NOT NULL,
+            emails_added INTEGER NOT NULL DEFAULT 0,
+            emails_updated INTEGER NOT NULL DEFAULT 0,
+            last_run_at TEXT NOT NULL,
+            last_delta_at TEXT,
+            last_error TEXT,
+            last_error_at TEXT,
+            PRIMARY KEY (account_id, folder_id)
+        );
+        "#,
+    )
+    .context("apply schema migration v27")?;
+    set_schema_version(conn, 27)?;
+    Ok(())
+}
+
+/// v28: `sync_runs`, one row per account per sync with how it went.
+fn apply_v28(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS sync_runs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            account_id TEXT NOT NULL,
+            connector TEXT NOT NULL,
+            started_at TEXT NOT NULL,
+            finished_at TEXT NOT NULL,
+            duration_ms INTEGER NOT NULL,
+            emails_added INTEGER NOT NULL DEFAULT 0,
+            emails_updated INTEGER NOT NULL DEFAULT 0,
+            errors INTEGER NOT NULL DEFAULT 0,
+            error TEXT,
+            succeeded BOOLEAN NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_sync_runs_account ON sync_runs(account_id, finished_at);
+        "#,
+    )
+    .context("apply schema migration v28")?;
+    set_schema_version(conn, 28)?;
+    Ok(())
+}
+
+/// v29: `folder_sync_state.consecutive_failures`, so a folder whose delta
+/// link keeps failing gets its cursor reset.
+fn apply_v29(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE folder_sync_state ADD COLUMN consecutive_failures INTEGER NOT NULL DEFAULT 0;",
+    )
+    .context("apply schema migration v29")?;
+    set_schema_version(conn, 29)?;
+    Ok(())
+}
+
+/// v30: Message-IDs are unique per folder rather than per account, since
+/// Graph stores a message sent to yourself in both Inbox and Sent Items.
+/// Replaces the v19 index on `(account_id, internet_message_id)`; as there,
+/// a database holding copies of a message in one folder gets it from `ess
+/// dedupe --apply` instead.
+fn apply_v30(conn: &Connection) -> Result<()> {
+    let duplicated: bool = conn
+        .query_row(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM emails
+                WHERE account_id IS NOT NULL AND internet_message_id IS NOT NULL
+                  AND internet_message_id != ''
+                GROUP BY account_id, COALESCE(folder, ''), internet_message_id
+                HAVING COUNT(*) > 1
+            )
+            "#,
+            [],
+            |row| row.get(0),
+        )
+        .context("check for duplicate message ids")?;
+    conn.execute_batch("DROP INDEX IF EXISTS idx_emails_account_message_id;")
+        .context("apply schema migration v30")?;
+    if !duplicated {
+        conn.execute_batch(dedupe::UNIQUE_MESSAGE_ID_INDEX)
+            .context("apply schema migration v30")?;
+    }
+    set_schema_version(conn, 30)?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
@@ -86,7 +890,7 @@ mod tests {
     use rusqlite::Connection;
     use uuid::Uuid;
 
-    use super::{current_schema_version, migrate};
+    use super::{apply_v1, current_schema_version, migrate, LATEST_SCHEMA_VERSION};
 
     fn temp_db_path() -> PathBuf {
         let mut path = std::env::temp_dir();
@@ -95,12 +899,12 @@ mod tests {
     }
 
     #[test]
-    fn migrate_sets_v1_for_fresh_database() -> Result<()> {
+    fn migrate_sets_latest_version_for_fresh_database() -> Result<()> {
         let db_path = temp_db_path();
         let conn = Connection::open(&db_path)?;
 
         migrate(&conn)?;
-        assert_eq!(current_schema_version(&conn)?, 1);
+        assert_eq!(current_schema_version(&conn)?, LATEST_SCHEMA_VERSION);
 
         let _ = std::fs::remove_file(db_path);
         Ok(())
@@ -116,8 +920,108 @@ mod tests {
         migrate(&conn)?;
         let second_version = current_schema_version(&conn)?;
 
-        assert_eq!(first_version, 1);
-        assert_eq!(second_version, 1);
+        assert_eq!(first_version, LATEST_SCHEMA_VERSION);
+        assert_eq!(second_version, LATEST_SCHEMA_VERSION);
+
+        let _ = std::fs::remove_file(db_path);
+        Ok(())
+    }
+
+    #[test]
+    fn migrate_upgrades_v1_database_with_stats_snapshots() -> Result<()> {
+        let db_path = temp_db_path();
+        let conn = Connection::open(&db_path)?;
+        super::ensure_sync_state_table(&conn)?;
+        apply_v1(&conn)?;
+        assert_eq!(current_schema_version(&conn)?, 1);
+        conn.execute(
+            "INSERT INTO emails (id, from_address, received_at) \
+             VALUES ('old', 'Bob@Example.COM', '2026-01-01T00:00:00Z')",
+            [],
+        )?;
+        conn.execute(
+            r#"INSERT INTO emails (id, received_at, metadata) VALUES ('reply', '2026-01-02T00:00:00Z',
+               '{"source":"gmail_sync","thread_headers":{"in_reply_to":"<parent@example.com>","references":"<root@example.com> <parent@example.com>"}}')"#,
+            [],
+        )?;
+
+        migrate(&conn)?;
+        assert_eq!(current_schema_version(&conn)?, LATEST_SCHEMA_VERSION);
+        let snapshots: i64 =
+            conn.query_row("SELECT COUNT(*) FROM stats_snapshots", [], |row| row.get(0))?;
+        assert_eq!(snapshots, 0);
+        let mappings: i64 =
+            conn.query_row("SELECT COUNT(*) FROM thread_mappings", [], |row| row.get(0))?;
+        assert_eq!(mappings, 0);
+        let notes: i64 = conn.query_row("SELECT COUNT(*) FROM notes", [], |row| row.get(0))?;
+        assert_eq!(notes, 0);
+        let embeddings: i64 =
+            conn.query_row("SELECT COUNT(*) FROM embeddings", [], |row| row.get(0))?;
+        assert_eq!(embeddings, 0);
+        let stars: i64 = conn.query_row("SELECT COUNT(*) FROM stars", [], |row| row.get(0))?;
+        assert_eq!(stars, 0);
+        let summaries: i64 =
+            conn.query_row("SELECT COUNT(*) FROM summaries", [], |row| row.get(0))?;
+        assert_eq!(summaries, 0);
+        let tags: i64 = conn.query_row("SELECT COUNT(*) FROM tags", [], |row| row.get(0))?;
+        assert_eq!(tags, 0);
+        let collections: i64 =
+            conn.query_row("SELECT COUNT(*) FROM collections", [], |row| row.get(0))?;
+        assert_eq!(collections, 0);
+        let domain: Option<String> = conn.query_row(
+            "SELECT from_domain FROM emails WHERE id = 'old'",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(domain.as_deref(), Some("example.com"));
+        let phones: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM contacts WHERE phone IS NOT NULL",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(phones, 0);
+        let signals: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM reply_signals WHERE email_id = 'old'",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(signals, 1);
+        let lists: i64 =
+            conn.query_row("SELECT COUNT(*) FROM mailing_lists", [], |row| row.get(0))?;
+        assert_eq!(lists, 0);
+        let followups: i64 =
+            conn.query_row("SELECT COUNT(*) FROM followups", [], |row| row.get(0))?;
+        assert_eq!(followups, 0);
+        let unique_index: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE name = 'idx_emails_account_folder_message_id'",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(unique_index, 1);
+        let meetings: i64 =
+            conn.query_row("SELECT COUNT(*) FROM meeting_links", [], |row| row.get(0))?;
+        assert_eq!(meetings, 0);
+        let hashes: i64 = conn.query_row("SELECT COUNT(*) FROM attachment_hashes", [], |row| {
+            row.get(0)
+        })?;
+        assert_eq!(hashes, 0);
+        let links: i64 =
+            conn.query_row("SELECT COUNT(*) FROM thread_links", [], |row| row.get(0))?;
+        assert_eq!(links, 2);
+        let (in_reply_to, references, metadata): (Option<String>, String, String) = conn
+            .query_row(
+                "SELECT in_reply_to, reference_ids, metadata FROM emails WHERE id = 'reply'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )?;
+        assert_eq!(in_reply_to.as_deref(), Some("<parent@example.com>"));
+        assert_eq!(
+            references,
+            r#"["<root@example.com>","<parent@example.com>"]"#
+        );
+        assert_eq!(metadata, r#"{"source":"gmail_sync"}"#);
+        let usage: i64 = conn.query_row("SELECT COUNT(*) FROM sync_usage", [], |row| row.get(0))?;
+        assert_eq!(usage, 0);
 
         let _ = std::fs::remove_file(db_path);
         Ok(())
@@ -1,10 +1,12 @@
 use std::path::{Path, PathBuf};
 
-use rusqlite::{params, Connection, ToSql};
+use chrono::NaiveDate;
+use rusqlite::{params, Connection};
 use serde::Serialize;
 use thiserror::Error;
 
 use self::models::{Account, Contact, Email, SyncState};
+use crate::paths;
 
 #[derive(Debug, Error)]
 pub enum DbError {
@@ -19,11 +21,91 @@ pub enum DbError {
 
     #[error("{0}")]
     Config(String),
+
+    #[error("email {id} is a copy of stored email {copy}; merge the stored copy first")]
+    DuplicateMessage { id: String, copy: String },
 }
 
+pub mod analytics;
+pub mod attachments;
+pub mod changes;
+pub mod collections;
+pub mod contacts;
+pub mod dedupe;
+pub mod embeddings;
+pub mod encryption;
+pub mod folders;
+pub mod followups;
+pub mod labels;
+pub mod mailing_lists;
+pub mod meetings;
+pub mod meta;
 pub mod migrations;
 pub mod models;
+pub mod notes;
+pub mod previews;
+pub mod quarantine;
+pub mod reply_signals;
 pub mod schema;
+pub mod sql;
+pub mod stars;
+pub mod stats;
+pub mod summaries;
+pub mod sync_runs;
+pub mod tags;
+pub mod thread_links;
+pub mod threads;
+pub mod trim;
+pub mod usage;
+
+pub use self::analytics::{Analytics, AnalyticsFilters, AnalyticsInterval};
+pub use self::attachments::AttachmentVersion;
+pub use self::changes::{Change, ChangeEntity};
+pub use self::collections::CollectionSummary;
+pub use self::contacts::ContactDetails;
+pub use self::dedupe::DuplicateGroup;
+pub use self::folders::{FolderDiscovery, FolderSyncRun, FolderSyncState};
+pub use self::followups::Followup;
+pub use self::labels::Label;
+pub use self::mailing_lists::{ListHeaders, MailingList};
+pub use self::meetings::{CalendarEvent, MeetingLink};
+pub use self::notes::Note;
+pub use self::previews::Preview;
+pub use self::quarantine::{QuarantineRecord, QuarantinedMessage};
+pub use self::reply_signals::ReplySignals;
+pub use self::sql::SqlWhereClause;
+pub use self::stats::{StatsSnapshot, StatsTrend};
+pub use self::summaries::ConversationSummary;
+pub use self::sync_runs::{SyncHealth, SyncRun};
+pub use self::tags::{EmailTag, TagCount, TaggedEmail};
+pub use self::thread_links::{StitchedThread, ThreadHeaders};
+pub use self::threads::{ThreadMerge, ThreadSummary};
+pub use self::trim::{EvictionPolicy, TrimCandidate};
+pub use self::usage::{SyncUsage, UsageCounts};
+
+/// Inbound, not from an automated sender, and either asking a question or
+/// addressed to an account alone; with no later message in the
+/// conversation from any account address.
+const NEEDS_REPLY_CONDITION: &str = r#"(
+    datetime(received_at) <= datetime(?)
+    AND lower(trim(COALESCE(from_address, ''))) NOT IN (SELECT lower(email_address) FROM accounts)
+    AND EXISTS (
+        SELECT 1 FROM reply_signals r
+        WHERE r.email_id = emails.id
+          AND r.automated = 0
+          AND (r.asks_question = 1
+               OR (json_array_length(emails.to_addresses) = 1
+                   AND lower(trim(json_extract(emails.to_addresses, '$[0]')))
+                       IN (SELECT lower(email_address) FROM accounts)))
+    )
+    AND NOT EXISTS (
+        SELECT 1 FROM emails reply
+        WHERE ((reply.conversation_id = emails.conversation_id
+                AND reply.received_at > emails.received_at)
+               OR reply.in_reply_to = emails.internet_message_id)
+          AND lower(trim(reply.from_address)) IN (SELECT lower(email_address) FROM accounts)
+    )
+)"#;
 
 #[derive(Debug, Clone, Default)]
 pub struct EmailSearchFilters {
@@ -32,8 +114,40 @@ pub struct EmailSearchFilters {
     pub account_type: Option<String>,
     pub folder: Option<String>,
     pub from_address: Option<String>,
+    /// Sender domain, as `normalize_domain` leaves it.
+    pub from_domain: Option<String>,
+    /// Received on or after this day.
+    pub since: Option<NaiveDate>,
+    /// Received on or before this day.
+    pub until: Option<NaiveDate>,
+    /// Tagged with this by the rules in `rules.toml`.
+    pub tag: Option<String>,
+    /// Only mail that looks like it is waiting on an answer (see
+    /// `reply_signals`), received at or before this RFC 3339 time and not
+    /// replied to from an account since, in its conversation or by
+    /// `In-Reply-To`.
+    pub needs_reply_before: Option<String>,
     pub limit: usize,
     pub offset: usize,
+    /// Only rows strictly after this one in result order.
+    pub after: Option<EmailKeyset>,
+}
+
+/// A row's place in `search_emails` order (`received_at` descending, then
+/// `id` descending), used to resume a listing without an offset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmailKeyset {
+    pub received_at: String,
+    pub id: String,
+}
+
+impl EmailKeyset {
+    pub fn of(email: &Email) -> Self {
+        Self {
+            received_at: email.received_at.clone(),
+            id: email.id.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -42,12 +156,29 @@ pub struct AccountEmailCount {
     pub count: i64,
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct FolderEmailCount {
+    pub account_id: Option<String>,
+    pub folder: Option<String>,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountLastSync {
+    pub account_id: String,
+    pub enabled: bool,
+    pub last_sync: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct DatabaseStats {
     pub total_accounts: i64,
     pub total_emails: i64,
     pub total_contacts: i64,
     pub emails_by_account: Vec<AccountEmailCount>,
+    pub emails_by_folder: Vec<FolderEmailCount>,
+    pub last_sync_by_account: Vec<AccountLastSync>,
+    pub emails_by_tag: Vec<TagCount>,
 }
 
 pub struct Database {
@@ -61,7 +192,7 @@ impl Database {
             std::fs::create_dir_all(parent)?;
         }
 
-        let conn = Connection::open(path)?;
+        let conn = encryption::open_connection(path)?;
         conn.execute("PRAGMA foreign_keys = ON", [])?;
 
         let mut db = Self {
@@ -82,9 +213,8 @@ impl Database {
     }
 
     pub fn default_db_path() -> Result<PathBuf, DbError> {
-        let home = dirs::home_dir()
-            .ok_or_else(|| DbError::Config("failed to determine home directory".to_string()))?;
-        Ok(home.join(".ess").join("ess.db"))
+        let dir = paths::data_dir().map_err(|e| DbError::Config(e.to_string()))?;
+        Ok(dir.join("ess.db"))
     }
 
     pub fn conn(&self) -> &Connection {
@@ -156,14 +286,39 @@ impl Database {
         Ok(accounts)
     }
 
+    /// Turns syncing of an account on or off; `false` when there is no such
+    /// account.
+    pub fn set_account_enabled(&self, account_id: &str, enabled: bool) -> Result<bool, DbError> {
+        let updated = self.conn.execute(
+            "UPDATE accounts SET enabled = ?1 WHERE account_id = ?2",
+            params![enabled, account_id],
+        )?;
+        Ok(updated > 0)
+    }
+
     pub fn remove_account(&self, account_id: &str) -> Result<usize, DbError> {
         let deleted = self
             .conn
             .execute("DELETE FROM accounts WHERE account_id = ?", [account_id])?;
+        self.conn
+            .execute("DELETE FROM labels WHERE account_id = ?", [account_id])?;
         Ok(deleted)
     }
 
+    /// Inserts or replaces an email. A thread correction recorded with
+    /// `merge_threads`/`split_thread` wins over the provider's conversation id,
+    /// so re-syncing a message does not undo it. Storing a second copy of a
+    /// message in a folder under a new id fails with
+    /// `DbError::DuplicateMessage`, rather than the `OR REPLACE` dropping the
+    /// stored copy and its local state; fold it in with
+    /// `merge_stored_copies` first.
     pub fn insert_email(&self, email: &Email) -> Result<(), DbError> {
+        if let Some(copy) = dedupe::stored_copies(&self.conn, email)?.into_iter().next() {
+            return Err(DbError::DuplicateMessage {
+                id: email.id.clone(),
+                copy,
+            });
+        }
         let to_addresses = serde_json::to_string(&email.to_addresses)?;
         let cc_addresses = serde_json::to_string(&email.cc_addresses)?;
         let bcc_addresses = serde_json::to_string(&email.bcc_addresses)?;
@@ -180,8 +335,18 @@ impl Database {
                 id, internet_message_id, conversation_id, account_id, subject, from_address, from_name,
                 to_addresses, cc_addresses, bcc_addresses, body_text, body_html, body_preview,
                 received_at, sent_at, importance, is_read, has_attachments, folder, categories,
-                flag_status, web_link, metadata
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                flag_status, web_link, metadata, from_domain, in_reply_to, reference_ids
+            ) VALUES (
+                ?1,
+                ?2,
+                COALESCE(
+                    (SELECT thread_id FROM thread_mappings WHERE kind = 'email' AND source_id = ?1),
+                    (SELECT thread_id FROM thread_mappings WHERE kind = 'thread' AND source_id = ?3),
+                    ?3
+                ),
+                ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20,
+                ?21, ?22, ?23, ?24, ?25, ?26
+            )
             "#,
             params![
                 email.id,
@@ -207,12 +372,90 @@ impl Database {
                 email.flag_status,
                 email.web_link,
                 metadata,
+                email.from_domain(),
+                email.in_reply_to,
+                serde_json::to_string(&email.references)?,
             ],
         )?;
+        previews::store_preview(&self.conn, &email.id, &Preview::of(email))?;
+        reply_signals::store_reply_signals(&self.conn, &email.id, ReplySignals::of(email))?;
+        mailing_lists::store_list_membership(&self.conn, email)?;
+        meetings::store_meeting_links(&self.conn, email)?;
+        thread_links::store_thread_links(&self.conn, email)?;
 
         Ok(())
     }
 
+    /// Persists the read, flag, folder and category state of an already
+    /// stored email. Unlike `insert_email` the row is updated in place, so
+    /// its attachment records are kept.
+    pub fn update_email_flags(&self, email: &Email) -> Result<usize, DbError> {
+        let categories = serde_json::to_string(&email.categories)?;
+        let updated = self.conn.execute(
+            r#"
+            UPDATE emails SET is_read = ?, flag_status = ?, folder = ?, categories = ?
+            WHERE id = ?
+            "#,
+            params![
+                email.is_read,
+                email.flag_status,
+                email.folder,
+                categories,
+                email.id
+            ],
+        )?;
+        Ok(updated)
+    }
+
+    /// Persists the header and body fields of an already stored email, as
+    /// re-derived by `ess reprocess`. Like `update_email_flags` the row is
+    /// updated in place; its thread, state and metadata are left alone.
+    pub fn update_email_content(&self, email: &Email) -> Result<usize, DbError> {
+        let updated = self.conn.execute(
+            r#"
+            UPDATE emails SET
+                internet_message_id = ?, subject = ?, from_address = ?, from_name = ?,
+                to_addresses = ?, cc_addresses = ?, bcc_addresses = ?, body_text = ?,
+                body_html = ?, body_preview = ?, received_at = ?, sent_at = ?, importance = ?,
+                has_attachments = ?, web_link = ?, from_domain = ?, in_reply_to = ?,
+                reference_ids = ?
+            WHERE id = ?
+            "#,
+            params![
+                email.internet_message_id,
+                email.subject,
+                email.from_address,
+                email.from_name,
+                serde_json::to_string(&email.to_addresses)?,
+                serde_json::to_string(&email.cc_addresses)?,
+                serde_json::to_string(&email.bcc_addresses)?,
+                email.body_text,
+                email.body_html,
+                email.body_preview,
+                email.received_at,
+                email.sent_at,
+                email.importance,
+                email.has_attachments,
+                email.web_link,
+                email.from_domain(),
+                email.in_reply_to,
+                serde_json::to_string(&email.references)?,
+                email.id
+            ],
+        )?;
+        if updated > 0 {
+            previews::store_preview(&self.conn, &email.id, &Preview::of(email))?;
+            reply_signals::store_reply_signals(&self.conn, &email.id, ReplySignals::of(email))?;
+            meetings::store_meeting_links(&self.conn, email)?;
+            thread_links::store_thread_links(&self.conn, email)?;
+        }
+        Ok(updated)
+    }
+
+    pub fn delete_email(&self, id: &str) -> Result<usize, DbError> {
+        dedupe::delete_email_rows(&self.conn, id)
+    }
+
     pub fn email_exists(&self, id: &str) -> Result<bool, DbError> {
         let mut stmt = self
             .conn
@@ -221,6 +464,13 @@ impl Database {
         Ok(exists)
     }
 
+    /// Whether any email is stored at all.
+    pub fn has_emails(&self) -> Result<bool, DbError> {
+        Ok(self
+            .conn
+            .query_row("SELECT EXISTS(SELECT 1 FROM emails)", [], |row| row.get(0))?)
+    }
+
     /// Return the set of all email IDs for a given account.
     pub fn get_email_ids_for_account(
         &self,
@@ -243,7 +493,7 @@ impl Database {
             SELECT id, internet_message_id, conversation_id, account_id, subject, from_address, from_name,
                    to_addresses, cc_addresses, bcc_addresses, body_text, body_html, body_preview,
                    received_at, sent_at, importance, is_read, has_attachments, folder, categories,
-                   flag_status, web_link, metadata
+                   flag_status, web_link, metadata, in_reply_to, reference_ids
             FROM emails
             WHERE id = ?
             "#,
@@ -263,7 +513,7 @@ impl Database {
             SELECT id, internet_message_id, conversation_id, account_id, subject, from_address, from_name,
                    to_addresses, cc_addresses, bcc_addresses, body_text, body_html, body_preview,
                    received_at, sent_at, importance, is_read, has_attachments, folder, categories,
-                   flag_status, web_link, metadata
+                   flag_status, web_link, metadata, in_reply_to, reference_ids
             FROM emails
             WHERE conversation_id = ?
             ORDER BY received_at ASC
@@ -282,84 +532,107 @@ impl Database {
             filters.limit = 50;
         }
 
-        let mut sql = String::from(
-            r#"
-            SELECT id, internet_message_id, conversation_id, account_id, subject, from_address, from_name,
-                   to_addresses, cc_addresses, bcc_addresses, body_text, body_html, body_preview,
-                   received_at, sent_at, importance, is_read, has_attachments, folder, categories,
-                   flag_status, web_link, metadata
-            FROM emails
-            WHERE 1 = 1
-            "#,
-        );
-        let mut params_vec: Vec<Box<dyn ToSql>> = Vec::new();
-
+        let mut conditions = Vec::new();
         if let Some(query) = filters.query.filter(|s| !s.trim().is_empty()) {
-            sql.push_str(" AND (subject LIKE ? OR body_text LIKE ? OR from_name LIKE ? OR from_address LIKE ?)");
-            let pattern = format!("%{query}%");
-            params_vec.push(Box::new(pattern.clone()));
-            params_vec.push(Box::new(pattern.clone()));
-            params_vec.push(Box::new(pattern.clone()));
-            params_vec.push(Box::new(pattern));
+            conditions.push(SqlWhereClause::new(
+                "(subject LIKE ? OR body_text LIKE ? OR from_name LIKE ? OR from_address LIKE ?)",
+                vec![format!("%{query}%"); 4],
+            ));
         }
-
         if let Some(account_id) = filters.account_id {
-            sql.push_str(" AND account_id = ?");
-            params_vec.push(Box::new(account_id));
+            conditions.push(SqlWhereClause::new("account_id = ?", [account_id]));
         }
-
         if let Some(account_type) = filters.account_type {
-            sql.push_str(
-                " AND account_id IN (SELECT account_id FROM accounts WHERE account_type = ?)",
-            );
-            params_vec.push(Box::new(account_type));
+            conditions.push(SqlWhereClause::new(
+                "account_id IN (SELECT account_id FROM accounts WHERE account_type = ?)",
+                [account_type],
+            ));
         }
-
         if let Some(folder) = filters.folder {
-            sql.push_str(" AND folder = ?");
-            params_vec.push(Box::new(folder));
+            conditions.push(SqlWhereClause::new("folder = ?", [folder]));
         }
-
         if let Some(from_address) = filters.from_address {
-            sql.push_str(" AND from_address = ?");
-            params_vec.push(Box::new(from_address));
+            conditions.push(SqlWhereClause::new("from_address = ?", [from_address]));
         }
+        if let Some(from_domain) = filters.from_domain {
+            conditions.push(SqlWhereClause::new("from_domain = ?", [from_domain]));
+        }
+        if let Some(since) = filters.since {
+            conditions.push(SqlWhereClause::new(
+                "DATE(received_at) >= DATE(?)",
+                [since.to_string()],
+            ));
+        }
+        if let Some(until) = filters.until {
+            conditions.push(SqlWhereClause::new(
+                "DATE(received_at) <= DATE(?)",
+                [until.to_string()],
+            ));
+        }
+        if let Some(tag) = filters.tag {
+            conditions.push(SqlWhereClause::new(
+                "id IN (SELECT email_id FROM tags WHERE tag = ?)",
+                [tag.to_lowercase()],
+            ));
+        }
+        if let Some(cutoff) = filters.needs_reply_before {
+            conditions.push(SqlWhereClause::new(NEEDS_REPLY_CONDITION, [cutoff]));
+        }
+        if let Some(after) = filters.after {
+            conditions.push(SqlWhereClause::new(
+                "(received_at < ? OR (received_at = ? AND id < ?))",
+                [after.received_at.clone(), after.received_at, after.id],
+            ));
+        }
+        let predicate = SqlWhereClause::all(conditions);
 
-        sql.push_str(" ORDER BY received_at DESC LIMIT ? OFFSET ?");
-        params_vec.push(Box::new(filters.limit as i64));
-        params_vec.push(Box::new(filters.offset as i64));
-
-        let params_refs: Vec<&dyn ToSql> = params_vec.iter().map(|v| v.as_ref()).collect();
+        let sql = format!(
+            r#"
+            SELECT id, internet_message_id, conversation_id, account_id, subject, from_address, from_name,
+                   to_addresses, cc_addresses, bcc_addresses, body_text, body_html, body_preview,
+                   received_at, sent_at, importance, is_read, has_attachments, folder, categories,
+                   flag_status, web_link, metadata, in_reply_to, reference_ids
+            FROM emails
+            WHERE {}
+            ORDER BY received_at DESC, id DESC LIMIT ? OFFSET ?
+            "#,
+            predicate.clause
+        );
+        let limit = filters.limit as i64;
+        let offset = filters.offset as i64;
         let mut stmt = self.conn.prepare(&sql)?;
         let results = stmt
-            .query_map(params_refs.as_slice(), Email::from_row)?
+            .query_map(
+                predicate.bind(&[&limit, &offset]).as_slice(),
+                Email::from_row,
+            )?
             .collect::<rusqlite::Result<Vec<_>>>()?;
 
         Ok(results)
     }
 
     pub fn get_contacts(&self, query: Option<&str>) -> Result<Vec<Contact>, DbError> {
-        let mut sql = String::from(
+        let predicate = match query.filter(|s| !s.trim().is_empty()) {
+            Some(q) => SqlWhereClause::new(
+                "(email_address LIKE ? OR display_name LIKE ?)",
+                vec![format!("%{q}%"); 2],
+            ),
+            None => SqlWhereClause::always(),
+        };
+        let sql = format!(
             r#"
-            SELECT email_address, display_name, company, attio_person_id, attio_company_id,
+            SELECT email_address, display_name, company, phone, attio_person_id, attio_company_id,
                    message_count, first_seen, last_seen
             FROM contacts
+            WHERE {}
+            ORDER BY message_count DESC, email_address ASC
             "#,
+            predicate.clause
         );
 
-        let mut params_vec: Vec<Box<dyn ToSql>> = Vec::new();
-        if let Some(q) = query.filter(|s| !s.trim().is_empty()) {
-            sql.push_str(" WHERE email_address LIKE ? OR display_name LIKE ?");
-            let pattern = format!("%{q}%");
-            params_vec.push(Box::new(pattern.clone()));
-            params_vec.push(Box::new(pattern));
-        }
-        sql.push_str(" ORDER BY message_count DESC, email_address ASC");
-
-        let params_refs: Vec<&dyn ToSql> = params_vec.iter().map(|v| v.as_ref()).collect();
         let mut stmt = self.conn.prepare(&sql)?;
         let contacts = stmt
-            .query_map(params_refs.as_slice(), Contact::from_row)?
+            .query_map(predicate.bind(&[]).as_slice(), Contact::from_row)?
             .collect::<rusqlite::Result<Vec<_>>>()?;
         Ok(contacts)
     }
@@ -404,6 +677,24 @@ impl Database {
         Ok(())
     }
 
+    /// All sync_state rows whose key starts with `prefix`.
+    pub fn list_sync_state(&self, prefix: &str) -> Result<Vec<SyncState>, DbError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT key, value, updated_at FROM sync_state WHERE substr(key, 1, length(?1)) = ?1 ORDER BY key",
+        )?;
+        let states = stmt
+            .query_map([prefix], SyncState::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(states)
+    }
+
+    pub fn delete_sync_state(&self, key: &str) -> Result<usize, DbError> {
+        let deleted = self
+            .conn
+            .execute("DELETE FROM sync_state WHERE key = ?", [key])?;
+        Ok(deleted)
+    }
+
     pub fn get_stats(&self) -> Result<DatabaseStats, DbError> {
         let total_accounts: i64 =
             self.conn
@@ -427,11 +718,43 @@ impl Database {
             })?
             .collect::<rusqlite::Result<Vec<_>>>()?;
 
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT account_id, folder, COUNT(*) AS count FROM emails
+            GROUP BY account_id, folder ORDER BY account_id, count DESC, folder
+            "#,
+        )?;
+        let emails_by_folder = stmt
+            .query_map([], |row| {
+                Ok(FolderEmailCount {
+                    account_id: row.get(0)?,
+                    folder: row.get(1)?,
+                    count: row.get(2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT account_id, enabled, last_sync FROM accounts ORDER BY account_id")?;
+        let last_sync_by_account = stmt
+            .query_map([], |row| {
+                Ok(AccountLastSync {
+                    account_id: row.get(0)?,
+                    enabled: row.get(1)?,
+                    last_sync: row.get(2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
         Ok(DatabaseStats {
             total_accounts,
             total_emails,
             total_contacts,
             emails_by_account,
+            emails_by_folder,
+            last_sync_by_account,
+            emails_by_tag: self.tag_counts()?,
         })
     }
 }
@@ -440,7 +763,7 @@ impl Database {
 mod tests {
     use std::path::PathBuf;
 
-    use super::{Database, EmailSearchFilters};
+    use super::{Database, EmailKeyset, EmailSearchFilters};
     use crate::db::models::{Account, AccountType, Email};
     use uuid::Uuid;
 
@@ -465,7 +788,6 @@ mod tests {
 
     fn sample_email() -> Email {
         Email {
-            id: "msg-1".to_string(),
             internet_message_id: Some("<msg-1@example.com>".to_string()),
             conversation_id: Some("thread-1".to_string()),
             account_id: Some("acc-1".to_string()),
@@ -473,10 +795,7 @@ mod tests {
             from_address: Some("sender@example.com".to_string()),
             from_name: Some("Sender".to_string()),
             to_addresses: vec!["owner@example.com".to_string()],
-            cc_addresses: vec![],
-            bcc_addresses: vec![],
             body_text: Some("Let us meet tomorrow".to_string()),
-            body_html: None,
             body_preview: Some("Let us meet tomorrow".to_string()),
             received_at: "2026-02-01T12:00:00Z".to_string(),
             sent_at: Some("2026-02-01T11:59:00Z".to_string()),
@@ -485,9 +804,7 @@ mod tests {
             has_attachments: Some(false),
             folder: Some("inbox".to_string()),
             categories: vec!["work".to_string()],
-            flag_status: None,
-            web_link: None,
-            metadata: None,
+            ..Email::fixture("msg-1")
         }
     }
 
@@ -509,6 +826,113 @@ mod tests {
         let _ = std::fs::remove_file(path);
     }
 
+    #[test]
+    fn set_account_enabled_flips_the_flag() {
+        let path = temp_db_path();
+        let db = Database::open(&path).expect("open db");
+        let account = sample_account();
+        db.insert_account(&account).expect("insert account");
+
+        assert!(db
+            .set_account_enabled(&account.account_id, false)
+            .expect("disable"));
+        let loaded = db
+            .get_account(&account.account_id)
+            .expect("get account")
+            .expect("account exists");
+        assert!(!loaded.enabled);
+        assert!(!db.set_account_enabled("missing", true).expect("enable"));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn database_update_email_flags() {
+        let path = temp_db_path();
+        let db = Database::open(&path).expect("open db");
+
+        db.insert_account(&sample_account())
+            .expect("insert account");
+        let mut email = sample_email();
+        db.insert_email(&email).expect("insert email");
+
+        email.is_read = Some(true);
+        email.flag_status = Some("flagged".to_string());
+        email.categories = vec!["Receipts".to_string()];
+        assert_eq!(db.update_email_flags(&email).expect("update flags"), 1);
+
+        let loaded = db
+            .get_email("msg-1")
+            .expect("get email")
+            .expect("email exists");
+        assert_eq!(loaded.is_read, Some(true));
+        assert_eq!(loaded.flag_status.as_deref(), Some("flagged"));
+        assert_eq!(loaded.categories, vec!["Receipts"]);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn needs_reply_skips_answered_automated_and_recent_mail() {
+        let path = temp_db_path();
+        let db = Database::open(&path).expect("open db");
+        db.insert_account(&sample_account())
+            .expect("insert account");
+
+        // Addressed to the owner alone, no question: still waiting.
+        db.insert_email(&sample_email())
+            .expect("insert direct email");
+        let mut question = sample_email();
+        question.id = "msg-2".to_string();
+        question.internet_message_id = Some("<msg-2@example.com>".to_string());
+        question.conversation_id = Some("thread-2".to_string());
+        question.to_addresses = vec!["owner@example.com".into(), "team@example.com".into()];
+        question.body_text = Some("Can you review this?".to_string());
+        db.insert_email(&question).expect("insert question");
+        let mut cc = question.clone();
+        cc.id = "msg-3".to_string();
+        cc.internet_message_id = Some("<msg-3@example.com>".to_string());
+        cc.conversation_id = Some("thread-3".to_string());
+        cc.body_text = Some("FYI".to_string());
+        db.insert_email(&cc).expect("insert fyi");
+        let mut automated = question.clone();
+        automated.id = "msg-4".to_string();
+        automated.internet_message_id = Some("<msg-4@example.com>".to_string());
+        automated.conversation_id = Some("thread-4".to_string());
+        automated.from_address = Some("noreply@example.com".to_string());
+        db.insert_email(&automated).expect("insert automated");
+
+        let waiting = |cutoff: &str| {
+            db.search_emails(EmailSearchFilters {
+                needs_reply_before: Some(cutoff.to_string()),
+                ..EmailSearchFilters::default()
+            })
+            .expect("search needs reply")
+            .into_iter()
+            .map(|email| email.id)
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(waiting("2026-02-03T00:00:00Z"), ["msg-2", "msg-1"]);
+        assert!(waiting("2026-02-01T00:00:00Z").is_empty());
+
+        let mut reply = sample_email();
+        reply.id = "msg-5".to_string();
+        reply.internet_message_id = Some("<msg-5@example.com>".to_string());
+        reply.conversation_id = Some("thread-2".to_string());
+        reply.from_address = Some("Owner@Example.com".to_string());
+        reply.received_at = "2026-02-01T15:00:00Z".to_string();
+        db.insert_email(&reply).expect("insert reply");
+        assert_eq!(waiting("2026-02-03T00:00:00Z"), ["msg-1"]);
+
+        // A reply filed in another conversation counts by its In-Reply-To.
+        reply.id = "msg-6".to_string();
+        reply.internet_message_id = Some("<msg-6@example.com>".to_string());
+        reply.conversation_id = Some("elsewhere".to_string());
+        reply.in_reply_to = Some("<msg-1@example.com>".to_string());
+        db.insert_email(&reply).expect("insert reply elsewhere");
+        assert!(waiting("2026-02-03T00:00:00Z").is_empty());
+
+        let _ = std::fs::remove_file(path);
+    }
+
     #[test]
     fn database_search_and_stats() {
         let path = temp_db_path();
@@ -531,10 +955,61 @@ mod tests {
             .expect("search emails");
         assert_eq!(results.len(), 1);
 
+        let dated = |since: &str, until: &str| {
+            db.search_emails(EmailSearchFilters {
+                since: since.parse().ok(),
+                until: until.parse().ok(),
+                ..EmailSearchFilters::default()
+            })
+            .expect("search emails by date")
+            .len()
+        };
+        assert_eq!(dated("2026-02-01", "2026-02-01"), 1);
+        assert_eq!(dated("2026-02-02", ""), 0);
+        assert_eq!(dated("", "2026-01-31"), 0);
+
+        let by_domain = |domain: &str| {
+            db.search_emails(EmailSearchFilters {
+                from_domain: Some(domain.to_string()),
+                ..EmailSearchFilters::default()
+            })
+            .expect("search emails by sender domain")
+            .len()
+        };
+        assert_eq!(by_domain("example.com"), 1);
+        assert_eq!(by_domain("other.com"), 0);
+
+        let mut later = sample_email();
+        later.id = "msg-2".to_string();
+        later.internet_message_id = Some("<msg-2@example.com>".to_string());
+        later.received_at = "2026-02-03T08:00:00Z".to_string();
+        db.insert_email(&later).expect("insert later email");
+        let first_page = db
+            .search_emails(EmailSearchFilters {
+                limit: 1,
+                ..EmailSearchFilters::default()
+            })
+            .expect("first page");
+        assert_eq!(first_page[0].id, "msg-2");
+        let second_page = db
+            .search_emails(EmailSearchFilters {
+                limit: 1,
+                after: Some(EmailKeyset::of(&first_page[0])),
+                ..EmailSearchFilters::default()
+            })
+            .expect("second page");
+        assert_eq!(second_page.len(), 1);
+        assert_eq!(second_page[0].id, sample_email().id);
+        db.delete_email("msg-2").expect("delete later email");
+
         let stats = db.get_stats().expect("db stats");
         assert_eq!(stats.total_accounts, 1);
         assert_eq!(stats.total_emails, 1);
         assert_eq!(stats.total_contacts, 1);
+        assert_eq!(stats.emails_by_folder.len(), 1);
+        assert_eq!(stats.emails_by_folder[0].folder, sample_email().folder);
+        assert_eq!(stats.emails_by_folder[0].count, 1);
+        assert_eq!(stats.last_sync_by_account.len(), 1);
 
         let state = db.get_sync_state("cursor").expect("get sync state");
         assert_eq!(state.expect("state").value.as_deref(), Some("abc123"));
@@ -32,10 +32,17 @@ impl FromStr for AccountType {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub struct Email {
     pub id: String,
     pub internet_message_id: Option<String>,
+    /// The Message-ID of the message this one answers, from `In-Reply-To`.
+    #[serde(default)]
+    pub in_reply_to: Option<String>,
+    /// The Message-IDs of the thread so far, oldest first, from
+    /// `References`. Stored as `emails.reference_ids`.
+    #[serde(default)]
+    pub references: Vec<String>,
     pub conversation_id: Option<String>,
     pub account_id: Option<String>,
     pub subject: Option<String>,
@@ -59,6 +66,19 @@ pub struct Email {
     pub metadata: Option<serde_json::Value>,
 }
 
+#[cfg(test)]
+impl Email {
+    /// An email with just an id and a receive time, for tests to fill in
+    /// what they need: `Email { subject: ..., ..Email::fixture("m1") }`.
+    pub fn fixture(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            received_at: "2026-01-01T00:00:00Z".to_string(),
+            ..Self::default()
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Account {
     pub account_id: String,
@@ -76,6 +96,7 @@ pub struct Contact {
     pub email_address: String,
     pub display_name: Option<String>,
     pub company: Option<String>,
+    pub phone: Option<String>,
     pub attio_person_id: Option<String>,
     pub attio_company_id: Option<String>,
     pub message_count: i64,
@@ -105,15 +126,46 @@ fn parse_json_array(raw: Option<String>) -> Vec<String> {
         .unwrap_or_default()
 }
 
+/// The value of a column added by a later migration, `None` while an
+/// earlier migration's backfill reads rows that do not have it yet.
+fn optional_column<T>(value: SqlResult<Option<T>>) -> SqlResult<Option<T>> {
+    match value {
+        Err(rusqlite::Error::InvalidColumnName(_)) => Ok(None),
+        value => value,
+    }
+}
+
 fn parse_json_value(raw: Option<String>) -> Option<serde_json::Value> {
     raw.and_then(|s| serde_json::from_str::<serde_json::Value>(&s).ok())
 }
 
+/// The part of an address after its `@`, lowercased, or `None` when there
+/// is no domain. Matches the backfill of `emails.from_domain` in migration v8.
+pub fn address_domain(address: &str) -> Option<String> {
+    let (_, domain) = address.split_once('@')?;
+    let domain = domain.trim().to_ascii_lowercase();
+    (!domain.is_empty()).then_some(domain)
+}
+
+/// Normalizes a domain typed by a user (`Example.com`, `@example.com`) to
+/// the form `address_domain` stores.
+pub fn normalize_domain(domain: &str) -> String {
+    domain.trim().trim_start_matches('@').to_ascii_lowercase()
+}
+
 impl Email {
+    /// The sender's domain, stored as `emails.from_domain` and indexed for
+    /// `--from-domain` and the `domain` facet.
+    pub fn from_domain(&self) -> Option<String> {
+        self.from_address.as_deref().and_then(address_domain)
+    }
+
     pub fn from_row(row: &Row<'_>) -> SqlResult<Self> {
         Ok(Self {
             id: row.get("id")?,
             internet_message_id: row.get("internet_message_id")?,
+            in_reply_to: optional_column(row.get("in_reply_to"))?,
+            references: parse_json_array(optional_column(row.get("reference_ids"))?),
             conversation_id: row.get("conversation_id")?,
             account_id: row.get("account_id")?,
             subject: row.get("subject")?,
@@ -169,6 +221,7 @@ impl Contact {
             email_address: row.get("email_address")?,
             display_name: row.get("display_name")?,
             company: row.get("company")?,
+            phone: row.get("phone")?,
             attio_person_id: row.get("attio_person_id")?,
             attio_company_id: row.get("attio_company_id")?,
             message_count: row.get("message_count")?,
@@ -203,7 +256,18 @@ impl SyncState {
 
 #[cfg(test)]
 mod tests {
-    use super::{Account, AccountType, Email};
+    use super::{address_domain, normalize_domain, Account, AccountType, Email};
+
+    #[test]
+    fn sender_domain_is_lowercased_text_after_at() {
+        assert_eq!(
+            address_domain("Alice@Mail.Example.COM").as_deref(),
+            Some("mail.example.com")
+        );
+        assert_eq!(address_domain("no-domain"), None);
+        assert_eq!(address_domain("trailing@"), None);
+        assert_eq!(normalize_domain(" @Example.com "), "example.com");
+    }
 
     #[test]
     fn account_type_display_and_parse() {
@@ -230,7 +294,6 @@ mod tests {
         };
 
         let email = Email {
-            id: "msg-1".to_string(),
             internet_message_id: Some("<m1@example.com>".to_string()),
             conversation_id: Some("c1".to_string()),
             account_id: Some("acc-1".to_string()),
@@ -238,21 +301,14 @@ mod tests {
             from_address: Some("sender@example.com".to_string()),
             from_name: Some("Sender".to_string()),
             to_addresses: vec!["to@example.com".to_string()],
-            cc_addresses: vec![],
-            bcc_addresses: vec![],
             body_text: Some("Hello".to_string()),
-            body_html: None,
-            body_preview: None,
-            received_at: "2026-01-01T00:00:00Z".to_string(),
-            sent_at: None,
             importance: Some("normal".to_string()),
             is_read: Some(false),
             has_attachments: Some(false),
             folder: Some("inbox".to_string()),
             categories: vec!["test".to_string()],
-            flag_status: None,
-            web_link: None,
             metadata: Some(serde_json::json!({"source": "test"})),
+            ..Email::fixture("msg-1")
         };
 
         let account_json = serde_json::to_string(&account).expect("serialize account");
@@ -0,0 +1,177 @@
+use std::collections::BTreeSet;
+
+use rusqlite::{params, OptionalExtension};
+use serde::Serialize;
+
+use crate::db::{Database, DbError};
+
+/// A free-text annotation on a conversation, added with `ess note add`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Note {
+    pub id: i64,
+    pub conversation_id: String,
+    pub body: String,
+    /// RFC3339 UTC timestamp.
+    pub created_at: String,
+}
+
+impl Note {
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get(0)?,
+            conversation_id: row.get(1)?,
+            body: row.get(2)?,
+            created_at: row.get(3)?,
+        })
+    }
+}
+
+impl Database {
+    pub fn add_note(&self, conversation_id: &str, body: &str) -> Result<Note, DbError> {
+        self.conn.execute(
+            "INSERT INTO notes (conversation_id, body) VALUES (?1, ?2)",
+            params![conversation_id, body],
+        )?;
+        let note = self.conn.query_row(
+            "SELECT id, conversation_id, body, created_at FROM notes WHERE id = ?",
+            [self.conn.last_insert_rowid()],
+            Note::from_row,
+        )?;
+        Ok(note)
+    }
+
+    /// Notes on one conversation, oldest first.
+    pub fn notes_for_conversation(&self, conversation_id: &str) -> Result<Vec<Note>, DbError> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id, conversation_id, body, created_at
+            FROM notes
+            WHERE conversation_id = ?
+            ORDER BY created_at ASC, id ASC
+            "#,
+        )?;
+        let notes = stmt
+            .query_map([conversation_id], Note::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(notes)
+    }
+
+    /// Every note, newest first.
+    pub fn list_notes(&self) -> Result<Vec<Note>, DbError> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id, conversation_id, body, created_at
+            FROM notes
+            ORDER BY created_at DESC, id DESC
+            "#,
+        )?;
+        let notes = stmt
+            .query_map([], Note::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(notes)
+    }
+
+    pub fn delete_note(&self, id: i64) -> Result<Option<Note>, DbError> {
+        let note = self
+            .conn
+            .query_row(
+                "SELECT id, conversation_id, body, created_at FROM notes WHERE id = ?",
+                [id],
+                Note::from_row,
+            )
+            .optional()?;
+        if note.is_some() {
+            self.conn.execute("DELETE FROM notes WHERE id = ?", [id])?;
+        }
+        Ok(note)
+    }
+
+    /// Ids of the emails in threads that have a note containing `text`
+    /// (case-insensitive), for the `note:` search prefix.
+    pub fn email_ids_with_note(&self, text: &str) -> Result<BTreeSet<String>, DbError> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id FROM emails
+            WHERE conversation_id IN (
+                SELECT conversation_id FROM notes WHERE LOWER(body) LIKE LOWER(?)
+            )
+            "#,
+        )?;
+        let ids = stmt
+            .query_map([format!("%{text}%")], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<BTreeSet<_>>>()?;
+        Ok(ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::models::{Account, AccountType, Email};
+    use crate::db::Database;
+
+    fn email(id: &str, conversation_id: &str) -> Email {
+        Email {
+            conversation_id: Some(conversation_id.to_string()),
+            account_id: Some("acc".to_string()),
+            subject: Some("Vendor contract".to_string()),
+            from_address: Some("alice@example.com".to_string()),
+            received_at: "2026-02-01T10:00:00Z".to_string(),
+            is_read: Some(true),
+            has_attachments: Some(false),
+            folder: Some("inbox".to_string()),
+            ..Email::fixture(id)
+        }
+    }
+
+    #[test]
+    fn notes_attach_to_threads_and_follow_merges() {
+        let path = std::env::temp_dir().join(format!("ess-notes-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        db.insert_account(&Account {
+            account_id: "acc".to_string(),
+            email_address: "me@example.com".to_string(),
+            display_name: None,
+            tenant_id: None,
+            account_type: AccountType::Professional,
+            enabled: true,
+            last_sync: None,
+            config: None,
+        })
+        .expect("insert account");
+        for email in [email("a1", "A"), email("a2", "A"), email("b1", "B")] {
+            db.insert_email(&email).expect("insert email");
+        }
+
+        let note = db
+            .add_note("B", "Decided to renew with Acme")
+            .expect("add note");
+        assert_eq!(note.conversation_id, "B");
+        assert_eq!(
+            db.email_ids_with_note("acme").expect("note search"),
+            ["b1".to_string()].into()
+        );
+
+        db.merge_threads("A", "B").expect("merge");
+        let notes = db.notes_for_conversation("A").expect("notes for A");
+        assert_eq!(
+            notes,
+            vec![super::Note {
+                conversation_id: "A".to_string(),
+                ..note.clone()
+            }]
+        );
+        assert_eq!(
+            db.email_ids_with_note("ACME").expect("note search").len(),
+            3
+        );
+
+        assert_eq!(
+            db.delete_note(note.id).expect("delete note").map(|n| n.id),
+            Some(note.id)
+        );
+        assert!(db.list_notes().expect("list notes").is_empty());
+        assert_eq!(db.delete_note(note.id).expect("delete missing note"), None);
+
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+
+use rusqlite::{params, params_from_iter, Connection};
+use serde::Serialize;
+
+use crate::db::models::Email;
+use crate::db::{Database, DbError};
+
+/// Longest preview text, in characters.
+pub const PREVIEW_CHARS: usize = 500;
+
+/// Reply and forward markers stripped from the front of a title, compared
+/// case-insensitively with their trailing colon.
+const SUBJECT_PREFIXES: &[&str] = &["re", "fwd", "fw", "aw", "wg", "sv", "tr"];
+
+/// A display-ready title and plain-text excerpt, computed once when an
+/// email is stored so list and search output need no HTML conversion.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Preview {
+    pub title: String,
+    pub text: String,
+}
+
+impl Preview {
+    pub fn of(email: &Email) -> Self {
+        Self::build(
+            email.subject.as_deref(),
+            email.body_text.as_deref(),
+            email.body_html.as_deref(),
+            email.body_preview.as_deref(),
+        )
+    }
+
+    /// The body source is the plain-text body, then the provider preview,
+    /// then the HTML body converted to text.
+    fn build(
+        subject: Option<&str>,
+        body_text: Option<&str>,
+        body_html: Option<&str>,
+        body_preview: Option<&str>,
+    ) -> Self {
+        fn non_empty(value: Option<&str>) -> Option<&str> {
+            value.filter(|value| !value.trim().is_empty())
+        }
+        let body = match non_empty(body_text).or(non_empty(body_preview)) {
+            Some(text) => text.to_string(),
+            None => non_empty(body_html)
+                .map(|html| html2text::from_read(html.as_bytes(), 120))
+                .unwrap_or_default(),
+        };
+        Self {
+            title: normalize_title(subject),
+            text: preview_text(&body),
+        }
+    }
+}
+
+/// The subject without `Re:`/`Fwd:`-style prefixes (repeated or nested, as
+/// in `Re: Fwd: RE:`), with whitespace collapsed; `(no subject)` when
+/// nothing is left.
+pub fn normalize_title(subject: Option<&str>) -> String {
+    let mut title = subject.unwrap_or_default().trim();
+    loop {
+        let stripped = title.split_once(':').and_then(|(prefix, rest)| {
+            SUBJECT_PREFIXES
+                .iter()
+                .any(|marker| prefix.trim().eq_ignore_ascii_case(marker))
+                .then_some(rest.trim_start())
+        });
+        match stripped {
+            Some(rest) => title = rest,
+            None => break,
+        }
+    }
+    let title = collapse_whitespace(title);
+    if title.is_empty() {
+        "(no subject)".to_string()
+    } else {
+        title
+    }
+}
+
+/// Quoted lines (`> ...`) and everything after a `-- ` signature separator
+/// are dropped, whitespace is collapsed, and the result is cut to
+/// `PREVIEW_CHARS` with an ellipsis.
+fn preview_text(body: &str) -> String {
+    let kept = body
+        .lines()
+        .take_while(|line| line.trim_end() != "--")
+        .filter(|line| !line.trim_start().starts_with('>'))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let text = collapse_whitespace(&kept);
+    match text.char_indices().nth(PREVIEW_CHARS) {
+        Some((cut, _)) => format!("{}…", text[..cut].trim_end()),
+        None => text,
+    }
+}
+
+fn collapse_whitespace(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub(crate) fn store_preview(
+    conn: &Connection,
+    email_id: &str,
+    preview: &Preview,
+) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT OR REPLACE INTO previews (email_id, title, text) VALUES (?, ?, ?)",
+        params![email_id, preview.title, preview.text],
+    )?;
+    Ok(())
+}
+
+/// Computes previews for emails stored before the `previews` table existed.
+pub(crate) fn backfill_previews(conn: &Connection) -> Result<usize, rusqlite::Error> {
+    let rows = {
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, subject, body_text, body_html, body_preview FROM emails
+            WHERE id NOT IN (SELECT email_id FROM previews)
+            "#,
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        rows
+    };
+    for (id, subject, body_text, body_html, body_preview) in &rows {
+        let preview = Preview::build(
+            subject.as_deref(),
+            body_text.as_deref(),
+            body_html.as_deref(),
+            body_preview.as_deref(),
+        );
+        store_preview(conn, id, &preview)?;
+    }
+    Ok(rows.len())
+}
+
+impl Database {
+    /// Stored previews for the given emails, keyed by email id. Emails
+    /// without a row are left out.
+    pub fn previews(&self, email_ids: &[&str]) -> Result<HashMap<String, Preview>, DbError> {
+        let mut previews = HashMap::new();
+        // Stay under SQLite's default limit on bound parameters.
+        for chunk in email_ids.chunks(500) {
+            let placeholders = vec!["?"; chunk.len()].join(", ");
+            let sql = format!(
+                "SELECT email_id, title, text FROM previews WHERE email_id IN ({placeholders})"
+            );
+            let mut stmt = self.conn.prepare(&sql)?;
+            let rows = stmt.query_map(params_from_iter(chunk.iter()), |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    Preview {
+                        title: row.get(1)?,
+                        text: row.get(2)?,
+                    },
+                ))
+            })?;
+            for row in rows {
+                let (id, preview) = row?;
+                previews.insert(id, preview);
+            }
+        }
+        Ok(previews)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize_title, Preview, PREVIEW_CHARS};
+    use crate::db::models::Email;
+    use crate::db::Database;
+
+    #[test]
+    fn titles_drop_reply_and_forward_prefixes() {
+        assert_eq!(
+            normalize_title(Some("Re: Fwd: RE:  Q3   budget")),
+            "Q3 budget"
+        );
+        assert_eq!(
+            normalize_title(Some("AW: Treffen: Montag")),
+            "Treffen: Montag"
+        );
+        assert_eq!(normalize_title(Some("Re:")), "(no subject)");
+        assert_eq!(normalize_title(None), "(no subject)");
+
+        let preview = Preview::build(
+            None,
+            Some("Numbers attached.\n\n> earlier reply\n--\nAlice\nAcme"),
+            None,
+            None,
+        );
+        assert_eq!(preview.text, "Numbers attached.");
+
+        let html = Preview::build(None, None, Some("<p>Hello <b>there</b></p>"), None);
+        assert_eq!(html.text, "Hello there");
+
+        let long = Preview::build(None, Some(&"é".repeat(PREVIEW_CHARS + 10)), None, None);
+        assert_eq!(long.text.chars().count(), PREVIEW_CHARS + 1);
+        assert!(long.text.ends_with('…'));
+    }
+
+    #[test]
+    fn insert_email_stores_its_preview() {
+        let path = std::env::temp_dir().join(format!("ess-previews-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        db.insert_email(&Email {
+            subject: Some("Fwd: Offsite".to_string()),
+            body_text: Some("See you\n  on Friday".to_string()),
+            received_at: "2026-02-01T10:00:00Z".to_string(),
+            ..Email::fixture("e1")
+        })
+        .expect("insert email");
+
+        let previews = db.previews(&["e1", "missing"]).expect("load previews");
+        assert_eq!(previews.len(), 1);
+        assert_eq!(
+            previews["e1"],
+            Preview {
+                title: "Offsite".to_string(),
+                text: "See you on Friday".to_string(),
+            }
+        );
+
+        db.delete_email("e1").expect("delete email");
+        assert!(db.previews(&["e1"]).expect("load previews").is_empty());
+
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,237 @@
+use chrono::Utc;
+use rusqlite::{params, OptionalExtension, Row};
+use serde::Serialize;
+
+use crate::db::{Database, DbError};
+
+/// A message a connector fetched but could not decode or map, kept as the
+/// provider sent it so nothing is lost and `ess quarantine retry` can try
+/// again once the parser is fixed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QuarantinedMessage {
+    pub id: i64,
+    pub account_id: String,
+    /// Name of the connector that fetched it (`graph_api`, `gmail_api`,
+    /// `json_archive`).
+    pub connector: String,
+    /// Provider message id, when the payload got far enough to carry one.
+    pub message_id: Option<String>,
+    /// Where it came from: the archive file of an import, the folder of a
+    /// Graph sync.
+    pub source: Option<String>,
+    /// The undecoded payload, usually the provider's JSON.
+    pub payload: String,
+    pub error: String,
+    pub created_at: String,
+    pub retry_count: i64,
+    pub last_retry_at: Option<String>,
+}
+
+/// What a connector hands over when it quarantines a message.
+#[derive(Debug, Clone, Copy)]
+pub struct QuarantineRecord<'a> {
+    pub account_id: &'a str,
+    pub connector: &'a str,
+    pub message_id: Option<&'a str>,
+    pub source: Option<&'a str>,
+    pub payload: &'a str,
+    pub error: &'a str,
+}
+
+const COLUMNS: &str = "id, account_id, connector, message_id, source, payload, error, \
+                       created_at, retry_count, last_retry_at";
+
+impl Database {
+    /// Stores a message that failed to decode or map and returns its
+    /// quarantine id. A message already quarantined under the same
+    /// connector and message id (or source, without an id) is replaced, so
+    /// repeated syncs of a bad message keep one entry.
+    pub fn quarantine_message(&self, record: &QuarantineRecord<'_>) -> Result<i64, DbError> {
+        let existing = match (record.message_id, record.source) {
+            (None, None) => None,
+            (message_id, source) => self
+                .conn
+                .query_row(
+                    r#"
+                    SELECT id FROM quarantine
+                    WHERE account_id = ?1 AND connector = ?2
+                      AND (message_id = ?3 OR (?3 IS NULL AND message_id IS NULL AND source = ?4))
+                    "#,
+                    params![record.account_id, record.connector, message_id, source],
+                    |row| row.get::<_, i64>(0),
+                )
+                .optional()?,
+        };
+        let now = Utc::now().to_rfc3339();
+        if let Some(id) = existing {
+            self.conn.execute(
+                "UPDATE quarantine SET source = ?1, payload = ?2, error = ?3, created_at = ?4 WHERE id = ?5",
+                params![record.source, record.payload, record.error, now, id],
+            )?;
+            return Ok(id);
+        }
+        self.conn.execute(
+            r#"
+            INSERT INTO quarantine (
+                account_id, connector, message_id, source, payload, error, created_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            "#,
+            params![
+                record.account_id,
+                record.connector,
+                record.message_id,
+                record.source,
+                record.payload,
+                record.error,
+                now,
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Quarantined messages, oldest first, optionally for one account.
+    pub fn quarantined(
+        &self,
+        account_id: Option<&str>,
+        limit: Option<usize>,
+    ) -> Result<Vec<QuarantinedMessage>, DbError> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {COLUMNS} FROM quarantine WHERE ?1 IS NULL OR account_id = ?1 ORDER BY id LIMIT ?2"
+        ))?;
+        let limit = limit.map_or(-1, |limit| limit.min(i64::MAX as usize) as i64);
+        let entries = stmt
+            .query_map(params![account_id, limit], quarantined_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(entries)
+    }
+
+    pub fn quarantined_message(&self, id: i64) -> Result<Option<QuarantinedMessage>, DbError> {
+        let entry = self
+            .conn
+            .query_row(
+                &format!("SELECT {COLUMNS} FROM quarantine WHERE id = ?"),
+                [id],
+                quarantined_from_row,
+            )
+            .optional()?;
+        Ok(entry)
+    }
+
+    /// Drops a quarantined message, once it was stored after all. Returns
+    /// whether it existed.
+    pub fn release_quarantined(&self, id: i64) -> Result<bool, DbError> {
+        let removed = self
+            .conn
+            .execute("DELETE FROM quarantine WHERE id = ?", [id])?;
+        Ok(removed > 0)
+    }
+
+    /// Drops the entry an import quarantined for `source`, once the file
+    /// imports after all.
+    pub fn release_quarantined_source(
+        &self,
+        account_id: &str,
+        connector: &str,
+        source: &str,
+    ) -> Result<bool, DbError> {
+        let removed = self.conn.execute(
+            "DELETE FROM quarantine WHERE account_id = ?1 AND connector = ?2 AND message_id IS NULL AND source = ?3",
+            params![account_id, connector, source],
+        )?;
+        Ok(removed > 0)
+    }
+
+    /// Notes a failed retry and the error it ended with.
+    pub fn record_quarantine_retry(&self, id: i64, error: &str) -> Result<(), DbError> {
+        self.conn.execute(
+            r#"
+            UPDATE quarantine
+            SET error = ?1, retry_count = retry_count + 1, last_retry_at = ?2
+            WHERE id = ?3
+            "#,
+            params![error, Utc::now().to_rfc3339(), id],
+        )?;
+        Ok(())
+    }
+}
+
+fn quarantined_from_row(row: &Row<'_>) -> rusqlite::Result<QuarantinedMessage> {
+    Ok(QuarantinedMessage {
+        id: row.get(0)?,
+        account_id: row.get(1)?,
+        connector: row.get(2)?,
+        message_id: row.get(3)?,
+        source: row.get(4)?,
+        payload: row.get(5)?,
+        error: row.get(6)?,
+        created_at: row.get(7)?,
+        retry_count: row.get(8)?,
+        last_retry_at: row.get(9)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::QuarantineRecord;
+    use crate::db::Database;
+
+    #[test]
+    fn quarantine_keeps_one_entry_per_message_until_released() {
+        let path = std::env::temp_dir().join(format!("ess-quarantine-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        let record = QuarantineRecord {
+            account_id: "acc",
+            connector: "gmail_api",
+            message_id: Some("m1"),
+            source: None,
+            payload: "{\"id\": \"m1\", \"payload\": 7}",
+            error: "invalid type: integer",
+        };
+
+        let first = db.quarantine_message(&record).expect("quarantine");
+        let again = db
+            .quarantine_message(&QuarantineRecord {
+                error: "still invalid",
+                ..record
+            })
+            .expect("quarantine again");
+        assert_eq!(first, again);
+        let archived = db
+            .quarantine_message(&QuarantineRecord {
+                connector: "json_archive",
+                message_id: None,
+                source: Some("/tmp/archive/broken.json"),
+                payload: "{",
+                error: "EOF while parsing",
+                ..record
+            })
+            .expect("quarantine file");
+        assert_ne!(archived, first);
+
+        db.record_quarantine_retry(first, "retry failed")
+            .expect("record retry");
+        let entries = db.quarantined(Some("acc"), None).expect("list");
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].error, "retry failed");
+        assert_eq!(entries[0].retry_count, 1);
+        assert!(entries[0].last_retry_at.is_some());
+        assert_eq!(db.quarantined(None, Some(1)).expect("list").len(), 1);
+        assert!(db
+            .quarantined(Some("other"), None)
+            .expect("list")
+            .is_empty());
+
+        assert!(db.release_quarantined(first).expect("release"));
+        assert!(!db.release_quarantined(first).expect("release again"));
+        assert!(db.quarantined_message(first).expect("get").is_none());
+        assert_eq!(
+            db.quarantined_message(archived)
+                .expect("get")
+                .and_then(|entry| entry.source)
+                .as_deref(),
+            Some("/tmp/archive/broken.json")
+        );
+
+        let _ = std::fs::remove_file(path);
+    }
+}
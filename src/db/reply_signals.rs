@@ -0,0 +1,216 @@
+//! Whether an email reads like it wants an answer, computed once when it is
+//! stored. `ess list --needs-reply` combines these with what is only known
+//! at query time: who the accounts are, how the email was addressed and
+//! whether a reply has been sent since.
+
+use rusqlite::{params, Connection};
+
+use crate::db::models::Email;
+
+/// Sender local parts (before the `@`, any `+tag` removed) that mark mail
+/// nobody reads replies to.
+const AUTOMATED_LOCAL_PARTS: &[&str] = &[
+    "noreply",
+    "no-reply",
+    "no_reply",
+    "donotreply",
+    "do-not-reply",
+    "do_not_reply",
+    "mailer-daemon",
+    "postmaster",
+    "bounce",
+    "bounces",
+    "notification",
+    "notifications",
+    "newsletter",
+    "news",
+    "alerts",
+    "automated",
+];
+
+/// Lines at which a reply's own text ends and the quoted message begins.
+const QUOTE_MARKERS: &[&str] = &[
+    "-----original message-----",
+    "________________________________",
+    "---------- forwarded message ---------",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplySignals {
+    /// The sender's own text, leaving out quotes and signature, has a
+    /// question mark ending a sentence.
+    pub asks_question: bool,
+    /// Sent from a no-reply, notification or bounce address.
+    pub automated: bool,
+}
+
+impl ReplySignals {
+    pub fn of(email: &Email) -> Self {
+        Self::build(
+            email.from_address.as_deref(),
+            email.body_text.as_deref(),
+            email.body_html.as_deref(),
+            email.body_preview.as_deref(),
+        )
+    }
+
+    fn build(
+        from_address: Option<&str>,
+        body_text: Option<&str>,
+        body_html: Option<&str>,
+        body_preview: Option<&str>,
+    ) -> Self {
+        Self {
+            asks_question: asks_question(&written_text(body_text, body_html, body_preview)),
+            automated: from_address.is_some_and(is_automated_sender),
+        }
+    }
+}
+
+/// What the sender wrote in `email` themselves: the lines before the first
+/// quote marker or signature separator, without `>` quotes.
+pub fn own_text(email: &Email) -> String {
+    written_text(
+        email.body_text.as_deref(),
+        email.body_html.as_deref(),
+        email.body_preview.as_deref(),
+    )
+}
+
+/// The body read is the plain-text body, then the HTML body converted to
+/// text, then the provider preview.
+fn written_text(
+    body_text: Option<&str>,
+    body_html: Option<&str>,
+    body_preview: Option<&str>,
+) -> String {
+    fn non_empty(value: Option<&str>) -> Option<&str> {
+        value.filter(|value| !value.trim().is_empty())
+    }
+    let body = match non_empty(body_text) {
+        Some(text) => text.to_string(),
+        None => match non_empty(body_html) {
+            Some(html) => html2text::from_read(html.as_bytes(), 120),
+            None => body_preview.unwrap_or_default().to_string(),
+        },
+    };
+    body.lines()
+        .take_while(|line| !starts_quoted_text(line))
+        .filter(|line| !line.trim_start().starts_with('>'))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A `?` followed by whitespace, closing punctuation or the end of a line.
+/// The `?` of a URL query string does not count.
+fn asks_question(text: &str) -> bool {
+    text.lines().any(|line| {
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '?'
+                && chars
+                    .peek()
+                    .is_none_or(|next| next.is_whitespace() || "\"')]*_".contains(*next))
+            {
+                return true;
+            }
+        }
+        false
+    })
+}
+
+fn starts_quoted_text(line: &str) -> bool {
+    let line = line.trim();
+    let lower = line.to_lowercase();
+    line == "--"
+        || QUOTE_MARKERS.contains(&lower.as_str())
+        || (lower.starts_with("on ") && lower.ends_with("wrote:"))
+        || (lower.starts_with("from:") && lower.contains('@'))
+}
+
+fn is_automated_sender(address: &str) -> bool {
+    let address = address.trim().to_lowercase();
+    let local = address.split('@').next().unwrap_or_default();
+    let local = local.split('+').next().unwrap_or_default();
+    AUTOMATED_LOCAL_PARTS.contains(&local)
+}
+
+pub(crate) fn store_reply_signals(
+    conn: &Connection,
+    email_id: &str,
+    signals: ReplySignals,
+) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT OR REPLACE INTO reply_signals (email_id, asks_question, automated) VALUES (?, ?, ?)",
+        params![email_id, signals.asks_question, signals.automated],
+    )?;
+    Ok(())
+}
+
+/// Computes signals for emails stored before the `reply_signals` table
+/// existed.
+pub(crate) fn backfill_reply_signals(conn: &Connection) -> Result<usize, rusqlite::Error> {
+    let rows = {
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, from_address, body_text, body_html, body_preview FROM emails
+            WHERE id NOT IN (SELECT email_id FROM reply_signals)
+            "#,
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        rows
+    };
+    for (id, from_address, body_text, body_html, body_preview) in &rows {
+        let signals = ReplySignals::build(
+            from_address.as_deref(),
+            body_text.as_deref(),
+            body_html.as_deref(),
+            body_preview.as_deref(),
+        );
+        store_reply_signals(conn, id, signals)?;
+    }
+    Ok(rows.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReplySignals;
+
+    fn signals(from: &str, body: &str) -> ReplySignals {
+        ReplySignals::build(Some(from), Some(body), None, None)
+    }
+
+    #[test]
+    fn questions_count_only_in_the_senders_own_text() {
+        assert!(signals("bob@example.com", "Can you send the numbers?\nThanks").asks_question);
+        assert!(signals("bob@example.com", "Thoughts (Friday?)").asks_question);
+        assert!(!signals("bob@example.com", "Numbers at https://x.example/?q=1 ").asks_question);
+        assert!(!signals("bob@example.com", "Sounds good.\n> Can you come?").asks_question);
+        assert!(
+            !signals(
+                "bob@example.com",
+                "Done.\n\nOn Mon, Feb 2, 2026 Alice <alice@example.com> wrote:\nReady?"
+            )
+            .asks_question
+        );
+        assert!(!signals("bob@example.com", "Done.\n--\nWhy not?").asks_question);
+
+        let html = ReplySignals::build(None, None, Some("<p>Free on <b>Friday</b>?</p>"), None);
+        assert!(html.asks_question);
+        assert!(!html.automated);
+
+        assert!(signals("No-Reply@Example.com", "Any questions?").automated);
+        assert!(signals("notifications+abc@github.example", "").automated);
+        assert!(!signals("replies@example.com", "").automated);
+    }
+}
@@ -0,0 +1,237 @@
+//! WHERE clauses built from typed fragments.
+//!
+//! A fragment's SQL text is always a string literal from this crate and
+//! every value a caller supplies travels as a `?` parameter next to it, so
+//! filter input never becomes SQL. Combining fragments keeps each one's
+//! parameters in placeholder order; [`SqlWhereClause::placeholders`] always
+//! equals the number of parameters.
+
+use rusqlite::ToSql;
+
+/// A SQL condition and the values for its `?` placeholders, in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqlWhereClause {
+    pub clause: String,
+    pub params: Vec<String>,
+}
+
+impl SqlWhereClause {
+    /// One condition. `clause` must hold exactly one `?` per parameter.
+    pub fn new(clause: &'static str, params: impl IntoIterator<Item = String>) -> Self {
+        let fragment = Self {
+            clause: clause.to_string(),
+            params: params.into_iter().collect(),
+        };
+        debug_assert_eq!(
+            fragment.placeholders(),
+            fragment.params.len(),
+            "placeholders and parameters differ in `{clause}`"
+        );
+        fragment
+    }
+
+    /// Matches every row.
+    pub fn always() -> Self {
+        Self::new("1 = 1", [])
+    }
+
+    /// All of `parts`; [`SqlWhereClause::always`] when there are none.
+    pub fn all(parts: impl IntoIterator<Item = Self>) -> Self {
+        Self::join(parts, " AND ")
+    }
+
+    /// Any of `parts`; [`SqlWhereClause::always`] when there are none.
+    pub fn any(parts: impl IntoIterator<Item = Self>) -> Self {
+        Self::join(parts, " OR ")
+    }
+
+    pub fn negate(self) -> Self {
+        self.wrap("NOT (", ")")
+    }
+
+    /// The condition inside fixed SQL, e.g. a sub-select:
+    /// `wrap("id IN (SELECT email_id FROM attachments WHERE ", ")")`.
+    pub fn wrap(self, prefix: &'static str, suffix: &'static str) -> Self {
+        Self {
+            clause: format!("{prefix}{}{suffix}", self.clause),
+            params: self.params,
+        }
+    }
+
+    /// Number of `?` placeholders in the clause.
+    pub fn placeholders(&self) -> usize {
+        self.clause.matches('?').count()
+    }
+
+    /// The parameters as rusqlite arguments, followed by `extra` for
+    /// placeholders the caller appends after the clause (`LIMIT ?`).
+    pub fn bind<'a>(&'a self, extra: &[&'a dyn ToSql]) -> Vec<&'a dyn ToSql> {
+        self.params
+            .iter()
+            .map(|param| param as &dyn ToSql)
+            .chain(extra.iter().copied())
+            .collect()
+    }
+
+    fn join(parts: impl IntoIterator<Item = Self>, separator: &str) -> Self {
+        let mut parts = parts.into_iter().collect::<Vec<_>>();
+        match parts.len() {
+            0 => Self::always(),
+            1 => parts.remove(0),
+            _ => Self {
+                clause: format!(
+                    "({})",
+                    parts
+                        .iter()
+                        .map(|part| part.clause.as_str())
+                        .collect::<Vec<_>>()
+                        .join(separator)
+                ),
+                params: parts.into_iter().flat_map(|part| part.params).collect(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use proptest::prelude::*;
+
+    use super::SqlWhereClause;
+    use crate::db::{Database, EmailSearchFilters};
+    use crate::search::filters::{EmailFilters, Scope};
+    use crate::search::query::parse_query;
+
+    /// Filter values with quotes, placeholders and SQL keywords mixed in.
+    fn input() -> impl Strategy<Value = Option<String>> {
+        proptest::option::of(prop_oneof![
+            "[a-z@.'\"?%_ ;()-]{0,12}",
+            Just("x' OR '1'='1".to_string()),
+            Just("?; DROP TABLE emails; --".to_string()),
+        ])
+    }
+
+    fn date() -> impl Strategy<Value = Option<NaiveDate>> {
+        proptest::option::of((0i64..3650).prop_map(|days| {
+            NaiveDate::from_ymd_opt(2020, 1, 1).unwrap() + chrono::Days::new(days as u64)
+        }))
+    }
+
+    fn email_filters() -> impl Strategy<Value = EmailFilters> {
+        (
+            (input(), input(), input(), input(), input(), input()),
+            (input(), input(), date(), date()),
+            (0usize..3, any::<bool>(), any::<bool>()),
+        )
+            .prop_map(
+                |(
+                    (query, from, from_domain, to, account, folder),
+                    (attachment_name, attachment_type, since, until),
+                    (scope, unread_only, has_attachment),
+                )| EmailFilters {
+                    query,
+                    scope: [Scope::All, Scope::Professional, Scope::Personal][scope],
+                    from,
+                    from_domain,
+                    to,
+                    since,
+                    until,
+                    account,
+                    folder,
+                    unread_only,
+                    has_attachment,
+                    attachment_name,
+                    attachment_type,
+                    ..EmailFilters::default()
+                },
+            )
+    }
+
+    fn scratch_db() -> (Database, std::path::PathBuf) {
+        let path = std::env::temp_dir().join(format!("ess-sql-{}.db", uuid::Uuid::new_v4()));
+        (Database::open(&path).expect("open db"), path)
+    }
+
+    #[test]
+    fn combinators_keep_params_in_placeholder_order() {
+        let clause = SqlWhereClause::all([
+            SqlWhereClause::new("a = ?", ["1".to_string()]),
+            SqlWhereClause::any([
+                SqlWhereClause::new("b = ?", ["2".to_string()]),
+                SqlWhereClause::new("c BETWEEN ? AND ?", ["3".to_string(), "4".to_string()])
+                    .negate(),
+            ]),
+            SqlWhereClause::new("d = ?", ["5".to_string()])
+                .wrap("id IN (SELECT email_id FROM attachments WHERE ", ")"),
+        ]);
+        assert_eq!(
+            clause.clause,
+            "(a = ? AND (b = ? OR NOT (c BETWEEN ? AND ?)) AND id IN (SELECT email_id FROM attachments WHERE d = ?))"
+        );
+        assert_eq!(clause.params, ["1", "2", "3", "4", "5"]);
+        assert_eq!(SqlWhereClause::all([]).clause, "1 = 1");
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn search_filters_bind_every_placeholder(filters in email_filters()) {
+            let (db, path) = scratch_db();
+            let predicate = filters.to_sql_where();
+            prop_assert_eq!(predicate.placeholders(), predicate.params.len());
+            for value in [&filters.from, &filters.to, &filters.account, &filters.folder]
+                .into_iter()
+                .flatten()
+                .filter(|value| value.contains('\''))
+            {
+                prop_assert!(!predicate.clause.contains(value.as_str()));
+            }
+            let sql = format!("SELECT COUNT(*) FROM emails WHERE {}", predicate.clause);
+            let count: i64 = db
+                .conn()
+                .query_row(&sql, predicate.bind(&[]).as_slice(), |row| row.get(0))
+                .expect("run predicate");
+            prop_assert_eq!(count, 0);
+            let _ = std::fs::remove_file(path);
+        }
+
+        #[test]
+        fn parsed_queries_bind_every_placeholder(query in "[a-z:\"()?' -]{0,40}") {
+            if let Ok(Some(node)) = parse_query(&query) {
+                let predicate = node.to_sql();
+                prop_assert_eq!(predicate.placeholders(), predicate.params.len());
+            }
+        }
+
+        #[test]
+        fn db_listings_accept_any_filter_values(
+            query in input(),
+            account_id in input(),
+            folder in input(),
+            from_address in input(),
+            since in date(),
+        ) {
+            let (db, path) = scratch_db();
+            let emails = db.search_emails(EmailSearchFilters {
+                query: query.clone(),
+                account_id,
+                account_type: Some("professional".to_string()),
+                folder,
+                from_address,
+                from_domain: None,
+                since,
+                until: None,
+                tag: None,
+                needs_reply_before: None,
+                limit: 10,
+                offset: 0,
+                after: None,
+            });
+            prop_assert!(emails.expect("search emails").is_empty());
+            prop_assert!(db.get_contacts(query.as_deref()).expect("get contacts").is_empty());
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::BTreeSet;
+
+use rusqlite::OptionalExtension;
+
+use crate::db::models::Email;
+use crate::db::{Database, DbError};
+
+impl Database {
+    /// Stars an email locally. Returns `false` when it was already starred.
+    pub fn star_email(&self, id: &str) -> Result<bool, DbError> {
+        let inserted = self
+            .conn
+            .execute("INSERT OR IGNORE INTO stars (email_id) VALUES (?)", [id])?;
+        Ok(inserted > 0)
+    }
+
+    /// Removes a local star. Returns `false` when the email was not starred.
+    pub fn unstar_email(&self, id: &str) -> Result<bool, DbError> {
+        let deleted = self
+            .conn
+            .execute("DELETE FROM stars WHERE email_id = ?", [id])?;
+        Ok(deleted > 0)
+    }
+
+    /// When the email was starred, or `None` if it is not.
+    pub fn starred_at(&self, id: &str) -> Result<Option<String>, DbError> {
+        let starred_at = self
+            .conn
+            .query_row(
+                "SELECT starred_at FROM stars WHERE email_id = ?",
+                [id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(starred_at)
+    }
+
+    /// Ids of every starred email, for the `is:starred` search term.
+    pub fn starred_email_ids(&self) -> Result<BTreeSet<String>, DbError> {
+        let mut stmt = self.conn.prepare("SELECT email_id FROM stars")?;
+        let ids = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<BTreeSet<_>>>()?;
+        Ok(ids)
+    }
+
+    /// Starred emails, most recently starred first.
+    pub fn starred_emails(&self, limit: usize) -> Result<Vec<Email>, DbError> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT e.id, e.internet_message_id, e.conversation_id, e.account_id, e.subject,
+                   e.from_address, e.from_name, e.to_addresses, e.cc_addresses, e.bcc_addresses,
+                   e.body_text, e.body_html, e.body_preview, e.received_at, e.sent_at,
+                   e.importance, e.is_read, e.has_attachments, e.folder, e.categories,
+                   e.flag_status, e.web_link, e.metadata
+            FROM stars s
+            JOIN emails e ON e.id = s.email_id
+            ORDER BY s.starred_at DESC, s.rowid DESC
+            LIMIT ?
+            "#,
+        )?;
+        let emails = stmt
+            .query_map([limit as i64], Email::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(emails)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::models::{Account, AccountType, Email};
+    use crate::db::Database;
+
+    fn email(id: &str, flag_status: Option<&str>) -> Email {
+        Email {
+            account_id: Some("acc".to_string()),
+            subject: Some("Boarding pass".to_string()),
+            from_address: Some("airline@example.com".to_string()),
+            received_at: "2026-02-01T10:00:00Z".to_string(),
+            is_read: Some(true),
+            has_attachments: Some(false),
+            folder: Some("inbox".to_string()),
+            flag_status: flag_status.map(str::to_string),
+            ..Email::fixture(id)
+        }
+    }
+
+    #[test]
+    fn stars_survive_provider_flag_changes() {
+        let path = std::env::temp_dir().join(format!("ess-stars-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        db.insert_account(&Account {
+            account_id: "acc".to_string(),
+            email_address: "me@example.com".to_string(),
+            display_name: None,
+            tenant_id: None,
+            account_type: AccountType::Personal,
+            enabled: true,
+            last_sync: None,
+            config: None,
+        })
+        .expect("insert account");
+        db.insert_email(&email("a", Some("flagged")))
+            .expect("insert email");
+        db.insert_email(&email("b", None)).expect("insert email");
+
+        assert!(db.star_email("a").expect("star"));
+        assert!(!db.star_email("a").expect("star again"));
+        assert!(db.star_email("b").expect("star"));
+
+        // A phone clears the provider flag; the next sync rewrites the row.
+        db.insert_email(&email("a", None)).expect("resync email");
+        assert!(db.starred_at("a").expect("starred at").is_some());
+        let starred = db
+            .starred_emails(10)
+            .expect("starred emails")
+            .into_iter()
+            .map(|email| email.id)
+            .collect::<Vec<_>>();
+        assert_eq!(starred, vec!["b", "a"]);
+
+        assert!(db.unstar_email("b").expect("unstar"));
+        assert!(!db.unstar_email("b").expect("unstar again"));
+        assert_eq!(
+            db.starred_email_ids().expect("starred ids"),
+            ["a".to_string()].into()
+        );
+
+        let _ = std::fs::remove_file(path);
+    }
+}
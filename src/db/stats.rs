@@ -0,0 +1,180 @@
+use rusqlite::params;
+use serde::Serialize;
+
+use crate::db::{Database, DatabaseStats, DbError};
+
+/// Point-in-time counts kept in `stats_snapshots` so growth can be charted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StatsSnapshot {
+    /// RFC3339 UTC timestamp.
+    pub captured_at: String,
+    pub total_accounts: i64,
+    pub total_emails: i64,
+    pub total_contacts: i64,
+    pub index_doc_count: i64,
+    pub index_size_bytes: i64,
+}
+
+impl StatsSnapshot {
+    pub fn new(
+        captured_at: String,
+        db: &DatabaseStats,
+        index_doc_count: u64,
+        index_size: u64,
+    ) -> Self {
+        Self {
+            captured_at,
+            total_accounts: db.total_accounts,
+            total_emails: db.total_emails,
+            total_contacts: db.total_contacts,
+            index_doc_count: i64::try_from(index_doc_count).unwrap_or(i64::MAX),
+            index_size_bytes: i64::try_from(index_size).unwrap_or(i64::MAX),
+        }
+    }
+}
+
+/// Growth between the oldest snapshot in a window and the newest one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StatsTrend {
+    pub first: StatsSnapshot,
+    pub last: StatsSnapshot,
+    pub emails_delta: i64,
+    pub contacts_delta: i64,
+    pub index_doc_delta: i64,
+    pub index_size_delta: i64,
+    /// The last snapshot of each day, oldest first.
+    pub daily: Vec<StatsSnapshot>,
+}
+
+impl StatsTrend {
+    /// Summarizes snapshots ordered by `captured_at`; `None` when there are
+    /// none.
+    pub fn from_snapshots(snapshots: &[StatsSnapshot]) -> Option<Self> {
+        let first = snapshots.first()?.clone();
+        let last = snapshots.last()?.clone();
+
+        let mut daily: Vec<StatsSnapshot> = Vec::new();
+        for snapshot in snapshots {
+            let day = snapshot.captured_at.get(..10);
+            match daily.last_mut() {
+                Some(previous) if previous.captured_at.get(..10) == day => {
+                    *previous = snapshot.clone();
+                }
+                _ => daily.push(snapshot.clone()),
+            }
+        }
+
+        Some(Self {
+            emails_delta: last.total_emails - first.total_emails,
+            contacts_delta: last.total_contacts - first.total_contacts,
+            index_doc_delta: last.index_doc_count - first.index_doc_count,
+            index_size_delta: last.index_size_bytes - first.index_size_bytes,
+            first,
+            last,
+            daily,
+        })
+    }
+}
+
+impl Database {
+    pub fn record_stats_snapshot(&self, snapshot: &StatsSnapshot) -> Result<(), DbError> {
+        self.conn.execute(
+            r#"
+            INSERT INTO stats_snapshots (
+                captured_at, total_accounts, total_emails, total_contacts,
+                index_doc_count, index_size_bytes
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            "#,
+            params![
+                snapshot.captured_at,
+                snapshot.total_accounts,
+                snapshot.total_emails,
+                snapshot.total_contacts,
+                snapshot.index_doc_count,
+                snapshot.index_size_bytes,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Snapshots captured at or after `since` (RFC3339), oldest first.
+    pub fn stats_snapshots_since(&self, since: &str) -> Result<Vec<StatsSnapshot>, DbError> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT captured_at, total_accounts, total_emails, total_contacts,
+                   index_doc_count, index_size_bytes
+            FROM stats_snapshots
+            WHERE captured_at >= ?1
+            ORDER BY captured_at ASC, id ASC
+            "#,
+        )?;
+        let snapshots = stmt
+            .query_map([since], |row| {
+                Ok(StatsSnapshot {
+                    captured_at: row.get(0)?,
+                    total_accounts: row.get(1)?,
+                    total_emails: row.get(2)?,
+                    total_contacts: row.get(3)?,
+                    index_doc_count: row.get(4)?,
+                    index_size_bytes: row.get(5)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(snapshots)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::Database;
+
+    use super::{StatsSnapshot, StatsTrend};
+
+    fn snapshot(captured_at: &str, emails: i64, index_size: i64) -> StatsSnapshot {
+        StatsSnapshot {
+            captured_at: captured_at.to_string(),
+            total_accounts: 1,
+            total_emails: emails,
+            total_contacts: emails / 10,
+            index_doc_count: emails,
+            index_size_bytes: index_size,
+        }
+    }
+
+    #[test]
+    fn trend_keeps_last_snapshot_per_day_within_window() {
+        let path = std::env::temp_dir().join(format!("ess-stats-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        for snapshot in [
+            snapshot("2026-01-01T08:00:00Z", 50, 500),
+            snapshot("2026-02-01T08:00:00Z", 100, 1_000),
+            snapshot("2026-02-01T20:00:00Z", 120, 1_200),
+            snapshot("2026-02-03T08:00:00Z", 400, 4_000),
+        ] {
+            db.record_stats_snapshot(&snapshot)
+                .expect("record snapshot");
+        }
+
+        let snapshots = db
+            .stats_snapshots_since("2026-01-15T00:00:00Z")
+            .expect("load snapshots");
+        let trend = StatsTrend::from_snapshots(&snapshots).expect("trend");
+
+        assert_eq!(trend.first.total_emails, 100);
+        assert_eq!(trend.last.total_emails, 400);
+        assert_eq!(trend.emails_delta, 300);
+        assert_eq!(trend.index_size_delta, 3_000);
+        let days = trend
+            .daily
+            .iter()
+            .map(|s| (s.captured_at.as_str(), s.total_emails))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            days,
+            vec![("2026-02-01T20:00:00Z", 120), ("2026-02-03T08:00:00Z", 400)]
+        );
+        assert!(StatsTrend::from_snapshots(&[]).is_none());
+
+        let _ = std::fs::remove_file(path);
+    }
+}
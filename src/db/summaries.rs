@@ -0,0 +1,138 @@
+use rusqlite::{params, OptionalExtension};
+use serde::Serialize;
+
+use crate::db::models::Email;
+use crate::db::{Database, DbError};
+
+/// An LLM-written summary of a conversation, stored by `ess summarize`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConversationSummary {
+    pub conversation_id: String,
+    pub summary: String,
+    /// Model that wrote it, as the backend was asked for it.
+    pub model: String,
+    /// Messages in the thread when it was written.
+    pub message_count: i64,
+    /// Received time of the newest of those messages.
+    pub last_received_at: Option<String>,
+    /// RFC3339 UTC timestamp.
+    pub created_at: String,
+}
+
+impl ConversationSummary {
+    /// Whether the summary was written from exactly these messages, i.e.
+    /// none arrived or left the thread since.
+    pub fn covers(&self, emails: &[Email]) -> bool {
+        self.message_count == emails.len() as i64
+            && self.last_received_at.as_deref() == latest_received_at(emails)
+    }
+
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            conversation_id: row.get(0)?,
+            summary: row.get(1)?,
+            model: row.get(2)?,
+            message_count: row.get(3)?,
+            last_received_at: row.get(4)?,
+            created_at: row.get(5)?,
+        })
+    }
+}
+
+/// The newest received time among `emails`.
+pub fn latest_received_at(emails: &[Email]) -> Option<&str> {
+    emails.iter().map(|email| email.received_at.as_str()).max()
+}
+
+impl Database {
+    /// Stores a conversation's summary, replacing the previous one.
+    pub fn store_summary(&self, summary: &ConversationSummary) -> Result<(), DbError> {
+        self.conn.execute(
+            r#"
+            INSERT OR REPLACE INTO summaries (
+                conversation_id, summary, model, message_count, last_received_at, created_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            "#,
+            params![
+                summary.conversation_id,
+                summary.summary,
+                summary.model,
+                summary.message_count,
+                summary.last_received_at,
+                summary.created_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn summary(&self, conversation_id: &str) -> Result<Option<ConversationSummary>, DbError> {
+        let summary = self
+            .conn
+            .query_row(
+                r#"
+                SELECT conversation_id, summary, model, message_count, last_received_at, created_at
+                FROM summaries
+                WHERE conversation_id = ?
+                "#,
+                [conversation_id],
+                ConversationSummary::from_row,
+            )
+            .optional()?;
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConversationSummary;
+    use crate::db::models::Email;
+    use crate::db::Database;
+
+    fn email(id: &str, received_at: &str) -> Email {
+        Email {
+            conversation_id: Some("t1".to_string()),
+            subject: Some("Budget".to_string()),
+            from_address: Some("alice@example.com".to_string()),
+            received_at: received_at.to_string(),
+            ..Email::fixture(id)
+        }
+    }
+
+    #[test]
+    fn summaries_round_trip_and_notice_new_messages() {
+        let path = std::env::temp_dir().join(format!("ess-summaries-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        let mut emails = vec![
+            email("m1", "2026-02-01T10:00:00Z"),
+            email("m2", "2026-02-02T10:00:00Z"),
+        ];
+        let summary = ConversationSummary {
+            conversation_id: "t1".to_string(),
+            summary: "Alice asks for the budget.".to_string(),
+            model: "llama3.2".to_string(),
+            message_count: 2,
+            last_received_at: Some("2026-02-02T10:00:00Z".to_string()),
+            created_at: "2026-02-03T10:00:00Z".to_string(),
+        };
+        assert!(db.summary("t1").expect("read summary").is_none());
+        db.store_summary(&summary).expect("store summary");
+        let stored = db.summary("t1").expect("read summary").expect("summary");
+        assert_eq!(stored, summary);
+        assert!(stored.covers(&emails));
+
+        emails.push(email("m3", "2026-02-04T10:00:00Z"));
+        assert!(!stored.covers(&emails));
+        db.store_summary(&ConversationSummary {
+            summary: "Alice got the budget.".to_string(),
+            message_count: 3,
+            last_received_at: Some("2026-02-04T10:00:00Z".to_string()),
+            ..summary
+        })
+        .expect("replace summary");
+        let replaced = db.summary("t1").expect("read summary").expect("summary");
+        assert_eq!(replaced.summary, "Alice got the budget.");
+        assert!(replaced.covers(&emails));
+
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,196 @@
+//! How each account's syncs went: one `sync_runs` row per account per
+//! sync, which `ess accounts sync-status` sums up into the time of the
+//! last successful sync, the last error and a warning when an account has
+//! gone without a successful sync for too long.
+
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::params;
+use serde::Serialize;
+
+use crate::db::{Database, DbError};
+
+/// How long an account may go without a successful sync before
+/// `ess accounts sync-status` warns about it.
+pub const STALE_AFTER_HOURS: i64 = 24;
+
+/// One sync of one account.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SyncRun {
+    pub account_id: String,
+    pub connector: String,
+    pub started_at: String,
+    pub finished_at: String,
+    pub duration_ms: u64,
+    pub emails_added: usize,
+    pub emails_updated: usize,
+    /// Errors the sync reported while carrying on.
+    pub errors: usize,
+    /// The error that stopped the sync, or else the first one it reported.
+    pub error: Option<String>,
+    /// Whether the sync ran to the end, even with errors along the way.
+    pub succeeded: bool,
+}
+
+/// An account's syncs, summed up.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SyncHealth {
+    pub runs: i64,
+    pub last_run_at: Option<String>,
+    pub last_duration_ms: Option<i64>,
+    pub last_success_at: Option<String>,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<String>,
+    /// Emails added or updated by all syncs recorded.
+    pub emails_synced: i64,
+}
+
+impl SyncHealth {
+    /// Whether the account has been synced but not successfully within
+    /// [`STALE_AFTER_HOURS`] of `now`.
+    pub fn is_stale(&self, now: DateTime<Utc>) -> bool {
+        if self.runs == 0 {
+            return false;
+        }
+        let Some(last_success) = self
+            .last_success_at
+            .as_deref()
+            .and_then(|at| DateTime::parse_from_rfc3339(at).ok())
+        else {
+            return true;
+        };
+        now.signed_duration_since(last_success) > Duration::hours(STALE_AFTER_HOURS)
+    }
+}
+
+impl Database {
+    /// Records a sync; a successful one also becomes the account's
+    /// `last_sync`.
+    pub fn record_sync_run(&self, run: &SyncRun) -> Result<(), DbError> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            r#"
+            INSERT INTO sync_runs (
+                account_id, connector, started_at, finished_at, duration_ms, emails_added,
+                emails_updated, errors, error, succeeded
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+            "#,
+            params![
+                run.account_id,
+                run.connector,
+                run.started_at,
+                run.finished_at,
+                i64::try_from(run.duration_ms).unwrap_or(i64::MAX),
+                run.emails_added as i64,
+                run.emails_updated as i64,
+                run.errors as i64,
+                run.error,
+                run.succeeded,
+            ],
+        )?;
+        if run.succeeded {
+            tx.execute(
+                "UPDATE accounts SET last_sync = ?1 WHERE account_id = ?2",
+                params![run.finished_at, run.account_id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn sync_health(&self, account_id: &str) -> Result<SyncHealth, DbError> {
+        let health = self.conn.query_row(
+            r#"
+            SELECT COUNT(*),
+                   (SELECT finished_at FROM sync_runs WHERE account_id = ?1
+                    ORDER BY id DESC LIMIT 1),
+                   (SELECT duration_ms FROM sync_runs WHERE account_id = ?1
+                    ORDER BY id DESC LIMIT 1),
+                   (SELECT MAX(finished_at) FROM sync_runs WHERE account_id = ?1 AND succeeded),
+                   (SELECT error FROM sync_runs WHERE account_id = ?1 AND error IS NOT NULL
+                    ORDER BY id DESC LIMIT 1),
+                   (SELECT finished_at FROM sync_runs WHERE account_id = ?1 AND error IS NOT NULL
+                    ORDER BY id DESC LIMIT 1),
+                   COALESCE(SUM(emails_added + emails_updated), 0)
+            FROM sync_runs
+            WHERE account_id = ?1
+            "#,
+            [account_id],
+            |row| {
+                Ok(SyncHealth {
+                    runs: row.get(0)?,
+                    last_run_at: row.get(1)?,
+                    last_duration_ms: row.get(2)?,
+                    last_success_at: row.get(3)?,
+                    last_error: row.get(4)?,
+                    last_error_at: row.get(5)?,
+                    emails_synced: row.get(6)?,
+                })
+            },
+        )?;
+        Ok(health)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::SyncRun;
+    use crate::db::models::{Account, AccountType};
+    use crate::db::Database;
+
+    fn run(finished_at: &str, succeeded: bool, error: Option<&str>) -> SyncRun {
+        SyncRun {
+            account_id: "work".to_string(),
+            connector: "graph_api".to_string(),
+            started_at: finished_at.to_string(),
+            finished_at: finished_at.to_string(),
+            duration_ms: 1_500,
+            emails_added: 3,
+            emails_updated: 1,
+            errors: usize::from(error.is_some()),
+            error: error.map(str::to_string),
+            succeeded,
+        }
+    }
+
+    #[test]
+    fn runs_update_last_sync_and_sum_up_into_health() {
+        let path = std::env::temp_dir().join(format!("ess-sync-runs-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        db.insert_account(&Account {
+            account_id: "work".to_string(),
+            email_address: "you@company.com".to_string(),
+            display_name: None,
+            tenant_id: None,
+            account_type: AccountType::Professional,
+            enabled: true,
+            last_sync: None,
+            config: None,
+        })
+        .expect("insert account");
+        assert!(!db.sync_health("work").expect("health").is_stale(Utc::now()));
+
+        db.record_sync_run(&run("2026-03-01T08:00:00Z", true, None))
+            .expect("record");
+        db.record_sync_run(&run("2026-03-02T08:00:00Z", false, Some("token expired")))
+            .expect("record");
+
+        let account = db.get_account("work").expect("get").expect("account");
+        assert_eq!(account.last_sync.as_deref(), Some("2026-03-01T08:00:00Z"));
+        let health = db.sync_health("work").expect("health");
+        assert_eq!(health.runs, 2);
+        assert_eq!(health.last_run_at.as_deref(), Some("2026-03-02T08:00:00Z"));
+        assert_eq!(
+            health.last_success_at.as_deref(),
+            Some("2026-03-01T08:00:00Z")
+        );
+        assert_eq!(health.last_error.as_deref(), Some("token expired"));
+        assert_eq!(health.emails_synced, 8);
+        let day_later = Utc.with_ymd_and_hms(2026, 3, 2, 8, 0, 0).unwrap();
+        assert!(!health.is_stale(day_later));
+        assert!(health.is_stale(day_later + chrono::Duration::minutes(1)));
+
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::BTreeSet;
+
+use chrono::{SecondsFormat, Utc};
+use rusqlite::params;
+use serde::Serialize;
+
+use crate::db::models::Email;
+use crate::db::{Database, DbError};
+
+/// A tag on an email and what assigned it: `rule` or `model`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EmailTag {
+    pub tag: String,
+    pub source: String,
+}
+
+/// An email's full set of tags and the rules that produced them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaggedEmail {
+    pub email_id: String,
+    pub tags: Vec<EmailTag>,
+    pub rules_hash: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: i64,
+}
+
+impl Database {
+    /// Replaces each email's tags and records the rules it was tagged
+    /// under, in one transaction.
+    pub fn store_email_tags(&self, tagged: &[TaggedEmail]) -> Result<(), DbError> {
+        let tagged_at = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+        let tx = self.conn.unchecked_transaction()?;
+        for email in tagged {
+            tx.execute("DELETE FROM tags WHERE email_id = ?", [&email.email_id])?;
+            for tag in &email.tags {
+                tx.execute(
+                    "INSERT OR IGNORE INTO tags (email_id, tag, source) VALUES (?1, ?2, ?3)",
+                    params![email.email_id, tag.tag, tag.source],
+                )?;
+            }
+            tx.execute(
+                "INSERT OR REPLACE INTO tagged_emails (email_id, rules_hash, tagged_at) \
+                 VALUES (?1, ?2, ?3)",
+                params![email.email_id, email.rules_hash, tagged_at],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Up to `limit` emails not yet tagged under `rules_hash`, in id order
+    /// after `after_id`.
+    pub fn emails_to_tag(
+        &self,
+        rules_hash: &str,
+        after_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<Email>, DbError> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT e.id, e.internet_message_id, e.conversation_id, e.account_id, e.subject,
+                   e.from_address, e.from_name, e.to_addresses, e.cc_addresses, e.bcc_addresses,
+                   e.body_text, e.body_html, e.body_preview, e.received_at, e.sent_at,
+                   e.importance, e.is_read, e.has_attachments, e.folder, e.categories,
+                   e.flag_status, e.web_link, e.metadata
+            FROM emails e
+            LEFT JOIN tagged_emails t ON t.email_id = e.id
+            WHERE (t.rules_hash IS NULL OR t.rules_hash != ?1) AND e.id > ?2
+            ORDER BY e.id
+            LIMIT ?3
+            "#,
+        )?;
+        let emails = stmt
+            .query_map(
+                params![rules_hash, after_id.unwrap_or(""), limit as i64],
+                Email::from_row,
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(emails)
+    }
+
+    /// Forgets which rules every email was tagged under, so the next run
+    /// tags them all again. Existing tags stay until then.
+    pub fn reset_tagging(&self) -> Result<usize, DbError> {
+        Ok(self.conn.execute("DELETE FROM tagged_emails", [])?)
+    }
+
+    pub fn email_tags(&self, email_id: &str) -> Result<Vec<EmailTag>, DbError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT tag, source FROM tags WHERE email_id = ? ORDER BY tag")?;
+        let tags = stmt
+            .query_map([email_id], |row| {
+                Ok(EmailTag {
+                    tag: row.get(0)?,
+                    source: row.get(1)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(tags)
+    }
+
+    /// Ids of every email with `tag`, for the `tag:` search term.
+    pub fn tagged_email_ids(&self, tag: &str) -> Result<BTreeSet<String>, DbError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT email_id FROM tags WHERE tag = ?")?;
+        let ids = stmt
+            .query_map([tag.to_lowercase()], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<BTreeSet<_>>>()?;
+        Ok(ids)
+    }
+
+    /// Emails per tag, most used first.
+    pub fn tag_counts(&self) -> Result<Vec<TagCount>, DbError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT tag, COUNT(*) AS count FROM tags GROUP BY tag ORDER BY count DESC, tag",
+        )?;
+        let counts = stmt
+            .query_map([], |row| {
+                Ok(TagCount {
+                    tag: row.get(0)?,
+                    count: row.get(1)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(counts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EmailTag, TaggedEmail};
+    use crate::db::models::Email;
+    use crate::db::Database;
+
+    fn email(id: &str) -> Email {
+        Email {
+            subject: Some("Your invoice".to_string()),
+            from_address: Some("billing@example.com".to_string()),
+            received_at: "2026-02-01T10:00:00Z".to_string(),
+            ..Email::fixture(id)
+        }
+    }
+
+    fn tagged(id: &str, tags: &[&str], rules_hash: &str) -> TaggedEmail {
+        TaggedEmail {
+            email_id: id.to_string(),
+            tags: tags
+                .iter()
+                .map(|tag| EmailTag {
+                    tag: tag.to_string(),
+                    source: "rule".to_string(),
+                })
+                .collect(),
+            rules_hash: rules_hash.to_string(),
+        }
+    }
+
+    #[test]
+    fn tagging_is_redone_for_new_rules_and_resynced_emails() {
+        let path = std::env::temp_dir().join(format!("ess-tags-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        for id in ["a", "b"] {
+            db.insert_email(&email(id)).expect("insert email");
+        }
+        let pending = |hash: &str| -> Vec<String> {
+            db.emails_to_tag(hash, None, 10)
+                .expect("emails to tag")
+                .into_iter()
+                .map(|email| email.id)
+                .collect()
+        };
+        assert_eq!(pending("h1"), ["a", "b"]);
+
+        db.store_email_tags(&[
+            tagged("a", &["invoice", "finance"], "h1"),
+            tagged("b", &["invoice"], "h1"),
+        ])
+        .expect("store tags");
+        assert!(pending("h1").is_empty());
+        assert_eq!(pending("h2"), ["a", "b"]);
+        assert_eq!(
+            db.emails_to_tag("h2", Some("a"), 10)
+                .expect("after a")
+                .len(),
+            1
+        );
+        let counts = db.tag_counts().expect("counts");
+        assert_eq!((counts[0].tag.as_str(), counts[0].count), ("invoice", 2));
+        assert_eq!(
+            db.tagged_email_ids("Invoice").expect("ids").len(),
+            2,
+            "tags are looked up case-insensitively"
+        );
+
+        db.insert_email(&email("a")).expect("re-sync a");
+        assert!(db.email_tags("a").expect("tags of a").is_empty());
+        assert_eq!(pending("h1"), ["a"]);
+
+        let _ = std::fs::remove_file(path);
+    }
+}
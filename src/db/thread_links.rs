@@ -0,0 +1,348 @@
+//! Threads stitched across accounts. `conversation_id` is provider
+//! specific, so a discussion carried on from a work and a personal account
+//! is stored as two conversations. `thread_links` records every Message-ID
+//! an email has or refers to (`Email::in_reply_to`, `Email::references`);
+//! conversations of different accounts that share one are the same thread.
+//!
+//! Within an account the provider's conversation, with any `ess thread
+//! merge` or `split`, stays authoritative.
+
+use std::collections::{HashSet, VecDeque};
+
+use crate::db::dedupe::normalize_message_id;
+use crate::db::models::Email;
+use crate::db::{Database, DbError};
+use rusqlite::{params, Connection};
+
+/// The threading headers of a message, as [`Email::in_reply_to`] and
+/// [`Email::references`] store them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ThreadHeaders {
+    pub in_reply_to: Option<String>,
+    pub references: Vec<String>,
+}
+
+impl ThreadHeaders {
+    /// Picks `In-Reply-To` and `References` out of a message's
+    /// `(name, value)` pairs. The first of each header counts.
+    pub fn from_headers<'a>(headers: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        let mut in_reply_to = None;
+        let mut references = None;
+        for (name, value) in headers {
+            let slot = if name.eq_ignore_ascii_case("In-Reply-To") {
+                &mut in_reply_to
+            } else if name.eq_ignore_ascii_case("References") {
+                &mut references
+            } else {
+                continue;
+            };
+            if slot.is_none() {
+                *slot = Some(parse_message_ids(value));
+            }
+        }
+        Self {
+            // Some clients put the whole chain in `In-Reply-To`; the parent
+            // is the last id.
+            in_reply_to: in_reply_to.and_then(|mut ids| ids.pop()),
+            references: references.unwrap_or_default(),
+        }
+    }
+}
+
+/// The Message-IDs of a header value as `<id>`, in order. A value without
+/// angle brackets is split on whitespace.
+pub fn parse_message_ids(value: &str) -> Vec<String> {
+    let ids: Vec<&str> = if value.contains('<') {
+        value
+            .split('<')
+            .skip(1)
+            .filter_map(|part| part.split_once('>').map(|(id, _)| id))
+            .collect()
+    } else {
+        value.split_whitespace().collect()
+    };
+    ids.into_iter()
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(|id| format!("<{id}>"))
+        .collect()
+}
+
+/// Records the Message-IDs `email` has and refers to.
+pub(crate) fn store_thread_links(conn: &Connection, email: &Email) -> Result<(), rusqlite::Error> {
+    conn.execute("DELETE FROM thread_links WHERE email_id = ?", [&email.id])?;
+    let own = email
+        .internet_message_id
+        .as_deref()
+        .map(normalize_message_id)
+        .filter(|id| !id.is_empty());
+    let referenced = email
+        .in_reply_to
+        .iter()
+        .chain(&email.references)
+        .map(|id| normalize_message_id(id))
+        .filter(|id| !id.is_empty());
+    for (message_id, is_own) in own
+        .into_iter()
+        .map(|id| (id, true))
+        .chain(referenced.map(|id| (id, false)))
+    {
+        conn.execute(
+            "INSERT OR IGNORE INTO thread_links (email_id, message_id, is_own) VALUES (?, ?, ?)",
+            params![email.id, message_id, is_own],
+        )?;
+    }
+    Ok(())
+}
+
+/// Records links for emails stored before the `thread_links` table
+/// existed.
+pub(crate) fn backfill_thread_links(conn: &Connection) -> Result<usize, rusqlite::Error> {
+    let emails = {
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT * FROM emails
+            WHERE COALESCE(internet_message_id, '') != ''
+               OR in_reply_to IS NOT NULL
+               OR COALESCE(reference_ids, '[]') != '[]'
+            "#,
+        )?;
+        let emails = stmt
+            .query_map([], Email::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        emails
+    };
+    for email in &emails {
+        store_thread_links(conn, email)?;
+    }
+    Ok(emails.len())
+}
+
+/// A thread as `ess thread` shows it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StitchedThread {
+    /// The conversation asked for, or the one the email asked for is in,
+    /// then the conversations of other accounts stitched to it.
+    pub conversation_ids: Vec<String>,
+    /// Every message, oldest first; one copy of a message several accounts
+    /// received.
+    pub emails: Vec<Email>,
+}
+
+impl Database {
+    /// The stitched thread of `id`: a conversation id, an email id or a
+    /// Message-ID (with or without angle brackets). `None` when nothing
+    /// stored matches.
+    pub fn stitched_thread(&self, id: &str) -> Result<Option<StitchedThread>, DbError> {
+        let Some(start) = self.thread_start(id)? else {
+            return Ok(None);
+        };
+
+        let start_account: Option<String> = self.conn.query_row(
+            "SELECT account_id FROM emails WHERE conversation_id = ? LIMIT 1",
+            [&start],
+            |row| row.get(0),
+        )?;
+        // One conversation per account, so a thread the provider (or `ess
+        // thread split`) keeps apart stays apart.
+        let mut accounts = HashSet::from([start_account]);
+        let mut conversation_ids = vec![start.clone()];
+        let mut queue = VecDeque::from([start]);
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT other.conversation_id, other.account_id
+            FROM emails e
+            JOIN thread_links l ON l.email_id = e.id
+            JOIN thread_links ol ON ol.message_id = l.message_id AND ol.email_id != l.email_id
+            JOIN emails other ON other.id = ol.email_id
+            WHERE e.conversation_id = ?1
+              AND other.account_id IS NOT e.account_id
+              AND other.conversation_id IS NOT NULL
+            GROUP BY other.conversation_id, other.account_id
+            ORDER BY COUNT(*) DESC, MIN(other.received_at), other.conversation_id
+            "#,
+        )?;
+        while let Some(conversation_id) = queue.pop_front() {
+            let linked = stmt
+                .query_map([&conversation_id], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            for (linked, account_id) in linked {
+                if accounts.insert(account_id) {
+                    conversation_ids.push(linked.clone());
+                    queue.push_back(linked);
+                }
+            }
+        }
+
+        let mut message_ids = HashSet::new();
+        let mut emails = Vec::new();
+        for conversation_id in &conversation_ids {
+            for email in self.get_emails_by_conversation(conversation_id)? {
+                let message_id = email
+                    .internet_message_id
+                    .as_deref()
+                    .map(normalize_message_id)
+                    .filter(|id| !id.is_empty());
+                if message_id.is_none_or(|message_id| message_ids.insert(message_id)) {
+                    emails.push(email);
+                }
+            }
+        }
+        emails.sort_by(|a, b| a.received_at.cmp(&b.received_at));
+        Ok(Some(StitchedThread {
+            conversation_ids,
+            emails,
+        }))
+    }
+
+    fn thread_start(&self, id: &str) -> Result<Option<String>, DbError> {
+        let by_conversation = self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM emails WHERE conversation_id = ?)",
+            [id],
+            |row| row.get::<_, bool>(0),
+        )?;
+        if by_conversation {
+            return Ok(Some(id.to_string()));
+        }
+        if let Some(email) = self.get_email(id)? {
+            return Ok(email.conversation_id);
+        }
+        // The email that has the Message-ID, else one that refers to it.
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT e.conversation_id FROM thread_links l
+            JOIN emails e ON e.id = l.email_id
+            WHERE l.message_id = ? AND e.conversation_id IS NOT NULL
+            ORDER BY l.is_own DESC, e.received_at
+            LIMIT 1
+            "#,
+        )?;
+        let mut rows = stmt.query_map([normalize_message_id(id)], |row| row.get(0))?;
+        Ok(rows.next().transpose()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ThreadHeaders;
+    use crate::db::models::{Account, AccountType, Email};
+    use crate::db::Database;
+
+    fn email(
+        id: &str,
+        account: &str,
+        conversation: &str,
+        message_id: &str,
+        in_reply_to: Option<&str>,
+        received_at: &str,
+    ) -> Email {
+        Email {
+            internet_message_id: Some(message_id.to_string()),
+            in_reply_to: in_reply_to.map(str::to_string),
+            references: in_reply_to
+                .map(|parent| vec!["<root@example.com>".to_string(), parent.to_string()])
+                .unwrap_or_default(),
+            conversation_id: Some(conversation.to_string()),
+            account_id: Some(account.to_string()),
+            subject: Some("Offsite".to_string()),
+            from_address: Some("alice@example.com".to_string()),
+            received_at: received_at.to_string(),
+            ..Email::fixture(id)
+        }
+    }
+
+    #[test]
+    fn thread_headers_parse_into_message_ids() {
+        let headers = ThreadHeaders::from_headers([
+            ("Subject", "Re: Offsite"),
+            (
+                "in-reply-to",
+                "<root@example.com> (Alice's message) <parent@example.com>",
+            ),
+            ("References", "root@example.com\r\n parent@example.com"),
+            ("References", "<ignored@example.com>"),
+        ]);
+        assert_eq!(headers.in_reply_to.as_deref(), Some("<parent@example.com>"));
+        assert_eq!(
+            headers.references,
+            ["<root@example.com>", "<parent@example.com>"]
+        );
+        assert_eq!(ThreadHeaders::from_headers([]), ThreadHeaders::default());
+    }
+
+    #[test]
+    fn conversations_of_different_accounts_stitch_by_message_id() {
+        let path = std::env::temp_dir().join(format!("ess-stitch-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        for account_id in ["work", "home"] {
+            db.insert_account(&Account {
+                account_id: account_id.to_string(),
+                email_address: format!("me@{account_id}.example.com"),
+                display_name: None,
+                tenant_id: None,
+                account_type: AccountType::Professional,
+                enabled: true,
+                last_sync: None,
+                config: None,
+            })
+            .expect("insert account");
+        }
+
+        let root = "<root@example.com>";
+        for email in [
+            email("w1", "work", "work-t", root, None, "2026-02-01T10:00:00Z"),
+            // The same message, also delivered to the personal account.
+            email("h1", "home", "home-t", root, None, "2026-02-01T10:00:01Z"),
+            email(
+                "h2",
+                "home",
+                "home-t",
+                "<reply@home.example.com>",
+                Some(root),
+                "2026-02-01T11:00:00Z",
+            ),
+            email(
+                "w2",
+                "work",
+                "work-t",
+                "<second@work.example.com>",
+                Some("<reply@home.example.com>"),
+                "2026-02-01T12:00:00Z",
+            ),
+            // A reply the work account filed in another conversation stays
+            // there.
+            email(
+                "w3",
+                "work",
+                "work-other",
+                "<other@work.example.com>",
+                Some(root),
+                "2026-02-02T09:00:00Z",
+            ),
+        ] {
+            db.insert_email(&email).expect("insert email");
+        }
+
+        let thread = db
+            .stitched_thread("work-t")
+            .expect("stitch")
+            .expect("thread");
+        assert_eq!(thread.conversation_ids, ["work-t", "home-t"]);
+        let ids: Vec<_> = thread
+            .emails
+            .iter()
+            .map(|email| email.id.as_str())
+            .collect();
+        assert_eq!(ids, ["w1", "h2", "w2"]);
+
+        for id in ["h2", "<REPLY@home.example.com>", "reply@home.example.com"] {
+            let thread = db.stitched_thread(id).expect("stitch").expect("thread");
+            assert_eq!(thread.conversation_ids, ["home-t", "work-t"], "{id}");
+        }
+        assert!(db.stitched_thread("missing").expect("stitch").is_none());
+
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,217 @@
+use rusqlite::{params, OptionalExtension};
+use serde::Serialize;
+
+use crate::db::{Database, DbError};
+
+/// Outcome of `Database::merge_threads`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadMerge {
+    /// The surviving thread; differs from the requested one when that was
+    /// itself merged away earlier.
+    pub thread_id: String,
+    /// Emails that changed thread.
+    pub moved: Vec<String>,
+}
+
+/// How many messages a conversation holds and who sent them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ThreadSummary {
+    pub messages: i64,
+    /// Distinct sender addresses, earliest first.
+    pub participants: Vec<String>,
+}
+
+impl Database {
+    pub fn thread_summary(&self, conversation_id: &str) -> Result<ThreadSummary, DbError> {
+        let messages = self.conn.query_row(
+            "SELECT COUNT(*) FROM emails WHERE conversation_id = ?",
+            [conversation_id],
+            |row| row.get(0),
+        )?;
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT MIN(LOWER(from_address))
+            FROM emails
+            WHERE conversation_id = ? AND COALESCE(from_address, '') <> ''
+            GROUP BY LOWER(from_address)
+            ORDER BY MIN(received_at), 1
+            "#,
+        )?;
+        let participants = stmt
+            .query_map([conversation_id], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(ThreadSummary {
+            messages,
+            participants,
+        })
+    }
+
+    /// Moves every message of thread `from` into thread `into` and records
+    /// the mapping, so messages the provider later files under `from` (or
+    /// under a thread already merged into it) land in `into` too. Notes on
+    /// `from` move along.
+    pub fn merge_threads(&self, into: &str, from: &str) -> Result<ThreadMerge, DbError> {
+        // Mappings are kept flat, so one lookup finds where `into` lives now.
+        let into = self
+            .conn
+            .query_row(
+                "SELECT thread_id FROM thread_mappings WHERE kind = 'thread' AND source_id = ?",
+                [into],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?
+            .unwrap_or_else(|| into.to_string());
+        if into == from {
+            return Ok(ThreadMerge {
+                thread_id: into,
+                moved: Vec::new(),
+            });
+        }
+
+        let tx = self.conn.unchecked_transaction()?;
+        let mut stmt = tx.prepare("SELECT id FROM emails WHERE conversation_id = ? ORDER BY id")?;
+        let moved = stmt
+            .query_map([from], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        drop(stmt);
+
+        tx.execute(
+            "UPDATE thread_mappings SET thread_id = ?1 WHERE thread_id = ?2",
+            params![into, from],
+        )?;
+        tx.execute(
+            r#"
+            INSERT INTO thread_mappings (kind, source_id, thread_id) VALUES ('thread', ?1, ?2)
+            ON CONFLICT(kind, source_id) DO UPDATE SET thread_id = excluded.thread_id
+            "#,
+            params![from, into],
+        )?;
+        tx.execute(
+            "UPDATE emails SET conversation_id = ?1 WHERE conversation_id = ?2",
+            params![into, from],
+        )?;
+        tx.execute(
+            "UPDATE notes SET conversation_id = ?1 WHERE conversation_id = ?2",
+            params![into, from],
+        )?;
+        tx.commit()?;
+
+        Ok(ThreadMerge {
+            thread_id: into,
+            moved,
+        })
+    }
+
+    /// Moves one email into a new thread of its own and returns that
+    /// thread's id, or `None` when the email does not exist.
+    pub fn split_thread(&self, email_id: &str) -> Result<Option<String>, DbError> {
+        if !self.email_exists(email_id)? {
+            return Ok(None);
+        }
+
+        let thread_id = format!("split:{email_id}");
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            r#"
+            INSERT INTO thread_mappings (kind, source_id, thread_id) VALUES ('email', ?1, ?2)
+            ON CONFLICT(kind, source_id) DO UPDATE SET thread_id = excluded.thread_id
+            "#,
+            params![email_id, thread_id],
+        )?;
+        tx.execute(
+            "UPDATE emails SET conversation_id = ?1 WHERE id = ?2",
+            params![thread_id, email_id],
+        )?;
+        tx.commit()?;
+
+        Ok(Some(thread_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::models::{Account, AccountType, Email};
+    use crate::db::Database;
+
+    fn email(id: &str, conversation_id: &str) -> Email {
+        Email {
+            conversation_id: Some(conversation_id.to_string()),
+            account_id: Some("acc".to_string()),
+            subject: Some("Offsite".to_string()),
+            from_address: Some("alice@example.com".to_string()),
+            received_at: "2026-02-01T10:00:00Z".to_string(),
+            is_read: Some(true),
+            has_attachments: Some(false),
+            folder: Some("inbox".to_string()),
+            ..Email::fixture(id)
+        }
+    }
+
+    fn thread_ids(db: &Database, conversation_id: &str) -> Vec<String> {
+        let mut ids = db
+            .get_emails_by_conversation(conversation_id)
+            .expect("load thread")
+            .into_iter()
+            .map(|email| email.id)
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn merge_and_split_survive_resync() {
+        let path = std::env::temp_dir().join(format!("ess-threads-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        db.insert_account(&Account {
+            account_id: "acc".to_string(),
+            email_address: "me@example.com".to_string(),
+            display_name: None,
+            tenant_id: None,
+            account_type: AccountType::Professional,
+            enabled: true,
+            last_sync: None,
+            config: None,
+        })
+        .expect("insert account");
+        for email in [email("a1", "A"), email("a2", "A"), email("b1", "B")] {
+            db.insert_email(&email).expect("insert email");
+        }
+
+        assert_eq!(db.merge_threads("A", "B").expect("merge").moved, vec!["b1"]);
+        assert_eq!(thread_ids(&db, "A"), vec!["a1", "a2", "b1"]);
+
+        // The provider still reports the old thread on re-sync and for new
+        // replies; both follow the merge.
+        db.insert_email(&email("b1", "B")).expect("resync email");
+        db.insert_email(&email("b2", "B")).expect("insert reply");
+        assert_eq!(thread_ids(&db, "A"), vec!["a1", "a2", "b1", "b2"]);
+
+        let split = db.split_thread("a2").expect("split").expect("email exists");
+        db.insert_email(&email("a2", "A"))
+            .expect("resync split email");
+        assert_eq!(thread_ids(&db, &split), vec!["a2"]);
+        assert_eq!(thread_ids(&db, "A"), vec!["a1", "b1", "b2"]);
+        assert_eq!(db.split_thread("missing").expect("split missing"), None);
+
+        // Merging into a thread that was merged away follows it.
+        db.insert_email(&email("c1", "C")).expect("insert email");
+        let merge = db
+            .merge_threads("B", "C")
+            .expect("merge into merged thread");
+        assert_eq!(merge.thread_id, "A");
+        assert_eq!(thread_ids(&db, "A"), vec!["a1", "b1", "b2", "c1"]);
+
+        let mut reply = email("c2", "C");
+        reply.from_address = Some("Bob@Example.com".to_string());
+        reply.received_at = "2026-02-02T10:00:00Z".to_string();
+        db.insert_email(&reply).expect("insert reply");
+        let summary = db.thread_summary("A").expect("thread summary");
+        assert_eq!(summary.messages, 5);
+        assert_eq!(
+            summary.participants,
+            vec!["alice@example.com", "bob@example.com"]
+        );
+
+        let _ = std::fs::remove_file(path);
+    }
+}
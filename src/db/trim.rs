@@ -0,0 +1,192 @@
+//! Dropping the bodies of emails to keep the store under
+//! `[storage] max_size_mb`. Headers, the preview and the attachment list
+//! stay, so a trimmed email still lists, threads and searches by subject
+//! and sender.
+
+use rusqlite::params;
+use serde::Serialize;
+
+use crate::db::{Database, DbError};
+
+/// Metadata key holding when an email's body was trimmed.
+pub const BODY_TRIMMED_KEY: &str = "body_trimmed_at";
+
+/// Which bodies go first once the store is over its cap.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EvictionPolicy {
+    /// Low-importance and automated mail first, high-importance mail last;
+    /// oldest first within each.
+    #[default]
+    Priority,
+    /// Oldest first.
+    Oldest,
+}
+
+impl EvictionPolicy {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "priority" => Some(Self::Priority),
+            "oldest" => Some(Self::Oldest),
+            _ => None,
+        }
+    }
+}
+
+/// An email whose body may be dropped.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TrimCandidate {
+    pub id: String,
+    pub account_id: Option<String>,
+    pub subject: Option<String>,
+    pub received_at: String,
+    /// Text and HTML body, in bytes.
+    pub body_bytes: u64,
+}
+
+impl Database {
+    /// Emails with a body, in the order `policy` drops them. Starred and
+    /// flagged emails and those in a collection are never trimmed.
+    pub fn trim_candidates(&self, policy: EvictionPolicy) -> Result<Vec<TrimCandidate>, DbError> {
+        let order = match policy {
+            EvictionPolicy::Priority => {
+                r#"
+                CASE
+                    WHEN lower(COALESCE(e.importance, '')) = 'high' THEN 2
+                    WHEN lower(COALESCE(e.importance, '')) = 'low'
+                      OR EXISTS (SELECT 1 FROM reply_signals r
+                                 WHERE r.email_id = e.id AND r.automated = 1) THEN 0
+                    ELSE 1
+                END, e.received_at, e.id
+                "#
+            }
+            EvictionPolicy::Oldest => "e.received_at, e.id",
+        };
+        let mut stmt = self.conn.prepare(&format!(
+            r#"
+            SELECT e.id, e.account_id, e.subject, e.received_at,
+                   length(CAST(COALESCE(e.body_text, '') AS BLOB))
+                       + length(CAST(COALESCE(e.body_html, '') AS BLOB))
+            FROM emails e
+            WHERE (e.body_text IS NOT NULL OR e.body_html IS NOT NULL)
+              AND COALESCE(e.flag_status, '') != 'flagged'
+              AND NOT EXISTS (SELECT 1 FROM stars s WHERE s.email_id = e.id)
+              AND NOT EXISTS (SELECT 1 FROM collection_emails c WHERE c.email_id = e.id)
+            ORDER BY {order}
+            "#
+        ))?;
+        let candidates = stmt
+            .query_map([], |row| {
+                Ok(TrimCandidate {
+                    id: row.get(0)?,
+                    account_id: row.get(1)?,
+                    subject: row.get(2)?,
+                    received_at: row.get(3)?,
+                    body_bytes: row.get(4)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(candidates)
+    }
+
+    /// Drops the text and HTML bodies of `ids` in one transaction,
+    /// recording `trimmed_at` under [`BODY_TRIMMED_KEY`]. Returns the
+    /// number of emails changed.
+    pub fn trim_bodies(&self, ids: &[String], trimmed_at: &str) -> Result<usize, DbError> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut trimmed = 0;
+        for id in ids {
+            trimmed += tx.execute(
+                &format!(
+                    r#"
+                    UPDATE emails SET
+                        body_text = NULL,
+                        body_html = NULL,
+                        metadata = json_set(COALESCE(metadata, '{{}}'), '$.{BODY_TRIMMED_KEY}', ?2)
+                    WHERE id = ?1
+                    "#
+                ),
+                params![id, trimmed_at],
+            )?;
+        }
+        tx.commit()?;
+        Ok(trimmed)
+    }
+
+    /// Rewrites the database file so the space freed by trimming is given
+    /// back to the filesystem.
+    pub fn vacuum(&self) -> Result<(), DbError> {
+        self.conn.execute_batch("VACUUM")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EvictionPolicy, BODY_TRIMMED_KEY};
+    use crate::db::models::Email;
+    use crate::db::Database;
+
+    fn email(id: &str, received_at: &str, importance: Option<&str>) -> Email {
+        Email {
+            subject: Some(format!("Subject {id}")),
+            from_address: Some("alice@example.com".to_string()),
+            body_text: Some("Body text".to_string()),
+            body_preview: Some("Body".to_string()),
+            received_at: received_at.to_string(),
+            importance: importance.map(str::to_string),
+            ..Email::fixture(id)
+        }
+    }
+
+    #[test]
+    fn trimming_follows_the_policy_and_spares_kept_mail() {
+        let path = std::env::temp_dir().join(format!("ess-trim-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        for email in [
+            email("old-high", "2026-01-01T00:00:00Z", Some("high")),
+            email("old-normal", "2026-01-02T00:00:00Z", Some("normal")),
+            email("new-low", "2026-03-01T00:00:00Z", Some("low")),
+            email("starred", "2025-12-01T00:00:00Z", None),
+        ] {
+            db.insert_email(&email).expect("insert");
+        }
+        db.star_email("starred").expect("star");
+
+        let ids = |policy| -> Vec<String> {
+            db.trim_candidates(policy)
+                .expect("candidates")
+                .into_iter()
+                .map(|candidate| candidate.id)
+                .collect()
+        };
+        assert_eq!(
+            ids(EvictionPolicy::Priority),
+            ["new-low", "old-normal", "old-high"]
+        );
+        assert_eq!(
+            ids(EvictionPolicy::Oldest),
+            ["old-high", "old-normal", "new-low"]
+        );
+        assert_eq!(
+            db.trim_candidates(EvictionPolicy::Oldest)
+                .expect("candidates")[0]
+                .body_bytes,
+            9
+        );
+
+        db.trim_bodies(&["new-low".to_string()], "2026-04-01T00:00:00Z")
+            .expect("trim");
+        let trimmed = db.get_email("new-low").expect("get").expect("email");
+        assert_eq!(trimmed.body_text, None);
+        assert_eq!(trimmed.body_preview.as_deref(), Some("Body"));
+        assert_eq!(
+            trimmed.metadata.expect("metadata")[BODY_TRIMMED_KEY],
+            "2026-04-01T00:00:00Z"
+        );
+        assert_eq!(ids(EvictionPolicy::Priority), ["old-normal", "old-high"]);
+        db.vacuum().expect("vacuum");
+
+        let _ = std::fs::remove_file(path);
+    }
+}
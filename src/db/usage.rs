@@ -0,0 +1,126 @@
+//! API requests made for each account and the bytes they downloaded, per
+//! UTC day, so `ess stats --usage` shows how close an account runs to its
+//! Gmail or Graph quota.
+
+use chrono::NaiveDate;
+use rusqlite::params;
+use serde::Serialize;
+
+use crate::db::{Database, DbError};
+
+/// Requests and bytes counted by a connector since it was last asked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct UsageCounts {
+    pub requests: u64,
+    pub bytes: u64,
+}
+
+impl UsageCounts {
+    pub fn is_empty(&self) -> bool {
+        self.requests == 0 && self.bytes == 0
+    }
+}
+
+/// One account's usage on one day.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SyncUsage {
+    pub account_id: String,
+    /// `YYYY-MM-DD`, UTC.
+    pub day: String,
+    pub requests: u64,
+    pub bytes: u64,
+}
+
+impl Database {
+    /// Adds `counts` to what `account_id` used on `day`.
+    pub fn add_sync_usage(
+        &self,
+        account_id: &str,
+        day: NaiveDate,
+        counts: UsageCounts,
+    ) -> Result<(), DbError> {
+        self.conn.execute(
+            r#"
+            INSERT INTO sync_usage (account_id, day, requests, bytes)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT(account_id, day) DO UPDATE SET
+                requests = requests + excluded.requests,
+                bytes = bytes + excluded.bytes
+            "#,
+            params![
+                account_id,
+                day.format("%Y-%m-%d").to_string(),
+                counts.requests,
+                counts.bytes
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Usage on `since` and later, newest day first, then by account.
+    pub fn sync_usage_since(&self, since: NaiveDate) -> Result<Vec<SyncUsage>, DbError> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT account_id, day, requests, bytes FROM sync_usage
+            WHERE day >= ?
+            ORDER BY day DESC, account_id
+            "#,
+        )?;
+        let usage = stmt
+            .query_map([since.format("%Y-%m-%d").to_string()], |row| {
+                Ok(SyncUsage {
+                    account_id: row.get(0)?,
+                    day: row.get(1)?,
+                    requests: row.get(2)?,
+                    bytes: row.get(3)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(usage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::UsageCounts;
+    use crate::db::Database;
+
+    #[test]
+    fn usage_adds_up_per_account_and_day() {
+        let path = std::env::temp_dir().join(format!("ess-usage-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        let day = |d| NaiveDate::from_ymd_opt(2026, 3, d).expect("valid date");
+        let counts = |requests, bytes| UsageCounts { requests, bytes };
+
+        db.add_sync_usage("work", day(1), counts(3, 1_000))
+            .expect("add");
+        db.add_sync_usage("work", day(2), counts(2, 500))
+            .expect("add");
+        db.add_sync_usage("work", day(2), counts(1, 250))
+            .expect("add again");
+        db.add_sync_usage("home", day(2), counts(4, 0))
+            .expect("add home");
+
+        let usage = db.sync_usage_since(day(2)).expect("usage");
+        let rows: Vec<_> = usage
+            .iter()
+            .map(|row| {
+                (
+                    row.account_id.as_str(),
+                    row.day.as_str(),
+                    row.requests,
+                    row.bytes,
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            [("home", "2026-03-02", 4, 0), ("work", "2026-03-02", 3, 750)]
+        );
+        assert_eq!(db.sync_usage_since(day(1)).expect("usage").len(), 3);
+
+        let _ = std::fs::remove_file(path);
+    }
+}
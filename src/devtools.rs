@@ -0,0 +1,545 @@
+//! `ess devtools seed`: fills a store with synthetic mail, so UI work,
+//! benchmarks and screenshots don't need a real mailbox.
+//!
+//! Everything comes from a small seeded generator and a fixed anchor date,
+//! so the same options always produce the same accounts, threads, contacts,
+//! folders and attachments, down to the ids and timestamps. Addresses use
+//! the reserved `.example` domains. Accounts are stored disabled and hold no
+//! credentials, so they never reach a provider.
+
+use std::collections::BTreeSet;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::db::models::{Account, AccountType, Attachment, Email};
+use crate::db::Database;
+use crate::indexer::EmailIndex;
+
+/// Index documents committed per batch.
+const BATCH_SIZE: usize = 500;
+
+/// Contacts each account exchanges mail with.
+const CONTACTS_PER_ACCOUNT: usize = 40;
+
+/// The newest message is received shortly before this instant.
+const ANCHOR: &str = "2026-06-01T09:00:00Z";
+
+const FIRST_NAMES: &[&str] = &[
+    "Ada", "Bruno", "Chloe", "Dev", "Elena", "Farid", "Grace", "Hiro", "Ines", "Jonas", "Keiko",
+    "Liam", "Maya", "Noor", "Oscar", "Priya", "Quinn", "Rosa", "Sami", "Tess", "Umar", "Vera",
+    "Wen", "Yusuf", "Zoe",
+];
+
+const LAST_NAMES: &[&str] = &[
+    "Abbott", "Berg", "Costa", "Dubois", "Evans", "Fischer", "Garcia", "Hansen", "Ito", "Jensen",
+    "Kowalski", "Larsen", "Moreau", "Nakamura", "Okafor", "Patel", "Quist", "Rossi", "Silva",
+    "Tanaka", "Varga", "Weber",
+];
+
+const COMPANIES: &[&str] = &["northwind", "contoso", "fabrikam", "tailspin", "litware"];
+
+const PERSONAL_DOMAINS: &[&str] = &["mail.example", "inbox.example", "post.example"];
+
+const PROJECTS: &[&str] = &[
+    "Atlas", "Beacon", "Cobalt", "Delta", "Ember", "Falcon", "Granite", "Harbor",
+];
+
+const CITIES: &[&str] = &[
+    "Lisbon",
+    "Kyoto",
+    "Oslo",
+    "Montreal",
+    "Cape Town",
+    "Valencia",
+];
+
+const WEEKDAYS: &[&str] = &[
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+const PROFESSIONAL_FOLDERS: &[&str] = &["inbox", "inbox", "inbox", "archive", "projects"];
+
+const PERSONAL_FOLDERS: &[&str] = &["inbox", "inbox", "inbox", "archive", "receipts", "travel"];
+
+const PROFESSIONAL_CATEGORIES: &[&str] = &["Follow up", "Finance", "Hiring", "Customers"];
+
+const PERSONAL_CATEGORIES: &[&str] = &["Family", "Travel", "Orders"];
+
+const SENTENCES: &[&str] = &[
+    "Thanks for sending this over so quickly.",
+    "I went through the numbers and they mostly line up with last quarter.",
+    "Can we move the review to later in the week?",
+    "I've attached the latest version with the changes we discussed.",
+    "Let me know if anything looks off before I share it more widely.",
+    "The timeline still works for us, though the second milestone is tight.",
+    "Happy to jump on a call if that's easier.",
+    "I'll follow up once I hear back from the rest of the team.",
+    "Could you double-check the dates on the second page?",
+    "No rush on this, end of next week is fine.",
+    "Looping in a couple of people who should weigh in.",
+    "Sounds good to me, let's go with the first option.",
+];
+
+#[derive(Debug, Clone)]
+pub struct SeedOptions {
+    pub emails: usize,
+    pub accounts: usize,
+    /// Same seed, same store.
+    pub seed: u64,
+}
+
+impl Default for SeedOptions {
+    fn default() -> Self {
+        Self {
+            emails: 5000,
+            accounts: 3,
+            seed: 42,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SeedReport {
+    pub accounts: usize,
+    pub emails: usize,
+    pub threads: usize,
+    pub contacts: usize,
+    pub attachments: usize,
+    pub seed: u64,
+}
+
+/// Writes `options.emails` synthetic emails spread over `options.accounts`
+/// accounts, and indexes them.
+pub fn seed(db: &Database, index: &mut EmailIndex, options: &SeedOptions) -> Result<SeedReport> {
+    let mut rng = Rng::new(options.seed);
+    let accounts = (0..options.accounts.max(1))
+        .map(|position| SeedAccount::generate(&mut rng, position))
+        .collect::<Vec<_>>();
+    for account in &accounts {
+        db.insert_account(&account.account)
+            .with_context(|| format!("insert account {}", account.account.account_id))?;
+    }
+
+    let mut report = SeedReport {
+        accounts: accounts.len(),
+        seed: options.seed,
+        ..SeedReport::default()
+    };
+    let mut contacts = BTreeSet::new();
+    let anchor = DateTime::parse_from_rfc3339(ANCHOR)
+        .expect("anchor is RFC 3339")
+        .with_timezone(&Utc);
+    let mut messages = Vec::with_capacity(options.emails);
+    while messages.len() < options.emails {
+        let account = &accounts[report.threads % accounts.len()];
+        let length = (1 + rng.below(6)).min(options.emails - messages.len());
+        messages.extend(
+            generate_thread(&mut rng, account, report.threads, length, anchor)
+                .into_iter()
+                .map(|(email, attachments)| (account, email, attachments)),
+        );
+        report.threads += 1;
+    }
+
+    // One SQLite transaction per batch; attachments are written after it,
+    // as `replace_attachments` opens a transaction of its own.
+    for batch in messages.chunks(BATCH_SIZE) {
+        let tx = db.conn().unchecked_transaction()?;
+        for (account, email, _) in batch {
+            db.insert_email(email)
+                .with_context(|| format!("insert email {}", email.id))?;
+            index
+                .add_email_buffered(email, &account.account.account_type.to_string())
+                .with_context(|| format!("index email {}", email.id))?;
+            for address in email
+                .from_address
+                .iter()
+                .chain(&email.to_addresses)
+                .chain(&email.cc_addresses)
+            {
+                db.update_contact_stats(address)
+                    .with_context(|| format!("update contact stats for {address}"))?;
+                contacts.insert(address.clone());
+            }
+        }
+        tx.commit()?;
+        for (_, email, attachments) in batch.iter().filter(|(_, _, a)| !a.is_empty()) {
+            db.replace_attachments(&email.id, attachments)
+                .with_context(|| format!("insert attachments for {}", email.id))?;
+            report.attachments += attachments.len();
+        }
+        index.commit().context("commit index batch")?;
+        report.emails += batch.len();
+    }
+    report.contacts = contacts.len();
+    Ok(report)
+}
+
+struct SeedAccount {
+    account: Account,
+    owner: Person,
+    contacts: Vec<Person>,
+    /// Company name, for professional accounts.
+    company: Option<&'static str>,
+}
+
+#[derive(Debug, Clone)]
+struct Person {
+    name: String,
+    address: String,
+}
+
+impl SeedAccount {
+    /// Even positions are work accounts, odd ones personal.
+    fn generate(rng: &mut Rng, position: usize) -> Self {
+        let professional = position.is_multiple_of(2);
+        let company = professional.then(|| COMPANIES[position / 2 % COMPANIES.len()]);
+        let first = FIRST_NAMES[(position * 7 + 3) % FIRST_NAMES.len()];
+        let last = LAST_NAMES[(position * 5 + 1) % LAST_NAMES.len()];
+        let owner = match company {
+            Some(company) => person(first, last, &format!("{company}.example")),
+            None => Person {
+                name: format!("{first} {last}"),
+                address: format!(
+                    "{}{}@{}",
+                    first.to_ascii_lowercase(),
+                    position + 1,
+                    PERSONAL_DOMAINS[position % PERSONAL_DOMAINS.len()]
+                ),
+            },
+        };
+
+        let mut contacts = Vec::with_capacity(CONTACTS_PER_ACCOUNT);
+        while contacts.len() < CONTACTS_PER_ACCOUNT {
+            let first = rng.pick(FIRST_NAMES);
+            let last = rng.pick(LAST_NAMES);
+            let domain = match company {
+                // Mostly colleagues, with customers and vendors mixed in.
+                Some(company) if rng.chance(60) => format!("{company}.example"),
+                Some(_) => format!("{}.example", rng.pick(COMPANIES)),
+                None => rng.pick(PERSONAL_DOMAINS).to_string(),
+            };
+            let contact = person(first, last, &domain);
+            if contact.address != owner.address
+                && !contacts
+                    .iter()
+                    .any(|known: &Person| known.address == contact.address)
+            {
+                contacts.push(contact);
+            }
+        }
+
+        let account = Account {
+            account_id: format!("seed-{}", position + 1),
+            email_address: owner.address.clone(),
+            display_name: Some(owner.name.clone()),
+            tenant_id: None,
+            account_type: if professional {
+                AccountType::Professional
+            } else {
+                AccountType::Personal
+            },
+            enabled: false,
+            last_sync: None,
+            config: Some(json!({ "synthetic": true })),
+        };
+        Self {
+            account,
+            owner,
+            contacts,
+            company,
+        }
+    }
+}
+
+fn person(first: &str, last: &str, domain: &str) -> Person {
+    Person {
+        name: format!("{first} {last}"),
+        address: format!(
+            "{}.{}@{domain}",
+            first.to_ascii_lowercase(),
+            last.to_ascii_lowercase()
+        ),
+    }
+}
+
+/// One conversation of `length` messages between the account owner and one
+/// to three of their contacts, replies alternating between the two sides.
+fn generate_thread(
+    rng: &mut Rng,
+    account: &SeedAccount,
+    number: usize,
+    length: usize,
+    anchor: DateTime<Utc>,
+) -> Vec<(Email, Vec<Attachment>)> {
+    let professional = account.company.is_some();
+    let subject = thread_subject(rng, professional);
+    let mut participants = vec![rng.pick(&account.contacts).clone()];
+    for _ in 0..rng.below(3) {
+        let contact = rng.pick(&account.contacts);
+        if participants
+            .iter()
+            .all(|known| known.address != contact.address)
+        {
+            participants.push(contact.clone());
+        }
+    }
+    let (folders, categories) = if professional {
+        (PROFESSIONAL_FOLDERS, PROFESSIONAL_CATEGORIES)
+    } else {
+        (PERSONAL_FOLDERS, PERSONAL_CATEGORIES)
+    };
+    let folder = *rng.pick(folders);
+    let category = rng.chance(15).then(|| rng.pick(categories).to_string());
+    let conversation_id = format!("{}-thread-{number:05}", account.account.account_id);
+
+    // Threads start anywhere in the year before the anchor, early enough
+    // for replies, which follow within two days of each other, to end
+    // before it.
+    let mut received = anchor
+        - Duration::days(2 * length as i64)
+        - Duration::minutes(rng.below(365 * 24 * 60) as i64);
+    let mut messages = Vec::with_capacity(length);
+    for position in 0..length {
+        let from_owner = position % 2 == 1 || (position == 0 && rng.chance(20));
+        let sender = if from_owner {
+            &account.owner
+        } else {
+            &participants[position % participants.len()]
+        };
+        let recipients = std::iter::once(&account.owner)
+            .chain(&participants)
+            .filter(|person| person.address != sender.address)
+            .collect::<Vec<_>>();
+        let to_addresses = vec![recipients[0].address.clone()];
+        let cc_addresses = recipients[1..]
+            .iter()
+            .map(|person| person.address.clone())
+            .collect();
+
+        let id = format!("{conversation_id}-{position}");
+        let body_text = message_body(rng, sender, recipients[0]);
+        let attachments = if rng.chance(12) {
+            vec![attachment(rng, &id, professional)]
+        } else {
+            Vec::new()
+        };
+        let received_at = received.to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        let references: Vec<String> = (0..position)
+            .map(|earlier| format!("<{conversation_id}-{earlier}@seed.ess.example>"))
+            .collect();
+        let email = Email {
+            id: id.clone(),
+            internet_message_id: Some(format!("<{id}@seed.ess.example>")),
+            in_reply_to: references.last().cloned(),
+            references,
+            conversation_id: Some(conversation_id.clone()),
+            account_id: Some(account.account.account_id.clone()),
+            subject: Some(if position == 0 {
+                subject.clone()
+            } else {
+                format!("Re: {subject}")
+            }),
+            from_address: Some(sender.address.clone()),
+            from_name: Some(sender.name.clone()),
+            to_addresses,
+            cc_addresses,
+            bcc_addresses: Vec::new(),
+            body_preview: Some(body_text.chars().take(120).collect()),
+            body_html: None,
+            body_text: Some(body_text),
+            received_at: received_at.clone(),
+            sent_at: Some(received_at),
+            importance: Some(if rng.chance(5) { "high" } else { "normal" }.to_string()),
+            is_read: Some(from_owner || !rng.chance(20)),
+            has_attachments: Some(!attachments.is_empty()),
+            folder: Some(if from_owner { "sentitems" } else { folder }.to_string()),
+            categories: category.iter().cloned().collect(),
+            flag_status: Some(
+                if rng.chance(6) {
+                    "flagged"
+                } else {
+                    "notFlagged"
+                }
+                .to_string(),
+            ),
+            web_link: None,
+            metadata: Some(json!({ "synthetic": true })),
+        };
+        messages.push((email, attachments));
+        received += Duration::minutes(rng.below(48 * 60) as i64 + 5);
+    }
+    messages
+}
+
+fn thread_subject(rng: &mut Rng, professional: bool) -> String {
+    let project = rng.pick(PROJECTS);
+    let city = rng.pick(CITIES);
+    let day = rng.pick(WEEKDAYS);
+    let number = 1000 + rng.below(9000);
+    if professional {
+        match rng.below(6) {
+            0 => format!("Q{} budget review", 1 + rng.below(4)),
+            1 => format!("{project} launch plan"),
+            2 => format!("Weekly sync: {project}"),
+            3 => format!("Contract renewal #{number}"),
+            4 => format!("Offsite in {city}"),
+            _ => format!("{project} incident follow-up"),
+        }
+    } else {
+        match rng.below(5) {
+            0 => format!("Dinner on {day}?"),
+            1 => format!("Trip to {city}"),
+            2 => format!("Your order #{number} has shipped"),
+            3 => format!("Photos from {city}"),
+            _ => format!("Plans for {day}"),
+        }
+    }
+}
+
+fn message_body(rng: &mut Rng, sender: &Person, recipient: &Person) -> String {
+    let first_name = |person: &Person| person.name.split(' ').next().unwrap_or("").to_string();
+    // Stepping by 5, coprime to the 12 sentences, never repeats one.
+    let start = rng.below(SENTENCES.len());
+    let sentences = (0..2 + rng.below(3))
+        .map(|step| SENTENCES[(start + step * 5) % SENTENCES.len()])
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "Hi {},\n\n{sentences}\n\nThanks,\n{}",
+        first_name(recipient),
+        first_name(sender)
+    )
+}
+
+fn attachment(rng: &mut Rng, email_id: &str, professional: bool) -> Attachment {
+    let number = 100 + rng.below(900);
+    let (name, content_type) = match (professional, rng.below(3)) {
+        (true, 0) => (
+            format!("{}-plan.pdf", rng.pick(PROJECTS).to_ascii_lowercase()),
+            "application/pdf",
+        ),
+        (true, 1) => (
+            format!("budget-{number}.xlsx"),
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        ),
+        (true, _) => (
+            format!("notes-{number}.docx"),
+            "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        ),
+        (false, 0) => (format!("receipt-{number}.pdf"), "application/pdf"),
+        (false, _) => (format!("IMG_{number}.jpg"), "image/jpeg"),
+    };
+    Attachment {
+        id: format!("{email_id}-att"),
+        email_id: email_id.to_string(),
+        name: Some(name),
+        content_type: Some(content_type.to_string()),
+        size_bytes: Some((20 + rng.below(4000)) as i64 * 1024),
+        is_inline: Some(false),
+    }
+}
+
+/// SplitMix64: tiny, fast, and stable across platforms and releases, which
+/// is all the seed needs.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A value in `0..bound`.
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{seed, SeedOptions};
+    use crate::db::{Database, EmailSearchFilters};
+    use crate::indexer::EmailIndex;
+
+    fn seeded(options: &SeedOptions) -> (Database, super::SeedReport, std::path::PathBuf) {
+        let root = std::env::temp_dir().join(format!("ess-seed-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).expect("create temp root");
+        let db = Database::open(&root.join("ess.db")).expect("open db");
+        let mut index = EmailIndex::open(&root.join("index")).expect("open index");
+        let report = seed(&db, &mut index, options).expect("seed");
+        (db, report, root)
+    }
+
+    fn all_emails(db: &Database) -> Vec<crate::db::models::Email> {
+        db.search_emails(EmailSearchFilters {
+            query: None,
+            account_id: None,
+            account_type: None,
+            folder: None,
+            from_address: None,
+            from_domain: None,
+            since: None,
+            until: None,
+            tag: None,
+            needs_reply_before: None,
+            limit: 1000,
+            offset: 0,
+            after: None,
+        })
+        .expect("list emails")
+    }
+
+    #[test]
+    fn seeding_is_deterministic_and_fills_the_requested_counts() {
+        let options = SeedOptions {
+            emails: 120,
+            accounts: 3,
+            seed: 7,
+        };
+        let (first_db, first, first_root) = seeded(&options);
+        let (second_db, second, second_root) = seeded(&options);
+
+        assert_eq!(first, second);
+        assert_eq!((first.accounts, first.emails), (3, 120));
+        assert!(first.threads < first.emails);
+        assert_eq!(all_emails(&first_db), all_emails(&second_db));
+        let stats = first_db.get_stats().expect("stats");
+        assert_eq!(stats.total_emails, 120);
+        assert_eq!(stats.total_accounts, 3);
+        assert_eq!(stats.total_contacts as usize, first.contacts);
+        assert!(stats
+            .emails_by_folder
+            .iter()
+            .any(|folder| folder.folder.as_deref() == Some("sentitems")));
+
+        let (other_db, _, other_root) = seeded(&SeedOptions { seed: 8, ..options });
+        assert_ne!(all_emails(&first_db), all_emails(&other_db));
+        for root in [first_root, second_root, other_root] {
+            let _ = std::fs::remove_dir_all(root);
+        }
+    }
+}
@@ -0,0 +1,305 @@
+//! Vector embeddings for `ess search --semantic` and `ess_semantic_search`.
+//!
+//! Two models plug in behind [`Embedder`]: a built-in feature-hashing model
+//! that needs no network or download, and any OpenAI-compatible
+//! `/embeddings` endpoint (OpenAI itself, or a local Ollama, llama.cpp or
+//! vLLM server) selected with `ESS_EMBEDDINGS_URL`.
+
+use async_trait::async_trait;
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::db::models::Email;
+use crate::env;
+
+/// Model name stored with vectors from [`HashingEmbedder`].
+pub const HASHING_MODEL: &str = "ess-hashing-v1";
+const HASHING_DIMENSIONS: usize = 512;
+pub const DEFAULT_API_MODEL: &str = "text-embedding-3-small";
+/// Characters of an email sent for embedding; API models cap their input at
+/// a few thousand tokens.
+const MAX_INPUT_CHARS: usize = 8_000;
+
+const STOPWORDS: &[&str] = &[
+    "a", "about", "an", "and", "are", "as", "at", "be", "by", "email", "emails", "for", "from",
+    "has", "have", "in", "is", "it", "me", "my", "of", "on", "or", "re", "that", "the", "this",
+    "to", "was", "we", "were", "with", "you", "your",
+];
+
+#[derive(Debug, Error)]
+pub enum EmbeddingError {
+    #[error("embedding request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("embedding API returned {status}: {body}")]
+    Api { status: u16, body: String },
+
+    #[error("embedding API returned {got} vectors for {expected} inputs")]
+    Mismatch { expected: usize, got: usize },
+
+    #[error("{0}")]
+    Config(String),
+}
+
+#[async_trait(?Send)]
+pub trait Embedder {
+    /// Name stored with each vector; vectors of different models are never
+    /// compared.
+    fn model(&self) -> &str;
+
+    /// One unit-length vector per input, in input order.
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError>;
+}
+
+/// The API embedder when `ESS_EMBEDDINGS_URL` is set, otherwise the hashing
+/// model.
+pub fn from_env() -> Result<Box<dyn Embedder>, EmbeddingError> {
+    match env::var(env::EMBEDDINGS_URL) {
+        Some(base_url) => Ok(Box::new(ApiEmbedder::new(
+            &base_url,
+            &env::var(env::EMBEDDINGS_MODEL).unwrap_or_else(|| DEFAULT_API_MODEL.to_string()),
+            env::var(env::EMBEDDINGS_API_KEY),
+        )?)),
+        None => Ok(Box::new(HashingEmbedder)),
+    }
+}
+
+/// The text embedded for an email: subject, then body (or preview), capped
+/// at `MAX_INPUT_CHARS`.
+pub fn embedding_text(email: &Email) -> String {
+    let body = email
+        .body_text
+        .as_deref()
+        .or(email.body_preview.as_deref())
+        .unwrap_or_default();
+    let text = format!(
+        "{}\n\n{}",
+        email.subject.as_deref().unwrap_or_default(),
+        body
+    );
+    match text.char_indices().nth(MAX_INPUT_CHARS) {
+        Some((cut, _)) => text[..cut].to_string(),
+        None => text,
+    }
+}
+
+/// Cosine similarity of two unit-length vectors; 0 when the dimensions
+/// differ.
+pub fn similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn normalize(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|value| value * value).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        for value in &mut vector {
+            *value /= norm;
+        }
+    }
+    vector
+}
+
+/// Hashes words and adjacent word pairs into a fixed-size vector. It
+/// captures shared vocabulary rather than meaning, but runs offline and is
+/// enough for hybrid ranking to pull in messages that share most of a
+/// question's words without matching all of them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HashingEmbedder;
+
+impl HashingEmbedder {
+    pub fn embed_text(text: &str) -> Vec<f32> {
+        let lowered = text.to_lowercase();
+        let words = lowered
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| word.chars().count() > 1 && !STOPWORDS.contains(word))
+            .map(stem)
+            .collect::<Vec<_>>();
+
+        let mut vector = vec![0.0; HASHING_DIMENSIONS];
+        let mut add = |feature: &str, weight: f32| {
+            let hash = fnv1a(feature);
+            let bucket = (hash % HASHING_DIMENSIONS as u64) as usize;
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            vector[bucket] += sign * weight;
+        };
+        for word in &words {
+            add(word, 1.0);
+        }
+        for pair in words.windows(2) {
+            add(&format!("{} {}", pair[0], pair[1]), 0.5);
+        }
+        normalize(vector)
+    }
+}
+
+#[async_trait(?Send)]
+impl Embedder for HashingEmbedder {
+    fn model(&self) -> &str {
+        HASHING_MODEL
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        Ok(texts.iter().map(|text| Self::embed_text(text)).collect())
+    }
+}
+
+/// Folds plural and common verb endings so `overruns` meets `overrun`.
+fn stem(word: &str) -> &str {
+    for suffix in ["ing", "ed", "es", "s"] {
+        if let Some(stripped) = word.strip_suffix(suffix) {
+            if stripped.chars().count() >= 3 && !stripped.ends_with('s') {
+                return stripped;
+            }
+        }
+    }
+    word
+}
+
+fn fnv1a(value: &str) -> u64 {
+    value.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Client for an OpenAI-compatible `POST {base}/embeddings` endpoint.
+#[derive(Debug, Clone)]
+pub struct ApiEmbedder {
+    client: Client,
+    endpoint: Url,
+    model: String,
+    api_key: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingDatum>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingDatum {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+impl ApiEmbedder {
+    pub fn new(
+        base_url: &str,
+        model: &str,
+        api_key: Option<String>,
+    ) -> Result<Self, EmbeddingError> {
+        let endpoint = Url::parse(&format!("{}/embeddings", base_url.trim_end_matches('/')))
+            .map_err(|error| {
+                EmbeddingError::Config(format!("invalid embeddings URL '{base_url}': {error}"))
+            })?;
+        Ok(Self {
+            client: Client::new(),
+            endpoint,
+            model: model.to_string(),
+            api_key,
+        })
+    }
+}
+
+#[async_trait(?Send)]
+impl Embedder for ApiEmbedder {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut request = self
+            .client
+            .post(self.endpoint.clone())
+            .json(&serde_json::json!({
+                "model": self.model,
+                "input": texts,
+            }));
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(EmbeddingError::Api {
+                status: status.as_u16(),
+                body: body.chars().take(500).collect(),
+            });
+        }
+        vectors_in_order(response.json().await?, texts.len())
+    }
+}
+
+/// Vectors from an API response, reordered by `index` and normalized.
+fn vectors_in_order(
+    response: EmbeddingResponse,
+    expected: usize,
+) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+    let mut data = response.data;
+    if data.len() != expected {
+        return Err(EmbeddingError::Mismatch {
+            expected,
+            got: data.len(),
+        });
+    }
+    data.sort_by_key(|datum| datum.index);
+    Ok(data
+        .into_iter()
+        .map(|datum| normalize(datum.embedding))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{similarity, vectors_in_order, EmbeddingError, EmbeddingResponse, HashingEmbedder};
+
+    #[test]
+    fn hashing_vectors_rank_shared_vocabulary_higher() {
+        let query = HashingEmbedder::embed_text("emails about budget overruns");
+        let close = HashingEmbedder::embed_text(
+            "Q3 budget: marketing overrun\n\nWe are 12% over budget on the campaign.",
+        );
+        let far = HashingEmbedder::embed_text("Team offsite\n\nBring walking shoes.");
+
+        assert!((similarity(&close, &close) - 1.0).abs() < 1e-5);
+        assert!(similarity(&query, &close) > 0.3);
+        assert!(similarity(&query, &close) > similarity(&query, &far));
+        assert_eq!(similarity(&query, &[1.0]), 0.0);
+    }
+
+    #[test]
+    fn api_vectors_follow_input_order() {
+        let response: EmbeddingResponse = serde_json::from_value(serde_json::json!({
+            "object": "list",
+            "data": [
+                {"object": "embedding", "index": 1, "embedding": [0.0, 2.0]},
+                {"object": "embedding", "index": 0, "embedding": [3.0, 4.0]}
+            ],
+            "model": "text-embedding-3-small"
+        }))
+        .expect("parse response");
+
+        assert_eq!(
+            vectors_in_order(response, 2).expect("vectors"),
+            vec![vec![0.6, 0.8], vec![0.0, 1.0]]
+        );
+
+        let short: EmbeddingResponse =
+            serde_json::from_value(serde_json::json!({"data": []})).expect("parse response");
+        assert!(matches!(
+            vectors_in_order(short, 1),
+            Err(EmbeddingError::Mismatch {
+                expected: 1,
+                got: 0
+            })
+        ));
+    }
+}
@@ -0,0 +1,473 @@
+//! Central registry of the environment variables ESS reads.
+//!
+//! Every lookup goes through [`var`] with one of the constants below so that
+//! `ess env` and `ess config check` can describe exactly what the binary
+//! consults.
+
+use serde::Serialize;
+
+use crate::config::check::mask_secret;
+use crate::connectors::conditions::parse_flag;
+
+pub const TENANT_ID: &str = "ESS_TENANT_ID";
+pub const CLIENT_ID: &str = "ESS_CLIENT_ID";
+pub const CLIENT_SECRET: &str = "ESS_CLIENT_SECRET";
+pub const GRAPH_API_BASE: &str = "ESS_GRAPH_API_BASE";
+pub const GRAPH_TOKEN_URL: &str = "ESS_GRAPH_TOKEN_URL";
+pub const GMAIL_CLIENT_ID: &str = "ESS_GMAIL_CLIENT_ID";
+pub const GMAIL_CLIENT_SECRET: &str = "ESS_GMAIL_CLIENT_SECRET";
+pub const GMAIL_REFRESH_TOKEN: &str = "ESS_GMAIL_REFRESH_TOKEN";
+pub const GMAIL_TOKEN_URL: &str = "ESS_GMAIL_TOKEN_URL";
+pub const GMAIL_API_BASE: &str = "ESS_GMAIL_API_BASE";
+pub const GMAIL_BATCH_URL: &str = "ESS_GMAIL_BATCH_URL";
+pub const TOKEN_CACHE_KEY: &str = "ESS_TOKEN_CACHE_KEY";
+pub const TOKEN_CACHE_KEY_PREVIOUS: &str = "ESS_TOKEN_CACHE_KEY_PREVIOUS";
+pub const TOKEN_SKEW_SECONDS: &str = "ESS_TOKEN_SKEW_SECONDS";
+pub const TOKEN_REFRESH_WINDOW_SECONDS: &str = "ESS_TOKEN_REFRESH_WINDOW_SECONDS";
+pub const EMBEDDINGS_URL: &str = "ESS_EMBEDDINGS_URL";
+pub const EMBEDDINGS_MODEL: &str = "ESS_EMBEDDINGS_MODEL";
+pub const EMBEDDINGS_API_KEY: &str = "ESS_EMBEDDINGS_API_KEY";
+pub const SUMMARY_URL: &str = "ESS_SUMMARY_URL";
+pub const SUMMARY_BACKEND: &str = "ESS_SUMMARY_BACKEND";
+pub const SUMMARY_MODEL: &str = "ESS_SUMMARY_MODEL";
+pub const SUMMARY_API_KEY: &str = "ESS_SUMMARY_API_KEY";
+pub const MCP_TOKEN: &str = "ESS_MCP_TOKEN";
+pub const DB_KEY: &str = "ESS_DB_KEY";
+pub const KEYCHAIN: &str = "ESS_KEYCHAIN";
+pub const REMOTE_TOKEN: &str = "ESS_REMOTE_TOKEN";
+pub const GRPC_TOKEN: &str = "ESS_GRPC_TOKEN";
+pub const ON_BATTERY: &str = "ESS_ON_BATTERY";
+pub const METERED: &str = "ESS_METERED";
+pub const RUST_LOG: &str = "RUST_LOG";
+pub const COLUMNS: &str = "COLUMNS";
+pub const NO_COLOR: &str = "NO_COLOR";
+pub const DBUS_SESSION_BUS_ADDRESS: &str = "DBUS_SESSION_BUS_ADDRESS";
+pub const XDG_RUNTIME_DIR: &str = "XDG_RUNTIME_DIR";
+
+/// Proxy variables honoured by the HTTP client; their presence also disables
+/// the pre-sync connectivity probe.
+pub const PROXY_VARS: &[&str] = &[
+    "HTTPS_PROXY",
+    "https_proxy",
+    "ALL_PROXY",
+    "all_proxy",
+    "HTTP_PROXY",
+    "http_proxy",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnvValueKind {
+    Text,
+    Url,
+    /// 64 hex characters encoding a 256-bit key.
+    HexKey,
+    /// A non-negative whole number of seconds.
+    Seconds,
+    /// `1`/`true`/`yes`/`on` or `0`/`false`/`no`/`off`.
+    Flag,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct EnvVar {
+    pub name: &'static str,
+    pub category: &'static str,
+    pub secret: bool,
+    pub kind: EnvValueKind,
+    pub description: &'static str,
+    /// Alternative spellings read with the same meaning.
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub aliases: &'static [&'static str],
+}
+
+/// A registry entry together with its current value, as shown by `ess env`.
+#[derive(Debug, Clone, Serialize)]
+pub struct EnvStatus {
+    #[serde(flatten)]
+    pub var: EnvVar,
+    pub set: bool,
+    /// Current value; secrets are masked.
+    pub value: Option<String>,
+}
+
+const fn entry(
+    name: &'static str,
+    category: &'static str,
+    secret: bool,
+    kind: EnvValueKind,
+    description: &'static str,
+) -> EnvVar {
+    EnvVar {
+        name,
+        category,
+        secret,
+        kind,
+        description,
+        aliases: &[],
+    }
+}
+
+pub const REGISTRY: &[EnvVar] = &[
+    entry(
+        TENANT_ID,
+        "graph",
+        false,
+        EnvValueKind::Text,
+        "Azure AD tenant for Graph sync; overrides account tenant_id",
+    ),
+    entry(
+        CLIENT_ID,
+        "graph",
+        false,
+        EnvValueKind::Text,
+        "Azure app registration client ID for Graph sync",
+    ),
+    entry(
+        CLIENT_SECRET,
+        "graph",
+        true,
+        EnvValueKind::Text,
+        "Azure app registration client secret for Graph sync",
+    ),
+    entry(
+        GRAPH_API_BASE,
+        "graph",
+        false,
+        EnvValueKind::Url,
+        "Override the Microsoft Graph API base URL (testing/sovereign clouds)",
+    ),
+    entry(
+        GRAPH_TOKEN_URL,
+        "graph",
+        false,
+        EnvValueKind::Url,
+        "Override the Azure AD OAuth token endpoint",
+    ),
+    entry(
+        GMAIL_CLIENT_ID,
+        "gmail",
+        false,
+        EnvValueKind::Text,
+        "Google OAuth client ID for Gmail sync",
+    ),
+    entry(
+        GMAIL_CLIENT_SECRET,
+        "gmail",
+        true,
+        EnvValueKind::Text,
+        "Google OAuth client secret for Gmail sync",
+    ),
+    entry(
+        GMAIL_REFRESH_TOKEN,
+        "gmail",
+        true,
+        EnvValueKind::Text,
+        "Google OAuth refresh token for Gmail sync",
+    ),
+    entry(
+        GMAIL_TOKEN_URL,
+        "gmail",
+        false,
+        EnvValueKind::Url,
+        "Override the Google OAuth token endpoint",
+    ),
+    entry(
+        GMAIL_API_BASE,
+        "gmail",
+        false,
+        EnvValueKind::Url,
+        "Override the Gmail API base URL (testing)",
+    ),
+    entry(
+        GMAIL_BATCH_URL,
+        "gmail",
+        false,
+        EnvValueKind::Url,
+        "Override the Gmail batch endpoint (testing)",
+    ),
+    entry(
+        TOKEN_CACHE_KEY,
+        "security",
+        true,
+        EnvValueKind::HexKey,
+        "AES-256 key (64 hex chars) for encrypting cached access tokens; unset disables caching",
+    ),
+    entry(
+        TOKEN_CACHE_KEY_PREVIOUS,
+        "security",
+        true,
+        EnvValueKind::HexKey,
+        "Previous token cache key, still accepted for decryption during a key rotation",
+    ),
+    entry(
+        TOKEN_SKEW_SECONDS,
+        "security",
+        false,
+        EnvValueKind::Seconds,
+        "Clock skew tolerated when deciding a cached access token has expired (default: 60)",
+    ),
+    entry(
+        TOKEN_REFRESH_WINDOW_SECONDS,
+        "security",
+        false,
+        EnvValueKind::Seconds,
+        "Refresh cached access tokens expiring within this many seconds (default: 300)",
+    ),
+    entry(
+        EMBEDDINGS_URL,
+        "embeddings",
+        false,
+        EnvValueKind::Url,
+        "OpenAI-compatible API base (e.g. http://localhost:11434/v1) for semantic search; unset uses the built-in hashing model",
+    ),
+    entry(
+        EMBEDDINGS_MODEL,
+        "embeddings",
+        false,
+        EnvValueKind::Text,
+        "Embedding model requested from ESS_EMBEDDINGS_URL (default: text-embedding-3-small)",
+    ),
+    entry(
+        EMBEDDINGS_API_KEY,
+        "embeddings",
+        true,
+        EnvValueKind::Text,
+        "Bearer token for ESS_EMBEDDINGS_URL, if the API needs one",
+    ),
+    entry(
+        SUMMARY_URL,
+        "summaries",
+        false,
+        EnvValueKind::Url,
+        "LLM API base for `ess summarize`: OpenAI-compatible (e.g. https://api.openai.com/v1) or an Ollama server (http://localhost:11434)",
+    ),
+    entry(
+        SUMMARY_BACKEND,
+        "summaries",
+        false,
+        EnvValueKind::Text,
+        "API spoken by ESS_SUMMARY_URL: `openai` (/chat/completions, the default) or `ollama` (/api/chat)",
+    ),
+    entry(
+        SUMMARY_MODEL,
+        "summaries",
+        false,
+        EnvValueKind::Text,
+        "Model asked for summaries (default: gpt-4o-mini, or llama3.2 with the ollama backend)",
+    ),
+    entry(
+        SUMMARY_API_KEY,
+        "summaries",
+        true,
+        EnvValueKind::Text,
+        "Bearer token for ESS_SUMMARY_URL, if the API needs one",
+    ),
+    entry(
+        MCP_TOKEN,
+        "security",
+        true,
+        EnvValueKind::Text,
+        "Bearer token clients of `ess mcp --http` must send; required to bind beyond loopback",
+    ),
+    entry(
+        DB_KEY,
+        "security",
+        true,
+        EnvValueKind::Text,
+        "SQLCipher key of an encrypted database; falls back to the OS keychain (sqlcipher builds)",
+    ),
+    entry(
+        KEYCHAIN,
+        "security",
+        false,
+        EnvValueKind::Flag,
+        "Look credentials up in the OS keychain (1, the default) or skip it (0)",
+    ),
+    entry(
+        REMOTE_TOKEN,
+        "security",
+        true,
+        EnvValueKind::Text,
+        "Bearer token of `ess sync --watch --listen` and of clients using [remote] url",
+    ),
+    entry(
+        GRPC_TOKEN,
+        "security",
+        true,
+        EnvValueKind::Text,
+        "Bearer token clients of `ess grpc` must send; required to bind beyond loopback",
+    ),
+    entry(
+        ON_BATTERY,
+        "sync",
+        false,
+        EnvValueKind::Flag,
+        "Treat the machine as on battery (1) or not (0) instead of detecting it",
+    ),
+    entry(
+        METERED,
+        "sync",
+        false,
+        EnvValueKind::Flag,
+        "Treat the connection as metered (1) or not (0) instead of asking NetworkManager",
+    ),
+    entry(
+        RUST_LOG,
+        "logging",
+        false,
+        EnvValueKind::Text,
+        "Log filter for stderr diagnostics (default: warn)",
+    ),
+    entry(
+        COLUMNS,
+        "output",
+        false,
+        EnvValueKind::Text,
+        "Terminal width that tables are fitted to, instead of the detected one",
+    ),
+    entry(
+        NO_COLOR,
+        "output",
+        false,
+        EnvValueKind::Text,
+        "Any value turns colors off in table output",
+    ),
+    entry(
+        DBUS_SESSION_BUS_ADDRESS,
+        "desktop",
+        false,
+        EnvValueKind::Text,
+        "Session bus the GNOME search provider connects to",
+    ),
+    entry(
+        XDG_RUNTIME_DIR,
+        "desktop",
+        false,
+        EnvValueKind::Text,
+        "Directory holding the session bus socket when DBUS_SESSION_BUS_ADDRESS is unset",
+    ),
+    EnvVar {
+        name: "HTTPS_PROXY",
+        category: "network",
+        secret: false,
+        kind: EnvValueKind::Url,
+        description: "Proxy for outbound requests; when set the offline probe is skipped",
+        aliases: PROXY_VARS,
+    },
+];
+
+/// Reads a registered variable, treating empty or whitespace-only values as
+/// unset.
+pub fn var(name: &str) -> Option<String> {
+    debug_assert!(
+        lookup(name).is_some(),
+        "environment variable {name} is not in the ESS registry"
+    );
+    std::env::var(name)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Finds the registry entry for `name`, including alias spellings.
+pub fn lookup(name: &str) -> Option<&'static EnvVar> {
+    REGISTRY
+        .iter()
+        .find(|var| var.name == name || var.aliases.contains(&name))
+}
+
+/// Current state of every registered variable, in registry order.
+pub fn snapshot() -> Vec<EnvStatus> {
+    REGISTRY
+        .iter()
+        .map(|var| {
+            let value = std::iter::once(var.name)
+                .chain(var.aliases.iter().copied())
+                .find_map(self::var)
+                .map(|value| {
+                    if var.secret {
+                        mask_secret(&value)
+                    } else {
+                        value
+                    }
+                });
+            EnvStatus {
+                var: *var,
+                set: value.is_some(),
+                value,
+            }
+        })
+        .collect()
+}
+
+/// Checks a value against the variable's expected shape.
+pub fn validate(var: &EnvVar, value: &str) -> Result<(), String> {
+    match var.kind {
+        EnvValueKind::Text => Ok(()),
+        EnvValueKind::Url => reqwest::Url::parse(value)
+            .map(|_| ())
+            .map_err(|error| format!("not a valid URL: {error}")),
+        EnvValueKind::HexKey => {
+            if value.len() == 64 && value.chars().all(|c| c.is_ascii_hexdigit()) {
+                Ok(())
+            } else {
+                Err("must be 64 hex characters (32 bytes)".to_string())
+            }
+        }
+        EnvValueKind::Seconds => value
+            .parse::<u64>()
+            .map(|_| ())
+            .map_err(|_| "must be a whole number of seconds".to_string()),
+        EnvValueKind::Flag => parse_flag(value)
+            .map(|_| ())
+            .ok_or_else(|| "must be 1, true, yes or on, or 0, false, no or off".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connectors::TOKEN_ENV_LOCK;
+
+    use super::{lookup, snapshot, validate, REGISTRY, TOKEN_CACHE_KEY};
+
+    #[test]
+    fn registry_names_are_unique() {
+        let mut names: Vec<&str> = REGISTRY.iter().map(|var| var.name).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), REGISTRY.len());
+        assert_eq!(
+            lookup("https_proxy").map(|var| var.name),
+            Some("HTTPS_PROXY")
+        );
+    }
+
+    #[test]
+    fn snapshot_masks_secrets() {
+        let _lock = TOKEN_ENV_LOCK.lock().expect("lock env mutation");
+        std::env::set_var(TOKEN_CACHE_KEY, "ab".repeat(32));
+        let status = snapshot()
+            .into_iter()
+            .find(|status| status.var.name == TOKEN_CACHE_KEY)
+            .expect("token cache key in snapshot");
+        std::env::remove_var(TOKEN_CACHE_KEY);
+
+        assert!(status.set);
+        assert_eq!(status.value.as_deref(), Some("********abab"));
+    }
+
+    #[test]
+    fn validates_value_shapes() {
+        let key = lookup(TOKEN_CACHE_KEY).expect("token cache key registered");
+        assert!(validate(key, &"ab".repeat(32)).is_ok());
+        assert!(validate(key, "abc").is_err());
+
+        let skew = lookup("ESS_TOKEN_SKEW_SECONDS").expect("token skew registered");
+        assert!(validate(skew, "120").is_ok());
+        assert!(validate(skew, "-5").is_err());
+
+        let base = lookup("ESS_GRAPH_API_BASE").expect("graph base registered");
+        assert!(validate(base, "https://graph.microsoft.com/v1.0").is_ok());
+        assert!(validate(base, "graph.microsoft.com").is_err());
+    }
+}
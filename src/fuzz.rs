@@ -0,0 +1,332 @@
+//! Entry points for the parsers that read untrusted input: Gmail batch
+//! responses, address headers, JSON archive payloads and search queries.
+//!
+//! Each takes arbitrary bytes, runs the parser and asserts the invariants
+//! its callers rely on, panicking when one breaks. The cargo-fuzz targets in
+//! `fuzz/` call them with libFuzzer's inputs through the `fuzzing` feature;
+//! the property tests below call them with generated inputs shaped like the
+//! real thing, so `cargo test` covers the same ground without a nightly
+//! toolchain.
+
+use serde_json::Value;
+
+use crate::connectors::{gmail_api, json_archive, SyncReport};
+use crate::db::models::{Account, AccountType, Email};
+use crate::search::query::{parse_query, parse_query_or_words};
+
+/// The message ids the batch was sent for, matched to its parts by position.
+const BATCH_IDS: [&str; 3] = ["m1", "m2", "m3"];
+
+/// A Gmail batch response: the `Content-Type` header on the first line and
+/// the multipart body after it.
+pub fn gmail_batch_response(data: &[u8]) {
+    let input = String::from_utf8_lossy(data);
+    let (content_type, body) = input.split_once('\n').unwrap_or((&input, ""));
+    let sent_ids = BATCH_IDS.map(str::to_string);
+    let mut report = SyncReport::default();
+    let result = gmail_api::parse_batch_response(body, content_type, &sent_ids, &mut report);
+
+    for (position, id) in result.retryable_ids.iter().enumerate() {
+        assert!(sent_ids.contains(id), "retryable id {id} was never sent");
+        assert!(
+            !result.retryable_ids[..position].contains(id),
+            "retryable id {id} listed twice"
+        );
+    }
+    let account = account();
+    for message in &result.messages {
+        if let Ok(email) = gmail_api::map_gmail_message_to_email(message, &account) {
+            assert_eq!(email.id, message.id);
+            assert_addresses(&email);
+        }
+    }
+}
+
+/// A `From`, `To` or `Cc` header value.
+pub fn address_header(data: &[u8]) {
+    let value = String::from_utf8_lossy(data);
+
+    let (name, address) = gmail_api::parse_from_header(Some(&value));
+    for part in [&name, &address].into_iter().flatten() {
+        assert!(!part.is_empty(), "empty from header part in {value:?}");
+    }
+    for address in gmail_api::parse_address_list(Some(&value)) {
+        assert!(!address.is_empty(), "empty address in {value:?}");
+        assert_eq!(address, address.trim(), "untrimmed address in {value:?}");
+    }
+    for address in json_archive::parse_addresses_from_header(&value) {
+        assert!(address.contains('@'), "{address:?} from {value:?}");
+        assert_eq!(address, address.to_ascii_lowercase());
+    }
+}
+
+/// One JSON archive file.
+pub fn archive_payload(data: &[u8]) {
+    let Ok(payload) = serde_json::from_slice::<Value>(data) else {
+        return;
+    };
+    let Ok(email) = json_archive::map_archive_record(&payload, "acc") else {
+        return;
+    };
+    assert!(!email.id.is_empty(), "mapped an email without an id");
+    assert_eq!(email.account_id.as_deref(), Some("acc"));
+    assert_addresses(&email);
+
+    let remapped = json_archive::remap_archive_payload(&email, &payload)
+        .expect("a payload that mapped once maps again");
+    assert_eq!(remapped.id, email.id);
+    assert_eq!(remapped.subject, email.subject);
+    assert_eq!(remapped.to_addresses, email.to_addresses);
+}
+
+/// An `ess search` query.
+pub fn search_query(data: &[u8]) {
+    let input = String::from_utf8_lossy(data);
+    parse_query_or_words(&input);
+    let Ok(Some(node)) = parse_query(&input) else {
+        return;
+    };
+    let predicate = node.to_sql();
+    assert_eq!(
+        predicate.placeholders(),
+        predicate.params.len(),
+        "{input:?} built `{}`",
+        predicate.clause
+    );
+    node.matches(&empty_email());
+    node.positive_terms();
+    node.needs_post_filter();
+}
+
+fn assert_addresses(email: &Email) {
+    for address in email
+        .to_addresses
+        .iter()
+        .chain(&email.cc_addresses)
+        .chain(&email.bcc_addresses)
+    {
+        assert!(
+            !address.trim().is_empty(),
+            "empty recipient on {}",
+            email.id
+        );
+    }
+}
+
+fn account() -> Account {
+    Account {
+        account_id: "acc".to_string(),
+        email_address: "me@example.com".to_string(),
+        display_name: None,
+        tenant_id: None,
+        account_type: AccountType::Personal,
+        enabled: true,
+        last_sync: None,
+        config: None,
+    }
+}
+
+fn empty_email() -> Email {
+    Email {
+        id: "e1".to_string(),
+        received_at: "2026-01-01T00:00:00Z".to_string(),
+        ..Email::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use serde_json::{json, Map, Value};
+
+    use super::{address_header, archive_payload, gmail_batch_response, search_query};
+
+    /// Display names and addresses with the punctuation headers get wrong:
+    /// quotes, commas inside quotes, stray and reversed angle brackets.
+    fn header() -> impl Strategy<Value = String> {
+        let entry = prop_oneof![
+            "[a-z.]{1,8}@[a-z]{1,8}\\.com",
+            "\"?[A-Za-z ,]{0,10}\"? ?<[a-z.@]{0,12}>",
+            "[A-Za-z<>\"@ ,.;]{0,16}",
+        ];
+        prop::collection::vec(entry, 0..4).prop_map(|entries| entries.join(", "))
+    }
+
+    fn gmail_message() -> impl Strategy<Value = String> {
+        (header(), header(), "[A-Za-z0-9+/=_-]{0,24}").prop_map(|(from, to, data)| {
+            json!({
+                "id": "m1",
+                "threadId": "t1",
+                "labelIds": ["INBOX", "UNREAD"],
+                "internalDate": "1767261600000",
+                "payload": {
+                    "mimeType": "text/plain",
+                    "headers": [
+                        {"name": "From", "value": from},
+                        {"name": "To", "value": to},
+                        {"name": "Date", "value": "Thu, 1 Jan 2026 10:00:00 +0000"}
+                    ],
+                    "body": {"data": data}
+                }
+            })
+            .to_string()
+        })
+    }
+
+    /// Sub-responses with real and odd statuses around bodies that decode,
+    /// do not decode, or are cut off.
+    fn batch_response() -> impl Strategy<Value = String> {
+        let body = prop_oneof![
+            gmail_message(),
+            gmail_message().prop_map(|message| message[..message.len() / 2].to_string()),
+            Just("{\"id\": 7}".to_string()),
+            "[{}\"a-z: ]{0,24}",
+        ];
+        let part = (
+            prop::sample::select(vec!["200 OK", "404 Not Found", "429 Too Many", "5xx", ""]),
+            body,
+        )
+            .prop_map(|(status, body)| {
+                format!("Content-Type: application/http\r\n\r\nHTTP/1.1 {status}\r\n\r\n{body}\r\n")
+            });
+        (
+            prop::sample::select(vec!["boundary=b", "boundary=\"b\"", ""]),
+            prop::collection::vec(part, 0..5),
+            any::<bool>(),
+        )
+            .prop_map(|(boundary, parts, closed)| {
+                let mut body = parts
+                    .iter()
+                    .map(|part| format!("--b\r\n{part}"))
+                    .collect::<String>();
+                if closed {
+                    body.push_str("--b--");
+                }
+                format!("multipart/mixed; {boundary}\n{body}")
+            })
+    }
+
+    /// Archive-shaped JSON: the fields the mapper reads, nested under
+    /// `email` or not, holding values of any type.
+    fn archive_json() -> impl Strategy<Value = String> {
+        let leaf = prop_oneof![
+            Just(Value::Null),
+            any::<bool>().prop_map(Value::from),
+            any::<i64>().prop_map(Value::from),
+            "[a-z@<>\", .:0-9TZ-]{0,20}".prop_map(Value::from),
+        ];
+        let value = leaf.prop_recursive(3, 24, 4, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..4).prop_map(Value::from),
+                prop::collection::btree_map(key(), inner, 0..4)
+                    .prop_map(|fields| Value::Object(fields.into_iter().collect())),
+            ]
+        });
+        (
+            prop::collection::btree_map(key(), value, 0..8),
+            any::<bool>(),
+        )
+            .prop_map(|(fields, nested)| {
+                let fields = fields.into_iter().collect::<Map<_, _>>();
+                if nested {
+                    json!({"email": fields, "graph_id": "g1"}).to_string()
+                } else {
+                    Value::Object(fields).to_string()
+                }
+            })
+    }
+
+    fn key() -> impl Strategy<Value = String> {
+        prop::sample::select(vec![
+            "id",
+            "graph_id",
+            "subject",
+            "from",
+            "toRecipients",
+            "ccRecipients",
+            "emailAddress",
+            "address",
+            "name",
+            "receivedDateTime",
+            "sentDateTime",
+            "body",
+            "content",
+            "contentType",
+            "isRead",
+            "categories",
+            "attachments",
+            "headers",
+        ])
+        .prop_map(str::to_string)
+    }
+
+    fn query() -> impl Strategy<Value = String> {
+        let token = prop_oneof![
+            "[a-z]{1,6}",
+            "\"[a-z ]{0,8}\"?",
+            "(from|to|subject|note|has|is|before|after|meta|account):[a-z0-9=.@-]{0,10}",
+            prop::sample::select(vec!["AND", "OR", "NOT", "-", "(", ")", "?", "'"])
+                .prop_map(str::to_string),
+        ];
+        prop::collection::vec(token, 0..8).prop_map(|tokens| tokens.join(" "))
+    }
+
+    /// Queries nested up to thousands of groups and negations deep, far
+    /// past the parser's depth limit, some with groups left open. Built
+    /// from a list of layers, since `prop_recursive` itself overflows the
+    /// stack at this depth.
+    fn nested_query() -> impl Strategy<Value = String> {
+        let layer = (
+            prop::sample::select(vec!["(", "-", "NOT (", "a OR (", "-("]),
+            any::<bool>(),
+        );
+        (prop::collection::vec(layer, 0..20_000), query()).prop_map(|(layers, inner)| {
+            let mut query = String::new();
+            let mut closes = String::new();
+            for (open, closed) in &layers {
+                query.push_str(open);
+                if open.ends_with('(') && *closed {
+                    closes.push(')');
+                }
+            }
+            query.push_str(&inner);
+            query.push_str(&closes);
+            query
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn gmail_batch_responses_keep_their_invariants(response in batch_response()) {
+            gmail_batch_response(response.as_bytes());
+        }
+
+        #[test]
+        fn address_headers_keep_their_invariants(value in header()) {
+            address_header(value.as_bytes());
+        }
+
+        #[test]
+        fn archive_payloads_keep_their_invariants(payload in archive_json()) {
+            archive_payload(payload.as_bytes());
+        }
+
+        #[test]
+        fn search_queries_keep_their_invariants(query in query()) {
+            search_query(query.as_bytes());
+        }
+
+        #[test]
+        fn deeply_nested_queries_keep_their_invariants(query in nested_query()) {
+            search_query(query.as_bytes());
+        }
+
+        #[test]
+        fn arbitrary_bytes_do_not_panic(data in prop::collection::vec(any::<u8>(), 0..256)) {
+            gmail_batch_response(&data);
+            address_header(&data);
+            archive_payload(&data);
+            search_query(&data);
+        }
+    }
+}
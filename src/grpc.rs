@@ -0,0 +1,466 @@
+//! The gRPC service of `proto/ess/v1/ess.proto`, served by `ess grpc` in
+//! builds with the `grpc` feature, for integrations that pull results in
+//! bulk:
+//! - `Search` streams every hit of a query, best first, without the page
+//!   round trips of `--json` or MCP;
+//! - `Changes` streams the emails upserted or deleted after a cursor, then
+//!   stays open and sends what later syncs write.
+//!
+//! Each stream reads on a blocking thread of its own and sends into a
+//! bounded channel, so a slow client slows the reads down instead of the
+//! server buffering the store. With a token set, every call needs
+//! `authorization: Bearer <token>` metadata.
+
+pub mod proto {
+    tonic::include_proto!("ess.v1");
+}
+
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tonic::service::Interceptor;
+use tonic::{Request, Response, Status};
+
+use crate::db::changes::ChangeEntity;
+use crate::db::models::Email;
+use crate::mcp::http::token_matches;
+use crate::mcp::state::ServerState;
+use crate::search::{self, EmailFilters, Scope};
+
+use self::proto::ess_server::{Ess, EssServer};
+use self::proto::{change, Change, ChangesRequest, SearchRequest, SearchResult};
+
+/// Hits read from the index per page of a `Search` stream.
+const SEARCH_PAGE_SIZE: usize = 500;
+/// Changes read per query of a `Changes` stream.
+const CHANGES_BATCH_SIZE: usize = 500;
+/// Messages a stream reads ahead of its client.
+const STREAM_BUFFER: usize = 256;
+/// How often an open `Changes` stream looks for new changes.
+const CHANGES_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone)]
+pub struct GrpcServerOptions {
+    pub addr: SocketAddr,
+    /// Bearer token every call must carry; required off loopback.
+    pub token: Option<String>,
+}
+
+/// Serves until Ctrl-C.
+pub async fn run_grpc_server(options: GrpcServerOptions) -> Result<()> {
+    if options.token.is_none() && !options.addr.ip().is_loopback() {
+        return Err(anyhow!(
+            "serving gRPC on {} needs a token (--token or ESS_GRPC_TOKEN); without one, bind to a loopback address",
+            options.addr
+        ));
+    }
+    let state = Arc::new(ServerState::for_default_paths()?);
+    let listener = TcpListener::bind(options.addr)
+        .await
+        .with_context(|| format!("bind gRPC server to {}", options.addr))?;
+    eprintln!(
+        "ESS gRPC server listening on {}",
+        listener
+            .local_addr()
+            .context("read gRPC listener address")?
+    );
+    serve(listener, state, options.token, async {
+        let _ = tokio::signal::ctrl_c().await;
+    })
+    .await
+}
+
+async fn serve(
+    listener: TcpListener,
+    state: Arc<ServerState>,
+    token: Option<String>,
+    shutdown: impl std::future::Future<Output = ()> + Send + 'static,
+) -> Result<()> {
+    let service = EssServer::with_interceptor(EssService { state }, BearerToken(token));
+    tonic::transport::Server::builder()
+        .add_service(service)
+        .serve_with_incoming_shutdown(TcpListenerStream::new(listener), shutdown)
+        .await
+        .context("serve gRPC")
+}
+
+/// Refuses calls without the bearer token, when there is one.
+#[derive(Clone)]
+struct BearerToken(Option<String>);
+
+impl Interceptor for BearerToken {
+    fn call(&mut self, request: Request<()>) -> Result<Request<()>, Status> {
+        let Some(token) = self.0.as_deref() else {
+            return Ok(request);
+        };
+        let presented = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        if !presented.is_some_and(|presented| token_matches(presented, token)) {
+            return Err(Status::unauthenticated("missing or wrong bearer token"));
+        }
+        Ok(request)
+    }
+}
+
+struct EssService {
+    state: Arc<ServerState>,
+}
+
+#[tonic::async_trait]
+impl Ess for EssService {
+    type SearchStream = ReceiverStream<Result<SearchResult, Status>>;
+    type ChangesStream = ReceiverStream<Result<Change, Status>>;
+
+    async fn search(
+        &self,
+        request: Request<SearchRequest>,
+    ) -> Result<Response<Self::SearchStream>, Status> {
+        let request = request.into_inner();
+        let filters = search_filters(&request).map_err(Status::invalid_argument)?;
+        let state = self.state.clone();
+        Ok(Response::new(spawn_stream(move |sender| {
+            stream_search(&state, &request, filters, sender)
+        })))
+    }
+
+    async fn changes(
+        &self,
+        request: Request<ChangesRequest>,
+    ) -> Result<Response<Self::ChangesStream>, Status> {
+        let request = request.into_inner();
+        let after = match request.cursor.as_str() {
+            "" => None,
+            cursor => Some(cursor.parse::<i64>().map_err(|_| {
+                Status::invalid_argument("cursor must be one sent in an earlier Change")
+            })?),
+        };
+        let state = self.state.clone();
+        Ok(Response::new(spawn_stream(move |sender| {
+            stream_changes(&state, after, request.omit_bodies, sender)
+        })))
+    }
+}
+
+/// Runs `produce` on a blocking thread, ending the stream with its error
+/// if it fails.
+fn spawn_stream<T: Send + 'static>(
+    produce: impl FnOnce(&mpsc::Sender<Result<T, Status>>) -> Result<()> + Send + 'static,
+) -> ReceiverStream<Result<T, Status>> {
+    let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+    tokio::task::spawn_blocking(move || {
+        if let Err(error) = produce(&sender) {
+            let _ = sender.blocking_send(Err(Status::internal(format!("{error:#}"))));
+        }
+    });
+    ReceiverStream::new(receiver)
+}
+
+fn search_filters(request: &SearchRequest) -> Result<EmailFilters, String> {
+    let scope = match request.scope.as_str() {
+        "" => Scope::All,
+        scope => Scope::from_str(scope)?,
+    };
+    let date = |value: &Option<String>, name: &str| {
+        value
+            .as_deref()
+            .map(|value| {
+                search::dates::parse_date(value).map_err(|error| format!("{name}: {error}"))
+            })
+            .transpose()
+    };
+    Ok(EmailFilters {
+        scope,
+        from: request.from.clone(),
+        since: date(&request.since, "since")?,
+        until: date(&request.until, "until")?,
+        account: request.account.clone(),
+        folder: request.folder.clone(),
+        ..EmailFilters::default()
+    })
+}
+
+/// Pages through the hits with search cursors until `limit` are sent or
+/// the client hangs up.
+fn stream_search(
+    state: &ServerState,
+    request: &SearchRequest,
+    mut filters: EmailFilters,
+    sender: &mpsc::Sender<Result<SearchResult, Status>>,
+) -> Result<()> {
+    let db = state.database()?;
+    let index = state.index(&db)?;
+    let wanted = match request.limit {
+        0 => usize::MAX,
+        limit => limit as usize,
+    };
+    let mut sent = 0;
+    let mut cursor = None;
+    while sent < wanted {
+        filters.limit = SEARCH_PAGE_SIZE.min(wanted - sent);
+        let page = search::search_emails_page(&index, &db, &request.query, &filters, cursor)?;
+        for result in page.results {
+            let hit = SearchResult {
+                email: Some(proto_email(result.email, request.omit_bodies)),
+                score: result.score,
+                snippet: result.snippet.map(|snippet| snippet.text),
+            };
+            if sender.blocking_send(Ok(hit)).is_err() {
+                return Ok(());
+            }
+            sent += 1;
+        }
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+    Ok(())
+}
+
+/// Sends the email changes recorded after `after` (or from now), then
+/// polls for new ones until the client hangs up.
+fn stream_changes(
+    state: &ServerState,
+    after: Option<i64>,
+    omit_bodies: bool,
+    sender: &mpsc::Sender<Result<Change, Status>>,
+) -> Result<()> {
+    let db = state.database()?;
+    let mut seq = match after {
+        Some(seq) => seq,
+        None => db.latest_change_seq()?,
+    };
+    while !sender.is_closed() {
+        let changes = db.changes_since(seq, CHANGES_BATCH_SIZE)?;
+        if changes.is_empty() {
+            std::thread::sleep(CHANGES_POLL_INTERVAL);
+            continue;
+        }
+        for change in changes {
+            seq = change.seq;
+            if change.entity != ChangeEntity::Email {
+                continue;
+            }
+            let email = db.get_email(&change.key)?;
+            let kind = match email {
+                Some(_) => change::Kind::Upserted,
+                None => change::Kind::Deleted,
+            };
+            let message = Change {
+                kind: kind.into(),
+                email_id: change.key,
+                email: email.map(|email| proto_email(email, omit_bodies)),
+                cursor: seq.to_string(),
+            };
+            if sender.blocking_send(Ok(message)).is_err() {
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn proto_email(email: Email, omit_bodies: bool) -> proto::Email {
+    let (body_text, body_html) = match omit_bodies {
+        true => (None, None),
+        false => (email.body_text, email.body_html),
+    };
+    proto::Email {
+        id: email.id,
+        internet_message_id: email.internet_message_id,
+        conversation_id: email.conversation_id,
+        account_id: email.account_id,
+        subject: email.subject,
+        from_address: email.from_address,
+        from_name: email.from_name,
+        to_addresses: email.to_addresses,
+        cc_addresses: email.cc_addresses,
+        bcc_addresses: email.bcc_addresses,
+        body_text,
+        body_html,
+        body_preview: email.body_preview,
+        received_at: email.received_at,
+        sent_at: email.sent_at,
+        importance: email.importance,
+        is_read: email.is_read,
+        has_attachments: email.has_attachments,
+        folder: email.folder,
+        categories: email.categories,
+        flag_status: email.flag_status,
+        web_link: email.web_link,
+        metadata_json: email.metadata.map(|metadata| metadata.to_string()),
+        in_reply_to: email.in_reply_to,
+        references: email.references,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::net::TcpListener;
+    use tonic::metadata::MetadataValue;
+    use tonic::transport::Endpoint;
+    use tonic::{Code, Request};
+
+    use super::proto::ess_client::EssClient;
+    use super::proto::{change, ChangesRequest, SearchRequest};
+    use super::serve;
+    use crate::db::models::Email;
+    use crate::mcp::state::ServerState;
+
+    fn authorized<T>(message: T) -> Request<T> {
+        let mut request = Request::new(message);
+        let bearer: MetadataValue<_> = "Bearer secret".parse().expect("metadata");
+        request.metadata_mut().insert("authorization", bearer);
+        request
+    }
+
+    fn email(n: usize) -> Email {
+        Email {
+            subject: Some(format!("Quarterly budget {n}")),
+            from_address: Some("alice@example.com".to_string()),
+            body_text: Some("numbers attached".to_string()),
+            received_at: format!("2026-02-{:02}T10:00:00Z", n % 28 + 1),
+            ..Email::fixture(&format!("e{n}"))
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn streams_search_hits_and_changes() {
+        let root = std::env::temp_dir().join(format!("ess-grpc-{}", uuid::Uuid::new_v4()));
+        let state = Arc::new(ServerState::new(
+            root.join("ess.db"),
+            root.join("index"),
+            root.join("config.toml"),
+        ));
+        {
+            let db = state.database().expect("database");
+            for n in 0..40 {
+                db.insert_email(&email(n)).expect("insert email");
+            }
+        }
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let addr = listener.local_addr().expect("listener address");
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(serve(
+            listener,
+            state.clone(),
+            Some("secret".to_string()),
+            async {
+                let _ = stopped.await;
+            },
+        ));
+        let channel = Endpoint::from_shared(format!("http://{addr}"))
+            .expect("endpoint")
+            .connect()
+            .await
+            .expect("connect");
+
+        let mut anonymous = EssClient::new(channel.clone());
+        let refused = anonymous
+            .search(SearchRequest {
+                query: "budget".to_string(),
+                ..SearchRequest::default()
+            })
+            .await
+            .expect_err("no token");
+        assert_eq!(refused.code(), Code::Unauthenticated);
+
+        let mut client = EssClient::new(channel);
+
+        // Every hit, best first, in one stream.
+        let mut hits = client
+            .search(authorized(SearchRequest {
+                query: "budget".to_string(),
+                omit_bodies: true,
+                ..SearchRequest::default()
+            }))
+            .await
+            .expect("search")
+            .into_inner();
+        let mut scores = Vec::new();
+        while let Some(hit) = hits.message().await.expect("next hit") {
+            let email = hit.email.expect("hit email");
+            assert!(email.body_text.is_none());
+            scores.push(hit.score);
+        }
+        assert_eq!(scores.len(), 40);
+        assert!(scores.windows(2).all(|pair| pair[0] >= pair[1]));
+
+        let mut limited = client
+            .search(authorized(SearchRequest {
+                query: "budget".to_string(),
+                limit: 5,
+                ..SearchRequest::default()
+            }))
+            .await
+            .expect("limited search")
+            .into_inner();
+        let mut count = 0;
+        while limited.message().await.expect("next hit").is_some() {
+            count += 1;
+        }
+        assert_eq!(count, 5);
+
+        // A feed opened now sees only what is written after it.
+        let mut changes = client
+            .changes(authorized(ChangesRequest::default()))
+            .await
+            .expect("changes")
+            .into_inner();
+        {
+            let db = state.database().expect("database");
+            db.insert_email(&email(40)).expect("insert new email");
+            db.delete_email("e0").expect("delete email");
+        }
+        let upserted = changes.message().await.expect("change").expect("upsert");
+        assert_eq!(upserted.kind, i32::from(change::Kind::Upserted));
+        assert_eq!(upserted.email_id, "e40");
+        assert_eq!(
+            upserted.email.and_then(|email| email.body_text).as_deref(),
+            Some("numbers attached")
+        );
+        let deleted = changes.message().await.expect("change").expect("delete");
+        assert_eq!(deleted.kind, i32::from(change::Kind::Deleted));
+        assert_eq!(deleted.email_id, "e0");
+        assert!(deleted.email.is_none());
+        drop(changes);
+
+        // Resuming from a cursor replays what came after it.
+        let mut resumed = client
+            .changes(authorized(ChangesRequest {
+                cursor: upserted.cursor,
+                omit_bodies: false,
+            }))
+            .await
+            .expect("resumed changes")
+            .into_inner();
+        let replayed = resumed.message().await.expect("change").expect("replay");
+        assert_eq!(replayed.email_id, "e0");
+        assert_eq!(replayed.cursor, deleted.cursor);
+        drop(resumed);
+
+        let invalid = client
+            .changes(authorized(ChangesRequest {
+                cursor: "not-a-cursor".to_string(),
+                omit_bodies: false,
+            }))
+            .await
+            .expect_err("bad cursor");
+        assert_eq!(invalid.code(), Code::InvalidArgument);
+
+        let _ = stop.send(());
+        server.await.expect("server task").expect("server");
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
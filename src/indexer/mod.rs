@@ -1,15 +1,26 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
 use std::ops::Bound;
 use std::path::{Path, PathBuf};
+use std::time::Instant;
 
 use chrono::{DateTime as ChronoDateTime, NaiveDate, Utc};
+use ring::digest::{digest, SHA256};
+use serde::{Deserialize, Serialize};
 use tantivy::collector::TopDocs;
+use tantivy::merge_policy::{LogMergePolicy, MergePolicy, NoMergePolicy};
 use tantivy::query::{AllQuery, BooleanQuery, Occur, Query, QueryParser, RangeQuery, TermQuery};
 use tantivy::schema::{Field, IndexRecordOption, TantivyDocument, Value};
-use tantivy::{doc, DateTime as TantivyDateTime, Index, IndexReader, IndexWriter, Term};
+use tantivy::{
+    doc, DateTime as TantivyDateTime, DocAddress, DocId, DocSet, Index, IndexReader, IndexWriter,
+    Score, SegmentReader, Term, TERMINATED,
+};
 use thiserror::Error;
 
+use crate::db::meta::INDEXED_META_KEYS;
 use crate::db::models::Email;
 use crate::db::Database;
+use crate::paths;
 
 #[derive(Debug, Error)]
 pub enum IndexError {
@@ -30,14 +41,25 @@ pub enum IndexError {
 
     #[error("{0}")]
     Config(String),
+
+    #[error("index was built with {found} but {expected} is configured; it must be rebuilt")]
+    AnalysisChanged { found: String, expected: String },
 }
 
 pub mod schema;
 
+pub use self::schema::{Analyzer, TextAnalysis};
+
 #[derive(Debug, Clone, Default)]
 pub struct SearchFilters {
     pub account_type: Option<String>,
     pub folder: Option<String>,
+    /// Exact sender domain, already normalized with `normalize_domain`.
+    pub from_domain: Option<String>,
+    /// Exact address in to, cc or bcc (case-insensitive).
+    pub to: Option<String>,
+    pub account_id: Option<String>,
+    pub unread_only: bool,
     pub since: Option<String>,
     pub until: Option<String>,
 }
@@ -45,6 +67,7 @@ pub struct SearchFilters {
 #[derive(Debug, Clone)]
 pub struct EmailSearchHit {
     pub email_db_id: String,
+    pub conversation_id: Option<String>,
     pub score: f32,
     pub subject: Option<String>,
     pub from_name: Option<String>,
@@ -52,17 +75,234 @@ pub struct EmailSearchHit {
     pub folder: Option<String>,
     pub account_type: Option<String>,
     pub received_at: Option<String>,
+    pub position: HitPosition,
+}
+
+/// Where a hit sits in the index ranking (score descending, then the
+/// email's `email_key` ascending), so a later search can resume right after
+/// it. It names the email rather than its index address, so it survives
+/// commits and segment merges.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HitPosition {
+    pub score: f32,
+    pub email_id: String,
 }
 
 #[derive(Debug, Clone)]
 pub struct EmailIndexStats {
     pub doc_count: u64,
     pub index_size_bytes: u64,
+    pub segment_count: usize,
+    /// Deleted documents still held by their segments until a merge.
+    pub deleted_doc_count: u64,
+    /// Opstamp of the last commit; it grows with every indexed change.
+    pub last_commit_opstamp: u64,
+}
+
+/// Email columns plus the owning account's type, which every indexed
+/// document carries.
+const EMAIL_WITH_ACCOUNT_TYPE: &str = r#"
+    SELECT
+        e.id,
+        e.internet_message_id,
+        e.conversation_id,
+        e.account_id,
+        e.subject,
+        e.from_address,
+        e.from_name,
+        e.to_addresses,
+        e.cc_addresses,
+        e.bcc_addresses,
+        e.body_text,
+        e.body_html,
+        e.body_preview,
+        e.received_at,
+        e.sent_at,
+        e.importance,
+        e.is_read,
+        e.has_attachments,
+        e.folder,
+        e.categories,
+        e.flag_status,
+        e.web_link,
+        e.metadata,
+        COALESCE(a.account_type, 'personal') AS account_type
+    FROM emails e
+    LEFT JOIN accounts a ON a.account_id = e.account_id
+"#;
+
+/// Queries `warm` runs so the postings and doc store pages a typical first
+/// search touches are resident: the recent-mail listing plus common words.
+pub const WARM_QUERIES: &[&str] = &["", "re", "meeting", "invoice", "update"];
+
+/// What `EmailIndex::warm` read and how long the primed queries took.
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexWarmReport {
+    pub segments: usize,
+    pub files: usize,
+    pub bytes_read: u64,
+    pub queries: Vec<WarmQueryTiming>,
+    pub duration_ms: u64,
+}
+
+/// What `EmailIndex::optimize` merged away.
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexOptimizeReport {
+    pub segments_before: usize,
+    pub segments_after: usize,
+    pub deleted_docs_purged: u64,
+    pub size_before_bytes: u64,
+    pub size_after_bytes: u64,
+    pub duration_ms: u64,
+}
+
+/// How the index differs from SQLite, from `EmailIndex::verify`. Id lists
+/// are sorted.
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexVerifyReport {
+    pub emails: usize,
+    pub documents: u64,
+    /// In SQLite but not in the index.
+    pub missing: Vec<String>,
+    /// In the index but no longer in SQLite.
+    pub orphaned: Vec<String>,
+    /// Indexed more than once.
+    pub duplicated: Vec<String>,
+    pub repaired: bool,
+    pub duration_ms: u64,
+}
+
+impl IndexVerifyReport {
+    pub fn is_consistent(&self) -> bool {
+        self.missing.is_empty() && self.orphaned.is_empty() && self.duplicated.is_empty()
+    }
+}
+
+/// Writer memory, background merge behaviour and text analysis, from
+/// `[index]` in config.toml.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexSettings {
+    pub writer_memory_bytes: usize,
+    pub merge_policy: MergePolicySetting,
+    pub analysis: TextAnalysis,
+}
+
+impl Default for IndexSettings {
+    fn default() -> Self {
+        Self {
+            writer_memory_bytes: DEFAULT_WRITER_MEMORY_BYTES,
+            merge_policy: MergePolicySetting::default(),
+            analysis: TextAnalysis::default(),
+        }
+    }
+}
+
+/// Written next to Tantivy's files when an index is created, recording how
+/// its text was analyzed. Indexes from before the stamp existed have none.
+const ANALYSIS_STAMP_FILE: &str = "ess-analysis.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct AnalysisStamp {
+    schema_version: u32,
+    analysis: TextAnalysis,
+}
+
+impl AnalysisStamp {
+    fn current(analysis: TextAnalysis) -> Self {
+        Self {
+            schema_version: schema::SCHEMA_VERSION,
+            analysis,
+        }
+    }
+
+    fn read(index_path: &Path) -> Option<Self> {
+        let raw = std::fs::read_to_string(index_path.join(ANALYSIS_STAMP_FILE)).ok()?;
+        serde_json::from_str(&raw).ok()
+    }
+
+    fn write(self, index_path: &Path) -> Result<(), IndexError> {
+        let raw = serde_json::to_string(&self)
+            .map_err(|e| IndexError::Config(format!("encode analysis stamp: {e}")))?;
+        std::fs::write(index_path.join(ANALYSIS_STAMP_FILE), raw)?;
+        Ok(())
+    }
+
+    fn describe(stamp: Option<Self>) -> String {
+        match stamp {
+            Some(stamp) => format!(
+                "schema version {} ({})",
+                stamp.schema_version, stamp.analysis
+            ),
+            None => "schema version 1".to_string(),
+        }
+    }
+}
+
+/// Smallest writer budget Tantivy accepts (15 MB per indexing thread).
+pub const MIN_WRITER_MEMORY_BYTES: usize = 15_000_000;
+pub const DEFAULT_WRITER_MEMORY_BYTES: usize = 50_000_000;
+/// Tantivy's log merge policy defaults.
+pub const DEFAULT_MIN_MERGE_SEGMENTS: usize = 8;
+pub const DEFAULT_MAX_DOCS_BEFORE_MERGE: usize = 10_000_000;
+pub const DEFAULT_DELETED_RATIO_BEFORE_MERGE: f32 = 1.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MergePolicySetting {
+    /// Tantivy's log merge policy. A segment whose share of deleted
+    /// documents exceeds `del_docs_ratio_before_merge` is rewritten even
+    /// when it has nothing to merge with, which is what reclaims the space
+    /// delta syncs leave behind.
+    Log {
+        min_num_segments: usize,
+        max_docs_before_merge: usize,
+        del_docs_ratio_before_merge: f32,
+    },
+    /// No background merges; only `ess index optimize` compacts.
+    None,
+}
+
+impl Default for MergePolicySetting {
+    fn default() -> Self {
+        Self::Log {
+            min_num_segments: DEFAULT_MIN_MERGE_SEGMENTS,
+            max_docs_before_merge: DEFAULT_MAX_DOCS_BEFORE_MERGE,
+            del_docs_ratio_before_merge: DEFAULT_DELETED_RATIO_BEFORE_MERGE,
+        }
+    }
+}
+
+impl MergePolicySetting {
+    fn to_policy(self) -> Box<dyn MergePolicy> {
+        match self {
+            Self::Log {
+                min_num_segments,
+                max_docs_before_merge,
+                del_docs_ratio_before_merge,
+            } => {
+                let mut policy = LogMergePolicy::default();
+                policy.set_min_num_segments(min_num_segments);
+                policy.set_max_docs_before_merge(max_docs_before_merge);
+                policy.set_del_docs_ratio_before_merge(del_docs_ratio_before_merge);
+                Box::new(policy)
+            }
+            Self::None => Box::new(NoMergePolicy),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WarmQueryTiming {
+    pub query: String,
+    pub hits: usize,
+    pub duration_ms: u64,
 }
 
 pub struct EmailIndex {
     index: Index,
-    writer: IndexWriter,
+    /// Taken on the first write, so searches never hold Tantivy's writer
+    /// lock and any number of them can run next to a sync.
+    writer: Option<IndexWriter>,
+    settings: IndexSettings,
     reader: IndexReader,
     fields: schema::EmailSearchFields,
     path: PathBuf,
@@ -70,36 +310,54 @@ pub struct EmailIndex {
 
 impl EmailIndex {
     pub fn open(path: &Path) -> Result<Self, IndexError> {
+        Self::open_with_settings(path, &IndexSettings::default())
+    }
+
+    pub fn open_with_settings(path: &Path, settings: &IndexSettings) -> Result<Self, IndexError> {
         std::fs::create_dir_all(path)?;
 
         let schema_def = schema::build_schema();
+        let stamp = AnalysisStamp::current(settings.analysis);
         let mut index = if path.join("meta.json").exists() {
+            let found = AnalysisStamp::read(path);
+            if found != Some(stamp) {
+                return Err(IndexError::AnalysisChanged {
+                    found: AnalysisStamp::describe(found),
+                    expected: AnalysisStamp::describe(Some(stamp)),
+                });
+            }
             Index::open_in_dir(path)?
         } else {
-            Index::create_in_dir(path, schema_def)?
+            let index = Index::create_in_dir(path, schema_def)?;
+            stamp.write(path)?;
+            index
         };
 
-        schema::ensure_edge_ngram_tokenizer(&mut index)
+        schema::ensure_edge_ngram_tokenizer(&mut index, settings.analysis)
             .map_err(|e| IndexError::Config(format!("register tokenizer: {e}")))?;
         let fields = schema::fields_from_schema(&index.schema())
             .map_err(|e| IndexError::Config(format!("resolve schema fields: {e}")))?;
 
-        let writer = index.writer(50_000_000)?;
         let reader = index.reader()?;
 
         Ok(Self {
             index,
-            writer,
+            writer: None,
+            settings: settings.clone(),
             reader,
             fields,
             path: path.to_path_buf(),
         })
     }
 
+    /// The underlying Tantivy index, for building queries against its schema.
+    pub fn tantivy_index(&self) -> &Index {
+        &self.index
+    }
+
     pub fn default_index_path() -> Result<PathBuf, IndexError> {
-        let home = dirs::home_dir()
-            .ok_or_else(|| IndexError::Config("failed to determine home directory".to_string()))?;
-        Ok(home.join(".ess").join("index"))
+        let dir = paths::cache_dir().map_err(|e| IndexError::Config(e.to_string()))?;
+        Ok(dir.join("index"))
     }
 
     pub fn add_email(&mut self, email: &Email, account_type: &str) -> Result<(), IndexError> {
@@ -116,6 +374,21 @@ impl EmailIndex {
         self.index_email_document(email, account_type)
     }
 
+    /// Removes an email's document without committing, like
+    /// `add_email_buffered`.
+    pub fn delete_email_buffered(&mut self, email_db_id: &str) -> Result<(), IndexError> {
+        let term = Term::from_field_text(self.fields.email_db_id, email_db_id);
+        self.writer()?.delete_term(term);
+        Ok(())
+    }
+
+    /// Replaces the document of an email whose read state, flags, folder or
+    /// labels changed. Buffered like `add_email_buffered`, so a sync can
+    /// apply a page of state changes and `commit()` once.
+    pub fn update_email(&mut self, email: &Email, account_type: &str) -> Result<(), IndexError> {
+        self.index_email_document(email, account_type)
+    }
+
     /// Commit all buffered writes and reload the reader.
     pub fn commit(&mut self) -> Result<(), IndexError> {
         self.commit_and_reload()
@@ -127,19 +400,21 @@ impl EmailIndex {
         filters: &SearchFilters,
         limit: usize,
     ) -> Result<Vec<EmailSearchHit>, IndexError> {
-        let requested_limit = limit.max(1);
-
+        let boosted = [
+            (self.fields.subject, schema::SUBJECT_BOOST),
+            (self.fields.subject_words, schema::SUBJECT_BOOST),
+            (self.fields.from_name, schema::FROM_NAME_BOOST),
+            (self.fields.from_name_words, schema::FROM_NAME_BOOST),
+            (self.fields.body_text, schema::BODY_BOOST),
+            (self.fields.body_words, schema::BODY_BOOST),
+        ];
         let mut parser = QueryParser::for_index(
             &self.index,
-            vec![
-                self.fields.subject,
-                self.fields.from_name,
-                self.fields.body_text,
-            ],
+            boosted.iter().map(|(field, _)| *field).collect(),
         );
-        parser.set_field_boost(self.fields.subject, schema::SUBJECT_BOOST);
-        parser.set_field_boost(self.fields.from_name, schema::FROM_NAME_BOOST);
-        parser.set_field_boost(self.fields.body_text, schema::BODY_BOOST);
+        for (field, boost) in boosted {
+            parser.set_field_boost(field, boost);
+        }
 
         let base_query: Box<dyn Query> = if query.trim().is_empty() {
             Box::new(AllQuery)
@@ -147,6 +422,33 @@ impl EmailIndex {
             parser.parse_query(query)?
         };
 
+        self.search_with_query(base_query, filters, limit)
+    }
+
+    /// Runs an already-built query, adding the scope/folder/date filters.
+    pub fn search_with_query(
+        &self,
+        base_query: Box<dyn Query>,
+        filters: &SearchFilters,
+        limit: usize,
+    ) -> Result<Vec<EmailSearchHit>, IndexError> {
+        self.search_with_query_after(base_query, filters, limit, None)
+    }
+
+    /// Like `search_with_query`, but only returns hits ranked after `after`,
+    /// used as a search-after bound: nothing before the position is
+    /// collected, fetched or skipped. A position stays valid across commits
+    /// and merges; emails indexed since then show up only if they rank
+    /// after it.
+    pub fn search_with_query_after(
+        &self,
+        base_query: Box<dyn Query>,
+        filters: &SearchFilters,
+        limit: usize,
+        after: Option<&HitPosition>,
+    ) -> Result<Vec<EmailSearchHit>, IndexError> {
+        let requested_limit = limit.max(1);
+
         let mut clauses: Vec<(Occur, Box<dyn Query>)> = vec![(Occur::Must, base_query)];
 
         if let Some(account_type) = filters
@@ -176,6 +478,65 @@ impl EmailIndex {
             ));
         }
 
+        if let Some(from_domain) = filters
+            .from_domain
+            .as_deref()
+            .filter(|value| !value.is_empty())
+        {
+            let term = Term::from_field_text(self.fields.from_domain, from_domain);
+            clauses.push((
+                Occur::Must,
+                Box::new(TermQuery::new(term, IndexRecordOption::Basic)),
+            ));
+        }
+
+        if let Some(to) = filters
+            .to
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+        {
+            let address = to.to_lowercase();
+            let recipients = [
+                self.fields.to_addresses,
+                self.fields.cc_addresses,
+                self.fields.bcc_addresses,
+            ]
+            .into_iter()
+            .map(|field| {
+                (
+                    Occur::Should,
+                    Box::new(TermQuery::new(
+                        Term::from_field_text(field, &address),
+                        IndexRecordOption::Basic,
+                    )) as Box<dyn Query>,
+                )
+            })
+            .collect::<Vec<_>>();
+            clauses.push((Occur::Must, Box::new(BooleanQuery::new(recipients))));
+        }
+
+        if let Some(account_id) = filters
+            .account_id
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+        {
+            let term = Term::from_field_text(self.fields.account_id, account_id);
+            clauses.push((
+                Occur::Must,
+                Box::new(TermQuery::new(term, IndexRecordOption::Basic)),
+            ));
+        }
+
+        if filters.unread_only {
+            let term = Term::from_field_bool(self.fields.is_read, false);
+            clauses.push((
+                Occur::Must,
+                Box::new(TermQuery::new(term, IndexRecordOption::Basic)),
+            ));
+        }
+
         let lower_bound = filters
             .since
             .as_deref()
@@ -213,17 +574,38 @@ impl EmailIndex {
         };
 
         let searcher = self.reader.searcher();
-        let docs = searcher.search(
-            combined_query.as_ref(),
-            &TopDocs::with_limit(requested_limit),
-        )?;
+        let bound = after.map(|position| rank_of(position.score, &position.email_id));
+        let collector = TopDocs::with_limit(requested_limit).tweak_score(
+            move |segment_reader: &SegmentReader| {
+                let keys = segment_reader.fast_fields().u64("email_key").ok();
+                move |doc: DocId, score: Score| {
+                    let key = keys
+                        .as_ref()
+                        .and_then(|column| column.first(doc))
+                        .unwrap_or(u64::MAX);
+                    let rank = (score, Reverse(key));
+                    bound.is_none_or(|bound| rank < bound).then_some(rank)
+                }
+            },
+        );
+        let docs: Vec<(Score, DocAddress)> = searcher
+            .search(combined_query.as_ref(), &collector)?
+            .into_iter()
+            .filter_map(|(rank, address)| rank.map(|(score, _)| (score, address)))
+            .collect();
 
         let mut hits = Vec::with_capacity(docs.len());
         for (score, address) in docs {
             let retrieved_doc: TantivyDocument = searcher.doc(address)?;
+            let email_db_id =
+                first_string(&retrieved_doc, self.fields.email_db_id).unwrap_or_default();
             hits.push(EmailSearchHit {
-                email_db_id: first_string(&retrieved_doc, self.fields.email_db_id)
-                    .unwrap_or_default(),
+                position: HitPosition {
+                    score,
+                    email_id: email_db_id.clone(),
+                },
+                email_db_id,
+                conversation_id: first_string(&retrieved_doc, self.fields.conversation_id),
                 score,
                 subject: first_string(&retrieved_doc, self.fields.subject),
                 from_name: first_string(&retrieved_doc, self.fields.from_name),
@@ -241,40 +623,11 @@ impl EmailIndex {
     }
 
     pub fn reindex(&mut self, db: &Database) -> Result<usize, IndexError> {
-        self.writer.delete_all_documents()?;
-
-        let mut stmt = db.conn().prepare(
-            r#"
-            SELECT
-                e.id,
-                e.internet_message_id,
-                e.conversation_id,
-                e.account_id,
-                e.subject,
-                e.from_address,
-                e.from_name,
-                e.to_addresses,
-                e.cc_addresses,
-                e.bcc_addresses,
-                e.body_text,
-                e.body_html,
-                e.body_preview,
-                e.received_at,
-                e.sent_at,
-                e.importance,
-                e.is_read,
-                e.has_attachments,
-                e.folder,
-                e.categories,
-                e.flag_status,
-                e.web_link,
-                e.metadata,
-                COALESCE(a.account_type, 'personal') AS account_type
-            FROM emails e
-            LEFT JOIN accounts a ON a.account_id = e.account_id
-            ORDER BY e.received_at ASC
-            "#,
-        )?;
+        self.writer()?.delete_all_documents()?;
+
+        let mut stmt = db.conn().prepare(&format!(
+            "{EMAIL_WITH_ACCOUNT_TYPE} ORDER BY e.received_at ASC"
+        ))?;
 
         let mut indexed_count = 0usize;
         let rows = stmt.query_map([], |row| {
@@ -293,19 +646,225 @@ impl EmailIndex {
         Ok(indexed_count)
     }
 
+    /// Compares the index with SQLite by email id. With `repair`, missing and
+    /// duplicated emails are indexed afresh and orphans deleted, which is far
+    /// cheaper than a full reindex when only a few documents are off.
+    pub fn verify(&mut self, db: &Database, repair: bool) -> Result<IndexVerifyReport, IndexError> {
+        let started = Instant::now();
+        self.commit_and_reload()?;
+        let indexed = self.indexed_email_ids()?;
+        let emails = db
+            .conn()
+            .prepare("SELECT id FROM emails")?
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<HashSet<_>>>()?;
+
+        let mut missing = emails
+            .iter()
+            .filter(|id| !indexed.contains_key(*id))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut orphaned = indexed
+            .keys()
+            .filter(|id| !emails.contains(*id))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut duplicated = indexed
+            .iter()
+            .filter(|(id, count)| **count > 1 && emails.contains(*id))
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<_>>();
+        missing.sort();
+        orphaned.sort();
+        duplicated.sort();
+
+        let mut report = IndexVerifyReport {
+            emails: emails.len(),
+            documents: indexed.values().sum(),
+            missing,
+            orphaned,
+            duplicated,
+            repaired: false,
+            duration_ms: 0,
+        };
+        if repair && !report.is_consistent() {
+            for id in &report.orphaned {
+                let term = Term::from_field_text(self.fields.email_db_id, id);
+                self.writer()?.delete_term(term);
+            }
+            self.index_stored_emails(db, report.missing.iter().chain(&report.duplicated))?;
+            self.commit_and_reload()?;
+            report.repaired = true;
+        }
+        report.duration_ms = elapsed_ms(started);
+        Ok(report)
+    }
+
+    /// Indexes the stored emails `ids` afresh, e.g. after their bodies were
+    /// trimmed.
+    pub fn reindex_emails(&mut self, db: &Database, ids: &[String]) -> Result<(), IndexError> {
+        self.index_stored_emails(db, ids)?;
+        self.commit_and_reload()
+    }
+
+    fn index_stored_emails<'a>(
+        &mut self,
+        db: &Database,
+        ids: impl IntoIterator<Item = &'a String>,
+    ) -> Result<(), IndexError> {
+        let mut stmt = db
+            .conn()
+            .prepare(&format!("{EMAIL_WITH_ACCOUNT_TYPE} WHERE e.id = ?"))?;
+        for id in ids {
+            let (email, account_type) = stmt.query_row([id], |row| {
+                Ok((Email::from_row(row)?, row.get::<_, String>("account_type")?))
+            })?;
+            self.index_email_document(&email, &account_type)?;
+        }
+        Ok(())
+    }
+
+    /// Live documents per email id, read from the id field's term
+    /// dictionary rather than the document store.
+    fn indexed_email_ids(&self) -> Result<HashMap<String, u64>, IndexError> {
+        let mut counts = HashMap::new();
+        for segment_reader in self.reader.searcher().segment_readers() {
+            let inverted_index = segment_reader.inverted_index(self.fields.email_db_id)?;
+            let alive = segment_reader.alive_bitset();
+            let mut terms = inverted_index.terms().stream()?;
+            while terms.advance() {
+                let mut postings = inverted_index
+                    .read_postings_from_terminfo(terms.value(), IndexRecordOption::Basic)?;
+                let mut live = 0u64;
+                while postings.doc() != TERMINATED {
+                    if alive.is_none_or(|alive| alive.is_alive(postings.doc())) {
+                        live += 1;
+                    }
+                    postings.advance();
+                }
+                if live > 0 {
+                    *counts
+                        .entry(String::from_utf8_lossy(terms.key()).into_owned())
+                        .or_insert(0) += live;
+                }
+            }
+        }
+        Ok(counts)
+    }
+
     pub fn delete_email(&mut self, email_db_id: &str) -> Result<(), IndexError> {
-        self.writer
-            .delete_term(Term::from_field_text(self.fields.email_db_id, email_db_id));
+        let term = Term::from_field_text(self.fields.email_db_id, email_db_id);
+        self.writer()?.delete_term(term);
         self.commit_and_reload()
     }
 
+    /// Picks up commits made through other handles, e.g. by `ess sync`.
+    pub fn reload(&self) -> Result<(), IndexError> {
+        self.reader.reload()?;
+        Ok(())
+    }
+
     pub fn get_stats(&self) -> Result<EmailIndexStats, IndexError> {
-        let doc_count = self.reader.searcher().num_docs();
+        let searcher = self.reader.searcher();
         let index_size_bytes = directory_size(&self.path)?;
 
         Ok(EmailIndexStats {
-            doc_count,
+            doc_count: searcher.num_docs(),
             index_size_bytes,
+            segment_count: searcher.segment_readers().len(),
+            deleted_doc_count: searcher
+                .segment_readers()
+                .iter()
+                .map(|segment| u64::from(segment.num_deleted_docs()))
+                .sum(),
+            last_commit_opstamp: self.index.load_metas()?.opstamp,
+        })
+    }
+
+    /// Merges every segment into one and deletes the files merged away, so
+    /// the index is as compact as after a fresh reindex. Buffered writes are
+    /// committed first.
+    pub fn optimize(&mut self) -> Result<IndexOptimizeReport, IndexError> {
+        let started = Instant::now();
+        self.commit_and_reload()?;
+        // Background merges started by the commit hold on to the segments
+        // they read, and with them their files.
+        if let Some(writer) = self.writer.take() {
+            writer.wait_merging_threads()?;
+            self.reader.reload()?;
+        }
+        let size_before_bytes = directory_size(&self.path)?;
+        let (segments_before, deleted_docs_purged) = {
+            let searcher = self.reader.searcher();
+            let segments = searcher.segment_readers();
+            let deleted = segments
+                .iter()
+                .map(|segment| u64::from(segment.num_deleted_docs()))
+                .sum::<u64>();
+            (segments.len(), deleted)
+        };
+
+        let segment_ids = self.index.searchable_segment_ids()?;
+        let writer = self.writer()?;
+        if segment_ids.len() > 1 || deleted_docs_purged > 0 {
+            writer.merge(&segment_ids).wait()?;
+        }
+        // Files of the merged segments stay while a searcher still reads
+        // them, so the reader moves to the merged segment first.
+        self.reader.reload()?;
+        self.writer()?.garbage_collect_files().wait()?;
+
+        Ok(IndexOptimizeReport {
+            segments_before,
+            segments_after: self.reader.searcher().segment_readers().len(),
+            deleted_docs_purged,
+            size_before_bytes,
+            size_after_bytes: directory_size(&self.path)?,
+            duration_ms: elapsed_ms(started),
+        })
+    }
+
+    /// Pulls the index into the OS page cache so the first interactive search
+    /// after a reboot is not dominated by cold mmap faults: every segment file
+    /// is read once, each segment's term dictionaries are opened, and `queries`
+    /// are run against a fresh searcher.
+    pub fn warm(&self, queries: &[&str]) -> Result<IndexWarmReport, IndexError> {
+        let started = Instant::now();
+        self.reader.reload()?;
+
+        let (files, bytes_read) = read_directory(&self.path)?;
+
+        let searcher = self.reader.searcher();
+        for segment_reader in searcher.segment_readers() {
+            for field in [
+                self.fields.subject,
+                self.fields.subject_words,
+                self.fields.from_name,
+                self.fields.from_name_words,
+                self.fields.body_text,
+                self.fields.body_words,
+            ] {
+                segment_reader.inverted_index(field)?;
+            }
+        }
+
+        let mut timings = Vec::with_capacity(queries.len());
+        for query in queries {
+            let query_started = Instant::now();
+            let hits = self.search(query, &SearchFilters::default(), 20)?;
+            timings.push(WarmQueryTiming {
+                query: query.to_string(),
+                hits: hits.len(),
+                duration_ms: elapsed_ms(query_started),
+            });
+        }
+
+        Ok(IndexWarmReport {
+            segments: searcher.segment_readers().len(),
+            files,
+            bytes_read,
+            queries: timings,
+            duration_ms: elapsed_ms(started),
         })
     }
 
@@ -314,11 +873,12 @@ impl EmailIndex {
         email: &Email,
         account_type: &str,
     ) -> Result<(), IndexError> {
-        self.writer
-            .delete_term(Term::from_field_text(self.fields.email_db_id, &email.id));
+        let term = Term::from_field_text(self.fields.email_db_id, &email.id);
+        self.writer()?.delete_term(term);
 
         let mut document = doc!(
             self.fields.email_db_id => email.id.clone(),
+            self.fields.email_key => email_key(&email.id),
             self.fields.account_type => account_type.to_ascii_lowercase(),
         );
 
@@ -328,6 +888,7 @@ impl EmailIndex {
             .filter(|value| !value.trim().is_empty())
         {
             document.add_text(self.fields.subject, subject);
+            document.add_text(self.fields.subject_words, subject);
         }
         if let Some(from_name) = email
             .from_name
@@ -335,6 +896,7 @@ impl EmailIndex {
             .filter(|value| !value.trim().is_empty())
         {
             document.add_text(self.fields.from_name, from_name);
+            document.add_text(self.fields.from_name_words, from_name);
         }
         if let Some(from_address) = email
             .from_address
@@ -343,12 +905,23 @@ impl EmailIndex {
         {
             document.add_text(self.fields.from_address, from_address);
         }
+        if let Some(from_domain) = email.from_domain() {
+            document.add_text(self.fields.from_domain, from_domain);
+        }
+        if let Some(conversation_id) = email
+            .conversation_id
+            .as_deref()
+            .filter(|value| !value.trim().is_empty())
+        {
+            document.add_text(self.fields.conversation_id, conversation_id);
+        }
         if let Some(body_text) = email
             .body_text
             .as_deref()
             .filter(|value| !value.trim().is_empty())
         {
             document.add_text(self.fields.body_text, body_text);
+            document.add_text(self.fields.body_words, body_text);
         }
         if let Some(folder) = email
             .folder
@@ -357,22 +930,79 @@ impl EmailIndex {
         {
             document.add_text(self.fields.folder, folder);
         }
+        if let Some(account_id) = email
+            .account_id
+            .as_deref()
+            .filter(|value| !value.trim().is_empty())
+        {
+            document.add_text(self.fields.account_id, account_id);
+        }
+        document.add_bool(self.fields.is_read, email.is_read.unwrap_or(false));
+        for key in INDEXED_META_KEYS {
+            if let Some(value) = email.meta_text(key) {
+                document.add_text(self.fields.meta, format!("{key}={value}"));
+            }
+        }
+
+        for (field, addresses) in [
+            (self.fields.to_addresses, &email.to_addresses),
+            (self.fields.cc_addresses, &email.cc_addresses),
+            (self.fields.bcc_addresses, &email.bcc_addresses),
+        ] {
+            for address in addresses.iter().map(|value| value.trim()) {
+                if !address.is_empty() {
+                    document.add_text(field, address.to_lowercase());
+                }
+            }
+        }
 
         let received_at = parse_timestamp(&email.received_at)?;
         document.add_date(self.fields.received_at, received_at);
 
-        self.writer.add_document(document)?;
+        self.writer()?.add_document(document)?;
 
         Ok(())
     }
 
+    /// The index writer, opened with the configured memory budget and merge
+    /// policy on first use.
+    fn writer(&mut self) -> Result<&mut IndexWriter, IndexError> {
+        let writer = match self.writer.take() {
+            Some(writer) => writer,
+            None => {
+                let writer = self.index.writer(self.settings.writer_memory_bytes)?;
+                writer.set_merge_policy(self.settings.merge_policy.to_policy());
+                writer
+            }
+        };
+        Ok(self.writer.insert(writer))
+    }
+
     fn commit_and_reload(&mut self) -> Result<(), IndexError> {
-        self.writer.commit()?;
+        if let Some(writer) = &mut self.writer {
+            writer.commit()?;
+        }
         self.reader.reload()?;
         Ok(())
     }
 }
 
+/// Orders hits: higher scores first, ties by ascending `email_key`.
+type Rank = (Score, Reverse<u64>);
+
+fn rank_of(score: Score, email_id: &str) -> Rank {
+    (score, Reverse(email_key(email_id)))
+}
+
+/// The `email_key` of an email id: the first eight bytes of its SHA-256,
+/// stable across processes and versions.
+fn email_key(email_id: &str) -> u64 {
+    let hash = digest(&SHA256, email_id.as_bytes());
+    let mut key = [0u8; 8];
+    key.copy_from_slice(&hash.as_ref()[..8]);
+    u64::from_be_bytes(key)
+}
+
 fn parse_timestamp(raw: &str) -> Result<TantivyDateTime, IndexError> {
     if let Ok(parsed) = ChronoDateTime::parse_from_rfc3339(raw) {
         return Ok(TantivyDateTime::from_timestamp_micros(
@@ -421,13 +1051,46 @@ fn directory_size(path: &Path) -> Result<u64, IndexError> {
     Ok(total)
 }
 
+/// Reads every file under `path` to the end, returning the file count and
+/// bytes read. Lock files are empty and skipped.
+fn read_directory(path: &Path) -> Result<(usize, u64), IndexError> {
+    let mut files = 0usize;
+    let mut bytes = 0u64;
+
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+
+        if metadata.is_dir() {
+            let (nested_files, nested_bytes) = read_directory(&entry.path())?;
+            files += nested_files;
+            bytes = bytes.saturating_add(nested_bytes);
+        } else if !entry.file_name().to_string_lossy().ends_with(".lock") {
+            let mut file = std::fs::File::open(entry.path())?;
+            bytes = bytes.saturating_add(std::io::copy(&mut file, &mut std::io::sink())?);
+            files += 1;
+        }
+    }
+
+    Ok((files, bytes))
+}
+
+fn elapsed_ms(started: Instant) -> u64 {
+    u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX)
+}
+
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
 
-    use super::{EmailIndex, SearchFilters};
+    use super::{
+        Analyzer, EmailIndex, IndexError, IndexSettings, MergePolicySetting, SearchFilters,
+        TextAnalysis, WARM_QUERIES,
+    };
     use crate::db::models::{Account, AccountType, Email};
     use crate::db::Database;
+    use tantivy::doc;
+    use tantivy::query::AllQuery;
     use uuid::Uuid;
 
     fn temp_root() -> PathBuf {
@@ -451,7 +1114,6 @@ mod tests {
 
     fn sample_email() -> Email {
         Email {
-            id: "msg-1".to_string(),
             internet_message_id: Some("<msg-1@example.com>".to_string()),
             conversation_id: Some("thread-1".to_string()),
             account_id: Some("acc-1".to_string()),
@@ -459,10 +1121,7 @@ mod tests {
             from_address: Some("sender@example.com".to_string()),
             from_name: Some("Sender".to_string()),
             to_addresses: vec!["owner@example.com".to_string()],
-            cc_addresses: vec![],
-            bcc_addresses: vec![],
             body_text: Some("Let us meet tomorrow for kickoff".to_string()),
-            body_html: None,
             body_preview: Some("Let us meet tomorrow".to_string()),
             received_at: "2026-02-01T12:00:00Z".to_string(),
             sent_at: Some("2026-02-01T11:59:00Z".to_string()),
@@ -471,9 +1130,7 @@ mod tests {
             has_attachments: Some(false),
             folder: Some("inbox".to_string()),
             categories: vec!["work".to_string()],
-            flag_status: None,
-            web_link: None,
-            metadata: None,
+            ..Email::fixture("msg-1")
         }
     }
 
@@ -504,6 +1161,29 @@ mod tests {
         let _ = std::fs::remove_dir_all(root);
     }
 
+    #[test]
+    fn readers_share_an_index_while_one_handle_writes() {
+        let root = temp_root();
+        let index_path = root.join("index");
+        let mut writer = EmailIndex::open(&index_path).expect("open writing index");
+        writer
+            .add_email(&sample_email(), "professional")
+            .expect("add email to index");
+
+        let reader = EmailIndex::open(&index_path).expect("open second index while writing");
+        let hits = reader
+            .search("kickoff", &SearchFilters::default(), 10)
+            .expect("search from second handle");
+        assert_eq!(hits.len(), 1);
+        let mut second_writer = EmailIndex::open(&index_path).expect("open third index");
+        assert!(
+            second_writer.delete_email("msg-1").is_err(),
+            "only one handle may hold the writer lock"
+        );
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
     #[test]
     fn reindex_rebuilds_from_database_source_of_truth() {
         let root = temp_root();
@@ -534,6 +1214,338 @@ mod tests {
         let stats = index.get_stats().expect("index stats");
         assert_eq!(stats.doc_count, 1);
         assert!(stats.index_size_bytes > 0);
+        assert!(stats.segment_count >= 1);
+        assert_eq!(stats.deleted_doc_count, 0);
+        assert!(stats.last_commit_opstamp > 0);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn verify_reports_and_repairs_drift_from_database() {
+        let root = temp_root();
+        let db = Database::open(&root.join("ess.db")).expect("open db");
+        db.insert_account(&sample_account())
+            .expect("insert account");
+        let mut index = EmailIndex::open(&root.join("index")).expect("open index");
+        let mut missing = sample_email();
+        missing.id = "msg-2".to_string();
+        missing.internet_message_id = Some("<msg-2@example.com>".to_string());
+        let mut orphan = sample_email();
+        orphan.id = "gone".to_string();
+        for email in [sample_email(), missing.clone()] {
+            db.insert_email(&email).expect("insert email");
+        }
+        index
+            .add_email(&sample_email(), "professional")
+            .expect("add email to index");
+        index
+            .add_email(&orphan, "professional")
+            .expect("add orphan to index");
+        let duplicate = doc!(index.fields.email_db_id => "msg-1");
+        index
+            .writer()
+            .expect("index writer")
+            .add_document(duplicate)
+            .expect("add duplicate document");
+
+        let report = index.verify(&db, false).expect("verify");
+        assert_eq!(report.emails, 2);
+        assert_eq!(report.documents, 3);
+        assert_eq!(report.missing, vec!["msg-2"]);
+        assert_eq!(report.orphaned, vec!["gone"]);
+        assert_eq!(report.duplicated, vec!["msg-1"]);
+        assert!(!report.repaired);
+
+        let report = index.verify(&db, true).expect("verify and repair");
+        assert!(report.repaired);
+        let report = index.verify(&db, false).expect("verify again");
+        assert!(report.is_consistent(), "{report:?}");
+        assert_eq!(report.documents, 2);
+        assert_eq!(
+            index
+                .search("kickoff", &SearchFilters::default(), 10)
+                .expect("search repaired index")
+                .len(),
+            2
+        );
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn changed_analyzer_requires_a_rebuild() {
+        let root = temp_root();
+        let index_path = root.join("index");
+        let mut index = EmailIndex::open(&index_path).expect("open index");
+        let mut email = sample_email();
+        email.subject = Some("Réunion au café: weekly meeting".to_string());
+        index
+            .add_email(&email, "professional")
+            .expect("add email to index");
+        assert_eq!(
+            index
+                .search("reunion cafe", &SearchFilters::default(), 10)
+                .expect("search folded")
+                .len(),
+            1
+        );
+        for query in ["meetings", "meeti", "week"] {
+            assert_eq!(
+                index
+                    .search(query, &SearchFilters::default(), 10)
+                    .expect("search stemmed")
+                    .len(),
+                1,
+                "{query}"
+            );
+        }
+        drop(index);
+
+        let cjk = IndexSettings {
+            analysis: TextAnalysis {
+                analyzer: Analyzer::Cjk,
+                ..TextAnalysis::default()
+            },
+            ..IndexSettings::default()
+        };
+        assert!(matches!(
+            EmailIndex::open_with_settings(&index_path, &cjk),
+            Err(IndexError::AnalysisChanged { .. })
+        ));
+        EmailIndex::open(&index_path).expect("reopen with the same analyzer");
+
+        std::fs::remove_file(index_path.join(super::ANALYSIS_STAMP_FILE)).expect("remove stamp");
+        assert!(matches!(
+            EmailIndex::open(&index_path),
+            Err(IndexError::AnalysisChanged { .. })
+        ));
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn recipient_filter_matches_to_cc_and_bcc_exactly() {
+        let root = temp_root();
+        let mut index = EmailIndex::open(&root.join("index")).expect("open index");
+        let mut email = sample_email();
+        email.cc_addresses = vec!["Carol@Example.com".to_string()];
+        email.bcc_addresses = vec!["audit@example.com".to_string()];
+        index
+            .add_email(&email, "professional")
+            .expect("add email to index");
+
+        let hits = |to: &str| {
+            index
+                .search(
+                    "",
+                    &SearchFilters {
+                        to: Some(to.to_string()),
+                        ..SearchFilters::default()
+                    },
+                    10,
+                )
+                .expect("search by recipient")
+                .len()
+        };
+        assert_eq!(hits("owner@example.com"), 1);
+        assert_eq!(hits("carol@example.COM"), 1);
+        assert_eq!(hits("audit@example.com"), 1);
+        assert_eq!(hits("carol@example"), 0);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn account_and_unread_filters_run_in_the_index() {
+        let root = temp_root();
+        let mut index = EmailIndex::open(&root.join("index")).expect("open index");
+        let mut read = sample_email();
+        read.id = "msg-2".to_string();
+        read.internet_message_id = Some("<msg-2@example.com>".to_string());
+        read.account_id = Some("acc-2".to_string());
+        read.is_read = Some(true);
+        let mut unknown = sample_email();
+        unknown.id = "msg-3".to_string();
+        unknown.internet_message_id = Some("<msg-3@example.com>".to_string());
+        unknown.is_read = None;
+        for email in [sample_email(), read, unknown] {
+            index
+                .add_email(&email, "professional")
+                .expect("add email to index");
+        }
+
+        let ids = |filters: SearchFilters| {
+            let mut ids = index
+                .search("kickoff", &filters, 10)
+                .expect("search with filters")
+                .into_iter()
+                .map(|hit| hit.email_db_id)
+                .collect::<Vec<_>>();
+            ids.sort();
+            ids
+        };
+        assert_eq!(
+            ids(SearchFilters {
+                account_id: Some("acc-2".to_string()),
+                ..SearchFilters::default()
+            }),
+            vec!["msg-2"]
+        );
+        assert_eq!(
+            ids(SearchFilters {
+                account_id: Some("acc-1".to_string()),
+                unread_only: true,
+                ..SearchFilters::default()
+            }),
+            vec!["msg-1", "msg-3"]
+        );
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn update_email_replaces_the_document_on_commit() {
+        let root = temp_root();
+        let mut index = EmailIndex::open(&root.join("index")).expect("open index");
+        let mut email = sample_email();
+        index
+            .add_email(&email, "professional")
+            .expect("add email to index");
+        let unread = |index: &EmailIndex| {
+            index
+                .search(
+                    "kickoff",
+                    &SearchFilters {
+                        unread_only: true,
+                        ..SearchFilters::default()
+                    },
+                    10,
+                )
+                .expect("search unread")
+                .len()
+        };
+        assert_eq!(unread(&index), 1);
+
+        email.is_read = Some(true);
+        index
+            .update_email(&email, "professional")
+            .expect("update email in index");
+        assert_eq!(unread(&index), 1, "buffered until commit");
+        index.commit().expect("commit");
+        assert_eq!(unread(&index), 0);
+        assert_eq!(index.get_stats().expect("index stats").doc_count, 1);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn optimize_merges_segments_and_purges_deletes() {
+        let root = temp_root();
+        let mut index = EmailIndex::open_with_settings(
+            &root.join("index"),
+            &IndexSettings {
+                merge_policy: MergePolicySetting::None,
+                ..IndexSettings::default()
+            },
+        )
+        .expect("open index");
+        let mut email = sample_email();
+        let mut other = sample_email();
+        other.id = "msg-2".to_string();
+        other.internet_message_id = Some("<msg-2@example.com>".to_string());
+        for item in [&email, &other] {
+            index
+                .add_email_buffered(item, "professional")
+                .expect("buffer email");
+        }
+        index.commit().expect("commit batch");
+        email.is_read = Some(true);
+        index
+            .add_email(&email, "professional")
+            .expect("add email to index");
+
+        let report = index.optimize().expect("optimize index");
+        assert_eq!(report.segments_before, 2);
+        assert_eq!(report.segments_after, 1);
+        assert_eq!(report.deleted_docs_purged, 1);
+        assert_eq!(index.get_stats().expect("index stats").doc_count, 2);
+        let hits = index
+            .search("kickoff", &SearchFilters::default(), 10)
+            .expect("search after optimize");
+        assert_eq!(hits.len(), 2);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn cursors_survive_commits_and_merges() {
+        let root = temp_root();
+        let mut index = EmailIndex::open_with_settings(
+            &root.join("index"),
+            &IndexSettings {
+                merge_policy: MergePolicySetting::None,
+                ..IndexSettings::default()
+            },
+        )
+        .expect("open index");
+        let ids = ["msg-a", "msg-b", "msg-c", "msg-d"];
+        for id in ids {
+            let mut email = sample_email();
+            email.id = id.to_string();
+            index
+                .add_email(&email, "professional")
+                .expect("add email to index");
+        }
+        // Every document scores the same, so only the tie-break orders them.
+        let page = |index: &EmailIndex, limit, after| {
+            index
+                .search_with_query_after(
+                    Box::new(AllQuery),
+                    &SearchFilters::default(),
+                    limit,
+                    after,
+                )
+                .expect("search page")
+        };
+        let first = page(&index, 2, None);
+        assert_eq!(first.len(), 2);
+
+        let mut late = sample_email();
+        late.id = "msg-late".to_string();
+        index
+            .add_email(&late, "professional")
+            .expect("add late email");
+        assert_eq!(index.optimize().expect("optimize").segments_after, 1);
+
+        let rest = page(&index, 10, Some(&first[1].position));
+        let mut seen = first
+            .iter()
+            .chain(&rest)
+            .map(|hit| hit.email_db_id.as_str())
+            .filter(|id| *id != "msg-late")
+            .collect::<Vec<_>>();
+        seen.sort_unstable();
+        assert_eq!(seen, ids);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn warm_reads_segments_and_runs_queries() {
+        let root = temp_root();
+        let mut index = EmailIndex::open(&root.join("index")).expect("open index");
+        index
+            .add_email(&sample_email(), "professional")
+            .expect("add email to index");
+
+        let report = index.warm(WARM_QUERIES).expect("warm index");
+
+        assert_eq!(report.segments, 1);
+        assert!(report.files > 0);
+        assert!(report.bytes_read > 0);
+        assert_eq!(report.queries.len(), WARM_QUERIES.len());
+        assert_eq!(report.queries[0].hits, 1);
 
         let _ = std::fs::remove_dir_all(root);
     }
@@ -1,8 +1,15 @@
+use std::fmt;
+use std::str::FromStr;
+
 use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
 use tantivy::schema::{
-    Field, IndexRecordOption, Schema, TextFieldIndexing, TextOptions, INDEXED, STORED, STRING,
+    Field, IndexRecordOption, Schema, TextFieldIndexing, TextOptions, FAST, INDEXED, STORED, STRING,
+};
+use tantivy::tokenizer::{
+    AsciiFoldingFilter, Language, LowerCaser, PreTokenizedStream, PreTokenizedString,
+    SimpleTokenizer, Stemmer, StopWordFilter, TextAnalyzer, Token, TokenStream, Tokenizer,
 };
-use tantivy::tokenizer::{LowerCaser, NgramTokenizer, TextAnalyzer};
 use tantivy::Index;
 
 pub const SUBJECT_BOOST: f32 = 5.0;
@@ -10,17 +17,184 @@ pub const FROM_NAME_BOOST: f32 = 3.0;
 pub const BODY_BOOST: f32 = 1.0;
 
 pub const EDGE_NGRAM_TOKENIZER: &str = "edge_ngram";
+pub const WORD_STEM_TOKENIZER: &str = "word_stem";
+
+/// Bumped whenever the fields or the text analysis change in a way that
+/// needs existing indexes rebuilt. Version 2 added ASCII folding and the
+/// analyzer setting; version 3 stems words (and optionally drops
+/// stopwords) before n-gramming; version 4 added `from_domain`, version 5
+/// `conversation_id`, version 6 `meta` and version 7 `email_key`; version 8
+/// n-grams words unstemmed and keeps their stems in the `*_words` fields.
+pub const SCHEMA_VERSION: u32 = 8;
+
+const MIN_GRAM: usize = 2;
+const MAX_GRAM: usize = 20;
+
+/// How the subject, sender and body fields are split into terms, from
+/// `index.analyzer` in config.toml. Both lowercase and fold accents
+/// (`café` matches `cafe`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Analyzer {
+    /// Every 2 to 20 character substring.
+    #[default]
+    Standard,
+    /// `Standard` plus each Chinese, Japanese or Korean character on its
+    /// own, so one-character words are searchable.
+    Cjk,
+}
+
+impl Analyzer {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Standard => "standard",
+            Self::Cjk => "cjk",
+        }
+    }
+}
+
+impl fmt::Display for Analyzer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Analyzer {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "standard" => Ok(Self::Standard),
+            "cjk" => Ok(Self::Cjk),
+            other => Err(format!(
+                "unknown index.analyzer '{other}' (expected standard or cjk)"
+            )),
+        }
+    }
+}
+
+/// Everything that decides which terms a text field produces; an index
+/// built with different settings must be rebuilt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextAnalysis {
+    pub analyzer: Analyzer,
+    /// Also indexes each word's stem, so `meetings` matches `meeting`;
+    /// `None` turns it off. The n-grams are of the unstemmed words, so a
+    /// partial word like `meeti` still matches.
+    pub stemming: Option<Language>,
+    /// Drops the language's common words (`the`, `and`, ...); off by
+    /// default.
+    pub stopwords: Option<Language>,
+}
+
+impl Default for TextAnalysis {
+    fn default() -> Self {
+        Self {
+            analyzer: Analyzer::default(),
+            stemming: Some(Language::English),
+            stopwords: None,
+        }
+    }
+}
+
+impl fmt::Display for TextAnalysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let language = |language: Option<Language>| language.map_or("none", language_name);
+        write!(
+            f,
+            "analyzer {}, stemming {}, stopwords {}",
+            self.analyzer,
+            language(self.stemming),
+            language(self.stopwords)
+        )
+    }
+}
+
+const LANGUAGES: &[(&str, Language)] = &[
+    ("arabic", Language::Arabic),
+    ("danish", Language::Danish),
+    ("dutch", Language::Dutch),
+    ("english", Language::English),
+    ("finnish", Language::Finnish),
+    ("french", Language::French),
+    ("german", Language::German),
+    ("greek", Language::Greek),
+    ("hungarian", Language::Hungarian),
+    ("italian", Language::Italian),
+    ("norwegian", Language::Norwegian),
+    ("portuguese", Language::Portuguese),
+    ("romanian", Language::Romanian),
+    ("russian", Language::Russian),
+    ("spanish", Language::Spanish),
+    ("swedish", Language::Swedish),
+    ("tamil", Language::Tamil),
+    ("turkish", Language::Turkish),
+];
+
+pub fn language_name(language: Language) -> &'static str {
+    LANGUAGES
+        .iter()
+        .find(|(_, known)| *known == language)
+        .map_or("unknown", |(name, _)| name)
+}
+
+/// Parses `index.stemming` or `index.stopwords`: a lowercase language name
+/// or `none`.
+pub fn parse_language_setting(key: &str, value: &str) -> Result<Option<Language>, String> {
+    let value = value.trim();
+    if value == "none" {
+        return Ok(None);
+    }
+    let language = LANGUAGES
+        .iter()
+        .find(|(name, _)| *name == value)
+        .map(|(_, language)| *language)
+        .ok_or_else(|| format!("unknown index.{key} language '{value}' (or 'none')"))?;
+    if key == "stopwords" && StopWordFilter::new(language).is_none() {
+        return Err(format!("no stopword list for '{value}'"));
+    }
+    Ok(Some(language))
+}
+
+/// The stemmed whole-word counterparts of `subject`, `from_name` and
+/// `body_text`.
+pub const WORD_FIELDS: [&str; 3] = ["subject_words", "from_name_words", "body_words"];
+
+/// Recipient fields. Each holds one untokenized, lowercased term per
+/// address, so an exact address is a single term lookup.
+pub const RECIPIENT_FIELDS: [&str; 3] = ["to_addresses", "cc_addresses", "bcc_addresses"];
 
 #[derive(Debug, Clone, Copy)]
 pub struct EmailSearchFields {
     pub subject: Field,
     pub from_name: Field,
     pub from_address: Field,
+    /// The sender's lowercased domain as one untokenized term.
+    pub from_domain: Field,
     pub body_text: Field,
+    /// Whole-word stems of `subject`, `from_name` and `body_text`, searched
+    /// next to their n-grams.
+    pub subject_words: Field,
+    pub from_name_words: Field,
+    pub body_words: Field,
     pub received_at: Field,
     pub account_type: Field,
     pub folder: Field,
     pub email_db_id: Field,
+    /// A fast fingerprint of `email_db_id` that orders hits of equal score,
+    /// so search cursors name a document rather than an index address.
+    pub email_key: Field,
+    /// Stored so hits can be grouped by thread before reading SQLite.
+    pub conversation_id: Field,
+    pub to_addresses: Field,
+    pub cc_addresses: Field,
+    pub bcc_addresses: Field,
+    pub account_id: Field,
+    /// Missing read state is indexed as unread, matching `--unread`.
+    pub is_read: Field,
+    /// One untokenized `key=value` term per metadata key listed in
+    /// `INDEXED_META_KEYS`.
+    pub meta: Field,
 }
 
 pub fn build_schema() -> Schema {
@@ -39,10 +213,28 @@ pub fn build_schema() -> Schema {
     schema.add_text_field("from_address", tokenized_text.clone());
     schema.add_text_field("body_text", tokenized_text);
 
+    let word_stems = TextOptions::default().set_indexing_options(
+        TextFieldIndexing::default()
+            .set_tokenizer(WORD_STEM_TOKENIZER)
+            .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+    );
+    for field in WORD_FIELDS {
+        schema.add_text_field(field, word_stems.clone());
+    }
+
     schema.add_date_field("received_at", INDEXED | STORED);
     schema.add_text_field("account_type", STRING | STORED);
     schema.add_text_field("folder", STRING | STORED);
     schema.add_text_field("email_db_id", STRING | STORED);
+    schema.add_u64_field("email_key", FAST);
+    schema.add_text_field("from_domain", STRING);
+    schema.add_text_field("conversation_id", STRING | STORED);
+    for field in RECIPIENT_FIELDS {
+        schema.add_text_field(field, STRING);
+    }
+    schema.add_text_field("account_id", STRING);
+    schema.add_bool_field("is_read", INDEXED);
+    schema.add_text_field("meta", STRING);
 
     schema.build()
 }
@@ -58,25 +250,136 @@ pub fn fields_from_schema(schema: &Schema) -> Result<EmailSearchFields> {
         subject: get("subject")?,
         from_name: get("from_name")?,
         from_address: get("from_address")?,
+        from_domain: get("from_domain")?,
         body_text: get("body_text")?,
+        subject_words: get("subject_words")?,
+        from_name_words: get("from_name_words")?,
+        body_words: get("body_words")?,
         received_at: get("received_at")?,
         account_type: get("account_type")?,
         folder: get("folder")?,
         email_db_id: get("email_db_id")?,
+        email_key: get("email_key")?,
+        conversation_id: get("conversation_id")?,
+        to_addresses: get("to_addresses")?,
+        cc_addresses: get("cc_addresses")?,
+        bcc_addresses: get("bcc_addresses")?,
+        account_id: get("account_id")?,
+        is_read: get("is_read")?,
+        meta: get("meta")?,
     })
 }
 
-pub fn ensure_edge_ngram_tokenizer(index: &mut Index) -> Result<()> {
-    let edge_ngrams = TextAnalyzer::builder(NgramTokenizer::new(2, 20, false)?)
-        .filter(LowerCaser)
-        .build();
+/// Registers the n-gram tokenizer of the text fields and the word-stem
+/// tokenizer of their `*_words` counterparts.
+pub fn ensure_edge_ngram_tokenizer(index: &mut Index, analysis: TextAnalysis) -> Result<()> {
+    let edge_ngrams = TextAnalyzer::from(WordNgramTokenizer {
+        words: word_analyzer(analysis, None)?,
+        cjk_characters: analysis.analyzer == Analyzer::Cjk,
+    });
+    let word_stems = word_analyzer(analysis, analysis.stemming)?;
 
-    index
-        .tokenizers()
-        .register(EDGE_NGRAM_TOKENIZER, edge_ngrams);
+    let tokenizers = index.tokenizers();
+    tokenizers.register(EDGE_NGRAM_TOKENIZER, edge_ngrams);
+    tokenizers.register(WORD_STEM_TOKENIZER, word_stems);
     Ok(())
 }
 
+/// Lowercased, accent-folded words without the configured stopwords,
+/// reduced to their stem in `stemming`.
+fn word_analyzer(analysis: TextAnalysis, stemming: Option<Language>) -> Result<TextAnalyzer> {
+    let mut words = TextAnalyzer::builder(SimpleTokenizer::default())
+        .filter(LowerCaser)
+        .filter(AsciiFoldingFilter)
+        .dynamic();
+    if let Some(language) = analysis.stopwords {
+        let stopwords = StopWordFilter::new(language)
+            .ok_or_else(|| anyhow!("no stopword list for {}", language_name(language)))?;
+        words = words.filter_dynamic(stopwords);
+    }
+    if let Some(language) = stemming {
+        words = words.filter_dynamic(Stemmer::new(language));
+    }
+    Ok(words.build())
+}
+
+/// Analyzes each word, joins the results with single spaces and emits
+/// every 2 to 20 character substring of that, plus, for the CJK analyzer,
+/// each CJK character on its own. Every token sits at position 0, so a
+/// query matches documents that contain all of its tokens.
+#[derive(Clone)]
+struct WordNgramTokenizer {
+    words: TextAnalyzer,
+    cjk_characters: bool,
+}
+
+impl Tokenizer for WordNgramTokenizer {
+    type TokenStream<'a> = PreTokenizedStream;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> Self::TokenStream<'a> {
+        let mut tokens = Vec::new();
+        // Analyzed text, one char at a time, with the byte span in `text`
+        // of the word each char came from.
+        let mut chars = Vec::new();
+        let mut words = self.words.token_stream(text);
+        while words.advance() {
+            let word = words.token();
+            let span = (word.offset_from, word.offset_to);
+            if !chars.is_empty() {
+                chars.push((' ', (word.offset_from, word.offset_from)));
+            }
+            for ch in word.text.chars() {
+                chars.push((ch, span));
+                if self.cjk_characters && is_cjk(ch) {
+                    tokens.push(Token {
+                        offset_from: span.0,
+                        offset_to: span.1,
+                        position: 0,
+                        text: ch.to_string(),
+                        position_length: 1,
+                    });
+                }
+            }
+        }
+
+        for start in 0..chars.len() {
+            for end in (start + MIN_GRAM)..=(start + MAX_GRAM).min(chars.len()) {
+                let (_, (offset_from, _)) = chars[start];
+                let (_, (_, offset_to)) = chars[end - 1];
+                tokens.push(Token {
+                    offset_from,
+                    offset_to,
+                    position: 0,
+                    text: chars[start..end].iter().map(|(ch, _)| ch).collect(),
+                    position_length: 1,
+                });
+            }
+        }
+        PreTokenizedStream::from(PreTokenizedString {
+            text: text.to_string(),
+            tokens,
+        })
+    }
+}
+
+/// Han ideographs and radicals, kana and Hangul.
+fn is_cjk(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{1100}'..='\u{11FF}'
+            | '\u{2E80}'..='\u{2FDF}'
+            | '\u{3040}'..='\u{30FF}'
+            | '\u{3130}'..='\u{318F}'
+            | '\u{31F0}'..='\u{31FF}'
+            | '\u{3400}'..='\u{4DBF}'
+            | '\u{4E00}'..='\u{9FFF}'
+            | '\u{AC00}'..='\u{D7AF}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{FF66}'..='\u{FF9F}'
+            | '\u{20000}'..='\u{2FA1F}'
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -88,11 +391,100 @@ mod tests {
         let _ = fields.subject;
         let _ = fields.from_name;
         let _ = fields.from_address;
+        let _ = fields.from_domain;
         let _ = fields.body_text;
+        let _ = fields.subject_words;
+        let _ = fields.from_name_words;
+        let _ = fields.body_words;
         let _ = fields.received_at;
         let _ = fields.account_type;
         let _ = fields.folder;
         let _ = fields.email_db_id;
+        let _ = fields.email_key;
+        let _ = fields.conversation_id;
+        let _ = fields.to_addresses;
+        let _ = fields.cc_addresses;
+        let _ = fields.bcc_addresses;
+        let _ = fields.account_id;
+        let _ = fields.is_read;
+        let _ = fields.meta;
+    }
+
+    fn terms_with(analysis: TextAnalysis, text: &str) -> Vec<String> {
+        tokens(EDGE_NGRAM_TOKENIZER, analysis, text)
+    }
+
+    fn tokens(tokenizer: &str, analysis: TextAnalysis, text: &str) -> Vec<String> {
+        let mut index = Index::create_in_ram(build_schema());
+        ensure_edge_ngram_tokenizer(&mut index, analysis).expect("register tokenizer");
+        let mut tokenizer = index
+            .tokenizers()
+            .get(tokenizer)
+            .expect("tokenizer registered");
+        let mut stream = tokenizer.token_stream(text);
+        let mut terms = Vec::new();
+        while stream.advance() {
+            terms.push(stream.token().text.clone());
+        }
+        terms
+    }
+
+    fn terms(analyzer: Analyzer, text: &str) -> Vec<String> {
+        terms_with(
+            TextAnalysis {
+                analyzer,
+                ..TextAnalysis::default()
+            },
+            text,
+        )
+    }
+
+    #[test]
+    fn stemming_and_stopwords_are_configurable() {
+        // The n-grams are of the word as written; its stem is a word term.
+        let grams = terms(Analyzer::Standard, "Meetings");
+        assert!(grams.contains(&"meetings".to_string()));
+        assert!(grams.contains(&"meeti".to_string()));
+        assert_eq!(
+            tokens(WORD_STEM_TOKENIZER, TextAnalysis::default(), "Meetings"),
+            ["meet"]
+        );
+
+        let plain = TextAnalysis {
+            stemming: None,
+            ..TextAnalysis::default()
+        };
+        assert_eq!(tokens(WORD_STEM_TOKENIZER, plain, "Meetings"), ["meetings"]);
+
+        let without_stopwords = TextAnalysis {
+            stopwords: Some(Language::English),
+            ..TextAnalysis::default()
+        };
+        assert_eq!(
+            terms_with(without_stopwords, "the an"),
+            Vec::<String>::new()
+        );
+        assert!(terms(Analyzer::Standard, "the").contains(&"the".to_string()));
+
+        assert_eq!(
+            parse_language_setting("stemming", "german"),
+            Ok(Some(Language::German))
+        );
+        assert_eq!(parse_language_setting("stemming", "none"), Ok(None));
+        assert!(parse_language_setting("stopwords", "tamil").is_err());
+    }
+
+    #[test]
+    fn analyzers_fold_accents_and_split_cjk_characters() {
+        assert!(terms(Analyzer::Standard, "Café").contains(&"cafe".to_string()));
+        assert!(!terms(Analyzer::Standard, "東京の猫").contains(&"猫".to_string()));
+
+        let cjk = terms(Analyzer::Cjk, "東京の猫 Résumé");
+        for term in ["東京", "猫", "東", "resum"] {
+            assert!(cjk.contains(&term.to_string()), "missing {term}: {cjk:?}");
+        }
+        assert_eq!("cjk".parse::<Analyzer>(), Ok(Analyzer::Cjk));
+        assert!("icu".parse::<Analyzer>().is_err());
     }
 
     #[test]
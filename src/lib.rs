@@ -1,6 +1,27 @@
+pub mod commitments;
+pub mod config;
 pub mod connectors;
+pub mod contacts;
+pub mod daemon;
 pub mod db;
+pub mod devtools;
+pub mod embeddings;
+pub mod env;
+#[cfg(any(test, feature = "fuzzing"))]
+#[doc(hidden)]
+pub mod fuzz;
+#[cfg(feature = "grpc")]
+pub mod grpc;
 pub mod indexer;
 pub mod mcp;
 pub mod output;
+pub mod paths;
+pub mod raw;
+pub mod replicate;
 pub mod search;
+pub mod search_provider;
+pub mod secrets;
+pub mod summaries;
+pub mod tags;
+pub mod trim;
+pub mod tui;
@@ -1,5 +1,11 @@
-use anyhow::Result;
-use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
+use ess::search::Facet;
 
 #[derive(Debug, Clone, Copy, ValueEnum)]
 enum Scope {
@@ -24,9 +30,23 @@ struct Cli {
     #[arg(long, global = true)]
     json: bool,
 
-    /// Filter account scope
-    #[arg(long, global = true, value_enum, default_value = "all")]
-    scope: Scope,
+    /// Filter account scope [default: all, or as set in config.toml]
+    #[arg(long, global = true, value_enum)]
+    scope: Option<Scope>,
+
+    /// Plain tables without ANSI colors (also set by the NO_COLOR variable)
+    #[arg(long, global = true)]
+    no_color: bool,
+
+    /// Run on the `ess sync --watch` daemon, failing when none is running
+    /// (search, list, show and thread use a running daemon, or the
+    /// [remote] url server, by default)
+    #[arg(long, global = true, conflicts_with = "no_connect")]
+    connect: bool,
+
+    /// Run in this process even when a daemon is running or [remote] url is set
+    #[arg(long, global = true)]
+    no_connect: bool,
 }
 
 #[derive(Debug, Subcommand)]
@@ -35,56 +55,392 @@ enum Commands {
     Search(SearchArgs),
     /// List emails with optional filters
     List(ListArgs),
+    /// Browse mail interactively: search box, result list and thread preview
+    Tui(TuiArgs),
     /// Show one email by ID
-    Show { id: String },
-    /// Show all messages in a thread
-    Thread { conversation_id: String },
+    Show {
+        id: String,
+        /// Output format; `json` is the same as --json
+        #[arg(long, value_enum)]
+        format: Option<DocumentFormat>,
+        /// Print the HTML body as a standalone page with scripts and remote images blocked
+        #[arg(long, conflicts_with = "format")]
+        html: bool,
+        /// Write that page to a temporary file and open it in the default browser
+        #[arg(long, conflicts_with_all = ["format", "html"])]
+        open: bool,
+        /// Print the original RFC 822 source, as `ess raw get` does
+        #[arg(long, conflicts_with_all = ["format", "html", "open"])]
+        raw: bool,
+        #[command(flatten)]
+        remote: RemoteArgs,
+    },
+    /// Show all messages in a thread, or repair threading with `merge`/`split`
+    Thread(ThreadArgs),
+    /// Summarize a thread with the LLM set by ESS_SUMMARY_URL; shown by `ess thread`
+    Summarize {
+        /// A conversation id, or the id of any email in it
+        id: String,
+        /// Write a new summary even if the stored one still covers every message
+        #[arg(long)]
+        refresh: bool,
+    },
+    /// Attach notes to threads; shown by `ess thread`, searchable with `note:`
+    Note {
+        #[command(subcommand)]
+        command: NoteCommands,
+    },
+    /// Follow attachments across a thread
+    Attachments {
+        #[command(subcommand)]
+        command: AttachmentsCommands,
+    },
+    /// Tags assigned at sync time by the rules in rules.toml; searchable with `--tag`
+    Tags {
+        #[command(subcommand)]
+        command: TagsCommands,
+    },
+    /// Mark an email read/unread or flagged/unflagged, locally and at the provider
+    Mark(MarkArgs),
+    /// Curate named collections of emails and export them as importable bundles
+    Collections {
+        #[command(subcommand)]
+        command: CollectionCommands,
+    },
+    /// Star an email locally; local stars are never sent to or changed by the provider
+    Star { id: String },
+    /// Remove a local star
+    Unstar { id: String },
+    /// List locally starred emails, most recently starred first
+    Starred {
+        #[arg(long, default_value_t = 25)]
+        limit: usize,
+    },
+    /// Move an email to the provider's archive and update local storage
+    Archive { id: String },
+    /// Move an email to the provider's trash and update local storage
+    Delete { id: String },
     /// Sync from configured accounts
     Sync(SyncArgs),
+    /// Control a running `ess sync --watch`
+    Daemon {
+        #[command(subcommand)]
+        command: DaemonCommands,
+    },
     /// Import from JSON archive path
     Import(ImportArgs),
+    /// Re-derive stored emails from their kept raw source or archive JSON after parser fixes
+    Reprocess(ReprocessArgs),
+    /// Find copies of the same message stored under different ids and remove all but the richest
+    Dedupe {
+        /// List the duplicates without removing anything (the default)
+        #[arg(long, conflicts_with = "apply")]
+        dry_run: bool,
+        /// Remove the extra copies from the database and the search index
+        #[arg(long)]
+        apply: bool,
+    },
+    /// Inspect, retry or export messages that failed to decode or map during sync or import
+    Quarantine {
+        #[command(subcommand)]
+        command: QuarantineCommands,
+    },
     /// List/search contacts
     Contacts(ContactsArgs),
+    /// List an account's Gmail labels or Outlook categories with their colors, as of the last sync
+    Labels {
+        #[arg(long)]
+        account: String,
+    },
+    /// Count stored emails per folder; --discovered shows what the last Graph folder discovery found
+    Folders {
+        /// Every folder the last sync of each Graph account discovered, with whether it was synced
+        #[arg(long)]
+        discovered: bool,
+        #[arg(long)]
+        account: Option<String>,
+    },
+    /// Show the mailing lists and newsletters you receive, with message volume and unsubscribe links
+    Lists,
+    /// List promises you made in sent mail ("I'll send it by Friday"), earliest due first
+    Commitments(CommitmentsArgs),
+    /// List meetings with their Zoom, Teams or Meet links, from invites and links in your mail
+    Meetings {
+        #[command(subcommand)]
+        command: MeetingsCommands,
+    },
+    /// Print matching contacts for mutt's `query_command` or aerc's `address-book-cmd`
+    MuttQuery(MuttQueryArgs),
+    /// Suggest addresses for a partly typed name or address, most written-to and recent first
+    CompleteAddress {
+        /// Start of, or any part of, a name or address
+        fragment: String,
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+    },
     /// Manage account configuration/state
     Accounts {
         #[command(subcommand)]
         command: AccountCommands,
     },
+    /// Inspect and validate configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+    /// List the environment variables ESS reads and whether each is set
+    Env,
+    /// Manage the token cache encryption key
+    Secrets {
+        #[command(subcommand)]
+        command: SecretsCommands,
+    },
+    /// Encrypt or decrypt the database at rest (builds with the sqlcipher feature)
+    Db {
+        #[command(subcommand)]
+        command: DbCommands,
+    },
     /// Show index and DB stats
-    Stats,
+    Stats(StatsArgs),
+    /// Mail volume over time, top senders, reply times and busiest hours
+    Analytics(AnalyticsArgs),
     /// Rebuild search index from SQLite source-of-truth
     Reindex,
-    /// Run MCP server over stdio
-    Mcp,
+    /// Pre-load the search index into memory so the next search starts fast
+    Warm,
+    /// Drop the bodies of old emails until the store fits [storage] max_size_mb
+    Trim {
+        /// List what would be trimmed without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Ship local changes to another machine's store, e.g. ssh://laptop
+    Replicate {
+        /// ssh://[user@]host[:port][/path], or a local store directory
+        #[arg(long, required_unless_present = "receive", conflicts_with = "receive")]
+        peer: Option<String>,
+        /// Ship every change again, not only those since the last run
+        #[arg(long, conflicts_with = "receive")]
+        full: bool,
+        /// Apply changes read from stdin; what --peer runs over ssh
+        #[arg(long, hide = true)]
+        receive: bool,
+        /// With --receive, the store directory to apply them to
+        #[arg(long, requires = "receive", hide = true)]
+        store: Option<PathBuf>,
+    },
+    /// Describe the JSON shapes of emails, contacts and accounts; --json prints JSON Schema
+    Schema {
+        /// Email, SearchResult, Snippet, ByteRange, Contact or Account [default: all]
+        #[arg(conflicts_with = "openapi")]
+        model: Option<String>,
+        /// Print an OpenAPI 3.1 document with the models as components
+        #[arg(long)]
+        openapi: bool,
+    },
+    /// Maintain the search index
+    Index {
+        #[command(subcommand)]
+        command: IndexCommands,
+    },
+    /// Read stored raw RFC 822 messages (kept when [storage] raw_messages is on)
+    Raw {
+        #[command(subcommand)]
+        command: RawCommands,
+    },
+    /// Compute embeddings for `search --semantic`; only emails without one are embedded
+    Embed(EmbedArgs),
+    /// Export email metadata or stats snapshots for analytics, or tags for notmuch
+    Export(ExportArgs),
+    /// Show ESS results in GNOME Shell's overview search (Linux, D-Bus)
+    SearchProvider {
+        #[command(subcommand)]
+        command: SearchProviderCommands,
+    },
+    /// Run the MCP server over stdio, or over HTTP with --http
+    Mcp {
+        /// Serve Streamable HTTP (POST /mcp) on this address, e.g. 127.0.0.1:8765
+        #[arg(long, value_name = "ADDR")]
+        http: Option<SocketAddr>,
+        /// Bearer token HTTP clients must send [default: ESS_MCP_TOKEN]
+        #[arg(long, requires = "http")]
+        token: Option<String>,
+    },
+    /// Serve streaming search and the change feed over gRPC (proto/ess/v1/ess.proto)
+    #[cfg(feature = "grpc")]
+    Grpc {
+        /// Address to listen on
+        #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:50051")]
+        listen: SocketAddr,
+        /// Bearer token clients must send [default: ESS_GRPC_TOKEN]
+        #[arg(long)]
+        token: Option<String>,
+    },
+    /// Tools for working on ESS itself
+    Devtools {
+        #[command(subcommand)]
+        command: DevtoolsCommands,
+    },
 }
 
 #[derive(Debug, Args)]
 struct SearchArgs {
+    /// Words, "quoted phrases", from:/to:/subject:, has:attachment,
+    /// is:unread|read|flagged, before:/after:YYYY-MM-DD, meta:key=value,
+    /// tag:name, AND/OR/NOT
     query: String,
     #[arg(long)]
     from: Option<String>,
+    /// Sender domain, e.g. example.com (exact; subdomains are not included)
+    #[arg(long)]
+    from_domain: Option<String>,
+    /// Received on or after: YYYY-MM-DD, yesterday, 7d, "2 weeks ago", "last monday"
     #[arg(long)]
     since: Option<String>,
+    /// Received on or before: YYYY-MM-DD, today, "end of last month"
     #[arg(long)]
     until: Option<String>,
     #[arg(long)]
     account: Option<String>,
     #[arg(long)]
     folder: Option<String>,
-    #[arg(long, default_value_t = 25)]
-    limit: usize,
+    /// Only emails with attachments
+    #[arg(long, default_value_t = false)]
+    has_attachment: bool,
+    /// Attachment file name glob, case-insensitive, e.g. "*budget*.xlsx"
+    #[arg(long, value_name = "GLOB")]
+    attachment_name: Option<String>,
+    /// Attachment type: pdf, spreadsheet, document, presentation, image, archive, or an extension
+    #[arg(long, value_name = "TYPE")]
+    attachment_type: Option<String>,
+    /// Only emails the tagging rules gave this tag (see `ess tags`)
+    #[arg(long)]
+    tag: Option<String>,
+    /// [default: 25, or defaults.search.limit in config.toml]
+    #[arg(long)]
+    limit: Option<usize>,
+    /// Also count all matches by sender, domain, folder and/or month (comma-separated)
+    #[arg(long, value_delimiter = ',')]
+    facets: Vec<Facet>,
+    /// Show professional and personal matches side by side (ignores --scope)
+    #[arg(long, default_value_t = false, conflicts_with = "facets")]
+    compare_scopes: bool,
+    /// Continue after a previous page; bare --cursor starts paging (JSON becomes {results, next_cursor})
+    #[arg(
+        long,
+        value_name = "TOKEN",
+        num_args = 0..=1,
+        default_missing_value = "",
+        conflicts_with_all = ["facets", "compare_scopes"]
+    )]
+    cursor: Option<String>,
+    /// Rank by meaning as well as keywords, using embeddings from `ess embed`
+    #[arg(
+        long,
+        default_value_t = false,
+        conflicts_with_all = ["facets", "compare_scopes", "cursor"]
+    )]
+    semantic: bool,
+    /// One row per thread: its best match, message count and participants
+    #[arg(
+        long,
+        default_value_t = false,
+        conflicts_with_all = ["facets", "compare_scopes", "cursor", "semantic"]
+    )]
+    group_by_thread: bool,
+    /// Output format; `json` is the same as --json, `raycast` prints Raycast/Alfred items
+    #[arg(long, value_enum)]
+    format: Option<SearchFormat>,
+    #[command(flatten)]
+    remote: RemoteArgs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SearchFormat {
+    Table,
+    Json,
+    /// Comma-separated values with a header row
+    Csv,
+    /// Tab-separated values with a header row
+    Tsv,
+    /// `{"items": [{title, subtitle, arg}]}` for Raycast and Alfred script filters
+    Raycast,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum TabularFormat {
+    Table,
+    Json,
+    /// Comma-separated values with a header row
+    Csv,
+    /// Tab-separated values with a header row
+    Tsv,
+    /// One compact JSON object per line, written as rows are read (list and contacts)
+    Ndjson,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum DocumentFormat {
+    Table,
+    Json,
+    /// Headers as a blockquote, the body converted to Markdown, attachments listed
+    Markdown,
 }
 
 #[derive(Debug, Args)]
 struct ListArgs {
     #[arg(long)]
     from: Option<String>,
+    /// Sender domain, e.g. example.com (exact; subdomains are not included)
+    #[arg(long)]
+    from_domain: Option<String>,
+    /// Received on or after: YYYY-MM-DD, yesterday, 7d, "2 weeks ago", "last monday"
+    #[arg(long)]
+    since: Option<String>,
+    /// Received on or before: YYYY-MM-DD, today, "end of last month"
+    #[arg(long)]
+    until: Option<String>,
     #[arg(long, default_value_t = false)]
     unread: bool,
     #[arg(long)]
     account: Option<String>,
-    #[arg(long, default_value_t = 50)]
-    limit: usize,
+    /// Only emails the tagging rules gave this tag (see `ess tags`)
+    #[arg(long)]
+    tag: Option<String>,
+    /// Only emails that look like they need a reply and have waited [needs_reply] after_days (default 2) without one
+    #[arg(long, default_value_t = false)]
+    needs_reply: bool,
+    /// [default: 50, or defaults.list.limit in config.toml]
+    #[arg(long)]
+    limit: Option<usize>,
+    /// Continue after a previous page; bare --cursor starts paging (JSON becomes {results, next_cursor})
+    #[arg(long, value_name = "TOKEN", num_args = 0..=1, default_missing_value = "")]
+    cursor: Option<String>,
+    /// Output format; `json` is the same as --json
+    #[arg(long, value_enum)]
+    format: Option<TabularFormat>,
+    #[command(flatten)]
+    remote: RemoteArgs,
+}
+
+/// Options of the commands that run on a daemon or `[remote] url` server.
+#[derive(Debug, Args)]
+struct RemoteArgs {
+    /// Give up on the [remote] url server, or on a running daemon and run locally, after this long (e.g. 10s, 2m) [default: 30s, 10s for a daemon]
+    #[arg(long, value_parser = parse_timeout_arg)]
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug, Args)]
+struct TuiArgs {
+    /// Initial query; empty lists the newest emails
+    #[arg(default_value = "")]
+    query: String,
+    #[arg(long)]
+    folder: Option<String>,
+    /// [default: 25, or defaults.search.limit in config.toml]
+    #[arg(long)]
+    limit: Option<usize>,
 }
 
 #[derive(Debug, Args)]
@@ -95,6 +451,160 @@ struct SyncArgs {
     full: bool,
     #[arg(long, default_value_t = false)]
     watch: bool,
+    /// Warm the search index (as `ess warm` does) before the first watch cycle
+    #[arg(long, default_value_t = false, requires = "watch")]
+    warm: bool,
+    /// Abort the sync after this long (e.g. 90s, 5m, 1h); work finished so far is kept
+    #[arg(long, value_parser = parse_timeout_arg)]
+    timeout: Option<Duration>,
+    /// Exit non-zero when the sync collects more than N errors (default 0, i.e. any error)
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "0",
+        conflicts_with = "watch"
+    )]
+    fail_on_errors: Option<usize>,
+    /// Record the provider API responses of this sync as an HTTP fixture in DIR, for connector tests
+    #[arg(long, value_name = "DIR", conflicts_with = "watch")]
+    record: Option<PathBuf>,
+    /// Also serve search, list, show and thread to remote clients over HTTP on ADDR, e.g. 0.0.0.0:8788
+    #[arg(long, value_name = "ADDR", requires = "watch")]
+    listen: Option<SocketAddr>,
+    /// Bearer token remote clients must send [default: ESS_REMOTE_TOKEN or the keychain]
+    #[arg(long, requires = "listen")]
+    token: Option<String>,
+}
+
+#[derive(Debug, Args)]
+struct EmbedArgs {
+    /// Drop the current model's embeddings and compute them all again
+    #[arg(long, default_value_t = false)]
+    rebuild: bool,
+    /// Embed at most this many emails, newest first
+    #[arg(long)]
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ExportTable {
+    Emails,
+    Stats,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    Parquet,
+    /// `notmuch dump` tags keyed by Message-ID, for `notmuch restore`
+    Notmuch,
+    /// One mboxrd file with every message
+    Mbox,
+    /// A directory of `.eml` files, one per message
+    Eml,
+    /// A directory of JSON archive files that `ess import` reads
+    Json,
+    /// One email JSON object per line; `--out -` streams to stdout
+    Ndjson,
+}
+
+#[derive(Debug, Args)]
+struct ExportArgs {
+    #[arg(long, value_enum, default_value = "emails")]
+    table: ExportTable,
+    #[arg(long, value_enum, default_value = "parquet")]
+    format: ExportFormat,
+    /// Output file, or directory for `eml` and `json`; `-` is stdout for `ndjson`
+    #[arg(long)]
+    out: PathBuf,
+    /// Export the hits of this search query instead of every stored email
+    #[arg(long)]
+    query: Option<String>,
+    /// Comma-separated Parquet column names [default: all email metadata, no bodies]
+    #[arg(long, value_delimiter = ',')]
+    columns: Vec<String>,
+    #[arg(long)]
+    account: Option<String>,
+    #[arg(long)]
+    folder: Option<String>,
+    /// Received on or after: YYYY-MM-DD, yesterday, 7d, "2 weeks ago", "last monday"
+    #[arg(long)]
+    since: Option<String>,
+    /// Received on or before: YYYY-MM-DD, today, "end of last month"
+    #[arg(long)]
+    until: Option<String>,
+}
+
+#[derive(Debug, Args)]
+struct StatsArgs {
+    /// Show growth over this window (e.g. 30d, 2w, 12h; bare numbers are days)
+    #[arg(long, value_parser = parse_trend_arg)]
+    trend: Option<Duration>,
+    /// Show API requests and bytes downloaded per account and day instead
+    /// (the last 7 days, or the --trend window)
+    #[arg(long)]
+    usage: bool,
+    /// Output format; `json` is the same as --json
+    #[arg(long, value_enum)]
+    format: Option<TabularFormat>,
+}
+
+#[derive(Debug, Args)]
+struct AnalyticsArgs {
+    /// Received on or after: YYYY-MM-DD, yesterday, 7d, "2 weeks ago", "last monday"
+    #[arg(long)]
+    since: Option<String>,
+    /// Received on or before: YYYY-MM-DD, today, "end of last month"
+    #[arg(long)]
+    until: Option<String>,
+    #[arg(long)]
+    account: Option<String>,
+    /// Group volume by week or month
+    #[arg(long, value_enum, default_value_t = AnalyticsPeriod::Month)]
+    by: AnalyticsPeriod,
+    /// Number of top senders to show
+    #[arg(long, default_value_t = 10)]
+    top: usize,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum AnalyticsPeriod {
+    Week,
+    Month,
+}
+
+#[derive(Debug, Args)]
+#[command(group(
+    ArgGroup::new("change")
+        .required(true)
+        .multiple(true)
+        .args(["read", "unread", "flag", "unflag"])
+))]
+struct MarkArgs {
+    id: String,
+    #[arg(long, conflicts_with = "unread")]
+    read: bool,
+    #[arg(long)]
+    unread: bool,
+    #[arg(long, conflicts_with = "unflag")]
+    flag: bool,
+    #[arg(long)]
+    unflag: bool,
+}
+
+impl MarkArgs {
+    fn flag_update(&self) -> ess::connectors::FlagUpdate {
+        let toggle = |on: bool, off: bool| match (on, off) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        };
+        ess::connectors::FlagUpdate {
+            read: toggle(self.read, self.unread),
+            flagged: toggle(self.flag, self.unflag),
+        }
+    }
 }
 
 #[derive(Debug, Args)]
@@ -105,11 +615,313 @@ struct ImportArgs {
 }
 
 #[derive(Debug, Args)]
+struct ReprocessArgs {
+    #[arg(long)]
+    account: Option<String>,
+    /// Received on or after: YYYY-MM-DD, yesterday, 7d, "2 weeks ago", "last monday"
+    #[arg(long)]
+    since: Option<String>,
+    /// Received on or before: YYYY-MM-DD, today, "end of last month"
+    #[arg(long)]
+    until: Option<String>,
+    /// Report what would change without writing anything
+    #[arg(long, default_value_t = false)]
+    dry_run: bool,
+}
+
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
 struct ContactsArgs {
     #[arg(long)]
     query: Option<String>,
     #[arg(long, default_value_t = false)]
     enrich: bool,
+    /// Output format; `json` is the same as --json
+    #[arg(long, value_enum)]
+    format: Option<TabularFormat>,
+    #[command(subcommand)]
+    command: Option<ContactsCommands>,
+}
+
+#[derive(Debug, Subcommand)]
+enum ContactsCommands {
+    /// Fill empty contact names, companies and phone numbers
+    Enrich {
+        /// From stored mail: the most common display name and the signatures of recent messages
+        #[arg(long)]
+        local: bool,
+    },
+}
+
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+struct CommitmentsArgs {
+    /// Leave out commitments marked done
+    #[arg(long)]
+    open: bool,
+    #[command(subcommand)]
+    command: Option<CommitmentsCommands>,
+}
+
+#[derive(Debug, Subcommand)]
+enum CommitmentsCommands {
+    /// Scan sent mail not scanned yet; `ess sync` does this after each cycle
+    Extract,
+    /// Mark a commitment done
+    Done { id: i64 },
+}
+
+#[derive(Debug, Subcommand)]
+enum MeetingsCommands {
+    /// Meetings starting today, then links from mail received today without an invite
+    Today,
+    /// Meetings starting in the next few days, today included
+    Upcoming {
+        #[arg(long, default_value_t = 7)]
+        days: u64,
+    },
+}
+
+#[derive(Debug, Args)]
+struct MuttQueryArgs {
+    /// Part of a name or address
+    query: String,
+    /// Leave out the status line mutt expects before the matches
+    #[arg(long, default_value_t = false)]
+    aerc: bool,
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommands {
+    /// Validate config file, env vars, credentials and paths; print the effective config
+    Check,
+}
+
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct ThreadArgs {
+    /// Conversation to show: a conversation id, an email id or a Message-ID
+    #[arg(required = true)]
+    conversation_id: Option<String>,
+    /// Output format; `json` is the same as --json
+    #[arg(long, value_enum)]
+    format: Option<DocumentFormat>,
+    #[command(flatten)]
+    remote: RemoteArgs,
+    #[command(subcommand)]
+    command: Option<ThreadCommands>,
+}
+
+#[derive(Debug, Subcommand)]
+enum ThreadCommands {
+    /// Move every message of thread B into thread A; later syncs keep them together
+    Merge {
+        /// Thread to keep
+        thread_a: String,
+        /// Thread to fold into A
+        thread_b: String,
+    },
+    /// Move one email out of its thread into a thread of its own
+    Split { email_id: String },
+}
+
+#[derive(Debug, Subcommand)]
+enum NoteCommands {
+    /// Add a note to a thread
+    Add {
+        conversation_id: String,
+        /// Note text
+        text: String,
+    },
+    /// List notes, for one thread or all of them
+    List { conversation_id: Option<String> },
+    /// Delete a note by its id
+    Remove { id: i64 },
+}
+
+#[derive(Debug, Subcommand)]
+enum TagsCommands {
+    /// Count emails per tag
+    List,
+    /// Show the tags of one email
+    Show { id: String },
+    /// Tag the emails `ess sync` has not tagged under the current rules yet
+    Apply {
+        /// Tag every email again, not just new, changed or untagged ones
+        #[arg(long)]
+        all: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum DaemonCommands {
+    /// Re-read config.toml, rules.toml and the account list (same as sending SIGHUP)
+    Reload,
+    /// Skip watch cycles for a while, e.g. on battery; a running cycle finishes first
+    Pause {
+        /// How long: seconds, or a number suffixed with `s`, `m` or `h`
+        #[arg(long = "for", value_parser = parse_pause_arg)]
+        duration: Duration,
+    },
+    /// End a pause early; the next cycle starts after the usual interval
+    Resume,
+}
+
+#[derive(Debug, Subcommand)]
+enum SearchProviderCommands {
+    /// Serve search requests on the session bus; D-Bus starts this on demand
+    Run,
+    /// Register the provider with GNOME Shell and D-Bus for this user
+    Install,
+    /// Remove the files `install` wrote
+    Uninstall,
+}
+
+#[derive(Debug, Subcommand)]
+enum IndexCommands {
+    /// Merge all segments and drop deleted documents to reclaim disk space
+    Optimize,
+    /// Compare indexed email ids with the database; exits non-zero on drift
+    Verify {
+        /// Index missing and duplicated emails and delete orphaned documents
+        #[arg(long)]
+        repair: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum AttachmentsCommands {
+    /// List every copy of a file sent in a thread, oldest first, numbered by contents
+    Versions {
+        /// A conversation id, or the id of any email in it
+        #[arg(long)]
+        thread: String,
+        /// The attachment's file name (case-insensitive)
+        name: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum RawCommands {
+    /// Print an email's original source, byte for byte
+    Get {
+        id: String,
+        /// Write to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum QuarantineCommands {
+    /// List quarantined messages, oldest first
+    List {
+        #[arg(long)]
+        account: Option<String>,
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+    /// Decode and map quarantined messages again, storing the ones that now succeed
+    #[command(group(ArgGroup::new("entries").required(true).args(["ids", "all"])))]
+    Retry {
+        /// Quarantine ids, as shown by `ess quarantine list`
+        ids: Vec<i64>,
+        /// Retry every quarantined message (of --account, when given)
+        #[arg(long)]
+        all: bool,
+        #[arg(long, requires = "all")]
+        account: Option<String>,
+    },
+    /// Write quarantined messages with their payloads as NDJSON
+    Export {
+        #[arg(long)]
+        account: Option<String>,
+        /// Write to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum DevtoolsCommands {
+    /// Fill the store with deterministic synthetic accounts, threads, contacts and folders
+    Seed {
+        /// Number of emails to generate
+        #[arg(long, default_value_t = 5000)]
+        emails: usize,
+        /// Number of accounts, alternating work and personal
+        #[arg(long, default_value_t = 3)]
+        accounts: usize,
+        /// Generator seed; the same seed always produces the same store
+        #[arg(long, default_value_t = 42)]
+        seed: u64,
+        /// Seed a store that already holds emails
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum CollectionCommands {
+    /// Add emails to a collection, creating it on first use
+    #[command(group(ArgGroup::new("emails").required(true).multiple(true).args(["ids", "search"])))]
+    Add {
+        name: String,
+        /// Email ids to add
+        ids: Vec<String>,
+        /// Add the top hits of this search (honours --scope)
+        #[arg(long, value_name = "QUERY")]
+        search: Option<String>,
+        /// How many search hits to add
+        #[arg(long, default_value_t = 25, requires = "search")]
+        limit: usize,
+    },
+    /// Remove emails from a collection
+    Remove {
+        name: String,
+        #[arg(required = true)]
+        ids: Vec<String>,
+    },
+    /// List collections, or the emails in one
+    List { name: Option<String> },
+    /// Write a collection's emails to a directory in the JSON archive format
+    Export { name: String, directory: String },
+    /// Delete a collection; its emails stay in the database
+    Delete { name: String },
+}
+
+#[derive(Debug, Subcommand)]
+enum SecretsCommands {
+    /// Re-encrypt cached tokens under ESS_TOKEN_CACHE_KEY, opening old entries with ESS_TOKEN_CACHE_KEY_PREVIOUS
+    RotateKey,
+    /// Print a new random key suitable for ESS_TOKEN_CACHE_KEY
+    GenerateKey,
+    /// Store an account credential in the OS keychain, reading the value from stdin
+    Set {
+        account_id: String,
+        #[arg(value_parser = ["client_id", "client_secret", "refresh_token"])]
+        key: String,
+    },
+    /// Remove an account credential from the OS keychain
+    Delete {
+        account_id: String,
+        #[arg(value_parser = ["client_id", "client_secret", "refresh_token"])]
+        key: String,
+    },
+    /// Move credentials out of account config into the OS keychain
+    Migrate {
+        /// Only this account [default: all]
+        #[arg(long)]
+        account: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum DbCommands {
+    /// Rewrite the database encrypted under ESS_DB_KEY or the keychain key
+    Encrypt,
+    /// Rewrite an encrypted database as a plain SQLite file
+    Decrypt,
 }
 
 #[derive(Debug, Subcommand)]
@@ -128,327 +940,4128 @@ enum AccountCommands {
     },
     /// Remove account configuration
     Remove { account_id: String },
+    /// Sync an account again after `disable`
+    Enable { account_id: String },
+    /// Stop syncing an account without removing it or its mail
+    Disable { account_id: String },
     /// Show account sync status
-    SyncStatus,
+    SyncStatus {
+        /// Also show each folder's sync totals, last run and last error
+        #[arg(long)]
+        folders: bool,
+    },
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::from_default_env()
-                .add_directive(tracing::Level::WARN.into()),
-        )
-        .with_target(false)
-        .with_writer(std::io::stderr)
-        .init();
+/// Parses `--timeout` values: bare seconds or a number suffixed with `s`, `m` or `h`.
+fn parse_timeout_arg(raw: &str) -> Result<Duration> {
+    let value = raw.trim();
+    let (digits, multiplier) = match value.char_indices().last() {
+        Some((idx, 's')) => (&value[..idx], 1),
+        Some((idx, 'm')) => (&value[..idx], 60),
+        Some((idx, 'h')) => (&value[..idx], 3600),
+        _ => (value, 1),
+    };
+    let amount = digits
+        .trim()
+        .parse::<u64>()
+        .map_err(|_| anyhow!("invalid timeout '{raw}', expected e.g. 30, 90s, 5m or 1h"))?;
+    if amount == 0 {
+        return Err(anyhow!("timeout must be greater than zero"));
+    }
+    let seconds = amount
+        .checked_mul(multiplier)
+        .ok_or_else(|| anyhow!("timeout '{raw}' is too long"))?;
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Parses `ess daemon pause --for`, in the units `--timeout` takes.
+fn parse_pause_arg(raw: &str) -> Result<Duration> {
+    parse_timeout_arg(raw).map_err(|_| anyhow!("invalid pause '{raw}', expected e.g. 30m or 2h"))
+}
+
+/// The longest `--trend` window, about a hundred years.
+const MAX_TREND_DAYS: u64 = 36_500;
+
+/// Parses `--trend` windows: bare days or a number suffixed with `h`, `d` or `w`.
+fn parse_trend_arg(raw: &str) -> Result<Duration> {
+    let value = raw.trim();
+    let (digits, multiplier) = match value.char_indices().last() {
+        Some((idx, 'h')) => (&value[..idx], 3600),
+        Some((idx, 'd')) => (&value[..idx], 86_400),
+        Some((idx, 'w')) => (&value[..idx], 7 * 86_400),
+        _ => (value, 86_400),
+    };
+    let amount = digits
+        .trim()
+        .parse::<u64>()
+        .map_err(|_| anyhow!("invalid trend window '{raw}', expected e.g. 30d, 2w or 12h"))?;
+    if amount == 0 {
+        return Err(anyhow!("trend window must be greater than zero"));
+    }
+    let seconds = amount
+        .checked_mul(multiplier)
+        .filter(|seconds| *seconds <= MAX_TREND_DAYS * 86_400)
+        .ok_or_else(|| {
+            anyhow!("trend window '{raw}' is too long, at most {MAX_TREND_DAYS} days")
+        })?;
+    Ok(Duration::from_secs(seconds))
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::from_default_env()
+                .add_directive(tracing::Level::WARN.into()),
+        )
+        .with_target(false)
+        .with_writer(std::io::stderr)
+        .init();
 
     let cli = Cli::parse();
-    commands::dispatch(cli).await
+    if cli.no_color {
+        ess::output::disable_color();
+    }
+    if let Err(error) = commands::migrate_legacy_home() {
+        eprintln!("Error: {error:?}");
+        return ExitCode::FAILURE;
+    }
+    match commands::run_on_daemon(&cli).await {
+        Ok(Some(code)) => return code,
+        Ok(None) => {}
+        Err(error) => {
+            eprintln!("Error: {error:?}");
+            return ExitCode::FAILURE;
+        }
+    }
+    match commands::dispatch(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {error:?}");
+            commands::exit_code_for(&error)
+        }
+    }
 }
 
 mod commands {
-    use std::path::Path;
+    use std::collections::{HashMap, HashSet};
+    use std::net::SocketAddr;
+    use std::ops::Deref;
+    use std::path::{Path, PathBuf};
+    use std::process::ExitCode;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
 
     use anyhow::{anyhow, Context, Result};
-    use chrono::NaiveDate;
+    use chrono::{DateTime, Local, NaiveDate, SecondsFormat, Utc};
+    use clap::Parser;
     use serde::Serialize;
+    use tokio::sync::Notify;
+
+    use std::io::{IsTerminal, Read, Write};
 
+    use ess::commitments::{self, CommitmentExtractor, CommitmentReport};
+    use ess::config::check::{self as config_check, CheckStatus, ConfigCheckReport};
+    use ess::config::{Config, StoreCap, SyncSchedule};
+    use ess::connectors::conditions::SyncConditions;
+    use ess::connectors::json_archive::archive_payload;
+    use ess::connectors::mime;
+    use ess::connectors::progress::StderrProgress;
+    use ess::connectors::quarantine;
+    use ess::connectors::recording::{FixtureRecorder, Upstream};
+    use ess::connectors::reprocess::{self, ReprocessOptions};
     use ess::connectors::{
-        EmailConnector, GmailApiConnector, GraphApiConnector, JsonArchiveConnector,
+        connectivity, EmailConnector, GmailApiConnector, GraphApiConnector, JsonArchiveConnector,
+        MessageAction, OfflineError, ProgressSink, ShutdownSignal, SyncReport,
     };
-    use ess::db::models::{Account, AccountType};
-    use ess::db::{Database, EmailSearchFilters};
-    use ess::indexer::EmailIndex;
-    use ess::output::{self, OutputFormat, SearchResultItem};
+    use ess::contacts;
+    use ess::daemon::{self, ClientResponse};
+    use ess::db::encryption::{self, Conversion};
+    use ess::db::followups::COMMITMENT_KIND;
+    use ess::db::meetings;
+    use ess::db::models::{normalize_domain, Account, AccountType};
+    use ess::db::{
+        AnalyticsFilters, AnalyticsInterval, Database, EmailKeyset, EmailSearchFilters,
+        StatsSnapshot, StatsTrend, SyncRun,
+    };
+    use ess::devtools::{self, SeedOptions};
+    use ess::embeddings::{self, embedding_text};
+    use ess::env;
+    use ess::indexer::{EmailIndex, IndexSettings, WARM_QUERIES};
+    use ess::mcp::state::ServerState;
+    use ess::mcp::HttpServerOptions;
+    use ess::output::csv::Delimiter;
+    use ess::output::markdown::MarkdownMessage;
+    use ess::output::{
+        self, export, schema, OutputFormat, ScopeComparisonItem, SearchResultItem, ThreadResultItem,
+    };
+    use ess::paths::{self, Layout};
+    use ess::raw::RawStore;
+    use ess::replicate::{self, Peer};
     use ess::search;
     use ess::search::filters::{EmailFilters, Scope as SearchScope};
+    use ess::search_provider;
+    use ess::secrets::keychain::{self, Keychain, SecretStore};
+    use ess::secrets::{self, TokenCacheKeys};
+    use ess::summaries;
+    use ess::tags::{self, TagReport, TagRules};
+    use ess::trim::{self, TrimReport};
+    use ess::tui::{self, TuiOptions};
+
+    use super::{
+        AccountCommands, AnalyticsPeriod, AttachmentsCommands, Cli, CollectionCommands, Commands,
+        CommitmentsCommands, ConfigCommands, ContactsCommands, DaemonCommands, DbCommands,
+        DevtoolsCommands, DocumentFormat, ExportFormat, ExportTable, IndexCommands,
+        MeetingsCommands, NoteCommands, QuarantineCommands, RawCommands, Scope,
+        SearchProviderCommands, SecretsCommands, TabularFormat, TagsCommands, ThreadCommands,
+    };
 
-    use super::{AccountCommands, Cli, Commands, Scope};
+    /// `--limit` for `ess search` and `ess list` when neither the flag nor
+    /// config.toml sets one.
+    const DEFAULT_SEARCH_LIMIT: usize = 25;
+    const DEFAULT_LIST_LIMIT: usize = 50;
+
+    /// Rows read per query while `ess list --format ndjson` streams.
+    const NDJSON_BATCH_ROWS: usize = 1_000;
+
+    /// Emails sent to the embedding model per request.
+    const EMBED_BATCH_SIZE: usize = 32;
+    /// Ids listed per problem by `ess index verify`; `--json` lists them all.
+    const VERIFY_IDS_SHOWN: usize = 10;
+    /// Days `ess stats --usage` covers without `--trend`, today included.
+    const DEFAULT_USAGE_DAYS: i64 = 7;
+
+    /// Exit code for a failed sync or remote operation when no endpoint is reachable.
+    const EXIT_OFFLINE: u8 = 3;
+
+    /// Exit code when a sync finished but collected more errors than
+    /// `--fail-on-errors` allows.
+    const EXIT_SYNC_ERRORS: u8 = 4;
+
+    /// Exit code after Ctrl-C stopped a sync (128 + SIGINT, as shells report it).
+    const EXIT_INTERRUPTED: u8 = 130;
+
+    pub fn exit_code_for(error: &anyhow::Error) -> ExitCode {
+        if error.is::<OfflineError>() {
+            ExitCode::from(EXIT_OFFLINE)
+        } else if error.is::<SyncInterrupted>() {
+            ExitCode::from(EXIT_INTERRUPTED)
+        } else if error.is::<SyncErrorsExceeded>() {
+            ExitCode::from(EXIT_SYNC_ERRORS)
+        } else {
+            ExitCode::FAILURE
+        }
+    }
 
     pub async fn dispatch(cli: Cli) -> Result<()> {
         match cli.command {
             Commands::Search(args) => handle_search(args, cli.scope, cli.json).await,
             Commands::List(args) => handle_list(args, cli.scope, cli.json).await,
-            Commands::Show { id } => handle_show(&id, cli.json).await,
-            Commands::Thread { conversation_id } => handle_thread(&conversation_id, cli.json).await,
-            Commands::Sync(args) => handle_sync(args).await,
+            Commands::Tui(args) => handle_tui(args, cli.scope),
+            Commands::Show {
+                id,
+                format,
+                html,
+                open,
+                raw,
+                ..
+            } => {
+                if raw {
+                    handle_raw(RawCommands::Get { id, output: None }, cli.json)
+                } else if html || open {
+                    handle_show_html(&id, open, cli.json)
+                } else {
+                    handle_show(&id, format, cli.json).await
+                }
+            }
+            Commands::Thread(args) => match (args.command, args.conversation_id) {
+                (Some(command), _) => handle_thread_command(command, cli.json),
+                (None, Some(conversation_id)) => {
+                    handle_thread(&conversation_id, args.format, cli.json).await
+                }
+                (None, None) => Err(anyhow!("missing conversation id")),
+            },
+            Commands::Summarize { id, refresh } => handle_summarize(&id, refresh, cli.json).await,
+            Commands::Note { command } => handle_note(command, cli.json),
+            Commands::Attachments { command } => handle_attachments(command, cli.json),
+            Commands::Tags { command } => handle_tags(command, cli.json).await,
+            Commands::Mark(args) => handle_mark(args, cli.json).await,
+            Commands::Collections { command } => {
+                handle_collection_command(command, cli.scope, cli.json)
+            }
+            Commands::Star { id } => handle_star(&id, true, cli.json),
+            Commands::Unstar { id } => handle_star(&id, false, cli.json),
+            Commands::Starred { limit } => handle_starred(limit, cli.json),
+            Commands::Archive { id } => {
+                handle_message_action(&id, MessageAction::Archive, cli.json).await
+            }
+            Commands::Delete { id } => {
+                handle_message_action(&id, MessageAction::Delete, cli.json).await
+            }
+            Commands::Sync(args) => handle_sync(args, cli.json).await,
+            Commands::Daemon { command } => handle_daemon(command, cli.json),
             Commands::Import(args) => handle_import(args, cli.json).await,
+            Commands::Reprocess(args) => handle_reprocess(args, cli.json),
+            Commands::Dedupe { apply, .. } => handle_dedupe(apply, cli.json),
+            Commands::Quarantine { command } => handle_quarantine(command, cli.json),
             Commands::Contacts(args) => handle_contacts(args, cli.json).await,
+            Commands::Labels { account } => handle_labels(&account, cli.json),
+            Commands::Folders {
+                discovered,
+                account,
+            } => handle_folders(discovered, account.as_deref(), cli.json),
+            Commands::Lists => handle_lists(cli.json),
+            Commands::Commitments(args) => handle_commitments(args, cli.json).await,
+            Commands::Meetings { command } => handle_meetings(command, cli.json),
+            Commands::MuttQuery(args) => handle_mutt_query(args),
+            Commands::CompleteAddress { fragment, limit } => {
+                handle_complete_address(&fragment, limit, cli.json)
+            }
             Commands::Accounts { command } => handle_accounts(command).await,
-            Commands::Stats => handle_stats(cli.json).await,
-            Commands::Reindex => handle_reindex().await,
-            Commands::Mcp => handle_mcp().await,
+            Commands::Config { command } => handle_config(command, cli.json).await,
+            Commands::Env => handle_env(cli.json),
+            Commands::Secrets { command } => handle_secrets(command, cli.json),
+            Commands::Db { command } => handle_db(command, cli.json),
+            Commands::Stats(args) => handle_stats(args, cli.json).await,
+            Commands::Analytics(args) => handle_analytics(args, cli.json),
+            Commands::Reindex => handle_reindex(cli.json).await,
+            Commands::Warm => handle_warm(cli.json),
+            Commands::Trim { dry_run } => handle_trim(dry_run, cli.json),
+            Commands::Replicate {
+                peer,
+                full,
+                receive,
+                store,
+            } => match receive {
+                true => handle_receive_replication(store.as_deref()).await,
+                false => handle_replicate(peer.as_deref().unwrap_or_default(), full, cli.json),
+            },
+            Commands::Schema { model, openapi } => {
+                handle_schema(model.as_deref(), openapi, cli.json)
+            }
+            Commands::Index { command } => handle_index_command(command, cli.json),
+            Commands::Raw { command } => handle_raw(command, cli.json),
+            Commands::Embed(args) => handle_embed(args, cli.json).await,
+            Commands::Export(args) => handle_export(args, cli.scope, cli.json),
+            Commands::SearchProvider { command } => handle_search_provider(command).await,
+            Commands::Mcp { http, token } => handle_mcp(http, token).await,
+            #[cfg(feature = "grpc")]
+            Commands::Grpc { listen, token } => handle_grpc(listen, token).await,
+            Commands::Devtools { command } => handle_devtools(command, cli.json),
         }
     }
 
-    async fn handle_search(args: super::SearchArgs, scope: Scope, json: bool) -> Result<()> {
-        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
-        let db = Database::open(&db_path)
-            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
-        let index = open_index_with_recovery(&db)?;
+    async fn handle_search(
+        args: super::SearchArgs,
+        scope: Option<Scope>,
+        json: bool,
+    ) -> Result<()> {
+        let open = || {
+            let db = open_local_db()?;
+            let index = Arc::new(open_index_with_recovery(&db)?);
+            Ok((db, index))
+        };
+        run_search(args, scope, json, open, &mut std::io::stdout().lock()).await
+    }
+
+    /// `ess search`, writing to `out`. The database and index come from
+    /// `open` once the arguments have been checked, so the daemon can run
+    /// it with the handles it keeps open.
+    async fn run_search<D: Deref<Target = Database>>(
+        args: super::SearchArgs,
+        scope: Option<Scope>,
+        json: bool,
+        open: impl FnOnce() -> Result<(D, Arc<EmailIndex>)>,
+        out: &mut dyn Write,
+    ) -> Result<()> {
+        let format = args.format.unwrap_or(if json {
+            super::SearchFormat::Json
+        } else {
+            super::SearchFormat::Table
+        });
+        let raycast = format == super::SearchFormat::Raycast;
+        let delimiter = match format {
+            super::SearchFormat::Csv => Some(Delimiter::Comma),
+            super::SearchFormat::Tsv => Some(Delimiter::Tab),
+            _ => None,
+        };
+        if (raycast || delimiter.is_some())
+            && (!args.facets.is_empty()
+                || args.compare_scopes
+                || args.group_by_thread
+                || args.cursor.is_some())
+        {
+            let name = match format {
+                super::SearchFormat::Csv => "csv",
+                super::SearchFormat::Tsv => "tsv",
+                _ => "raycast",
+            };
+            return Err(anyhow!(
+                "--format {name} cannot be combined with --facets, --compare-scopes, --group-by-thread or --cursor"
+            ));
+        }
+        let json = format == super::SearchFormat::Json;
+        let config = load_config()?;
+        let scope = effective_scope("search", scope, &config)?;
+        let limit = args
+            .limit
+            .or(config.default_limit("search"))
+            .unwrap_or(DEFAULT_SEARCH_LIMIT);
+        let (db, index) = open()?;
 
         let filters = EmailFilters {
             scope: map_scope(scope),
             from: args.from,
+            from_domain: args.from_domain,
             since: parse_date_arg("since", args.since)?,
             until: parse_date_arg("until", args.until)?,
             account: args.account,
             folder: args.folder,
-            limit: args.limit,
+            has_attachment: args.has_attachment,
+            attachment_name: args.attachment_name,
+            attachment_type: args.attachment_type,
+            tag: args.tag,
+            limit,
             ..EmailFilters::default()
         };
 
-        let results = search::search_emails(&index, &db, &args.query, &filters)?;
-        let formatted = output::format_search_results(
-            OutputFormat::from_json_flag(json),
-            &results
+        if args.semantic {
+            let embedder = embeddings::from_env().context("configure embedding model")?;
+            let mut items =
+                search::semantic_search(&index, &db, embedder.as_ref(), &args.query, &filters)
+                    .await?
+                    .into_iter()
+                    .map(|result| SearchResultItem {
+                        email: result.email,
+                        score: Some(result.score),
+                        snippet: result.snippet,
+                        preview: None,
+                    })
+                    .collect::<Vec<_>>();
+            output::attach_previews(&db, &mut items)?;
+            if let Some(delimiter) = delimiter {
+                write!(
+                    out,
+                    "{}",
+                    output::csv::format_search_results(&items, delimiter)
+                )?;
+                return Ok(());
+            }
+            let formatted = if raycast {
+                output::raycast::format_search_results(&items)?
+            } else {
+                output::format_search_results(OutputFormat::from_json_flag(json), &items)?
+            };
+            writeln!(out, "{formatted}")?;
+            return Ok(());
+        }
+
+        if args.group_by_thread {
+            let threads = search::search_threads(&index, &db, &args.query, &filters)?
                 .into_iter()
-                .map(|result| SearchResultItem {
-                    email: result.email,
-                    score: Some(result.score),
-                })
-                .collect::<Vec<_>>(),
+                .map(ThreadResultItem::from)
+                .collect::<Vec<_>>();
+            let formatted =
+                output::format_thread_results(OutputFormat::from_json_flag(json), &threads)?;
+            writeln!(out, "{formatted}")?;
+            return Ok(());
+        }
+
+        if args.compare_scopes {
+            let scopes = search::compare_scopes(
+                &index,
+                &db,
+                &args.query,
+                &filters,
+                search::compare::DEFAULT_COMPARE_SAMPLES,
+            )?
+            .into_iter()
+            .map(ScopeComparisonItem::from)
+            .collect::<Vec<_>>();
+            let formatted = output::format_scope_comparison(
+                OutputFormat::from_json_flag(json),
+                &args.query,
+                &scopes,
+            )?;
+            writeln!(out, "{formatted}")?;
+            return Ok(());
+        }
+
+        let cursor = parse_cursor_arg(args.cursor.as_deref())?;
+        let page = search::search_emails_page(
+            &index,
+            &db,
+            &args.query,
+            &filters,
+            cursor.clone().flatten(),
         )?;
-        println!("{formatted}");
+        let next_cursor = page.next_cursor.map(|cursor| cursor.to_string());
+        let mut items = page
+            .results
+            .into_iter()
+            .map(|result| SearchResultItem {
+                email: result.email,
+                score: Some(result.score),
+                snippet: result.snippet,
+                preview: None,
+            })
+            .collect::<Vec<_>>();
+        output::attach_previews(&db, &mut items)?;
+        if raycast {
+            writeln!(out, "{}", output::raycast::format_search_results(&items)?)?;
+            return Ok(());
+        }
+        if let Some(delimiter) = delimiter {
+            write!(
+                out,
+                "{}",
+                output::csv::format_search_results(&items, delimiter)
+            )?;
+            return Ok(());
+        }
+        let format = OutputFormat::from_json_flag(json);
+        let formatted = if args.facets.is_empty() {
+            if cursor.is_some() || format == OutputFormat::Table {
+                output::format_paged_search_results(format, &items, next_cursor.as_deref())?
+            } else {
+                output::format_search_results(format, &items)?
+            }
+        } else {
+            let facets = search::facet_counts(
+                &db,
+                &args.query,
+                &filters,
+                &args.facets,
+                search::facets::DEFAULT_FACET_LIMIT,
+            )?;
+            output::format_faceted_search_results(format, &items, &facets)?
+        };
+        writeln!(out, "{formatted}")?;
         Ok(())
     }
 
-    async fn handle_list(args: super::ListArgs, scope: Scope, json: bool) -> Result<()> {
-        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
-        let db = Database::open(&db_path)
-            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+    async fn handle_list(args: super::ListArgs, scope: Option<Scope>, json: bool) -> Result<()> {
+        run_list(
+            args,
+            scope,
+            json,
+            open_local_db,
+            &mut std::io::stdout().lock(),
+        )
+        .await
+    }
 
-        let mut emails = db.search_emails(EmailSearchFilters {
+    /// `ess list`, writing to `out`; see [`run_search`].
+    async fn run_list<D: Deref<Target = Database>>(
+        args: super::ListArgs,
+        scope: Option<Scope>,
+        json: bool,
+        open: impl FnOnce() -> Result<D>,
+        mut out: &mut dyn Write,
+    ) -> Result<()> {
+        let rendering = tabular_format(args.format, json);
+        if !matches!(rendering, Rendering::Formatted(_)) && args.cursor.is_some() {
+            return Err(anyhow!(
+                "--format csv, tsv and ndjson cannot be combined with --cursor"
+            ));
+        }
+        let config = load_config()?;
+        let scope = effective_scope("list", scope, &config)?;
+        let limit = args
+            .limit
+            .or(config.default_limit("list"))
+            .unwrap_or(DEFAULT_LIST_LIMIT);
+        let db = open()?;
+        let cursor = parse_cursor_arg(args.cursor.as_deref())?;
+        let filters = EmailSearchFilters {
             query: None,
             account_id: args.account,
             account_type: map_scope_to_account_type(scope),
             folder: None,
             from_address: args.from,
-            limit: args.limit,
+            from_domain: args.from_domain.as_deref().map(normalize_domain),
+            since: parse_date_arg("since", args.since)?,
+            until: parse_date_arg("until", args.until)?,
+            tag: args.tag,
+            needs_reply_before: args.needs_reply.then(|| {
+                (Utc::now() - config.needs_reply_wait()).to_rfc3339_opts(SecondsFormat::Secs, true)
+            }),
+            limit,
             offset: 0,
-        })?;
+            after: cursor
+                .clone()
+                .flatten()
+                .map(search::Cursor::into_recency)
+                .transpose()?,
+        };
+
+        if rendering == Rendering::Ndjson {
+            let mut remaining = limit;
+            let mut after = None;
+            while remaining > 0 {
+                let batch_limit = remaining.min(NDJSON_BATCH_ROWS);
+                let batch = db.search_emails(EmailSearchFilters {
+                    limit: batch_limit,
+                    after: after.take(),
+                    ..filters.clone()
+                })?;
+                let Some(last) = batch.last() else {
+                    break;
+                };
+                after = Some(EmailKeyset::of(last));
+                remaining -= batch.len();
+                let exhausted = batch.len() < batch_limit;
+                let mut items = batch
+                    .into_iter()
+                    .filter(|email| !args.unread || !email.is_read.unwrap_or(false))
+                    .map(|email| SearchResultItem {
+                        email,
+                        score: None,
+                        snippet: None,
+                        preview: None,
+                    })
+                    .collect::<Vec<_>>();
+                output::attach_previews(&db, &mut items)?;
+                if !print_ndjson(&mut out, &items)? || exhausted {
+                    break;
+                }
+            }
+            return Ok(());
+        }
+
+        let mut emails = db.search_emails(filters)?;
+        // Taken before --unread so the next page resumes after every row
+        // this one examined.
+        let next_cursor = (emails.len() == limit)
+            .then(|| emails.last())
+            .flatten()
+            .map(|email| search::Cursor::Recency(EmailKeyset::of(email)).to_string());
 
         if args.unread {
             emails.retain(|email| !email.is_read.unwrap_or(false));
         }
 
-        let formatted = output::format_search_results(
-            OutputFormat::from_json_flag(json),
-            &emails
-                .into_iter()
-                .map(|email| SearchResultItem { email, score: None })
-                .collect::<Vec<_>>(),
-        )?;
-        println!("{formatted}");
+        let mut items = emails
+            .into_iter()
+            .map(|email| SearchResultItem {
+                email,
+                score: None,
+                snippet: None,
+                preview: None,
+            })
+            .collect::<Vec<_>>();
+        output::attach_previews(&db, &mut items)?;
+        let format = match rendering {
+            Rendering::Formatted(format) => format,
+            Rendering::Delimited(delimiter) => {
+                write!(
+                    out,
+                    "{}",
+                    output::csv::format_search_results(&items, delimiter)
+                )?;
+                return Ok(());
+            }
+            Rendering::Ndjson => unreachable!("streamed above"),
+        };
+        let formatted = if cursor.is_some() || format == OutputFormat::Table {
+            output::format_paged_search_results(format, &items, next_cursor.as_deref())?
+        } else {
+            output::format_search_results(format, &items)?
+        };
+        writeln!(out, "{formatted}")?;
         Ok(())
     }
 
-    async fn handle_show(id: &str, json: bool) -> Result<()> {
+    /// Uses the search command's config defaults for the limit and scope.
+    fn handle_tui(args: super::TuiArgs, scope: Option<Scope>) -> Result<()> {
+        if !std::io::stdout().is_terminal() {
+            return Err(anyhow!("ess tui needs an interactive terminal"));
+        }
+        let config = load_config()?;
+        let scope = effective_scope("search", scope, &config)?;
+        let limit = args
+            .limit
+            .or(config.default_limit("search"))
+            .unwrap_or(DEFAULT_SEARCH_LIMIT);
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        let db = Database::open(&db_path)
+            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+        let index = open_index_with_recovery(&db)?;
+        tui::run(
+            &db,
+            &index,
+            TuiOptions {
+                query: args.query,
+                scope: map_scope(scope),
+                folder: args.folder,
+                limit,
+            },
+        )
+    }
+
+    async fn handle_show(id: &str, format: Option<DocumentFormat>, json: bool) -> Result<()> {
         let db_path = Database::default_db_path().context("resolve default ESS database path")?;
         let db = Database::open(&db_path)
             .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+        println!("{}", format_shown_email(&db, id, format, json)?);
+        Ok(())
+    }
+
+    fn format_shown_email(
+        db: &Database,
+        id: &str,
+        format: Option<DocumentFormat>,
+        json: bool,
+    ) -> Result<String> {
         let email = db
             .get_email(id)?
             .ok_or_else(|| anyhow!("email not found for id '{id}'"))?;
 
-        let formatted = output::format_email(OutputFormat::from_json_flag(json), &email)?;
-        println!("{formatted}");
+        let formatted = match document_format(format, json) {
+            DocumentFormat::Markdown => {
+                let attachments = db.get_attachments(&email.id)?;
+                output::markdown::format_email(&MarkdownMessage {
+                    email: &email,
+                    attachments: &attachments,
+                })
+            }
+            other => output::format_email(output_format(other), &email)?,
+        };
+        Ok(formatted)
+    }
+
+    /// Conversations of other accounts that share Message-IDs with this
+    /// one are shown with it; notes of each are listed, the summary is the
+    /// first conversation's.
+    async fn handle_thread(id: &str, format: Option<DocumentFormat>, json: bool) -> Result<()> {
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        let db = Database::open(&db_path)
+            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+        println!("{}", format_shown_thread(&db, id, format, json)?);
+        Ok(())
+    }
+
+    fn format_shown_thread(
+        db: &Database,
+        id: &str,
+        format: Option<DocumentFormat>,
+        json: bool,
+    ) -> Result<String> {
+        let (conversation_ids, emails) = match db.stitched_thread(id)? {
+            Some(thread) => (thread.conversation_ids, thread.emails),
+            None => (vec![id.to_string()], Vec::new()),
+        };
+        let mut notes = Vec::new();
+        for conversation_id in &conversation_ids {
+            notes.extend(db.notes_for_conversation(conversation_id)?);
+        }
+        let summary = db.summary(&conversation_ids[0])?;
+        let formatted = match document_format(format, json) {
+            DocumentFormat::Markdown => {
+                let attachments = emails
+                    .iter()
+                    .map(|email| db.get_attachments(&email.id))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let messages = emails
+                    .iter()
+                    .zip(&attachments)
+                    .map(|(email, attachments)| MarkdownMessage { email, attachments })
+                    .collect::<Vec<_>>();
+                output::markdown::format_thread(&messages, &notes, summary.as_ref())
+            }
+            other => {
+                output::format_thread(output_format(other), &emails, &notes, summary.as_ref())?
+            }
+        };
+        Ok(formatted)
+    }
+
+    /// `--html` prints the page; `--open` writes it to the temp directory and
+    /// hands it to the desktop's default browser.
+    fn handle_show_html(id: &str, open: bool, json: bool) -> Result<()> {
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        let db = Database::open(&db_path)
+            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+        let email = db
+            .get_email(id)?
+            .ok_or_else(|| anyhow!("email not found for id '{id}'"))?;
+        let page = output::html::format_email(&email);
+        if !open {
+            print!("{page}");
+            return Ok(());
+        }
+
+        let file_stem = email
+            .id
+            .chars()
+            .map(|ch| if ch.is_ascii_alphanumeric() { ch } else { '_' })
+            .collect::<String>();
+        let path = std::env::temp_dir().join(format!("ess-{file_stem}.html"));
+        std::fs::write(&path, page).with_context(|| format!("write {}", path.display()))?;
+        let opener = if cfg!(target_os = "macos") {
+            "open"
+        } else {
+            "xdg-open"
+        };
+        std::process::Command::new(opener)
+            .arg(&path)
+            .spawn()
+            .with_context(|| format!("run {opener} for {}", path.display()))?;
+        if json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&serde_json::json!({ "id": email.id, "path": path }))?
+            );
+        } else {
+            println!("Opened {}", path.display());
+        }
+        Ok(())
+    }
+
+    /// `--format` wins over `--json`.
+    fn document_format(format: Option<DocumentFormat>, json: bool) -> DocumentFormat {
+        match format {
+            Some(format) => format,
+            None if json => DocumentFormat::Json,
+            None => DocumentFormat::Table,
+        }
+    }
+
+    fn output_format(format: DocumentFormat) -> OutputFormat {
+        OutputFormat::from_json_flag(format == DocumentFormat::Json)
+    }
+
+    /// Applies a thread correction and refreshes the index documents of the
+    /// emails it moved.
+    fn handle_thread_command(command: ThreadCommands, json: bool) -> Result<()> {
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        let db = Database::open(&db_path)
+            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+
+        let (action, thread_id, moved) = match command {
+            ThreadCommands::Merge { thread_a, thread_b } => {
+                let merge = db.merge_threads(&thread_a, &thread_b)?;
+                ("merge", merge.thread_id, merge.moved)
+            }
+            ThreadCommands::Split { email_id } => {
+                let thread_id = db
+                    .split_thread(&email_id)?
+                    .ok_or_else(|| anyhow!("email not found for id '{email_id}'"))?;
+                ("split", thread_id, vec![email_id])
+            }
+        };
+
+        if !moved.is_empty() {
+            let mut index = open_index_with_recovery(&db)?;
+            let account_types = db
+                .list_accounts()?
+                .into_iter()
+                .map(|account| (account.account_id, account.account_type.to_string()))
+                .collect::<HashMap<_, _>>();
+            for id in &moved {
+                let Some(email) = db.get_email(id)? else {
+                    continue;
+                };
+                let account_type = email
+                    .account_id
+                    .as_ref()
+                    .and_then(|account_id| account_types.get(account_id))
+                    .map(String::as_str)
+                    .unwrap_or("professional");
+                index.add_email_buffered(&email, account_type)?;
+            }
+            index.commit()?;
+        }
+
+        if json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&serde_json::json!({
+                    "action": action,
+                    "thread_id": thread_id,
+                    "moved": moved,
+                }))?
+            );
+        } else if moved.is_empty() {
+            println!("Nothing to {action}: no emails moved.");
+        } else {
+            println!(
+                "{action}: {} email(s) now in thread {thread_id}",
+                moved.len()
+            );
+        }
+        Ok(())
+    }
+
+    async fn handle_summarize(id: &str, refresh: bool, json: bool) -> Result<()> {
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        let db = Database::open(&db_path)
+            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+        let (summary, cached) = summaries::summarize(&db, id, refresh).await?;
+
+        if json {
+            let mut value = serde_json::to_value(&summary)?;
+            value["cached"] = serde_json::Value::Bool(cached);
+            println!("{}", serde_json::to_string_pretty(&value)?);
+        } else {
+            println!(
+                "{} ({} messages, {}{}):\n\n{}",
+                summary.conversation_id,
+                summary.message_count,
+                summary.model,
+                if cached { ", stored" } else { "" },
+                summary.summary
+            );
+        }
+        Ok(())
+    }
+
+    async fn handle_tags(command: TagsCommands, json: bool) -> Result<()> {
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        let db = Database::open(&db_path)
+            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+
+        match command {
+            TagsCommands::List => {
+                let counts = db.tag_counts()?;
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&counts)?);
+                } else if counts.is_empty() {
+                    println!("No tagged emails.");
+                } else {
+                    for row in &counts {
+                        println!("{:<24} {:>8}", row.tag, row.count);
+                    }
+                }
+            }
+            TagsCommands::Show { id } => {
+                if db.get_email(&id)?.is_none() {
+                    return Err(anyhow!("email not found for id '{id}'"));
+                }
+                let tags = db.email_tags(&id)?;
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&tags)?);
+                } else if tags.is_empty() {
+                    println!("No tags on {id}.");
+                } else {
+                    for tag in &tags {
+                        println!("{:<24} {}", tag.tag, tag.source);
+                    }
+                }
+            }
+            TagsCommands::Apply { all } => {
+                let path = tags::default_rules_path()?;
+                let rules = TagRules::load(&path)?.ok_or_else(|| {
+                    anyhow!(
+                        "no tagging rules at {}; see `ess tags` in the README",
+                        path.display()
+                    )
+                })?;
+                if all {
+                    db.reset_tagging()?;
+                }
+                let report =
+                    tags::tag_pending(&db, &rules, raw_store_if_enabled()?.as_ref()).await?;
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&report)?);
+                } else {
+                    print_tag_report(&report);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn print_tag_report(report: &TagReport) {
+        if report.emails == 0 {
+            println!("Every email is already tagged under the current rules");
+            return;
+        }
+        println!(
+            "Tagged {} of {} checked email(s)",
+            report.tagged, report.emails
+        );
+        if report.model_skipped > 0 {
+            println!(
+                "{} email(s) were tagged by rules only after the tag model failed; they are retried on the next run",
+                report.model_skipped
+            );
+        }
+    }
+
+    /// The raw message store when `[storage] raw_messages` is on.
+    fn raw_store_if_enabled() -> Result<Option<RawStore>> {
+        if load_config()?.storage.raw_messages.unwrap_or(false) {
+            Ok(Some(
+                RawStore::open_default().context("resolve raw message directory")?,
+            ))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn handle_note(command: NoteCommands, json: bool) -> Result<()> {
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        let db = Database::open(&db_path)
+            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+
+        match command {
+            NoteCommands::Add {
+                conversation_id,
+                text,
+            } => {
+                let text = text.trim();
+                if text.is_empty() {
+                    return Err(anyhow!("note text must not be empty"));
+                }
+                if db.get_emails_by_conversation(&conversation_id)?.is_empty() {
+                    return Err(anyhow!(
+                        "thread not found for conversation id '{conversation_id}'"
+                    ));
+                }
+                let note = db.add_note(&conversation_id, text)?;
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&note)?);
+                } else {
+                    println!("Added note {} to thread {}", note.id, note.conversation_id);
+                }
+            }
+            NoteCommands::List { conversation_id } => {
+                let notes = match conversation_id {
+                    Some(conversation_id) => db.notes_for_conversation(&conversation_id)?,
+                    None => db.list_notes()?,
+                };
+                println!(
+                    "{}",
+                    output::format_notes(OutputFormat::from_json_flag(json), &notes)?
+                );
+            }
+            NoteCommands::Remove { id } => {
+                let note = db
+                    .delete_note(id)?
+                    .ok_or_else(|| anyhow!("note not found for id {id}"))?;
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&note)?);
+                } else {
+                    println!(
+                        "Removed note {} from thread {}",
+                        note.id, note.conversation_id
+                    );
+                }
+            }
+        }
         Ok(())
     }
 
-    async fn handle_thread(conversation_id: &str, json: bool) -> Result<()> {
-        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
-        let db = Database::open(&db_path)
-            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
-        let emails = db.get_emails_by_conversation(conversation_id)?;
-        let formatted = output::format_thread(OutputFormat::from_json_flag(json), &emails)?;
-        println!("{formatted}");
+    fn handle_collection_command(
+        command: CollectionCommands,
+        scope: Option<Scope>,
+        json: bool,
+    ) -> Result<()> {
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        let db = Database::open(&db_path)
+            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+        let format = OutputFormat::from_json_flag(json);
+
+        match command {
+            CollectionCommands::Add {
+                name,
+                mut ids,
+                search: query,
+                limit,
+            } => {
+                let missing = ids
+                    .iter()
+                    .filter_map(|id| match db.email_exists(id) {
+                        Ok(true) => None,
+                        Ok(false) => Some(Ok(id.as_str())),
+                        Err(error) => Some(Err(error)),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if !missing.is_empty() {
+                    return Err(anyhow!("email not found for id(s): {}", missing.join(", ")));
+                }
+                if let Some(query) = query {
+                    let index = open_index_with_recovery(&db)?;
+                    let scope = effective_scope("collections", scope, &load_config()?)?;
+                    let filters = EmailFilters {
+                        scope: map_scope(scope),
+                        limit,
+                        ..EmailFilters::default()
+                    };
+                    ids.extend(
+                        search::search_emails(&index, &db, &query, &filters)?
+                            .into_iter()
+                            .map(|result| result.email.id),
+                    );
+                }
+                let added = db.add_to_collection(&name, &ids)?;
+                if json {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&serde_json::json!({
+                            "collection": name,
+                            "added": added,
+                            "ids": ids,
+                        }))?
+                    );
+                } else {
+                    println!(
+                        "Added {added} email(s) to {name} ({} already there).",
+                        ids.len().saturating_sub(added)
+                    );
+                }
+            }
+            CollectionCommands::Remove { name, ids } => {
+                let removed = db.remove_from_collection(&name, &ids)?;
+                if json {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&serde_json::json!({
+                            "collection": name,
+                            "removed": removed,
+                        }))?
+                    );
+                } else {
+                    println!("Removed {removed} email(s) from {name}.");
+                }
+            }
+            CollectionCommands::List { name: None } => {
+                println!(
+                    "{}",
+                    output::format_collections(format, &db.list_collections()?)?
+                );
+            }
+            CollectionCommands::List { name: Some(name) } => {
+                let items = db
+                    .collection_emails(&name)?
+                    .ok_or_else(|| anyhow!("collection not found: {name}"))?
+                    .into_iter()
+                    .map(|email| SearchResultItem {
+                        email,
+                        score: None,
+                        snippet: None,
+                        preview: None,
+                    })
+                    .collect::<Vec<_>>();
+                println!("{}", output::format_search_results(format, &items)?);
+            }
+            CollectionCommands::Export { name, directory } => {
+                let emails = db
+                    .collection_emails(&name)?
+                    .ok_or_else(|| anyhow!("collection not found: {name}"))?;
+                let directory = Path::new(&directory);
+                std::fs::create_dir_all(directory)
+                    .with_context(|| format!("create export directory {}", directory.display()))?;
+                if std::fs::read_dir(directory)?.next().is_some() {
+                    return Err(anyhow!(
+                        "export directory {} is not empty",
+                        directory.display()
+                    ));
+                }
+                for (position, email) in emails.iter().enumerate() {
+                    let path = directory.join(format!("email-{:04}.json", position + 1));
+                    let payload = serde_json::to_string_pretty(&archive_payload(email))?;
+                    std::fs::write(&path, payload)
+                        .with_context(|| format!("write {}", path.display()))?;
+                }
+                if json {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&serde_json::json!({
+                            "collection": name,
+                            "directory": directory,
+                            "exported": emails.len(),
+                        }))?
+                    );
+                } else {
+                    println!(
+                        "Exported {} email(s) from {name} to {}",
+                        emails.len(),
+                        directory.display()
+                    );
+                }
+            }
+            CollectionCommands::Delete { name } => {
+                if !db.delete_collection(&name)? {
+                    return Err(anyhow!("collection not found: {name}"));
+                }
+                if json {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&serde_json::json!({
+                            "collection": name,
+                            "deleted": true,
+                        }))?
+                    );
+                } else {
+                    println!("Deleted collection {name}.");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_star(id: &str, star: bool, json: bool) -> Result<()> {
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        let db = Database::open(&db_path)
+            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+        if !db.email_exists(id)? {
+            return Err(anyhow!("email not found for id '{id}'"));
+        }
+
+        let changed = if star {
+            db.star_email(id)?
+        } else {
+            db.unstar_email(id)?
+        };
+        if json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&serde_json::json!({
+                    "id": id,
+                    "starred": star,
+                    "changed": changed,
+                }))?
+            );
+        } else {
+            let state = if star { "starred" } else { "not starred" };
+            if changed {
+                println!("Email {id} is now {state}.");
+            } else {
+                println!("Email {id} was already {state}.");
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_starred(limit: usize, json: bool) -> Result<()> {
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        let db = Database::open(&db_path)
+            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+        let items = db
+            .starred_emails(limit.max(1))?
+            .into_iter()
+            .map(|email| SearchResultItem {
+                email,
+                score: None,
+                snippet: None,
+                preview: None,
+            })
+            .collect::<Vec<_>>();
+        let formatted = output::format_search_results(OutputFormat::from_json_flag(json), &items)?;
+        println!("{formatted}");
+        Ok(())
+    }
+
+    fn handle_labels(account_id: &str, json: bool) -> Result<()> {
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        let db = Database::open(&db_path)
+            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+        if db.get_account(account_id)?.is_none() {
+            return Err(anyhow!("account not found: {account_id}"));
+        }
+        let labels = db.labels(account_id)?;
+        let formatted = output::format_labels(OutputFormat::from_json_flag(json), &labels)?;
+        println!("{formatted}");
+        Ok(())
+    }
+
+    fn handle_folders(discovered: bool, account_id: Option<&str>, json: bool) -> Result<()> {
+        let db = open_local_db()?;
+        if let Some(account_id) = account_id {
+            if db.get_account(account_id)?.is_none() {
+                return Err(anyhow!("account not found: {account_id}"));
+            }
+        }
+        let format = OutputFormat::from_json_flag(json);
+        let formatted = if discovered {
+            output::format_discovered_folders(format, &db.discovered_folders(account_id)?)?
+        } else {
+            let mut counts = db.get_stats()?.emails_by_folder;
+            counts.retain(|count| {
+                account_id.is_none_or(|account_id| count.account_id.as_deref() == Some(account_id))
+            });
+            output::format_folder_counts(format, &counts)?
+        };
+        println!("{formatted}");
+        Ok(())
+    }
+
+    fn handle_lists(json: bool) -> Result<()> {
+        let db = open_local_db()?;
+        let since =
+            (Utc::now() - chrono::Duration::days(30)).to_rfc3339_opts(SecondsFormat::Secs, true);
+        let lists = db.mailing_lists(&since)?;
+        let formatted = output::format_mailing_lists(OutputFormat::from_json_flag(json), &lists)?;
+        println!("{formatted}");
+        Ok(())
+    }
+
+    fn handle_meetings(command: MeetingsCommands, json: bool) -> Result<()> {
+        let days = match command {
+            MeetingsCommands::Today => 1,
+            MeetingsCommands::Upcoming { days } => days.max(1),
+        };
+        let (from, to) = meetings::local_days(Local::now().date_naive(), days)
+            .ok_or_else(|| anyhow!("cannot compute a {days} day range from today"))?;
+        let db = open_local_db()?;
+        let links = db.meeting_links(&from, &to)?;
+        let formatted = output::format_meeting_links(OutputFormat::from_json_flag(json), &links)?;
+        println!("{formatted}");
+        Ok(())
+    }
+
+    async fn handle_commitments(args: super::CommitmentsArgs, json: bool) -> Result<()> {
+        let db = open_local_db()?;
+        match args.command {
+            None => {
+                db.prune_followups()?;
+                let followups = db.followups(COMMITMENT_KIND, args.open)?;
+                let formatted =
+                    output::format_followups(OutputFormat::from_json_flag(json), &followups)?;
+                println!("{formatted}");
+            }
+            Some(CommitmentsCommands::Extract) => {
+                let extractor = CommitmentExtractor::from_config(&load_config()?)?;
+                let report = commitments::extract_pending(&db, &extractor).await?;
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&report)?);
+                } else {
+                    print_commitment_report(&report);
+                }
+            }
+            Some(CommitmentsCommands::Done { id }) => {
+                if !db.complete_followup(id)? {
+                    return Err(anyhow!("commitment not found for id {id}"));
+                }
+                if json {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(
+                            &serde_json::json!({ "id": id, "done": true })
+                        )?
+                    );
+                } else {
+                    println!("Marked commitment {id} done.");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn print_commitment_report(report: &CommitmentReport) {
+        if report.emails == 0 {
+            println!("Every sent email is already scanned");
+            return;
+        }
+        println!(
+            "Found {} commitment(s) in {} sent email(s)",
+            report.found, report.emails
+        );
+        if report.model_skipped > 0 {
+            println!(
+                "{} email(s) were scanned by patterns only after the commitment model failed; they are retried on the next run",
+                report.model_skipped
+            );
+        }
+    }
+
+    fn handle_attachments(command: AttachmentsCommands, json: bool) -> Result<()> {
+        let AttachmentsCommands::Versions { thread, name } = command;
+        let db = open_local_db()?;
+        let conversation_id = summaries::resolve_conversation(&db, &thread)?;
+        let mut copies = db.attachment_versions(&conversation_id, &name)?;
+
+        // Hashes come from stored sources; without one, copies compare by size.
+        let mut unhashed: Vec<String> = copies
+            .iter()
+            .filter(|copy| copy.sha256.is_none())
+            .map(|copy| copy.email_id.clone())
+            .collect();
+        unhashed.dedup();
+        if !unhashed.is_empty() {
+            let raw = RawStore::open_default().context("resolve raw message directory")?;
+            let mut hashed = false;
+            for email_id in &unhashed {
+                let Some(source) = raw.get(email_id).with_context(|| {
+                    format!("read raw message {}", raw.path(email_id).display())
+                })?
+                else {
+                    continue;
+                };
+                let digests: Vec<_> = mime::attachment_digests(&source)
+                    .with_context(|| format!("parse raw message for '{email_id}'"))?
+                    .into_iter()
+                    .filter(|found| found.name.trim().eq_ignore_ascii_case(name.trim()))
+                    .collect();
+                let stored: Vec<_> = copies
+                    .iter()
+                    .filter(|copy| &copy.email_id == email_id)
+                    .collect();
+                let sizes: Vec<_> = stored.iter().map(|copy| copy.size_bytes).collect();
+                let matched = mime::match_digests(&sizes, &digests);
+                for (copy, sha256) in stored.into_iter().zip(matched) {
+                    let Some(sha256) = sha256 else {
+                        continue;
+                    };
+                    db.store_attachment_hash(&copy.attachment_id, &sha256)?;
+                    hashed = true;
+                }
+            }
+            if hashed {
+                copies = db.attachment_versions(&conversation_id, &name)?;
+            }
+        }
+
+        let formatted =
+            output::format_attachment_versions(OutputFormat::from_json_flag(json), &copies)?;
+        println!("{formatted}");
+        Ok(())
+    }
+
+    fn handle_raw(command: RawCommands, json: bool) -> Result<()> {
+        let RawCommands::Get { id, output } = command;
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        let db = Database::open(&db_path)
+            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+        if !db.email_exists(&id)? {
+            return Err(anyhow!("email not found for id '{id}'"));
+        }
+        let raw = RawStore::open_default().context("resolve raw message directory")?;
+        let message = raw
+            .get(&id)
+            .with_context(|| format!("read raw message {}", raw.path(&id).display()))?
+            .ok_or_else(|| {
+                anyhow!(
+                    "no raw message stored for '{id}'; set raw_messages = true under [storage] in config.toml (messages synced from then on are kept)"
+                )
+            })?;
+
+        let Some(path) = output else {
+            let mut stdout = std::io::stdout().lock();
+            match stdout.write_all(&message).and_then(|()| stdout.flush()) {
+                Err(error) if error.kind() == std::io::ErrorKind::BrokenPipe => {}
+                result => result.context("write raw message to stdout")?,
+            }
+            return Ok(());
+        };
+        std::fs::write(&path, &message).with_context(|| format!("write {}", path.display()))?;
+        if json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&serde_json::json!({
+                    "id": id,
+                    "path": path,
+                    "size_bytes": message.len(),
+                }))?
+            );
+        } else {
+            println!("Wrote {} bytes to {}", message.len(), path.display());
+        }
+        Ok(())
+    }
+
+    async fn handle_mark(args: super::MarkArgs, json: bool) -> Result<()> {
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        let db = Database::open(&db_path)
+            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+        let mut email = db
+            .get_email(&args.id)?
+            .ok_or_else(|| anyhow!("email not found for id '{}'", args.id))?;
+        let account_id = email
+            .account_id
+            .clone()
+            .ok_or_else(|| anyhow!("email '{}' has no account", args.id))?;
+        let account = db
+            .get_account(&account_id)?
+            .ok_or_else(|| anyhow!("account not found: {account_id}"))?;
+
+        let update = args.flag_update();
+        let connector = connector_for_account(&account, &SyncHooks::default());
+        let pushed = connector.supports_flag_updates();
+        if pushed {
+            let result = connector.update_flags(&db, &account, &email, update).await;
+            record_usage(&db, &account, connector.as_ref())?;
+            result.with_context(|| format!("push flag change to {}", connector.name()))?;
+        }
+
+        update.apply_to(&mut email);
+        db.update_email_flags(&email)?;
+        let mut index = open_index_with_recovery(&db)?;
+        index.add_email(&email, &account.account_type.to_string())?;
+
+        if json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&serde_json::json!({
+                    "id": email.id,
+                    "is_read": email.is_read,
+                    "flag_status": email.flag_status,
+                    "pushed_to_provider": pushed,
+                }))?
+            );
+        } else if pushed {
+            println!("updated {} locally and in {}", email.id, connector.name());
+        } else {
+            println!(
+                "updated {} locally ({} cannot write to the provider)",
+                email.id,
+                connector.name()
+            );
+        }
+        Ok(())
+    }
+
+    async fn handle_message_action(id: &str, action: MessageAction, json: bool) -> Result<()> {
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        let db = Database::open(&db_path)
+            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+        let mut email = db
+            .get_email(id)?
+            .ok_or_else(|| anyhow!("email not found for id '{id}'"))?;
+        let account_id = email
+            .account_id
+            .clone()
+            .ok_or_else(|| anyhow!("email '{id}' has no account"))?;
+        let account = db
+            .get_account(&account_id)?
+            .ok_or_else(|| anyhow!("account not found: {account_id}"))?;
+
+        let connector = connector_for_account(&account, &SyncHooks::default());
+        if !connector.supports_message_actions() {
+            return Err(anyhow!(
+                "{} connector cannot {} messages on the provider",
+                connector.name(),
+                action.as_str()
+            ));
+        }
+        let result = connector
+            .apply_message_action(&db, &account, &email, action)
+            .await;
+        record_usage(&db, &account, connector.as_ref())?;
+        let moved = result
+            .with_context(|| format!("{} message via {}", action.as_str(), connector.name()))?;
+
+        // Update the stored row rather than storing it again: a replace
+        // would drop its attachment records, and deleting an old id first
+        // would drop its stars and collection memberships.
+        let mut index = open_index_with_recovery(&db)?;
+        email.id = moved.id.clone();
+        email.folder = Some(moved.folder.clone());
+        for copy in db.merge_stored_copies(&email)? {
+            index.delete_email_buffered(&copy)?;
+        }
+        if email.id == id {
+            db.update_email_flags(&email)?;
+        } else {
+            db.move_email(id, &email)?;
+            index.delete_email_buffered(id)?;
+        }
+        index.update_email(&email, &account.account_type.to_string())?;
+        index.commit()?;
+
+        if json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&serde_json::json!({
+                    "action": action,
+                    "previous_id": id,
+                    "id": moved.id,
+                    "folder": moved.folder,
+                }))?
+            );
+        } else {
+            println!(
+                "{id}: {} -> {} ({})",
+                action.as_str(),
+                moved.folder,
+                moved.id
+            );
+        }
+        Ok(())
+    }
+
+    async fn handle_sync(args: super::SyncArgs, json: bool) -> Result<()> {
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        let db = Database::open(&db_path)
+            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+        let mut index = open_index_with_recovery(&db)?;
+        let settings = SyncSettings::load(&db, args.account.as_deref())?;
+        let mut accounts = settings.accounts;
+        let mut hooks = SyncHooks {
+            progress: sync_progress_sink(json),
+            shutdown: ShutdownSignal::new(),
+            raw: settings.raw,
+            deferred_raw: HashSet::new(),
+            tag_rules: settings.tag_rules,
+            commitments: settings.commitments,
+            store_cap: settings.store_cap,
+        };
+        spawn_interrupt_handler(hooks.shutdown.clone());
+
+        if args.full {
+            eprintln!("--full requested: running full sync pass for selected account(s)");
+        }
+
+        if args.watch {
+            if args.warm {
+                match index.warm(WARM_QUERIES) {
+                    Ok(report) => eprintln!(
+                        "Warmed search index: {} files in {} ms",
+                        report.files, report.duration_ms
+                    ),
+                    Err(error) => tracing::warn!("failed to warm ESS index: {error}"),
+                }
+            }
+            let mut interval = watch_interval()?;
+            let mut schedule = sync_schedule()?;
+            let mut last_synced: HashMap<String, tokio::time::Instant> = HashMap::new();
+            let mut idle_reason: Option<String> = None;
+            let mut deferral: Option<String> = None;
+            let reload = Arc::new(Notify::new());
+            spawn_reload_handler(reload.clone());
+            let _pid_file = WatchPidFile::create(watch_pid_path()?)?;
+            let _client_server = spawn_client_server().map(AbortOnDrop);
+            let _remote_server = match args.listen {
+                Some(addr) => Some(AbortOnDrop(spawn_remote_server(addr, args.token).await?)),
+                None => None,
+            };
+            loop {
+                let due = due_accounts(&db, &accounts, &schedule, &last_synced)?;
+                let reason = match &due {
+                    Ok(due) if due.is_empty() && !accounts.is_empty() => {
+                        Some("every account is in its quiet hours".to_string())
+                    }
+                    Ok(_) => None,
+                    Err(paused_until) => Some(format!(
+                        "paused until {}",
+                        paused_until.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+                    )),
+                };
+                if reason != idle_reason {
+                    match &reason {
+                        Some(reason) => eprintln!("Skipping syncs: {reason}"),
+                        None if idle_reason.is_some() => eprintln!("Resuming syncs"),
+                        None => {}
+                    }
+                    idle_reason = reason;
+                }
+                if let (Ok(due), None) = (due, &idle_reason) {
+                    let conditions = SyncConditions::detect().await;
+                    let (due, full_syncs_deferred) =
+                        defer_for_conditions(&db, due, &schedule, conditions, &mut hooks)?;
+                    let note = deferral_note(conditions, &full_syncs_deferred, &hooks.deferred_raw);
+                    if note != deferral {
+                        match &note {
+                            Some(note) => eprintln!("{note}"),
+                            None => eprintln!("Resuming full syncs and raw message downloads"),
+                        }
+                        deferral = note;
+                    }
+                    let started = tokio::time::Instant::now();
+                    if !due.is_empty() {
+                        match run_reported_sync_cycle(
+                            &db,
+                            &mut index,
+                            &due,
+                            &hooks,
+                            args.timeout,
+                            json,
+                        )
+                        .await
+                        {
+                            Ok(_) => {}
+                            Err(error)
+                                if error.is::<SyncTimedOut>() || error.is::<OfflineError>() =>
+                            {
+                                eprintln!("{error}")
+                            }
+                            Err(error) => return Err(error),
+                        }
+                    }
+                    for account in due {
+                        last_synced.insert(account.account_id, started);
+                    }
+                }
+                // A reload requested during the cycle is applied here, once
+                // the cycle has finished.
+                let cycle_ended = tokio::time::Instant::now();
+                loop {
+                    tokio::select! {
+                        _ = tokio::time::sleep_until(cycle_ended + interval) => break,
+                        _ = reload.notified() => reload_watch(
+                            &db,
+                            args.account.as_deref(),
+                            &mut accounts,
+                            &mut interval,
+                            &mut schedule,
+                            &mut hooks,
+                        ),
+                        _ = hooks.shutdown.requested() => return Err(SyncInterrupted.into()),
+                    }
+                }
+            }
+        } else {
+            let recorder = match &args.record {
+                Some(_) => Some(
+                    FixtureRecorder::start(Upstream::providers())
+                        .await?
+                        .redirect_connectors(),
+                ),
+                None => None,
+            };
+            let synced =
+                run_reported_sync_cycle(&db, &mut index, &accounts, &hooks, args.timeout, json)
+                    .await;
+            if let (Some(recorder), Some(dir)) = (recorder, &args.record) {
+                save_recording(recorder, dir).await?;
+            }
+            check_error_threshold(synced?, args.fail_on_errors)
+        }
+    }
+
+    /// What a sync reads from the database and the config files. `ess sync
+    /// --watch` loads it again on SIGHUP or `ess daemon reload`.
+    struct SyncSettings {
+        accounts: Vec<Account>,
+        raw: Option<RawStore>,
+        tag_rules: Option<TagRules>,
+        commitments: CommitmentExtractor,
+        store_cap: Option<StoreCap>,
+    }
+
+    impl SyncSettings {
+        fn load(db: &Database, account_id: Option<&str>) -> Result<Self> {
+            let config = load_config()?;
+            Ok(Self {
+                accounts: resolve_accounts(db, account_id)?,
+                raw: raw_store_if_enabled()?,
+                tag_rules: TagRules::load_default()?,
+                commitments: CommitmentExtractor::from_config(&config)?,
+                store_cap: config.store_cap()?,
+            })
+        }
+    }
+
+    /// `[sync] interval_secs`, the pause between watch cycles.
+    fn watch_interval() -> Result<Duration> {
+        Ok(load_config()?.sync_interval()?)
+    }
+
+    /// `[sync] quiet_hours` and the per-account overrides.
+    fn sync_schedule() -> Result<SyncSchedule> {
+        Ok(load_config()?.sync_schedule()?)
+    }
+
+    /// Where `ess daemon pause` keeps the time watch cycles resume at.
+    const SYNC_PAUSED_UNTIL_KEY: &str = "sync_paused_until";
+
+    /// The end of an `ess daemon pause` still in effect.
+    fn sync_paused_until(db: &Database) -> Result<Option<DateTime<Utc>>> {
+        let Some(state) = db.get_sync_state(SYNC_PAUSED_UNTIL_KEY)? else {
+            return Ok(None);
+        };
+        let until = state
+            .value
+            .as_deref()
+            .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+            .map(|until| until.with_timezone(&Utc));
+        Ok(until.filter(|until| *until > Utc::now()))
+    }
+
+    /// The accounts a watch cycle starting now syncs, or the end of the
+    /// pause that holds it back.
+    fn due_accounts(
+        db: &Database,
+        accounts: &[Account],
+        schedule: &SyncSchedule,
+        last_synced: &HashMap<String, tokio::time::Instant>,
+    ) -> Result<Result<Vec<Account>, DateTime<Utc>>> {
+        if let Some(until) = sync_paused_until(db)? {
+            return Ok(Err(until));
+        }
+        let now = Local::now().time();
+        Ok(Ok(accounts
+            .iter()
+            .filter(|account| {
+                schedule.is_due(
+                    &account.account_id,
+                    &account.email_address,
+                    now,
+                    last_synced
+                        .get(&account.account_id)
+                        .map(tokio::time::Instant::elapsed),
+                )
+            })
+            .cloned()
+            .collect()))
+    }
+
+    /// Splits the due accounts of a watch cycle by `[sync] defer_on_battery`
+    /// and `defer_on_metered`: an account its deferral applies to is left
+    /// out (its id returned) when it would need a full sync, and otherwise
+    /// synced without raw downloads (recorded in `hooks.deferred_raw`).
+    fn defer_for_conditions(
+        db: &Database,
+        due: Vec<Account>,
+        schedule: &SyncSchedule,
+        conditions: SyncConditions,
+        hooks: &mut SyncHooks,
+    ) -> Result<(Vec<Account>, Vec<String>)> {
+        hooks.deferred_raw.clear();
+        let mut synced = Vec::new();
+        let mut deferred = Vec::new();
+        for account in due {
+            let defers = schedule.defers(
+                &account.account_id,
+                &account.email_address,
+                conditions.on_battery,
+                conditions.metered,
+            );
+            if !defers {
+                synced.push(account);
+            } else if connector_for_account(&account, hooks).needs_full_sync(db, &account)? {
+                deferred.push(account.account_id);
+            } else {
+                if hooks.raw.is_some() {
+                    hooks.deferred_raw.insert(account.account_id.clone());
+                }
+                synced.push(account);
+            }
+        }
+        Ok((synced, deferred))
+    }
+
+    /// What a watch cycle puts off and why, e.g. `Putting off full syncs of
+    /// work (on battery)`; `None` when nothing is.
+    fn deferral_note(
+        conditions: SyncConditions,
+        full_syncs: &[String],
+        raw: &HashSet<String>,
+    ) -> Option<String> {
+        let condition = conditions.describe()?;
+        let mut raw: Vec<&str> = raw.iter().map(String::as_str).collect();
+        raw.sort();
+        let mut parts = Vec::new();
+        if !full_syncs.is_empty() {
+            parts.push(format!("full syncs of {}", full_syncs.join(", ")));
+        }
+        if !raw.is_empty() {
+            parts.push(format!("raw message downloads of {}", raw.join(", ")));
+        }
+        (!parts.is_empty()).then(|| format!("Putting off {} ({condition})", parts.join(" and ")))
+    }
+
+    /// Swaps in freshly loaded settings between watch cycles. A config that
+    /// fails to load leaves the running settings in place.
+    fn reload_watch(
+        db: &Database,
+        account_id: Option<&str>,
+        accounts: &mut Vec<Account>,
+        interval: &mut Duration,
+        schedule: &mut SyncSchedule,
+        hooks: &mut SyncHooks,
+    ) {
+        let reloaded = SyncSettings::load(db, account_id)
+            .and_then(|settings| Ok((settings, watch_interval()?, sync_schedule()?)));
+        match reloaded {
+            Ok((settings, new_interval, new_schedule)) => {
+                *accounts = settings.accounts;
+                *interval = new_interval;
+                *schedule = new_schedule;
+                hooks.raw = settings.raw;
+                hooks.tag_rules = settings.tag_rules;
+                hooks.commitments = settings.commitments;
+                hooks.store_cap = settings.store_cap;
+                eprintln!(
+                    "Reloaded configuration: {} account(s), syncing every {}s, {} tagging rule(s)",
+                    accounts.len(),
+                    interval.as_secs(),
+                    hooks
+                        .tag_rules
+                        .as_ref()
+                        .map_or(0, |rules| rules.rules().len())
+                );
+            }
+            Err(error) => {
+                eprintln!("reload failed, keeping the current configuration: {error:#}")
+            }
+        }
+    }
+
+    /// Wakes the watch loop on every SIGHUP. A signal that arrives during a
+    /// cycle is kept until the loop next waits, so in-flight syncs finish
+    /// under the settings they started with.
+    #[cfg(unix)]
+    fn spawn_reload_handler(reload: Arc<Notify>) {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        tokio::spawn(async move {
+            let Ok(mut hangups) = signal(SignalKind::hangup()) else {
+                tracing::warn!("failed to listen for SIGHUP; use a restart to reload");
+                return;
+            };
+            while hangups.recv().await.is_some() {
+                reload.notify_one();
+            }
+        });
+    }
+
+    #[cfg(not(unix))]
+    fn spawn_reload_handler(_reload: Arc<Notify>) {}
+
+    /// `sync-watch.pid` in the data directory: the process `ess daemon
+    /// reload` signals.
+    fn watch_pid_path() -> Result<PathBuf> {
+        Ok(paths::data_dir()
+            .context("resolve ESS data directory")?
+            .join("sync-watch.pid"))
+    }
+
+    /// The pid file of a running `ess sync --watch`, locked while the loop
+    /// runs and removed when it ends.
+    pub(super) struct WatchPidFile {
+        path: PathBuf,
+        _file: std::fs::File,
+    }
+
+    /// Stops a background task when the owner goes out of scope.
+    struct AbortOnDrop(tokio::task::JoinHandle<()>);
+
+    impl Drop for AbortOnDrop {
+        fn drop(&mut self) {
+            self.0.abort();
+        }
+    }
+
+    impl WatchPidFile {
+        /// Takes the pid file at `path`, refusing when another watcher holds
+        /// it.
+        pub(super) fn create(path: PathBuf) -> Result<Self> {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("create {}", parent.display()))?;
+            }
+            let mut file = std::fs::OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(&path)
+                .with_context(|| format!("open pid file {}", path.display()))?;
+            match file.try_lock() {
+                Ok(()) => {}
+                Err(std::fs::TryLockError::WouldBlock) => {
+                    let pid = std::fs::read_to_string(&path).unwrap_or_default();
+                    return Err(anyhow!(
+                        "ess sync --watch is already running (pid {}); stop it first",
+                        pid.trim()
+                    ));
+                }
+                Err(std::fs::TryLockError::Error(error)) => {
+                    return Err(error).with_context(|| format!("lock pid file {}", path.display()))
+                }
+            }
+            file.set_len(0)
+                .and_then(|()| writeln!(file, "{}", std::process::id()))
+                .with_context(|| format!("write pid file {}", path.display()))?;
+            Ok(Self { path, _file: file })
+        }
+    }
+
+    impl Drop for WatchPidFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    /// The pid of the running `ess sync --watch`, if any.
+    fn running_watcher() -> Result<Option<u32>> {
+        watcher_pid(&watch_pid_path()?)
+    }
+
+    /// The pid in the pid file at `path` while its watcher holds the lock. A
+    /// pid file nobody holds was left behind by a killed watcher.
+    pub(super) fn watcher_pid(path: &Path) -> Result<Option<u32>> {
+        let file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => {
+                return Err(error).with_context(|| format!("open pid file {}", path.display()))
+            }
+        };
+        match file.try_lock_shared() {
+            Ok(()) => return Ok(None),
+            Err(std::fs::TryLockError::WouldBlock) => {}
+            Err(std::fs::TryLockError::Error(error)) => {
+                return Err(error).with_context(|| format!("lock pid file {}", path.display()))
+            }
+        }
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("read pid file {}", path.display()))?;
+        let pid = raw
+            .trim()
+            .parse::<u32>()
+            .with_context(|| format!("parse pid file {}", path.display()))?;
+        Ok(Some(pid))
+    }
+
+    fn handle_daemon(command: DaemonCommands, json: bool) -> Result<()> {
+        match command {
+            DaemonCommands::Reload => {
+                let Some(pid) = running_watcher()? else {
+                    return Err(anyhow!("no running `ess sync --watch` found"));
+                };
+                send_reload(pid)?;
+                if json {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&serde_json::json!({
+                            "pid": pid,
+                            "signal": "SIGHUP",
+                        }))?
+                    );
+                } else {
+                    println!("Asked ess sync --watch (pid {pid}) to reload its configuration");
+                }
+            }
+            DaemonCommands::Pause { duration } => {
+                let db = open_local_db()?;
+                let until = chrono::Duration::from_std(duration)
+                    .ok()
+                    .and_then(|duration| Utc::now().checked_add_signed(duration))
+                    .ok_or_else(|| anyhow!("pause is too long"))?;
+                db.set_sync_state(
+                    SYNC_PAUSED_UNTIL_KEY,
+                    &until.to_rfc3339_opts(SecondsFormat::Secs, true),
+                )?;
+                if json {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&serde_json::json!({
+                            "paused_until": until.to_rfc3339_opts(SecondsFormat::Secs, true),
+                        }))?
+                    );
+                } else {
+                    println!(
+                        "Paused ess sync --watch until {}",
+                        until.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+                    );
+                }
+            }
+            DaemonCommands::Resume => {
+                let db = open_local_db()?;
+                let was_paused = sync_paused_until(&db)?.is_some();
+                db.delete_sync_state(SYNC_PAUSED_UNTIL_KEY)?;
+                if json {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&serde_json::json!({
+                            "was_paused": was_paused,
+                        }))?
+                    );
+                } else if was_paused {
+                    println!("Resumed ess sync --watch");
+                } else {
+                    println!("ess sync --watch was not paused");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends SIGHUP to `pid`. Callers take it from [`running_watcher`], so
+    /// it belongs to a live watcher rather than a reused pid.
+    #[cfg(unix)]
+    fn send_reload(pid: u32) -> Result<()> {
+        use nix::sys::signal::{kill, Signal};
+        use nix::unistd::Pid;
+
+        let raw = i32::try_from(pid).map_err(|_| anyhow!("invalid pid {pid}"))?;
+        kill(Pid::from_raw(raw), Signal::SIGHUP)
+            .with_context(|| format!("send SIGHUP to process {pid}"))
+    }
+
+    #[cfg(not(unix))]
+    fn send_reload(_pid: u32) -> Result<()> {
+        Err(anyhow!(
+            "ess daemon reload needs Unix signals; restart ess sync --watch to pick up changes"
+        ))
+    }
+
+    /// Commands the daemon runs for clients. Semantic search stays local:
+    /// its embedding model is configured by the client's environment.
+    fn runs_on_daemon(command: &Commands) -> bool {
+        match command {
+            Commands::Search(args) => !args.semantic,
+            Commands::List(_) => true,
+            Commands::Show {
+                html, open, raw, ..
+            } => !(*html || *open || *raw),
+            Commands::Thread(args) => args.command.is_none() && args.conversation_id.is_some(),
+            _ => false,
+        }
+    }
+
+    /// The `--timeout` of a command `runs_on_daemon` accepts.
+    fn remote_timeout(command: &Commands) -> Option<Duration> {
+        match command {
+            Commands::Search(super::SearchArgs { remote, .. })
+            | Commands::List(super::ListArgs { remote, .. })
+            | Commands::Show { remote, .. }
+            | Commands::Thread(super::ThreadArgs { remote, .. }) => remote.timeout,
+            _ => None,
+        }
+    }
+
+    /// Runs the command line on the `[remote] url` server when one is
+    /// configured, else on the `ess sync --watch` daemon when one is
+    /// listening. `None` means the command should run in this process.
+    pub async fn run_on_daemon(cli: &Cli) -> Result<Option<ExitCode>> {
+        if cli.no_connect {
+            return Ok(None);
+        }
+        if !runs_on_daemon(&cli.command) {
+            return match cli.connect {
+                true => Err(anyhow!(
+                    "--connect supports ess search, list, show and thread"
+                )),
+                false => Ok(None),
+            };
+        }
+        let Some(args) = std::env::args_os()
+            .skip(1)
+            .map(|arg| arg.into_string().ok())
+            .collect::<Option<Vec<_>>>()
+        else {
+            return Ok(None);
+        };
+        let request = daemon::ClientRequest::new(args, output::table_style());
+        let config = load_config()?;
+        if let Some(url) = config.remote_url()? {
+            let token = daemon::remote_token();
+            let timeout = remote_timeout(&cli.command).unwrap_or(daemon::REMOTE_TIMEOUT);
+            return match daemon::request_http(url, token.as_deref(), timeout, &request).await? {
+                ClientResponse::Declined { reason } => Err(anyhow!(
+                    "the ess server at {url} declined the command: {reason}"
+                )),
+                response => print_daemon_response(response).map(Some),
+            };
+        }
+        let timeout = remote_timeout(&cli.command).unwrap_or(daemon::SOCKET_TIMEOUT);
+        match send_to_daemon(&request, timeout) {
+            Ok(Some(ClientResponse::Declined { reason })) if cli.connect => {
+                Err(anyhow!("the ess daemon declined the command: {reason}"))
+            }
+            Ok(None) if cli.connect => Err(anyhow!(
+                "no ess daemon is listening; start one with `ess sync --watch`"
+            )),
+            Err(error) if cli.connect => Err(error),
+            Err(error) => {
+                tracing::debug!("running locally after daemon request failed: {error:#}");
+                Ok(None)
+            }
+            Ok(Some(ClientResponse::Declined { .. })) | Ok(None) => Ok(None),
+            Ok(Some(response)) => print_daemon_response(response).map(Some),
+        }
+    }
+
+    /// Prints what a command run elsewhere wrote; a failed command also
+    /// prints its error and exits non-zero.
+    fn print_daemon_response(response: ClientResponse) -> Result<ExitCode> {
+        match response {
+            ClientResponse::Done { output } => {
+                write_daemon_output(&output)?;
+                Ok(ExitCode::SUCCESS)
+            }
+            ClientResponse::Failed { output, error } => {
+                write_daemon_output(&output)?;
+                eprintln!("Error: {error}");
+                Ok(ExitCode::FAILURE)
+            }
+            ClientResponse::Declined { reason } => Err(anyhow!("command declined: {reason}")),
+        }
+    }
+
+    #[cfg(unix)]
+    fn send_to_daemon(
+        request: &daemon::ClientRequest,
+        timeout: Duration,
+    ) -> Result<Option<ClientResponse>> {
+        let path = daemon::socket_path().context("resolve ess daemon socket path")?;
+        daemon::request(&path, request, timeout)
+            .with_context(|| format!("talk to the ess daemon at {}", path.display()))
+    }
+
+    #[cfg(not(unix))]
+    fn send_to_daemon(
+        _request: &daemon::ClientRequest,
+        _timeout: Duration,
+    ) -> Result<Option<ClientResponse>> {
+        Err(anyhow!("--connect needs Unix domain sockets"))
+    }
+
+    /// Prints the daemon's output; a reader that has gone away (`| head`)
+    /// is not an error, as with local output.
+    fn write_daemon_output(output: &str) -> Result<()> {
+        let mut stdout = std::io::stdout().lock();
+        match stdout
+            .write_all(output.as_bytes())
+            .and_then(|()| stdout.flush())
+        {
+            Err(error) if error.kind() != std::io::ErrorKind::BrokenPipe => Err(error.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Listens on the daemon socket for the life of the returned task.
+    /// Failing to listen only costs clients the shortcut, so it is logged
+    /// rather than returned.
+    #[cfg(unix)]
+    fn spawn_client_server() -> Option<tokio::task::JoinHandle<()>> {
+        let path = match daemon::socket_path() {
+            Ok(path) => path,
+            Err(error) => {
+                tracing::warn!("not serving ess clients: {error}");
+                return None;
+            }
+        };
+        let socket = match daemon::bind(&path) {
+            Ok(Some(socket)) => socket,
+            Ok(None) => {
+                tracing::warn!(
+                    "another ess daemon is serving clients on {}",
+                    path.display()
+                );
+                return None;
+            }
+            Err(error) => {
+                tracing::warn!("not serving ess clients on {}: {error}", path.display());
+                return None;
+            }
+        };
+        Some(tokio::spawn(async move {
+            daemon::serve(&socket, serve_client).await
+        }))
+    }
+
+    #[cfg(not(unix))]
+    fn spawn_client_server() -> Option<tokio::task::JoinHandle<()>> {
+        None
+    }
+
+    /// Serves remote clients over HTTP for the life of the returned task.
+    /// Unlike the socket, failing to listen is an error: it was asked for.
+    async fn spawn_remote_server(
+        addr: SocketAddr,
+        token: Option<String>,
+    ) -> Result<tokio::task::JoinHandle<()>> {
+        let token = token.or_else(daemon::remote_token);
+        let listener = daemon::bind_http(addr, token.as_deref()).await?;
+        eprintln!(
+            "Serving ess clients on http://{}{}",
+            listener
+                .local_addr()
+                .context("read client listener address")?,
+            daemon::HTTP_PATH
+        );
+        Ok(tokio::spawn(daemon::serve_http(
+            listener,
+            token,
+            serve_client,
+        )))
+    }
+
+    async fn serve_client(request: daemon::ClientRequest) -> ClientResponse {
+        let version = env!("CARGO_PKG_VERSION");
+        if request.version != version {
+            return ClientResponse::Declined {
+                reason: format!("the daemon runs ess {version}"),
+            };
+        }
+        // The database handles are not `Sync`, so each command runs on a
+        // blocking thread of its own rather than as a task.
+        let handle = tokio::runtime::Handle::current();
+        tokio::task::spawn_blocking(move || {
+            handle.block_on(output::with_table_style(
+                request.style,
+                run_client_command(request.args),
+            ))
+        })
+        .await
+        .unwrap_or_else(|error| ClientResponse::Failed {
+            output: String::new(),
+            error: format!("daemon task failed: {error}"),
+        })
+    }
+
+    async fn run_client_command(args: Vec<String>) -> ClientResponse {
+        let cli = match Cli::try_parse_from(std::iter::once("ess".to_string()).chain(args)) {
+            Ok(cli) if runs_on_daemon(&cli.command) => cli,
+            Ok(_) => {
+                return ClientResponse::Declined {
+                    reason: "only ess search, list, show and thread run on the daemon".to_string(),
+                }
+            }
+            Err(error) => {
+                return ClientResponse::Declined {
+                    reason: error.to_string(),
+                }
+            }
+        };
+        let mut out = Vec::new();
+        let result = match cli.command {
+            Commands::Search(args) => {
+                let open = || {
+                    let state = ServerState::shared()?;
+                    let db = state.database()?;
+                    let index = state.index(&db)?;
+                    Ok((db, index))
+                };
+                run_search(args, cli.scope, cli.json, open, &mut out).await
+            }
+            Commands::List(args) => {
+                let open = || ServerState::shared()?.database();
+                run_list(args, cli.scope, cli.json, open, &mut out).await
+            }
+            Commands::Show { id, format, .. } => ServerState::shared()
+                .and_then(|state| state.database())
+                .and_then(|db| format_shown_email(&db, &id, format, cli.json))
+                .and_then(|shown| Ok(writeln!(out, "{shown}")?)),
+            Commands::Thread(args) => {
+                let id = args.conversation_id.unwrap_or_default();
+                ServerState::shared()
+                    .and_then(|state| state.database())
+                    .and_then(|db| format_shown_thread(&db, &id, args.format, cli.json))
+                    .and_then(|shown| Ok(writeln!(out, "{shown}")?))
+            }
+            _ => unreachable!("checked by runs_on_daemon"),
+        };
+        let output = String::from_utf8_lossy(&out).into_owned();
+        match result {
+            Ok(()) => ClientResponse::Done { output },
+            Err(error) => ClientResponse::Failed {
+                output,
+                error: format!("{error:?}"),
+            },
+        }
+    }
+
+    /// Writes what `ess sync --record` captured to a new file in `dir`.
+    async fn save_recording(recorder: FixtureRecorder, dir: &Path) -> Result<()> {
+        let mut fixture = recorder.finish().await?;
+        let recorded_at = Utc::now();
+        fixture.description = format!(
+            "Recorded by ess sync --record at {}",
+            recorded_at.to_rfc3339_opts(SecondsFormat::Secs, true)
+        );
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("create fixture directory {}", dir.display()))?;
+        let path = dir.join(format!(
+            "sync-{}.json",
+            recorded_at.format("%Y%m%dT%H%M%SZ")
+        ));
+        fixture.save(&path)?;
+        eprintln!(
+            "Recorded {} HTTP exchanges to {}; it holds real mail, so review it before sharing",
+            fixture.exchanges.len(),
+            path.display()
+        );
+        Ok(())
+    }
+
+    /// Structured result of one sync cycle, printed as a single JSON line per
+    /// cycle with `--json`.
+    #[derive(Debug, Default, Serialize)]
+    struct SyncCycleReport {
+        status: SyncCycleStatus,
+        duration_ms: u64,
+        error_count: usize,
+        accounts: Vec<AccountSyncReport>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        tags: Option<TagReport>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        commitments: Option<CommitmentReport>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        trim: Option<TrimReport>,
+    }
+
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+    #[serde(rename_all = "snake_case")]
+    enum SyncCycleStatus {
+        #[default]
+        Completed,
+        Interrupted,
+        TimedOut,
+        Offline,
+        Failed,
+    }
+
+    impl SyncCycleStatus {
+        fn from_result<T>(result: &Result<T>) -> Self {
+            match result {
+                Ok(_) => Self::Completed,
+                Err(error) if error.is::<SyncInterrupted>() => Self::Interrupted,
+                Err(error) if error.is::<SyncTimedOut>() => Self::TimedOut,
+                Err(error) if error.is::<OfflineError>() => Self::Offline,
+                Err(_) => Self::Failed,
+            }
+        }
+    }
+
+    #[derive(Debug, Serialize)]
+    struct AccountSyncReport {
+        account_id: String,
+        connector: String,
+        skipped: bool,
+        duration_ms: u64,
+        #[serde(flatten)]
+        report: SyncReport,
+    }
+
+    /// Runs one cycle, prints its JSON report when requested (also when the
+    /// cycle failed part way), and returns the number of errors collected.
+    async fn run_reported_sync_cycle(
+        db: &Database,
+        index: &mut EmailIndex,
+        accounts: &[Account],
+        hooks: &SyncHooks,
+        timeout: Option<Duration>,
+        json: bool,
+    ) -> Result<usize> {
+        let started = Instant::now();
+        let mut cycle = SyncCycleReport::default();
+        let result =
+            run_sync_cycle_with_timeout(db, index, accounts, hooks, timeout, json, &mut cycle)
+                .await;
+        cycle.status = SyncCycleStatus::from_result(&result);
+        if let Some(rules) = &hooks.tag_rules {
+            match tags::tag_pending(db, rules, hooks.raw.as_ref()).await {
+                Ok(report) => {
+                    if !json && report.emails > 0 {
+                        eprintln!(
+                            "Tagged {} of {} new or changed email(s)",
+                            report.tagged, report.emails
+                        );
+                    }
+                    cycle.tags = Some(report);
+                }
+                Err(error) => tracing::warn!("failed to tag synced emails: {error:#}"),
+            }
+        }
+        match commitments::extract_pending(db, &hooks.commitments).await {
+            Ok(report) => {
+                if !json && report.found > 0 {
+                    eprintln!(
+                        "Found {} commitment(s) in {} sent email(s)",
+                        report.found, report.emails
+                    );
+                }
+                cycle.commitments = Some(report);
+            }
+            Err(error) => tracing::warn!("failed to scan sent mail for commitments: {error:#}"),
+        }
+        if cycle.status != SyncCycleStatus::Offline {
+            if let Some(cap) = hooks.store_cap {
+                let trimmed = RawStore::open_default()
+                    .context("resolve raw message directory")
+                    .and_then(|raw| trim::trim_after_sync(db, index, &raw, cap));
+                match trimmed {
+                    Ok(report) => {
+                        if !json && !report.trimmed.is_empty() {
+                            eprintln!(
+                                "Trimmed the bodies of {} email(s) to keep the store under {} MB",
+                                report.trimmed.len(),
+                                report.max_bytes / 1_000_000
+                            );
+                        }
+                        cycle.trim = Some(report);
+                    }
+                    Err(error) => tracing::warn!("failed to trim the store: {error:#}"),
+                }
+            }
+            record_sync_stats_snapshot(db, index);
+        }
+        cycle.duration_ms = elapsed_ms(started);
+        cycle.error_count = cycle
+            .accounts
+            .iter()
+            .map(|account| account.report.errors.len())
+            .sum();
+        if json {
+            println!("{}", serde_json::to_string(&cycle)?);
+        }
+        result.map(|()| cycle.error_count)
+    }
+
+    fn elapsed_ms(started: Instant) -> u64 {
+        u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX)
+    }
+
+    #[derive(Debug, thiserror::Error, PartialEq, Eq)]
+    #[error("sync finished with {count} error(s), more than --fail-on-errors={threshold} allows")]
+    struct SyncErrorsExceeded {
+        count: usize,
+        threshold: usize,
+    }
+
+    /// Turns a finished sync into a failure when `--fail-on-errors` was given
+    /// and the cycle collected more errors than it allows.
+    pub(super) fn check_error_threshold(count: usize, threshold: Option<usize>) -> Result<()> {
+        match threshold {
+            Some(threshold) if count > threshold => {
+                Err(SyncErrorsExceeded { count, threshold }.into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Per-run plumbing handed to every connector built for a sync.
+    #[derive(Default)]
+    struct SyncHooks {
+        progress: ProgressSink,
+        shutdown: ShutdownSignal,
+        /// Set when `[storage] raw_messages` is on.
+        raw: Option<RawStore>,
+        /// Accounts whose raw downloads this watch cycle puts off.
+        deferred_raw: HashSet<String>,
+        /// Set when `rules.toml` exists; new and changed emails are tagged
+        /// after each cycle.
+        tag_rules: Option<TagRules>,
+        /// Sent mail is scanned for commitments after each cycle.
+        commitments: CommitmentExtractor,
+        /// Set when `[storage] max_size_mb` is; the store is trimmed to it
+        /// after each cycle.
+        store_cap: Option<StoreCap>,
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("sync interrupted; committed work and resumable cursors were saved")]
+    struct SyncInterrupted;
+
+    /// First Ctrl-C asks connectors to stop at the next page boundary; a
+    /// second one aborts immediately.
+    fn spawn_interrupt_handler(shutdown: ShutdownSignal) {
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_err() {
+                return;
+            }
+            eprintln!(
+                "interrupt received: finishing the current page and saving sync state (press Ctrl-C again to abort)"
+            );
+            shutdown.request();
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(i32::from(EXIT_INTERRUPTED));
+            }
+        });
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("sync timed out after {}s; partial results were kept", .0.as_secs())]
+    struct SyncTimedOut(Duration);
+
+    /// Runs one sync cycle under an optional deadline. On expiry the in-flight
+    /// connector future is dropped, buffered index writes are committed so the
+    /// messages already stored in SQLite stay searchable, and a timeout error
+    /// is returned.
+    async fn run_sync_cycle_with_timeout(
+        db: &Database,
+        index: &mut EmailIndex,
+        accounts: &[Account],
+        hooks: &SyncHooks,
+        timeout: Option<Duration>,
+        json: bool,
+        cycle: &mut SyncCycleReport,
+    ) -> Result<()> {
+        let Some(timeout) = timeout else {
+            return run_sync_cycle_multi(db, index, accounts, hooks, json, cycle).await;
+        };
+
+        let outcome = tokio::time::timeout(
+            timeout,
+            run_sync_cycle_multi(db, index, accounts, hooks, json, cycle),
+        )
+        .await;
+        match outcome {
+            Ok(result) => result,
+            Err(_) => {
+                index
+                    .commit()
+                    .context("commit partial index writes after sync timeout")?;
+                Err(SyncTimedOut(timeout).into())
+            }
+        }
+    }
+
+    async fn handle_import(args: super::ImportArgs, json: bool) -> Result<()> {
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        let db = Database::open(&db_path)
+            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+        let mut index = open_index_with_recovery(&db)?;
+        let account = resolve_single_account(&db, args.account.as_deref())?;
+
+        let connector = JsonArchiveConnector::new()
+            .with_payloads(load_config()?.storage.raw_messages.unwrap_or(false));
+        let report = connector
+            .import(&db, &mut index, std::path::Path::new(&args.path), &account)
+            .await
+            .with_context(|| format!("import archive path {}", args.path))?;
+
+        if json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            println!("Import complete");
+            println!("Files processed: {}", report.files_processed);
+            println!("Emails imported: {}", report.emails_imported);
+            if report.quarantined > 0 {
+                println!(
+                    "Quarantined: {} (see `ess quarantine list`)",
+                    report.quarantined
+                );
+            }
+            if report.errors.is_empty() {
+                println!("Errors: 0");
+            } else {
+                println!("Errors: {}", report.errors.len());
+                for error in report.errors {
+                    println!("- {error}");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_quarantine(command: QuarantineCommands, json: bool) -> Result<()> {
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        let db = Database::open(&db_path)
+            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+
+        match command {
+            QuarantineCommands::List { account, limit } => {
+                let entries = db.quarantined(account.as_deref(), limit)?;
+                println!(
+                    "{}",
+                    output::format_quarantine(OutputFormat::from_json_flag(json), &entries)?
+                );
+            }
+            QuarantineCommands::Retry { ids, all, account } => {
+                let entries = if all {
+                    db.quarantined(account.as_deref(), None)?
+                } else {
+                    ids.iter()
+                        .map(|id| {
+                            db.quarantined_message(*id)?
+                                .ok_or_else(|| anyhow!("no quarantined message with id {id}"))
+                        })
+                        .collect::<Result<Vec<_>>>()?
+                };
+                let keep_payloads = load_config()?.storage.raw_messages.unwrap_or(false);
+                let mut index = open_index_with_recovery(&db)?;
+                let report = quarantine::retry(&db, &mut index, &entries, keep_payloads)?;
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&report)?);
+                } else {
+                    println!(
+                        "Retried {} quarantined messages: {} stored, {} already stored, {} still failing.",
+                        entries.len(),
+                        report.released.len(),
+                        report.already_stored.len(),
+                        report.failed.len()
+                    );
+                    for failure in &report.failed {
+                        println!("- #{}: {}", failure.id, failure.error);
+                    }
+                }
+            }
+            QuarantineCommands::Export { account, output } => {
+                let entries = db.quarantined(account.as_deref(), None)?;
+                match output {
+                    Some(path) => {
+                        let mut file = std::io::BufWriter::new(
+                            std::fs::File::create(&path)
+                                .with_context(|| format!("create {}", path.display()))?,
+                        );
+                        output::json::write_ndjson(&mut file, &entries)?;
+                        file.flush()
+                            .with_context(|| format!("write {}", path.display()))?;
+                        if json {
+                            println!(
+                                "{}",
+                                serde_json::to_string_pretty(&serde_json::json!({
+                                    "path": path,
+                                    "exported": entries.len(),
+                                }))?
+                            );
+                        } else {
+                            println!(
+                                "Exported {} quarantined messages to {}",
+                                entries.len(),
+                                path.display()
+                            );
+                        }
+                    }
+                    None => {
+                        print_ndjson(&mut std::io::stdout().lock(), &entries)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_reprocess(args: super::ReprocessArgs, json: bool) -> Result<()> {
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        let db = Database::open(&db_path)
+            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+        let mut index = open_index_with_recovery(&db)?;
+        if let Some(account) = args.account.as_deref() {
+            if db.get_account(account)?.is_none() {
+                return Err(anyhow!("account not found: {account}"));
+            }
+        }
+        let raw = RawStore::open_default().context("resolve raw message directory")?;
+        let options = ReprocessOptions {
+            account: args.account,
+            since: parse_date_arg("since", args.since)?,
+            until: parse_date_arg("until", args.until)?,
+            dry_run: args.dry_run,
+        };
+        let report = reprocess::reprocess(&db, &mut index, &raw, &options)?;
+
+        if json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            let verb = if report.dry_run {
+                "would be updated"
+            } else {
+                "updated"
+            };
+            println!(
+                "Reprocessed {} emails: {} {verb}, {} unchanged, {} without a stored payload.",
+                report.examined, report.updated, report.unchanged, report.skipped
+            );
+            if !report.errors.is_empty() {
+                println!("Errors: {}", report.errors.len());
+                for error in &report.errors {
+                    println!("- {error}");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_dedupe(apply: bool, json: bool) -> Result<()> {
+        let db = open_local_db()?;
+        let groups = db.find_duplicates()?;
+        let copies: usize = groups.iter().map(|group| group.remove.len()).sum();
+        let removed = if apply {
+            let mut index = open_index_with_recovery(&db)?;
+            let removed = db.remove_duplicates(&groups)?;
+            for id in groups.iter().flat_map(|group| &group.remove) {
+                index.delete_email_buffered(id)?;
+            }
+            index.commit()?;
+            removed
+        } else {
+            0
+        };
+
+        if json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&serde_json::json!({
+                    "dry_run": !apply,
+                    "groups": groups,
+                    "duplicates": copies,
+                    "removed": removed,
+                    "unique_index": db.has_unique_message_ids()?,
+                }))?
+            );
+            return Ok(());
+        }
+        if groups.is_empty() {
+            println!("No duplicate emails found.");
+        } else {
+            for group in &groups {
+                println!(
+                    "keep {}, remove {}: {}",
+                    group.keep,
+                    group.remove.join(", "),
+                    group.subject.as_deref().unwrap_or("(no subject)")
+                );
+            }
+            if apply {
+                println!(
+                    "Removed {removed} duplicate email(s) from {} message(s).",
+                    groups.len()
+                );
+            } else {
+                println!(
+                    "{copies} duplicate email(s) of {} message(s); run `ess dedupe --apply` to remove them.",
+                    groups.len()
+                );
+            }
+        }
+        if !db.has_unique_message_ids()? {
+            println!(
+                "New copies are still allowed until `ess dedupe --apply` adds the unique Message-ID index."
+            );
+        }
+        Ok(())
+    }
+
+    fn handle_devtools(command: DevtoolsCommands, json: bool) -> Result<()> {
+        let DevtoolsCommands::Seed {
+            emails,
+            accounts,
+            seed,
+            force,
+        } = command;
+        if accounts == 0 {
+            return Err(anyhow!("--accounts must be at least 1"));
+        }
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        let db = Database::open(&db_path)
+            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+        let existing = db.get_stats()?.total_emails;
+        if existing > 0 && !force {
+            return Err(anyhow!(
+                "{} already holds {existing} emails; pass --force to add synthetic mail to it, \
+                 or point HOME at a scratch directory (HOME=/tmp/ess-demo ess devtools seed)",
+                db_path.display()
+            ));
+        }
+        let mut index = open_index_with_recovery(&db)?;
+        let options = SeedOptions {
+            emails,
+            accounts,
+            seed,
+        };
+        let report = devtools::seed(&db, &mut index, &options)?;
+
+        if json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            println!(
+                "Seeded {} emails in {} threads across {} accounts ({} contacts, {} attachments, seed {}).",
+                report.emails,
+                report.threads,
+                report.accounts,
+                report.contacts,
+                report.attachments,
+                report.seed
+            );
+        }
+        Ok(())
+    }
+
+    async fn handle_contacts(args: super::ContactsArgs, json: bool) -> Result<()> {
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        let db = Database::open(&db_path)
+            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+        if let Some(ContactsCommands::Enrich { local }) = args.command {
+            if !local {
+                return Err(anyhow!(
+                    "only local enrichment is available; run `ess contacts enrich --local`"
+                ));
+            }
+            let report = contacts::enrich_local(&db)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else if report.contacts == 0 {
+                println!("No contact details to add from stored mail");
+            } else {
+                println!(
+                    "Enriched {} contact(s): {} name(s), {} compan{}, {} phone number(s)",
+                    report.contacts,
+                    report.names,
+                    report.companies,
+                    if report.companies == 1 { "y" } else { "ies" },
+                    report.phones
+                );
+            }
+            return Ok(());
+        }
+        let contacts = db.get_contacts(args.query.as_deref())?;
+        if args.enrich {
+            eprintln!(
+                "--enrich is not implemented yet; showing current contact data (`ess contacts enrich --local` fills names, companies and phones from stored mail)"
+            );
+        }
+        match tabular_format(args.format, json) {
+            Rendering::Formatted(format) => {
+                println!("{}", output::format_contacts(format, &contacts)?);
+            }
+            Rendering::Delimited(delimiter) => {
+                print!("{}", output::csv::format_contacts(&contacts, delimiter));
+            }
+            Rendering::Ndjson => {
+                print_ndjson(&mut std::io::stdout().lock(), &contacts)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_mutt_query(args: super::MuttQueryArgs) -> Result<()> {
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        let db = Database::open(&db_path)
+            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+        let contacts = db.get_contacts(Some(&args.query))?;
+        print!("{}", output::mutt::format_query(&contacts, !args.aerc));
+        Ok(())
+    }
+
+    fn handle_complete_address(fragment: &str, limit: usize, json: bool) -> Result<()> {
+        let db = open_local_db()?;
+        let matches = contacts::complete_address(&db, fragment, limit)?;
+        if json {
+            println!("{}", serde_json::to_string_pretty(&matches)?);
+        } else {
+            for found in &matches {
+                println!("{}", found.formatted);
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle_accounts(command: AccountCommands) -> Result<()> {
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        let db = Database::open(&db_path)
+            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+
+        match command {
+            AccountCommands::List => {
+                let accounts = db.list_accounts()?;
+                if accounts.is_empty() {
+                    println!("No accounts configured.");
+                } else {
+                    println!("Accounts");
+                    println!("========");
+                    for account in accounts {
+                        println!(
+                            "{}  {}  {}{}",
+                            account.account_id,
+                            account.email_address,
+                            account.account_type,
+                            if account.enabled { "" } else { "  (disabled)" }
+                        );
+                    }
+                }
+            }
+            AccountCommands::Add {
+                email,
+                account_type,
+                tenant_id,
+                config,
+            } => {
+                let parsed_config = config
+                    .map(|raw| {
+                        serde_json::from_str::<serde_json::Value>(&raw)
+                            .context("parse --config JSON")
+                    })
+                    .transpose()?;
+                let account = Account {
+                    account_id: email.trim().to_ascii_lowercase(),
+                    email_address: email,
+                    display_name: None,
+                    tenant_id,
+                    account_type: map_account_type(account_type),
+                    enabled: true,
+                    last_sync: None,
+                    config: parsed_config,
+                };
+                db.insert_account(&account)?;
+                println!("Added account: {}", account.account_id);
+            }
+            AccountCommands::Remove { account_id } => {
+                let removed = db.remove_account(&account_id)?;
+                if removed == 0 {
+                    println!("No account found: {account_id}");
+                } else {
+                    println!("Removed account: {account_id}");
+                }
+            }
+            AccountCommands::Enable { account_id } => set_account_enabled(&db, &account_id, true)?,
+            AccountCommands::Disable { account_id } => {
+                set_account_enabled(&db, &account_id, false)?
+            }
+            AccountCommands::SyncStatus { folders } => {
+                let accounts = db.list_accounts()?;
+                if accounts.is_empty() {
+                    println!("No accounts configured.");
+                } else {
+                    println!("Account Sync Status");
+                    println!("===================");
+                    for account in accounts {
+                        println!(
+                            "{}  enabled={}  last_sync={}",
+                            account.account_id,
+                            account.enabled,
+                            account.last_sync.as_deref().unwrap_or("never")
+                        );
+                        print_sync_health(&db, &account)?;
+                        if folders {
+                            print_folder_sync_states(&db, &account.account_id)?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn print_sync_health(db: &Database, account: &Account) -> Result<()> {
+        let health = db.sync_health(&account.account_id)?;
+        let Some(last_run) = &health.last_run_at else {
+            return Ok(());
+        };
+        println!(
+            "  last_success={}  last_run={last_run} ({:.1}s)  messages_synced={}",
+            health.last_success_at.as_deref().unwrap_or("never"),
+            health.last_duration_ms.unwrap_or(0) as f64 / 1000.0,
+            health.emails_synced
+        );
+        if let (Some(error), Some(at)) = (&health.last_error, &health.last_error_at) {
+            println!("  last_error={error} (at {at})");
+        }
+        if account.enabled && health.is_stale(Utc::now()) {
+            println!(
+                "  warning: no successful sync in the last {} hours",
+                ess::db::sync_runs::STALE_AFTER_HOURS
+            );
+        }
+        Ok(())
+    }
+
+    fn print_folder_sync_states(db: &Database, account_id: &str) -> Result<()> {
+        let states = db.folder_sync_states(Some(account_id))?;
+        if states.is_empty() {
+            println!("  no folder syncs recorded");
+        }
+        for state in states {
+            println!(
+                "  {}  added={}  updated={}  last_run={}  last_delta={}",
+                state.folder,
+                state.emails_added,
+                state.emails_updated,
+                state.last_run_at,
+                state.last_delta_at.as_deref().unwrap_or("never")
+            );
+            if let Some(error) = &state.last_error {
+                println!(
+                    "    last_error={error}  failures_in_a_row={}",
+                    state.consecutive_failures
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn set_account_enabled(db: &Database, account_id: &str, enabled: bool) -> Result<()> {
+        if !db.set_account_enabled(account_id, enabled)? {
+            return Err(anyhow!("account not found: {account_id}"));
+        }
+        let verb = if enabled { "Enabled" } else { "Disabled" };
+        println!("{verb} account: {account_id}");
+        if matches!(running_watcher(), Ok(Some(_))) {
+            println!("Run 'ess daemon reload' for a running `ess sync --watch` to pick it up.");
+        }
+        Ok(())
+    }
+
+    async fn handle_config(command: ConfigCommands, json: bool) -> Result<()> {
+        match command {
+            ConfigCommands::Check => handle_config_check(json),
+        }
+    }
+
+    fn handle_secrets(command: SecretsCommands, json: bool) -> Result<()> {
+        match command {
+            SecretsCommands::RotateKey => handle_rotate_key(json),
+            SecretsCommands::GenerateKey => {
+                println!("{}", secrets::generate_key_hex()?);
+                Ok(())
+            }
+            SecretsCommands::Set { account_id, key } => {
+                let mut value = String::new();
+                std::io::stdin()
+                    .read_to_string(&mut value)
+                    .context("read credential from stdin")?;
+                let value = value.trim();
+                if value.is_empty() {
+                    return Err(anyhow!("no credential on stdin"));
+                }
+                Keychain
+                    .set(&keychain::entry_name(&account_id, &key), value)
+                    .with_context(|| format!("store {key} of {account_id}"))?;
+                println!("Stored {key} of {account_id} in the OS keychain");
+                Ok(())
+            }
+            SecretsCommands::Delete { account_id, key } => {
+                let deleted = Keychain
+                    .delete(&keychain::entry_name(&account_id, &key))
+                    .with_context(|| format!("delete {key} of {account_id}"))?;
+                if deleted {
+                    println!("Deleted {key} of {account_id} from the OS keychain");
+                } else {
+                    println!("No {key} of {account_id} in the OS keychain");
+                }
+                Ok(())
+            }
+            SecretsCommands::Migrate { account } => {
+                handle_migrate_secrets(account.as_deref(), json)
+            }
+        }
+    }
+
+    fn handle_migrate_secrets(account_id: Option<&str>, json: bool) -> Result<()> {
+        let db = open_local_db()?;
+        let mut migrated = Vec::new();
+        // Disabled accounts keep their credentials, so they are migrated too.
+        let accounts = match account_id {
+            Some(account_id) => vec![db
+                .get_account(account_id)?
+                .ok_or_else(|| anyhow!("account not found: {account_id}"))?],
+            None => db.list_accounts()?,
+        };
+        for account in accounts {
+            let moved = secrets::migrate_credentials(&db, &Keychain, &account)
+                .with_context(|| format!("migrate credentials of {}", account.account_id))?;
+            if !json {
+                if moved.is_empty() {
+                    println!("{}: no credentials in config", account.account_id);
+                } else {
+                    println!("{}: moved {}", account.account_id, moved.join(", "));
+                }
+            }
+            migrated.push(serde_json::json!({
+                "account_id": account.account_id,
+                "moved": moved,
+            }));
+        }
+        if json {
+            println!("{}", serde_json::to_string_pretty(&migrated)?);
+        }
+        Ok(())
+    }
+
+    fn handle_db(command: DbCommands, json: bool) -> Result<()> {
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        refuse_while_served()?;
+        let (conversion, done, unchanged) = match command {
+            DbCommands::Encrypt => (
+                encryption::encrypt_database(&db_path).context("encrypt database")?,
+                "encrypted",
+                "already encrypted",
+            ),
+            DbCommands::Decrypt => (
+                encryption::decrypt_database(&db_path).context("decrypt database")?,
+                "decrypted",
+                "not encrypted",
+            ),
+        };
+        let converted = conversion == Conversion::Converted;
+        if json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&serde_json::json!({
+                    "path": db_path,
+                    "converted": converted,
+                    "encrypted": encryption::is_encrypted(&db_path)?,
+                }))?
+            );
+        } else if converted {
+            println!("{done} {}", db_path.display());
+        } else {
+            println!("{} is {unchanged}", db_path.display());
+        }
+        Ok(())
+    }
+
+    /// Errors while a watcher or daemon has the database open: converting
+    /// replaces the file, and their writes would go to the old one.
+    fn refuse_while_served() -> Result<()> {
+        if let Some(pid) = running_watcher()? {
+            return Err(anyhow!(
+                "ess sync --watch (pid {pid}) has the database open; stop it and try again"
+            ));
+        }
+        #[cfg(unix)]
+        {
+            let path = daemon::socket_path().context("resolve ess daemon socket path")?;
+            if daemon::is_serving(&path) {
+                return Err(anyhow!(
+                    "an ess daemon is serving on {}; stop it and try again",
+                    path.display()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_rotate_key(json: bool) -> Result<()> {
+        let keys = TokenCacheKeys::from_env()?.ok_or_else(|| {
+            anyhow!(
+                "{} is not set; set it to the new key (and {} to the old one) before rotating",
+                env::TOKEN_CACHE_KEY,
+                env::TOKEN_CACHE_KEY_PREVIOUS
+            )
+        })?;
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        let db = Database::open(&db_path)
+            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+        let report = secrets::rotate_token_cache(&db, &keys).context("rotate token cache")?;
+
+        if json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            println!(
+                "re-encrypted {} cached token(s), {} already current, {} discarded",
+                report.reencrypted, report.unchanged, report.discarded
+            );
+            if keys.has_previous() {
+                println!(
+                    "all cached tokens now use {}; {} can be unset",
+                    env::TOKEN_CACHE_KEY,
+                    env::TOKEN_CACHE_KEY_PREVIOUS
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_config_check(json: bool) -> Result<()> {
+        let mut report = ConfigCheckReport::default();
+        let config_path = Config::default_path().context("resolve default ESS config path")?;
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        let index_path =
+            EmailIndex::default_index_path().context("resolve default ESS index path")?;
+
+        let config = config_check::check_config_file(&config_path, &mut report);
+        let env = config_check::check_env(&mut report);
+
+        if let Some(data_dir) = db_path.parent() {
+            config_check::check_path("data directory", data_dir, true, &mut report);
+        }
+        config_check::check_path("database", &db_path, true, &mut report);
+        config_check::check_path("index", &index_path, false, &mut report);
+        if config_path.exists() {
+            config_check::check_path("config file", &config_path, true, &mut report);
+        }
+        let rules_path = tags::default_rules_path()?;
+        match TagRules::load(&rules_path) {
+            Ok(Some(rules)) => report.push(
+                "config",
+                rules_path.display().to_string(),
+                CheckStatus::Ok,
+                format!("{} tagging rule(s)", rules.rules().len()),
+            ),
+            Ok(None) => {}
+            Err(error) => report.push(
+                "config",
+                rules_path.display().to_string(),
+                CheckStatus::Error,
+                format!("{error:#}"),
+            ),
+        }
+
+        let accounts = if db_path.exists() {
+            let db = Database::open(&db_path)
+                .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+            db.list_accounts()?
+        } else {
+            Vec::new()
+        };
+        if accounts.is_empty() {
+            report.push(
+                "credentials",
+                "accounts",
+                CheckStatus::Warn,
+                "no accounts registered; use 'ess accounts add'",
+            );
+        }
+        let mut effective_accounts = Vec::new();
+        for account in &accounts {
+            let connector = connector_for_account(account, &SyncHooks::default());
+            match connector.check_credentials(account) {
+                Ok(()) => report.push(
+                    "credentials",
+                    &account.account_id,
+                    CheckStatus::Ok,
+                    format!("{} credentials complete", connector.name()),
+                ),
+                Err(error) => report.push(
+                    "credentials",
+                    &account.account_id,
+                    CheckStatus::Error,
+                    error.to_string(),
+                ),
+            }
+            effective_accounts.push(serde_json::json!({
+                "account_id": account.account_id,
+                "email": account.email_address,
+                "type": account.account_type.to_string(),
+                "connector": connector.name(),
+                "tenant_id": account.tenant_id,
+                "enabled": account.enabled,
+                "config": account.config.as_ref().map(config_check::mask_json),
+            }));
+        }
+        if let Some(config) = &config {
+            for (name, entry) in &config.accounts {
+                let id = entry
+                    .account_id
+                    .clone()
+                    .or_else(|| entry.email.as_ref().map(|email| email.to_ascii_lowercase()));
+                let registered = id
+                    .as_deref()
+                    .is_some_and(|id| accounts.iter().any(|account| account.account_id == id));
+                if !registered {
+                    report.push(
+                        "config",
+                        format!("accounts.{name}"),
+                        CheckStatus::Warn,
+                        "declared in config.toml but not registered; run 'ess accounts add'",
+                    );
+                }
+            }
+        }
+
+        let default_scope = config
+            .as_ref()
+            .and_then(|config| config.general.default_scope.clone())
+            .unwrap_or_else(|| "all".to_string());
+        report.effective.insert(
+            "paths".to_string(),
+            serde_json::json!({
+                "config": config_path,
+                "database": db_path,
+                "index": index_path,
+            }),
+        );
+        report.effective.insert(
+            "general".to_string(),
+            serde_json::json!({
+                "default_scope": default_scope,
+                "work_hours": config.as_ref().and_then(|config| config.general.work_hours.clone()),
+            }),
+        );
+        report.effective.insert(
+            "defaults".to_string(),
+            serde_json::to_value(config.as_ref().map(|config| &config.defaults))?,
+        );
+        report.effective.insert(
+            "index".to_string(),
+            serde_json::to_value(config.as_ref().map(|config| &config.index))?,
+        );
+        report
+            .effective
+            .insert("accounts".to_string(), effective_accounts.into());
+        report
+            .effective
+            .insert("env".to_string(), serde_json::Value::Object(env));
+
+        if json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            println!("Configuration Check");
+            println!("===================");
+            for check in &report.checks {
+                println!(
+                    "[{:<5}] {:<11} {}: {}",
+                    check.status, check.area, check.subject, check.message
+                );
+            }
+            println!();
+            println!("Effective Configuration (secrets masked)");
+            println!("========================================");
+            println!("{}", serde_json::to_string_pretty(&report.effective)?);
+        }
+
+        let errors = report
+            .checks
+            .iter()
+            .filter(|check| check.status == CheckStatus::Error)
+            .count();
+        if errors > 0 {
+            return Err(anyhow!("configuration check found {errors} error(s)"));
+        }
+        Ok(())
+    }
+
+    fn handle_env(json: bool) -> Result<()> {
+        let rendered = output::format_env(OutputFormat::from_json_flag(json), &env::snapshot())?;
+        println!("{rendered}");
+        Ok(())
+    }
+
+    fn handle_analytics(args: super::AnalyticsArgs, json: bool) -> Result<()> {
+        let db = open_local_db()?;
+        let filters = AnalyticsFilters {
+            account_id: args.account,
+            since: parse_date_arg("since", args.since)?,
+            until: parse_date_arg("until", args.until)?,
+        };
+        let interval = match args.by {
+            AnalyticsPeriod::Week => AnalyticsInterval::Week,
+            AnalyticsPeriod::Month => AnalyticsInterval::Month,
+        };
+        let analytics = db.analytics(&filters, interval, args.top)?;
+        let formatted = output::format_analytics(OutputFormat::from_json_flag(json), &analytics)?;
+        println!("{formatted}");
+        Ok(())
+    }
+
+    async fn handle_stats(args: super::StatsArgs, json: bool) -> Result<()> {
+        let (format, delimiter) = match tabular_format(args.format, json) {
+            Rendering::Formatted(format) => (format, None),
+            Rendering::Delimited(delimiter) => (OutputFormat::Table, Some(delimiter)),
+            Rendering::Ndjson => {
+                return Err(anyhow!("--format ndjson only applies to list and contacts"));
+            }
+        };
+        if args.usage {
+            return handle_stats_usage(args.trend, format, delimiter);
+        }
+        if delimiter.is_some() && args.trend.is_some() {
+            return Err(anyhow!(
+                "--format csv and tsv cannot be combined with --trend"
+            ));
+        }
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        let db = Database::open(&db_path)
+            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+        let index = open_index_with_recovery(&db)?;
+        let db_stats = db.get_stats()?;
+        let index_stats = index.get_stats()?;
+        db.record_stats_snapshot(&StatsSnapshot::new(
+            snapshot_timestamp(Utc::now()),
+            &db_stats,
+            index_stats.doc_count,
+            index_stats.index_size_bytes,
+        ))?;
+
+        let trend = match args.trend {
+            Some(window) => {
+                let since = trend_start(window)?;
+                let snapshots = db.stats_snapshots_since(&snapshot_timestamp(since))?;
+                StatsTrend::from_snapshots(&snapshots)
+            }
+            None => None,
+        };
+
+        if let Some(delimiter) = delimiter {
+            print!(
+                "{}",
+                output::csv::format_stats(
+                    &db_stats,
+                    index_stats.doc_count,
+                    index_stats.index_size_bytes,
+                    delimiter
+                )
+            );
+        } else if format == OutputFormat::Json {
+            #[derive(Serialize)]
+            struct StatsPayload {
+                database: ess::db::DatabaseStats,
+                index_doc_count: u64,
+                index_size_bytes: u64,
+                index_segments: usize,
+                index_deleted_docs: u64,
+                index_opstamp: u64,
+                #[serde(skip_serializing_if = "Option::is_none")]
+                trend: Option<StatsTrend>,
+            }
+            let payload = StatsPayload {
+                database: db_stats,
+                index_doc_count: index_stats.doc_count,
+                index_size_bytes: index_stats.index_size_bytes,
+                index_segments: index_stats.segment_count,
+                index_deleted_docs: index_stats.deleted_doc_count,
+                index_opstamp: index_stats.last_commit_opstamp,
+                trend,
+            };
+            println!("{}", serde_json::to_string_pretty(&payload)?);
+        } else {
+            let rendered = output::format_stats(OutputFormat::Table, &db_stats)?;
+            println!("{rendered}");
+            println!("Index Docs: {}", index_stats.doc_count);
+            println!("Index Size (bytes): {}", index_stats.index_size_bytes);
+            if let Some(trend) = &trend {
+                println!();
+                println!(
+                    "{}",
+                    output::format_stats_trend(OutputFormat::Table, trend)?
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// The start of a `--trend` window ending now.
+    fn trend_start(window: Duration) -> Result<DateTime<Utc>> {
+        chrono::Duration::from_std(window)
+            .ok()
+            .and_then(|window| Utc::now().checked_sub_signed(window))
+            .ok_or_else(|| anyhow!("trend window out of range"))
+    }
+
+    fn handle_stats_usage(
+        window: Option<Duration>,
+        format: OutputFormat,
+        delimiter: Option<Delimiter>,
+    ) -> Result<()> {
+        let since = match window {
+            Some(window) => trend_start(window)?,
+            None => Utc::now() - chrono::Duration::days(DEFAULT_USAGE_DAYS - 1),
+        };
+        let db = open_local_db()?;
+        let usage = db.sync_usage_since(since.date_naive())?;
+        match delimiter {
+            Some(delimiter) => print!("{}", output::csv::format_sync_usage(&usage, delimiter)),
+            None => println!("{}", output::format_sync_usage(format, &usage)?),
+        }
+        Ok(())
+    }
+
+    /// How list, contacts and stats print, from `--format` or `--json`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Rendering {
+        Formatted(OutputFormat),
+        Delimited(Delimiter),
+        Ndjson,
+    }
+
+    fn tabular_format(format: Option<TabularFormat>, json: bool) -> Rendering {
+        match format {
+            Some(TabularFormat::Table) => Rendering::Formatted(OutputFormat::Table),
+            Some(TabularFormat::Json) => Rendering::Formatted(OutputFormat::Json),
+            Some(TabularFormat::Csv) => Rendering::Delimited(Delimiter::Comma),
+            Some(TabularFormat::Tsv) => Rendering::Delimited(Delimiter::Tab),
+            Some(TabularFormat::Ndjson) => Rendering::Ndjson,
+            None => Rendering::Formatted(OutputFormat::from_json_flag(json)),
+        }
+    }
+
+    /// Writes NDJSON records as they come; `false` once the reader has gone
+    /// away (`| head`), which ends a listing quietly.
+    fn print_ndjson<T: serde::Serialize>(out: &mut impl Write, records: &[T]) -> Result<bool> {
+        let written = output::json::write_ndjson(out, records)
+            .and_then(|()| out.flush().map_err(anyhow::Error::from));
+        match written {
+            Ok(()) => Ok(true),
+            Err(error) if is_broken_pipe(&error) => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn is_broken_pipe(error: &anyhow::Error) -> bool {
+        let kind = error
+            .downcast_ref::<std::io::Error>()
+            .map(std::io::Error::kind)
+            .or_else(|| {
+                error
+                    .downcast_ref::<serde_json::Error>()
+                    .and_then(serde_json::Error::io_error_kind)
+            });
+        kind == Some(std::io::ErrorKind::BrokenPipe)
+    }
+
+    /// Second-precision UTC, so snapshot timestamps sort as text.
+    fn snapshot_timestamp(at: DateTime<Utc>) -> String {
+        at.to_rfc3339_opts(SecondsFormat::Secs, true)
+    }
+
+    /// Keeps the `ess stats --trend` history current after every sync; a
+    /// failure here must not fail the sync itself.
+    fn record_sync_stats_snapshot(db: &Database, index: &EmailIndex) {
+        let snapshot = db
+            .get_stats()
+            .map_err(anyhow::Error::from)
+            .and_then(|db_stats| {
+                let index_stats = index.get_stats()?;
+                Ok(StatsSnapshot::new(
+                    snapshot_timestamp(Utc::now()),
+                    &db_stats,
+                    index_stats.doc_count,
+                    index_stats.index_size_bytes,
+                ))
+            });
+        if let Err(error) = snapshot.and_then(|snapshot| Ok(db.record_stats_snapshot(&snapshot)?)) {
+            tracing::warn!("failed to record stats snapshot after sync: {error:#}");
+        }
+    }
+
+    async fn handle_reindex(json: bool) -> Result<()> {
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        let db = Database::open(&db_path)
+            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+        let mut index = open_index_with_recovery(&db)?;
+        let started = Instant::now();
+        let indexed = index.reindex(&db)?;
+        let duration_ms = elapsed_ms(started);
+        if json {
+            let report = serde_json::json!({
+                "emails_indexed": indexed,
+                "duration_ms": duration_ms,
+            });
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            println!("Reindex complete: {indexed} emails indexed.");
+        }
+        Ok(())
+    }
+
+    /// Trims to `cap` the database, the index and the raw store, whether or
+    /// not raw messages are still being kept.
+    fn trim_store(
+        db: &Database,
+        index: &mut EmailIndex,
+        cap: StoreCap,
+        dry_run: bool,
+    ) -> Result<TrimReport> {
+        let raw = RawStore::open_default().context("resolve raw message directory")?;
+        trim::trim_store(db, index, &raw, cap, dry_run)
+    }
+
+    fn handle_trim(dry_run: bool, json: bool) -> Result<()> {
+        let Some(cap) = load_config()?.store_cap()? else {
+            return Err(anyhow!(
+                "no store size cap; set [storage] max_size_mb in config.toml"
+            ));
+        };
+        let db = open_local_db()?;
+        let mut index = open_index_with_recovery(&db)?;
+        let report = trim_store(&db, &mut index, cap, dry_run)?;
+        let format = if json {
+            OutputFormat::Json
+        } else {
+            OutputFormat::Table
+        };
+        println!("{}", output::format_trim_report(format, &report)?);
+        Ok(())
+    }
+
+    fn handle_replicate(peer: &str, full: bool, json: bool) -> Result<()> {
+        let peer: Peer = peer.parse()?;
+        let db = open_local_db()?;
+        let report = replicate::replicate(&db, &peer, full)
+            .with_context(|| format!("replicate to {peer}"))?;
+        if json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            println!(
+                "Shipped {} change(s) to {}: {} applied, {} kept as changed there later",
+                report.shipped, report.peer, report.peer_report.applied, report.peer_report.skipped
+            );
+            if report.peer_report.accounts_added > 0 {
+                println!(
+                    "Added {} account(s) there, disabled",
+                    report.peer_report.accounts_added
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// The other end of `ess replicate --peer ssh://...`: applies the changes
+    /// on stdin, tags the emails it took under this machine's rules and
+    /// prints what it did as one JSON line.
+    async fn handle_receive_replication(store: Option<&Path>) -> Result<()> {
+        let (db, mut index) = match store {
+            Some(dir) => replicate::open_store(dir)?,
+            None => {
+                let db = *open_local_db()?;
+                let index = open_index_with_recovery(&db)?;
+                (db, index)
+            }
+        };
+        let report = replicate::apply_changes(&db, &mut index, std::io::stdin().lock())?;
+        if let Some(rules) = TagRules::load_default()? {
+            if let Err(error) =
+                tags::tag_pending(&db, &rules, raw_store_if_enabled()?.as_ref()).await
+            {
+                tracing::warn!("failed to tag replicated emails: {error:#}");
+            }
+        }
+        println!("{}", serde_json::to_string(&report)?);
+        Ok(())
+    }
+
+    fn handle_warm(json: bool) -> Result<()> {
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        let db = Database::open(&db_path)
+            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+        let index = open_index_with_recovery(&db)?;
+        let report = index.warm(WARM_QUERIES).context("warm ESS index")?;
+        if json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            println!(
+                "Warmed {} segment(s): read {} files ({} bytes) in {} ms.",
+                report.segments, report.files, report.bytes_read, report.duration_ms
+            );
+            for timing in &report.queries {
+                let label = if timing.query.is_empty() {
+                    "(recent)"
+                } else {
+                    timing.query.as_str()
+                };
+                println!(
+                    "  {label:<10} {:>4} hits  {:>5} ms",
+                    timing.hits, timing.duration_ms
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_export(args: super::ExportArgs, scope: Option<Scope>, json: bool) -> Result<()> {
+        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
+        let db = Database::open(&db_path)
+            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+
+        let index = match (&args.table, &args.query) {
+            (ExportTable::Emails, Some(_)) => Some(open_index_with_recovery(&db)?),
+            _ => None,
+        };
+        let email_source = |args: &super::ExportArgs| -> Result<export::EmailSource> {
+            let scope = effective_scope("export", scope, &load_config()?)?;
+            let since = parse_date_arg("since", args.since.clone())?;
+            let until = parse_date_arg("until", args.until.clone())?;
+            Ok(match (&index, args.query.as_deref()) {
+                (Some(index), Some(query)) => export::EmailSource::Search {
+                    index,
+                    query: query.to_string(),
+                    filters: EmailFilters {
+                        scope: map_scope(scope),
+                        account: args.account.clone(),
+                        folder: args.folder.clone(),
+                        since,
+                        until,
+                        ..EmailFilters::default()
+                    },
+                },
+                _ => export::EmailSource::Stored(EmailSearchFilters {
+                    account_id: args.account.clone(),
+                    account_type: map_scope_to_account_type(scope),
+                    folder: args.folder.clone(),
+                    since,
+                    until,
+                    ..EmailSearchFilters::default()
+                }),
+            })
+        };
+        if !args.columns.is_empty() && !matches!(args.format, ExportFormat::Parquet) {
+            return Err(anyhow!("--columns only applies to --format parquet"));
+        }
+        let report = match (args.table, args.format) {
+            (ExportTable::Emails, ExportFormat::Parquet) => {
+                let columns = export::select_columns(export::EMAIL_COLUMNS, &args.columns)?;
+                export::export_emails_parquet(&db, &email_source(&args)?, &columns, &args.out)?
+            }
+            (ExportTable::Emails, ExportFormat::Notmuch) => {
+                export::export_emails_notmuch(&db, &email_source(&args)?, &args.out)?
+            }
+            (ExportTable::Emails, ExportFormat::Mbox) => {
+                let raw = RawStore::open_default().context("resolve raw message directory")?;
+                export::export_emails_mbox(&db, &email_source(&args)?, &raw, &args.out)?
+            }
+            (ExportTable::Emails, ExportFormat::Eml) => {
+                let raw = RawStore::open_default().context("resolve raw message directory")?;
+                export::export_emails_eml(&db, &email_source(&args)?, &raw, &args.out)?
+            }
+            (ExportTable::Emails, ExportFormat::Json) => {
+                export::export_emails_json(&db, &email_source(&args)?, &args.out)?
+            }
+            (ExportTable::Emails, ExportFormat::Ndjson) if args.out.as_os_str() == "-" => {
+                let started = std::time::Instant::now();
+                let rows = export::write_emails_ndjson(
+                    &db,
+                    &email_source(&args)?,
+                    &mut std::io::stdout().lock(),
+                );
+                match rows {
+                    Err(error) if is_broken_pipe(&error) => {}
+                    rows => eprintln!(
+                        "Exported {} emails to stdout in {} ms",
+                        rows?,
+                        started.elapsed().as_millis()
+                    ),
+                }
+                return Ok(());
+            }
+            (ExportTable::Emails, ExportFormat::Ndjson) => {
+                export::export_emails_ndjson(&db, &email_source(&args)?, &args.out)?
+            }
+            (ExportTable::Stats, ExportFormat::Parquet) => {
+                if args.account.is_some()
+                    || args.folder.is_some()
+                    || args.since.is_some()
+                    || args.until.is_some()
+                    || args.query.is_some()
+                {
+                    return Err(anyhow!(
+                        "--account, --folder, --since, --until and --query only apply to --table emails"
+                    ));
+                }
+                let columns = export::select_columns(export::STATS_COLUMNS, &args.columns)?;
+                export::export_stats_parquet(&db, &columns, &args.out)?
+            }
+            (ExportTable::Stats, _) => {
+                return Err(anyhow!("only --format parquet applies to --table stats"));
+            }
+        };
+
+        if json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            return Ok(());
+        }
+        match (args.table, args.format) {
+            (_, ExportFormat::Notmuch) => {
+                println!(
+                    "Exported tags for {} messages to {} in {} ms",
+                    report.rows,
+                    report.path.display(),
+                    report.duration_ms
+                );
+                if report.skipped > 0 {
+                    println!(
+                        "Skipped {} emails without a Message-ID; notmuch cannot match them",
+                        report.skipped
+                    );
+                }
+            }
+            (
+                _,
+                ExportFormat::Mbox | ExportFormat::Eml | ExportFormat::Json | ExportFormat::Ndjson,
+            ) => {
+                println!(
+                    "Exported {} messages ({} bytes) to {} in {} ms",
+                    report.rows,
+                    report.size_bytes,
+                    report.path.display(),
+                    report.duration_ms
+                );
+            }
+            (table, ExportFormat::Parquet) => {
+                let noun = match table {
+                    ExportTable::Emails => "emails",
+                    ExportTable::Stats => "stats snapshots",
+                };
+                println!(
+                    "Exported {} {noun} ({} columns, {} bytes) to {} in {} ms",
+                    report.rows,
+                    report.columns.len(),
+                    report.size_bytes,
+                    report.path.display(),
+                    report.duration_ms
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_schema(model: Option<&str>, openapi: bool, json: bool) -> Result<()> {
+        if openapi {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&schema::openapi_document())?
+            );
+            return Ok(());
+        }
+        let selected = model
+            .map(|name| {
+                schema::model(name).ok_or_else(|| {
+                    let known = schema::MODELS
+                        .iter()
+                        .map(|model| model.name)
+                        .collect::<Vec<_>>();
+                    anyhow!(
+                        "unknown model '{name}'; expected one of {}",
+                        known.join(", ")
+                    )
+                })
+            })
+            .transpose()?;
+        if json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&schema::json_schema(selected))?
+            );
+            return Ok(());
+        }
+
+        let models = selected.map_or(schema::MODELS, std::slice::from_ref);
+        for (position, model) in models.iter().enumerate() {
+            if position > 0 {
+                println!();
+            }
+            println!("{}: {}", model.name, model.description);
+            for field in model.fields {
+                let mut kind = field.kind.label();
+                if field.nullable {
+                    kind.push_str(" | null");
+                }
+                if !field.required {
+                    kind.push_str(" (optional)");
+                }
+                println!("  {:<20} {:<30} {}", field.name, kind, field.description);
+            }
+        }
         Ok(())
     }
 
-    async fn handle_sync(args: super::SyncArgs) -> Result<()> {
+    fn handle_index_command(command: IndexCommands, json: bool) -> Result<()> {
         let db_path = Database::default_db_path().context("resolve default ESS database path")?;
         let db = Database::open(&db_path)
             .with_context(|| format!("open ESS database at {}", db_path.display()))?;
         let mut index = open_index_with_recovery(&db)?;
-        let accounts = resolve_accounts(&db, args.account.as_deref())?;
-
-        if args.full {
-            eprintln!("--full requested: running full sync pass for selected account(s)");
-        }
-
-        if args.watch {
-            loop {
-                run_sync_cycle_multi(&db, &mut index, &accounts).await?;
-                tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+        match command {
+            IndexCommands::Optimize => {
+                let report = index.optimize().context("optimize ESS index")?;
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&report)?);
+                } else {
+                    println!(
+                        "Optimized index: {} -> {} segment(s), {} deleted document(s) purged, {} -> {} bytes in {} ms.",
+                        report.segments_before,
+                        report.segments_after,
+                        report.deleted_docs_purged,
+                        report.size_before_bytes,
+                        report.size_after_bytes,
+                        report.duration_ms
+                    );
+                }
+            }
+            IndexCommands::Verify { repair } => {
+                let report = index.verify(&db, repair).context("verify ESS index")?;
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&report)?);
+                } else {
+                    println!(
+                        "Checked {} email(s) against {} index document(s) in {} ms.",
+                        report.emails, report.documents, report.duration_ms
+                    );
+                    for (label, ids) in [
+                        ("missing from the index", &report.missing),
+                        ("orphaned in the index", &report.orphaned),
+                        ("indexed more than once", &report.duplicated),
+                    ] {
+                        if ids.is_empty() {
+                            continue;
+                        }
+                        println!("{} {label}:", ids.len());
+                        for id in ids.iter().take(VERIFY_IDS_SHOWN) {
+                            println!("  {id}");
+                        }
+                        if ids.len() > VERIFY_IDS_SHOWN {
+                            println!("  ... and {} more", ids.len() - VERIFY_IDS_SHOWN);
+                        }
+                    }
+                    if report.repaired {
+                        println!("Repaired.");
+                    } else if report.is_consistent() {
+                        println!("Index is consistent.");
+                    }
+                }
+                if !report.is_consistent() && !report.repaired {
+                    return Err(anyhow!(
+                        "index is out of sync with the database; run 'ess index verify --repair'"
+                    ));
+                }
             }
-        } else {
-            run_sync_cycle_multi(&db, &mut index, &accounts).await
         }
+        Ok(())
     }
 
-    async fn handle_import(args: super::ImportArgs, json: bool) -> Result<()> {
+    /// Embeds emails that have no vector for the configured model yet, in
+    /// batches, newest first.
+    async fn handle_embed(args: super::EmbedArgs, json: bool) -> Result<()> {
         let db_path = Database::default_db_path().context("resolve default ESS database path")?;
         let db = Database::open(&db_path)
             .with_context(|| format!("open ESS database at {}", db_path.display()))?;
-        let mut index = open_index_with_recovery(&db)?;
-        let account = resolve_single_account(&db, args.account.as_deref())?;
+        let embedder = embeddings::from_env().context("configure embedding model")?;
+        let model = embedder.model().to_string();
+        let started = Instant::now();
 
-        let connector = JsonArchiveConnector::new();
-        let report = connector
-            .import(&db, &mut index, std::path::Path::new(&args.path), &account)
-            .await
-            .with_context(|| format!("import archive path {}", args.path))?;
-
-        if json {
-            println!("{}", serde_json::to_string_pretty(&report)?);
+        let cleared = if args.rebuild {
+            db.clear_embeddings(&model)?
         } else {
-            println!("Import complete");
-            println!("Files processed: {}", report.files_processed);
-            println!("Emails imported: {}", report.emails_imported);
-            if report.errors.is_empty() {
-                println!("Errors: 0");
-            } else {
-                println!("Errors: {}", report.errors.len());
-                for error in report.errors {
-                    println!("- {error}");
+            0
+        };
+        let mut embedded = 0;
+        loop {
+            let remaining = args
+                .limit
+                .map_or(usize::MAX, |limit| limit.saturating_sub(embedded));
+            let ids = db.email_ids_without_embedding(&model, EMBED_BATCH_SIZE.min(remaining))?;
+            if ids.is_empty() {
+                break;
+            }
+            let mut emails = Vec::with_capacity(ids.len());
+            for id in &ids {
+                if let Some(email) = db.get_email(id)? {
+                    emails.push(email);
                 }
             }
+            let texts = emails.iter().map(embedding_text).collect::<Vec<_>>();
+            let vectors = embedder
+                .embed(&texts)
+                .await
+                .with_context(|| format!("embed emails with model {model}"))?;
+            for (email, vector) in emails.iter().zip(&vectors) {
+                db.upsert_embedding(&email.id, &model, vector)?;
+            }
+            embedded += emails.len();
+            tracing::info!("embedded {embedded} email(s) with {model}");
         }
-        Ok(())
-    }
 
-    async fn handle_contacts(args: super::ContactsArgs, json: bool) -> Result<()> {
-        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
-        let db = Database::open(&db_path)
-            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
-        let contacts = db.get_contacts(args.query.as_deref())?;
-        if args.enrich {
-            eprintln!("--enrich is not implemented yet; showing current contact data");
+        let stored = db.count_embeddings(&model)?;
+        let duration_ms = elapsed_ms(started);
+        if json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&serde_json::json!({
+                    "model": model,
+                    "embedded": embedded,
+                    "cleared": cleared,
+                    "stored": stored,
+                    "duration_ms": duration_ms,
+                }))?
+            );
+        } else {
+            println!(
+                "Embedded {embedded} email(s) with {model} in {duration_ms} ms ({stored} stored)."
+            );
         }
-        let formatted = output::format_contacts(OutputFormat::from_json_flag(json), &contacts)?;
-        println!("{formatted}");
         Ok(())
     }
 
-    async fn handle_accounts(command: AccountCommands) -> Result<()> {
-        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
-        let db = Database::open(&db_path)
-            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
-
+    async fn handle_search_provider(command: SearchProviderCommands) -> Result<()> {
         match command {
-            AccountCommands::List => {
-                let accounts = db.list_accounts()?;
-                if accounts.is_empty() {
-                    println!("No accounts configured.");
-                } else {
-                    println!("Accounts");
-                    println!("========");
-                    for account in accounts {
-                        println!(
-                            "{}  {}  {}",
-                            account.account_id, account.email_address, account.account_type
-                        );
-                    }
-                }
-            }
-            AccountCommands::Add {
-                email,
-                account_type,
-                tenant_id,
-                config,
-            } => {
-                let parsed_config = config
-                    .map(|raw| {
-                        serde_json::from_str::<serde_json::Value>(&raw)
-                            .context("parse --config JSON")
-                    })
-                    .transpose()?;
-                let account = Account {
-                    account_id: email.trim().to_ascii_lowercase(),
-                    email_address: email,
-                    display_name: None,
-                    tenant_id,
-                    account_type: map_account_type(account_type),
-                    enabled: true,
-                    last_sync: None,
-                    config: parsed_config,
-                };
-                db.insert_account(&account)?;
-                println!("Added account: {}", account.account_id);
-            }
-            AccountCommands::Remove { account_id } => {
-                let removed = db.remove_account(&account_id)?;
-                if removed == 0 {
-                    println!("No account found: {account_id}");
-                } else {
-                    println!("Removed account: {account_id}");
-                }
+            SearchProviderCommands::Run => {
+                let db_path =
+                    Database::default_db_path().context("resolve default ESS database path")?;
+                let db = Database::open(&db_path)
+                    .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+                let index = open_index_with_recovery(&db)?;
+                search_provider::run(index, db).await
             }
-            AccountCommands::SyncStatus => {
-                let accounts = db.list_accounts()?;
-                if accounts.is_empty() {
-                    println!("No accounts configured.");
-                } else {
-                    println!("Account Sync Status");
-                    println!("===================");
-                    for account in accounts {
-                        println!(
-                            "{}  enabled={}  last_sync={}",
-                            account.account_id,
-                            account.enabled,
-                            account.last_sync.as_deref().unwrap_or("never")
-                        );
+            SearchProviderCommands::Install | SearchProviderCommands::Uninstall => {
+                let data_dir = dirs::data_dir().context("resolve XDG data directory")?;
+                let exe = std::env::current_exe().context("resolve path of the ess binary")?;
+                let install = matches!(command, SearchProviderCommands::Install);
+                for (path, contents) in search_provider::registration_files(&data_dir, &exe) {
+                    if install {
+                        if let Some(parent) = path.parent() {
+                            std::fs::create_dir_all(parent)
+                                .with_context(|| format!("create {}", parent.display()))?;
+                        }
+                        std::fs::write(&path, contents)
+                            .with_context(|| format!("write {}", path.display()))?;
+                        println!("wrote {}", path.display());
+                    } else if path.exists() {
+                        std::fs::remove_file(&path)
+                            .with_context(|| format!("remove {}", path.display()))?;
+                        println!("removed {}", path.display());
                     }
                 }
+                if install {
+                    println!(
+                        "Log out and back in (or restart GNOME Shell) to enable the provider."
+                    );
+                }
+                Ok(())
             }
         }
-        Ok(())
     }
 
-    async fn handle_stats(json: bool) -> Result<()> {
-        let db_path = Database::default_db_path().context("resolve default ESS database path")?;
-        let db = Database::open(&db_path)
-            .with_context(|| format!("open ESS database at {}", db_path.display()))?;
-        let index = open_index_with_recovery(&db)?;
-        let db_stats = db.get_stats()?;
-        let index_stats = index.get_stats()?;
+    async fn handle_mcp(http: Option<SocketAddr>, token: Option<String>) -> Result<()> {
+        let Some(addr) = http else {
+            return ess::mcp::run_stdio_server().await;
+        };
+        ess::mcp::run_http_server(HttpServerOptions {
+            addr,
+            token: token.or_else(|| env::var(env::MCP_TOKEN)),
+        })
+        .await
+    }
 
-        if json {
-            #[derive(Serialize)]
-            struct StatsPayload {
-                database: ess::db::DatabaseStats,
-                index_doc_count: u64,
-                index_size_bytes: u64,
+    #[cfg(feature = "grpc")]
+    async fn handle_grpc(listen: SocketAddr, token: Option<String>) -> Result<()> {
+        ess::grpc::run_grpc_server(ess::grpc::GrpcServerOptions {
+            addr: listen,
+            token: token.or_else(|| env::var(env::GRPC_TOKEN)),
+        })
+        .await
+    }
+
+    /// Moves a pre-XDG `~/.ess` into the data, config and cache directories
+    /// on the first run after an upgrade, and keeps the `~/.ess`
+    /// compatibility links when `[storage] legacy_symlink` asks for them.
+    pub fn migrate_legacy_home() -> Result<()> {
+        let layout = Layout::current().context("resolve ESS directories")?;
+        if let Some(migration) = layout.migrate_legacy().with_context(|| {
+            format!(
+                "move {} to the XDG base directories",
+                layout.legacy.display()
+            )
+        })? {
+            for (from, to) in &migration.moved {
+                eprintln!("Moved {} to {}", from.display(), to.display());
+            }
+            for conflict in &migration.conflicts {
+                eprintln!(
+                    "Warning: left {} in place; its new location already exists",
+                    conflict.display()
+                );
+            }
+        }
+        let wants_link = Config::load_default()
+            .map(|config| config.storage.legacy_symlink == Some(true))
+            .unwrap_or(false);
+        if wants_link {
+            for link in layout.link_legacy().context("create ~/.ess links")? {
+                if let Ok(target) = std::fs::read_link(&link) {
+                    eprintln!("Linked {} to {}", link.display(), target.display());
+                }
             }
-            let payload = StatsPayload {
-                database: db_stats,
-                index_doc_count: index_stats.doc_count,
-                index_size_bytes: index_stats.index_size_bytes,
-            };
-            println!("{}", serde_json::to_string_pretty(&payload)?);
-        } else {
-            let rendered = output::format_stats(OutputFormat::Table, &db_stats)?;
-            println!("{rendered}");
-            println!("Index Docs: {}", index_stats.doc_count);
-            println!("Index Size (bytes): {}", index_stats.index_size_bytes);
         }
         Ok(())
     }
 
-    async fn handle_reindex() -> Result<()> {
+    /// The default database, boxed so it stands in for the daemon's pooled
+    /// connections in `run_search` and `run_list`.
+    fn open_local_db() -> Result<Box<Database>> {
         let db_path = Database::default_db_path().context("resolve default ESS database path")?;
         let db = Database::open(&db_path)
             .with_context(|| format!("open ESS database at {}", db_path.display()))?;
-        let mut index = open_index_with_recovery(&db)?;
-        let indexed = index.reindex(&db)?;
-        println!("Reindex complete: {indexed} emails indexed.");
-        Ok(())
+        Ok(Box::new(db))
     }
 
-    async fn handle_mcp() -> Result<()> {
-        ess::mcp::run_stdio_server()
+    fn load_config() -> Result<Config> {
+        let path = Config::default_path().context("resolve default ESS config path")?;
+        Config::load(&path).with_context(|| format!("load ESS config at {}", path.display()))
+    }
+
+    /// `--scope` when given, otherwise the configured default for `command`
+    /// at the current local time, otherwise `all`.
+    fn effective_scope(command: &str, flag: Option<Scope>, config: &Config) -> Result<Scope> {
+        if let Some(scope) = flag {
+            return Ok(scope);
+        }
+        let Some(configured) = config.default_scope(command, Local::now().naive_local())? else {
+            return Ok(Scope::All);
+        };
+        let scope = configured
+            .parse::<SearchScope>()
+            .map_err(|error| anyhow!("config.toml: {error}"))?;
+        Ok(match scope {
+            SearchScope::Professional => Scope::Pro,
+            SearchScope::Personal => Scope::Personal,
+            SearchScope::All => Scope::All,
+        })
     }
 
     fn map_scope(scope: Scope) -> SearchScope {
@@ -467,10 +5080,24 @@ mod commands {
         }
     }
 
+    /// `None` without `--cursor`, `Some(None)` for a bare `--cursor`.
+    fn parse_cursor_arg(raw: Option<&str>) -> Result<Option<Option<search::Cursor>>> {
+        raw.map(|token| {
+            let token = token.trim();
+            if token.is_empty() {
+                return Ok(None);
+            }
+            token
+                .parse::<search::Cursor>()
+                .map(Some)
+                .context("parse --cursor")
+        })
+        .transpose()
+    }
+
     fn parse_date_arg(label: &str, raw: Option<String>) -> Result<Option<NaiveDate>> {
         raw.map(|value| {
-            NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
-                .with_context(|| format!("invalid --{label} date '{value}', expected YYYY-MM-DD"))
+            search::dates::parse_date(&value).with_context(|| format!("invalid --{label} date"))
         })
         .transpose()
     }
@@ -478,27 +5105,43 @@ mod commands {
     fn open_index_with_recovery(db: &Database) -> Result<EmailIndex> {
         let index_path =
             EmailIndex::default_index_path().context("resolve default ESS index path")?;
-        match EmailIndex::open(&index_path) {
+        let settings = load_config()?
+            .index_settings()
+            .context("read [index] settings from config.toml")?;
+        if !index_path.join("meta.json").exists() && db.has_emails()? {
+            tracing::warn!(
+                "no ESS index at {}; rebuilding it from SQLite",
+                index_path.display()
+            );
+            rebuild_index_from_db(db, &index_path, &settings).with_context(|| {
+                format!("rebuild missing ESS index at {}", index_path.display())
+            })?;
+        }
+        match EmailIndex::open_with_settings(&index_path, &settings) {
             Ok(index) => Ok(index),
             Err(open_error) => {
                 tracing::warn!(
                     "failed to open ESS index at {}: {open_error}; attempting rebuild from SQLite",
                     index_path.display()
                 );
-                rebuild_index_from_db(db, &index_path).with_context(|| {
+                rebuild_index_from_db(db, &index_path, &settings).with_context(|| {
                     format!(
                         "rebuild ESS index at {} after open failure",
                         index_path.display()
                     )
                 })?;
-                EmailIndex::open(&index_path).with_context(|| {
+                EmailIndex::open_with_settings(&index_path, &settings).with_context(|| {
                     format!("re-open rebuilt ESS index at {}", index_path.display())
                 })
             }
         }
     }
 
-    fn rebuild_index_from_db(db: &Database, index_path: &Path) -> Result<usize> {
+    fn rebuild_index_from_db(
+        db: &Database,
+        index_path: &Path,
+        settings: &IndexSettings,
+    ) -> Result<usize> {
         if index_path.exists() {
             std::fs::remove_dir_all(index_path).with_context(|| {
                 format!(
@@ -509,7 +5152,7 @@ mod commands {
         }
         std::fs::create_dir_all(index_path)
             .with_context(|| format!("create ESS index directory {}", index_path.display()))?;
-        let mut index = EmailIndex::open(index_path)
+        let mut index = EmailIndex::open_with_settings(index_path, settings)
             .with_context(|| format!("initialize ESS index at {}", index_path.display()))?;
         let indexed = index
             .reindex(db)
@@ -528,11 +5171,17 @@ mod commands {
         }
     }
 
+    /// The accounts to sync: `account_id`, or every enabled account.
     fn resolve_accounts(db: &Database, account_id: Option<&str>) -> Result<Vec<Account>> {
         if let Some(account_id) = account_id {
             let account = db
                 .get_account(account_id)?
                 .ok_or_else(|| anyhow!("account not found: {account_id}"))?;
+            if !account.enabled {
+                return Err(anyhow!(
+                    "account {account_id} is disabled; use 'ess accounts enable {account_id}'"
+                ));
+            }
             return Ok(vec![account]);
         }
 
@@ -542,7 +5191,13 @@ mod commands {
                 "no accounts configured; use 'ess accounts add' first"
             ));
         }
-        Ok(accounts)
+        let enabled: Vec<Account> = accounts.into_iter().filter(|a| a.enabled).collect();
+        if enabled.is_empty() {
+            return Err(anyhow!(
+                "every account is disabled; use 'ess accounts enable <id>'"
+            ));
+        }
+        Ok(enabled)
     }
 
     fn resolve_single_account(db: &Database, account_id: Option<&str>) -> Result<Account> {
@@ -564,7 +5219,19 @@ mod commands {
         }
     }
 
-    fn connector_for_account(account: &Account) -> Box<dyn EmailConnector> {
+    /// Progress bars when stderr is a terminal, NDJSON events with `--json`,
+    /// and plain log lines otherwise (e.g. when piped to a file).
+    fn sync_progress_sink(json: bool) -> ProgressSink {
+        if json {
+            ProgressSink::new(output::progress::JsonProgressStream::new())
+        } else if std::io::stderr().is_terminal() {
+            ProgressSink::new(output::progress::SyncProgressBars::new())
+        } else {
+            ProgressSink::new(StderrProgress)
+        }
+    }
+
+    fn connector_for_account(account: &Account, hooks: &SyncHooks) -> Box<dyn EmailConnector> {
         let connector_name = account
             .config
             .as_ref()
@@ -573,36 +5240,280 @@ mod commands {
             .unwrap_or("graph_api");
 
         match connector_name {
-            "gmail_api" => Box::new(GmailApiConnector::new()),
-            _ => Box::new(GraphApiConnector::new()),
+            "gmail_api" => {
+                let mut connector = GmailApiConnector::new()
+                    .with_progress(hooks.progress.clone())
+                    .with_shutdown(hooks.shutdown.clone());
+                if let Some(raw) = &hooks.raw {
+                    connector = connector
+                        .with_raw_store(raw.clone())
+                        .with_raw_fetches_deferred(
+                            hooks.deferred_raw.contains(&account.account_id),
+                        );
+                }
+                Box::new(connector)
+            }
+            _ => {
+                let mut connector = GraphApiConnector::new()
+                    .with_progress(hooks.progress.clone())
+                    .with_shutdown(hooks.shutdown.clone());
+                if let Some(raw) = &hooks.raw {
+                    connector = connector
+                        .with_raw_store(raw.clone())
+                        .with_raw_fetches_deferred(
+                            hooks.deferred_raw.contains(&account.account_id),
+                        );
+                }
+                Box::new(connector)
+            }
+        }
+    }
+
+    /// Adds the requests and bytes `connector` used for `account` to
+    /// today's `sync_usage`.
+    fn record_usage(
+        db: &Database,
+        account: &Account,
+        connector: &dyn EmailConnector,
+    ) -> Result<()> {
+        let usage = connector.take_usage();
+        if !usage.is_empty() {
+            db.add_sync_usage(&account.account_id, Utc::now().date_naive(), usage)
+                .context("record sync usage")?;
+        }
+        Ok(())
+    }
+
+    /// Adds a sync of `account` to `sync_runs`, and to its `last_sync`
+    /// when it ran to the end.
+    fn record_sync_run(
+        db: &Database,
+        account: &Account,
+        summary: &AccountSyncReport,
+        started_at: DateTime<Utc>,
+        result: &Result<SyncReport>,
+    ) -> Result<()> {
+        let mut run = SyncRun {
+            account_id: account.account_id.clone(),
+            connector: summary.connector.clone(),
+            started_at: started_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+            finished_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            duration_ms: summary.duration_ms,
+            emails_added: 0,
+            emails_updated: 0,
+            errors: 0,
+            error: None,
+            succeeded: false,
+        };
+        match result {
+            Ok(report) => {
+                run.emails_added = report.emails_added;
+                run.emails_updated = report.emails_updated;
+                run.errors = report.errors.len();
+                run.error = report.errors.first().map(ToString::to_string);
+                run.succeeded = !report.interrupted;
+            }
+            Err(error) => run.error = Some(format!("{error:#}")),
         }
+        db.record_sync_run(&run).context("record sync run")
     }
 
     async fn run_sync_cycle_multi(
         db: &Database,
         index: &mut EmailIndex,
         accounts: &[Account],
+        hooks: &SyncHooks,
+        json: bool,
+        cycle: &mut SyncCycleReport,
     ) -> Result<()> {
-        for account in accounts {
-            let connector = connector_for_account(account);
-            let report = connector.sync(db, index, account).await?;
-            println!(
-                "sync {}: added={} updated={} errors={}",
-                account.account_id,
-                report.emails_added,
-                report.emails_updated,
-                report.errors.len()
-            );
-            if !report.errors.is_empty() {
-                let show = report.errors.len().min(10);
-                for error in &report.errors[..show] {
-                    eprintln!("  error: {error}");
-                }
-                if report.errors.len() > 10 {
-                    eprintln!("  ... and {} more errors", report.errors.len() - 10);
+        let connectors = accounts
+            .iter()
+            .map(|account| connector_for_account(account, hooks))
+            .collect::<Vec<_>>();
+        let endpoints = connectors
+            .iter()
+            .flat_map(|connector| connector.remote_endpoints())
+            .collect::<Vec<_>>();
+        connectivity::ensure_online(&endpoints, connectivity::DEFAULT_PROBE_TIMEOUT).await?;
+
+        for (account, connector) in accounts.iter().zip(connectors) {
+            let mut summary = AccountSyncReport {
+                account_id: account.account_id.clone(),
+                connector: connector.name().to_string(),
+                skipped: false,
+                duration_ms: 0,
+                report: SyncReport::default(),
+            };
+            if hooks.shutdown.is_requested() {
+                if !json {
+                    println!("sync {}: skipped (interrupted)", account.account_id);
                 }
+                summary.skipped = true;
+                cycle.accounts.push(summary);
+                continue;
+            }
+            let started_at = Utc::now();
+            let started = Instant::now();
+            let result = connector.sync(db, index, account).await;
+            summary.duration_ms = elapsed_ms(started);
+            record_usage(db, account, connector.as_ref())?;
+            record_sync_run(db, account, &summary, started_at, &result)?;
+            let report = result?;
+            if !json {
+                print_account_sync_report(&account.account_id, &report);
             }
+            summary.report = report;
+            cycle.accounts.push(summary);
+        }
+        if hooks.shutdown.is_requested() {
+            return Err(SyncInterrupted.into());
         }
         Ok(())
     }
+
+    fn print_account_sync_report(account_id: &str, report: &SyncReport) {
+        println!(
+            "sync {account_id}: added={} updated={} errors={}{}",
+            report.emails_added,
+            report.emails_updated,
+            report.errors.len(),
+            if report.interrupted {
+                " (interrupted)"
+            } else {
+                ""
+            }
+        );
+        if !report.errors.is_empty() {
+            let show = report.errors.len().min(10);
+            for error in &report.errors[..show] {
+                eprintln!("  error [{}]: {error}", error.kind());
+            }
+            if report.errors.len() > 10 {
+                eprintln!("  ... and {} more errors", report.errors.len() - 10);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use clap::Parser;
+
+    use super::commands::{check_error_threshold, watcher_pid, WatchPidFile};
+    use super::{parse_timeout_arg, parse_trend_arg, Cli, Commands, ThreadCommands};
+
+    #[test]
+    fn parses_timeout_units() {
+        assert_eq!(parse_timeout_arg("45").unwrap(), Duration::from_secs(45));
+        assert_eq!(parse_timeout_arg("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_timeout_arg("5m").unwrap(), Duration::from_secs(300));
+        assert_eq!(parse_timeout_arg("1h").unwrap(), Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn rejects_invalid_timeouts() {
+        assert!(parse_timeout_arg("0").is_err());
+        assert!(parse_timeout_arg("soon").is_err());
+        assert!(parse_timeout_arg("-5s").is_err());
+        assert!(parse_timeout_arg("999999999999999999h").is_err());
+        assert!(parse_trend_arg("999999999999999999w").is_err());
+    }
+
+    #[test]
+    fn parses_trend_windows() {
+        assert_eq!(
+            parse_trend_arg("30").unwrap(),
+            Duration::from_secs(30 * 86_400)
+        );
+        assert_eq!(
+            parse_trend_arg("30d").unwrap(),
+            Duration::from_secs(30 * 86_400)
+        );
+        assert_eq!(
+            parse_trend_arg("2w").unwrap(),
+            Duration::from_secs(14 * 86_400)
+        );
+        assert_eq!(
+            parse_trend_arg("12h").unwrap(),
+            Duration::from_secs(12 * 3600)
+        );
+        assert!(parse_trend_arg("0d").is_err());
+        assert!(parse_trend_arg("month").is_err());
+        assert!(parse_trend_arg("100000000d").is_err());
+        assert!(parse_trend_arg("36500d").is_ok());
+    }
+
+    #[test]
+    fn fail_on_errors_defaults_to_zero_when_bare() {
+        let threshold = |args: &[&str]| match Cli::parse_from(args).command {
+            Commands::Sync(sync) => sync.fail_on_errors,
+            _ => unreachable!(),
+        };
+        assert_eq!(threshold(&["ess", "sync"]), None);
+        assert_eq!(threshold(&["ess", "sync", "--fail-on-errors"]), Some(0));
+        assert_eq!(threshold(&["ess", "sync", "--fail-on-errors=5"]), Some(5));
+        assert!(Cli::try_parse_from(["ess", "sync", "--watch", "--fail-on-errors"]).is_err());
+    }
+
+    #[test]
+    fn thread_accepts_an_id_or_a_repair_subcommand() {
+        let thread = |args: &[&str]| match Cli::parse_from(args).command {
+            Commands::Thread(thread) => thread,
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            thread(&["ess", "thread", "AAQk"])
+                .conversation_id
+                .as_deref(),
+            Some("AAQk")
+        );
+        assert!(matches!(
+            thread(&["ess", "thread", "merge", "A", "B"]).command,
+            Some(ThreadCommands::Merge { thread_a, thread_b }) if thread_a == "A" && thread_b == "B"
+        ));
+        assert!(matches!(
+            thread(&["ess", "thread", "split", "msg-1"]).command,
+            Some(ThreadCommands::Split { email_id }) if email_id == "msg-1"
+        ));
+        assert!(Cli::try_parse_from(["ess", "thread"]).is_err());
+    }
+
+    #[test]
+    fn connect_flags_go_anywhere_but_not_together() {
+        let cli = Cli::parse_from(["ess", "search", "budget", "--connect"]);
+        assert!(cli.connect && !cli.no_connect);
+        assert!(Cli::parse_from(["ess", "--no-connect", "list"]).no_connect);
+        assert!(Cli::try_parse_from(["ess", "--connect", "--no-connect", "list"]).is_err());
+    }
+
+    #[test]
+    fn error_threshold_fails_only_when_exceeded() {
+        assert!(check_error_threshold(3, None).is_ok());
+        assert!(check_error_threshold(0, Some(0)).is_ok());
+        assert!(check_error_threshold(1, Some(0)).is_err());
+        assert!(check_error_threshold(5, Some(5)).is_ok());
+        assert!(check_error_threshold(6, Some(5)).is_err());
+    }
+
+    #[test]
+    fn one_watcher_holds_the_pid_file() {
+        let path = std::env::temp_dir().join(format!("ess-watch-{}.pid", uuid::Uuid::new_v4()));
+        let pid_file = WatchPidFile::create(path.clone()).expect("first watcher");
+        assert_eq!(watcher_pid(&path).unwrap(), Some(std::process::id()));
+        let second = WatchPidFile::create(path.clone())
+            .err()
+            .expect("second watcher is refused");
+        assert!(second.to_string().contains("already running"));
+        assert!(path.exists(), "the refused watcher keeps the file");
+
+        drop(pid_file);
+        assert_eq!(watcher_pid(&path).unwrap(), None);
+
+        // Left behind by a killed watcher: nobody holds the lock.
+        std::fs::write(&path, "1\n").unwrap();
+        assert_eq!(watcher_pid(&path).unwrap(), None);
+        std::fs::remove_file(&path).unwrap();
+    }
 }
@@ -0,0 +1,545 @@
+//! MCP over Streamable HTTP, for agents and IDEs that connect to a URL
+//! instead of spawning `ess mcp`.
+//!
+//! A single endpoint, `/mcp`, takes JSON-RPC messages and batches by POST
+//! and hands them to the same handler as the stdio transport. Responses are
+//! `application/json`, or one `message` event on a `text/event-stream` when
+//! the client accepts only SSE. Payloads holding only notifications get 202
+//! Accepted. ESS never sends requests or notifications of its own, so the
+//! optional server-to-client stream (GET) answers 405.
+//!
+//! `initialize` opens a session whose id comes back in `Mcp-Session-Id`;
+//! requests naming an unknown or ended session get 404, and DELETE ends
+//! one. A session unused for an hour ends by itself, as does the least
+//! recently used one once `MAX_SESSIONS` are open. Requests without the
+//! header are served too. With a token set, every request needs
+//! `Authorization: Bearer <token>`. Without one, requests from browser
+//! pages on other origins are refused, so a web page cannot reach a server
+//! bound to localhost. POST bodies must be sent as `application/json`.
+//!
+//! Editor plugins that only need recipients can skip JSON-RPC: `GET
+//! /complete-address?q=ali&limit=10` answers with the `ess_complete_address`
+//! result as plain JSON, behind the same checks.
+//!
+//! `GET /openapi.json` describes these endpoints (see
+//! `output::schema::openapi_document`) and needs no token.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::Router;
+use ring::digest::{digest, SHA256};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+
+use crate::mcp::{server, tools};
+use crate::output::schema::{openapi_document, OPENAPI_PATH};
+
+pub const MCP_PATH: &str = "/mcp";
+pub const COMPLETE_ADDRESS_PATH: &str = "/complete-address";
+const SESSION_HEADER: &str = "mcp-session-id";
+/// How long a session may go unused before it ends.
+const SESSION_IDLE: Duration = Duration::from_secs(60 * 60);
+/// Open sessions kept at most; clients need not end theirs with DELETE.
+const MAX_SESSIONS: usize = 1024;
+
+#[derive(Debug, Clone)]
+pub struct HttpServerOptions {
+    pub addr: SocketAddr,
+    /// Bearer token every request must carry; required off loopback.
+    pub token: Option<String>,
+}
+
+struct HttpState {
+    token: Option<String>,
+    /// Open sessions and when each was last used.
+    sessions: Mutex<HashMap<String, Instant>>,
+}
+
+/// Serves until Ctrl-C.
+pub async fn run_http_server(options: HttpServerOptions) -> Result<()> {
+    if options.token.is_none() && !options.addr.ip().is_loopback() {
+        return Err(anyhow!(
+            "serving MCP on {} needs a token (--token or ESS_MCP_TOKEN); without one, bind to a loopback address",
+            options.addr
+        ));
+    }
+    let listener = TcpListener::bind(options.addr)
+        .await
+        .with_context(|| format!("bind MCP HTTP server to {}", options.addr))?;
+    eprintln!(
+        "ESS MCP server listening on http://{}{MCP_PATH}",
+        listener.local_addr().context("read MCP listener address")?
+    );
+    serve(listener, options.token, async {
+        let _ = tokio::signal::ctrl_c().await;
+    })
+    .await
+}
+
+async fn serve(
+    listener: TcpListener,
+    token: Option<String>,
+    shutdown: impl std::future::Future<Output = ()> + Send + 'static,
+) -> Result<()> {
+    let state = Arc::new(HttpState {
+        token,
+        sessions: Mutex::new(HashMap::new()),
+    });
+    let router = Router::new()
+        .route(
+            MCP_PATH,
+            post(handle_post).get(handle_get).delete(handle_delete),
+        )
+        .route(COMPLETE_ADDRESS_PATH, get(handle_complete_address))
+        .route(
+            OPENAPI_PATH,
+            get(|| async { axum::Json(openapi_document()) }),
+        )
+        .with_state(state);
+    axum::serve(listener, router)
+        .with_graceful_shutdown(shutdown)
+        .await
+        .context("serve MCP over HTTP")
+}
+
+async fn handle_post(
+    State(state): State<Arc<HttpState>>,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    if let Some(refused) = refusal(&state, &headers) {
+        return refused;
+    }
+    if !is_json_request(&headers) {
+        return (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "expected Content-Type: application/json",
+        )
+            .into_response();
+    }
+    let accept = headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("*/*")
+        .to_ascii_lowercase();
+    let sse = !accepts(&accept, "application/json") && accepts(&accept, "text/event-stream");
+    if !sse && !accepts(&accept, "application/json") {
+        return (
+            StatusCode::NOT_ACCEPTABLE,
+            "accept application/json or text/event-stream",
+        )
+            .into_response();
+    }
+    let initialize = serde_json::from_str::<Value>(&body)
+        .ok()
+        .is_some_and(|message| message.get("method").and_then(Value::as_str) == Some("initialize"));
+
+    // Tools read SQLite and the index synchronously.
+    let handled = tokio::task::spawn_blocking(move || server::handle_payload(&body)).await;
+    let response = match handled {
+        Ok(Some(response)) => response,
+        Ok(None) => return StatusCode::ACCEPTED.into_response(),
+        Err(error) => {
+            return (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()).into_response();
+        }
+    };
+
+    let mut reply = if sse {
+        (
+            [(header::CONTENT_TYPE, "text/event-stream")],
+            format!("event: message\ndata: {response}\n\n"),
+        )
+            .into_response()
+    } else {
+        (
+            [(header::CONTENT_TYPE, "application/json")],
+            response.to_string(),
+        )
+            .into_response()
+    };
+    if initialize && response.get("result").is_some() {
+        let session = uuid::Uuid::new_v4().to_string();
+        if let Ok(value) = HeaderValue::from_str(&session) {
+            reply.headers_mut().insert(SESSION_HEADER, value);
+        }
+        open_session(&state.sessions, session);
+    }
+    reply
+}
+
+async fn handle_get(State(state): State<Arc<HttpState>>, headers: HeaderMap) -> Response {
+    if let Some(refused) = refusal(&state, &headers) {
+        return refused;
+    }
+    (
+        StatusCode::METHOD_NOT_ALLOWED,
+        [(header::ALLOW, "POST, DELETE")],
+        "ESS sends no server-initiated messages",
+    )
+        .into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct CompletionQuery {
+    q: String,
+    limit: Option<usize>,
+}
+
+async fn handle_complete_address(
+    State(state): State<Arc<HttpState>>,
+    headers: HeaderMap,
+    Query(query): Query<CompletionQuery>,
+) -> Response {
+    if let Some(refused) = refusal(&state, &headers) {
+        return refused;
+    }
+    if query.limit == Some(0) {
+        return (StatusCode::BAD_REQUEST, "limit must be greater than zero").into_response();
+    }
+    let arguments = json!({ "fragment": query.q, "limit": query.limit.unwrap_or(10) });
+    let completed =
+        tokio::task::spawn_blocking(move || tools::call_tool("ess_complete_address", arguments))
+            .await;
+    match completed {
+        Ok(Ok(matches)) => (
+            [(header::CONTENT_TYPE, "application/json")],
+            matches.to_string(),
+        )
+            .into_response(),
+        Ok(Err(error)) => (StatusCode::INTERNAL_SERVER_ERROR, format!("{error:#}")).into_response(),
+        Err(error) => (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()).into_response(),
+    }
+}
+
+async fn handle_delete(State(state): State<Arc<HttpState>>, headers: HeaderMap) -> Response {
+    if let Some(refused) = refusal(&state, &headers) {
+        return refused;
+    }
+    match session_id(&headers) {
+        Some(session) => {
+            lock(&state.sessions).remove(session);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        None => (StatusCode::BAD_REQUEST, "missing Mcp-Session-Id").into_response(),
+    }
+}
+
+/// Authorization, origin and session checks shared by every method; the
+/// response to send instead when one fails.
+fn refusal(state: &HttpState, headers: &HeaderMap) -> Option<Response> {
+    match state.token.as_deref() {
+        Some(token) => {
+            if !has_bearer_token(headers, token) {
+                return Some(
+                    (
+                        StatusCode::UNAUTHORIZED,
+                        [(header::WWW_AUTHENTICATE, "Bearer")],
+                        "missing or wrong bearer token",
+                    )
+                        .into_response(),
+                );
+            }
+        }
+        None => {
+            let origin = headers
+                .get(header::ORIGIN)
+                .and_then(|value| value.to_str().ok());
+            if origin.is_some_and(|origin| !is_local_origin(origin)) {
+                return Some(
+                    (StatusCode::FORBIDDEN, "cross-origin request refused").into_response(),
+                );
+            }
+        }
+    }
+    if let Some(session) = session_id(headers) {
+        let mut sessions = lock(&state.sessions);
+        match sessions.get_mut(session) {
+            Some(last_used) if last_used.elapsed() < SESSION_IDLE => *last_used = Instant::now(),
+            _ => {
+                sessions.remove(session);
+                return Some((StatusCode::NOT_FOUND, "unknown or ended session").into_response());
+            }
+        }
+    }
+    None
+}
+
+/// Records a new session, first ending idle ones and, at the cap, the
+/// least recently used.
+fn open_session(sessions: &Mutex<HashMap<String, Instant>>, session: String) {
+    let mut sessions = lock(sessions);
+    sessions.retain(|_, last_used| last_used.elapsed() < SESSION_IDLE);
+    if sessions.len() >= MAX_SESSIONS {
+        let oldest = sessions
+            .iter()
+            .min_by_key(|(_, last_used)| **last_used)
+            .map(|(session, _)| session.clone());
+        if let Some(oldest) = oldest {
+            sessions.remove(&oldest);
+        }
+    }
+    sessions.insert(session, Instant::now());
+}
+
+fn session_id(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(SESSION_HEADER)
+        .and_then(|value| value.to_str().ok())
+}
+
+fn accepts(accept: &str, media_type: &str) -> bool {
+    accept.split(',').any(|range| {
+        let range = range.split(';').next().unwrap_or_default().trim();
+        range == media_type
+            || range == "*/*"
+            || range
+                .strip_suffix("/*")
+                .is_some_and(|kind| media_type.starts_with(&format!("{kind}/")))
+    })
+}
+
+/// Whether the request carries `Authorization: Bearer <token>`. A missing or
+/// malformed header does not.
+pub(crate) fn has_bearer_token(headers: &HeaderMap, token: &str) -> bool {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|presented| token_matches(presented, token))
+}
+
+/// Compares the SHA-256 digests rather than the tokens, so the time taken
+/// tells nothing about how much of the token was right.
+pub(crate) fn token_matches(presented: &str, token: &str) -> bool {
+    digest(&SHA256, presented.as_bytes()).as_ref() == digest(&SHA256, token.as_bytes()).as_ref()
+}
+
+/// Whether the body is declared as JSON. A browser sends a cross-site POST
+/// without a preflight only as a form or text/plain.
+pub(crate) fn is_json_request(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|media_type| media_type.trim().eq_ignore_ascii_case("application/json"))
+}
+
+/// Whether an `Origin` header names this machine.
+pub(crate) fn is_local_origin(origin: &str) -> bool {
+    let host = origin
+        .split_once("://")
+        .map_or(origin, |(_, rest)| rest)
+        .trim_end_matches('/');
+    let host = match host.strip_prefix('[') {
+        Some(bracketed) => bracketed.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "::1")
+}
+
+fn lock(
+    sessions: &Mutex<HashMap<String, Instant>>,
+) -> std::sync::MutexGuard<'_, HashMap<String, Instant>> {
+    sessions
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+
+    use super::{
+        accepts, is_local_origin, open_session, serve, COMPLETE_ADDRESS_PATH, MAX_SESSIONS,
+        MCP_PATH, SESSION_IDLE,
+    };
+
+    async fn start(token: Option<&str>) -> (String, tokio::sync::oneshot::Sender<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let url = format!(
+            "http://{}{MCP_PATH}",
+            listener.local_addr().expect("local addr")
+        );
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        tokio::spawn(serve(listener, token.map(str::to_string), async {
+            let _ = stopped.await;
+        }));
+        (url, stop)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn serves_json_rpc_with_sessions() {
+        let (url, _stop) = start(None).await;
+        let client = reqwest::Client::builder()
+            .no_proxy()
+            .build()
+            .expect("client");
+
+        let response = client
+            .post(&url)
+            .header("accept", "application/json, text/event-stream")
+            .json(&json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}))
+            .send()
+            .await
+            .expect("initialize");
+        assert_eq!(response.status(), 200);
+        let session = response.headers()["mcp-session-id"]
+            .to_str()
+            .expect("session id")
+            .to_string();
+        let body: Value = response.json().await.expect("initialize body");
+        assert_eq!(body["result"]["serverInfo"]["name"], "ess");
+
+        let response = client
+            .post(&url)
+            .header("mcp-session-id", &session)
+            .json(&json!({"jsonrpc": "2.0", "method": "notifications/initialized"}))
+            .send()
+            .await
+            .expect("notification");
+        assert_eq!(response.status(), 202);
+
+        let response = client
+            .post(&url)
+            .header("accept", "text/event-stream")
+            .header("mcp-session-id", &session)
+            .json(&json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"}))
+            .send()
+            .await
+            .expect("tools/list");
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+        let event = response.text().await.expect("event");
+        let data = event
+            .strip_prefix("event: message\ndata: ")
+            .expect("message event");
+        let body: Value = serde_json::from_str(data.trim_end()).expect("event data");
+        assert_eq!(body["id"], 2);
+        assert!(body["result"]["tools"].is_array());
+
+        assert_eq!(client.get(&url).send().await.expect("get").status(), 405);
+        let ended = client
+            .delete(&url)
+            .header("mcp-session-id", &session)
+            .send()
+            .await
+            .expect("delete");
+        assert_eq!(ended.status(), 204);
+        let response = client
+            .post(&url)
+            .header("mcp-session-id", &session)
+            .json(&json!({"jsonrpc": "2.0", "id": 3, "method": "tools/list"}))
+            .send()
+            .await
+            .expect("ended session");
+        assert_eq!(response.status(), 404);
+
+        let response = client
+            .post(&url)
+            .header("origin", "https://evil.example")
+            .json(&json!({"jsonrpc": "2.0", "id": 4, "method": "tools/list"}))
+            .send()
+            .await
+            .expect("cross origin");
+        assert_eq!(response.status(), 403);
+
+        let response = client
+            .post(&url)
+            .header("content-type", "text/plain")
+            .body(json!({"jsonrpc": "2.0", "id": 5, "method": "tools/list"}).to_string())
+            .send()
+            .await
+            .expect("text/plain body");
+        assert_eq!(response.status(), 415);
+    }
+
+    #[test]
+    fn sessions_expire_and_are_capped() {
+        let sessions = Mutex::new(HashMap::new());
+        let now = Instant::now();
+        let idle = now
+            .checked_sub(SESSION_IDLE + Duration::from_secs(1))
+            .expect("instant in the past");
+        {
+            let mut open = sessions.lock().expect("lock");
+            open.insert("idle".to_string(), idle);
+            for n in 1..MAX_SESSIONS {
+                open.insert(format!("s{n}"), now + Duration::from_millis(n as u64));
+            }
+            open.insert("oldest".to_string(), now);
+        }
+
+        open_session(&sessions, "new".to_string());
+        let open = sessions.lock().expect("lock");
+        assert_eq!(open.len(), MAX_SESSIONS);
+        assert!(!open.contains_key("idle"));
+        assert!(!open.contains_key("oldest"));
+        assert!(open.contains_key("new") && open.contains_key("s1"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn requires_the_bearer_token_when_set() {
+        let (url, _stop) = start(Some("s3cret")).await;
+        let client = reqwest::Client::builder()
+            .no_proxy()
+            .build()
+            .expect("client");
+        let request = json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"});
+
+        let response = client.post(&url).json(&request).send().await.expect("post");
+        assert_eq!(response.status(), 401);
+        let openapi: Value = client
+            .get(url.replace(MCP_PATH, "/openapi.json"))
+            .send()
+            .await
+            .expect("openapi without token")
+            .json()
+            .await
+            .expect("openapi document");
+        assert!(openapi["paths"][MCP_PATH]["post"].is_object());
+        let completion = url.replace(MCP_PATH, COMPLETE_ADDRESS_PATH);
+        let response = client
+            .get(&completion)
+            .query(&[("q", "ali")])
+            .send()
+            .await
+            .expect("complete without token");
+        assert_eq!(response.status(), 401);
+        let response = client
+            .get(&completion)
+            .bearer_auth("s3cret")
+            .query(&[("q", "ali"), ("limit", "0")])
+            .send()
+            .await
+            .expect("complete with zero limit");
+        assert_eq!(response.status(), 400);
+        let response = client
+            .post(&url)
+            .bearer_auth("s3cret")
+            .header("origin", "https://agent.example")
+            .json(&request)
+            .send()
+            .await
+            .expect("post");
+        assert_eq!(response.status(), 200);
+
+        assert!(accepts("application/*;q=0.9", "application/json"));
+        assert!(!accepts("text/event-stream", "application/json"));
+        assert!(is_local_origin("http://localhost:3000"));
+        assert!(is_local_origin("http://[::1]:8080"));
+        assert!(!is_local_origin("http://localhost.evil.example"));
+    }
+}
@@ -1,4 +1,7 @@
+pub mod http;
 pub mod server;
+pub mod state;
 pub mod tools;
 
+pub use http::{run_http_server, HttpServerOptions};
 pub use server::run_stdio_server;
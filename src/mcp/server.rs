@@ -1,13 +1,19 @@
-use std::io::{self, BufRead, Write};
+use std::sync::Arc;
 
 use anyhow::{Context, Result};
 use serde::Deserialize;
 use serde_json::{json, Value};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{mpsc, Semaphore};
 
 use crate::mcp::tools;
 
 const JSONRPC_VERSION: &str = "2.0";
 
+/// Requests handled at once over stdio; further lines wait to be read until
+/// one finishes.
+const MAX_CONCURRENT_REQUESTS: usize = 8;
+
 #[derive(Debug, Deserialize)]
 struct JsonRpcRequest {
     jsonrpc: String,
@@ -17,19 +23,67 @@ struct JsonRpcRequest {
     params: Value,
 }
 
-pub fn run_stdio_server() -> Result<()> {
-    let stdin = io::stdin();
-    let mut stdout = io::stdout().lock();
+/// Serves newline-delimited JSON-RPC on stdin and stdout until stdin
+/// closes. Each request runs on the blocking pool as soon as it is read, so
+/// a slow `ess_search` does not hold up an `ess_stats` sent after it;
+/// responses are written as they complete, matched to requests by id.
+pub async fn run_stdio_server() -> Result<()> {
+    serve(
+        BufReader::new(tokio::io::stdin()),
+        tokio::io::stdout(),
+        handle_request_line,
+    )
+    .await
+}
 
-    for line in stdin.lock().lines() {
-        let line = line.context("read MCP stdin line")?;
-        if line.trim().is_empty() {
-            continue;
-        }
+async fn serve<R, W, H>(input: R, mut output: W, handler: H) -> Result<()>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+    H: Fn(&str) -> Option<String> + Send + Sync + 'static,
+{
+    let handler = Arc::new(handler);
+    let pool = Arc::new(Semaphore::new(MAX_CONCURRENT_REQUESTS));
+    let (sender, mut responses) = mpsc::unbounded_channel::<String>();
+    // Dropped at end of input; the loop ends once in-flight requests have
+    // dropped their clones too.
+    let mut sender = Some(sender);
+    let mut lines = input.lines();
 
-        if let Some(response_line) = handle_request_line(&line) {
-            writeln!(stdout, "{response_line}").context("write MCP stdout response")?;
-            stdout.flush().context("flush MCP stdout response")?;
+    loop {
+        tokio::select! {
+            line = lines.next_line(), if sender.is_some() => {
+                let Some(line) = line.context("read MCP stdin line")? else {
+                    sender = None;
+                    continue;
+                };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let permit = pool
+                    .clone()
+                    .acquire_owned()
+                    .await
+                    .context("wait for a free MCP request slot")?;
+                let (handler, sender) = (handler.clone(), sender.clone());
+                // Tools read SQLite and the index synchronously.
+                tokio::task::spawn_blocking(move || {
+                    let _permit = permit;
+                    if let (Some(response), Some(sender)) = (handler(&line), sender) {
+                        let _ = sender.send(response);
+                    }
+                });
+            }
+            response = responses.recv() => {
+                let Some(response) = response else {
+                    break;
+                };
+                output
+                    .write_all(format!("{response}\n").as_bytes())
+                    .await
+                    .context("write MCP stdout response")?;
+                output.flush().await.context("flush MCP stdout response")?;
+            }
         }
     }
 
@@ -37,25 +91,62 @@ pub fn run_stdio_server() -> Result<()> {
 }
 
 fn handle_request_line(line: &str) -> Option<String> {
-    let request: JsonRpcRequest = match serde_json::from_str(line) {
+    handle_payload(line).map(|response| response.to_string())
+}
+
+/// Handles one JSON-RPC message or batch, however it arrived. Returns the
+/// response (an array for a batch), or `None` when the payload held only
+/// notifications and nothing is to be sent back.
+pub fn handle_payload(payload: &str) -> Option<Value> {
+    let message: Value = match serde_json::from_str(payload) {
         Ok(value) => value,
         Err(error) => {
-            return Some(
-                jsonrpc_error(None, -32700, "Parse error", Some(error.to_string())).to_string(),
-            );
+            return Some(jsonrpc_error(
+                None,
+                -32700,
+                "Parse error",
+                Some(error.to_string()),
+            ));
         }
     };
+    let Value::Array(batch) = message else {
+        return handle_message(message);
+    };
+    if batch.is_empty() {
+        return Some(jsonrpc_error(
+            None,
+            -32600,
+            "Invalid Request",
+            Some("batch must not be empty".to_string()),
+        ));
+    }
+    let responses = batch
+        .into_iter()
+        .filter_map(handle_message)
+        .collect::<Vec<_>>();
+    (!responses.is_empty()).then_some(Value::Array(responses))
+}
 
-    if request.jsonrpc != JSONRPC_VERSION {
-        return Some(
-            jsonrpc_error(
-                request.id,
+fn handle_message(message: Value) -> Option<Value> {
+    let request: JsonRpcRequest = match serde_json::from_value(message) {
+        Ok(value) => value,
+        Err(error) => {
+            return Some(jsonrpc_error(
+                None,
                 -32600,
                 "Invalid Request",
-                Some("jsonrpc must be \"2.0\"".to_string()),
-            )
-            .to_string(),
-        );
+                Some(error.to_string()),
+            ));
+        }
+    };
+
+    if request.jsonrpc != JSONRPC_VERSION {
+        return Some(jsonrpc_error(
+            request.id,
+            -32600,
+            "Invalid Request",
+            Some("jsonrpc must be \"2.0\"".to_string()),
+        ));
     }
 
     let id = request.id.clone();
@@ -80,15 +171,12 @@ fn handle_request_line(line: &str) -> Option<String> {
         ),
         "tools/call" => {
             let Some(tool_name) = request.params.get("name").and_then(Value::as_str) else {
-                return Some(
-                    jsonrpc_error(
-                        id,
-                        -32602,
-                        "Invalid params",
-                        Some("tools/call requires params.name".to_string()),
-                    )
-                    .to_string(),
-                );
+                return Some(jsonrpc_error(
+                    id,
+                    -32602,
+                    "Invalid params",
+                    Some("tools/call requires params.name".to_string()),
+                ));
             };
 
             let arguments = request
@@ -115,7 +203,7 @@ fn handle_request_line(line: &str) -> Option<String> {
         return None;
     }
 
-    Some(response.to_string())
+    Some(response)
 }
 
 fn jsonrpc_result(id: Option<Value>, result: Value) -> Value {
@@ -144,9 +232,11 @@ fn jsonrpc_error(id: Option<Value>, code: i64, message: &str, data: Option<Strin
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use serde_json::Value;
 
-    use super::handle_request_line;
+    use super::{handle_request_line, serve};
 
     fn parse_response(line: &str) -> Value {
         serde_json::from_str(line).expect("parse response JSON")
@@ -174,4 +264,55 @@ mod tests {
         let response = parse_response(&handle_request_line("{").expect("response"));
         assert_eq!(response["error"]["code"], -32700);
     }
+
+    #[test]
+    fn batches_answer_requests_and_skip_notifications() {
+        let batch = r#"[
+            {"jsonrpc":"2.0","method":"notifications/initialized"},
+            {"jsonrpc":"2.0","id":2,"method":"tools/list"},
+            {"jsonrpc":"2.0","id":3}
+        ]"#;
+        let response = parse_response(&handle_request_line(batch).expect("response"));
+        let responses = response.as_array().expect("batch response");
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0]["id"], 2);
+        assert_eq!(responses[1]["error"]["code"], -32600);
+
+        assert!(
+            handle_request_line(r#"[{"jsonrpc":"2.0","method":"notifications/initialized"}]"#)
+                .is_none()
+        );
+        let empty = parse_response(&handle_request_line("[]").expect("response"));
+        assert_eq!(empty["error"]["code"], -32600);
+    }
+
+    #[tokio::test]
+    async fn slow_requests_do_not_hold_up_later_ones() {
+        let input = concat!(
+            r#"{"jsonrpc":"2.0","id":1,"method":"slow"}"#,
+            "\n\n",
+            r#"{"jsonrpc":"2.0","id":2,"method":"tools/list"}"#,
+            "\n",
+        );
+        let mut output = Vec::new();
+        serve(input.as_bytes(), &mut output, |line: &str| {
+            if line.contains("slow") {
+                std::thread::sleep(Duration::from_millis(300));
+            }
+            handle_request_line(line)
+        })
+        .await
+        .expect("serve");
+
+        let responses = String::from_utf8(output)
+            .expect("utf-8 output")
+            .lines()
+            .map(parse_response)
+            .collect::<Vec<_>>();
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0]["id"], 2);
+        assert!(responses[0]["result"]["tools"].is_array());
+        assert_eq!(responses[1]["id"], 1);
+        assert_eq!(responses[1]["error"]["code"], -32601);
+    }
 }
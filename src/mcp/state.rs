@@ -0,0 +1,346 @@
+//! Database and index handles kept open for the life of an `ess mcp`
+//! process, so a tool call costs a query rather than a migration check, a
+//! config parse and an index open.
+//!
+//! Handles are checked against the files behind them on every call:
+//! - a commit to the index by `ess sync` (a new `meta.json`) reloads the
+//!   cached reader;
+//! - a rebuilt index directory or a changed `config.toml` reopens the index;
+//! - a replaced or deleted database file drops the pooled connections.
+//!
+//! Writes to the database itself need nothing: SQLite connections always
+//! read the latest committed data.
+
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+
+use crate::config::Config;
+use crate::db::Database;
+use crate::indexer::{EmailIndex, IndexSettings};
+
+/// Idle connections kept for reuse; one per request the stdio server runs
+/// at once.
+const MAX_IDLE_CONNECTIONS: usize = 8;
+
+static SHARED: OnceLock<ServerState> = OnceLock::new();
+
+/// Open handles for the default database, index and config.
+pub struct ServerState {
+    db_path: PathBuf,
+    index_path: PathBuf,
+    config_path: PathBuf,
+    connections: Mutex<ConnectionPool>,
+    index: Mutex<Option<CachedIndex>>,
+}
+
+#[derive(Default)]
+struct ConnectionPool {
+    /// The database file the idle connections were opened on.
+    file: Option<FileStamp>,
+    idle: Vec<Database>,
+}
+
+struct CachedIndex {
+    index: Arc<EmailIndex>,
+    directory: Option<FileStamp>,
+    config: Option<FileStamp>,
+    meta: Option<FileStamp>,
+}
+
+/// A pooled connection; returned to the pool when dropped.
+pub struct PooledDatabase<'a> {
+    db: Option<Database>,
+    file: Option<FileStamp>,
+    state: &'a ServerState,
+}
+
+/// Enough of a file's metadata to tell that it changed or was replaced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    id: u64,
+    /// Told apart from the id so a new file that reuses a freed inode
+    /// still counts as new, where the platform records creation times.
+    created: Option<SystemTime>,
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(Self {
+            id: file_id(&metadata),
+            created: metadata.created().ok(),
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    /// Whether both stamps are of the same file, however it has changed.
+    fn same_file(this: Option<Self>, other: Option<Self>) -> bool {
+        this.map(|stamp| (stamp.id, stamp.created)) == other.map(|stamp| (stamp.id, stamp.created))
+    }
+}
+
+#[cfg(unix)]
+fn file_id(metadata: &std::fs::Metadata) -> u64 {
+    std::os::unix::fs::MetadataExt::ino(metadata)
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &std::fs::Metadata) -> u64 {
+    0
+}
+
+impl ServerState {
+    pub fn new(db_path: PathBuf, index_path: PathBuf, config_path: PathBuf) -> Self {
+        Self {
+            db_path,
+            index_path,
+            config_path,
+            connections: Mutex::new(ConnectionPool::default()),
+            index: Mutex::new(None),
+        }
+    }
+
+    /// Handles for the default database, index and config locations.
+    pub fn for_default_paths() -> Result<Self> {
+        Ok(Self::new(
+            Database::default_db_path().context("resolve ESS database path")?,
+            EmailIndex::default_index_path().context("resolve ESS index path")?,
+            Config::default_path().context("resolve ESS config path")?,
+        ))
+    }
+
+    /// The state shared by every tool call in this process, for the default
+    /// locations.
+    pub fn shared() -> Result<&'static Self> {
+        if let Some(state) = SHARED.get() {
+            return Ok(state);
+        }
+        let state = Self::for_default_paths()?;
+        Ok(SHARED.get_or_init(|| state))
+    }
+
+    /// A connection to the database, reused from an earlier call when the
+    /// file is still the one it was opened on.
+    pub fn database(&self) -> Result<PooledDatabase<'_>> {
+        let file = FileStamp::of(&self.db_path);
+        let reused = {
+            let mut pool = lock(&self.connections);
+            if !FileStamp::same_file(pool.file, file) {
+                pool.idle.clear();
+                pool.file = file;
+            }
+            pool.idle.pop()
+        };
+        let db = match reused {
+            Some(db) => db,
+            None => Database::open(&self.db_path)
+                .with_context(|| format!("open ESS database at {}", self.db_path.display()))?,
+        };
+        Ok(PooledDatabase {
+            db: Some(db),
+            // Stamped after the open when the open created the file.
+            file: file.or_else(|| FileStamp::of(&self.db_path)),
+            state: self,
+        })
+    }
+
+    /// The search index, opened (and rebuilt from `db` when missing or
+    /// unreadable) on first use, then reloaded or reopened as its files
+    /// change.
+    pub fn index(&self, db: &Database) -> Result<Arc<EmailIndex>> {
+        let mut cached = lock(&self.index);
+        let directory = FileStamp::of(&self.index_path);
+        let config = FileStamp::of(&self.config_path);
+        if let Some(entry) = cached.as_mut() {
+            if FileStamp::same_file(entry.directory, directory) && entry.config == config {
+                let meta = FileStamp::of(&self.index_path.join("meta.json"));
+                if entry.meta != meta {
+                    entry.index.reload().context("reload ESS index")?;
+                    entry.meta = meta;
+                }
+                return Ok(entry.index.clone());
+            }
+        }
+
+        let settings = Config::load(&self.config_path)
+            .context("load ESS config")?
+            .index_settings()
+            .context("read [index] settings from config.toml")?;
+        let index = Arc::new(open_index_with_recovery(db, &self.index_path, &settings)?);
+        *cached = Some(CachedIndex {
+            index: index.clone(),
+            directory: FileStamp::of(&self.index_path),
+            config,
+            meta: FileStamp::of(&self.index_path.join("meta.json")),
+        });
+        Ok(index)
+    }
+}
+
+impl Deref for PooledDatabase<'_> {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        self.db.as_ref().expect("connection is held until drop")
+    }
+}
+
+impl Drop for PooledDatabase<'_> {
+    fn drop(&mut self) {
+        let Some(db) = self.db.take() else {
+            return;
+        };
+        let mut pool = lock(&self.state.connections);
+        if pool.file.is_none() {
+            pool.file = self.file;
+        }
+        if FileStamp::same_file(pool.file, self.file) && pool.idle.len() < MAX_IDLE_CONNECTIONS {
+            pool.idle.push(db);
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // A panicking tool call leaves nothing half-updated behind these locks.
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn open_index_with_recovery(
+    db: &Database,
+    index_path: &Path,
+    settings: &IndexSettings,
+) -> Result<EmailIndex> {
+    if !index_path.join("meta.json").exists() && db.has_emails()? {
+        tracing::warn!(
+            "no ESS index at {}; rebuilding it from SQLite",
+            index_path.display()
+        );
+        rebuild_index_from_db(db, index_path, settings)
+            .with_context(|| format!("rebuild missing ESS index at {}", index_path.display()))?;
+    }
+    match EmailIndex::open_with_settings(index_path, settings) {
+        Ok(index) => Ok(index),
+        Err(open_error) => {
+            tracing::warn!(
+                "failed to open ESS index at {}: {open_error}; attempting rebuild from SQLite",
+                index_path.display()
+            );
+            rebuild_index_from_db(db, index_path, settings).with_context(|| {
+                format!(
+                    "rebuild ESS index at {} after open failure",
+                    index_path.display()
+                )
+            })?;
+            EmailIndex::open_with_settings(index_path, settings)
+                .with_context(|| format!("re-open rebuilt ESS index at {}", index_path.display()))
+        }
+    }
+}
+
+fn rebuild_index_from_db(
+    db: &Database,
+    index_path: &Path,
+    settings: &IndexSettings,
+) -> Result<usize> {
+    if index_path.exists() {
+        std::fs::remove_dir_all(index_path).with_context(|| {
+            format!(
+                "remove corrupted ESS index directory {}",
+                index_path.display()
+            )
+        })?;
+    }
+    std::fs::create_dir_all(index_path)
+        .with_context(|| format!("create ESS index directory {}", index_path.display()))?;
+    let mut index = EmailIndex::open_with_settings(index_path, settings)
+        .with_context(|| format!("initialize ESS index at {}", index_path.display()))?;
+    let indexed = index
+        .reindex(db)
+        .context("reindex ESS index from SQLite source-of-truth")?;
+    tracing::warn!(
+        "rebuilt ESS index at {} with {indexed} indexed emails",
+        index_path.display()
+    );
+    Ok(indexed)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::ServerState;
+    use crate::db::models::Email;
+    use crate::db::Database;
+    use crate::indexer::EmailIndex;
+
+    fn email(id: &str) -> Email {
+        Email {
+            subject: Some("Quarterly budget".to_string()),
+            from_address: Some("alice@example.com".to_string()),
+            body_text: Some("numbers attached".to_string()),
+            received_at: "2026-02-01T10:00:00Z".to_string(),
+            ..Email::fixture(id)
+        }
+    }
+
+    #[test]
+    fn handles_are_reused_until_their_files_change() {
+        let root = std::env::temp_dir().join(format!("ess-mcp-state-{}", uuid::Uuid::new_v4()));
+        let (db_path, index_path) = (root.join("ess.db"), root.join("index"));
+        let state = ServerState::new(
+            db_path.clone(),
+            index_path.clone(),
+            root.join("config.toml"),
+        );
+
+        let index = {
+            let db = state.database().expect("database");
+            db.insert_email(&email("a")).expect("insert a");
+            state.index(&db).expect("rebuild missing index")
+        };
+        assert_eq!(index.get_stats().expect("stats").doc_count, 1);
+        let db = state.database().expect("pooled database");
+        assert!(Arc::ptr_eq(
+            &index,
+            &state.index(&db).expect("cached index")
+        ));
+
+        // A sync in another process commits to the same index.
+        db.insert_email(&email("b")).expect("insert b");
+        let mut writer = EmailIndex::open(&index_path).expect("writer handle");
+        writer.add_email(&email("b"), "personal").expect("index b");
+        drop(writer);
+        let reloaded = state.index(&db).expect("reloaded index");
+        assert!(Arc::ptr_eq(&index, &reloaded));
+        assert_eq!(reloaded.get_stats().expect("stats").doc_count, 2);
+
+        // `ess reindex` replaces the directory.
+        std::fs::remove_dir_all(&index_path).expect("remove index");
+        let rebuilt = state.index(&db).expect("rebuilt index");
+        assert!(!Arc::ptr_eq(&index, &rebuilt));
+        assert_eq!(rebuilt.get_stats().expect("stats").doc_count, 2);
+        drop(db);
+
+        // A database swapped out underneath the server is not read through
+        // the old connections.
+        std::fs::remove_file(&db_path).expect("remove database");
+        let fresh = Database::open(&db_path).expect("new database");
+        fresh.insert_email(&email("c")).expect("insert c");
+        drop(fresh);
+        let db = state.database().expect("database after swap");
+        assert!(db.get_email("a").expect("lookup a").is_none());
+        assert!(db.get_email("c").expect("lookup c").is_some());
+
+        drop((db, index, reloaded, rebuilt));
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -1,14 +1,65 @@
-use std::path::Path;
+use std::collections::HashMap;
+use std::future::Future;
 use std::str::FromStr;
 
 use anyhow::{anyhow, Context, Result};
 use chrono::NaiveDate;
 use serde_json::{json, Value};
 
-use crate::db::{Database, EmailSearchFilters};
-use crate::indexer::EmailIndex;
+use crate::contacts;
+use crate::db::models::Email;
+use crate::db::{Database, EmailKeyset, EmailSearchFilters, Preview};
+use crate::embeddings;
+use crate::mcp::state::ServerState;
+use crate::output::markdown::{self, MarkdownMessage};
 use crate::search;
 use crate::search::filters::{EmailFilters, Scope};
+use crate::search::{Cursor, Facet, SearchResult};
+use crate::summaries;
+
+const CURSOR_DESCRIPTION: &str =
+    "Page through results: pass \"\" for the first page, then each response's next_cursor; the result becomes {results, next_cursor}";
+
+const OFFSET_DESCRIPTION: &str =
+    "Skip this many results first; with cursor, counted from the cursor's position";
+const FIELDS_DESCRIPTION: &str =
+    "Email fields to return, e.g. [\"subject\", \"from_address\", \"received_at\"], or fields to leave out, e.g. [\"-body_html\", \"-metadata\"]; id is always returned [default: all]";
+const MAX_BODY_CHARS_DESCRIPTION: &str =
+    "Cut body_text to this many characters; cut bodies end with … and carry body_text_truncated: true, and ess_show returns the whole email";
+
+/// Serialized [`crate::db::models::Email`] fields that `fields` can select.
+const EMAIL_FIELDS: &[&str] = &[
+    "id",
+    "internet_message_id",
+    "in_reply_to",
+    "references",
+    "conversation_id",
+    "account_id",
+    "subject",
+    "from_address",
+    "from_name",
+    "to_addresses",
+    "cc_addresses",
+    "bcc_addresses",
+    "body_text",
+    "body_html",
+    "body_preview",
+    "received_at",
+    "sent_at",
+    "importance",
+    "is_read",
+    "has_attachments",
+    "folder",
+    "categories",
+    "flag_status",
+    "web_link",
+    "metadata",
+];
+
+const FROM_DOMAIN_DESCRIPTION: &str =
+    "Sender domain, e.g. example.com; matches that exact domain, not its subdomains";
+const DATE_DESCRIPTION: &str =
+    "YYYY-MM-DD or relative: today, yesterday, 7d, 2w, 3mo, '2 weeks ago', 'last monday', 'end of last month'";
 
 pub fn tool_schemas() -> Vec<Value> {
     vec![
@@ -20,28 +71,100 @@ pub fn tool_schemas() -> Vec<Value> {
                 "properties": {
                     "query": {"type": "string"},
                     "from": {"type": "string"},
+                    "from_domain": {"type": "string", "description": FROM_DOMAIN_DESCRIPTION},
+                    "to": {"type": "string"},
+                    "since": {"type": "string", "description": DATE_DESCRIPTION},
+                    "until": {"type": "string", "description": DATE_DESCRIPTION},
+                    "scope": {"type": "string"},
+                    "account": {"type": "string"},
+                    "folder": {"type": "string"},
+                    "limit": {"type": "integer", "minimum": 1},
+                    "offset": {"type": "integer", "minimum": 0, "description": OFFSET_DESCRIPTION},
+                    "cursor": {"type": "string", "description": CURSOR_DESCRIPTION},
+                    "fields": fields_schema(),
+                    "max_body_chars": {"type": "integer", "minimum": 1, "description": MAX_BODY_CHARS_DESCRIPTION},
+                    "facets": {
+                        "type": "array",
+                        "items": {"type": "string", "enum": ["sender", "domain", "folder", "month"]},
+                        "description": "Also return match counts per value; the result becomes {results, facets}"
+                    },
+                    "group_by_thread": {
+                        "type": "boolean",
+                        "description": "One result per conversation: its best match plus conversation_id, messages and participants; not with cursor or facets"
+                    }
+                },
+                "required": ["query"]
+            }
+        }),
+        json!({
+            "name": "ess_semantic_search",
+            "description": "Search emails by meaning: a natural-language question ranked by keyword and embedding similarity together (needs `ess embed`)",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": {"type": "string"},
+                    "from": {"type": "string"},
+                    "from_domain": {"type": "string", "description": FROM_DOMAIN_DESCRIPTION},
                     "to": {"type": "string"},
-                    "since": {"type": "string"},
-                    "until": {"type": "string"},
+                    "since": {"type": "string", "description": DATE_DESCRIPTION},
+                    "until": {"type": "string", "description": DATE_DESCRIPTION},
                     "scope": {"type": "string"},
                     "account": {"type": "string"},
                     "folder": {"type": "string"},
-                    "limit": {"type": "integer", "minimum": 1}
+                    "limit": {"type": "integer", "minimum": 1},
+                    "offset": {"type": "integer", "minimum": 0, "description": OFFSET_DESCRIPTION},
+                    "fields": fields_schema(),
+                    "max_body_chars": {"type": "integer", "minimum": 1, "description": MAX_BODY_CHARS_DESCRIPTION}
                 },
                 "required": ["query"]
             }
         }),
         json!({
             "name": "ess_thread",
-            "description": "Return messages for a conversation",
+            "description": "Return messages for a conversation, as JSON or as one Markdown document",
             "inputSchema": {
                 "type": "object",
                 "properties": {
-                    "conversation_id": {"type": "string"}
+                    "conversation_id": {"type": "string"},
+                    "format": {
+                        "type": "string",
+                        "enum": ["json", "markdown"],
+                        "description": "`markdown` returns {conversation_id, markdown}: quoted headers, bodies converted from HTML, attachment lists [default: json]"
+                    }
                 },
                 "required": ["conversation_id"]
             }
         }),
+        json!({
+            "name": "ess_show",
+            "description": "Return one email in full by id, as `ess show --json` prints it",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "id": {"type": "string"},
+                    "include_html": {
+                        "type": "boolean",
+                        "description": "Include body_html; set false to save tokens when body_text is enough [default: true]"
+                    }
+                },
+                "required": ["id"]
+            }
+        }),
+        json!({
+            "name": "ess_summarize",
+            "description": "Summarize a conversation with the LLM set by ESS_SUMMARY_URL; a stored summary is returned while it still covers every message",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "id": {"type": "string", "description": "A conversation id, or the id of any email in it"},
+                    "refresh": {
+                        "type": "boolean",
+                        "description": "Write a new summary even if the stored one is current [default: false]"
+                    }
+                },
+                "required": ["id"]
+            }
+        }),
         json!({
             "name": "ess_contacts",
             "description": "Search contacts by name/email",
@@ -53,6 +176,18 @@ pub fn tool_schemas() -> Vec<Value> {
                 "required": ["query"]
             }
         }),
+        json!({
+            "name": "ess_complete_address",
+            "description": "Suggest recipients for a partly typed name or address, ranked by match, message count and recency",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "fragment": {"type": "string", "description": "Start of, or any part of, a name or address"},
+                    "limit": {"type": "integer", "minimum": 1, "default": 10}
+                },
+                "required": ["fragment"]
+            }
+        }),
         json!({
             "name": "ess_recent",
             "description": "List most recent emails",
@@ -62,8 +197,14 @@ pub fn tool_schemas() -> Vec<Value> {
                     "scope": {"type": "string"},
                     "account": {"type": "string"},
                     "folder": {"type": "string"},
+                    "since": {"type": "string", "description": DATE_DESCRIPTION},
+                    "until": {"type": "string", "description": DATE_DESCRIPTION},
                     "unread_only": {"type": "boolean"},
-                    "limit": {"type": "integer", "minimum": 1}
+                    "limit": {"type": "integer", "minimum": 1},
+                    "offset": {"type": "integer", "minimum": 0, "description": OFFSET_DESCRIPTION},
+                    "cursor": {"type": "string", "description": CURSOR_DESCRIPTION},
+                    "fields": fields_schema(),
+                    "max_body_chars": {"type": "integer", "minimum": 1, "description": MAX_BODY_CHARS_DESCRIPTION}
                 }
             }
         }),
@@ -78,11 +219,23 @@ pub fn tool_schemas() -> Vec<Value> {
     ]
 }
 
+fn fields_schema() -> Value {
+    json!({
+        "type": "array",
+        "items": {"type": "string"},
+        "description": FIELDS_DESCRIPTION
+    })
+}
+
 pub fn call_tool(name: &str, arguments: Value) -> Result<Value> {
     match name {
         "ess_search" => ess_search(&arguments),
+        "ess_semantic_search" => ess_semantic_search(&arguments),
         "ess_thread" => ess_thread(&arguments),
+        "ess_show" => ess_show(&arguments),
+        "ess_summarize" => ess_summarize(&arguments),
         "ess_contacts" => ess_contacts(&arguments),
+        "ess_complete_address" => ess_complete_address(&arguments),
         "ess_recent" => ess_recent(&arguments),
         "ess_stats" => ess_stats(),
         other => Err(anyhow!("unknown tool: {other}")),
@@ -92,6 +245,7 @@ pub fn call_tool(name: &str, arguments: Value) -> Result<Value> {
 fn ess_search(arguments: &Value) -> Result<Value> {
     let query = required_string(arguments, "query")?;
     let from = optional_string(arguments, "from");
+    let from_domain = optional_string(arguments, "from_domain");
     let to = optional_string(arguments, "to");
     let since = optional_date(arguments, "since")?;
     let until = optional_date(arguments, "until")?;
@@ -99,74 +253,271 @@ fn ess_search(arguments: &Value) -> Result<Value> {
     let account = optional_string(arguments, "account");
     let folder = optional_string(arguments, "folder");
     let limit = optional_usize(arguments, "limit")?.unwrap_or(20);
+    let offset = optional_offset(arguments, "offset")?;
+    let projection = EmailProjection::from_arguments(arguments)?;
+    let facets = optional_facets(arguments, "facets")?;
+    let cursor = optional_cursor(arguments, "cursor")?;
+    let group_by_thread = optional_bool(arguments, "group_by_thread").unwrap_or(false);
+    if group_by_thread && (cursor.is_some() || !facets.is_empty()) {
+        return Err(anyhow!(
+            "group_by_thread cannot be combined with cursor or facets"
+        ));
+    }
 
-    let db = open_db()?;
-    let index = open_index_with_recovery(&db)?;
+    let state = ServerState::shared()?;
+    let db = state.database()?;
+    let index = state.index(&db)?;
     let filters = EmailFilters {
         scope,
         from,
+        from_domain,
         to,
         since,
         until,
         account,
         folder,
         limit,
+        offset,
         ..EmailFilters::default()
     };
 
-    let results = search::search_emails(&index, &db, &query, &filters)?;
+    if group_by_thread {
+        let threads = search::search_threads(&index, &db, &query, &filters)?;
+        return Ok(json!(threads
+            .into_iter()
+            .map(|thread| {
+                Ok(json!({
+                    "conversation_id": thread.conversation_id,
+                    "messages": thread.messages,
+                    "participants": thread.participants,
+                    "email": projection.apply(&thread.best.email)?,
+                    "score": thread.best.score,
+                    "snippet": thread.best.snippet.as_ref().map(search::Snippet::html),
+                }))
+            })
+            .collect::<Result<Vec<_>>>()?));
+    }
+
+    let paged = cursor.is_some();
+    let page = search::search_emails_page(&index, &db, &query, &filters, cursor.flatten())?;
+    let previews = result_previews(&db, &page.results)?;
+    let results = json!(page
+        .results
+        .into_iter()
+        .map(|result| {
+            Ok(json!({
+                "preview": previews.get(&result.email.id),
+                "email": projection.apply(&result.email)?,
+                "score": result.score,
+                "snippet": result.snippet.as_ref().map(search::Snippet::html),
+            }))
+        })
+        .collect::<Result<Vec<_>>>()?);
+
+    if facets.is_empty() && !paged {
+        return Ok(results);
+    }
+    let mut response = json!({ "results": results });
+    if paged {
+        response["next_cursor"] = json!(page.next_cursor.map(|cursor| cursor.to_string()));
+    }
+    if !facets.is_empty() {
+        response["facets"] = json!(search::facet_counts(
+            &db,
+            &query,
+            &filters,
+            &facets,
+            search::facets::DEFAULT_FACET_LIMIT,
+        )?);
+    }
+    Ok(response)
+}
+
+fn ess_semantic_search(arguments: &Value) -> Result<Value> {
+    let query = required_string(arguments, "query")?;
+    let filters = EmailFilters {
+        scope: optional_scope(arguments, "scope")?,
+        from: optional_string(arguments, "from"),
+        from_domain: optional_string(arguments, "from_domain"),
+        to: optional_string(arguments, "to"),
+        since: optional_date(arguments, "since")?,
+        until: optional_date(arguments, "until")?,
+        account: optional_string(arguments, "account"),
+        folder: optional_string(arguments, "folder"),
+        limit: optional_usize(arguments, "limit")?.unwrap_or(20),
+        offset: optional_offset(arguments, "offset")?,
+        ..EmailFilters::default()
+    };
+    let projection = EmailProjection::from_arguments(arguments)?;
+
+    let state = ServerState::shared()?;
+    let db = state.database()?;
+    let index = state.index(&db)?;
+    let embedder = embeddings::from_env()?;
+    let results = block_on(search::semantic_search(
+        &index,
+        &db,
+        embedder.as_ref(),
+        &query,
+        &filters,
+    ))??;
+    let previews = result_previews(&db, &results)?;
     Ok(json!(results
         .into_iter()
-        .map(|result| json!({
-            "email": result.email,
-            "score": result.score,
-            "snippet": result.snippet,
-        }))
-        .collect::<Vec<_>>()))
+        .map(|result| {
+            Ok(json!({
+                "preview": previews.get(&result.email.id),
+                "email": projection.apply(&result.email)?,
+                "score": result.score,
+                "snippet": result.snippet.as_ref().map(search::Snippet::html),
+            }))
+        })
+        .collect::<Result<Vec<_>>>()?))
+}
+
+fn result_previews(db: &Database, results: &[SearchResult]) -> Result<HashMap<String, Preview>> {
+    let ids = results
+        .iter()
+        .map(|result| result.email.id.as_str())
+        .collect::<Vec<_>>();
+    Ok(db.previews(&ids)?)
 }
 
 fn ess_thread(arguments: &Value) -> Result<Value> {
     let conversation_id = required_string(arguments, "conversation_id")?;
-    let db = open_db()?;
+    let db = ServerState::shared()?.database()?;
     let emails = db.get_emails_by_conversation(&conversation_id)?;
-    Ok(serde_json::to_value(emails)?)
+    match optional_string(arguments, "format").as_deref() {
+        None | Some("json") => Ok(serde_json::to_value(emails)?),
+        Some("markdown") => {
+            let attachments = emails
+                .iter()
+                .map(|email| db.get_attachments(&email.id))
+                .collect::<Result<Vec<_>, _>>()?;
+            let messages = emails
+                .iter()
+                .zip(&attachments)
+                .map(|(email, attachments)| MarkdownMessage { email, attachments })
+                .collect::<Vec<_>>();
+            let notes = db.notes_for_conversation(&conversation_id)?;
+            let summary = db.summary(&conversation_id)?;
+            Ok(json!({
+                "conversation_id": conversation_id,
+                "markdown": markdown::format_thread(&messages, &notes, summary.as_ref()),
+            }))
+        }
+        Some(other) => Err(anyhow!(
+            "invalid format '{other}'; expected json or markdown"
+        )),
+    }
+}
+
+fn ess_show(arguments: &Value) -> Result<Value> {
+    let id = required_string(arguments, "id")?;
+    let include_html = optional_bool(arguments, "include_html").unwrap_or(true);
+    let db = ServerState::shared()?.database()?;
+    show_email(&db, &id, include_html)
+}
+
+fn show_email(db: &Database, id: &str, include_html: bool) -> Result<Value> {
+    let mut email = db
+        .get_email(id)?
+        .ok_or_else(|| anyhow!("email not found for id '{id}'"))?;
+    if !include_html {
+        email.body_html = None;
+    }
+    Ok(serde_json::to_value(email)?)
+}
+
+fn ess_summarize(arguments: &Value) -> Result<Value> {
+    let id = required_string(arguments, "id")?;
+    let refresh = optional_bool(arguments, "refresh").unwrap_or(false);
+    let db = ServerState::shared()?.database()?;
+    let (summary, cached) = block_on(summaries::summarize(&db, &id, refresh))??;
+    let mut value = serde_json::to_value(summary)?;
+    value["cached"] = Value::Bool(cached);
+    Ok(value)
 }
 
 fn ess_contacts(arguments: &Value) -> Result<Value> {
     let query = required_string(arguments, "query")?;
-    let db = open_db()?;
+    let db = ServerState::shared()?.database()?;
     let contacts = db.get_contacts(Some(query.as_str()))?;
     Ok(serde_json::to_value(contacts)?)
 }
 
+fn ess_complete_address(arguments: &Value) -> Result<Value> {
+    let fragment = required_string(arguments, "fragment")?;
+    let limit = optional_usize(arguments, "limit")?.unwrap_or(10);
+    let db = ServerState::shared()?.database()?;
+    Ok(serde_json::to_value(contacts::complete_address(
+        &db, &fragment, limit,
+    )?)?)
+}
+
 fn ess_recent(arguments: &Value) -> Result<Value> {
     let scope = optional_scope(arguments, "scope")?;
     let account = optional_string(arguments, "account");
     let folder = optional_string(arguments, "folder");
+    let since = optional_date(arguments, "since")?;
+    let until = optional_date(arguments, "until")?;
     let unread_only = optional_bool(arguments, "unread_only").unwrap_or(false);
     let limit = optional_usize(arguments, "limit")?.unwrap_or(20);
+    let offset = optional_offset(arguments, "offset")?;
+    let cursor = optional_cursor(arguments, "cursor")?;
+    let projection = EmailProjection::from_arguments(arguments)?;
 
-    let db = open_db()?;
+    let db = ServerState::shared()?.database()?;
     let mut emails = db.search_emails(EmailSearchFilters {
         query: None,
         account_id: account,
         account_type: scope_to_account_type(scope),
         folder,
         from_address: None,
+        from_domain: None,
+        since,
+        until,
+        tag: None,
+        needs_reply_before: None,
         limit,
-        offset: 0,
+        offset,
+        after: cursor
+            .clone()
+            .flatten()
+            .map(Cursor::into_recency)
+            .transpose()?,
     })?;
+    // Taken before the unread filter so the next page resumes after every
+    // row this one examined.
+    let next_cursor = (emails.len() == limit)
+        .then(|| {
+            emails
+                .last()
+                .map(|email| Cursor::Recency(EmailKeyset::of(email)))
+        })
+        .flatten();
 
     if unread_only {
         emails.retain(|email| !email.is_read.unwrap_or(false));
     }
 
-    Ok(serde_json::to_value(emails)?)
+    let emails = emails
+        .iter()
+        .map(|email| projection.apply(email))
+        .collect::<Result<Vec<_>>>()?;
+    if cursor.is_none() {
+        return Ok(json!(emails));
+    }
+    Ok(json!({
+        "results": emails,
+        "next_cursor": next_cursor.map(|cursor| cursor.to_string()),
+    }))
 }
 
 fn ess_stats() -> Result<Value> {
-    let db = open_db()?;
-    let index = open_index_with_recovery(&db)?;
+    let state = ServerState::shared()?;
+    let db = state.database()?;
+    let index = state.index(&db)?;
 
     let db_stats = db.get_stats()?;
     let accounts = db.list_accounts()?;
@@ -195,57 +546,24 @@ fn ess_stats() -> Result<Value> {
         "total_emails": db_stats.total_emails,
         "accounts": account_entries,
         "index_size": index_stats.index_size_bytes,
-        "contact_count": db_stats.total_contacts
+        "index_segments": index_stats.segment_count,
+        "index_deleted_docs": index_stats.deleted_doc_count,
+        "contact_count": db_stats.total_contacts,
+        "folders": db_stats.emails_by_folder
     }))
 }
 
-fn open_db() -> Result<Database> {
-    let db_path = Database::default_db_path().context("resolve ESS database path")?;
-    Database::open(&db_path).with_context(|| format!("open ESS database at {}", db_path.display()))
-}
-
-fn open_index_with_recovery(db: &Database) -> Result<EmailIndex> {
-    let index_path = EmailIndex::default_index_path().context("resolve ESS index path")?;
-    match EmailIndex::open(&index_path) {
-        Ok(index) => Ok(index),
-        Err(open_error) => {
-            tracing::warn!(
-                "failed to open ESS index at {}: {open_error}; attempting rebuild from SQLite",
-                index_path.display()
-            );
-            rebuild_index_from_db(db, &index_path).with_context(|| {
-                format!(
-                    "rebuild ESS index at {} after open failure",
-                    index_path.display()
-                )
-            })?;
-            EmailIndex::open(&index_path)
-                .with_context(|| format!("re-open rebuilt ESS index at {}", index_path.display()))
-        }
-    }
-}
-
-fn rebuild_index_from_db(db: &Database, index_path: &Path) -> Result<usize> {
-    if index_path.exists() {
-        std::fs::remove_dir_all(index_path).with_context(|| {
-            format!(
-                "remove corrupted ESS index directory {}",
-                index_path.display()
-            )
-        })?;
+/// Runs an async call from these synchronous handlers: on the `ess mcp`
+/// runtime when there is one, otherwise on a throwaway one.
+fn block_on<F: Future>(future: F) -> Result<F::Output> {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => Ok(tokio::task::block_in_place(|| handle.block_on(future))),
+        Err(_) => Ok(tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .context("start runtime for tool call")?
+            .block_on(future)),
     }
-    std::fs::create_dir_all(index_path)
-        .with_context(|| format!("create ESS index directory {}", index_path.display()))?;
-    let mut index = EmailIndex::open(index_path)
-        .with_context(|| format!("initialize ESS index at {}", index_path.display()))?;
-    let indexed = index
-        .reindex(db)
-        .context("reindex ESS index from SQLite source-of-truth")?;
-    tracing::warn!(
-        "rebuilt ESS index at {} with {indexed} indexed emails",
-        index_path.display()
-    );
-    Ok(indexed)
 }
 
 fn required_string(arguments: &Value, key: &str) -> Result<String> {
@@ -282,8 +600,8 @@ fn optional_usize(arguments: &Value, key: &str) -> Result<Option<usize>> {
 fn optional_date(arguments: &Value, key: &str) -> Result<Option<NaiveDate>> {
     optional_string(arguments, key)
         .map(|value| {
-            NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
-                .with_context(|| format!("param '{key}' must be YYYY-MM-DD"))
+            search::dates::parse_date(&value)
+                .with_context(|| format!("param '{key}' must be a date"))
         })
         .transpose()
 }
@@ -296,6 +614,127 @@ fn optional_scope(arguments: &Value, key: &str) -> Result<Scope> {
     Ok(scope)
 }
 
+/// Accepts either an array of facet names or a comma-separated string.
+fn optional_facets(arguments: &Value, key: &str) -> Result<Vec<Facet>> {
+    let names: Vec<String> = match arguments.get(key) {
+        None | Some(Value::Null) => return Ok(Vec::new()),
+        Some(Value::String(value)) => value.split(',').map(str::to_string).collect(),
+        Some(Value::Array(values)) => values
+            .iter()
+            .map(|value| {
+                value
+                    .as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| anyhow!("param '{key}' must contain facet names"))
+            })
+            .collect::<Result<_>>()?,
+        Some(_) => return Err(anyhow!("param '{key}' must be an array of facet names")),
+    };
+    names
+        .iter()
+        .filter(|name| !name.trim().is_empty())
+        .map(|name| Facet::from_str(name).map_err(anyhow::Error::msg))
+        .collect()
+}
+
+/// `None` when the caller did not ask for paging; `Some(None)` for the first
+/// page (an empty string).
+fn optional_cursor(arguments: &Value, key: &str) -> Result<Option<Option<Cursor>>> {
+    match arguments.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(value)) if value.trim().is_empty() => Ok(Some(None)),
+        Some(Value::String(value)) => Ok(Some(Some(Cursor::from_str(value)?))),
+        Some(_) => Err(anyhow!("param '{key}' must be a string")),
+    }
+}
+
+/// Which email fields a tool returns, and how much of the body.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct EmailProjection {
+    /// Only these fields, when the caller listed any.
+    include: Vec<String>,
+    /// Fields listed with a leading `-`.
+    exclude: Vec<String>,
+    max_body_chars: Option<usize>,
+}
+
+impl EmailProjection {
+    fn from_arguments(arguments: &Value) -> Result<Self> {
+        let names: Vec<String> = match arguments.get("fields") {
+            None | Some(Value::Null) => Vec::new(),
+            Some(Value::String(value)) => value.split(',').map(str::to_string).collect(),
+            Some(Value::Array(values)) => values
+                .iter()
+                .map(|value| {
+                    value
+                        .as_str()
+                        .map(str::to_string)
+                        .ok_or_else(|| anyhow!("param 'fields' must contain field names"))
+                })
+                .collect::<Result<_>>()?,
+            Some(_) => return Err(anyhow!("param 'fields' must be an array of field names")),
+        };
+        let mut projection = Self {
+            max_body_chars: optional_usize(arguments, "max_body_chars")?,
+            ..Self::default()
+        };
+        for name in names
+            .iter()
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty())
+        {
+            let (list, field) = match name.strip_prefix('-') {
+                Some(field) => (&mut projection.exclude, field),
+                None => (&mut projection.include, name),
+            };
+            if !EMAIL_FIELDS.contains(&field) {
+                return Err(anyhow!(
+                    "unknown email field '{field}' in param 'fields'; expected one of {}",
+                    EMAIL_FIELDS.join(", ")
+                ));
+            }
+            list.push(field.to_string());
+        }
+        if !projection.include.is_empty() && !projection.exclude.is_empty() {
+            return Err(anyhow!(
+                "param 'fields' must either list fields to return or fields to leave out, not both"
+            ));
+        }
+        Ok(projection)
+    }
+
+    fn apply(&self, email: &Email) -> Result<Value> {
+        let mut value = serde_json::to_value(email)?;
+        let Some(fields) = value.as_object_mut() else {
+            return Ok(value);
+        };
+        if !self.include.is_empty() {
+            fields.retain(|name, _| name == "id" || self.include.contains(name));
+        }
+        fields.retain(|name, _| name == "id" || !self.exclude.contains(name));
+        if let (Some(max), Some(Value::String(body))) =
+            (self.max_body_chars, fields.get_mut("body_text"))
+        {
+            if let Some((cut, _)) = body.char_indices().nth(max) {
+                body.truncate(cut);
+                body.push('…');
+                fields.insert("body_text_truncated".to_string(), Value::Bool(true));
+            }
+        }
+        Ok(value)
+    }
+}
+
+fn optional_offset(arguments: &Value, key: &str) -> Result<usize> {
+    match arguments.get(key) {
+        None | Some(Value::Null) => Ok(0),
+        Some(raw) => raw
+            .as_u64()
+            .map(|value| value as usize)
+            .ok_or_else(|| anyhow!("param '{key}' must be a non-negative integer")),
+    }
+}
+
 fn scope_to_account_type(scope: Scope) -> Option<String> {
     match scope {
         Scope::Professional => Some("professional".to_string()),
@@ -303,3 +742,107 @@ fn scope_to_account_type(scope: Scope) -> Option<String> {
         Scope::All => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::{show_email, EmailProjection, EMAIL_FIELDS};
+    use crate::db::models::Email;
+    use crate::db::Database;
+    use crate::output::{self, OutputFormat};
+
+    fn email() -> Email {
+        Email {
+            internet_message_id: Some("<m1@example.com>".to_string()),
+            conversation_id: Some("t1".to_string()),
+            subject: Some("Q3 budget".to_string()),
+            from_address: Some("alice@example.com".to_string()),
+            from_name: Some("Alice".to_string()),
+            to_addresses: vec!["me@example.com".to_string()],
+            body_text: Some("Numbers attached.".to_string()),
+            body_html: Some("<p>Numbers attached.</p>".to_string()),
+            received_at: "2026-02-01T10:00:00Z".to_string(),
+            is_read: Some(false),
+            has_attachments: Some(false),
+            folder: Some("inbox".to_string()),
+            categories: vec!["Work".to_string()],
+            ..Email::fixture("m1")
+        }
+    }
+
+    #[test]
+    fn ess_show_matches_show_json() {
+        let path = std::env::temp_dir().join(format!("ess-tools-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        db.insert_email(&email()).expect("insert email");
+
+        let stored = db.get_email("m1").expect("get email").expect("email");
+        let cli: Value = serde_json::from_str(
+            &output::format_email(OutputFormat::Json, &stored).expect("format email"),
+        )
+        .expect("parse show --json");
+        assert_eq!(show_email(&db, "m1", true).expect("show"), cli);
+
+        let text_only = show_email(&db, "m1", false).expect("show without html");
+        assert_eq!(text_only["body_html"], Value::Null);
+        assert_eq!(text_only["body_text"], "Numbers attached.");
+        assert!(show_email(&db, "missing", true).is_err());
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn projections_select_fields_and_cut_bodies() {
+        let email = email();
+        let projection = |arguments: Value| EmailProjection::from_arguments(&arguments);
+
+        let compact = projection(json!({"fields": ["subject", "body_text"], "max_body_chars": 7}))
+            .expect("projection")
+            .apply(&email)
+            .expect("apply");
+        assert_eq!(
+            compact,
+            json!({
+                "id": "m1",
+                "subject": "Q3 budget",
+                "body_text": "Numbers…",
+                "body_text_truncated": true
+            })
+        );
+
+        let without_html = projection(json!({"fields": "-body_html,-metadata"}))
+            .expect("projection")
+            .apply(&email)
+            .expect("apply");
+        assert!(without_html.get("body_html").is_none());
+        assert_eq!(without_html["body_text"], "Numbers attached.");
+        assert!(without_html.get("body_text_truncated").is_none());
+        assert_eq!(
+            projection(json!({}))
+                .expect("projection")
+                .apply(&email)
+                .expect("apply"),
+            serde_json::to_value(&email).expect("serialize")
+        );
+
+        assert!(projection(json!({"fields": ["subjcet"]})).is_err());
+        assert!(projection(json!({"fields": ["subject", "-body_html"]})).is_err());
+        assert!(projection(json!({"max_body_chars": 0})).is_err());
+    }
+
+    #[test]
+    fn selectable_fields_match_the_serialized_email() {
+        let serialized = serde_json::to_value(email()).expect("serialize");
+        let mut serialized: Vec<&str> = serialized
+            .as_object()
+            .expect("object")
+            .keys()
+            .map(String::as_str)
+            .collect();
+        let mut selectable = EMAIL_FIELDS.to_vec();
+        serialized.sort_unstable();
+        selectable.sort_unstable();
+        assert_eq!(selectable, serialized);
+    }
+}
@@ -0,0 +1,288 @@
+//! CSV and TSV output for `--format csv|tsv`.
+//!
+//! Every table starts with a header row. CSV fields are quoted per RFC 4180
+//! when they contain the delimiter, a quote or a line break; TSV has no
+//! quoting, so tabs and line breaks inside fields become spaces. Rows end
+//! with `\n` so the output pipes cleanly into awk and `cut`.
+//!
+//! Fields come from mail anyone can send, and the output is meant for
+//! spreadsheets, so a field starting with `=`, `+`, `-`, `@`, a tab or a
+//! carriage return is prefixed with `'` to keep it from running as a
+//! formula.
+
+use std::borrow::Cow;
+
+use crate::db::models::Contact;
+use crate::db::{DatabaseStats, SyncUsage};
+use crate::output::SearchResultItem;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delimiter {
+    Comma,
+    Tab,
+}
+
+pub fn format_search_results(results: &[SearchResultItem], delimiter: Delimiter) -> String {
+    let mut out = String::new();
+    push_row(
+        &mut out,
+        delimiter,
+        &[
+            "id",
+            "received_at",
+            "from_name",
+            "from_address",
+            "subject",
+            "folder",
+            "account_id",
+            "score",
+            "excerpt",
+        ],
+    );
+    for result in results {
+        let email = &result.email;
+        let score = result
+            .score
+            .map(|score| format!("{score:.4}"))
+            .unwrap_or_default();
+        let excerpt = result
+            .snippet
+            .as_ref()
+            .map(|snippet| snippet.text.as_str())
+            .or(result.preview.as_ref().map(|preview| preview.text.as_str()))
+            .unwrap_or_default();
+        push_row(
+            &mut out,
+            delimiter,
+            &[
+                &email.id,
+                &email.received_at,
+                email.from_name.as_deref().unwrap_or_default(),
+                email.from_address.as_deref().unwrap_or_default(),
+                email.subject.as_deref().unwrap_or_default(),
+                email.folder.as_deref().unwrap_or_default(),
+                email.account_id.as_deref().unwrap_or_default(),
+                &score,
+                excerpt,
+            ],
+        );
+    }
+    out
+}
+
+pub fn format_contacts(contacts: &[Contact], delimiter: Delimiter) -> String {
+    let mut out = String::new();
+    push_row(
+        &mut out,
+        delimiter,
+        &[
+            "email_address",
+            "display_name",
+            "company",
+            "phone",
+            "message_count",
+            "first_seen",
+            "last_seen",
+        ],
+    );
+    for contact in contacts {
+        push_row(
+            &mut out,
+            delimiter,
+            &[
+                &contact.email_address,
+                contact.display_name.as_deref().unwrap_or_default(),
+                contact.company.as_deref().unwrap_or_default(),
+                contact.phone.as_deref().unwrap_or_default(),
+                &contact.message_count.to_string(),
+                contact.first_seen.as_deref().unwrap_or_default(),
+                contact.last_seen.as_deref().unwrap_or_default(),
+            ],
+        );
+    }
+    out
+}
+
+/// One `metric,account_id,value` row per figure; `account_id` is only set
+/// on the per-account `emails` rows.
+pub fn format_stats(
+    stats: &DatabaseStats,
+    index_doc_count: u64,
+    index_size_bytes: u64,
+    delimiter: Delimiter,
+) -> String {
+    let mut out = String::new();
+    push_row(&mut out, delimiter, &["metric", "account_id", "value"]);
+    let totals = [
+        ("total_accounts", stats.total_accounts.to_string()),
+        ("total_emails", stats.total_emails.to_string()),
+        ("total_contacts", stats.total_contacts.to_string()),
+        ("index_doc_count", index_doc_count.to_string()),
+        ("index_size_bytes", index_size_bytes.to_string()),
+    ];
+    for (metric, value) in &totals {
+        push_row(&mut out, delimiter, &[metric, "", value]);
+    }
+    for account in &stats.emails_by_account {
+        push_row(
+            &mut out,
+            delimiter,
+            &["emails", &account.account_id, &account.count.to_string()],
+        );
+    }
+    for tag in &stats.emails_by_tag {
+        push_row(
+            &mut out,
+            delimiter,
+            &[&format!("tag:{}", tag.tag), "", &tag.count.to_string()],
+        );
+    }
+    out
+}
+
+/// One `day,account_id,requests,bytes` row per account and day.
+pub fn format_sync_usage(usage: &[SyncUsage], delimiter: Delimiter) -> String {
+    let mut out = String::new();
+    push_row(
+        &mut out,
+        delimiter,
+        &["day", "account_id", "requests", "bytes"],
+    );
+    for row in usage {
+        push_row(
+            &mut out,
+            delimiter,
+            &[
+                &row.day,
+                &row.account_id,
+                &row.requests.to_string(),
+                &row.bytes.to_string(),
+            ],
+        );
+    }
+    out
+}
+
+fn push_row(out: &mut String, delimiter: Delimiter, fields: &[&str]) {
+    let separator = match delimiter {
+        Delimiter::Comma => ",",
+        Delimiter::Tab => "\t",
+    };
+    let row = fields
+        .iter()
+        .map(|value| field(value, delimiter))
+        .collect::<Vec<_>>()
+        .join(separator);
+    out.push_str(&row);
+    out.push('\n');
+}
+
+fn field(value: &str, delimiter: Delimiter) -> String {
+    let value = &defuse_formula(value);
+    match delimiter {
+        Delimiter::Comma if value.contains([',', '"', '\n', '\r']) => {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        }
+        Delimiter::Comma => value.to_string(),
+        Delimiter::Tab => value.replace(['\t', '\n', '\r'], " "),
+    }
+}
+
+fn defuse_formula(value: &str) -> Cow<'_, str> {
+    if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        Cow::Owned(format!("'{value}"))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_contacts, format_search_results, format_stats, Delimiter};
+    use crate::db::models::{Contact, Email};
+    use crate::db::{AccountEmailCount, DatabaseStats, TagCount};
+    use crate::output::SearchResultItem;
+
+    #[test]
+    fn quotes_csv_fields_and_flattens_tsv_fields() {
+        let contacts = [Contact {
+            email_address: "alice@example.com".to_string(),
+            display_name: Some("Smith, \"Al\"".to_string()),
+            company: Some("Acme\tLtd".to_string()),
+            phone: Some("+1 555 0100".to_string()),
+            attio_person_id: None,
+            attio_company_id: None,
+            message_count: 3,
+            first_seen: None,
+            last_seen: Some("2026-02-01T10:00:00Z".to_string()),
+        }];
+
+        assert_eq!(
+            format_contacts(&contacts, Delimiter::Comma),
+            "email_address,display_name,company,phone,message_count,first_seen,last_seen\n\
+             alice@example.com,\"Smith, \"\"Al\"\"\",Acme\tLtd,'+1 555 0100,3,,2026-02-01T10:00:00Z\n"
+        );
+        assert_eq!(
+            format_contacts(&contacts, Delimiter::Tab),
+            "email_address\tdisplay_name\tcompany\tphone\tmessage_count\tfirst_seen\tlast_seen\n\
+             alice@example.com\tSmith, \"Al\"\tAcme Ltd\t'+1 555 0100\t3\t\t2026-02-01T10:00:00Z\n"
+        );
+
+        let stats = DatabaseStats {
+            total_accounts: 1,
+            total_emails: 2,
+            total_contacts: 3,
+            emails_by_account: vec![AccountEmailCount {
+                account_id: "acc".to_string(),
+                count: 2,
+            }],
+            emails_by_folder: vec![],
+            last_sync_by_account: vec![],
+            emails_by_tag: vec![TagCount {
+                tag: "invoice".to_string(),
+                count: 1,
+            }],
+        };
+        assert_eq!(
+            format_stats(&stats, 2, 4096, Delimiter::Comma),
+            "metric,account_id,value\ntotal_accounts,,1\ntotal_emails,,2\ntotal_contacts,,3\n\
+             index_doc_count,,2\nindex_size_bytes,,4096\nemails,acc,2\ntag:invoice,,1\n"
+        );
+    }
+
+    #[test]
+    fn prefixes_fields_that_would_run_as_formulas() {
+        let results = [SearchResultItem {
+            email: Email {
+                from_name: Some("@SUM(A1:A9)".to_string()),
+                from_address: Some("-x@example.com".to_string()),
+                subject: Some("=HYPERLINK(\"http://evil.example\",\"Invoice\")".to_string()),
+                folder: Some("\tinbox".to_string()),
+                account_id: Some("a=b".to_string()),
+                ..Email::fixture("m1")
+            },
+            score: None,
+            snippet: None,
+            preview: None,
+        }];
+
+        assert_eq!(
+            format_search_results(&results, Delimiter::Comma)
+                .lines()
+                .nth(1),
+            Some(
+                "m1,2026-01-01T00:00:00Z,'@SUM(A1:A9),'-x@example.com,\
+                 \"'=HYPERLINK(\"\"http://evil.example\"\",\"\"Invoice\"\")\",'\tinbox,a=b,,"
+            )
+        );
+        assert_eq!(
+            format_search_results(&results, Delimiter::Tab)
+                .lines()
+                .nth(1),
+            Some(
+                "m1\t2026-01-01T00:00:00Z\t'@SUM(A1:A9)\t'-x@example.com\t\
+                 '=HYPERLINK(\"http://evil.example\",\"Invoice\")\t' inbox\ta=b\t\t"
+            )
+        );
+    }
+}
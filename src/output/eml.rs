@@ -0,0 +1,316 @@
+//! RFC 5322 messages for `ess export --format eml` and `--format mbox`.
+//!
+//! Messages are rebuilt from the stored headers and bodies: a plain-text
+//! and/or HTML part in quoted-printable UTF-8, with non-ASCII names and
+//! subjects as RFC 2047 encoded words. Attachment contents are not stored,
+//! so they are not part of the rebuilt message. When raw storage kept the
+//! message's original source, that is written instead. The mbox flavour is
+//! mboxrd: a `From ` separator line per message and `>`-quoting of body
+//! lines that look like one.
+
+use std::io::{self, Write};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::DateTime;
+
+use crate::db::models::Email;
+
+/// Quoted-printable output never contains `=_`, so this cannot collide
+/// with body content.
+const ALTERNATIVE_BOUNDARY: &str = "=_ess_alternative";
+
+/// Quoted-printable lines, soft breaks included, stay within this length.
+const QP_LINE: usize = 76;
+
+/// Writes the message with CRLF line endings, as `.eml` files carry them.
+pub fn write_eml<W: Write>(email: &Email, out: &mut W) -> io::Result<()> {
+    out.write_all(format_message(email).replace('\n', "\r\n").as_bytes())
+}
+
+/// Writes a `From ` separator line, the message with `From `-like lines
+/// quoted, and the blank line that ends an mbox entry.
+pub fn write_mbox_entry<W: Write>(email: &Email, out: &mut W) -> io::Result<()> {
+    write_mbox_message(email, format_message(email).as_bytes(), out)
+}
+
+/// Like `write_mbox_entry`, with the message's stored source in place of a
+/// rebuilt one. CRLF line endings become LF, as in the rest of the file.
+pub fn write_raw_mbox_entry<W: Write>(email: &Email, raw: &[u8], out: &mut W) -> io::Result<()> {
+    write_mbox_message(email, raw, out)
+}
+
+fn write_mbox_message<W: Write>(email: &Email, message: &[u8], out: &mut W) -> io::Result<()> {
+    let sender = email
+        .from_address
+        .as_deref()
+        .filter(|address| !address.is_empty() && !address.contains(char::is_whitespace))
+        .unwrap_or("MAILER-DAEMON");
+    let date = DateTime::parse_from_rfc3339(&email.received_at)
+        .map(|date| date.to_utc().format("%a %b %e %H:%M:%S %Y").to_string())
+        .unwrap_or_else(|_| "Thu Jan  1 00:00:00 1970".to_string());
+    writeln!(out, "From {sender} {date}")?;
+    let message = message.strip_suffix(b"\n").unwrap_or(message);
+    for line in message.split(|&byte| byte == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let unquoted = &line[line.iter().take_while(|&&byte| byte == b'>').count()..];
+        if unquoted.starts_with(b"From ") {
+            out.write_all(b">")?;
+        }
+        out.write_all(line)?;
+        out.write_all(b"\n")?;
+    }
+    writeln!(out)
+}
+
+/// The message with LF line endings.
+pub fn format_message(email: &Email) -> String {
+    let mut headers = Vec::new();
+    let from = match email.from_address.as_deref() {
+        Some(address) => mailbox(email.from_name.as_deref(), address),
+        None => "undisclosed-sender:;".to_string(),
+    };
+    headers.push(("From", from));
+    for (name, addresses) in [
+        ("To", &email.to_addresses),
+        ("Cc", &email.cc_addresses),
+        ("Bcc", &email.bcc_addresses),
+    ] {
+        if !addresses.is_empty() {
+            headers.push((name, addresses.join(",\n ")));
+        }
+    }
+    if let Some(subject) = email.subject.as_deref() {
+        headers.push(("Subject", encode_word(subject)));
+    }
+    let date = email.sent_at.as_deref().unwrap_or(&email.received_at);
+    if let Ok(date) = DateTime::parse_from_rfc3339(date) {
+        headers.push(("Date", date.to_rfc2822()));
+    }
+    if let Some(message_id) = email
+        .internet_message_id
+        .as_deref()
+        .map(|value| value.trim().trim_start_matches('<').trim_end_matches('>'))
+        .filter(|value| !value.is_empty())
+    {
+        headers.push(("Message-ID", format!("<{message_id}>")));
+    }
+    if let Some(in_reply_to) = email.in_reply_to.as_deref() {
+        headers.push(("In-Reply-To", in_reply_to.to_string()));
+    }
+    if !email.references.is_empty() {
+        headers.push(("References", email.references.join("\n ")));
+    }
+    if email.is_read == Some(true) {
+        headers.push(("Status", "RO".to_string()));
+    }
+    headers.push(("X-ESS-Id", email.id.clone()));
+    if let Some(account) = email.account_id.as_deref() {
+        headers.push(("X-ESS-Account", account.to_string()));
+    }
+    if let Some(folder) = email.folder.as_deref() {
+        headers.push(("X-ESS-Folder", encode_word(folder)));
+    }
+    headers.push(("MIME-Version", "1.0".to_string()));
+
+    let text = email.body_text.as_deref().or(email.body_preview.as_deref());
+    let body = match (text, email.body_html.as_deref()) {
+        (Some(text), Some(html)) => {
+            headers.push((
+                "Content-Type",
+                format!("multipart/alternative; boundary=\"{ALTERNATIVE_BOUNDARY}\""),
+            ));
+            format!(
+                "--{ALTERNATIVE_BOUNDARY}\n{}\n--{ALTERNATIVE_BOUNDARY}\n{}\n--{ALTERNATIVE_BOUNDARY}--\n",
+                part("text/plain", text),
+                part("text/html", html),
+            )
+        }
+        (None, Some(html)) => {
+            headers.push(("Content-Type", "text/html; charset=utf-8".to_string()));
+            headers.push(("Content-Transfer-Encoding", "quoted-printable".to_string()));
+            quoted_printable(html)
+        }
+        (text, None) => {
+            headers.push(("Content-Type", "text/plain; charset=utf-8".to_string()));
+            headers.push(("Content-Transfer-Encoding", "quoted-printable".to_string()));
+            quoted_printable(text.unwrap_or_default())
+        }
+    };
+
+    let mut message = String::new();
+    for (name, value) in headers {
+        message.push_str(&format!("{name}: {}\n", strip_line_breaks(&value)));
+    }
+    message.push('\n');
+    message.push_str(&body);
+    if !message.ends_with('\n') {
+        message.push('\n');
+    }
+    message
+}
+
+fn part(content_type: &str, content: &str) -> String {
+    format!(
+        "Content-Type: {content_type}; charset=utf-8\nContent-Transfer-Encoding: quoted-printable\n\n{}",
+        quoted_printable(content)
+    )
+}
+
+/// `Name <address>`, with the name quoted or encoded as needed.
+fn mailbox(name: Option<&str>, address: &str) -> String {
+    match name.map(str::trim).filter(|name| !name.is_empty()) {
+        Some(name) if name.is_ascii() => {
+            let escaped = name.replace('\\', "\\\\").replace('"', "\\\"");
+            format!("\"{escaped}\" <{address}>")
+        }
+        Some(name) => format!("{} <{address}>", encode_word(name)),
+        None => address.to_string(),
+    }
+}
+
+/// Header text as is when it is ASCII, otherwise as base64 encoded words
+/// (at most 45 bytes of UTF-8 each, keeping them under 76 characters),
+/// folded onto continuation lines.
+fn encode_word(value: &str) -> String {
+    if value.is_ascii() {
+        return value.to_string();
+    }
+    let mut words = Vec::new();
+    let mut chunk = String::new();
+    for ch in value.chars() {
+        if chunk.len() + ch.len_utf8() > 45 {
+            words.push(format!("=?UTF-8?B?{}?=", STANDARD.encode(&chunk)));
+            chunk.clear();
+        }
+        chunk.push(ch);
+    }
+    if !chunk.is_empty() {
+        words.push(format!("=?UTF-8?B?{}?=", STANDARD.encode(&chunk)));
+    }
+    words.join("\n ")
+}
+
+/// Collapses line breaks inside a header value, keeping the deliberate
+/// `\n ` folds.
+fn strip_line_breaks(value: &str) -> String {
+    value
+        .replace("\n ", "\u{0}")
+        .replace(['\r', '\n'], " ")
+        .replace('\u{0}', "\n ")
+}
+
+/// Quoted-printable (RFC 2045) with LF hard line breaks.
+fn quoted_printable(text: &str) -> String {
+    let mut out = String::new();
+    let normalized = text.replace("\r\n", "\n");
+    for line in normalized.split('\n') {
+        let mut current = String::new();
+        let bytes = line.as_bytes();
+        for (position, &byte) in bytes.iter().enumerate() {
+            let last = position + 1 == bytes.len();
+            let encoded = match byte {
+                b' ' | b'\t' if last => format!("={byte:02X}"),
+                b'=' => "=3D".to_string(),
+                b' ' | b'\t' | 33..=126 => (byte as char).to_string(),
+                _ => format!("={byte:02X}"),
+            };
+            if current.len() + encoded.len() > QP_LINE - 1 {
+                out.push_str(&current);
+                out.push_str("=\n");
+                current.clear();
+            }
+            current.push_str(&encoded);
+        }
+        out.push_str(&current);
+        out.push('\n');
+    }
+    // `split` yields one line past a trailing newline.
+    if normalized.ends_with('\n') {
+        out.pop();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_message, quoted_printable, write_mbox_entry, write_raw_mbox_entry};
+    use crate::db::models::Email;
+
+    fn email() -> Email {
+        Email {
+            internet_message_id: Some("abc@example.com".to_string()),
+            in_reply_to: Some("<parent@example.com>".to_string()),
+            references: vec![
+                "<root@example.com>".to_string(),
+                "<parent@example.com>".to_string(),
+            ],
+            account_id: Some("acc".to_string()),
+            subject: Some("Budget für Q3".to_string()),
+            from_address: Some("alice@example.com".to_string()),
+            from_name: Some("Alice \"Al\" Smith".to_string()),
+            to_addresses: vec!["me@example.com".to_string(), "bob@example.com".to_string()],
+            body_text: Some("Numbers attached.\nFrom now on, a=b.".to_string()),
+            received_at: "2026-02-01T10:00:00Z".to_string(),
+            is_read: Some(true),
+            folder: Some("inbox".to_string()),
+            ..Email::fixture("e1")
+        }
+    }
+
+    #[test]
+    fn rebuilds_headers_and_quoted_printable_body() {
+        let message = format_message(&email());
+        assert_eq!(
+            message,
+            "From: \"Alice \\\"Al\\\" Smith\" <alice@example.com>\n\
+             To: me@example.com,\n bob@example.com\n\
+             Subject: =?UTF-8?B?QnVkZ2V0IGbDvHIgUTM=?=\n\
+             Date: Sun, 1 Feb 2026 10:00:00 +0000\n\
+             Message-ID: <abc@example.com>\n\
+             In-Reply-To: <parent@example.com>\n\
+             References: <root@example.com>\n <parent@example.com>\n\
+             Status: RO\n\
+             X-ESS-Id: e1\n\
+             X-ESS-Account: acc\n\
+             X-ESS-Folder: inbox\n\
+             MIME-Version: 1.0\n\
+             Content-Type: text/plain; charset=utf-8\n\
+             Content-Transfer-Encoding: quoted-printable\n\
+             \n\
+             Numbers attached.\n\
+             From now on, a=3Db.\n"
+        );
+
+        let mut html = email();
+        html.body_html = Some("<p>Hi</p>".to_string());
+        let message = format_message(&html);
+        assert!(message.contains("boundary=\"=_ess_alternative\""));
+        assert!(message.contains("Content-Type: text/html; charset=utf-8\n"));
+        assert!(message.ends_with("--=_ess_alternative--\n"));
+
+        let long = quoted_printable(&format!("{} é ", "x".repeat(80)));
+        assert_eq!(
+            long,
+            format!("{}=\n{} =C3=A9=20\n", "x".repeat(75), "x".repeat(5))
+        );
+    }
+
+    #[test]
+    fn mbox_entries_quote_from_lines() {
+        let mut out = Vec::new();
+        write_mbox_entry(&email(), &mut out).expect("write entry");
+        let entry = String::from_utf8(out).expect("utf-8");
+        assert!(entry.starts_with("From alice@example.com Sun Feb  1 10:00:00 2026\nFrom: "));
+        assert!(entry.contains("\n>From now on, a=3Db.\n"));
+        assert!(entry.ends_with("a=3Db.\n\n"));
+
+        let mut out = Vec::new();
+        let raw = b"Subject: Hi\r\n\r\nFrom here on\r\n\xff\r\n";
+        write_raw_mbox_entry(&email(), raw, &mut out).expect("write raw entry");
+        assert_eq!(
+            out,
+            b"From alice@example.com Sun Feb  1 10:00:00 2026\nSubject: Hi\n\n>From here on\n\xff\n\n"
+        );
+    }
+}
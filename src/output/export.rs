@@ -0,0 +1,681 @@
+//! Columnar, tag and message exports for `ess export`.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::DateTime;
+use serde::Serialize;
+
+use crate::connectors::json_archive::archive_payload;
+use crate::db::models::Email;
+use crate::db::{Database, EmailKeyset, EmailSearchFilters, StatsSnapshot};
+use crate::indexer::EmailIndex;
+use crate::output::notmuch::TagDump;
+use crate::output::parquet::{ColumnKind, ColumnSpec, ColumnValues, ParquetWriter};
+use crate::output::{eml, json};
+use crate::raw::RawStore;
+use crate::search::{self, EmailFilters};
+
+/// Rows per Parquet row group, and per database read.
+const ROW_GROUP_ROWS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ExportColumn {
+    pub name: &'static str,
+    pub kind: ColumnKind,
+    /// Exported when `--columns` is not given.
+    pub default: bool,
+}
+
+const fn column(name: &'static str, kind: ColumnKind, default: bool) -> ExportColumn {
+    ExportColumn {
+        name,
+        kind,
+        default,
+    }
+}
+
+/// Email columns. Address and category lists are joined with `", "`;
+/// bodies and metadata are left out unless asked for.
+pub const EMAIL_COLUMNS: &[ExportColumn] = &[
+    column("id", ColumnKind::Utf8, true),
+    column("internet_message_id", ColumnKind::Utf8, true),
+    column("conversation_id", ColumnKind::Utf8, true),
+    column("account_id", ColumnKind::Utf8, true),
+    column("subject", ColumnKind::Utf8, true),
+    column("from_address", ColumnKind::Utf8, true),
+    column("from_name", ColumnKind::Utf8, true),
+    column("from_domain", ColumnKind::Utf8, true),
+    column("to_addresses", ColumnKind::Utf8, true),
+    column("cc_addresses", ColumnKind::Utf8, true),
+    column("bcc_addresses", ColumnKind::Utf8, true),
+    column("received_at", ColumnKind::TimestampMillis, true),
+    column("sent_at", ColumnKind::TimestampMillis, true),
+    column("importance", ColumnKind::Utf8, true),
+    column("is_read", ColumnKind::Boolean, true),
+    column("has_attachments", ColumnKind::Boolean, true),
+    column("folder", ColumnKind::Utf8, true),
+    column("categories", ColumnKind::Utf8, true),
+    column("flag_status", ColumnKind::Utf8, true),
+    column("web_link", ColumnKind::Utf8, true),
+    column("body_preview", ColumnKind::Utf8, false),
+    column("body_text", ColumnKind::Utf8, false),
+    column("body_html", ColumnKind::Utf8, false),
+    column("metadata", ColumnKind::Utf8, false),
+];
+
+/// `ess stats` snapshot columns.
+pub const STATS_COLUMNS: &[ExportColumn] = &[
+    column("captured_at", ColumnKind::TimestampMillis, true),
+    column("total_accounts", ColumnKind::Int64, true),
+    column("total_emails", ColumnKind::Int64, true),
+    column("total_contacts", ColumnKind::Int64, true),
+    column("index_doc_count", ColumnKind::Int64, true),
+    column("index_size_bytes", ColumnKind::Int64, true),
+];
+
+/// Which emails an export covers.
+pub enum EmailSource<'a> {
+    /// Every stored email matching the filters, newest first.
+    Stored(EmailSearchFilters),
+    /// Every hit of a search query, best match first. The filters' limit
+    /// and offset are ignored.
+    Search {
+        index: &'a EmailIndex,
+        query: String,
+        filters: EmailFilters,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportReport {
+    pub path: PathBuf,
+    pub rows: usize,
+    /// Emails the format cannot represent, such as ones without a
+    /// Message-ID in a notmuch dump.
+    pub skipped: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub columns: Vec<String>,
+    pub size_bytes: u64,
+    pub duration_ms: u64,
+}
+
+/// The catalog's default columns for an empty selection, otherwise the
+/// named ones in the order given.
+pub fn select_columns(
+    catalog: &'static [ExportColumn],
+    names: &[String],
+) -> Result<Vec<&'static ExportColumn>> {
+    if names.is_empty() {
+        return Ok(catalog.iter().filter(|column| column.default).collect());
+    }
+    names
+        .iter()
+        .map(|name| {
+            catalog
+                .iter()
+                .find(|column| column.name == name.trim())
+                .ok_or_else(|| {
+                    let known = catalog.iter().map(|column| column.name).collect::<Vec<_>>();
+                    anyhow!(
+                        "unknown column '{}'; expected one of {}",
+                        name.trim(),
+                        known.join(", ")
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Writes every email of `source`, one row group per batch.
+pub fn export_emails_parquet(
+    db: &Database,
+    source: &EmailSource,
+    columns: &[&ExportColumn],
+    path: &Path,
+) -> Result<ExportReport> {
+    write_parquet(path, columns, |writer| {
+        let mut rows = 0;
+        for_each_email_batch(db, source, |batch| {
+            let values = columns
+                .iter()
+                .map(|column| email_values(column.name, batch))
+                .collect::<Vec<_>>();
+            writer.write_row_group(&values)?;
+            rows += batch.len();
+            Ok(())
+        })?;
+        Ok(rows)
+    })
+}
+
+/// Writes a `notmuch dump` of the folders, flags, categories and local
+/// stars of every email of `source`, keyed by Message-ID.
+pub fn export_emails_notmuch(
+    db: &Database,
+    source: &EmailSource,
+    path: &Path,
+) -> Result<ExportReport> {
+    let started = Instant::now();
+    let starred = db.starred_email_ids()?;
+    let mut dump = TagDump::default();
+    let mut skipped = 0;
+    for_each_email_batch(db, source, |batch| {
+        for email in batch {
+            if !dump.add(email, starred.contains(&email.id)) {
+                skipped += 1;
+            }
+        }
+        Ok(())
+    })?;
+    write_atomically(path, |mut out| {
+        dump.write(&mut out)?;
+        out.flush()?;
+        Ok(())
+    })?;
+    report(path, dump.len(), skipped, Vec::new(), started)
+}
+
+/// Writes every email of `source` into one mboxrd file, using the stored
+/// raw source of each message that has one.
+pub fn export_emails_mbox(
+    db: &Database,
+    source: &EmailSource,
+    raw: &RawStore,
+    path: &Path,
+) -> Result<ExportReport> {
+    let started = Instant::now();
+    let rows = write_atomically(path, |mut out| {
+        let mut rows = 0;
+        for_each_email_batch(db, source, |batch| {
+            for email in batch {
+                match raw.get(&email.id)? {
+                    Some(message) => eml::write_raw_mbox_entry(email, &message, &mut out)?,
+                    None => eml::write_mbox_entry(email, &mut out)?,
+                }
+            }
+            rows += batch.len();
+            Ok(())
+        })?;
+        out.flush()?;
+        Ok(rows)
+    })?;
+    report(path, rows, 0, Vec::new(), started)
+}
+
+/// Writes every email of `source` to `out` as NDJSON, one `Email` object
+/// per line, a batch at a time; returns the number written.
+pub fn write_emails_ndjson<W: Write>(
+    db: &Database,
+    source: &EmailSource,
+    out: &mut W,
+) -> Result<usize> {
+    let mut rows = 0;
+    for_each_email_batch(db, source, |batch| {
+        json::write_ndjson(out, batch)?;
+        rows += batch.len();
+        Ok(())
+    })?;
+    out.flush()?;
+    Ok(rows)
+}
+
+/// Writes every email of `source` into one NDJSON file.
+pub fn export_emails_ndjson(
+    db: &Database,
+    source: &EmailSource,
+    path: &Path,
+) -> Result<ExportReport> {
+    let started = Instant::now();
+    let rows = write_atomically(path, |mut out| write_emails_ndjson(db, source, &mut out))?;
+    report(path, rows, 0, Vec::new(), started)
+}
+
+/// Writes every email of `source` as `email-NNNN.eml` into `directory`,
+/// which must be new or empty. Messages with a stored raw source are
+/// written byte for byte.
+pub fn export_emails_eml(
+    db: &Database,
+    source: &EmailSource,
+    raw: &RawStore,
+    directory: &Path,
+) -> Result<ExportReport> {
+    export_email_files(db, source, directory, "eml", |email, out| {
+        match raw.get(&email.id)? {
+            Some(message) => out.write_all(&message)?,
+            None => eml::write_eml(email, out)?,
+        }
+        Ok(())
+    })
+}
+
+/// Writes every email of `source` as `email-NNNN.json` into `directory`,
+/// in the archive format `ess import` reads.
+pub fn export_emails_json(
+    db: &Database,
+    source: &EmailSource,
+    directory: &Path,
+) -> Result<ExportReport> {
+    export_email_files(db, source, directory, "json", |email, out| {
+        serde_json::to_writer_pretty(out, &archive_payload(email))?;
+        Ok(())
+    })
+}
+
+fn export_email_files(
+    db: &Database,
+    source: &EmailSource,
+    directory: &Path,
+    extension: &str,
+    write: impl Fn(&Email, &mut BufWriter<File>) -> Result<()>,
+) -> Result<ExportReport> {
+    let started = Instant::now();
+    std::fs::create_dir_all(directory)
+        .with_context(|| format!("create export directory {}", directory.display()))?;
+    if std::fs::read_dir(directory)?.next().is_some() {
+        return Err(anyhow!(
+            "export directory {} is not empty",
+            directory.display()
+        ));
+    }
+    let mut rows = 0;
+    let mut size_bytes = 0;
+    for_each_email_batch(db, source, |batch| {
+        for email in batch {
+            rows += 1;
+            let path = directory.join(format!("email-{rows:04}.{extension}"));
+            write_atomically(&path, |mut out| {
+                write(email, &mut out)?;
+                out.flush()?;
+                Ok(())
+            })?;
+            size_bytes += std::fs::metadata(&path)?.len();
+        }
+        Ok(())
+    })?;
+    Ok(ExportReport {
+        path: directory.to_path_buf(),
+        rows,
+        skipped: 0,
+        columns: Vec::new(),
+        size_bytes,
+        duration_ms: u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX),
+    })
+}
+
+/// Reads every email of `source`, `ROW_GROUP_ROWS` at a time.
+fn for_each_email_batch(
+    db: &Database,
+    source: &EmailSource,
+    mut visit: impl FnMut(&[Email]) -> Result<()>,
+) -> Result<()> {
+    let filters = match source {
+        EmailSource::Stored(filters) => filters,
+        EmailSource::Search {
+            index,
+            query,
+            filters,
+        } => {
+            let filters = EmailFilters {
+                limit: ROW_GROUP_ROWS,
+                offset: 0,
+                ..filters.clone()
+            };
+            let mut cursor = None;
+            loop {
+                let page = search::search_emails_page(index, db, query, &filters, cursor)?;
+                let batch = page
+                    .results
+                    .into_iter()
+                    .map(|result| result.email)
+                    .collect::<Vec<_>>();
+                visit(&batch)?;
+                match page.next_cursor {
+                    Some(next) => cursor = Some(next),
+                    None => return Ok(()),
+                }
+            }
+        }
+    };
+    // The filters' limit, offset and keyset are ignored; rows come newest
+    // first.
+    let mut after = None;
+    loop {
+        let batch = db.search_emails(EmailSearchFilters {
+            limit: ROW_GROUP_ROWS,
+            offset: 0,
+            after: after.take(),
+            ..filters.clone()
+        })?;
+        let Some(last) = batch.last() else {
+            return Ok(());
+        };
+        after = Some(EmailKeyset::of(last));
+        visit(&batch)?;
+        if batch.len() < ROW_GROUP_ROWS {
+            return Ok(());
+        }
+    }
+}
+
+/// Writes every recorded `ess stats` snapshot, oldest first.
+pub fn export_stats_parquet(
+    db: &Database,
+    columns: &[&ExportColumn],
+    path: &Path,
+) -> Result<ExportReport> {
+    let snapshots = db.stats_snapshots_since("")?;
+    write_parquet(path, columns, |writer| {
+        for chunk in snapshots.chunks(ROW_GROUP_ROWS) {
+            let values = columns
+                .iter()
+                .map(|column| stats_values(column.name, chunk))
+                .collect::<Vec<_>>();
+            writer.write_row_group(&values)?;
+        }
+        Ok(snapshots.len())
+    })
+}
+
+fn write_parquet(
+    path: &Path,
+    columns: &[&ExportColumn],
+    write_rows: impl FnOnce(&mut ParquetWriter<BufWriter<File>>) -> Result<usize>,
+) -> Result<ExportReport> {
+    let started = Instant::now();
+    let specs = columns
+        .iter()
+        .map(|column| ColumnSpec {
+            name: column.name.to_string(),
+            kind: column.kind,
+            // Timestamps that fail to parse are written as null.
+            nullable: column.name != "id",
+        })
+        .collect();
+    let rows = write_atomically(path, |out| {
+        let mut writer = ParquetWriter::new(out, specs)?;
+        let rows = write_rows(&mut writer)?;
+        writer.finish()?;
+        Ok(rows)
+    })?;
+    let names = columns
+        .iter()
+        .map(|column| column.name.to_string())
+        .collect();
+    report(path, rows, 0, names, started)
+}
+
+/// Writes to a `.partial` file beside `path` and renames it into place, so
+/// a failed export never leaves a truncated file behind.
+fn write_atomically<T>(path: &Path, write: impl FnOnce(BufWriter<File>) -> Result<T>) -> Result<T> {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+
+    let result = (|| {
+        let file = File::create(&partial)
+            .with_context(|| format!("create export file {}", partial.display()))?;
+        let written = write(BufWriter::new(file))?;
+        std::fs::rename(&partial, path)
+            .with_context(|| format!("move export into place at {}", path.display()))?;
+        Ok(written)
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&partial);
+    }
+    result
+}
+
+fn report(
+    path: &Path,
+    rows: usize,
+    skipped: usize,
+    columns: Vec<String>,
+    started: Instant,
+) -> Result<ExportReport> {
+    Ok(ExportReport {
+        path: path.to_path_buf(),
+        rows,
+        skipped,
+        columns,
+        size_bytes: std::fs::metadata(path)?.len(),
+        duration_ms: u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX),
+    })
+}
+
+fn email_values(name: &str, emails: &[Email]) -> ColumnValues {
+    let text = |value: fn(&Email) -> Option<String>| {
+        ColumnValues::Utf8(emails.iter().map(value).collect())
+    };
+    let joined = |values: &[String]| (!values.is_empty()).then(|| values.join(", "));
+    match name {
+        "id" => text(|email| Some(email.id.clone())),
+        "internet_message_id" => text(|email| email.internet_message_id.clone()),
+        "conversation_id" => text(|email| email.conversation_id.clone()),
+        "account_id" => text(|email| email.account_id.clone()),
+        "subject" => text(|email| email.subject.clone()),
+        "from_address" => text(|email| email.from_address.clone()),
+        "from_name" => text(|email| email.from_name.clone()),
+        "from_domain" => text(Email::from_domain),
+        "to_addresses" => ColumnValues::Utf8(
+            emails
+                .iter()
+                .map(|email| joined(&email.to_addresses))
+                .collect(),
+        ),
+        "cc_addresses" => ColumnValues::Utf8(
+            emails
+                .iter()
+                .map(|email| joined(&email.cc_addresses))
+                .collect(),
+        ),
+        "bcc_addresses" => ColumnValues::Utf8(
+            emails
+                .iter()
+                .map(|email| joined(&email.bcc_addresses))
+                .collect(),
+        ),
+        "received_at" => ColumnValues::Int64(
+            emails
+                .iter()
+                .map(|email| timestamp_millis(&email.received_at))
+                .collect(),
+        ),
+        "sent_at" => ColumnValues::Int64(
+            emails
+                .iter()
+                .map(|email| email.sent_at.as_deref().and_then(timestamp_millis))
+                .collect(),
+        ),
+        "importance" => text(|email| email.importance.clone()),
+        "is_read" => ColumnValues::Boolean(emails.iter().map(|email| email.is_read).collect()),
+        "has_attachments" => {
+            ColumnValues::Boolean(emails.iter().map(|email| email.has_attachments).collect())
+        }
+        "folder" => text(|email| email.folder.clone()),
+        "categories" => ColumnValues::Utf8(
+            emails
+                .iter()
+                .map(|email| joined(&email.categories))
+                .collect(),
+        ),
+        "flag_status" => text(|email| email.flag_status.clone()),
+        "web_link" => text(|email| email.web_link.clone()),
+        "body_preview" => text(|email| email.body_preview.clone()),
+        "body_text" => text(|email| email.body_text.clone()),
+        "body_html" => text(|email| email.body_html.clone()),
+        "metadata" => text(|email| email.metadata.as_ref().map(|value| value.to_string())),
+        other => unreachable!("column '{other}' is not in EMAIL_COLUMNS"),
+    }
+}
+
+fn stats_values(name: &str, snapshots: &[StatsSnapshot]) -> ColumnValues {
+    let count = |value: fn(&StatsSnapshot) -> i64| {
+        ColumnValues::Int64(snapshots.iter().map(|s| Some(value(s))).collect())
+    };
+    match name {
+        "captured_at" => ColumnValues::Int64(
+            snapshots
+                .iter()
+                .map(|snapshot| timestamp_millis(&snapshot.captured_at))
+                .collect(),
+        ),
+        "total_accounts" => count(|snapshot| snapshot.total_accounts),
+        "total_emails" => count(|snapshot| snapshot.total_emails),
+        "total_contacts" => count(|snapshot| snapshot.total_contacts),
+        "index_doc_count" => count(|snapshot| snapshot.index_doc_count),
+        "index_size_bytes" => count(|snapshot| snapshot.index_size_bytes),
+        other => unreachable!("column '{other}' is not in STATS_COLUMNS"),
+    }
+}
+
+/// RFC 3339 to milliseconds since the epoch; `None` when unparseable.
+fn timestamp_millis(raw: &str) -> Option<i64> {
+    DateTime::parse_from_rfc3339(raw)
+        .ok()
+        .map(|parsed| parsed.timestamp_millis())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        export_emails_eml, export_emails_mbox, export_emails_notmuch, export_emails_parquet,
+        select_columns, write_emails_ndjson, EmailSource, EMAIL_COLUMNS,
+    };
+    use crate::db::models::{Account, AccountType, Email};
+    use crate::db::{Database, EmailSearchFilters};
+    use crate::raw::RawStore;
+
+    fn email(id: &str, received_at: &str) -> Email {
+        Email {
+            account_id: Some("acc".to_string()),
+            subject: Some("Quarterly numbers".to_string()),
+            from_address: Some("alice@example.com".to_string()),
+            to_addresses: vec!["me@example.com".to_string()],
+            body_text: Some("Secret body".to_string()),
+            received_at: received_at.to_string(),
+            is_read: Some(true),
+            folder: Some("inbox".to_string()),
+            ..Email::fixture(id)
+        }
+    }
+
+    #[test]
+    fn columns_default_to_metadata_and_reject_unknown_names() {
+        let defaults = select_columns(EMAIL_COLUMNS, &[]).expect("default columns");
+        assert!(defaults.iter().any(|column| column.name == "subject"));
+        assert!(!defaults.iter().any(|column| column.name == "body_text"));
+
+        let picked = select_columns(
+            EMAIL_COLUMNS,
+            &["received_at".to_string(), " id".to_string()],
+        )
+        .expect("picked columns");
+        assert_eq!(
+            picked.iter().map(|column| column.name).collect::<Vec<_>>(),
+            vec!["received_at", "id"]
+        );
+        assert!(select_columns(EMAIL_COLUMNS, &["bodies".to_string()]).is_err());
+    }
+
+    #[test]
+    fn email_export_writes_every_matching_row() {
+        let root = std::env::temp_dir().join(format!("ess-export-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).expect("create temp root");
+        let db = Database::open(&root.join("ess.db")).expect("open db");
+        db.insert_account(&Account {
+            account_id: "acc".to_string(),
+            email_address: "me@example.com".to_string(),
+            display_name: None,
+            tenant_id: None,
+            account_type: AccountType::Professional,
+            enabled: true,
+            last_sync: None,
+            config: None,
+        })
+        .expect("insert account");
+        db.insert_email(&email("a", "2026-01-01T10:00:00Z"))
+            .expect("insert email");
+        db.insert_email(&email("b", "2026-02-01T10:00:00Z"))
+            .expect("insert email");
+
+        let path = root.join("emails.parquet");
+        let columns = select_columns(EMAIL_COLUMNS, &[]).expect("default columns");
+        let report = export_emails_parquet(
+            &db,
+            &EmailSource::Stored(EmailSearchFilters::default()),
+            &columns,
+            &path,
+        )
+        .expect("export emails");
+        assert_eq!(report.rows, 2);
+        assert_eq!(
+            report.size_bytes,
+            std::fs::metadata(&path).expect("stat").len()
+        );
+        let bytes = std::fs::read(&path).expect("read export");
+        assert!(bytes.starts_with(b"PAR1") && bytes.ends_with(b"PAR1"));
+        assert!(bytes.windows(9).any(|window| window == b"Quarterly"));
+        assert!(!bytes.windows(6).any(|window| window == b"Secret"));
+        assert!(!root.join("emails.parquet.partial").exists());
+
+        let mut tagged = email("c", "2026-03-01T10:00:00Z");
+        tagged.internet_message_id = Some("<c@example.com>".to_string());
+        db.insert_email(&tagged).expect("insert email");
+        db.star_email("c").expect("star email");
+        let path = root.join("tags.dump");
+        let report = export_emails_notmuch(
+            &db,
+            &EmailSource::Stored(EmailSearchFilters::default()),
+            &path,
+        )
+        .expect("export tags");
+        assert_eq!((report.rows, report.skipped), (1, 2));
+        assert_eq!(
+            std::fs::read_to_string(&path).expect("read dump"),
+            "#notmuch-dump batch-tag:3 tags\n+inbox +starred -- id:c@example.com\n"
+        );
+
+        let stored = EmailSource::Stored(EmailSearchFilters::default());
+        let raw = RawStore::new(root.join("raw"));
+        let source = b"Subject: Original\r\nX-Mailer: Outlook\r\n\r\nAs sent.\r\n";
+        raw.put("a", source).expect("store raw message");
+        let path = root.join("emails.mbox");
+        let report = export_emails_mbox(&db, &stored, &raw, &path).expect("export mbox");
+        assert_eq!(report.rows, 3);
+        let mbox = std::fs::read_to_string(&path).expect("read mbox");
+        assert_eq!(mbox.matches("\nFrom alice@example.com ").count(), 2);
+        assert!(mbox.ends_with("\nSubject: Original\nX-Mailer: Outlook\n\nAs sent.\n\n"));
+        assert!(mbox.starts_with("From alice@example.com Sun Mar  1 10:00:00 2026\n"));
+
+        let directory = root.join("eml");
+        let report = export_emails_eml(&db, &stored, &raw, &directory).expect("export eml");
+        assert_eq!(report.rows, 3);
+        let first = std::fs::read_to_string(directory.join("email-0001.eml")).expect("read eml");
+        assert!(first.contains("Message-ID: <c@example.com>\r\n"));
+        assert_eq!(
+            std::fs::read(directory.join("email-0003.eml")).expect("read raw eml"),
+            source
+        );
+        assert!(export_emails_eml(&db, &stored, &raw, &directory).is_err());
+
+        let mut ndjson = Vec::new();
+        let rows = write_emails_ndjson(&db, &stored, &mut ndjson).expect("write ndjson");
+        let lines = String::from_utf8(ndjson).expect("utf-8");
+        assert_eq!(rows, 3);
+        let ids = lines
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<Email>(line)
+                    .expect("one email per line")
+                    .id
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["c", "b", "a"]);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
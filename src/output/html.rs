@@ -0,0 +1,129 @@
+//! A standalone HTML page for `ess show --html` and `ess show --open`.
+//!
+//! The stored HTML body is wrapped with the message headers. Scripts are
+//! removed, and a Content-Security-Policy lets the page load nothing from
+//! the network, so remote images, tracking pixels and external styles stay
+//! blocked. `<img>` sources pointing at remote or `cid:` URLs are moved to
+//! `data-ess-blocked-src`: attachment contents are not stored, so inline
+//! (CID) images cannot be filled in and show as their alt text instead.
+
+use regex::Regex;
+
+use crate::db::models::Email;
+
+/// Allows inline styles and `data:` images only.
+const CONTENT_SECURITY_POLICY: &str =
+    "default-src 'none'; img-src data:; style-src 'unsafe-inline'; font-src data:";
+
+pub fn format_email(email: &Email) -> String {
+    let body = match email.body_html.as_deref() {
+        Some(html) if !html.trim().is_empty() => sanitize(html),
+        _ => {
+            let text = email
+                .body_text
+                .as_deref()
+                .or(email.body_preview.as_deref())
+                .unwrap_or_default();
+            format!(
+                "<pre style=\"white-space: pre-wrap\">{}</pre>",
+                escape(text)
+            )
+        }
+    };
+    let subject = escape(email.subject.as_deref().unwrap_or("(no subject)"));
+
+    let mut headers = Vec::new();
+    let from = match (email.from_name.as_deref(), email.from_address.as_deref()) {
+        (Some(name), Some(address)) => format!("{name} <{address}>"),
+        (name, address) => name.or(address).unwrap_or("(unknown)").to_string(),
+    };
+    headers.push(("From", from));
+    for (name, addresses) in [("To", &email.to_addresses), ("Cc", &email.cc_addresses)] {
+        if !addresses.is_empty() {
+            headers.push((name, addresses.join(", ")));
+        }
+    }
+    headers.push((
+        "Date",
+        email
+            .sent_at
+            .clone()
+            .unwrap_or_else(|| email.received_at.clone()),
+    ));
+    let rows = headers
+        .iter()
+        .map(|(name, value)| format!("<tr><th>{name}</th><td>{}</td></tr>", escape(value)))
+        .collect::<String>();
+
+    format!(
+        "<!DOCTYPE html>\n\
+         <html>\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <meta http-equiv=\"Content-Security-Policy\" content=\"{CONTENT_SECURITY_POLICY}\">\n\
+         <title>{subject}</title>\n\
+         <style>.ess-headers {{ font-family: sans-serif; border-bottom: 1px solid #ccc; margin-bottom: 1em; padding-bottom: 0.5em; }} \
+         .ess-headers th {{ text-align: right; padding-right: 0.75em; color: #666; font-weight: normal; vertical-align: top; }}</style>\n\
+         </head>\n\
+         <body>\n\
+         <div class=\"ess-headers\"><h2>{subject}</h2><table>{rows}</table></div>\n\
+         {body}\n\
+         </body>\n\
+         </html>\n"
+    )
+}
+
+/// Drops `<script>` elements and moves remote and `cid:` image sources out
+/// of `src`.
+fn sanitize(html: &str) -> String {
+    let script = Regex::new(r"(?is)<script\b.*?</script\s*>").expect("compile script regex");
+    let image_source = Regex::new(r#"(?i)(<img\b[^>]*?\s)src(\s*=\s*["']?\s*(?:https?:|//|cid:))"#)
+        .expect("compile image source regex");
+    let without_scripts = script.replace_all(html, "");
+    image_source
+        .replace_all(&without_scripts, "${1}data-ess-blocked-src${2}")
+        .into_owned()
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_email, sanitize};
+    use crate::db::models::Email;
+
+    #[test]
+    fn blocks_scripts_and_remote_images() {
+        let html = "<p>Hi</p><script>alert(1)</script>\
+                    <img alt=\"logo\" src=\"https://tracker.example/p.gif\">\
+                    <IMG SRC='cid:image001@01D'>\
+                    <img src=\"data:image/png;base64,AAAA\">";
+        assert_eq!(
+            sanitize(html),
+            "<p>Hi</p>\
+             <img alt=\"logo\" data-ess-blocked-src=\"https://tracker.example/p.gif\">\
+             <IMG data-ess-blocked-src='cid:image001@01D'>\
+             <img src=\"data:image/png;base64,AAAA\">"
+        );
+
+        let email = Email {
+            subject: Some("Q3 <draft>".to_string()),
+            from_address: Some("alice@example.com".to_string()),
+            from_name: Some("Alice".to_string()),
+            body_text: Some("a < b".to_string()),
+            received_at: "2026-02-01T10:00:00Z".to_string(),
+            ..Email::fixture("e1")
+        };
+        let page = format_email(&email);
+        assert!(page.contains("content=\"default-src 'none'; img-src data:;"));
+        assert!(page.contains("<title>Q3 &lt;draft&gt;</title>"));
+        assert!(page.contains("<td>Alice &lt;alice@example.com&gt;</td>"));
+        assert!(page.contains("<pre style=\"white-space: pre-wrap\">a &lt; b</pre>"));
+    }
+}
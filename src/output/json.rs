@@ -1,19 +1,136 @@
+use std::io::Write;
+
 use anyhow::Result;
+use serde::Serialize;
 
 use crate::db::models::{Contact, Email};
-use crate::db::DatabaseStats;
-use crate::output::SearchResultItem;
+use crate::db::{
+    Analytics, AttachmentVersion, CollectionSummary, ConversationSummary, DatabaseStats,
+    FolderDiscovery, FolderEmailCount, Followup, Label, MailingList, MeetingLink, Note,
+    QuarantinedMessage, StatsTrend, SyncUsage,
+};
+use crate::env::EnvStatus;
+use crate::output::{ScopeComparisonItem, SearchResultItem, ThreadResultItem};
+use crate::search::FacetCounts;
+use crate::trim::TrimReport;
+
+/// Writes each record as compact JSON on its own line (NDJSON), so a
+/// listing can be streamed batch by batch instead of built up in memory.
+pub fn write_ndjson<W: Write, T: Serialize>(out: &mut W, records: &[T]) -> Result<()> {
+    for record in records {
+        serde_json::to_writer(&mut *out, record)?;
+        out.write_all(b"\n")?;
+    }
+    Ok(())
+}
 
 pub fn format_search_results(results: &[SearchResultItem]) -> Result<String> {
     Ok(serde_json::to_string_pretty(results)?)
 }
 
+pub fn format_thread_results(threads: &[ThreadResultItem]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(threads)?)
+}
+
+pub fn format_paged_search_results(
+    results: &[SearchResultItem],
+    next_cursor: Option<&str>,
+) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&serde_json::json!({
+        "results": results,
+        "next_cursor": next_cursor,
+    }))?)
+}
+
+pub fn format_faceted_search_results(
+    results: &[SearchResultItem],
+    facets: &[FacetCounts],
+) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&serde_json::json!({
+        "results": results,
+        "facets": facets,
+    }))?)
+}
+
+pub fn format_scope_comparison(query: &str, scopes: &[ScopeComparisonItem]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&serde_json::json!({
+        "query": query,
+        "scopes": scopes,
+    }))?)
+}
+
 pub fn format_email(email: &Email) -> Result<String> {
     Ok(serde_json::to_string_pretty(email)?)
 }
 
-pub fn format_thread(emails: &[Email]) -> Result<String> {
-    Ok(serde_json::to_string_pretty(emails)?)
+pub fn format_thread(
+    emails: &[Email],
+    notes: &[Note],
+    summary: Option<&ConversationSummary>,
+) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&serde_json::json!({
+        "emails": emails,
+        "notes": notes,
+        "summary": summary,
+    }))?)
+}
+
+pub fn format_notes(notes: &[Note]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(notes)?)
+}
+
+pub fn format_collections(collections: &[CollectionSummary]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(collections)?)
+}
+
+pub fn format_folder_counts(folders: &[FolderEmailCount]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(folders)?)
+}
+
+pub fn format_discovered_folders(folders: &[FolderDiscovery]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(folders)?)
+}
+
+pub fn format_labels(labels: &[Label]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(labels)?)
+}
+
+pub fn format_mailing_lists(lists: &[MailingList]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(lists)?)
+}
+
+pub fn format_followups(followups: &[Followup]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(followups)?)
+}
+
+pub fn format_attachment_versions(copies: &[AttachmentVersion]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(copies)?)
+}
+
+pub fn format_meeting_links(links: &[MeetingLink]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(links)?)
+}
+
+/// Entries without their payloads; `ess quarantine export` writes those.
+pub fn format_quarantine(entries: &[QuarantinedMessage]) -> Result<String> {
+    let entries = entries
+        .iter()
+        .map(|entry| {
+            serde_json::json!({
+                "id": entry.id,
+                "account_id": entry.account_id,
+                "connector": entry.connector,
+                "message_id": entry.message_id,
+                "source": entry.source,
+                "error": entry.error,
+                "created_at": entry.created_at,
+                "retry_count": entry.retry_count,
+                "last_retry_at": entry.last_retry_at,
+                "payload_bytes": entry.payload.len(),
+            })
+        })
+        .collect::<Vec<_>>();
+    Ok(serde_json::to_string_pretty(&entries)?)
 }
 
 pub fn format_contacts(contacts: &[Contact]) -> Result<String> {
@@ -23,3 +140,23 @@ pub fn format_contacts(contacts: &[Contact]) -> Result<String> {
 pub fn format_stats(stats: &DatabaseStats) -> Result<String> {
     Ok(serde_json::to_string_pretty(stats)?)
 }
+
+pub fn format_analytics(analytics: &Analytics) -> Result<String> {
+    Ok(serde_json::to_string_pretty(analytics)?)
+}
+
+pub fn format_stats_trend(trend: &StatsTrend) -> Result<String> {
+    Ok(serde_json::to_string_pretty(trend)?)
+}
+
+pub fn format_sync_usage(usage: &[SyncUsage]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(usage)?)
+}
+
+pub fn format_trim_report(report: &TrimReport) -> Result<String> {
+    Ok(serde_json::to_string_pretty(report)?)
+}
+
+pub fn format_env(vars: &[EnvStatus]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(vars)?)
+}
@@ -0,0 +1,260 @@
+//! Markdown for `ess show --format markdown` and `ess thread --format
+//! markdown`, meant for pasting into notes tools and for agents.
+//!
+//! Headers are a blockquote above the body. HTML bodies are converted to
+//! Markdown-flavoured text (emphasis, lists, quotes, reference-style
+//! links); plain-text bodies are kept as they are. Attachments are listed
+//! by name, type and size, as their contents are not stored.
+
+use crate::db::models::{Attachment, Email};
+use crate::db::previews::normalize_title;
+use crate::db::{ConversationSummary, Note};
+
+/// Column at which converted HTML bodies are wrapped.
+const BODY_WIDTH: usize = 100;
+
+/// An email and the attachments stored for it.
+pub struct MarkdownMessage<'a> {
+    pub email: &'a Email,
+    pub attachments: &'a [Attachment],
+}
+
+pub fn format_email(message: &MarkdownMessage<'_>) -> String {
+    let mut out = format!(
+        "# {}\n\n",
+        escape(message.email.subject.as_deref().unwrap_or("(no subject)"))
+    );
+    push_message(&mut out, message);
+    finish(out)
+}
+
+/// One `##` section per message, oldest first, under the thread's title,
+/// with the thread's notes before them.
+pub fn format_thread(
+    messages: &[MarkdownMessage<'_>],
+    notes: &[Note],
+    summary: Option<&ConversationSummary>,
+) -> String {
+    let title = messages
+        .first()
+        .map(|message| normalize_title(message.email.subject.as_deref()))
+        .unwrap_or_else(|| "(no messages)".to_string());
+    let mut out = format!("# {}\n\n", escape(&title));
+    if let Some(summary) = summary {
+        out.push_str(&format!(
+            "**Summary** _({})_\n\n{}\n\n",
+            escape(&summary.model),
+            escape(summary.summary.trim())
+        ));
+    }
+    if !notes.is_empty() {
+        out.push_str("**Notes**\n\n");
+        for note in notes {
+            out.push_str(&format!(
+                "- {} _({})_\n",
+                escape(&note.body.replace('\n', " ")),
+                note.created_at
+            ));
+        }
+        out.push('\n');
+    }
+    for (idx, message) in messages.iter().enumerate() {
+        if idx > 0 || !notes.is_empty() || summary.is_some() {
+            out.push_str("---\n\n");
+        }
+        let email = message.email;
+        let sender = email
+            .from_name
+            .as_deref()
+            .or(email.from_address.as_deref())
+            .unwrap_or("(unknown)");
+        out.push_str(&format!(
+            "## {}, {}\n\n",
+            escape(sender),
+            email.sent_at.as_deref().unwrap_or(&email.received_at)
+        ));
+        push_message(&mut out, message);
+    }
+    finish(out)
+}
+
+/// Ends the document with a single line break.
+fn finish(mut out: String) -> String {
+    out.truncate(out.trim_end().len());
+    out.push('\n');
+    out
+}
+
+fn push_message(out: &mut String, message: &MarkdownMessage<'_>) {
+    let email = message.email;
+    let mut headers = Vec::new();
+    let from = match (email.from_name.as_deref(), email.from_address.as_deref()) {
+        (Some(name), Some(address)) => format!("{} <{address}>", escape(name)),
+        (None, Some(address)) => format!("<{address}>"),
+        (Some(name), None) => escape(name),
+        (None, None) => "(unknown)".to_string(),
+    };
+    headers.push(("From", from));
+    for (name, addresses) in [("To", &email.to_addresses), ("Cc", &email.cc_addresses)] {
+        if !addresses.is_empty() {
+            headers.push((name, escape(&addresses.join(", "))));
+        }
+    }
+    if let Some(subject) = email.subject.as_deref() {
+        headers.push(("Subject", escape(subject)));
+    }
+    headers.push((
+        "Date",
+        email
+            .sent_at
+            .clone()
+            .unwrap_or_else(|| email.received_at.clone()),
+    ));
+    if let Some(folder) = email.folder.as_deref() {
+        headers.push(("Folder", escape(folder)));
+    }
+    headers.push(("ID", format!("`{}`", email.id)));
+    let quoted = headers
+        .iter()
+        .map(|(name, value)| format!("> **{name}:** {value}"))
+        .collect::<Vec<_>>()
+        .join("  \n");
+    out.push_str(&quoted);
+    out.push_str("\n\n");
+
+    let body = match (email.body_html.as_deref(), email.body_text.as_deref()) {
+        (Some(html), _) if !html.trim().is_empty() => {
+            let decorator = html2text::render::text_renderer::PlainDecorator::new();
+            html2text::from_read_with_decorator(html.as_bytes(), BODY_WIDTH, decorator)
+        }
+        (_, Some(text)) if !text.trim().is_empty() => text.replace("\r\n", "\n"),
+        _ => email.body_preview.clone().unwrap_or_default(),
+    };
+    let body = body.trim();
+    out.push_str(if body.is_empty() { "_(empty)_" } else { body });
+    out.push_str("\n\n");
+
+    if !message.attachments.is_empty() {
+        out.push_str("**Attachments**\n\n");
+        for attachment in message.attachments {
+            let mut details = Vec::new();
+            if let Some(content_type) = attachment.content_type.as_deref() {
+                details.push(content_type.to_string());
+            }
+            if let Some(size) = attachment.size_bytes {
+                details.push(format_size(size));
+            }
+            let name = escape(attachment.name.as_deref().unwrap_or("(unnamed)"));
+            if details.is_empty() {
+                out.push_str(&format!("- {name}\n"));
+            } else {
+                out.push_str(&format!("- {name} ({})\n", details.join(", ")));
+            }
+        }
+        out.push('\n');
+    }
+}
+
+/// Backslash-escapes the characters that would otherwise start emphasis,
+/// code, links or HTML in inline text.
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for ch in value.chars() {
+        if matches!(ch, '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '>' | '#') {
+            out.push('\\');
+        }
+        out.push(ch);
+    }
+    out
+}
+
+pub(crate) fn format_size(bytes: i64) -> String {
+    const KB: f64 = 1024.0;
+    let size = bytes as f64;
+    if size >= KB * KB {
+        format!("{:.1} MB", size / (KB * KB))
+    } else if size >= KB {
+        format!("{:.1} KB", size / KB)
+    } else {
+        format!("{bytes} B")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_email, format_thread, MarkdownMessage};
+    use crate::db::models::{Attachment, Email};
+
+    fn email(id: &str, subject: &str) -> Email {
+        Email {
+            conversation_id: Some("conv".to_string()),
+            account_id: Some("acc".to_string()),
+            subject: Some(subject.to_string()),
+            from_address: Some("alice@example.com".to_string()),
+            from_name: Some("Alice_Smith".to_string()),
+            to_addresses: vec!["me@example.com".to_string()],
+            body_text: Some("Plain *text*".to_string()),
+            body_html: Some(
+                "<p>Numbers are <strong>in</strong>.</p><ul><li>Q3</li></ul>".to_string(),
+            ),
+            received_at: "2026-02-01T10:00:00Z".to_string(),
+            has_attachments: Some(true),
+            folder: Some("inbox".to_string()),
+            ..Email::fixture(id)
+        }
+    }
+
+    #[test]
+    fn renders_quoted_headers_converted_body_and_attachments() {
+        let email = email("e1", "Budget [Q3]");
+        let attachments = [Attachment {
+            id: "a1".to_string(),
+            email_id: "e1".to_string(),
+            name: Some("budget.xlsx".to_string()),
+            content_type: Some("application/vnd.ms-excel".to_string()),
+            size_bytes: Some(12_800),
+            is_inline: Some(false),
+        }];
+        let markdown = format_email(&MarkdownMessage {
+            email: &email,
+            attachments: &attachments,
+        });
+        assert_eq!(
+            markdown,
+            "# Budget \\[Q3\\]\n\n\
+             > **From:** Alice\\_Smith <alice@example.com>  \n\
+             > **To:** me@example.com  \n\
+             > **Subject:** Budget \\[Q3\\]  \n\
+             > **Date:** 2026-02-01T10:00:00Z  \n\
+             > **Folder:** inbox  \n\
+             > **ID:** `e1`\n\n\
+             Numbers are **in**.\n\n* Q3\n\n\
+             **Attachments**\n\n\
+             - budget.xlsx (application/vnd.ms-excel, 12.5 KB)\n"
+        );
+    }
+
+    #[test]
+    fn threads_get_one_section_per_message() {
+        let first = email("e1", "Budget");
+        let mut reply = email("e2", "Re: Budget");
+        reply.body_html = None;
+        let markdown = format_thread(
+            &[
+                MarkdownMessage {
+                    email: &first,
+                    attachments: &[],
+                },
+                MarkdownMessage {
+                    email: &reply,
+                    attachments: &[],
+                },
+            ],
+            &[],
+            None,
+        );
+        assert!(markdown.starts_with("# Budget\n\n## Alice\\_Smith, 2026-02-01T10:00:00Z\n\n"));
+        assert_eq!(markdown.matches("\n---\n\n## ").count(), 1);
+        assert!(markdown.ends_with("`e2`\n\nPlain *text*\n"));
+    }
+}
@@ -1,11 +1,34 @@
+pub mod csv;
+pub mod eml;
+pub mod export;
+pub mod html;
 pub mod json;
+pub mod markdown;
+pub mod mutt;
+pub mod notmuch;
+pub mod parquet;
+pub mod progress;
+pub mod raycast;
+pub mod schema;
 pub mod table;
 
 use anyhow::Result;
 use serde::Serialize;
 
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use crate::db::models::{Contact, Email};
-use crate::db::DatabaseStats;
+use crate::db::{
+    Analytics, AttachmentVersion, CollectionSummary, ConversationSummary, Database, DatabaseStats,
+    FolderDiscovery, FolderEmailCount, Followup, Label, MailingList, MeetingLink, Note, Preview,
+    QuarantinedMessage, StatsTrend, SyncUsage,
+};
+use crate::env::{self, EnvStatus};
+use crate::search::{FacetCounts, ScopeMatches, Snippet, ThreadMatch};
+use crate::trim::TrimReport;
+
+pub use self::table::TableStyle;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OutputFormat {
@@ -28,26 +51,245 @@ pub struct SearchResultItem {
     pub email: Email,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub score: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<Snippet>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preview: Option<Preview>,
+}
+
+/// Fills `preview` from the `previews` table in one lookup.
+pub fn attach_previews(db: &Database, results: &mut [SearchResultItem]) -> Result<()> {
+    let ids = results
+        .iter()
+        .map(|result| result.email.id.as_str())
+        .collect::<Vec<_>>();
+    let mut previews = db.previews(&ids)?;
+    for result in results {
+        result.preview = previews.remove(&result.email.id);
+    }
+    Ok(())
 }
 
 pub fn format_search_results(format: OutputFormat, results: &[SearchResultItem]) -> Result<String> {
     match format {
-        OutputFormat::Table => Ok(table::format_search_results(results)),
+        OutputFormat::Table => Ok(table::format_search_results(results, &table_style())),
         OutputFormat::Json => json::format_search_results(results),
     }
 }
 
+/// A page of results plus the token for the next one: a trailing hint in
+/// the table, `{results, next_cursor}` in JSON.
+pub fn format_paged_search_results(
+    format: OutputFormat,
+    results: &[SearchResultItem],
+    next_cursor: Option<&str>,
+) -> Result<String> {
+    match format {
+        OutputFormat::Table => {
+            let mut out = table::format_search_results(results, &table_style());
+            if let Some(cursor) = next_cursor {
+                out.push_str(&format!("\nMore results: --cursor {cursor}"));
+            }
+            Ok(out)
+        }
+        OutputFormat::Json => json::format_paged_search_results(results, next_cursor),
+    }
+}
+
+/// Results followed by facet counts; JSON wraps both in one object.
+pub fn format_faceted_search_results(
+    format: OutputFormat,
+    results: &[SearchResultItem],
+    facets: &[FacetCounts],
+) -> Result<String> {
+    match format {
+        OutputFormat::Table => Ok(format!(
+            "{}\n{}",
+            table::format_search_results(results, &table_style()),
+            table::format_facets(facets)
+        )),
+        OutputFormat::Json => json::format_faceted_search_results(results, facets),
+    }
+}
+
+/// One row of `ess search --group-by-thread`; in JSON the best message's
+/// `email`, `score` and `snippet` sit next to the thread fields.
+#[derive(Debug, Clone, Serialize)]
+pub struct ThreadResultItem {
+    pub conversation_id: Option<String>,
+    pub messages: i64,
+    pub participants: Vec<String>,
+    #[serde(flatten)]
+    pub best: SearchResultItem,
+}
+
+impl From<ThreadMatch> for ThreadResultItem {
+    fn from(thread: ThreadMatch) -> Self {
+        Self {
+            conversation_id: thread.conversation_id,
+            messages: thread.messages,
+            participants: thread.participants,
+            best: SearchResultItem {
+                email: thread.best.email,
+                score: Some(thread.best.score),
+                snippet: thread.best.snippet,
+                preview: None,
+            },
+        }
+    }
+}
+
+pub fn format_thread_results(format: OutputFormat, threads: &[ThreadResultItem]) -> Result<String> {
+    match format {
+        OutputFormat::Table => Ok(table::format_thread_results(threads, &table_style())),
+        OutputFormat::Json => json::format_thread_results(threads),
+    }
+}
+
+/// One side of `ess search --compare-scopes`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScopeComparisonItem {
+    pub scope: &'static str,
+    pub total: i64,
+    pub first_received_at: Option<String>,
+    pub last_received_at: Option<String>,
+    pub samples: Vec<SearchResultItem>,
+}
+
+impl From<ScopeMatches> for ScopeComparisonItem {
+    fn from(matches: ScopeMatches) -> Self {
+        Self {
+            scope: matches.scope.as_str(),
+            total: matches.total,
+            first_received_at: matches.first_received_at,
+            last_received_at: matches.last_received_at,
+            samples: matches
+                .samples
+                .into_iter()
+                .map(|result| SearchResultItem {
+                    email: result.email,
+                    score: Some(result.score),
+                    snippet: result.snippet,
+                    preview: None,
+                })
+                .collect(),
+        }
+    }
+}
+
+pub fn format_scope_comparison(
+    format: OutputFormat,
+    query: &str,
+    scopes: &[ScopeComparisonItem],
+) -> Result<String> {
+    match format {
+        OutputFormat::Table => Ok(table::format_scope_comparison(scopes)),
+        OutputFormat::Json => json::format_scope_comparison(query, scopes),
+    }
+}
+
 pub fn format_email(format: OutputFormat, email: &Email) -> Result<String> {
     match format {
-        OutputFormat::Table => Ok(table::format_email(email)),
+        OutputFormat::Table => Ok(table::format_email(email, table_style().color)),
         OutputFormat::Json => json::format_email(email),
     }
 }
 
-pub fn format_thread(format: OutputFormat, emails: &[Email]) -> Result<String> {
+/// A conversation's messages; its stored summary and notes head the table
+/// and sit next to `emails` in JSON.
+pub fn format_thread(
+    format: OutputFormat,
+    emails: &[Email],
+    notes: &[Note],
+    summary: Option<&ConversationSummary>,
+) -> Result<String> {
+    match format {
+        OutputFormat::Table => Ok(table::format_thread(
+            emails,
+            notes,
+            summary,
+            table_style().color,
+        )),
+        OutputFormat::Json => json::format_thread(emails, notes, summary),
+    }
+}
+
+pub fn format_notes(format: OutputFormat, notes: &[Note]) -> Result<String> {
+    match format {
+        OutputFormat::Table => Ok(table::format_notes(notes)),
+        OutputFormat::Json => json::format_notes(notes),
+    }
+}
+
+pub fn format_collections(
+    format: OutputFormat,
+    collections: &[CollectionSummary],
+) -> Result<String> {
+    match format {
+        OutputFormat::Table => Ok(table::format_collections(collections)),
+        OutputFormat::Json => json::format_collections(collections),
+    }
+}
+
+pub fn format_folder_counts(format: OutputFormat, folders: &[FolderEmailCount]) -> Result<String> {
+    match format {
+        OutputFormat::Table => Ok(table::format_folder_counts(folders)),
+        OutputFormat::Json => json::format_folder_counts(folders),
+    }
+}
+
+pub fn format_discovered_folders(
+    format: OutputFormat,
+    folders: &[FolderDiscovery],
+) -> Result<String> {
+    match format {
+        OutputFormat::Table => Ok(table::format_discovered_folders(folders)),
+        OutputFormat::Json => json::format_discovered_folders(folders),
+    }
+}
+
+pub fn format_labels(format: OutputFormat, labels: &[Label]) -> Result<String> {
+    match format {
+        OutputFormat::Table => Ok(table::format_labels(labels)),
+        OutputFormat::Json => json::format_labels(labels),
+    }
+}
+
+pub fn format_mailing_lists(format: OutputFormat, lists: &[MailingList]) -> Result<String> {
+    match format {
+        OutputFormat::Table => Ok(table::format_mailing_lists(lists)),
+        OutputFormat::Json => json::format_mailing_lists(lists),
+    }
+}
+
+pub fn format_followups(format: OutputFormat, followups: &[Followup]) -> Result<String> {
+    match format {
+        OutputFormat::Table => Ok(table::format_followups(followups)),
+        OutputFormat::Json => json::format_followups(followups),
+    }
+}
+
+pub fn format_attachment_versions(
+    format: OutputFormat,
+    copies: &[AttachmentVersion],
+) -> Result<String> {
+    match format {
+        OutputFormat::Table => Ok(table::format_attachment_versions(copies)),
+        OutputFormat::Json => json::format_attachment_versions(copies),
+    }
+}
+
+pub fn format_meeting_links(format: OutputFormat, links: &[MeetingLink]) -> Result<String> {
+    match format {
+        OutputFormat::Table => Ok(table::format_meeting_links(links)),
+        OutputFormat::Json => json::format_meeting_links(links),
+    }
+}
+
+pub fn format_quarantine(format: OutputFormat, entries: &[QuarantinedMessage]) -> Result<String> {
     match format {
-        OutputFormat::Table => Ok(table::format_thread(emails)),
-        OutputFormat::Json => json::format_thread(emails),
+        OutputFormat::Table => Ok(table::format_quarantine(entries)),
+        OutputFormat::Json => json::format_quarantine(entries),
     }
 }
 
@@ -64,3 +306,79 @@ pub fn format_stats(format: OutputFormat, stats: &DatabaseStats) -> Result<Strin
         OutputFormat::Json => json::format_stats(stats),
     }
 }
+
+pub fn format_analytics(format: OutputFormat, analytics: &Analytics) -> Result<String> {
+    match format {
+        OutputFormat::Table => Ok(table::format_analytics(analytics)),
+        OutputFormat::Json => json::format_analytics(analytics),
+    }
+}
+
+pub fn format_stats_trend(format: OutputFormat, trend: &StatsTrend) -> Result<String> {
+    match format {
+        OutputFormat::Table => Ok(table::format_stats_trend(trend)),
+        OutputFormat::Json => json::format_stats_trend(trend),
+    }
+}
+
+pub fn format_sync_usage(format: OutputFormat, usage: &[SyncUsage]) -> Result<String> {
+    match format {
+        OutputFormat::Table => Ok(table::format_sync_usage(usage)),
+        OutputFormat::Json => json::format_sync_usage(usage),
+    }
+}
+
+pub fn format_trim_report(format: OutputFormat, report: &TrimReport) -> Result<String> {
+    match format {
+        OutputFormat::Table => Ok(table::format_trim_report(report)),
+        OutputFormat::Json => json::format_trim_report(report),
+    }
+}
+
+pub fn format_env(format: OutputFormat, vars: &[EnvStatus]) -> Result<String> {
+    match format {
+        OutputFormat::Table => Ok(table::format_env(vars)),
+        OutputFormat::Json => json::format_env(vars),
+    }
+}
+
+static COLOR_DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turns off ANSI styling for the rest of the process (`--no-color`).
+pub fn disable_color() {
+    COLOR_DISABLED.store(true, Ordering::Relaxed);
+}
+
+tokio::task_local! {
+    /// The terminal of the `ess` client a daemon is rendering for.
+    static CLIENT_STYLE: TableStyle;
+}
+
+/// Runs `render` with tables styled for a client's terminal rather than
+/// this process's stdout.
+pub async fn with_table_style<F: std::future::Future>(style: TableStyle, render: F) -> F::Output {
+    CLIENT_STYLE.scope(style, render).await
+}
+
+/// Colors and width-fitting only make sense on an interactive terminal;
+/// `NO_COLOR` and `--no-color` turn the colors off there too. `COLUMNS`
+/// overrides the detected width. Piped output keeps the fixed layout.
+pub fn table_style() -> TableStyle {
+    if let Ok(style) = CLIENT_STYLE.try_with(|style| *style) {
+        return style;
+    }
+    if !std::io::stdout().is_terminal() {
+        return TableStyle::plain();
+    }
+    let width = env::var(env::COLUMNS)
+        .and_then(|columns| columns.trim().parse().ok())
+        .or_else(|| {
+            console::Term::stdout()
+                .size_checked()
+                .map(|(_, columns)| usize::from(columns))
+        });
+    TableStyle {
+        width,
+        color: !COLOR_DISABLED.load(Ordering::Relaxed) && env::var(env::NO_COLOR).is_none(),
+    }
+}
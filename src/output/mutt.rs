@@ -0,0 +1,81 @@
+//! Address completion output for `ess mutt-query`.
+//!
+//! mutt's `query_command` reads one status line followed by
+//! `address<TAB>name<TAB>other` lines; aerc's `address-book-cmd` reads the
+//! same lines without the status line.
+
+use crate::db::models::Contact;
+
+/// Formats contacts as completion lines, with mutt's status line first when
+/// `status_line` is set.
+pub fn format_query(contacts: &[Contact], status_line: bool) -> String {
+    let mut out = String::new();
+    if status_line {
+        match contacts.len() {
+            0 => out.push_str("ESS: no matching contacts\n"),
+            1 => out.push_str("ESS: 1 matching contact\n"),
+            count => out.push_str(&format!("ESS: {count} matching contacts\n")),
+        }
+    }
+    for contact in contacts {
+        let address = field(&contact.email_address);
+        if address.is_empty() {
+            continue;
+        }
+        let name = contact
+            .display_name
+            .as_deref()
+            .map(field)
+            .unwrap_or_default();
+        let other = contact.company.as_deref().map(field).unwrap_or_default();
+        out.push_str(&format!("{address}\t{name}\t{other}\n"));
+    }
+    out
+}
+
+/// Tabs and line breaks would split a line into bogus columns or entries.
+fn field(value: &str) -> String {
+    value
+        .split(['\t', '\n', '\r'])
+        .filter(|part| !part.trim().is_empty())
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format_query;
+    use crate::db::models::Contact;
+
+    fn contact(address: &str, name: Option<&str>, company: Option<&str>) -> Contact {
+        Contact {
+            email_address: address.to_string(),
+            display_name: name.map(str::to_string),
+            company: company.map(str::to_string),
+            phone: None,
+            attio_person_id: None,
+            attio_company_id: None,
+            message_count: 1,
+            first_seen: None,
+            last_seen: None,
+        }
+    }
+
+    #[test]
+    fn formats_mutt_and_aerc_lines() {
+        let contacts = vec![
+            contact("alice@example.com", Some("Alice\tLiddell"), Some("Acme")),
+            contact("bob@example.com", None, None),
+        ];
+
+        assert_eq!(
+            format_query(&contacts, true),
+            "ESS: 2 matching contacts\n\
+             alice@example.com\tAlice Liddell\tAcme\n\
+             bob@example.com\t\t\n"
+        );
+        assert_eq!(format_query(&contacts[1..], false), "bob@example.com\t\t\n");
+        assert_eq!(format_query(&[], true), "ESS: no matching contacts\n");
+    }
+}
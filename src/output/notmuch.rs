@@ -0,0 +1,160 @@
+//! `notmuch dump` output for `ess export --format notmuch`.
+//!
+//! The file uses notmuch's batch-tag format, one line per Message-ID:
+//! `+inbox +unread -- id:abc@example.com`, so `notmuch restore` can load
+//! ESS folders and flags as tags onto the same messages in a notmuch
+//! database.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+
+use crate::db::models::Email;
+
+pub const DUMP_HEADER: &str = "#notmuch-dump batch-tag:3 tags";
+
+/// Tags per Message-ID. Copies of a message (the same Message-ID in two
+/// accounts or folders) share one line carrying the tags of every copy.
+#[derive(Debug, Default)]
+pub struct TagDump {
+    messages: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl TagDump {
+    /// Adds an email's tags; `false` when it has no Message-ID and so
+    /// cannot be matched to a notmuch message.
+    pub fn add(&mut self, email: &Email, starred: bool) -> bool {
+        let Some(message_id) = email
+            .internet_message_id
+            .as_deref()
+            .map(|value| value.trim().trim_start_matches('<').trim_end_matches('>'))
+            .filter(|value| !value.is_empty())
+        else {
+            return false;
+        };
+        self.messages
+            .entry(message_id.to_string())
+            .or_default()
+            .extend(message_tags(email, starred));
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Writes the header and one line per message, ordered by Message-ID.
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "{DUMP_HEADER}")?;
+        for (message_id, tags) in &self.messages {
+            for tag in tags {
+                write!(out, "+{} ", encode(tag))?;
+            }
+            writeln!(out, "-- id:{}", encode(message_id))?;
+        }
+        Ok(())
+    }
+}
+
+/// The folder (lowercased), categories, and `unread`, `flagged`,
+/// `attachment` and `starred` in notmuch's conventional spelling.
+pub fn message_tags(email: &Email, starred: bool) -> BTreeSet<String> {
+    let mut tags = BTreeSet::new();
+    if let Some(folder) = email
+        .folder
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+    {
+        tags.insert(folder.to_lowercase());
+    }
+    tags.extend(
+        email
+            .categories
+            .iter()
+            .map(|category| category.trim())
+            .filter(|category| !category.is_empty())
+            .map(str::to_string),
+    );
+    if !email.is_read.unwrap_or(false) {
+        tags.insert("unread".to_string());
+    }
+    if email
+        .flag_status
+        .as_deref()
+        .is_some_and(|status| status.eq_ignore_ascii_case("flagged"))
+    {
+        tags.insert("flagged".to_string());
+    }
+    if email.has_attachments == Some(true) {
+        tags.insert("attachment".to_string());
+    }
+    if starred {
+        tags.insert("starred".to_string());
+    }
+    tags
+}
+
+/// notmuch's hex encoding: every byte outside `[A-Za-z0-9@=.,_+-]` becomes
+/// `%XX`.
+fn encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || b"@=.,_+-".contains(&byte) {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.push_str(&format!("%{byte:02x}"));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode, TagDump};
+    use crate::db::models::Email;
+
+    fn email(message_id: Option<&str>, folder: &str, is_read: bool) -> Email {
+        Email {
+            internet_message_id: message_id.map(str::to_string),
+            account_id: Some("acc".to_string()),
+            is_read: Some(is_read),
+            has_attachments: Some(false),
+            folder: Some(folder.to_string()),
+            ..Email::fixture(&format!("{folder}-{message_id:?}"))
+        }
+    }
+
+    #[test]
+    fn encodes_like_notmuch_dump() {
+        assert_eq!(encode("inbox"), "inbox");
+        assert_eq!(encode("Red category"), "Red%20category");
+        assert_eq!(encode("a/b%c"), "a%2fb%25c");
+        assert_eq!(encode("é"), "%c3%a9");
+    }
+
+    #[test]
+    fn copies_of_a_message_share_one_line() {
+        let mut dump = TagDump::default();
+        let mut flagged = email(Some("<m1@example.com>"), "Inbox", false);
+        flagged.flag_status = Some("flagged".to_string());
+        flagged.categories = vec!["Red category".to_string()];
+        assert!(dump.add(&flagged, false));
+        assert!(dump.add(&email(Some("m1@example.com"), "Archive", true), true));
+        assert!(dump.add(&email(Some("<m0@example.com>"), "inbox", true), false));
+        assert!(!dump.add(&email(None, "inbox", false), false));
+        assert_eq!(dump.len(), 2);
+
+        let mut out = Vec::new();
+        dump.write(&mut out).expect("write dump");
+        assert_eq!(
+            String::from_utf8(out).expect("utf-8"),
+            "#notmuch-dump batch-tag:3 tags\n\
+             +inbox -- id:m0@example.com\n\
+             +Red%20category +archive +flagged +inbox +starred +unread -- id:m1@example.com\n"
+        );
+    }
+}
@@ -0,0 +1,236 @@
+//! Parquet output for `ess export --format parquet`, through the `parquet`
+//! crate's `ArrowWriter`.
+//!
+//! Exports are flat: UTF-8 strings, 64-bit integers, booleans and UTC
+//! millisecond timestamps, each optionally nullable, zstd-compressed, one
+//! row group per batch the caller hands over. Files open in pandas, Polars,
+//! DuckDB and Spark.
+
+use std::io::{self, Write};
+use std::sync::Arc;
+
+use arrow_array::{
+    ArrayRef, BooleanArray, Int64Array, RecordBatch, StringArray, TimestampMillisecondArray,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+
+const CREATED_BY: &str = concat!("ess version ", env!("CARGO_PKG_VERSION"));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnKind {
+    Utf8,
+    Int64,
+    Boolean,
+    /// Milliseconds since the Unix epoch, UTC.
+    TimestampMillis,
+}
+
+impl ColumnKind {
+    fn data_type(self) -> DataType {
+        match self {
+            Self::Utf8 => DataType::Utf8,
+            Self::Int64 => DataType::Int64,
+            Self::Boolean => DataType::Boolean,
+            Self::TimestampMillis => DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnSpec {
+    pub name: String,
+    pub kind: ColumnKind,
+    pub nullable: bool,
+}
+
+/// One column's values for a row group; `None` is null.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnValues {
+    Utf8(Vec<Option<String>>),
+    Int64(Vec<Option<i64>>),
+    Boolean(Vec<Option<bool>>),
+}
+
+impl ColumnValues {
+    /// The Arrow array holding these values as a `kind` column, or `None`
+    /// when they are of another type.
+    fn into_array(self, kind: ColumnKind) -> Option<ArrayRef> {
+        let array: ArrayRef = match (self, kind) {
+            (Self::Utf8(values), ColumnKind::Utf8) => Arc::new(StringArray::from(values)),
+            (Self::Int64(values), ColumnKind::Int64) => Arc::new(Int64Array::from(values)),
+            (Self::Int64(values), ColumnKind::TimestampMillis) => {
+                Arc::new(TimestampMillisecondArray::from(values).with_timezone("UTC"))
+            }
+            (Self::Boolean(values), ColumnKind::Boolean) => Arc::new(BooleanArray::from(values)),
+            _ => return None,
+        };
+        Some(array)
+    }
+}
+
+pub struct ParquetWriter<W: Write + Send> {
+    writer: ArrowWriter<W>,
+    columns: Vec<ColumnSpec>,
+    schema: SchemaRef,
+}
+
+impl<W: Write + Send> ParquetWriter<W> {
+    pub fn new(out: W, columns: Vec<ColumnSpec>) -> io::Result<Self> {
+        let schema = Arc::new(Schema::new(
+            columns
+                .iter()
+                .map(|column| Field::new(&column.name, column.kind.data_type(), column.nullable))
+                .collect::<Vec<_>>(),
+        ));
+        let properties = WriterProperties::builder()
+            .set_created_by(CREATED_BY.to_string())
+            .set_compression(Compression::ZSTD(ZstdLevel::default()))
+            .build();
+        let writer = ArrowWriter::try_new(out, schema.clone(), Some(properties))
+            .map_err(io::Error::other)?;
+        Ok(Self {
+            writer,
+            columns,
+            schema,
+        })
+    }
+
+    /// Writes one row group; `values` holds one entry per column, in column
+    /// order, all of the same length.
+    pub fn write_row_group(&mut self, values: &[ColumnValues]) -> io::Result<()> {
+        if values.len() != self.columns.len() {
+            return Err(invalid(format!(
+                "expected {} columns, got {}",
+                self.columns.len(),
+                values.len()
+            )));
+        }
+        let arrays =
+            self.columns
+                .iter()
+                .zip(values)
+                .map(|(spec, column)| {
+                    column.clone().into_array(spec.kind).ok_or_else(|| {
+                        invalid(format!("column '{}' has the wrong type", spec.name))
+                    })
+                })
+                .collect::<io::Result<Vec<_>>>()?;
+        if arrays.first().is_none_or(|array| array.is_empty()) {
+            return Ok(());
+        }
+        // Checks the lengths and that required columns hold no nulls.
+        let batch = RecordBatch::try_new(self.schema.clone(), arrays)
+            .map_err(|error| invalid(error.to_string()))?;
+        self.writer.write(&batch).map_err(io::Error::other)?;
+        // Ends the row group here rather than at the writer's own size limit.
+        self.writer.flush().map_err(io::Error::other)
+    }
+
+    /// Writes the footer and returns the underlying writer.
+    pub fn finish(self) -> io::Result<W> {
+        let mut out = self.writer.into_inner().map_err(io::Error::other)?;
+        out.flush()?;
+        Ok(out)
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::{Array, BooleanArray, StringArray, TimestampMillisecondArray};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use super::{ColumnKind, ColumnSpec, ColumnValues, ParquetWriter};
+
+    fn column(name: &str, kind: ColumnKind, nullable: bool) -> ColumnSpec {
+        ColumnSpec {
+            name: name.to_string(),
+            kind,
+            nullable,
+        }
+    }
+
+    #[test]
+    fn files_read_back_and_bad_columns_are_rejected() {
+        let columns = vec![
+            column("id", ColumnKind::Utf8, false),
+            column("received_at", ColumnKind::TimestampMillis, false),
+            column("is_read", ColumnKind::Boolean, true),
+        ];
+        let path =
+            std::env::temp_dir().join(format!("ess-parquet-{}.parquet", uuid::Uuid::new_v4()));
+        let file = std::fs::File::create(&path).expect("create file");
+        let mut writer = ParquetWriter::new(file, columns).expect("start file");
+        writer
+            .write_row_group(&[
+                ColumnValues::Utf8(vec![Some("a".to_string()), Some("b".to_string())]),
+                ColumnValues::Int64(vec![Some(1_767_225_600_000), Some(0)]),
+                ColumnValues::Boolean(vec![Some(true), None]),
+            ])
+            .expect("write row group");
+        writer
+            .write_row_group(&[
+                ColumnValues::Utf8(vec![Some("c".to_string())]),
+                ColumnValues::Int64(vec![Some(1)]),
+                ColumnValues::Boolean(vec![Some(false)]),
+            ])
+            .expect("write second row group");
+        assert!(writer
+            .write_row_group(&[
+                ColumnValues::Utf8(vec![None]),
+                ColumnValues::Int64(vec![Some(0)]),
+                ColumnValues::Boolean(vec![None]),
+            ])
+            .is_err());
+        assert!(writer
+            .write_row_group(&[ColumnValues::Utf8(vec![Some("c".to_string())])])
+            .is_err());
+        assert!(writer
+            .write_row_group(&[
+                ColumnValues::Int64(vec![Some(0)]),
+                ColumnValues::Int64(vec![Some(0)]),
+                ColumnValues::Boolean(vec![None]),
+            ])
+            .is_err());
+        writer.finish().expect("finish file");
+
+        let file = std::fs::File::open(&path).expect("open file");
+        let builder = ParquetRecordBatchReaderBuilder::try_new(file).expect("read footer");
+        assert_eq!(builder.metadata().num_row_groups(), 2);
+        let batches = builder
+            .build()
+            .expect("reader")
+            .collect::<Result<Vec<_>, _>>()
+            .expect("read batches");
+        let rows: usize = batches.iter().map(|batch| batch.num_rows()).sum();
+        assert_eq!(rows, 3);
+        let first = &batches[0];
+        let ids = first
+            .column(0)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .expect("ids");
+        assert_eq!(ids.value(1), "b");
+        let received = first
+            .column(1)
+            .as_any()
+            .downcast_ref::<TimestampMillisecondArray>()
+            .expect("timestamps");
+        assert_eq!(received.value(0), 1_767_225_600_000);
+        assert_eq!(received.timezone(), Some("UTC"));
+        let read = first
+            .column(2)
+            .as_any()
+            .downcast_ref::<BooleanArray>()
+            .expect("booleans");
+        assert!(read.value(0));
+        assert!(read.is_null(1));
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+use crate::connectors::progress::{describe, ProgressReporter, SyncProgress};
+
+const BAR_TEMPLATE: &str =
+    "{prefix:.bold} [{elapsed_precise}] {bar:30.cyan/blue} {pos}/{len} ETA {eta} {msg}";
+const SPINNER_TEMPLATE: &str = "{prefix:.bold} [{elapsed_precise}] {spinner} {pos} {msg}";
+
+type StageKey = (String, String, Option<String>);
+
+fn stage_key(account_id: &str, stage: &str, folder: &Option<String>) -> StageKey {
+    (account_id.to_string(), stage.to_string(), folder.clone())
+}
+
+/// Renders sync progress as one indicatif bar per stage on stderr. Stages
+/// with a known total get a bounded bar with ETA; the rest get a spinner.
+pub struct SyncProgressBars {
+    multi: MultiProgress,
+    bars: Mutex<HashMap<StageKey, ProgressBar>>,
+}
+
+impl Default for SyncProgressBars {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SyncProgressBars {
+    pub fn new() -> Self {
+        Self {
+            multi: MultiProgress::with_draw_target(ProgressDrawTarget::stderr()),
+            bars: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn start_bar(
+        &self,
+        account_id: &str,
+        stage: &str,
+        folder: &Option<String>,
+        total: Option<u64>,
+    ) {
+        let bar = match total {
+            Some(total) => {
+                let bar = ProgressBar::new(total);
+                if let Ok(style) = ProgressStyle::with_template(BAR_TEMPLATE) {
+                    bar.set_style(style.progress_chars("=> "));
+                }
+                bar
+            }
+            None => {
+                let bar = ProgressBar::new_spinner();
+                if let Ok(style) = ProgressStyle::with_template(SPINNER_TEMPLATE) {
+                    bar.set_style(style);
+                }
+                bar.enable_steady_tick(Duration::from_millis(120));
+                bar
+            }
+        };
+        let prefix = match folder {
+            Some(folder) => format!("{account_id} {folder} {stage}"),
+            None => format!("{account_id} {stage}"),
+        };
+        bar.set_prefix(prefix);
+        let bar = self.multi.add(bar);
+        if let Ok(mut bars) = self.bars.lock() {
+            if let Some(previous) = bars.insert(stage_key(account_id, stage, folder), bar) {
+                previous.finish_and_clear();
+            }
+        }
+    }
+}
+
+impl ProgressReporter for SyncProgressBars {
+    fn report(&self, event: &SyncProgress) {
+        match event {
+            SyncProgress::StageStarted {
+                account_id,
+                stage,
+                folder,
+                total,
+            } => self.start_bar(account_id, stage, folder, *total),
+            SyncProgress::PageCompleted {
+                account_id,
+                stage,
+                folder,
+                processed,
+                total,
+                added,
+                updated,
+                errors,
+                ..
+            } => {
+                let bars = match self.bars.lock() {
+                    Ok(bars) => bars,
+                    Err(_) => return,
+                };
+                if let Some(bar) = bars.get(&stage_key(account_id, stage, folder)) {
+                    if let Some(total) = total {
+                        bar.set_length(*total.max(processed));
+                    }
+                    bar.set_position(*processed);
+                    bar.set_message(format!("added={added} updated={updated} errors={errors}"));
+                }
+            }
+            SyncProgress::StageFinished {
+                account_id,
+                stage,
+                folder,
+                added,
+                updated,
+                errors,
+            } => {
+                let bar = self
+                    .bars
+                    .lock()
+                    .ok()
+                    .and_then(|mut bars| bars.remove(&stage_key(account_id, stage, folder)));
+                if let Some(bar) = bar {
+                    bar.finish_with_message(format!(
+                        "done: added={added} updated={updated} errors={errors}"
+                    ));
+                }
+            }
+            SyncProgress::Notice { .. } => {
+                let _ = self.multi.println(describe(event));
+            }
+        }
+    }
+}
+
+/// Streams progress as newline-delimited JSON on stderr (stdout stays
+/// reserved for the command's own JSON output). Page events carry an
+/// `eta_secs` estimate when the stage total is known.
+#[derive(Default)]
+pub struct JsonProgressStream {
+    started: Mutex<HashMap<StageKey, Instant>>,
+}
+
+impl JsonProgressStream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn render(&self, event: &SyncProgress) -> serde_json::Value {
+        let mut value = serde_json::to_value(event).unwrap_or(serde_json::Value::Null);
+        let Ok(mut started) = self.started.lock() else {
+            return value;
+        };
+        match event {
+            SyncProgress::StageStarted {
+                account_id,
+                stage,
+                folder,
+                ..
+            } => {
+                started.insert(stage_key(account_id, stage, folder), Instant::now());
+            }
+            SyncProgress::PageCompleted {
+                account_id,
+                stage,
+                folder,
+                processed,
+                total,
+                ..
+            } => {
+                let eta = started
+                    .get(&stage_key(account_id, stage, folder))
+                    .and_then(|start| estimate_eta(start.elapsed(), *processed, *total));
+                if let (Some(eta), Some(object)) = (eta, value.as_object_mut()) {
+                    object.insert("eta_secs".to_string(), eta.as_secs().into());
+                }
+            }
+            SyncProgress::StageFinished {
+                account_id,
+                stage,
+                folder,
+                ..
+            } => {
+                started.remove(&stage_key(account_id, stage, folder));
+            }
+            SyncProgress::Notice { .. } => {}
+        }
+        value
+    }
+}
+
+impl ProgressReporter for JsonProgressStream {
+    fn report(&self, event: &SyncProgress) {
+        let line = self.render(event);
+        let mut stderr = std::io::stderr().lock();
+        let _ = writeln!(stderr, "{line}");
+    }
+}
+
+/// Linear extrapolation of the time left for a stage.
+pub fn estimate_eta(elapsed: Duration, processed: u64, total: Option<u64>) -> Option<Duration> {
+    let total = total?;
+    if processed == 0 || processed >= total {
+        return None;
+    }
+    let remaining = (total - processed) as f64;
+    Some(Duration::from_secs_f64(
+        elapsed.as_secs_f64() * remaining / processed as f64,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{estimate_eta, JsonProgressStream};
+    use crate::connectors::progress::SyncProgress;
+
+    #[test]
+    fn eta_extrapolates_linearly() {
+        assert_eq!(
+            estimate_eta(Duration::from_secs(10), 25, Some(100)),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(estimate_eta(Duration::from_secs(10), 0, Some(100)), None);
+        assert_eq!(estimate_eta(Duration::from_secs(10), 25, None), None);
+    }
+
+    #[test]
+    fn json_stream_tags_events() {
+        let stream = JsonProgressStream::new();
+        let started = stream.render(&SyncProgress::StageStarted {
+            account_id: "a@example.com".to_string(),
+            stage: "fetch_messages".to_string(),
+            folder: None,
+            total: Some(10),
+        });
+        assert_eq!(started["event"], "stage_started");
+
+        let page = stream.render(&SyncProgress::PageCompleted {
+            account_id: "a@example.com".to_string(),
+            stage: "fetch_messages".to_string(),
+            folder: None,
+            page: 1,
+            page_size: 5,
+            processed: 5,
+            total: Some(10),
+            added: 5,
+            updated: 0,
+            errors: 0,
+        });
+        assert_eq!(page["event"], "page_completed");
+        assert!(page.get("eta_secs").is_some());
+    }
+}
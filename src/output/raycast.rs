@@ -0,0 +1,97 @@
+//! Launcher output for `ess search --format raycast`.
+//!
+//! Raycast and Alfred script filters both read `{"items": [...]}` where each
+//! item has a `title`, a `subtitle` and an `arg` handed to the next action.
+//! The `arg` is the email id, so an action can run `ess show <arg>`.
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::output::SearchResultItem;
+
+#[derive(Debug, Serialize)]
+struct Items<'a> {
+    items: Vec<Item<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct Item<'a> {
+    uid: &'a str,
+    title: &'a str,
+    subtitle: String,
+    arg: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    quicklookurl: Option<&'a str>,
+}
+
+pub fn format_search_results(results: &[SearchResultItem]) -> Result<String> {
+    let items = results
+        .iter()
+        .map(|result| {
+            let email = &result.email;
+            let from = email
+                .from_name
+                .as_deref()
+                .or(email.from_address.as_deref())
+                .unwrap_or("(unknown)");
+            let date = email.received_at.get(..10).unwrap_or(&email.received_at);
+            let subtitle = match email.folder.as_deref() {
+                Some(folder) => format!("{from} · {date} · {folder}"),
+                None => format!("{from} · {date}"),
+            };
+            Item {
+                uid: &email.id,
+                title: email.subject.as_deref().unwrap_or("(no subject)"),
+                subtitle,
+                arg: &email.id,
+                quicklookurl: email.web_link.as_deref(),
+            }
+        })
+        .collect();
+    Ok(serde_json::to_string(&Items { items })?)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::format_search_results;
+    use crate::db::models::Email;
+    use crate::output::SearchResultItem;
+
+    #[test]
+    fn one_item_per_hit_with_email_id_as_arg() {
+        let email = Email {
+            account_id: Some("acc".to_string()),
+            subject: Some("Q3 budget".to_string()),
+            from_address: Some("alice@example.com".to_string()),
+            from_name: Some("Alice".to_string()),
+            received_at: "2026-02-01T10:00:00Z".to_string(),
+            folder: Some("Inbox".to_string()),
+            ..Email::fixture("e1")
+        };
+        let mut no_subject = email.clone();
+        no_subject.id = "e2".to_string();
+        no_subject.subject = None;
+        no_subject.folder = None;
+        no_subject.web_link = Some("https://mail.example.com/e2".to_string());
+        let results = [email, no_subject].map(|email| SearchResultItem {
+            email,
+            score: Some(1.0),
+            snippet: None,
+            preview: None,
+        });
+
+        let formatted: serde_json::Value =
+            serde_json::from_str(&format_search_results(&results).expect("format"))
+                .expect("valid json");
+        assert_eq!(
+            formatted,
+            json!({"items": [
+                {"uid": "e1", "title": "Q3 budget", "subtitle": "Alice · 2026-02-01 · Inbox", "arg": "e1"},
+                {"uid": "e2", "title": "(no subject)", "subtitle": "Alice · 2026-02-01", "arg": "e2",
+                 "quicklookurl": "https://mail.example.com/e2"}
+            ]})
+        );
+    }
+}
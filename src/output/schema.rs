@@ -0,0 +1,663 @@
+//! JSON shapes of the records the CLI (`--json`) and the MCP tools emit,
+//! described as JSON Schema for `ess schema` and as OpenAPI components for
+//! `ess schema --openapi`. The OpenAPI document also describes the HTTP
+//! endpoints of `ess mcp --http` and `ess sync --watch --listen`, which
+//! serve it at `/openapi.json`.
+//!
+//! The descriptions are written out here rather than derived; the tests
+//! serialize each model and fail when a field is added, renamed or removed
+//! without updating its description.
+
+use serde_json::{json, Map, Value};
+
+use crate::daemon::HTTP_PATH as CLI_PATH;
+use crate::mcp::http::{COMPLETE_ADDRESS_PATH, MCP_PATH};
+
+pub const JSON_SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+/// 3.1 is the first OpenAPI version whose schemas are plain JSON Schema
+/// 2020-12, so the same model schemas serve both documents.
+pub const OPENAPI_VERSION: &str = "3.1.0";
+
+/// Where both HTTP servers serve [`openapi_document`].
+pub const OPENAPI_PATH: &str = "/openapi.json";
+
+const DEFS_PREFIX: &str = "#/$defs/";
+const COMPONENTS_PREFIX: &str = "#/components/schemas/";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    String,
+    /// RFC 3339 timestamp.
+    DateTime,
+    Integer,
+    Number,
+    Boolean,
+    /// Arbitrary JSON.
+    Any,
+    Enum(&'static [&'static str]),
+    Array(&'static FieldKind),
+    /// Another model in this catalog.
+    Model(&'static str),
+}
+
+impl FieldKind {
+    /// Short type name for the table listing.
+    pub fn label(self) -> String {
+        match self {
+            Self::String => "string".to_string(),
+            Self::DateTime => "string (date-time)".to_string(),
+            Self::Integer => "integer".to_string(),
+            Self::Number => "number".to_string(),
+            Self::Boolean => "boolean".to_string(),
+            Self::Any => "any".to_string(),
+            Self::Enum(values) => values.join(" | "),
+            Self::Array(item) => format!("{}[]", item.label()),
+            Self::Model(name) => name.to_string(),
+        }
+    }
+
+    fn json_schema(self, ref_prefix: &str) -> Value {
+        match self {
+            Self::String => json!({ "type": "string" }),
+            Self::DateTime => json!({ "type": "string", "format": "date-time" }),
+            Self::Integer => json!({ "type": "integer" }),
+            Self::Number => json!({ "type": "number" }),
+            Self::Boolean => json!({ "type": "boolean" }),
+            Self::Any => json!({}),
+            Self::Enum(values) => json!({ "type": "string", "enum": values }),
+            Self::Array(item) => json!({ "type": "array", "items": item.json_schema(ref_prefix) }),
+            Self::Model(name) => json!({ "$ref": format!("{ref_prefix}{name}") }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FieldSpec {
+    pub name: &'static str,
+    pub kind: FieldKind,
+    /// The value may be `null`.
+    pub nullable: bool,
+    /// The key is always present; optional keys are left out when empty.
+    pub required: bool,
+    pub description: &'static str,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ModelSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub fields: &'static [FieldSpec],
+}
+
+const fn field(
+    name: &'static str,
+    kind: FieldKind,
+    nullable: bool,
+    description: &'static str,
+) -> FieldSpec {
+    FieldSpec {
+        name,
+        kind,
+        nullable,
+        required: true,
+        description,
+    }
+}
+
+const fn omitted_when_empty(
+    name: &'static str,
+    kind: FieldKind,
+    description: &'static str,
+) -> FieldSpec {
+    FieldSpec {
+        name,
+        kind,
+        nullable: false,
+        required: false,
+        description,
+    }
+}
+
+const STRINGS: FieldKind = FieldKind::Array(&FieldKind::String);
+
+pub const MODELS: &[ModelSpec] = &[
+    ModelSpec {
+        name: "Email",
+        description: "A stored message: `ess show`, `ess thread`, `ess list`, the `email` of each search result and the MCP `ess_get_email`/`ess_get_thread` tools.",
+        fields: &[
+            field("id", FieldKind::String, false, "Provider message id; the key for `ess show`, `ess mark` and friends."),
+            field("internet_message_id", FieldKind::String, true, "RFC 2822 Message-ID header."),
+            field("in_reply_to", FieldKind::String, true, "Message-ID of the message this one answers (`In-Reply-To`)."),
+            field("references", STRINGS, false, "Message-IDs of the thread so far, oldest first (`References`)."),
+            field("conversation_id", FieldKind::String, true, "Thread id, after any `ess thread merge`/`split` correction."),
+            field("account_id", FieldKind::String, true, "Account the message was synced or imported into."),
+            field("subject", FieldKind::String, true, "Subject line."),
+            field("from_address", FieldKind::String, true, "Sender address."),
+            field("from_name", FieldKind::String, true, "Sender display name."),
+            field("to_addresses", STRINGS, false, "To recipients."),
+            field("cc_addresses", STRINGS, false, "Cc recipients."),
+            field("bcc_addresses", STRINGS, false, "Bcc recipients (sent mail only)."),
+            field("body_text", FieldKind::String, true, "Plain-text body, converted from HTML when needed."),
+            field("body_html", FieldKind::String, true, "HTML body as received."),
+            field("body_preview", FieldKind::String, true, "Provider preview or the start of the body."),
+            field("received_at", FieldKind::DateTime, false, "When the message arrived."),
+            field("sent_at", FieldKind::DateTime, true, "Date header of the message."),
+            field("importance", FieldKind::String, true, "`low`, `normal` or `high`."),
+            field("is_read", FieldKind::Boolean, true, "Read state at the provider."),
+            field("has_attachments", FieldKind::Boolean, true, "Whether the message has attachments."),
+            field("folder", FieldKind::String, true, "Normalized folder, e.g. `inbox`, `sent`, `archive`."),
+            field("categories", STRINGS, false, "Outlook categories or Gmail user labels."),
+            field("flag_status", FieldKind::String, true, "`flagged`, `notFlagged` or `complete`."),
+            field("web_link", FieldKind::String, true, "Link to the message in the provider's web client."),
+            field("metadata", FieldKind::Any, true, "Connector details such as `connector` and `source`."),
+        ],
+    },
+    ModelSpec {
+        name: "SearchResult",
+        description: "One hit from `ess search --json`, `ess starred --json` and `ess collections list <name> --json`.",
+        fields: &[
+            field("email", FieldKind::Model("Email"), false, "The matching email."),
+            omitted_when_empty("score", FieldKind::Number, "Relevance score; absent for lists that are not ranked."),
+            omitted_when_empty("snippet", FieldKind::Model("Snippet"), "Body excerpt around the match."),
+            omitted_when_empty("preview", FieldKind::Model("Preview"), "Stored title and text excerpt, in JSON list and search output."),
+        ],
+    },
+    ModelSpec {
+        name: "Preview",
+        description: "A cleaned title and plain-text excerpt, computed when the email is stored.",
+        fields: &[
+            field("title", FieldKind::String, false, "Subject without `Re:`/`Fwd:` prefixes, or `(no subject)`."),
+            field("text", FieldKind::String, false, "Up to 500 characters of body text, without quoted replies or signature."),
+        ],
+    },
+    ModelSpec {
+        name: "Snippet",
+        description: "A body excerpt with the parts that matched the query.",
+        fields: &[
+            field("text", FieldKind::String, false, "The excerpt."),
+            field("html", FieldKind::String, false, "The excerpt, HTML-escaped, with matches wrapped in `<em>`."),
+            field("highlights", FieldKind::Array(&FieldKind::Model("ByteRange")), false, "Matched ranges of `text`."),
+        ],
+    },
+    ModelSpec {
+        name: "ByteRange",
+        description: "A half-open byte range into a string.",
+        fields: &[
+            field("start", FieldKind::Integer, false, "First byte."),
+            field("end", FieldKind::Integer, false, "One past the last byte."),
+        ],
+    },
+    ModelSpec {
+        name: "Contact",
+        description: "A correspondent aggregated from synced mail: `ess contacts --json` and the MCP `ess_get_contacts` tool.",
+        fields: &[
+            field("email_address", FieldKind::String, false, "Lowercased address."),
+            field("display_name", FieldKind::String, true, "Most recently seen display name."),
+            field("company", FieldKind::String, true, "Company, when enriched."),
+            field("phone", FieldKind::String, true, "Phone number from the contact's signature, when enriched."),
+            field("attio_person_id", FieldKind::String, true, "Attio person record, when enriched."),
+            field("attio_company_id", FieldKind::String, true, "Attio company record, when enriched."),
+            field("message_count", FieldKind::Integer, false, "Messages from this address."),
+            field("first_seen", FieldKind::DateTime, true, "Oldest message from this address."),
+            field("last_seen", FieldKind::DateTime, true, "Newest message from this address."),
+        ],
+    },
+    ModelSpec {
+        name: "Account",
+        description: "A configured mail account, as registered with `ess accounts add`.",
+        fields: &[
+            field("account_id", FieldKind::String, false, "Lowercased account address; the key for `--account`."),
+            field("email_address", FieldKind::String, false, "Address as entered."),
+            field("display_name", FieldKind::String, true, "Display name."),
+            field("tenant_id", FieldKind::String, true, "Microsoft Entra tenant, for Graph accounts."),
+            field("account_type", FieldKind::Enum(&["professional", "personal"]), false, "Scope the account belongs to."),
+            field("enabled", FieldKind::Boolean, false, "Whether `ess sync` includes the account."),
+            field("last_sync", FieldKind::DateTime, true, "End of the last successful sync."),
+            field("config", FieldKind::Any, true, "Connector settings; may hold credentials."),
+        ],
+    },
+];
+
+/// Looks a model up by name, ignoring case.
+pub fn model(name: &str) -> Option<&'static ModelSpec> {
+    MODELS
+        .iter()
+        .find(|model| model.name.eq_ignore_ascii_case(name.trim()))
+}
+
+/// A JSON Schema document with every model under `$defs`; with `root`,
+/// the document validates that model.
+pub fn json_schema(root: Option<&ModelSpec>) -> Value {
+    let mut document = json!({
+        "$schema": JSON_SCHEMA_DIALECT,
+        "title": "ESS JSON output",
+        "$defs": model_schemas(DEFS_PREFIX),
+    });
+    if let Some(model) = root {
+        document["$ref"] = json!(format!("{DEFS_PREFIX}{}", model.name));
+    }
+    document
+}
+
+/// An OpenAPI document with every model under `components.schemas`, for
+/// generating client types, and the HTTP endpoints under `paths`. One
+/// server only answers some of them: `/v1/cli` is `ess sync --watch
+/// --listen`, `/mcp` and `/complete-address` are `ess mcp --http`.
+pub fn openapi_document() -> Value {
+    let mut schemas = model_schemas(COMPONENTS_PREFIX);
+    schemas.extend(http_schemas());
+    json!({
+        "openapi": OPENAPI_VERSION,
+        "info": {
+            "title": "ESS",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Records emitted by `ess --json` and the ESS MCP tools, and the HTTP endpoints of `ess mcp --http` and `ess sync --watch --listen`.",
+        },
+        "jsonSchemaDialect": JSON_SCHEMA_DIALECT,
+        // The token is required off loopback only.
+        "security": [{}, { "bearer": [] }],
+        "paths": {
+            CLI_PATH: {
+                "post": {
+                    "summary": "Run an `ess` command on the server",
+                    "description": "Served by `ess sync --watch --listen`. Runs `ess search`, `ess list` and the other read commands against the server's store and returns their output.",
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": component("CliRequest") } },
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "The command's outcome.",
+                            "content": { "application/json": { "schema": component("CliResponse") } },
+                        },
+                        "401": { "description": "Missing or wrong bearer token." },
+                        "403": { "description": "Cross-origin request without a token configured." },
+                        "415": { "description": "The body is not sent as `application/json`." },
+                    },
+                },
+            },
+            MCP_PATH: {
+                "post": {
+                    "summary": "Send MCP JSON-RPC messages",
+                    "description": "Served by `ess mcp --http` (Streamable HTTP). `initialize` returns the session id in `Mcp-Session-Id`; later requests may send it back.",
+                    "parameters": [session_header(false)],
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": component("JsonRpcPayload") } },
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "The responses, as JSON or as one `message` event when the client accepts only SSE.",
+                            "content": {
+                                "application/json": { "schema": component("JsonRpcPayload") },
+                                "text/event-stream": { "schema": { "type": "string" } },
+                            },
+                        },
+                        "202": { "description": "The payload held only notifications." },
+                        "401": { "description": "Missing or wrong bearer token." },
+                        "403": { "description": "Cross-origin request without a token configured." },
+                        "404": { "description": "Unknown, ended or expired session." },
+                        "406": { "description": "Accepts neither JSON nor SSE." },
+                        "415": { "description": "The body is not sent as `application/json`." },
+                    },
+                },
+                "get": {
+                    "summary": "Server-to-client stream (not offered)",
+                    "responses": { "405": { "description": "ESS sends no server-initiated messages." } },
+                },
+                "delete": {
+                    "summary": "End an MCP session",
+                    "parameters": [session_header(true)],
+                    "responses": {
+                        "204": { "description": "The session ended." },
+                        "400": { "description": "No `Mcp-Session-Id` header." },
+                    },
+                },
+            },
+            COMPLETE_ADDRESS_PATH: {
+                "get": {
+                    "summary": "Complete a partly typed address",
+                    "description": "Served by `ess mcp --http`. The `ess_complete_address` tool without JSON-RPC.",
+                    "parameters": [
+                        { "name": "q", "in": "query", "required": true, "schema": { "type": "string" }, "description": "The typed fragment." },
+                        { "name": "limit", "in": "query", "required": false, "schema": { "type": "integer", "minimum": 1, "default": 10 } },
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "Matching contacts, best first.",
+                            "content": { "application/json": { "schema": {
+                                "type": "array",
+                                "items": component("AddressMatch"),
+                            } } },
+                        },
+                        "400": { "description": "`limit` is zero." },
+                        "401": { "description": "Missing or wrong bearer token." },
+                    },
+                },
+            },
+            OPENAPI_PATH: {
+                "get": {
+                    "summary": "This document",
+                    "security": [],
+                    "responses": { "200": { "description": "The OpenAPI document.", "content": { "application/json": {} } } },
+                },
+            },
+        },
+        "components": {
+            "schemas": schemas,
+            "securitySchemes": { "bearer": { "type": "http", "scheme": "bearer" } },
+        },
+    })
+}
+
+fn component(name: &str) -> Value {
+    json!({ "$ref": format!("{COMPONENTS_PREFIX}{name}") })
+}
+
+fn session_header(required: bool) -> Value {
+    json!({
+        "name": "Mcp-Session-Id",
+        "in": "header",
+        "required": required,
+        "schema": { "type": "string" },
+    })
+}
+
+/// Request and response bodies of the HTTP endpoints, which are not
+/// `--json` output and so not in [`MODELS`].
+fn http_schemas() -> Map<String, Value> {
+    let object = |description: &str, properties: Value| {
+        let required: Vec<_> = properties
+            .as_object()
+            .map(|properties| properties.keys().cloned().collect())
+            .unwrap_or_default();
+        json!({
+            "type": "object",
+            "description": description,
+            "properties": properties,
+            "required": required,
+            "additionalProperties": false,
+        })
+    };
+    let status = |value: &str| json!({ "type": "string", "const": value });
+    [
+        (
+            "CliRequest",
+            object(
+                "A command line for `POST /v1/cli`.",
+                json!({
+                    "version": { "type": "string", "description": "The client's ESS version; a server of another version declines." },
+                    "args": { "type": "array", "items": { "type": "string" }, "description": "The arguments after `ess`." },
+                    "style": {
+                        "type": "object",
+                        "description": "How the client's terminal wants tables drawn.",
+                        "properties": {
+                            "width": { "anyOf": [{ "type": "integer" }, { "type": "null" }], "description": "Terminal columns; `null` keeps the fixed layout." },
+                            "color": { "type": "boolean", "description": "ANSI styling." },
+                        },
+                        "required": ["width", "color"],
+                        "additionalProperties": false,
+                    },
+                }),
+            ),
+        ),
+        (
+            "CliResponse",
+            json!({
+                "description": "What the server made of a `POST /v1/cli` request.",
+                "oneOf": [
+                    object("The command ran.", json!({
+                        "status": status("done"),
+                        "output": { "type": "string", "description": "Its standard output." },
+                    })),
+                    object("The command failed.", json!({
+                        "status": status("failed"),
+                        "output": { "type": "string", "description": "What it wrote before failing." },
+                        "error": { "type": "string", "description": "The error it failed with." },
+                    })),
+                    object("The server does not run this command; the client runs it itself.", json!({
+                        "status": status("declined"),
+                        "reason": { "type": "string" },
+                    })),
+                ],
+            }),
+        ),
+        (
+            "JsonRpcPayload",
+            json!({
+                "description": "A JSON-RPC 2.0 message or a batch of them.",
+                "oneOf": [
+                    { "type": "object", "properties": { "jsonrpc": { "const": "2.0" } }, "required": ["jsonrpc"] },
+                    { "type": "array", "items": { "type": "object", "properties": { "jsonrpc": { "const": "2.0" } }, "required": ["jsonrpc"] } },
+                ],
+            }),
+        ),
+        (
+            "AddressMatch",
+            object(
+                "A contact offered for a partly typed address.",
+                json!({
+                    "email_address": { "type": "string" },
+                    "display_name": { "anyOf": [{ "type": "string" }, { "type": "null" }] },
+                    "formatted": { "type": "string", "description": "`Name <address>`, ready for a To or Cc line." },
+                    "message_count": { "type": "integer" },
+                    "last_seen": { "anyOf": [{ "type": "string", "format": "date-time" }, { "type": "null" }] },
+                    "score": { "type": "number" },
+                }),
+            ),
+        ),
+    ]
+    .into_iter()
+    .map(|(name, schema)| (name.to_string(), schema))
+    .collect()
+}
+
+fn model_schemas(ref_prefix: &str) -> Map<String, Value> {
+    MODELS
+        .iter()
+        .map(|model| (model.name.to_string(), model_schema(model, ref_prefix)))
+        .collect()
+}
+
+fn model_schema(model: &ModelSpec, ref_prefix: &str) -> Value {
+    let properties = model
+        .fields
+        .iter()
+        .map(|field| {
+            let mut schema = field.kind.json_schema(ref_prefix);
+            if field.nullable {
+                schema = json!({ "anyOf": [schema, { "type": "null" }] });
+            }
+            schema["description"] = json!(field.description);
+            (field.name.to_string(), schema)
+        })
+        .collect::<Map<_, _>>();
+    let required = model
+        .fields
+        .iter()
+        .filter(|field| field.required)
+        .map(|field| field.name)
+        .collect::<Vec<_>>();
+    json!({
+        "type": "object",
+        "description": model.description,
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use serde_json::Value;
+
+    use super::{json_schema, model, openapi_document, MODELS, OPENAPI_PATH};
+    use crate::contacts::AddressMatch;
+    use crate::daemon::{ClientRequest, ClientResponse, HTTP_PATH};
+    use crate::db::models::{Account, AccountType, Contact, Email};
+    use crate::db::Preview;
+    use crate::mcp::http::{COMPLETE_ADDRESS_PATH, MCP_PATH};
+    use crate::output::SearchResultItem;
+    use crate::output::TableStyle;
+    use crate::search::Snippet;
+
+    fn keys(value: &Value) -> BTreeSet<String> {
+        value.as_object().expect("object").keys().cloned().collect()
+    }
+
+    fn described(name: &str) -> BTreeSet<String> {
+        model(name)
+            .expect("model described")
+            .fields
+            .iter()
+            .map(|field| field.name.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn descriptions_match_serialized_models() {
+        let email = Email::fixture("msg-1");
+        let result = serde_json::to_value(SearchResultItem {
+            email: email.clone(),
+            score: Some(1.0),
+            snippet: Some(Snippet {
+                text: "hello".to_string(),
+                highlights: std::iter::once(0..5).collect(),
+            }),
+            preview: Some(Preview {
+                title: "Hello".to_string(),
+                text: "hello".to_string(),
+            }),
+        })
+        .expect("serialize result");
+        let contact = Contact {
+            email_address: "a@example.com".to_string(),
+            display_name: None,
+            company: None,
+            phone: None,
+            attio_person_id: None,
+            attio_company_id: None,
+            message_count: 1,
+            first_seen: None,
+            last_seen: None,
+        };
+        let account = Account {
+            account_id: "a@example.com".to_string(),
+            email_address: "a@example.com".to_string(),
+            display_name: None,
+            tenant_id: None,
+            account_type: AccountType::Personal,
+            enabled: true,
+            last_sync: None,
+            config: None,
+        };
+
+        let serialized = [
+            (
+                "Email",
+                serde_json::to_value(&email).expect("serialize email"),
+            ),
+            ("SearchResult", result.clone()),
+            ("Snippet", result["snippet"].clone()),
+            ("Preview", result["preview"].clone()),
+            ("ByteRange", result["snippet"]["highlights"][0].clone()),
+            (
+                "Contact",
+                serde_json::to_value(&contact).expect("serialize contact"),
+            ),
+            (
+                "Account",
+                serde_json::to_value(&account).expect("serialize account"),
+            ),
+        ];
+        assert_eq!(serialized.len(), MODELS.len());
+        for (name, value) in serialized {
+            assert_eq!(keys(&value), described(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn schema_document_references_resolve() {
+        let document = json_schema(model("email"));
+        assert_eq!(document["$ref"], "#/$defs/Email");
+        let defs = document["$defs"].as_object().expect("defs");
+        let text = document.to_string();
+        for reference in text.split("\"#/$defs/").skip(1) {
+            let name = reference.split('"').next().expect("reference name");
+            assert!(defs.contains_key(name), "dangling reference to {name}");
+        }
+        assert_eq!(
+            document["$defs"]["Email"]["properties"]["subject"]["anyOf"][1]["type"],
+            "null"
+        );
+    }
+
+    #[test]
+    fn openapi_components_reference_each_other() {
+        let document = openapi_document();
+        assert_eq!(document["openapi"], "3.1.0");
+        let result = &document["components"]["schemas"]["SearchResult"];
+        assert_eq!(
+            result["properties"]["email"]["$ref"],
+            "#/components/schemas/Email"
+        );
+        assert!(!document.to_string().contains("#/$defs/"));
+    }
+
+    #[test]
+    fn openapi_paths_describe_the_http_endpoints() {
+        let document = openapi_document();
+        let paths = keys(&document["paths"]);
+        for path in [HTTP_PATH, MCP_PATH, COMPLETE_ADDRESS_PATH, OPENAPI_PATH] {
+            assert!(paths.contains(path), "{path} is described");
+        }
+        let schemas = &document["components"]["schemas"];
+        let text = document.to_string();
+        for reference in text.split("\"#/components/schemas/").skip(1) {
+            let name = reference.split('"').next().expect("reference name");
+            assert!(schemas.get(name).is_some(), "dangling reference to {name}");
+        }
+
+        let properties = |schema: &Value| keys(&schema["properties"]);
+        let request = serde_json::to_value(ClientRequest::new(vec![], TableStyle::plain()))
+            .expect("serialize request");
+        assert_eq!(keys(&request), properties(&schemas["CliRequest"]));
+        assert_eq!(
+            keys(&request["style"]),
+            properties(&schemas["CliRequest"]["properties"]["style"])
+        );
+        let responses = [
+            ClientResponse::Done {
+                output: String::new(),
+            },
+            ClientResponse::Failed {
+                output: String::new(),
+                error: String::new(),
+            },
+            ClientResponse::Declined {
+                reason: String::new(),
+            },
+        ];
+        for (response, schema) in responses.iter().zip(
+            schemas["CliResponse"]["oneOf"]
+                .as_array()
+                .expect("variants"),
+        ) {
+            let value = serde_json::to_value(response).expect("serialize response");
+            assert_eq!(value["status"], schema["properties"]["status"]["const"]);
+            assert_eq!(keys(&value), properties(schema));
+        }
+        let address = serde_json::to_value(AddressMatch {
+            email_address: "a@example.com".to_string(),
+            display_name: None,
+            formatted: "a@example.com".to_string(),
+            message_count: 1,
+            last_seen: None,
+            score: 1.0,
+        })
+        .expect("serialize match");
+        assert_eq!(keys(&address), properties(&schemas["AddressMatch"]));
+    }
+}
@@ -1,76 +1,365 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
 use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 use crate::db::models::{Contact, Email};
-use crate::db::DatabaseStats;
-use crate::output::SearchResultItem;
+use crate::db::{
+    Analytics, AnalyticsInterval, AttachmentVersion, CollectionSummary, ConversationSummary,
+    DatabaseStats, FolderDiscovery, FolderEmailCount, Followup, Label, MailingList, MeetingLink,
+    Note, QuarantinedMessage, StatsTrend, SyncUsage,
+};
+use crate::env::EnvStatus;
+use crate::output::{ScopeComparisonItem, SearchResultItem, ThreadResultItem};
+use crate::search::{FacetCounts, Snippet};
+use crate::trim::TrimReport;
 
 const FROM_WIDTH: usize = 24;
 const SUBJECT_WIDTH: usize = 56;
 const DATE_WIDTH: usize = 12;
 const SCORE_WIDTH: usize = 7;
+const MESSAGES_WIDTH: usize = 4;
 
-pub fn format_search_results(results: &[SearchResultItem]) -> String {
+/// Narrowest From and Subject columns on a small terminal, and the widest
+/// a Subject column grows to on a large one.
+const MIN_FROM_WIDTH: usize = 10;
+const MIN_SUBJECT_WIDTH: usize = 16;
+const MAX_SUBJECT_WIDTH: usize = 2 * SUBJECT_WIDTH;
+
+const COMPARE_COLUMN_WIDTH: usize = 48;
+
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const NORMAL_INTENSITY: &str = "\x1b[22m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const DEFAULT_COLOR: &str = "\x1b[39m";
+
+/// Layout and styling of the result tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableStyle {
+    /// Terminal columns to fit; the From and Subject columns shrink or grow
+    /// to fill them. `None` keeps the fixed layout, as for piped output.
+    pub width: Option<usize>,
+    /// ANSI styling: unread rows and snippet matches in bold, scores
+    /// colored by how close they are to the best match.
+    pub color: bool,
+}
+
+impl TableStyle {
+    pub const fn plain() -> Self {
+        Self {
+            width: None,
+            color: false,
+        }
+    }
+
+    /// From and Subject widths for rows whose other columns, separators
+    /// included, take `fixed` columns.
+    fn text_columns(self, fixed: usize) -> (usize, usize) {
+        let Some(width) = self.width else {
+            return (FROM_WIDTH, SUBJECT_WIDTH);
+        };
+        let available = width
+            .saturating_sub(fixed)
+            .max(MIN_FROM_WIDTH + MIN_SUBJECT_WIDTH);
+        let from = (available * FROM_WIDTH / (FROM_WIDTH + SUBJECT_WIDTH))
+            .clamp(MIN_FROM_WIDTH, FROM_WIDTH);
+        (from, (available - from).min(MAX_SUBJECT_WIDTH))
+    }
+}
+
+/// Renders the result table; each row with a snippet is followed by an
+/// indented excerpt line. With `style.color`, unread rows and query matches
+/// are bold and scores are colored.
+pub fn format_search_results(results: &[SearchResultItem], style: &TableStyle) -> String {
     if results.is_empty() {
         return "No emails found.".to_string();
     }
 
+    let (from_width, subject_width) = style.text_columns(DATE_WIDTH + SCORE_WIDTH + 6);
+    let best_score = best_score(results.iter());
     let mut out = String::new();
     out.push_str(&format!(
-        "{:<from$}  {:<subject$}  {:<date$}  {:>score$}\n",
-        "From",
-        "Subject",
-        "Date",
+        "{}  {}  {}  {:>score$}\n",
+        pad("From", from_width),
+        pad("Subject", subject_width),
+        pad("Date", DATE_WIDTH),
         "Score",
-        from = FROM_WIDTH,
-        subject = SUBJECT_WIDTH,
-        date = DATE_WIDTH,
         score = SCORE_WIDTH
     ));
     out.push_str(&format!(
         "{}  {}  {}  {}\n",
-        "-".repeat(FROM_WIDTH),
-        "-".repeat(SUBJECT_WIDTH),
+        "-".repeat(from_width),
+        "-".repeat(subject_width),
         "-".repeat(DATE_WIDTH),
         "-".repeat(SCORE_WIDTH)
     ));
 
     for item in results {
-        let from = truncate_for_width(
-            item.email
-                .from_name
-                .as_deref()
-                .or(item.email.from_address.as_deref())
-                .unwrap_or("(unknown)"),
-            FROM_WIDTH,
-        );
-        let subject = truncate_for_width(
-            item.email.subject.as_deref().unwrap_or("(no subject)"),
-            SUBJECT_WIDTH,
+        let columns = format!(
+            "{}  {}  {}",
+            pad(&sender(&item.email), from_width),
+            pad(
+                item.email.subject.as_deref().unwrap_or("(no subject)"),
+                subject_width
+            ),
+            pad(&relative_date(&item.email.received_at), DATE_WIDTH),
         );
-        let date = truncate_for_width(&relative_date(&item.email.received_at), DATE_WIDTH);
-        let score = item
-            .score
+        out.push_str(&row(&item.email, columns, item.score, best_score, style));
+
+        if let Some(snippet) = &item.snippet {
+            out.push_str(&snippet_line(snippet, style.color));
+        }
+    }
+
+    out
+}
+
+fn sender(email: &Email) -> String {
+    email
+        .from_name
+        .as_deref()
+        .or(email.from_address.as_deref())
+        .unwrap_or("(unknown)")
+        .to_string()
+}
+
+fn best_score<'a>(results: impl Iterator<Item = &'a SearchResultItem>) -> Option<f32> {
+    results
+        .filter_map(|item| item.score)
+        .fold(None, |best, score| {
+            Some(best.map_or(score, |b: f32| b.max(score)))
+        })
+}
+
+/// A result row: the text columns, then the score. Unread emails are bold;
+/// scores are green near the best match, yellow in the middle and dim below.
+fn row(
+    email: &Email,
+    columns: String,
+    score: Option<f32>,
+    best: Option<f32>,
+    style: &TableStyle,
+) -> String {
+    let score_text = format!(
+        "{:>width$}",
+        score
             .map(|v| format!("{v:.2}"))
-            .unwrap_or_else(|| "-".to_string());
+            .unwrap_or_else(|| "-".to_string()),
+        width = SCORE_WIDTH
+    );
+    if !style.color {
+        return format!("{columns}  {score_text}\n");
+    }
+    let columns = if email.is_read == Some(false) {
+        format!("{BOLD}{columns}{NORMAL_INTENSITY}")
+    } else {
+        columns
+    };
+    let score_text = match (score, best) {
+        (Some(score), Some(best)) if best > 0.0 => {
+            let (on, off) = match score / best {
+                share if share >= 0.75 => (GREEN, DEFAULT_COLOR),
+                share if share >= 0.4 => (YELLOW, DEFAULT_COLOR),
+                _ => (DIM, NORMAL_INTENSITY),
+            };
+            format!("{on}{score_text}{off}")
+        }
+        _ => score_text,
+    };
+    format!("{columns}  {score_text}\n")
+}
 
+/// One row per conversation: the best hit's sender, subject, date and score
+/// plus the thread's message count, then its participants and the snippet.
+pub fn format_thread_results(threads: &[ThreadResultItem], style: &TableStyle) -> String {
+    if threads.is_empty() {
+        return "No emails found.".to_string();
+    }
+
+    let (from_width, subject_width) =
+        style.text_columns(MESSAGES_WIDTH + DATE_WIDTH + SCORE_WIDTH + 8);
+    let best_score = best_score(threads.iter().map(|thread| &thread.best));
+    let mut out = String::new();
+    out.push_str(&format!(
+        "{}  {}  {:>messages$}  {}  {:>score$}\n",
+        pad("From", from_width),
+        pad("Subject", subject_width),
+        "Msgs",
+        pad("Date", DATE_WIDTH),
+        "Score",
+        messages = MESSAGES_WIDTH,
+        score = SCORE_WIDTH
+    ));
+    out.push_str(&format!(
+        "{}  {}  {}  {}  {}\n",
+        "-".repeat(from_width),
+        "-".repeat(subject_width),
+        "-".repeat(MESSAGES_WIDTH),
+        "-".repeat(DATE_WIDTH),
+        "-".repeat(SCORE_WIDTH)
+    ));
+
+    for thread in threads {
+        let email = &thread.best.email;
+        let columns = format!(
+            "{}  {}  {:>messages$}  {}",
+            pad(&sender(email), from_width),
+            pad(
+                email.subject.as_deref().unwrap_or("(no subject)"),
+                subject_width
+            ),
+            thread.messages,
+            pad(&relative_date(&email.received_at), DATE_WIDTH),
+            messages = MESSAGES_WIDTH,
+        );
+        out.push_str(&row(email, columns, thread.best.score, best_score, style));
+        if !thread.participants.is_empty() {
+            out.push_str(&format!("    With: {}\n", thread.participants.join(", ")));
+        }
+        if let Some(snippet) = &thread.best.snippet {
+            out.push_str(&snippet_line(snippet, style.color));
+        }
+    }
+
+    out
+}
+
+/// The indented excerpt under a result row, on one line.
+fn snippet_line(snippet: &Snippet, emphasis: bool) -> String {
+    let excerpt = if emphasis {
+        snippet.render(BOLD, NORMAL_INTENSITY)
+    } else {
+        snippet.text.clone()
+    };
+    let excerpt = excerpt.split_whitespace().collect::<Vec<_>>().join(" ");
+    format!("    {excerpt}\n")
+}
+
+pub fn format_facets(facets: &[FacetCounts]) -> String {
+    let mut out = String::new();
+    for facet in facets {
+        out.push_str(&format!("By {}:\n", facet.facet));
+        if facet.counts.is_empty() {
+            out.push_str("  (none)\n");
+        }
+        for entry in &facet.counts {
+            let value = if entry.value.is_empty() {
+                "(none)"
+            } else {
+                entry.value.as_str()
+            };
+            out.push_str(&format!(
+                "  {:<width$}  {:>6}\n",
+                truncate_for_width(value, FROM_WIDTH + 8),
+                entry.count,
+                width = FROM_WIDTH + 8
+            ));
+        }
+    }
+    out
+}
+
+/// Renders the scopes as columns: match count, date span, then one sample
+/// per row, followed by the share of matches in the busiest scope.
+pub fn format_scope_comparison(scopes: &[ScopeComparisonItem]) -> String {
+    let total: i64 = scopes.iter().map(|scope| scope.total).sum();
+    if total == 0 {
+        return "No emails found in any scope.".to_string();
+    }
+
+    let mut rows: Vec<Vec<String>> = vec![
+        scopes
+            .iter()
+            .map(|scope| {
+                let noun = if scope.total == 1 { "match" } else { "matches" };
+                format!("{}: {} {noun}", capitalize(scope.scope), scope.total)
+            })
+            .collect(),
+        scopes
+            .iter()
+            .map(
+                |scope| match (&scope.first_received_at, &scope.last_received_at) {
+                    (Some(first), Some(last)) => {
+                        format!("{} .. {}", day_of(first), day_of(last))
+                    }
+                    _ => "-".to_string(),
+                },
+            )
+            .collect(),
+        scopes
+            .iter()
+            .map(|_| "-".repeat(COMPARE_COLUMN_WIDTH))
+            .collect(),
+    ];
+    let sample_rows = scopes
+        .iter()
+        .map(|scope| scope.samples.len())
+        .max()
+        .unwrap_or(0);
+    for row in 0..sample_rows {
+        rows.push(
+            scopes
+                .iter()
+                .map(|scope| {
+                    scope
+                        .samples
+                        .get(row)
+                        .map(|item| {
+                            format!(
+                                "{}  {}",
+                                day_of(&item.email.received_at),
+                                item.email.subject.as_deref().unwrap_or("(no subject)")
+                            )
+                        })
+                        .unwrap_or_default()
+                })
+                .collect(),
+        );
+    }
+
+    let mut out = String::new();
+    for mut row in rows {
+        while row.last().is_some_and(String::is_empty) {
+            row.pop();
+        }
+        let cells = row
+            .iter()
+            .map(|cell| {
+                let cell = truncate_for_width(cell, COMPARE_COLUMN_WIDTH);
+                let padding =
+                    COMPARE_COLUMN_WIDTH.saturating_sub(UnicodeWidthStr::width(cell.as_str()));
+                format!("{cell}{}", " ".repeat(padding))
+            })
+            .collect::<Vec<_>>();
+        out.push_str(cells.join(" | ").trim_end());
+        out.push('\n');
+    }
+
+    if let Some(busiest) = scopes.iter().max_by_key(|scope| scope.total) {
         out.push_str(&format!(
-            "{:<from$}  {:<subject$}  {:<date$}  {:>score$}\n",
-            from,
-            subject,
-            date,
-            score,
-            from = FROM_WIDTH,
-            subject = SUBJECT_WIDTH,
-            date = DATE_WIDTH,
-            score = SCORE_WIDTH
+            "\nMostly {}: {}% of {total} matches.\n",
+            busiest.scope,
+            busiest.total * 100 / total
         ));
     }
-
     out
 }
 
-pub fn format_email(email: &Email) -> String {
+fn day_of(timestamp: &str) -> &str {
+    timestamp.get(..10).unwrap_or(timestamp)
+}
+
+fn capitalize(value: &str) -> String {
+    let mut chars = value.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// With `color`, the importance is colored.
+pub fn format_email(email: &Email, color: bool) -> String {
     let mut out = String::new();
     out.push_str(&format!("ID: {}\n", email.id));
     out.push_str(&format!(
@@ -90,12 +379,17 @@ pub fn format_email(email: &Email) -> String {
     }
     out.push_str(&format!(
         "Date: {} ({})\n",
-        email.received_at,
+        local_time(&email.received_at),
         relative_date(&email.received_at)
     ));
+    let importance = email.importance.as_deref().unwrap_or("normal");
     out.push_str(&format!(
         "Importance: {}\n",
-        colorize_importance(email.importance.as_deref().unwrap_or("normal"))
+        if color {
+            colorize_importance(importance)
+        } else {
+            importance.to_string()
+        }
     ));
     out.push_str(&format!(
         "Folder: {}\n",
@@ -120,19 +414,381 @@ pub fn format_email(email: &Email) -> String {
     out
 }
 
-pub fn format_thread(emails: &[Email]) -> String {
-    if emails.is_empty() {
+pub fn format_thread(
+    emails: &[Email],
+    notes: &[Note],
+    summary: Option<&ConversationSummary>,
+    color: bool,
+) -> String {
+    if emails.is_empty() && notes.is_empty() {
         return "Thread has no messages.".to_string();
     }
 
     let mut out = String::new();
+    if let Some(summary) = summary {
+        let freshness = if summary.covers(emails) {
+            String::new()
+        } else {
+            "; newer messages since, run `ess summarize --refresh`".to_string()
+        };
+        out.push_str(&format!(
+            "Summary ({}{freshness}):\n{}\n",
+            summary.model,
+            summary.summary.trim()
+        ));
+        out.push_str(&"=".repeat(80));
+        out.push('\n');
+    }
+    if !notes.is_empty() {
+        out.push_str("Notes:\n");
+        for note in notes {
+            out.push_str(&format_note_line(note));
+        }
+        out.push_str(&"=".repeat(80));
+        out.push('\n');
+    }
     for (idx, email) in emails.iter().enumerate() {
         if idx > 0 {
             out.push('\n');
             out.push_str(&"-".repeat(80));
             out.push('\n');
         }
-        out.push_str(&format_email(email));
+        out.push_str(&format_email(email, color));
+    }
+    out
+}
+
+pub fn format_notes(notes: &[Note]) -> String {
+    if notes.is_empty() {
+        return "No notes found.".to_string();
+    }
+
+    let mut out = String::new();
+    let mut conversation = None;
+    for note in notes {
+        if conversation != Some(note.conversation_id.as_str()) {
+            out.push_str(&format!("{}:\n", note.conversation_id));
+            conversation = Some(note.conversation_id.as_str());
+        }
+        out.push_str(&format_note_line(note));
+    }
+    out
+}
+
+fn format_note_line(note: &Note) -> String {
+    format!(
+        "  [{}] {}  {}\n",
+        note.id,
+        relative_date(&note.created_at),
+        note.body
+    )
+}
+
+pub fn format_collections(collections: &[CollectionSummary]) -> String {
+    if collections.is_empty() {
+        return "No collections found.".to_string();
+    }
+
+    let mut out = String::new();
+    out.push_str(
+        "Collection                  Emails  Last Added
+",
+    );
+    out.push_str(
+        "--------------------------  ------  --------------------
+",
+    );
+    for collection in collections {
+        out.push_str(&format!(
+            "{:<26}  {:>6}  {}
+",
+            truncate_for_width(&collection.name, 26),
+            collection.emails,
+            collection.last_added_at.as_deref().unwrap_or("-")
+        ));
+    }
+    out
+}
+
+pub fn format_folder_counts(folders: &[FolderEmailCount]) -> String {
+    if folders.is_empty() {
+        return "No emails stored.".to_string();
+    }
+
+    let mut out = String::new();
+    out.push_str(&format!(
+        "{:<30}  {:<30}  {:>8}\n",
+        "Account", "Folder", "Emails"
+    ));
+    out.push_str(&format!(
+        "{}  {}  {}\n",
+        "-".repeat(30),
+        "-".repeat(30),
+        "-".repeat(8)
+    ));
+    for folder in folders {
+        out.push_str(&format!(
+            "{:<30}  {:<30}  {:>8}\n",
+            truncate_for_width(folder.account_id.as_deref().unwrap_or("-"), 30),
+            truncate_for_width(folder.folder.as_deref().unwrap_or("-"), 30),
+            folder.count
+        ));
+    }
+    out
+}
+
+/// One block per account, headed by when its folders were discovered;
+/// skipped folders say why.
+pub fn format_discovered_folders(folders: &[FolderDiscovery]) -> String {
+    if folders.is_empty() {
+        return "No folder discovery recorded; sync a Microsoft Graph account first.".to_string();
+    }
+
+    let mut out = String::new();
+    let mut account = None;
+    for folder in folders {
+        if account != Some(&folder.account_id) {
+            if account.is_some() {
+                out.push('\n');
+            }
+            account = Some(&folder.account_id);
+            out.push_str(&format!(
+                "{} (discovered {})\n",
+                folder.account_id, folder.discovered_at
+            ));
+            out.push_str(&format!(
+                "{:<30}  {:<20}  {:>8}  {:>8}  Status\n",
+                "Folder", "Label", "Items", "Stored"
+            ));
+            out.push_str(&format!(
+                "{}  {}  {}  {}  ------\n",
+                "-".repeat(30),
+                "-".repeat(20),
+                "-".repeat(8),
+                "-".repeat(8)
+            ));
+        }
+        let items = folder
+            .total_items
+            .map_or_else(|| "-".to_string(), |items| items.to_string());
+        let mut status = match &folder.reason {
+            Some(reason) => format!("skipped: {reason}"),
+            None => "synced".to_string(),
+        };
+        if folder.hidden {
+            status.push_str(" (hidden)");
+        }
+        out.push_str(&format!(
+            "{:<30}  {:<20}  {:>8}  {:>8}  {}\n",
+            truncate_for_width(&folder.path, 30),
+            truncate_for_width(folder.label.as_deref().unwrap_or("-"), 20),
+            items,
+            folder.stored_emails,
+            status
+        ));
+    }
+    out
+}
+
+/// Nested labels are shown by their last name segment, indented under
+/// their parent.
+pub fn format_labels(labels: &[Label]) -> String {
+    if labels.is_empty() {
+        return "No labels found.".to_string();
+    }
+
+    let mut out = String::new();
+    out.push_str("Label                                     Type    Color\n");
+    out.push_str("----------------------------------------  ------  --------------------\n");
+    for label in labels {
+        let depth = std::iter::successors(label.parent.as_deref(), |parent| {
+            labels
+                .iter()
+                .find(|candidate| candidate.name == *parent)
+                .and_then(|candidate| candidate.parent.as_deref())
+        })
+        .count();
+        let name = match &label.parent {
+            Some(parent) => label
+                .name
+                .strip_prefix(parent.as_str())
+                .map(|rest| rest.trim_start_matches('/'))
+                .unwrap_or(&label.name),
+            None => &label.name,
+        };
+        let color = match (&label.text_color, &label.background_color) {
+            (Some(text), Some(background)) => format!("{text} on {background}"),
+            (None, Some(background)) => background.clone(),
+            (Some(text), None) => text.clone(),
+            (None, None) => "-".to_string(),
+        };
+        out.push_str(&format!(
+            "{:<40}  {:<6}  {}\n",
+            truncate_for_width(&format!("{}{name}", "  ".repeat(depth)), 40),
+            label.label_type,
+            color
+        ));
+    }
+    out
+}
+
+/// The unsubscribe column prefers the web link over `mailto:`.
+pub fn format_mailing_lists(lists: &[MailingList]) -> String {
+    if lists.is_empty() {
+        return "No mailing lists found.".to_string();
+    }
+
+    let mut out = String::new();
+    out.push_str("List                            Messages  30 days  Last          Unsubscribe\n");
+    out.push_str(
+        "------------------------------  --------  -------  ------------  --------------------\n",
+    );
+    for list in lists {
+        let unsubscribe = list
+            .unsubscribe_url
+            .as_deref()
+            .or(list.unsubscribe_mailto.as_deref())
+            .unwrap_or("-");
+        out.push_str(&format!(
+            "{:<30}  {:>8}  {:>7}  {}  {}\n",
+            truncate_for_width(list.name.as_deref().unwrap_or(&list.list_id), 30),
+            list.messages,
+            list.recent_messages,
+            pad(&relative_date(&list.last_received), DATE_WIDTH),
+            unsubscribe
+        ));
+    }
+    out
+}
+
+/// Done follow-ups are marked with `x` before their id.
+pub fn format_followups(followups: &[Followup]) -> String {
+    if followups.is_empty() {
+        return "No commitments found.".to_string();
+    }
+
+    let mut out = String::new();
+    out.push_str("     Id  Due         Sent          To                    Commitment\n");
+    out.push_str(
+        "-------  ----------  ------------  --------------------  ------------------------------\n",
+    );
+    for followup in followups {
+        let done = if followup.done_at.is_some() { "x" } else { " " };
+        let due = followup
+            .due_date
+            .map_or_else(|| "-".to_string(), |day| day.to_string());
+        let to = followup.to_addresses.first().map_or("-", String::as_str);
+        out.push_str(&format!(
+            "{done}{:>6}  {:<10}  {}  {:<20}  {}\n",
+            followup.id,
+            due,
+            pad(&relative_date(&followup.sent_at), DATE_WIDTH),
+            truncate_for_width(to, 20),
+            followup.text
+        ));
+    }
+    out
+}
+
+/// Copies that repeat an earlier version are marked `=`. Hashes are
+/// shortened to 12 characters.
+pub fn format_attachment_versions(copies: &[AttachmentVersion]) -> String {
+    if copies.is_empty() {
+        return "No attachments with that name in the thread.".to_string();
+    }
+
+    let mut out = String::new();
+    out.push_str("Version  Sent          From                  Size        SHA-256       Email\n");
+    out.push_str(
+        "-------  ------------  --------------------  ----------  ------------  --------------------\n",
+    );
+    for copy in copies {
+        let resent = if copy.resent { "=" } else { " " };
+        let size = copy
+            .size_bytes
+            .map_or_else(|| "-".to_string(), super::markdown::format_size);
+        let hash = copy
+            .sha256
+            .as_deref()
+            .map_or("-", |hash| &hash[..hash.len().min(12)]);
+        out.push_str(&format!(
+            "{resent}{:>6}  {}  {:<20}  {:>10}  {:<12}  {}\n",
+            format!("v{}", copy.version),
+            pad(&relative_date(&copy.sent_at), DATE_WIDTH),
+            truncate_for_width(copy.from_address.as_deref().unwrap_or("-"), 20),
+            size,
+            hash,
+            copy.email_id
+        ));
+    }
+    out
+}
+
+/// Links without an invite show the day their email arrived instead of a
+/// start time.
+pub fn format_meeting_links(links: &[MeetingLink]) -> String {
+    if links.is_empty() {
+        return "No meetings found.".to_string();
+    }
+
+    let mut out = String::new();
+    out.push_str(
+        "When              Via    Title                           Link
+",
+    );
+    out.push_str(
+        "----------------  -----  ------------------------------  ------------------------------
+",
+    );
+    for link in links {
+        let when = match link.starts_at.as_deref().map(DateTime::parse_from_rfc3339) {
+            Some(Ok(starts_at)) => starts_at
+                .with_timezone(&Local)
+                .format("%a %b %d %H:%M")
+                .to_string(),
+            _ => relative_date(&link.received_at),
+        };
+        out.push_str(&format!(
+            "{:<16}  {:<5}  {}  {}\n",
+            when,
+            link.provider,
+            pad(
+                &truncate_for_width(link.title.as_deref().unwrap_or("(no subject)"), 30),
+                30
+            ),
+            link.url
+        ));
+    }
+    out
+}
+
+pub fn format_quarantine(entries: &[QuarantinedMessage]) -> String {
+    if entries.is_empty() {
+        return "No quarantined messages.".to_string();
+    }
+
+    let mut out = String::new();
+    out.push_str(
+        "    Id  Connector     Message / Source              Quarantined   Retries  Error\n",
+    );
+    out.push_str(
+        "------  ------------  ----------------------------  ------------  -------  ------------------------------\n",
+    );
+    for entry in entries {
+        let origin = entry
+            .message_id
+            .as_deref()
+            .or(entry.source.as_deref())
+            .unwrap_or("-");
+        out.push_str(&format!(
+            "{:>6}  {:<12}  {:<28}  {:<12}  {:>7}  {}\n",
+            entry.id,
+            truncate_for_width(&entry.connector, 12),
+            truncate_for_width(origin, 28),
+            truncate_for_width(&relative_date(&entry.created_at), 12),
+            entry.retry_count,
+            truncate_for_width(entry.error.lines().next().unwrap_or_default(), 60)
+        ));
     }
     out
 }
@@ -179,6 +835,297 @@ pub fn format_stats(stats: &DatabaseStats) -> String {
         }
     }
 
+    if !stats.emails_by_tag.is_empty() {
+        out.push('\n');
+        out.push_str("Emails by tag\n");
+        out.push_str("-------------\n");
+        for row in &stats.emails_by_tag {
+            out.push_str(&format!("{:<24} {:>8}\n", row.tag, row.count));
+        }
+    }
+
+    out
+}
+
+/// Widest bar in the response time and busiest hour sections.
+const ANALYTICS_BAR_WIDTH: usize = 30;
+
+pub fn format_analytics(analytics: &Analytics) -> String {
+    let mut out = String::new();
+    let heading = match analytics.interval {
+        AnalyticsInterval::Week => "Volume by week (starting Monday)",
+        AnalyticsInterval::Month => "Volume by month",
+    };
+    out.push_str(&format!("{heading}\n{}\n", "-".repeat(heading.len())));
+    if analytics.volume.is_empty() {
+        out.push_str("No emails in range.\n");
+    } else {
+        out.push_str(&format!(
+            "{:<10}  {:>8}  {:>8}\n",
+            "Period", "Received", "Sent"
+        ));
+        for period in &analytics.volume {
+            out.push_str(&format!(
+                "{:<10}  {:>8}  {:>8}\n",
+                period.period, period.received, period.sent
+            ));
+        }
+    }
+
+    if !analytics.top_senders.is_empty() {
+        out.push_str("\nTop senders\n-----------\n");
+        for sender in &analytics.top_senders {
+            let label = match &sender.name {
+                Some(name) => format!("{name} <{}>", sender.address),
+                None => sender.address.clone(),
+            };
+            out.push_str(&format!(
+                "{}  {:>8}\n",
+                pad(&truncate_for_width(&label, 40), 40),
+                sender.messages
+            ));
+        }
+    }
+
+    let times = &analytics.response_times;
+    if times.replies > 0 {
+        let heading = format!(
+            "Response times ({} repl{}, median {})",
+            times.replies,
+            if times.replies == 1 { "y" } else { "ies" },
+            times
+                .median_minutes
+                .map_or_else(|| "-".to_string(), format_minutes)
+        );
+        out.push_str(&format!("\n{heading}\n{}\n", "-".repeat(heading.len())));
+        let most = times
+            .buckets
+            .iter()
+            .map(|bucket| bucket.replies)
+            .max()
+            .unwrap_or(0);
+        for bucket in &times.buckets {
+            out.push_str(&format!(
+                "{:<6}  {:>6}  {}\n",
+                bucket.label,
+                bucket.replies,
+                bar(bucket.replies as i64, most as i64)
+            ));
+        }
+    }
+
+    let busiest = analytics
+        .busiest_hours
+        .iter()
+        .map(|hour| hour.received + hour.sent)
+        .max()
+        .unwrap_or(0);
+    if busiest > 0 {
+        out.push_str("\nBusiest hours\n-------------\n");
+        out.push_str(&format!(
+            "{:<5}  {:>8}  {:>8}\n",
+            "Hour", "Received", "Sent"
+        ));
+        for hour in &analytics.busiest_hours {
+            out.push_str(&format!(
+                "{:02}:00  {:>8}  {:>8}  {}\n",
+                hour.hour,
+                hour.received,
+                hour.sent,
+                bar(hour.received + hour.sent, busiest)
+            ));
+        }
+    }
+    out
+}
+
+/// `value` as a run of `#`, scaled so that `max` fills the bar.
+fn bar(value: i64, max: i64) -> String {
+    if max <= 0 || value <= 0 {
+        return String::new();
+    }
+    let width = (value * ANALYTICS_BAR_WIDTH as i64 + max - 1) / max;
+    "#".repeat(width as usize)
+}
+
+/// `45m`, `3h 12m` or `2d 4h`.
+fn format_minutes(minutes: f64) -> String {
+    let minutes = minutes.round() as i64;
+    match minutes {
+        ..60 => format!("{minutes}m"),
+        60..1_440 => format!("{}h {}m", minutes / 60, minutes % 60),
+        _ => format!("{}d {}h", minutes / 1_440, minutes % 1_440 / 60),
+    }
+}
+
+pub fn format_sync_usage(usage: &[SyncUsage]) -> String {
+    if usage.is_empty() {
+        return "No sync usage recorded.".to_string();
+    }
+    let mut out = String::new();
+    out.push_str("Sync usage (UTC days)\n");
+    out.push_str("---------------------\n");
+    out.push_str(&format!(
+        "{:<10}  {:<24} {:>10}  {:>12}\n",
+        "Day", "Account", "Requests", "Downloaded"
+    ));
+    for row in usage {
+        out.push_str(&format!(
+            "{:<10}  {:<24} {:>10}  {:>12}\n",
+            row.day,
+            row.account_id,
+            row.requests,
+            super::markdown::format_size(i64::try_from(row.bytes).unwrap_or(i64::MAX))
+        ));
+    }
+    out
+}
+
+/// What `ess trim` dropped, or with `--dry-run` would drop.
+pub fn format_trim_report(report: &TrimReport) -> String {
+    let size = |bytes: u64| super::markdown::format_size(i64::try_from(bytes).unwrap_or(i64::MAX));
+    if report.trimmed.is_empty() {
+        let state = if report.size_after.total > report.max_bytes {
+            "over the cap, but no body is left to trim"
+        } else {
+            "nothing to trim"
+        };
+        return format!(
+            "Store is {} of {} MB; {state}.",
+            size(report.size_after.total),
+            report.max_bytes / 1_000_000
+        );
+    }
+    let mut out = String::new();
+    let verb = if report.dry_run {
+        "Would trim"
+    } else {
+        "Trimmed"
+    };
+    out.push_str(&format!(
+        "{verb} the bodies of {} email(s) ({}) to keep the store under {} MB\n",
+        report.trimmed.len(),
+        size(report.bytes_trimmed),
+        report.max_bytes / 1_000_000
+    ));
+    if !report.dry_run {
+        out.push_str(&format!(
+            "Store: {} -> {}\n",
+            size(report.size_before.total),
+            size(report.size_after.total)
+        ));
+    }
+    out.push('\n');
+    out.push_str(&format!(
+        "{:<10}  {:<20}  {:>9}  Subject\n",
+        "Received", "Account", "Body"
+    ));
+    for email in &report.trimmed {
+        out.push_str(&format!(
+            "{:<10}  {}  {:>9}  {}\n",
+            day_of(&email.received_at),
+            pad(email.account_id.as_deref().unwrap_or("-"), 20),
+            size(email.body_bytes),
+            truncate_for_width(
+                email.subject.as_deref().unwrap_or("(no subject)"),
+                SUBJECT_WIDTH
+            )
+        ));
+    }
+    out
+}
+
+pub fn format_stats_trend(trend: &StatsTrend) -> String {
+    let mut out = String::new();
+    out.push_str(&format!(
+        "Trend since {} ({} snapshot day(s))\n",
+        trend.first.captured_at,
+        trend.daily.len()
+    ));
+    out.push_str("-----------------\n");
+    let rows = [
+        (
+            "Emails",
+            trend.first.total_emails,
+            trend.last.total_emails,
+            trend.emails_delta,
+        ),
+        (
+            "Contacts",
+            trend.first.total_contacts,
+            trend.last.total_contacts,
+            trend.contacts_delta,
+        ),
+        (
+            "Index docs",
+            trend.first.index_doc_count,
+            trend.last.index_doc_count,
+            trend.index_doc_delta,
+        ),
+        (
+            "Index bytes",
+            trend.first.index_size_bytes,
+            trend.last.index_size_bytes,
+            trend.index_size_delta,
+        ),
+    ];
+    for (label, first, last, delta) in rows {
+        out.push_str(&format!(
+            "{label:<12} {first:>12} -> {last:>12}  ({delta:+})\n"
+        ));
+    }
+
+    if trend.daily.len() > 1 {
+        out.push('\n');
+        out.push_str(&format!(
+            "{:<10}  {:>10}  {:>10}  {:>14}\n",
+            "Day", "Emails", "Contacts", "Index bytes"
+        ));
+        for snapshot in &trend.daily {
+            out.push_str(&format!(
+                "{:<10}  {:>10}  {:>10}  {:>14}\n",
+                snapshot
+                    .captured_at
+                    .get(..10)
+                    .unwrap_or(&snapshot.captured_at),
+                snapshot.total_emails,
+                snapshot.total_contacts,
+                snapshot.index_size_bytes
+            ));
+        }
+    }
+
+    out
+}
+
+pub fn format_env(vars: &[EnvStatus]) -> String {
+    let name_width = vars
+        .iter()
+        .map(|status| status.var.name.len())
+        .max()
+        .unwrap_or(0);
+    let mut out = String::new();
+    out.push_str(&format!(
+        "{:<name_width$}  {:<3}  {:<8}  Affects\n",
+        "Variable", "Set", "Category"
+    ));
+    out.push_str(&format!(
+        "{}  {}  {}  {}\n",
+        "-".repeat(name_width),
+        "-".repeat(3),
+        "-".repeat(8),
+        "-".repeat(7)
+    ));
+    for status in vars {
+        let set = if status.set { "yes" } else { "no" };
+        out.push_str(&format!(
+            "{:<name_width$}  {:<3}  {:<8}  {}\n",
+            status.var.name, set, status.var.category, status.var.description
+        ));
+        if let Some(value) = &status.value {
+            out.push_str(&format!("{:<name_width$}  value: {value}\n", ""));
+        }
+    }
     out
 }
 
@@ -217,7 +1164,27 @@ fn relative_date(input: &str) -> String {
     if delta.num_days() < 7 {
         return format!("{}d ago", delta.num_days());
     }
-    parsed.format("%Y-%m-%d").to_string()
+    parsed.with_timezone(&Local).format("%Y-%m-%d").to_string()
+}
+
+/// An RFC 3339 timestamp in the local time zone, to the minute.
+fn local_time(input: &str) -> String {
+    match DateTime::parse_from_rfc3339(input) {
+        Ok(value) => value
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M %:z")
+            .to_string(),
+        Err(_) => input.to_string(),
+    }
+}
+
+/// Truncates to `width` display columns and pads to exactly that many, so
+/// wide (CJK, emoji) characters keep the columns aligned.
+fn pad(value: &str, width: usize) -> String {
+    let mut out = truncate_for_width(value, width);
+    let used = UnicodeWidthStr::width(out.as_str());
+    out.push_str(&" ".repeat(width.saturating_sub(used)));
+    out
 }
 
 fn truncate_for_width(value: &str, max_width: usize) -> String {
@@ -248,52 +1215,158 @@ mod tests {
     use chrono::{Duration, Utc};
 
     use crate::db::models::Email;
-    use crate::output::SearchResultItem;
+    use crate::output::{ScopeComparisonItem, SearchResultItem};
+    use crate::search::Snippet;
 
-    use super::{format_email, format_search_results};
+    use super::{format_email, format_scope_comparison, format_search_results, TableStyle};
 
     fn sample_email() -> Email {
         Email {
-            id: "msg-1".to_string(),
-            internet_message_id: None,
             conversation_id: Some("thread-1".to_string()),
             account_id: Some("acc-1".to_string()),
             subject: Some("A very long subject line that should be truncated in table output because it exceeds width".to_string()),
             from_address: Some("sender@example.com".to_string()),
             from_name: Some("Sender Name".to_string()),
             to_addresses: vec!["owner@example.com".to_string()],
-            cc_addresses: vec![],
-            bcc_addresses: vec![],
             body_text: Some("Body".to_string()),
-            body_html: None,
             body_preview: Some("Preview".to_string()),
             received_at: (Utc::now() - Duration::hours(2)).to_rfc3339(),
-            sent_at: None,
             importance: Some("high".to_string()),
             is_read: Some(false),
             has_attachments: Some(false),
             folder: Some("inbox".to_string()),
-            categories: vec![],
-            flag_status: None,
-            web_link: None,
-            metadata: None,
+            ..Email::fixture("msg-1")
         }
     }
 
     #[test]
     fn table_search_output_has_headers() {
-        let rendered = format_search_results(&[SearchResultItem {
-            email: sample_email(),
-            score: Some(12.34),
-        }]);
+        let rendered = format_search_results(
+            &[SearchResultItem {
+                email: sample_email(),
+                score: Some(12.34),
+                snippet: None,
+                preview: None,
+            }],
+            &TableStyle::plain(),
+        );
         assert!(rendered.contains("From"));
         assert!(rendered.contains("Subject"));
         assert!(rendered.contains("Score"));
     }
 
+    #[test]
+    fn table_search_output_bolds_snippet_matches() {
+        let item = SearchResultItem {
+            email: sample_email(),
+            score: Some(1.0),
+            snippet: Some(Snippet {
+                text: "the Q3\nbudget draft budgets".to_string(),
+                highlights: vec![7..13, 20..27],
+            }),
+            preview: None,
+        };
+
+        let plain = format_search_results(std::slice::from_ref(&item), &TableStyle::plain());
+        assert!(plain.contains("    the Q3 budget draft budgets\n"));
+
+        let styled = format_search_results(
+            &[item],
+            &TableStyle {
+                width: None,
+                color: true,
+            },
+        );
+        assert!(styled.contains("the Q3 \x1b[1mbudget\x1b[22m draft \x1b[1mbudgets\x1b[22m"));
+    }
+
+    #[test]
+    fn table_fits_terminal_width_and_colors_unread_rows() {
+        use unicode_width::UnicodeWidthStr;
+
+        let mut unread = sample_email();
+        unread.subject = Some("会議の議題について、来週の予定を確認してください".to_string());
+        let mut read = sample_email();
+        read.is_read = Some(true);
+        let items = [
+            SearchResultItem {
+                email: unread,
+                score: Some(2.0),
+                snippet: None,
+                preview: None,
+            },
+            SearchResultItem {
+                email: read,
+                score: Some(0.5),
+                snippet: None,
+                preview: None,
+            },
+        ];
+
+        let narrow = format_search_results(
+            &items,
+            &TableStyle {
+                width: Some(60),
+                color: false,
+            },
+        );
+        for line in narrow.lines() {
+            assert_eq!(UnicodeWidthStr::width(line), 60, "{line:?}");
+        }
+
+        let colored = format_search_results(
+            &items,
+            &TableStyle {
+                width: Some(60),
+                color: true,
+            },
+        );
+        let rows = colored.lines().skip(2).collect::<Vec<_>>();
+        assert!(rows[0].starts_with("\x1b[1m"));
+        assert!(rows[0].ends_with("\x1b[32m   2.00\x1b[39m"));
+        assert!(!rows[1].starts_with("\x1b[1m"));
+        assert!(rows[1].ends_with("\x1b[2m   0.50\x1b[22m"));
+    }
+
+    #[test]
+    fn scope_comparison_renders_columns_and_share() {
+        let mut email = sample_email();
+        email.subject = Some("Offsite venue".to_string());
+        email.received_at = "2026-02-01T10:00:00Z".to_string();
+        let scopes = [
+            ScopeComparisonItem {
+                scope: "professional",
+                total: 3,
+                first_received_at: Some("2026-01-10T10:00:00Z".to_string()),
+                last_received_at: Some("2026-02-01T10:00:00Z".to_string()),
+                samples: vec![SearchResultItem {
+                    email,
+                    score: Some(1.0),
+                    snippet: None,
+                    preview: None,
+                }],
+            },
+            ScopeComparisonItem {
+                scope: "personal",
+                total: 1,
+                first_received_at: Some("2026-01-20T10:00:00Z".to_string()),
+                last_received_at: Some("2026-01-20T10:00:00Z".to_string()),
+                samples: vec![],
+            },
+        ];
+
+        let rendered = format_scope_comparison(&scopes);
+        let lines = rendered.lines().collect::<Vec<_>>();
+        assert!(lines[0].starts_with("Professional: 3 matches "));
+        assert!(lines[0].ends_with("| Personal: 1 match"));
+        assert!(lines[1].contains("2026-01-10 .. 2026-02-01"));
+        assert_eq!(lines[3].trim_end(), "2026-02-01  Offsite venue");
+        assert!(rendered.ends_with("Mostly professional: 75% of 4 matches.\n"));
+    }
+
     #[test]
     fn full_email_output_contains_body() {
-        let rendered = format_email(&sample_email());
+        let rendered = format_email(&sample_email(), false);
         assert!(rendered.contains("Body"));
         assert!(rendered.contains("Importance"));
     }
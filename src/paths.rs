@@ -0,0 +1,376 @@
+//! Where ESS keeps its files, following each platform's conventions:
+//!
+//! - data (`ess.db`, `raw/`): `$XDG_DATA_HOME/ess`, i.e. `~/.local/share/ess`
+//!   on Linux and `~/Library/Application Support/ess` on macOS;
+//! - config (`config.toml`, `rules.toml`): `$XDG_CONFIG_HOME/ess`, i.e. `~/.config/ess` on
+//!   Linux and `~/Library/Application Support/ess` on macOS;
+//! - cache (`index/`): `$XDG_CACHE_HOME/ess`, i.e. `~/.cache/ess` on Linux and
+//!   `~/Library/Caches/ess` on macOS. The search index is derived from the
+//!   database and rebuilt from it when missing.
+//!
+//! Earlier versions kept everything under `~/.ess`. The first run after an
+//! upgrade moves that directory's contents to the locations above, holding
+//! a lock so that two processes starting together do not both move them;
+//! with `[storage] legacy_symlink = true`, `~/.ess` then holds a symlink per
+//! old entry (`ess.db`, `config.toml`, `index`, ...) to its new location,
+//! which keeps scripts written against the old layout working.
+
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+const APP_DIR: &str = "ess";
+
+const CONFIG_FILES: &[&str] = &["config.toml", "rules.toml"];
+const INDEX_DIR: &str = "index";
+const DB_FILE: &str = "ess.db";
+const RAW_DIR: &str = "raw";
+/// Held by the process moving `~/.ess`; kept in the data directory, which
+/// the move never removes.
+const MIGRATION_LOCK: &str = ".migrate.lock";
+/// The `~/.ess` entries `link_legacy` points at their new locations.
+const LEGACY_LINKS: &[&str] = &[DB_FILE, RAW_DIR, "config.toml", "rules.toml", INDEX_DIR];
+
+fn platform_dir(dir: Option<PathBuf>, kind: &str) -> io::Result<PathBuf> {
+    dir.map(|dir| dir.join(APP_DIR)).ok_or_else(|| {
+        io::Error::new(
+            ErrorKind::NotFound,
+            format!("failed to determine {kind} directory"),
+        )
+    })
+}
+
+/// `$XDG_DATA_HOME/ess`: the database and raw message sources.
+pub fn data_dir() -> io::Result<PathBuf> {
+    platform_dir(dirs::data_dir(), "data")
+}
+
+/// `$XDG_CONFIG_HOME/ess`: `config.toml`.
+pub fn config_dir() -> io::Result<PathBuf> {
+    platform_dir(dirs::config_dir(), "config")
+}
+
+/// `$XDG_CACHE_HOME/ess`: the search index.
+pub fn cache_dir() -> io::Result<PathBuf> {
+    platform_dir(dirs::cache_dir(), "cache")
+}
+
+/// `~/.ess`, where everything lived before the move to the XDG directories.
+pub fn legacy_dir() -> io::Result<PathBuf> {
+    dirs::home_dir()
+        .map(|home| home.join(".ess"))
+        .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "failed to determine home directory"))
+}
+
+/// The directories ESS reads and writes, resolved once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    pub data: PathBuf,
+    pub config: PathBuf,
+    pub cache: PathBuf,
+    pub legacy: PathBuf,
+}
+
+/// What [`Layout::migrate_legacy`] did.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Migration {
+    /// `(from, to)` for every entry moved out of `~/.ess`.
+    pub moved: Vec<(PathBuf, PathBuf)>,
+    /// Entries left in `~/.ess` because their destination already exists.
+    pub conflicts: Vec<PathBuf>,
+    /// Whether `~/.ess` ended up empty and was removed.
+    pub removed_legacy: bool,
+}
+
+impl Layout {
+    pub fn current() -> io::Result<Self> {
+        Ok(Self {
+            data: data_dir()?,
+            config: config_dir()?,
+            cache: cache_dir()?,
+            legacy: legacy_dir()?,
+        })
+    }
+
+    /// Where an entry of the legacy directory belongs now.
+    fn destination(&self, name: &str) -> PathBuf {
+        if CONFIG_FILES.contains(&name) {
+            self.config.join(name)
+        } else if name == INDEX_DIR {
+            self.cache.join(name)
+        } else {
+            self.data.join(name)
+        }
+    }
+
+    /// Moves the contents of `~/.ess` to the XDG directories and removes it
+    /// once empty. Returns `None` when there is nothing to move: no legacy
+    /// directory, or only the compatibility links `link_legacy` made.
+    /// Entries whose destination exists stay put, and `ess.db` moves
+    /// together with its `-wal` and `-shm` files, so a rerun never
+    /// overwrites newer data. A process that finds another one moving the
+    /// directory waits for it and then finds nothing left to do.
+    pub fn migrate_legacy(&self) -> io::Result<Option<Migration>> {
+        if self.legacy_entries()?.is_empty() {
+            return Ok(None);
+        }
+        fs::create_dir_all(&self.data)?;
+        let lock = fs::File::create(self.data.join(MIGRATION_LOCK))?;
+        lock.lock()?;
+        // Another process may have moved everything while this one waited.
+        let names = self.legacy_entries()?;
+        if names.is_empty() {
+            return Ok(None);
+        }
+        let db_taken = self.data.join(DB_FILE).exists();
+
+        let mut migration = Migration::default();
+        for name in names {
+            let from = self.legacy.join(&name);
+            let name = name.to_string_lossy();
+            let to = self.destination(&name);
+            let conflict = if name.starts_with(DB_FILE) {
+                db_taken
+            } else {
+                fs::symlink_metadata(&to).is_ok()
+            };
+            if conflict {
+                migration.conflicts.push(from);
+                continue;
+            }
+            if let Some(parent) = to.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            move_entry(&from, &to)?;
+            migration.moved.push((from, to));
+        }
+
+        if migration.conflicts.is_empty() && fs::read_dir(&self.legacy)?.next().is_none() {
+            fs::remove_dir(&self.legacy)?;
+            migration.removed_legacy = true;
+        }
+        Ok(Some(migration))
+    }
+
+    /// The entries of `~/.ess` left to move, sorted: everything but
+    /// symlinks. Empty when it is missing or is itself a symlink.
+    fn legacy_entries(&self) -> io::Result<Vec<std::ffi::OsString>> {
+        match fs::symlink_metadata(&self.legacy) {
+            Ok(metadata) if metadata.is_dir() => {}
+            Ok(_) => return Ok(Vec::new()),
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error),
+        }
+        let mut names = Vec::new();
+        for entry in fs::read_dir(&self.legacy)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_symlink() {
+                names.push(entry.file_name());
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Links each of `LEGACY_LINKS` in `~/.ess` to its new location, once
+    /// that exists, replacing the single `~/.ess` symlink to the data
+    /// directory earlier versions made. Entries already in `~/.ess` are
+    /// left alone. Returns the links created.
+    pub fn link_legacy(&self) -> io::Result<Vec<PathBuf>> {
+        match fs::symlink_metadata(&self.legacy) {
+            Ok(metadata) if metadata.is_symlink() => {
+                if fs::read_link(&self.legacy)? != self.data {
+                    return Ok(Vec::new());
+                }
+                remove_symlink_dir(&self.legacy)?;
+            }
+            Ok(metadata) if !metadata.is_dir() => return Ok(Vec::new()),
+            Ok(_) => {}
+            Err(error) if error.kind() == ErrorKind::NotFound => {}
+            Err(error) => return Err(error),
+        }
+        let mut linked = Vec::new();
+        for name in LEGACY_LINKS {
+            let target = self.destination(name);
+            let link = self.legacy.join(name);
+            if !target.exists() || fs::symlink_metadata(&link).is_ok() {
+                continue;
+            }
+            fs::create_dir_all(&self.legacy)?;
+            symlink(&target, &link)?;
+            linked.push(link);
+        }
+        Ok(linked)
+    }
+}
+
+/// Renames `from` to `to`, copying and then deleting when they are on
+/// different filesystems.
+fn move_entry(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Ok(()) => return Ok(()),
+        // Moved already, e.g. by an `ess` from before the migration lock.
+        Err(error) if error.kind() == ErrorKind::NotFound && to.exists() => return Ok(()),
+        Err(_) => {}
+    }
+    copy_tree(from, to)?;
+    if from.is_dir() {
+        fs::remove_dir_all(from)
+    } else {
+        fs::remove_file(from)
+    }
+}
+
+fn copy_tree(from: &Path, to: &Path) -> io::Result<()> {
+    if !from.is_dir() {
+        return fs::copy(from, to).map(|_| ());
+    }
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        copy_tree(&entry.path(), &to.join(entry.file_name()))?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    if target.is_dir() {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+}
+
+#[cfg(unix)]
+fn remove_symlink_dir(link: &Path) -> io::Result<()> {
+    fs::remove_file(link)
+}
+
+#[cfg(windows)]
+fn remove_symlink_dir(link: &Path) -> io::Result<()> {
+    fs::remove_dir(link)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Layout;
+
+    fn layout() -> Layout {
+        let root = std::env::temp_dir().join(format!("ess-paths-{}", uuid::Uuid::new_v4()));
+        Layout {
+            data: root.join("share/ess"),
+            config: root.join("config/ess"),
+            cache: root.join("cache/ess"),
+            legacy: root.join("home/.ess"),
+        }
+    }
+
+    #[test]
+    fn legacy_directory_moves_to_xdg_locations_once() {
+        let layout = layout();
+        std::fs::create_dir_all(layout.legacy.join("index")).expect("create legacy index");
+        std::fs::create_dir_all(layout.legacy.join("raw/ab")).expect("create legacy raw");
+        std::fs::write(layout.legacy.join("ess.db"), "db").expect("write db");
+        std::fs::write(layout.legacy.join("ess.db-wal"), "wal").expect("write wal");
+        std::fs::write(layout.legacy.join("config.toml"), "[general]").expect("write config");
+        std::fs::write(layout.legacy.join("index/meta.json"), "{}").expect("write index");
+        std::fs::write(layout.legacy.join("raw/ab/x.eml.zst"), "raw").expect("write raw");
+
+        let migration = layout
+            .migrate_legacy()
+            .expect("migrate")
+            .expect("legacy directory found");
+        assert_eq!(migration.moved.len(), 5);
+        assert!(migration.conflicts.is_empty());
+        assert!(migration.removed_legacy);
+        let read = |path: std::path::PathBuf| std::fs::read_to_string(path).expect("read moved");
+        assert_eq!(read(layout.data.join("ess.db")), "db");
+        assert_eq!(read(layout.data.join("ess.db-wal")), "wal");
+        assert_eq!(read(layout.data.join("raw/ab/x.eml.zst")), "raw");
+        assert_eq!(read(layout.config.join("config.toml")), "[general]");
+        assert_eq!(read(layout.cache.join("index/meta.json")), "{}");
+        assert_eq!(layout.migrate_legacy().expect("rerun"), None);
+
+        assert_eq!(layout.link_legacy().expect("link").len(), 4);
+        assert_eq!(read(layout.legacy.join("ess.db")), "db");
+        assert_eq!(read(layout.legacy.join("config.toml")), "[general]");
+        assert_eq!(read(layout.legacy.join("index/meta.json")), "{}");
+        assert!(layout.link_legacy().expect("link again").is_empty());
+        assert_eq!(layout.migrate_legacy().expect("links are skipped"), None);
+
+        // The single link to the data directory of earlier versions is
+        // replaced by the per-entry links.
+        #[cfg(unix)]
+        {
+            std::fs::remove_dir_all(&layout.legacy).expect("remove links");
+            std::os::unix::fs::symlink(&layout.data, &layout.legacy).expect("old link");
+            assert_eq!(layout.link_legacy().expect("relink").len(), 4);
+            assert_eq!(read(layout.legacy.join("config.toml")), "[general]");
+        }
+
+        let _ = std::fs::remove_dir_all(layout.legacy.parent().unwrap().parent().unwrap());
+    }
+
+    #[test]
+    fn concurrent_migrations_both_succeed() {
+        let layout = layout();
+        std::fs::create_dir_all(layout.legacy.join("raw/ab")).expect("create legacy raw");
+        std::fs::write(layout.legacy.join("ess.db"), "db").expect("write db");
+        std::fs::write(layout.legacy.join("config.toml"), "[general]").expect("write config");
+
+        let runs = (0..4)
+            .map(|_| {
+                let layout = layout.clone();
+                std::thread::spawn(move || layout.migrate_legacy())
+            })
+            .collect::<Vec<_>>();
+        let moved = runs
+            .into_iter()
+            .map(|run| run.join().expect("thread").expect("migrate"))
+            .map(|migration| migration.map_or(0, |migration| migration.moved.len()))
+            .sum::<usize>();
+        assert_eq!(moved, 3);
+        assert!(!layout.legacy.exists());
+        assert!(layout.data.join("ess.db").exists());
+
+        let _ = std::fs::remove_dir_all(layout.legacy.parent().unwrap().parent().unwrap());
+    }
+
+    #[test]
+    fn existing_destinations_are_never_overwritten() {
+        let layout = layout();
+        std::fs::create_dir_all(&layout.legacy).expect("create legacy");
+        std::fs::create_dir_all(&layout.data).expect("create data");
+        std::fs::write(layout.legacy.join("ess.db"), "old").expect("write old db");
+        std::fs::write(layout.legacy.join("ess.db-wal"), "old wal").expect("write old wal");
+        std::fs::write(layout.legacy.join("sync-history.ndjson"), "{}").expect("write history");
+        std::fs::write(layout.data.join("ess.db"), "new").expect("write new db");
+
+        let migration = layout
+            .migrate_legacy()
+            .expect("migrate")
+            .expect("legacy directory found");
+        assert_eq!(
+            migration.conflicts,
+            vec![
+                layout.legacy.join("ess.db"),
+                layout.legacy.join("ess.db-wal")
+            ]
+        );
+        assert!(!migration.removed_legacy);
+        assert_eq!(
+            std::fs::read_to_string(layout.data.join("ess.db")).expect("read db"),
+            "new"
+        );
+        assert!(!layout.data.join("ess.db-wal").exists());
+        assert!(layout.data.join("sync-history.ndjson").exists());
+
+        let _ = std::fs::remove_dir_all(layout.legacy.parent().unwrap().parent().unwrap());
+    }
+}
@@ -0,0 +1,125 @@
+//! Raw RFC 822 message storage, opt-in with `[storage] raw_messages`.
+//!
+//! During sync the Gmail connector fetches each new message with
+//! `format=raw` and the Graph connector reads its MIME source from
+//! `/messages/{id}/$value`. The bytes are zstd-compressed into one file per
+//! email under `$XDG_DATA_HOME/ess/raw`, named after the SHA-256 of the email id (ids
+//! may hold characters that are awkward in file names) and spread over 256
+//! subdirectories. The stored source is what `ess raw get` prints and what
+//! `ess export --format eml|mbox` writes, byte for byte, in place of a
+//! message rebuilt from the parsed fields. It also keeps a message
+//! re-parseable once the parsers improve.
+
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use ring::digest::{digest, SHA256};
+
+use crate::paths;
+
+/// zstd's default level: fast, and mail text still shrinks severalfold.
+const COMPRESSION_LEVEL: i32 = 3;
+
+const FILE_EXTENSION: &str = "eml.zst";
+
+#[derive(Debug, Clone)]
+pub struct RawStore {
+    root: PathBuf,
+}
+
+impl RawStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// `$XDG_DATA_HOME/ess/raw`.
+    pub fn default_root() -> io::Result<PathBuf> {
+        Ok(paths::data_dir()?.join("raw"))
+    }
+
+    pub fn open_default() -> io::Result<Self> {
+        Self::default_root().map(Self::new)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn path(&self, email_id: &str) -> PathBuf {
+        let hash = digest(&SHA256, email_id.as_bytes())
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+        self.root
+            .join(&hash[..2])
+            .join(format!("{hash}.{FILE_EXTENSION}"))
+    }
+
+    pub fn contains(&self, email_id: &str) -> bool {
+        self.path(email_id).is_file()
+    }
+
+    /// Compresses and stores the message, replacing an earlier copy. The
+    /// file is written next to its final name and renamed into place, so a
+    /// reader never sees a partial message.
+    pub fn put(&self, email_id: &str, message: &[u8]) -> io::Result<()> {
+        let path = self.path(email_id);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let compressed = zstd::encode_all(message, COMPRESSION_LEVEL)?;
+        let partial = path.with_extension("partial");
+        fs::write(&partial, compressed)?;
+        fs::rename(&partial, &path)
+    }
+
+    /// The stored message, or `None` when none was stored for the email.
+    pub fn get(&self, email_id: &str) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(self.path(email_id)) {
+            Ok(compressed) => zstd::decode_all(compressed.as_slice()).map(Some),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Removes the stored message; returns false when there was none.
+    pub fn delete(&self, email_id: &str) -> io::Result<bool> {
+        match fs::remove_file(self.path(email_id)) {
+            Ok(()) => Ok(true),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RawStore;
+
+    #[test]
+    fn stores_compressed_messages_per_email() {
+        let root = std::env::temp_dir().join(format!("ess-raw-{}", uuid::Uuid::new_v4()));
+        let store = RawStore::new(&root);
+        let message = b"From: alice@example.com\r\nSubject: Hi\r\n\r\nHello\r\n".repeat(20);
+
+        assert_eq!(store.get("AAMk/id=").expect("read missing"), None);
+        store.put("AAMk/id=", &message).expect("store message");
+        assert!(store.contains("AAMk/id="));
+        assert!(!store.contains("other"));
+
+        let path = store.path("AAMk/id=");
+        assert!(path.starts_with(&root));
+        assert!(path.to_string_lossy().ends_with(".eml.zst"));
+        assert!(std::fs::metadata(&path).expect("stat").len() < message.len() as u64);
+        assert_eq!(
+            store.get("AAMk/id=").expect("read message").as_deref(),
+            Some(message.as_slice())
+        );
+
+        assert!(store.delete("AAMk/id=").expect("delete"));
+        assert!(!store.delete("AAMk/id=").expect("delete again"));
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,651 @@
+//! `ess replicate`: shipping the local change feed (see `db::changes`) to
+//! another machine's store, so a desktop and a laptop share what one of
+//! them synced without both syncing the providers.
+//!
+//! The changes go over as NDJSON records: first every account, without
+//! its credentials, then the current state of each email, star, collection
+//! and collection member that changed since the last run to that peer.
+//! The peer applies a record only when its own last change to the entry
+//! is older, so whichever side changed an entry last wins, and it indexes
+//! the emails it took. Accounts the peer lacks are added disabled, so it
+//! does not sync them itself.
+//!
+//! A peer is `ssh://[user@]host[:port][/path]`, which runs
+//! `ess replicate --receive` over `ssh`, or a local store directory. A
+//! store directory holds `ess.db` and `index/`.
+
+use std::fmt::{self, Display, Formatter};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::db::models::{Account, AccountType, Attachment, Email};
+use crate::db::{Change, ChangeEntity, Database};
+use crate::indexer::EmailIndex;
+
+/// Prefix of the `sync_state` keys holding the last change shipped to
+/// each peer.
+const CURSOR_KEY_PREFIX: &str = "replicate:";
+
+/// Records applied per SQLite transaction.
+const BATCH_SIZE: usize = 500;
+
+/// One line of the stream a peer applies.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Record {
+    Account {
+        account_id: String,
+        email_address: String,
+        display_name: Option<String>,
+        tenant_id: Option<String>,
+        account_type: AccountType,
+    },
+    /// `email` is `None` once the email is deleted.
+    Email {
+        id: String,
+        changed_at: String,
+        email: Option<Box<Email>>,
+        #[serde(default)]
+        attachments: Vec<Attachment>,
+    },
+    Star {
+        email_id: String,
+        changed_at: String,
+        starred: bool,
+    },
+    Collection {
+        name: String,
+        changed_at: String,
+        exists: bool,
+    },
+    CollectionEmail {
+        collection: String,
+        email_id: String,
+        changed_at: String,
+        member: bool,
+    },
+}
+
+impl Record {
+    /// The feed entry the record carries, and when it changed.
+    fn change(&self) -> Option<(ChangeEntity, String, String)> {
+        match self {
+            Self::Account { .. } => None,
+            Self::Email { id, changed_at, .. } => {
+                Some((ChangeEntity::Email, id.clone(), changed_at.clone()))
+            }
+            Self::Star {
+                email_id,
+                changed_at,
+                ..
+            } => Some((ChangeEntity::Star, email_id.clone(), changed_at.clone())),
+            Self::Collection {
+                name, changed_at, ..
+            } => Some((ChangeEntity::Collection, name.clone(), changed_at.clone())),
+            Self::CollectionEmail {
+                collection,
+                email_id,
+                changed_at,
+                ..
+            } => Some((
+                ChangeEntity::CollectionEmail,
+                serde_json::json!([collection, email_id]).to_string(),
+                changed_at.clone(),
+            )),
+        }
+    }
+}
+
+/// What a peer did with the records it was sent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApplyReport {
+    pub applied: usize,
+    /// Changed later on the peer, so kept as they were there.
+    pub skipped: usize,
+    pub accounts_added: usize,
+}
+
+/// The outcome of `ess replicate --peer`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReplicationReport {
+    pub peer: String,
+    /// Changes shipped, not counting accounts.
+    pub shipped: usize,
+    #[serde(flatten)]
+    pub peer_report: ApplyReport,
+}
+
+/// Where `ess replicate` ships to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Peer {
+    Ssh {
+        destination: String,
+        port: Option<u16>,
+        /// The store directory on the peer; its default store when `None`.
+        path: Option<String>,
+    },
+    Directory(PathBuf),
+}
+
+impl FromStr for Peer {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        let Some(rest) = raw.strip_prefix("ssh://") else {
+            if raw.contains("://") {
+                return Err(anyhow!(
+                    "unsupported peer '{raw}', expected ssh://host/path or a store directory"
+                ));
+            }
+            return Ok(Self::Directory(PathBuf::from(raw)));
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(slash) => (&rest[..slash], Some(&rest[slash..])),
+            None => (rest, None),
+        };
+        let (destination, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => {
+                let port = port
+                    .parse()
+                    .map_err(|_| anyhow!("invalid port in peer '{raw}'"))?;
+                (host, Some(port))
+            }
+            None => (authority, None),
+        };
+        if destination.is_empty() {
+            return Err(anyhow!("peer '{raw}' has no host"));
+        }
+        Ok(Self::Ssh {
+            destination: destination.to_string(),
+            port,
+            path: path.filter(|path| *path != "/").map(str::to_string),
+        })
+    }
+}
+
+impl Display for Peer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ssh {
+                destination,
+                port,
+                path,
+            } => {
+                write!(f, "ssh://{destination}")?;
+                if let Some(port) = port {
+                    write!(f, ":{port}")?;
+                }
+                write!(f, "{}", path.as_deref().unwrap_or(""))
+            }
+            Self::Directory(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Writes the accounts and the changes recorded after `since` to `out`,
+/// reading the feed `BATCH_SIZE` changes at a time and flushing `out` after
+/// each batch. Returns how many changes went out and the last one's
+/// sequence number.
+pub fn write_changes(db: &Database, since: i64, out: &mut impl Write) -> Result<(usize, i64)> {
+    for account in db.list_accounts()? {
+        write_record(
+            out,
+            &Record::Account {
+                account_id: account.account_id,
+                email_address: account.email_address,
+                display_name: account.display_name,
+                tenant_id: account.tenant_id,
+                account_type: account.account_type,
+            },
+        )?;
+    }
+    let mut shipped = 0;
+    let mut through = since;
+    loop {
+        let changes = db.changes_since(through, BATCH_SIZE)?;
+        let Some(last) = changes.last() else {
+            break;
+        };
+        through = last.seq;
+        shipped += changes.len();
+        for change in &changes {
+            write_record(out, &change_record(db, change)?)?;
+        }
+        out.flush()?;
+    }
+    Ok((shipped, through))
+}
+
+fn write_record(out: &mut impl Write, record: &Record) -> Result<()> {
+    serde_json::to_writer(&mut *out, record)?;
+    out.write_all(b"\n")?;
+    Ok(())
+}
+
+/// The record carrying the current state of what `change` touched.
+fn change_record(db: &Database, change: &Change) -> Result<Record> {
+    let changed_at = change.changed_at.clone();
+    Ok(match change.entity {
+        ChangeEntity::Email => {
+            let email = db.get_email(&change.key)?;
+            let attachments = match email {
+                Some(_) => db.get_attachments(&change.key)?,
+                None => Vec::new(),
+            };
+            Record::Email {
+                id: change.key.clone(),
+                changed_at,
+                email: email.map(Box::new),
+                attachments,
+            }
+        }
+        ChangeEntity::Star => Record::Star {
+            email_id: change.key.clone(),
+            changed_at,
+            starred: db.starred_at(&change.key)?.is_some(),
+        },
+        ChangeEntity::Collection => Record::Collection {
+            name: change.key.clone(),
+            changed_at,
+            exists: db.has_collection(&change.key)?,
+        },
+        ChangeEntity::CollectionEmail => {
+            let (collection, email_id): (String, String) = serde_json::from_str(&change.key)
+                .with_context(|| format!("parse change key {}", change.key))?;
+            Record::CollectionEmail {
+                member: db.collection_has_email(&collection, &email_id)?,
+                collection,
+                email_id,
+                changed_at,
+            }
+        }
+    })
+}
+
+/// Applies the records read from `input`, keeping entries changed here
+/// after the record was, and indexes the emails that changed. An email
+/// taken replaces the stored row, which drops its tags; they come back
+/// the next time this store tags pending emails (`ess tags apply`, a sync,
+/// or `ess replicate --receive` itself).
+pub fn apply_changes(
+    db: &Database,
+    index: &mut EmailIndex,
+    input: impl BufRead,
+) -> Result<ApplyReport> {
+    let mut report = ApplyReport::default();
+    let mut reindex = Vec::new();
+    let mut lines = input.lines();
+    loop {
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+        for line in lines.by_ref() {
+            let line = line.context("read replicated change")?;
+            if !line.trim().is_empty() {
+                batch.push(
+                    serde_json::from_str::<Record>(&line).context("parse replicated change")?,
+                );
+            }
+            if batch.len() == BATCH_SIZE {
+                break;
+            }
+        }
+        if batch.is_empty() {
+            break;
+        }
+        // One SQLite transaction per batch. Attachments and collections
+        // are written after it, as their methods open transactions of
+        // their own.
+        let mut after = Vec::new();
+        let tx = db.conn().unchecked_transaction()?;
+        for record in batch {
+            let change = record.change();
+            if let Some((entity, key, changed_at)) = &change {
+                let local = db.last_changed_at(*entity, key)?;
+                if local.is_some_and(|local| local >= *changed_at) {
+                    report.skipped += 1;
+                    continue;
+                }
+            }
+            match record {
+                Record::Account {
+                    account_id,
+                    email_address,
+                    display_name,
+                    tenant_id,
+                    account_type,
+                } => {
+                    if db.get_account(&account_id)?.is_none() {
+                        db.insert_account(&Account {
+                            account_id,
+                            email_address,
+                            display_name,
+                            tenant_id,
+                            account_type,
+                            enabled: false,
+                            last_sync: None,
+                            config: None,
+                        })?;
+                        report.accounts_added += 1;
+                    }
+                }
+                Record::Email {
+                    id,
+                    email: Some(mut email),
+                    attachments,
+                    changed_at,
+                } => {
+                    // A body trimmed on the sender stays here.
+                    if email.body_text.is_none() && email.body_html.is_none() {
+                        if let Some(local) = db.get_email(&id)? {
+                            email.body_text = local.body_text;
+                            email.body_html = local.body_html;
+                        }
+                    }
+                    for copy in db.merge_stored_copies(&email)? {
+                        index.delete_email_buffered(&copy)?;
+                    }
+                    db.insert_email(&email)?;
+                    db.stamp_change(ChangeEntity::Email, &id, &changed_at)?;
+                    if !attachments.is_empty() {
+                        after.push(Record::Email {
+                            id: id.clone(),
+                            changed_at,
+                            email: None,
+                            attachments,
+                        });
+                    }
+                    reindex.push(id);
+                }
+                Record::Email {
+                    id,
+                    email: None,
+                    changed_at,
+                    ..
+                } => {
+                    db.delete_email(&id)?;
+                    db.stamp_change(ChangeEntity::Email, &id, &changed_at)?;
+                    index.delete_email_buffered(&id)?;
+                }
+                Record::Star {
+                    email_id,
+                    starred,
+                    changed_at,
+                } => {
+                    if starred {
+                        db.star_email(&email_id)?;
+                    } else {
+                        db.unstar_email(&email_id)?;
+                    }
+                    db.stamp_change(ChangeEntity::Star, &email_id, &changed_at)?;
+                }
+                record @ (Record::Collection { .. } | Record::CollectionEmail { .. }) => {
+                    after.push(record);
+                }
+            }
+            if change.is_some() {
+                report.applied += 1;
+            }
+        }
+        tx.commit()?;
+        for record in after {
+            match record {
+                Record::Email {
+                    id, attachments, ..
+                } => db.replace_attachments(&id, &attachments)?,
+                Record::Collection {
+                    name,
+                    exists,
+                    changed_at,
+                } => {
+                    if exists {
+                        db.add_to_collection(&name, &[])?;
+                    } else {
+                        db.delete_collection(&name)?;
+                    }
+                    db.stamp_change(ChangeEntity::Collection, &name, &changed_at)?;
+                }
+                Record::CollectionEmail {
+                    collection,
+                    email_id,
+                    member,
+                    changed_at,
+                } => {
+                    let key = serde_json::json!([collection, email_id]).to_string();
+                    let ids = [email_id];
+                    if member {
+                        db.add_to_collection(&collection, &ids)?;
+                    } else {
+                        db.remove_from_collection(&collection, &ids)?;
+                    }
+                    db.stamp_change(ChangeEntity::CollectionEmail, &key, &changed_at)?;
+                }
+                Record::Account { .. } | Record::Star { .. } => {}
+            }
+        }
+    }
+    index.reindex_emails(db, &reindex)?;
+    Ok(report)
+}
+
+/// Ships what changed since the last run to `peer` (everything with
+/// `full`) and remembers how far it got.
+pub fn replicate(db: &Database, peer: &Peer, full: bool) -> Result<ReplicationReport> {
+    let cursor_key = format!("{CURSOR_KEY_PREFIX}{peer}");
+    let since = match db.get_sync_state(&cursor_key)? {
+        Some(state) if !full => state
+            .value
+            .as_deref()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(0),
+        _ => 0,
+    };
+    let (shipped, through, peer_report) = match peer {
+        Peer::Directory(dir) => apply_to_directory(db, since, dir)?,
+        Peer::Ssh {
+            destination,
+            port,
+            path,
+        } => apply_over_ssh(db, since, destination, *port, path.as_deref())?,
+    };
+    db.set_sync_state(&cursor_key, &through.to_string())?;
+    Ok(ReplicationReport {
+        peer: peer.to_string(),
+        shipped,
+        peer_report,
+    })
+}
+
+/// Opens the store in `dir` (`ess.db` and `index/`), creating it if needed.
+pub fn open_store(dir: &Path) -> Result<(Database, EmailIndex)> {
+    let db_path = dir.join("ess.db");
+    let db = Database::open(&db_path)
+        .with_context(|| format!("open ESS database at {}", db_path.display()))?;
+    let index_path = dir.join("index");
+    let index = EmailIndex::open(&index_path)
+        .with_context(|| format!("open ESS index at {}", index_path.display()))?;
+    Ok((db, index))
+}
+
+/// Streams the changes after `since` through a pipe to a thread applying
+/// them to the store in `dir`.
+fn apply_to_directory(db: &Database, since: i64, dir: &Path) -> Result<(usize, i64, ApplyReport)> {
+    let (reader, writer) = std::io::pipe().context("open replication pipe")?;
+    let applier = std::thread::spawn({
+        let dir = dir.to_path_buf();
+        move || -> Result<ApplyReport> {
+            let (db, mut index) = open_store(&dir)?;
+            apply_changes(&db, &mut index, BufReader::new(reader))
+        }
+    });
+    let mut out = BufWriter::new(writer);
+    let written = write_changes(db, since, &mut out);
+    // Closing the pipe ends the applier's input.
+    drop(out);
+    let report = applier
+        .join()
+        .map_err(|_| anyhow!("applying changes panicked"))??;
+    let (shipped, through) = written?;
+    Ok((shipped, through, report))
+}
+
+/// Streams the changes after `since` into `ess replicate --receive` on
+/// `destination` and reads back its report.
+fn apply_over_ssh(
+    db: &Database,
+    since: i64,
+    destination: &str,
+    port: Option<u16>,
+    path: Option<&str>,
+) -> Result<(usize, i64, ApplyReport)> {
+    let mut remote = String::from("ess replicate --receive");
+    if let Some(path) = path {
+        remote.push_str(&format!(" --store '{}'", path.replace('\'', r"'\''")));
+    }
+    let mut command = Command::new("ssh");
+    if let Some(port) = port {
+        command.args(["-p", &port.to_string()]);
+    }
+    let mut child = command
+        .args(["--", destination, &remote])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("run ssh")?;
+    let stdout = child.stdout.take().context("open ssh stdout")?;
+    let reader = std::thread::spawn(move || {
+        BufReader::new(stdout)
+            .lines()
+            .map_while(Result::ok)
+            .filter(|line| !line.trim().is_empty())
+            .last()
+    });
+    let mut stdin = BufWriter::new(child.stdin.take().context("open ssh stdin")?);
+    let written = write_changes(db, since, &mut stdin);
+    drop(stdin);
+    let status = child.wait().context("wait for ssh")?;
+    let last_line = reader
+        .join()
+        .map_err(|_| anyhow!("reading from ssh panicked"))?;
+    if !status.success() {
+        return Err(anyhow!(
+            "ess replicate --receive on {destination} failed ({status})"
+        ));
+    }
+    let (shipped, through) = written.context("send changes over ssh")?;
+    let last_line = last_line.ok_or_else(|| anyhow!("{destination} sent no report"))?;
+    let report = serde_json::from_str(&last_line)
+        .with_context(|| format!("parse the report from {destination}: {last_line}"))?;
+    Ok((shipped, through, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_changes, open_store, replicate, write_changes, Peer, BATCH_SIZE};
+    use crate::db::models::Email;
+    use crate::db::ChangeEntity;
+    use crate::indexer::SearchFilters;
+
+    #[test]
+    fn parses_peers() {
+        assert_eq!(
+            "ssh://me@desk:2222/home/me/ess".parse::<Peer>().unwrap(),
+            Peer::Ssh {
+                destination: "me@desk".to_string(),
+                port: Some(2222),
+                path: Some("/home/me/ess".to_string()),
+            }
+        );
+        let desk: Peer = "ssh://desk".parse().unwrap();
+        assert_eq!(desk.to_string(), "ssh://desk");
+        assert!(matches!(
+            "/mnt/laptop/ess".parse::<Peer>().unwrap(),
+            Peer::Directory(_)
+        ));
+        assert!("https://desk".parse::<Peer>().is_err());
+    }
+
+    #[test]
+    fn later_changes_win_on_either_side() {
+        let root = std::env::temp_dir().join(format!("ess-replicate-{}", uuid::Uuid::new_v4()));
+        let (desk, _) = open_store(&root.join("desk")).expect("desk");
+        let (laptop, mut laptop_index) = open_store(&root.join("laptop")).expect("laptop");
+        let email: Email = serde_json::from_value(serde_json::json!({
+            "id": "m1",
+            "subject": "Offsite plan",
+            "from_address": "alice@example.com",
+            "to_addresses": [],
+            "cc_addresses": [],
+            "bcc_addresses": [],
+            "body_text": "venue options",
+            "received_at": "2026-03-01T00:00:00Z",
+            "categories": [],
+        }))
+        .expect("email");
+        desk.insert_email(&email).expect("insert");
+        desk.star_email("m1").expect("star");
+
+        let mut batch = Vec::new();
+        let (shipped, through) = write_changes(&desk, 0, &mut batch).expect("write");
+        assert_eq!(shipped, 2);
+        let report = apply_changes(&laptop, &mut laptop_index, batch.as_slice()).expect("apply");
+        assert_eq!(report.applied, 2);
+        assert!(laptop.get_email("m1").expect("get").is_some());
+        assert!(laptop.starred_at("m1").expect("starred").is_some());
+        assert_eq!(
+            laptop_index
+                .search("offsite", &SearchFilters::default(), 10)
+                .expect("search")
+                .len(),
+            1
+        );
+
+        // The laptop unstars later than the desk starred, so a stale star
+        // from the desk no longer applies, and the unstar goes back.
+        laptop.unstar_email("m1").expect("unstar");
+        let again = apply_changes(&laptop, &mut laptop_index, batch.as_slice()).expect("apply");
+        assert_eq!(again.applied, 0);
+        assert!(laptop.starred_at("m1").expect("starred").is_none());
+
+        let mut back = Vec::new();
+        write_changes(&laptop, 0, &mut back).expect("write");
+        let (_, mut desk_index) = open_store(&root.join("desk")).expect("desk index");
+        let report = apply_changes(&desk, &mut desk_index, back.as_slice()).expect("apply");
+        assert_eq!(report.applied, 1);
+        assert!(desk.starred_at("m1").expect("starred").is_none());
+        assert!(desk
+            .changes_since(through, 100)
+            .expect("changes")
+            .iter()
+            .any(|change| change.entity == ChangeEntity::Star));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn directory_peers_take_the_stream_in_batches() {
+        let root = std::env::temp_dir().join(format!("ess-replicate-{}", uuid::Uuid::new_v4()));
+        let (desk, _) = open_store(&root.join("desk")).expect("desk");
+        for n in 0..=BATCH_SIZE {
+            desk.insert_email(&Email::fixture(&format!("m{n}")))
+                .expect("insert");
+        }
+        let peer = Peer::Directory(root.join("laptop"));
+
+        let report = replicate(&desk, &peer, false).expect("replicate");
+        assert_eq!(report.shipped, BATCH_SIZE + 1);
+        assert_eq!(report.peer_report.applied, BATCH_SIZE + 1);
+        let (laptop, _) = open_store(&root.join("laptop")).expect("laptop");
+        let stored: usize = laptop
+            .conn()
+            .query_row("SELECT COUNT(*) FROM emails", [], |row| row.get(0))
+            .expect("count");
+        assert_eq!(stored, BATCH_SIZE + 1);
+        assert_eq!(replicate(&desk, &peer, false).expect("again").shipped, 0);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,64 @@
+use anyhow::Result;
+use rusqlite::params_from_iter;
+
+use crate::db::Database;
+use crate::indexer::EmailIndex;
+use crate::search::filters::{EmailFilters, Scope};
+use crate::search::{search_emails, SearchResult};
+
+/// How many top hits each side of a comparison shows.
+pub const DEFAULT_COMPARE_SAMPLES: usize = 5;
+
+/// Matches for one scope: the full count and date span (from SQLite, so not
+/// capped by the sample size) plus the best-ranked hits.
+#[derive(Debug, Clone)]
+pub struct ScopeMatches {
+    pub scope: Scope,
+    pub total: i64,
+    pub first_received_at: Option<String>,
+    pub last_received_at: Option<String>,
+    pub samples: Vec<SearchResult>,
+}
+
+/// Runs `query` once per account type so professional and personal hits can
+/// be read side by side. `filters.scope` is ignored.
+pub fn compare_scopes(
+    index: &EmailIndex,
+    db: &Database,
+    query: &str,
+    filters: &EmailFilters,
+    samples: usize,
+) -> Result<Vec<ScopeMatches>> {
+    let mut comparison = Vec::with_capacity(2);
+    for scope in [Scope::Professional, Scope::Personal] {
+        let mut scoped = filters.clone();
+        scoped.scope = scope;
+        scoped.limit = samples.max(1);
+        scoped.offset = 0;
+
+        let hits = search_emails(index, db, query, &scoped)?;
+
+        if !query.trim().is_empty() {
+            scoped.query = Some(query.to_string());
+        }
+        let predicate = scoped.to_sql_where();
+        let sql = format!(
+            "SELECT COUNT(*), MIN(received_at), MAX(received_at) FROM emails WHERE {}",
+            predicate.clause
+        );
+        let (total, first_received_at, last_received_at) =
+            db.conn()
+                .query_row(&sql, params_from_iter(predicate.params.iter()), |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+                })?;
+
+        comparison.push(ScopeMatches {
+            scope,
+            total,
+            first_received_at,
+            last_received_at,
+            samples: hits,
+        });
+    }
+    Ok(comparison)
+}
@@ -0,0 +1,136 @@
+//! Opaque `next_cursor` tokens for paging through `ess search`/`ess list`
+//! and the MCP tools without offsets.
+
+use std::fmt;
+use std::str::FromStr;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use thiserror::Error;
+
+use crate::db::EmailKeyset;
+use crate::indexer::HitPosition;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum CursorError {
+    #[error("invalid cursor '{0}'")]
+    Invalid(String),
+    #[error("cursor is for a {found} listing, not a {expected} one")]
+    WrongKind {
+        expected: &'static str,
+        found: &'static str,
+    },
+}
+
+/// Where the previous page ended.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cursor {
+    /// Position in the index ranking, for full-text search.
+    Ranked(HitPosition),
+    /// Position in the newest-first listing, for `ess list`/`ess_recent`.
+    Recency(EmailKeyset),
+}
+
+impl Cursor {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Ranked(_) => "search",
+            Self::Recency(_) => "list",
+        }
+    }
+
+    pub fn into_ranked(self) -> Result<HitPosition, CursorError> {
+        match self {
+            Self::Ranked(position) => Ok(position),
+            other => Err(CursorError::WrongKind {
+                expected: "search",
+                found: other.kind(),
+            }),
+        }
+    }
+
+    pub fn into_recency(self) -> Result<EmailKeyset, CursorError> {
+        match self {
+            Self::Recency(keyset) => Ok(keyset),
+            other => Err(CursorError::WrongKind {
+                expected: "list",
+                found: other.kind(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let raw = match self {
+            Self::Ranked(position) => {
+                format!("r|{:08x}|{}", position.score.to_bits(), position.email_id)
+            }
+            Self::Recency(keyset) => format!("d|{}|{}", keyset.received_at, keyset.id),
+        };
+        f.write_str(&URL_SAFE_NO_PAD.encode(raw))
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = CursorError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || CursorError::Invalid(value.to_string());
+        let raw = URL_SAFE_NO_PAD
+            .decode(value.trim())
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(invalid)?;
+
+        match raw.split_once('|').ok_or_else(invalid)? {
+            // The ids come last so they may themselves contain `|`.
+            ("r", rest) => {
+                let (score, email_id) = rest.split_once('|').ok_or_else(invalid)?;
+                Ok(Self::Ranked(HitPosition {
+                    score: f32::from_bits(u32::from_str_radix(score, 16).map_err(|_| invalid())?),
+                    email_id: email_id.to_string(),
+                }))
+            }
+            ("d", rest) => {
+                let (received_at, id) = rest.split_once('|').ok_or_else(invalid)?;
+                Ok(Self::Recency(EmailKeyset {
+                    received_at: received_at.to_string(),
+                    id: id.to_string(),
+                }))
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::EmailKeyset;
+    use crate::indexer::HitPosition;
+
+    use super::{Cursor, CursorError};
+
+    #[test]
+    fn cursors_round_trip_through_tokens() {
+        let ranked = Cursor::Ranked(HitPosition {
+            score: 0.1 + 0.2,
+            email_id: "AAMkAD|42".to_string(),
+        });
+        let recency = Cursor::Recency(EmailKeyset {
+            received_at: "2026-02-01T12:00:00Z".to_string(),
+            id: "AAMk|odd=id".to_string(),
+        });
+
+        for cursor in [ranked, recency] {
+            let token = cursor.to_string();
+            assert!(!token.contains('|'));
+            assert_eq!(token.parse::<Cursor>(), Ok(cursor));
+        }
+
+        assert!(matches!(
+            "not a cursor".parse::<Cursor>(),
+            Err(CursorError::Invalid(_))
+        ));
+    }
+}
@@ -0,0 +1,257 @@
+//! Absolute and relative dates for `--since`/`--until` and the MCP tools:
+//! `2026-01-31`, `today`, `yesterday`, `7d`, `2w`, `3mo`, `1y`,
+//! `2 weeks ago`, `monday`, `last friday`, `this month`, `last week`,
+//! `start of last month`, `end of last month`. Everything resolves to a
+//! calendar day; weeks start on Monday.
+
+use chrono::{Datelike, Days, Local, Months, NaiveDate, Weekday};
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum DateParseError {
+    #[error(
+        "unrecognized date '{0}', expected YYYY-MM-DD, 'yesterday', '7d', '2 weeks ago', 'last monday' or 'end of last month'"
+    )]
+    Unrecognized(String),
+    #[error("date '{0}' is out of range")]
+    OutOfRange(String),
+}
+
+/// Resolves `input` against the local calendar day.
+pub fn parse_date(input: &str) -> Result<NaiveDate, DateParseError> {
+    parse_date_relative_to(input, Local::now().date_naive())
+}
+
+pub fn parse_date_relative_to(input: &str, today: NaiveDate) -> Result<NaiveDate, DateParseError> {
+    let normalized = input
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    let unrecognized = || DateParseError::Unrecognized(input.trim().to_string());
+    let out_of_range = || DateParseError::OutOfRange(input.trim().to_string());
+
+    if let Ok(date) = NaiveDate::parse_from_str(&normalized, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(&normalized, "%Y/%m/%d"))
+    {
+        return Ok(date);
+    }
+
+    match normalized.as_str() {
+        "today" | "now" => return Ok(today),
+        "yesterday" => {
+            return today
+                .checked_sub_days(Days::new(1))
+                .ok_or_else(out_of_range)
+        }
+        "tomorrow" => {
+            return today
+                .checked_add_days(Days::new(1))
+                .ok_or_else(out_of_range)
+        }
+        _ => {}
+    }
+
+    if let Some((amount, unit)) = split_compact_offset(&normalized) {
+        return subtract(today, amount, unit).ok_or_else(out_of_range);
+    }
+
+    let words = normalized.split(' ').collect::<Vec<_>>();
+    match words.as_slice() {
+        [amount, unit, "ago"] => {
+            let amount = parse_amount(amount).ok_or_else(unrecognized)?;
+            let unit = parse_unit(unit).ok_or_else(unrecognized)?;
+            subtract(today, amount, unit).ok_or_else(out_of_range)
+        }
+        [day] if parse_weekday(day).is_some() => {
+            let weekday = parse_weekday(day).ok_or_else(unrecognized)?;
+            previous_weekday(today, weekday, true).ok_or_else(out_of_range)
+        }
+        ["last", day] if parse_weekday(day).is_some() => {
+            let weekday = parse_weekday(day).ok_or_else(unrecognized)?;
+            previous_weekday(today, weekday, false).ok_or_else(out_of_range)
+        }
+        [which, period] | ["start" | "beginning", "of", which, period] => {
+            let (start, _) = period_bounds(today, which, period)
+                .ok_or_else(unrecognized)?
+                .ok_or_else(out_of_range)?;
+            Ok(start)
+        }
+        ["end", "of", which, period] => {
+            let (_, end) = period_bounds(today, which, period)
+                .ok_or_else(unrecognized)?
+                .ok_or_else(out_of_range)?;
+            Ok(end)
+        }
+        _ => Err(unrecognized()),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Unit {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+/// `7d`, `2w`, `3mo`, `1y`.
+fn split_compact_offset(value: &str) -> Option<(u32, Unit)> {
+    let split = value.find(|c: char| !c.is_ascii_digit())?;
+    let (digits, suffix) = value.split_at(split);
+    let amount = digits.parse().ok()?;
+    let unit = match suffix.trim() {
+        "d" => Unit::Day,
+        "w" => Unit::Week,
+        "mo" => Unit::Month,
+        "y" => Unit::Year,
+        _ => return None,
+    };
+    Some((amount, unit))
+}
+
+fn parse_amount(value: &str) -> Option<u32> {
+    match value {
+        "a" | "an" | "one" => Some(1),
+        "two" => Some(2),
+        "three" => Some(3),
+        _ => value.parse().ok(),
+    }
+}
+
+fn parse_unit(value: &str) -> Option<Unit> {
+    match value.trim_end_matches('s') {
+        "day" => Some(Unit::Day),
+        "week" => Some(Unit::Week),
+        "month" => Some(Unit::Month),
+        "year" => Some(Unit::Year),
+        _ => None,
+    }
+}
+
+fn parse_weekday(value: &str) -> Option<Weekday> {
+    match value {
+        "mon" | "monday" => Some(Weekday::Mon),
+        "tue" | "tues" | "tuesday" => Some(Weekday::Tue),
+        "wed" | "wednesday" => Some(Weekday::Wed),
+        "thu" | "thurs" | "thursday" => Some(Weekday::Thu),
+        "fri" | "friday" => Some(Weekday::Fri),
+        "sat" | "saturday" => Some(Weekday::Sat),
+        "sun" | "sunday" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+fn subtract(today: NaiveDate, amount: u32, unit: Unit) -> Option<NaiveDate> {
+    match unit {
+        Unit::Day => today.checked_sub_days(Days::new(amount.into())),
+        Unit::Week => today.checked_sub_days(Days::new(u64::from(amount) * 7)),
+        Unit::Month => today.checked_sub_months(Months::new(amount)),
+        Unit::Year => today.checked_sub_months(Months::new(amount.checked_mul(12)?)),
+    }
+}
+
+/// Most recent `weekday` on or before `today` (`include_today`) or strictly
+/// before it.
+fn previous_weekday(today: NaiveDate, weekday: Weekday, include_today: bool) -> Option<NaiveDate> {
+    let mut back =
+        (7 + today.weekday().num_days_from_monday() - weekday.num_days_from_monday()) % 7;
+    if back == 0 && !include_today {
+        back = 7;
+    }
+    today.checked_sub_days(Days::new(back.into()))
+}
+
+/// First and last day of `this|last week|month|year`. The outer `None` means
+/// the words are not a period; the inner one that the dates overflow.
+fn period_bounds(
+    today: NaiveDate,
+    which: &str,
+    period: &str,
+) -> Option<Option<(NaiveDate, NaiveDate)>> {
+    let back = match which {
+        "this" => 0,
+        "last" | "previous" => 1,
+        _ => return None,
+    };
+    let bounds = match period {
+        "week" => {
+            let monday = today
+                .checked_sub_days(Days::new(today.weekday().num_days_from_monday().into()))
+                .and_then(|monday| monday.checked_sub_days(Days::new(7 * back)));
+            monday.and_then(|start| Some((start, start.checked_add_days(Days::new(6))?)))
+        }
+        "month" => today
+            .with_day(1)
+            .and_then(|first| first.checked_sub_months(Months::new(back as u32)))
+            .and_then(|start| {
+                let end = start
+                    .checked_add_months(Months::new(1))?
+                    .checked_sub_days(Days::new(1))?;
+                Some((start, end))
+            }),
+        "year" => NaiveDate::from_ymd_opt(today.year() - back as i32, 1, 1)
+            .and_then(|start| Some((start, NaiveDate::from_ymd_opt(start.year(), 12, 31)?))),
+        _ => return None,
+    };
+    Some(bounds)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::{parse_date_relative_to, DateParseError};
+
+    fn day(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).expect("valid date")
+    }
+
+    #[test]
+    fn resolves_relative_expressions() {
+        // A Wednesday.
+        let today = day(2026, 3, 18);
+        let parse = |input: &str| parse_date_relative_to(input, today).expect(input);
+
+        assert_eq!(parse("2026-01-31"), day(2026, 1, 31));
+        assert_eq!(parse("today"), today);
+        assert_eq!(parse("Yesterday"), day(2026, 3, 17));
+        assert_eq!(parse("7d"), day(2026, 3, 11));
+        assert_eq!(parse("2w"), day(2026, 3, 4));
+        assert_eq!(parse("3mo"), day(2025, 12, 18));
+        assert_eq!(parse("1y"), day(2025, 3, 18));
+        assert_eq!(parse("2 weeks ago"), day(2026, 3, 4));
+        assert_eq!(parse("a month  ago"), day(2026, 2, 18));
+        assert_eq!(parse("monday"), day(2026, 3, 16));
+        assert_eq!(parse("wednesday"), today);
+        assert_eq!(parse("last wednesday"), day(2026, 3, 11));
+        assert_eq!(parse("last monday"), day(2026, 3, 16));
+        assert_eq!(parse("this week"), day(2026, 3, 16));
+        assert_eq!(parse("last week"), day(2026, 3, 9));
+        assert_eq!(parse("end of last week"), day(2026, 3, 15));
+        assert_eq!(parse("this month"), day(2026, 3, 1));
+        assert_eq!(parse("start of last month"), day(2026, 2, 1));
+        assert_eq!(parse("end of last month"), day(2026, 2, 28));
+        assert_eq!(parse("end of this year"), day(2026, 12, 31));
+    }
+
+    #[test]
+    fn rejects_unknown_expressions() {
+        let today = day(2026, 3, 18);
+        for input in [
+            "soon",
+            "last fortnight",
+            "3 lightyears ago",
+            "5x",
+            "end of monday",
+        ] {
+            assert!(
+                matches!(
+                    parse_date_relative_to(input, today),
+                    Err(DateParseError::Unrecognized(_))
+                ),
+                "{input} should be rejected"
+            );
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::Result;
+use rusqlite::params_from_iter;
+use serde::Serialize;
+
+use crate::db::Database;
+use crate::search::filters::EmailFilters;
+
+/// How many values each facet reports, most frequent first.
+pub const DEFAULT_FACET_LIMIT: usize = 10;
+
+/// A dimension search results can be counted by, like a mail client sidebar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Facet {
+    Sender,
+    /// The sender's domain, from the indexed `from_domain` column.
+    Domain,
+    Folder,
+    Month,
+}
+
+impl Facet {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Sender => "sender",
+            Self::Domain => "domain",
+            Self::Folder => "folder",
+            Self::Month => "month",
+        }
+    }
+
+    /// SQL expression over `emails` that yields the bucket for a row.
+    fn sql_value(self) -> &'static str {
+        match self {
+            Self::Sender => "LOWER(COALESCE(from_address, ''))",
+            Self::Domain => "COALESCE(from_domain, '')",
+            Self::Folder => "COALESCE(folder, '')",
+            Self::Month => "SUBSTR(received_at, 1, 7)",
+        }
+    }
+
+    /// Months read best newest first; everything else by frequency.
+    fn sql_order(self) -> &'static str {
+        match self {
+            Self::Month => "value DESC",
+            Self::Sender | Self::Domain | Self::Folder => "count DESC, value ASC",
+        }
+    }
+}
+
+impl fmt::Display for Facet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Facet {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "sender" | "from" => Ok(Self::Sender),
+            "domain" => Ok(Self::Domain),
+            "folder" => Ok(Self::Folder),
+            "month" => Ok(Self::Month),
+            other => Err(format!(
+                "invalid facet: {other} (expected sender, domain, folder or month)"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FacetCounts {
+    pub facet: Facet,
+    pub counts: Vec<FacetCount>,
+}
+
+/// Counts every message matching `query` and `filters` (not just the returned
+/// page) per facet value, with a SQL `GROUP BY` over the same predicate
+/// `EmailFilters::to_sql_where` builds.
+pub fn facet_counts(
+    db: &Database,
+    query: &str,
+    filters: &EmailFilters,
+    facets: &[Facet],
+    limit: usize,
+) -> Result<Vec<FacetCounts>> {
+    let mut filters = filters.clone();
+    if !query.trim().is_empty() {
+        filters.query = Some(query.to_string());
+    }
+    let predicate = filters.to_sql_where();
+
+    let mut results = Vec::with_capacity(facets.len());
+    for facet in facets {
+        let sql = format!(
+            "SELECT {value} AS value, COUNT(*) AS count FROM emails WHERE {clause} \
+             GROUP BY value ORDER BY {order} LIMIT {limit}",
+            value = facet.sql_value(),
+            clause = predicate.clause,
+            order = facet.sql_order(),
+            limit = limit.max(1),
+        );
+        let mut stmt = db.conn().prepare(&sql)?;
+        let counts = stmt
+            .query_map(params_from_iter(predicate.params.iter()), |row| {
+                Ok(FacetCount {
+                    value: row.get(0)?,
+                    count: row.get(1)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        results.push(FacetCounts {
+            facet: *facet,
+            counts,
+        });
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::models::{Account, AccountType, Email};
+    use crate::db::Database;
+    use crate::search::filters::EmailFilters;
+
+    use super::{facet_counts, Facet, FacetCount};
+
+    fn email(id: &str, from: &str, folder: &str, received_at: &str) -> Email {
+        Email {
+            account_id: Some("acc".to_string()),
+            subject: Some(format!("Budget {id}")),
+            from_address: Some(from.to_string()),
+            body_text: Some("numbers attached".to_string()),
+            received_at: received_at.to_string(),
+            is_read: Some(true),
+            has_attachments: Some(false),
+            folder: Some(folder.to_string()),
+            ..Email::fixture(id)
+        }
+    }
+
+    #[test]
+    fn counts_matching_messages_per_facet() {
+        let path = std::env::temp_dir().join(format!("ess-facets-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        db.insert_account(&Account {
+            account_id: "acc".to_string(),
+            email_address: "me@example.com".to_string(),
+            display_name: None,
+            tenant_id: None,
+            account_type: AccountType::Professional,
+            enabled: true,
+            last_sync: None,
+            config: None,
+        })
+        .expect("insert account");
+        for email in [
+            email("1", "Alice@example.com", "inbox", "2026-01-05T09:00:00Z"),
+            email("2", "alice@example.com", "archive", "2026-02-01T09:00:00Z"),
+            email("3", "bob@example.com", "inbox", "2026-02-03T09:00:00Z"),
+        ] {
+            db.insert_email(&email).expect("insert email");
+        }
+        let mut unrelated = email("4", "carol@example.com", "inbox", "2026-02-04T09:00:00Z");
+        unrelated.subject = Some("Lunch".to_string());
+        db.insert_email(&unrelated).expect("insert email");
+
+        let facets = facet_counts(
+            &db,
+            "budget",
+            &EmailFilters::default(),
+            &[Facet::Sender, Facet::Folder, Facet::Month],
+            10,
+        )
+        .expect("facet counts");
+
+        let count = |value: &str, count| FacetCount {
+            value: value.to_string(),
+            count,
+        };
+        assert_eq!(
+            facets[0].counts,
+            vec![count("alice@example.com", 2), count("bob@example.com", 1)]
+        );
+        assert_eq!(
+            facets[1].counts,
+            vec![count("inbox", 2), count("archive", 1)]
+        );
+        assert_eq!(
+            facets[2].counts,
+            vec![count("2026-02", 2), count("2026-01", 1)]
+        );
+        let domains = facet_counts(&db, "", &EmailFilters::default(), &[Facet::Domain], 10)
+            .expect("domain facet counts");
+        assert_eq!(domains[0].counts, vec![count("example.com", 4)]);
+        assert_eq!("from".parse::<Facet>(), Ok(Facet::Sender));
+        assert!("size".parse::<Facet>().is_err());
+
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -3,10 +3,16 @@ use std::str::FromStr;
 
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, NaiveDate, Utc};
-use tantivy::query::{AllQuery, BooleanQuery, Occur, Query, QueryParser, RangeQuery, TermQuery};
+use tantivy::query::{AllQuery, BooleanQuery, Occur, Query, RangeQuery, TermQuery};
 use tantivy::schema::IndexRecordOption;
 use tantivy::{DateTime as TantivyDateTime, Index, Term};
 
+use crate::db::attachments::attachment_predicate;
+use crate::db::models::normalize_domain;
+pub use crate::db::sql::SqlWhereClause;
+use crate::indexer::schema::RECIPIENT_FIELDS;
+use crate::search::query::parse_query_or_words;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum Scope {
     Professional,
@@ -16,6 +22,14 @@ pub enum Scope {
 }
 
 impl Scope {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Professional => "professional",
+            Self::Personal => "personal",
+            Self::All => "all",
+        }
+    }
+
     fn account_type_filter(self) -> Option<&'static str> {
         match self {
             Self::Professional => Some("professional"),
@@ -38,23 +52,26 @@ impl FromStr for Scope {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct SqlWhereClause {
-    pub clause: String,
-    pub params: Vec<String>,
-}
-
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct EmailFilters {
     pub query: Option<String>,
     pub scope: Scope,
     pub from: Option<String>,
+    /// Exact sender domain; matched against the indexed `from_domain`.
+    pub from_domain: Option<String>,
     pub to: Option<String>,
     pub since: Option<NaiveDate>,
     pub until: Option<NaiveDate>,
     pub account: Option<String>,
     pub folder: Option<String>,
     pub unread_only: bool,
+    pub has_attachment: bool,
+    /// Case-insensitive glob over attachment file names, e.g. `*budget*`.
+    pub attachment_name: Option<String>,
+    /// `pdf`, `spreadsheet`, `image`, ..., a file extension or a MIME type.
+    pub attachment_type: Option<String>,
+    /// Tagged with this by the rules in `rules.toml`.
+    pub tag: Option<String>,
     pub limit: usize,
     pub offset: usize,
 }
@@ -65,12 +82,17 @@ impl Default for EmailFilters {
             query: None,
             scope: Scope::All,
             from: None,
+            from_domain: None,
             to: None,
             since: None,
             until: None,
             account: None,
             folder: None,
             unread_only: false,
+            has_attachment: false,
+            attachment_name: None,
+            attachment_type: None,
+            tag: None,
             limit: 20,
             offset: 0,
         }
@@ -82,13 +104,9 @@ impl EmailFilters {
         let schema = index.schema();
         let get_field = |name: &str| schema.get_field(name).ok();
 
-        let subject_field = get_field("subject");
-        let from_name_field = get_field("from_name");
-        let body_text_field = get_field("body_text");
-
-        let query_fields: Vec<_> = [subject_field, from_name_field, body_text_field]
+        let query_fields: Vec<_> = ["subject", "from_name", "body_text"]
             .into_iter()
-            .flatten()
+            .filter_map(get_field)
             .collect();
 
         let base_query: Box<dyn Query> = if let Some(query) = self
@@ -103,20 +121,11 @@ impl EmailFilters {
                 ));
             }
 
-            let mut parser = QueryParser::for_index(index, query_fields);
-            if let Some(field) = subject_field {
-                parser.set_field_boost(field, 5.0);
-            }
-            if let Some(field) = from_name_field {
-                parser.set_field_boost(field, 3.0);
-            }
-            if let Some(field) = body_text_field {
-                parser.set_field_boost(field, 1.0);
-            }
-
-            parser
-                .parse_query(query)
-                .map_err(|error| anyhow!("failed to parse query '{query}': {error}"))?
+            parse_query_or_words(query)
+                .map(|node| node.to_tantivy(index))
+                .transpose()?
+                .flatten()
+                .unwrap_or_else(|| Box::new(AllQuery))
         } else {
             Box::new(AllQuery)
         };
@@ -161,20 +170,39 @@ impl EmailFilters {
             ));
         }
 
-        if let (Some(to_address), Some(field)) = (
-            self.to
-                .as_deref()
-                .map(str::trim)
-                .filter(|value| !value.is_empty()),
-            get_field("to_addresses"),
-        ) {
-            let term = Term::from_field_text(field, &to_address.to_ascii_lowercase());
+        if let (Some(domain), Some(field)) = (self.domain(), get_field("from_domain")) {
+            let term = Term::from_field_text(field, &domain);
             clauses.push((
                 Occur::Must,
                 Box::new(TermQuery::new(term, IndexRecordOption::Basic)),
             ));
         }
 
+        if let Some(to_address) = self
+            .to
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+        {
+            let address = to_address.to_lowercase();
+            let recipients = RECIPIENT_FIELDS
+                .into_iter()
+                .filter_map(get_field)
+                .map(|field| {
+                    (
+                        Occur::Should,
+                        Box::new(TermQuery::new(
+                            Term::from_field_text(field, &address),
+                            IndexRecordOption::Basic,
+                        )) as Box<dyn Query>,
+                    )
+                })
+                .collect::<Vec<_>>();
+            if !recipients.is_empty() {
+                clauses.push((Occur::Must, Box::new(BooleanQuery::new(recipients))));
+            }
+        }
+
         if let (Some(account_id), Some(field)) = (
             self.account
                 .as_deref()
@@ -189,6 +217,14 @@ impl EmailFilters {
             ));
         }
 
+        if let (true, Some(field)) = (self.unread_only, get_field("is_read")) {
+            let term = Term::from_field_bool(field, false);
+            clauses.push((
+                Occur::Must,
+                Box::new(TermQuery::new(term, IndexRecordOption::Basic)),
+            ));
+        }
+
         if let Some(field) = get_field("received_at") {
             let lower_bound = self
                 .since
@@ -230,8 +266,7 @@ impl EmailFilters {
     }
 
     pub fn to_sql_where(&self) -> SqlWhereClause {
-        let mut fragments: Vec<String> = Vec::new();
-        let mut params: Vec<String> = Vec::new();
+        let mut conditions = Vec::new();
 
         if let Some(query) = self
             .query
@@ -239,23 +274,16 @@ impl EmailFilters {
             .map(str::trim)
             .filter(|value| !value.is_empty())
         {
-            fragments.push(
-                "(subject LIKE ? OR body_text LIKE ? OR from_name LIKE ? OR from_address LIKE ?)"
-                    .to_string(),
-            );
-            let pattern = format!("%{query}%");
-            params.push(pattern.clone());
-            params.push(pattern.clone());
-            params.push(pattern.clone());
-            params.push(pattern);
+            if let Some(node) = parse_query_or_words(query) {
+                conditions.push(node.to_sql());
+            }
         }
 
         if let Some(account_type) = self.scope.account_type_filter() {
-            fragments.push(
-                "account_id IN (SELECT account_id FROM accounts WHERE account_type = ?)"
-                    .to_string(),
-            );
-            params.push(account_type.to_string());
+            conditions.push(SqlWhereClause::new(
+                "account_id IN (SELECT account_id FROM accounts WHERE account_type = ?)",
+                [account_type.to_string()],
+            ));
         }
 
         if let Some(from_address) = self
@@ -264,8 +292,14 @@ impl EmailFilters {
             .map(str::trim)
             .filter(|value| !value.is_empty())
         {
-            fragments.push("LOWER(from_address) = LOWER(?)".to_string());
-            params.push(from_address.to_string());
+            conditions.push(SqlWhereClause::new(
+                "LOWER(from_address) = LOWER(?)",
+                [from_address.to_string()],
+            ));
+        }
+
+        if let Some(domain) = self.domain() {
+            conditions.push(SqlWhereClause::new("from_domain = ?", [domain]));
         }
 
         if let Some(to_address) = self
@@ -274,24 +308,24 @@ impl EmailFilters {
             .map(str::trim)
             .filter(|value| !value.is_empty())
         {
-            fragments.push(
-                "(LOWER(to_addresses) LIKE LOWER(?) OR LOWER(cc_addresses) LIKE LOWER(?) OR LOWER(bcc_addresses) LIKE LOWER(?))"
-                    .to_string(),
-            );
-            let pattern = format!("%{to_address}%");
-            params.push(pattern.clone());
-            params.push(pattern.clone());
-            params.push(pattern);
+            conditions.push(SqlWhereClause::new(
+                "(LOWER(to_addresses) LIKE LOWER(?) OR LOWER(cc_addresses) LIKE LOWER(?) OR LOWER(bcc_addresses) LIKE LOWER(?))",
+                vec![format!("%{to_address}%"); 3],
+            ));
         }
 
         if let Some(since) = self.since {
-            fragments.push("DATE(received_at) >= DATE(?)".to_string());
-            params.push(since.to_string());
+            conditions.push(SqlWhereClause::new(
+                "DATE(received_at) >= DATE(?)",
+                [since.to_string()],
+            ));
         }
 
         if let Some(until) = self.until {
-            fragments.push("DATE(received_at) <= DATE(?)".to_string());
-            params.push(until.to_string());
+            conditions.push(SqlWhereClause::new(
+                "DATE(received_at) <= DATE(?)",
+                [until.to_string()],
+            ));
         }
 
         if let Some(account_id) = self
@@ -300,8 +334,10 @@ impl EmailFilters {
             .map(str::trim)
             .filter(|value| !value.is_empty())
         {
-            fragments.push("account_id = ?".to_string());
-            params.push(account_id.to_string());
+            conditions.push(SqlWhereClause::new(
+                "account_id = ?",
+                [account_id.to_string()],
+            ));
         }
 
         if let Some(folder) = self
@@ -310,22 +346,46 @@ impl EmailFilters {
             .map(str::trim)
             .filter(|value| !value.is_empty())
         {
-            fragments.push("folder = ?".to_string());
-            params.push(folder.to_string());
+            conditions.push(SqlWhereClause::new("folder = ?", [folder.to_string()]));
         }
 
         if self.unread_only {
-            fragments.push("COALESCE(is_read, 0) = 0".to_string());
+            conditions.push(SqlWhereClause::new("COALESCE(is_read, 0) = 0", []));
+        }
+
+        if self.has_attachment {
+            conditions.push(SqlWhereClause::new("COALESCE(has_attachments, 0) = 1", []));
         }
 
-        SqlWhereClause {
-            clause: if fragments.is_empty() {
-                "1 = 1".to_string()
-            } else {
-                fragments.join(" AND ")
-            },
-            params,
+        if let Some(attachments) = attachment_predicate(
+            self.attachment_name.as_deref(),
+            self.attachment_type.as_deref(),
+        ) {
+            conditions
+                .push(attachments.wrap("id IN (SELECT email_id FROM attachments WHERE ", ")"));
         }
+
+        if let Some(tag) = self
+            .tag
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+        {
+            conditions.push(SqlWhereClause::new(
+                "id IN (SELECT email_id FROM tags WHERE tag = ?)",
+                [tag.to_lowercase()],
+            ));
+        }
+
+        SqlWhereClause::all(conditions)
+    }
+
+    /// `from_domain` normalized for lookup, or `None` when unset or blank.
+    pub fn domain(&self) -> Option<String> {
+        self.from_domain
+            .as_deref()
+            .map(normalize_domain)
+            .filter(|domain| !domain.is_empty())
     }
 }
 
@@ -354,7 +414,7 @@ mod tests {
     use chrono::NaiveDate;
     use tantivy::Index;
 
-    use crate::indexer::schema::{build_schema, ensure_edge_ngram_tokenizer};
+    use crate::indexer::schema::{build_schema, ensure_edge_ngram_tokenizer, TextAnalysis};
 
     use super::{EmailFilters, Scope};
 
@@ -391,12 +451,17 @@ mod tests {
             query: Some("kickoff".to_string()),
             scope: Scope::Professional,
             from: Some("alice@example.com".to_string()),
+            from_domain: Some("@Example.com".to_string()),
             to: Some("owner@example.com".to_string()),
             since: Some(NaiveDate::from_ymd_opt(2026, 1, 1).expect("valid since")),
             until: Some(NaiveDate::from_ymd_opt(2026, 1, 31).expect("valid until")),
             account: Some("acc-pro".to_string()),
             folder: Some("inbox".to_string()),
             unread_only: true,
+            has_attachment: true,
+            attachment_name: None,
+            attachment_type: Some("pdf".to_string()),
+            tag: Some("Invoice".to_string()),
             limit: 20,
             offset: 0,
         };
@@ -407,18 +472,32 @@ mod tests {
         assert!(where_clause
             .clause
             .contains("LOWER(from_address) = LOWER(?)"));
+        assert!(where_clause.clause.contains("from_domain = ?"));
+        assert!(where_clause.params.contains(&"example.com".to_string()));
         assert!(where_clause.clause.contains("DATE(received_at) >= DATE(?)"));
         assert!(where_clause.clause.contains("account_id = ?"));
         assert!(where_clause.clause.contains("folder = ?"));
         assert!(where_clause.clause.contains("COALESCE(is_read, 0) = 0"));
-        assert_eq!(where_clause.params.len(), 13);
+        assert!(where_clause
+            .clause
+            .contains("COALESCE(has_attachments, 0) = 1"));
+        assert!(where_clause
+            .clause
+            .contains("id IN (SELECT email_id FROM attachments WHERE"));
+        assert!(where_clause.params.contains(&"%.pdf".to_string()));
+        assert!(where_clause
+            .clause
+            .contains("id IN (SELECT email_id FROM tags WHERE tag = ?)"));
+        assert!(where_clause.params.contains(&"invoice".to_string()));
+        assert_eq!(where_clause.params.len(), 17);
     }
 
     #[test]
     fn tantivy_query_builds_with_filters() {
         let schema = build_schema();
         let mut index = Index::create_in_ram(schema);
-        ensure_edge_ngram_tokenizer(&mut index).expect("register edge ngram tokenizer");
+        ensure_edge_ngram_tokenizer(&mut index, TextAnalysis::default())
+            .expect("register edge ngram tokenizer");
 
         let filters = EmailFilters {
             query: Some("kickoff".to_string()),
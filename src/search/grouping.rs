@@ -0,0 +1,103 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+
+use crate::db::{Database, ThreadSummary};
+use crate::indexer::EmailIndex;
+use crate::search::filters::EmailFilters;
+use crate::search::{PreparedSearch, SearchResult};
+
+/// One conversation in `ess search --group-by-thread`: its best-ranked
+/// matching message plus the size and senders of the whole thread.
+#[derive(Debug, Clone)]
+pub struct ThreadMatch {
+    /// `None` for an email without a conversation id, which stands alone.
+    pub conversation_id: Option<String>,
+    pub messages: i64,
+    pub participants: Vec<String>,
+    pub best: SearchResult,
+}
+
+/// Searches like `search_emails` but returns one entry per conversation,
+/// ranked by its best hit. Later hits from a thread already in the results
+/// are recognised by the conversation id stored in the index and skipped
+/// without reading SQLite, so only each thread's best message is loaded.
+pub fn search_threads(
+    index: &EmailIndex,
+    db: &Database,
+    query: &str,
+    filters: &EmailFilters,
+) -> Result<Vec<ThreadMatch>> {
+    let prepared = PreparedSearch::new(index, db, query, filters)?;
+    let wanted = filters.limit.max(1).saturating_add(filters.offset);
+    let batch_size = prepared.batch_size(wanted);
+
+    // Conversation ids as indexed, and as stored in SQLite.
+    let mut seen = HashSet::new();
+    let mut grouped = HashSet::new();
+    let mut threads = Vec::with_capacity(wanted);
+    let mut after = None;
+    'search: loop {
+        let hits = prepared.hits(index, batch_size, after.as_ref())?;
+        let exhausted = hits.len() < batch_size;
+        for hit in hits {
+            if threads.len() == wanted {
+                break 'search;
+            }
+            after = Some(hit.position.clone());
+            if hit
+                .conversation_id
+                .as_ref()
+                .is_some_and(|id| seen.contains(id))
+            {
+                continue;
+            }
+            let Some(best) = prepared.hydrate(db, &hit, filters)? else {
+                continue;
+            };
+            if let Some(id) = hit.conversation_id {
+                seen.insert(id);
+            }
+            // SQLite has the final say: a sync indexes the provider's thread
+            // id, which a local `ess thread merge` may have replaced.
+            let conversation_id = best
+                .email
+                .conversation_id
+                .clone()
+                .filter(|id| !id.trim().is_empty());
+            let summary = match &conversation_id {
+                Some(id) => {
+                    if !grouped.insert(id.clone()) {
+                        continue;
+                    }
+                    seen.insert(id.clone());
+                    db.thread_summary(id)?
+                }
+                None => ThreadSummary {
+                    messages: 1,
+                    participants: best
+                        .email
+                        .from_address
+                        .iter()
+                        .map(|address| address.to_lowercase())
+                        .collect(),
+                },
+            };
+            threads.push(ThreadMatch {
+                conversation_id,
+                messages: summary.messages,
+                participants: summary.participants,
+                best,
+            });
+        }
+        if exhausted {
+            break;
+        }
+    }
+
+    Ok(threads
+        .into_iter()
+        .skip(filters.offset)
+        .take(filters.limit.max(1))
+        .collect())
+}
@@ -0,0 +1,151 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use tantivy::schema::Field;
+use tantivy::snippet::SnippetGenerator;
+use tantivy::tokenizer::{LowerCaser, SimpleTokenizer, TextAnalyzer};
+
+use crate::db::models::Email;
+
+/// Longest fragment picked from the body, in bytes.
+const MAX_SNIPPET_CHARS: usize = 150;
+
+/// A body excerpt with the byte ranges (into `text`) that matched the query.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Snippet {
+    pub text: String,
+    pub highlights: Vec<Range<usize>>,
+}
+
+impl Snippet {
+    /// Wraps every highlighted range in `open`/`close`.
+    pub fn render(&self, open: &str, close: &str) -> String {
+        self.render_with(open, close, str::to_string)
+    }
+
+    /// The excerpt as HTML-escaped text with matches wrapped in `<em>`.
+    pub fn html(&self) -> String {
+        self.render_with("<em>", "</em>", escape_html)
+    }
+
+    fn render_with(&self, open: &str, close: &str, escape: impl Fn(&str) -> String) -> String {
+        let mut out = String::with_capacity(self.text.len());
+        let mut cursor = 0;
+        for range in &self.highlights {
+            if range.start < cursor || range.end > self.text.len() {
+                continue;
+            }
+            out.push_str(&escape(&self.text[cursor..range.start]));
+            out.push_str(open);
+            out.push_str(&escape(&self.text[range.clone()]));
+            out.push_str(close);
+            cursor = range.end;
+        }
+        out.push_str(&escape(&self.text[cursor..]));
+        out
+    }
+}
+
+impl Serialize for Snippet {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Snippet", 3)?;
+        state.serialize_field("text", &self.text)?;
+        state.serialize_field("html", &self.html())?;
+        state.serialize_field("highlights", &self.highlights)?;
+        state.end()
+    }
+}
+
+/// Picks the best-matching fragment of the email body with Tantivy's snippet
+/// generator.
+///
+/// The index tokenizes bodies into character n-grams, so the terms Tantivy
+/// would derive from the query highlight every short n-gram. Instead the body
+/// is split into words and each word that contains one of `terms` is scored,
+/// mirroring the substring semantics of the n-gram search. Without a match the
+/// start of the body is returned unhighlighted.
+pub fn build_snippet(email: &Email, terms: &[&str], field: Field) -> Option<Snippet> {
+    let needles = terms
+        .iter()
+        .flat_map(|term| term.split_whitespace())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>();
+    if needles.is_empty() {
+        return None;
+    }
+
+    let body = email
+        .body_text
+        .as_deref()
+        .or(email.body_preview.as_deref())?
+        .trim();
+    if body.is_empty() {
+        return None;
+    }
+
+    let mut analyzer = word_analyzer();
+    let mut matched_words = BTreeMap::new();
+    {
+        let mut stream = analyzer.token_stream(body);
+        while let Some(token) = stream.next() {
+            if needles
+                .iter()
+                .any(|needle| token.text.contains(needle.as_str()))
+            {
+                matched_words.insert(token.text.clone(), 1.0);
+            }
+        }
+    }
+
+    let snippet =
+        SnippetGenerator::new(matched_words, analyzer, field, MAX_SNIPPET_CHARS).snippet(body);
+    if snippet.is_empty() {
+        return Some(Snippet {
+            text: body.chars().take(140).collect(),
+            highlights: Vec::new(),
+        });
+    }
+
+    Some(Snippet {
+        text: snippet.fragment().to_string(),
+        highlights: snippet.highlighted().to_vec(),
+    })
+}
+
+fn word_analyzer() -> TextAnalyzer {
+    TextAnalyzer::builder(SimpleTokenizer::default())
+        .filter(LowerCaser)
+        .build()
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Snippet;
+
+    #[test]
+    fn renders_markers_around_highlights() {
+        let snippet = Snippet {
+            text: "Q3 budget & <draft> budgets".to_string(),
+            highlights: vec![3..9, 20..27],
+        };
+
+        assert_eq!(snippet.render("[", "]"), "Q3 [budget] & <draft> [budgets]");
+        assert_eq!(
+            snippet.html(),
+            "Q3 <em>budget</em> &amp; &lt;draft&gt; <em>budgets</em>"
+        );
+        let value = serde_json::to_value(&snippet).expect("serialize snippet");
+        assert_eq!(value["highlights"][0]["start"], 3);
+        assert_eq!(value["highlights"][0]["end"], 9);
+    }
+}
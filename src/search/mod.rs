@@ -1,181 +1,287 @@
+pub mod compare;
+pub mod cursor;
+pub mod dates;
+pub mod facets;
 pub mod filters;
+pub mod grouping;
+pub mod highlight;
+pub mod query;
+pub mod semantic;
+pub use self::compare::{compare_scopes, ScopeMatches};
+pub use self::cursor::{Cursor, CursorError};
+pub use self::facets::{facet_counts, Facet, FacetCount, FacetCounts};
 pub use self::filters::{EmailFilters, Scope, SqlWhereClause};
+pub use self::grouping::{search_threads, ThreadMatch};
+pub use self::highlight::Snippet;
+pub use self::query::{parse_query, parse_query_or_words, LocalLookup, QueryNode, QueryParseError};
+pub use self::semantic::semantic_search;
 
 use anyhow::Result;
+use tantivy::query::{AllQuery, Query};
+use tantivy::schema::Field;
 
 use crate::db::models::Email;
 use crate::db::Database;
-use crate::indexer::{EmailIndex, SearchFilters as IndexSearchFilters};
+use crate::indexer::{
+    EmailIndex, EmailSearchHit, HitPosition, SearchFilters as IndexSearchFilters,
+};
 
 #[derive(Debug, Clone)]
 pub struct SearchResult {
     pub email: Email,
     pub score: f32,
-    pub snippet: Option<String>,
+    pub snippet: Option<Snippet>,
 }
 
+/// How many extra index hits to fetch when part of the query can only be
+/// checked against the stored email.
+const POST_FILTER_OVERFETCH: usize = 5;
+
 pub fn search_emails(
     index: &EmailIndex,
     db: &Database,
     query: &str,
     filters: &EmailFilters,
 ) -> Result<Vec<SearchResult>> {
-    let query_text = if query.trim().is_empty() {
-        filters.query.as_deref().unwrap_or("")
+    Ok(search_emails_page(index, db, query, filters, None)?.results)
+}
+
+/// One page of results and the cursor that resumes right after it (`None`
+/// once the results are exhausted).
+#[derive(Debug, Clone)]
+pub struct SearchPage {
+    pub results: Vec<SearchResult>,
+    pub next_cursor: Option<Cursor>,
+}
+
+/// Searches like `search_emails`, resuming after `cursor` when given. Index
+/// hits that fail the stored-email filters are consumed in batches until the
+/// page is full, and the cursor points at the last hit examined, so no match
+/// is skipped or repeated between pages.
+pub fn search_emails_page(
+    index: &EmailIndex,
+    db: &Database,
+    query: &str,
+    filters: &EmailFilters,
+    cursor: Option<Cursor>,
+) -> Result<SearchPage> {
+    let prepared = PreparedSearch::new(index, db, query, filters)?;
+    let wanted = filters.limit.max(1).saturating_add(filters.offset);
+    let batch_size = prepared.batch_size(wanted);
+
+    let mut after = cursor.map(Cursor::into_ranked).transpose()?;
+    let mut results = Vec::with_capacity(wanted);
+    let mut exhausted = false;
+    while results.len() < wanted && !exhausted {
+        let index_hits = prepared.hits(index, batch_size, after.as_ref())?;
+        exhausted = index_hits.len() < batch_size;
+
+        for hit in index_hits {
+            if results.len() == wanted {
+                break;
+            }
+            after = Some(hit.position.clone());
+            if let Some(result) = prepared.hydrate(db, &hit, filters)? {
+                results.push(result);
+            }
+        }
+    }
+
+    let next_cursor = if exhausted && results.len() < wanted {
+        None
     } else {
-        query
+        after.map(Cursor::Ranked)
     };
+    Ok(SearchPage {
+        results: results
+            .into_iter()
+            .skip(filters.offset)
+            .take(filters.limit.max(1))
+            .collect(),
+        next_cursor,
+    })
+}
 
-    let requested_limit = filters.limit.saturating_add(filters.offset).max(1);
+/// A parsed query and its index filters, shared by the flat and the
+/// thread-grouped search.
+struct PreparedSearch {
+    parsed: Option<QueryNode>,
+    base_query: Box<dyn Query>,
+    body_field: Field,
+    index_filters: IndexSearchFilters,
+}
 
-    let scope = match filters.scope {
-        Scope::Professional => Some("professional".to_string()),
-        Scope::Personal => Some("personal".to_string()),
-        Scope::All => None,
-    };
+impl PreparedSearch {
+    fn new(index: &EmailIndex, db: &Database, query: &str, filters: &EmailFilters) -> Result<Self> {
+        let query_text = if query.trim().is_empty() {
+            filters.query.as_deref().unwrap_or("")
+        } else {
+            query
+        };
+
+        let mut parsed = parse_query_or_words(query_text);
+        if let Some(tag) = filters.tag.as_deref() {
+            let tagged = QueryNode::Tag {
+                tag: tag.to_lowercase(),
+                email_ids: Default::default(),
+            };
+            parsed = Some(match parsed {
+                Some(QueryNode::And(mut children)) => {
+                    children.push(tagged);
+                    QueryNode::And(children)
+                }
+                Some(node) => QueryNode::And(vec![node, tagged]),
+                None => tagged,
+            });
+        }
+        if let Some(node) = parsed.as_mut() {
+            node.resolve_local(&mut |lookup| match lookup {
+                LocalLookup::Note(text) => db.email_ids_with_note(text),
+                LocalLookup::Starred => db.starred_email_ids(),
+                LocalLookup::Tag(tag) => db.tagged_email_ids(tag),
+            })?;
+        }
+        let base_query: Box<dyn Query> = match &parsed {
+            Some(node) => node
+                .to_tantivy(index.tantivy_index())?
+                .unwrap_or_else(|| Box::new(AllQuery)),
+            None => Box::new(AllQuery),
+        };
+        let body_field = index.tantivy_index().schema().get_field("body_text")?;
 
-    let index_hits = index.search(
-        query_text,
-        &IndexSearchFilters {
+        let scope = match filters.scope {
+            Scope::Professional => Some("professional".to_string()),
+            Scope::Personal => Some("personal".to_string()),
+            Scope::All => None,
+        };
+        let index_filters = IndexSearchFilters {
             account_type: scope,
             folder: filters.folder.clone(),
+            from_domain: filters.domain(),
+            to: filters.to.clone(),
+            account_id: filters.account.clone(),
+            unread_only: filters.unread_only,
             since: filters
                 .since
                 .map(|date| date.format("%Y-%m-%d").to_string()),
             until: filters
                 .until
                 .map(|date| date.format("%Y-%m-%d").to_string()),
-        },
-        requested_limit,
-    )?;
-
-    let mut results = Vec::with_capacity(index_hits.len());
-
-    for hit in index_hits {
-        let Some(email) = db.get_email(&hit.email_db_id)? else {
-            continue;
         };
 
-        if let Some(from_address) = filters
-            .from
-            .as_deref()
-            .map(str::trim)
-            .filter(|value| !value.is_empty())
-        {
-            let from_matches = email
-                .from_address
-                .as_deref()
-                .map(|value| value.eq_ignore_ascii_case(from_address))
-                .unwrap_or(false);
-            if !from_matches {
-                continue;
-            }
-        }
+        Ok(Self {
+            parsed,
+            base_query,
+            body_field,
+            index_filters,
+        })
+    }
 
-        if let Some(account_id) = filters
-            .account
-            .as_deref()
-            .map(str::trim)
-            .filter(|value| !value.is_empty())
+    /// Index hits to read per round for `wanted` results.
+    fn batch_size(&self, wanted: usize) -> usize {
+        if self
+            .parsed
+            .as_ref()
+            .is_some_and(QueryNode::needs_post_filter)
         {
-            let account_matches = email
-                .account_id
-                .as_deref()
-                .map(|value| value == account_id)
-                .unwrap_or(false);
-            if !account_matches {
-                continue;
-            }
+            wanted.saturating_mul(POST_FILTER_OVERFETCH)
+        } else {
+            wanted
         }
+    }
 
-        if let Some(to_filter) = filters
-            .to
-            .as_deref()
-            .map(str::trim)
-            .filter(|value| !value.is_empty())
-        {
-            let to_matches = email
-                .to_addresses
-                .iter()
-                .chain(email.cc_addresses.iter())
-                .chain(email.bcc_addresses.iter())
-                .any(|value| value.eq_ignore_ascii_case(to_filter));
-            if !to_matches {
-                continue;
-            }
-        }
+    fn hits(
+        &self,
+        index: &EmailIndex,
+        limit: usize,
+        after: Option<&HitPosition>,
+    ) -> Result<Vec<EmailSearchHit>> {
+        Ok(index.search_with_query_after(
+            self.base_query.box_clone(),
+            &self.index_filters,
+            limit,
+            after,
+        )?)
+    }
 
-        if filters.unread_only && email.is_read.unwrap_or(false) {
-            continue;
+    /// The stored email for `hit`, or `None` when it is gone or fails the
+    /// filters the index cannot answer.
+    fn hydrate(
+        &self,
+        db: &Database,
+        hit: &EmailSearchHit,
+        filters: &EmailFilters,
+    ) -> Result<Option<SearchResult>> {
+        let Some(email) = db.get_email(&hit.email_db_id)? else {
+            return Ok(None);
+        };
+        if !matches_stored_filters(&email, filters, self.parsed.as_ref())
+            || !db.has_matching_attachment(
+                &email.id,
+                filters.attachment_name.as_deref(),
+                filters.attachment_type.as_deref(),
+            )?
+        {
+            return Ok(None);
         }
-
-        results.push(SearchResult {
-            snippet: build_snippet(&email, query_text),
+        let snippet_terms = self
+            .parsed
+            .as_ref()
+            .map(QueryNode::positive_terms)
+            .unwrap_or_default();
+        Ok(Some(SearchResult {
+            snippet: highlight::build_snippet(&email, &snippet_terms, self.body_field),
             email,
             score: hit.score,
-        });
-    }
-
-    if filters.offset > 0 {
-        return Ok(results.into_iter().skip(filters.offset).collect());
+        }))
     }
-
-    Ok(results)
 }
 
-fn build_snippet(email: &Email, query: &str) -> Option<String> {
-    if query.trim().is_empty() {
-        return None;
-    }
-
-    let body = email
-        .body_text
+/// Filters the index cannot answer, checked against the stored email.
+fn matches_stored_filters(
+    email: &Email,
+    filters: &EmailFilters,
+    parsed: Option<&QueryNode>,
+) -> bool {
+    if let Some(from_address) = filters
+        .from
         .as_deref()
-        .or(email.body_preview.as_deref())?
-        .trim();
-
-    if body.is_empty() {
-        return None;
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+    {
+        let from_matches = email
+            .from_address
+            .as_deref()
+            .map(|value| value.eq_ignore_ascii_case(from_address))
+            .unwrap_or(false);
+        if !from_matches {
+            return false;
+        }
     }
 
-    let query_lower = query.to_ascii_lowercase();
-    let body_lower = body.to_ascii_lowercase();
-
-    if let Some(pos) = body_lower.find(&query_lower) {
-        let start = floor_char_boundary(body, pos.saturating_sub(50));
-        let end = ceil_char_boundary(body, (pos + query_lower.len() + 90).min(body.len()));
-        return Some(body[start..end].trim().to_string());
+    if filters.has_attachment && email.has_attachments != Some(true) {
+        return false;
     }
 
-    Some(body.chars().take(140).collect())
-}
-
-fn floor_char_boundary(value: &str, mut index: usize) -> usize {
-    index = index.min(value.len());
-    while index > 0 && !value.is_char_boundary(index) {
-        index -= 1;
+    if parsed.is_some_and(|node| !node.matches(email)) {
+        return false;
     }
-    index
-}
 
-fn ceil_char_boundary(value: &str, mut index: usize) -> usize {
-    index = index.min(value.len());
-    while index < value.len() && !value.is_char_boundary(index) {
-        index += 1;
-    }
-    index
+    true
 }
 
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
 
-    use crate::db::models::{Account, AccountType, Email};
+    use crate::db::models::{Account, AccountType, Attachment, Email};
     use crate::db::Database;
     use crate::indexer::EmailIndex;
 
+    use super::compare::compare_scopes;
     use super::filters::{EmailFilters, Scope};
-    use super::search_emails;
+    use super::{search_emails, search_emails_page, search_threads, semantic_search, Cursor};
+    use crate::embeddings::{embedding_text, HashingEmbedder, HASHING_MODEL};
 
     fn temp_root() -> PathBuf {
         let root = std::env::temp_dir().join(format!("ess-search-test-{}", uuid::Uuid::new_v4()));
@@ -205,7 +311,6 @@ mod tests {
         received_at: &str,
     ) -> Email {
         Email {
-            id: id.to_string(),
             internet_message_id: Some(format!("<{id}@example.com>")),
             conversation_id: Some("thread-1".to_string()),
             account_id: Some(account_id.to_string()),
@@ -213,10 +318,7 @@ mod tests {
             from_address: Some(format!("{}@example.com", from_name.to_ascii_lowercase())),
             from_name: Some(from_name.to_string()),
             to_addresses: vec!["owner@example.com".to_string()],
-            cc_addresses: vec![],
-            bcc_addresses: vec![],
             body_text: Some(body_text.to_string()),
-            body_html: None,
             body_preview: Some(body_text.chars().take(80).collect()),
             received_at: received_at.to_string(),
             sent_at: Some(received_at.to_string()),
@@ -224,10 +326,7 @@ mod tests {
             is_read: Some(false),
             has_attachments: Some(false),
             folder: Some("inbox".to_string()),
-            categories: vec![],
-            flag_status: None,
-            web_link: None,
-            metadata: None,
+            ..Email::fixture(id)
         }
     }
 
@@ -295,6 +394,453 @@ mod tests {
         assert_eq!(pro_only.len(), 1);
         assert_eq!(pro_only[0].email.id, "email-subject");
 
+        let by_domain = |domain: &str| {
+            search_emails(
+                &index,
+                &db,
+                "kickoff",
+                &EmailFilters {
+                    from_domain: Some(domain.to_string()),
+                    limit: 10,
+                    ..EmailFilters::default()
+                },
+            )
+            .expect("search by sender domain")
+            .len()
+        };
+        assert_eq!(by_domain("Example.com"), 2);
+        assert_eq!(by_domain("mail.example.com"), 0);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn group_by_thread_returns_one_row_per_conversation() {
+        let root = temp_root();
+        let db = Database::open(&root.join("ess.db")).expect("open db");
+        db.insert_account(&account("acc-pro", AccountType::Professional))
+            .expect("insert account");
+        for (id, subject, from_name, thread, received_at) in [
+            (
+                "a-1",
+                "Budget review",
+                "Alice",
+                "thread-1",
+                "2026-03-01T10:00:00Z",
+            ),
+            (
+                "a-2",
+                "Re: Budget review",
+                "Bob",
+                "thread-1",
+                "2026-03-02T10:00:00Z",
+            ),
+            (
+                "b-1",
+                "Budget for offsite",
+                "Carol",
+                "thread-2",
+                "2026-03-03T10:00:00Z",
+            ),
+        ] {
+            let mut message = email(id, "acc-pro", subject, "numbers", from_name, received_at);
+            message.conversation_id = Some(thread.to_string());
+            db.insert_email(&message).expect("insert email");
+        }
+        let mut index = EmailIndex::open(&root.join("index")).expect("open index");
+        index.reindex(&db).expect("reindex");
+
+        let threads = search_threads(
+            &index,
+            &db,
+            "budget",
+            &EmailFilters {
+                limit: 10,
+                ..EmailFilters::default()
+            },
+        )
+        .expect("search threads");
+
+        assert_eq!(threads.len(), 2);
+        let review = threads
+            .iter()
+            .find(|thread| thread.conversation_id.as_deref() == Some("thread-1"))
+            .expect("thread-1 grouped");
+        assert_eq!(review.messages, 2);
+        assert_eq!(
+            review.participants,
+            vec!["alice@example.com", "bob@example.com"]
+        );
+        let offsite = threads
+            .iter()
+            .find(|thread| thread.conversation_id.as_deref() == Some("thread-2"))
+            .expect("thread-2 grouped");
+        assert_eq!(offsite.messages, 1);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn attachment_filters_match_stored_attachments() {
+        let root = temp_root();
+        let db = Database::open(&root.join("ess.db")).expect("open db");
+        db.insert_account(&account("acc-pro", AccountType::Professional))
+            .expect("insert account");
+        for (id, attachment) in [
+            (
+                "sheet",
+                Some(("Q3 budget.xlsx", "application/vnd.ms-excel")),
+            ),
+            ("deck", Some(("Q3 review.pptx", "application/octet-stream"))),
+            ("plain", None),
+        ] {
+            let mut message = email(
+                id,
+                "acc-pro",
+                "Q3 numbers",
+                "see attached",
+                "Finance",
+                "2026-03-01T10:00:00Z",
+            );
+            message.has_attachments = Some(attachment.is_some());
+            db.insert_email(&message).expect("insert email");
+            if let Some((name, content_type)) = attachment {
+                db.replace_attachments(
+                    id,
+                    &[Attachment {
+                        id: format!("{id}:1"),
+                        email_id: id.to_string(),
+                        name: Some(name.to_string()),
+                        content_type: Some(content_type.to_string()),
+                        size_bytes: Some(2048),
+                        is_inline: Some(false),
+                    }],
+                )
+                .expect("store attachment");
+            }
+        }
+        let mut index = EmailIndex::open(&root.join("index")).expect("open index");
+        index.reindex(&db).expect("reindex");
+
+        let ids = |filters: EmailFilters| {
+            let mut ids = search_emails(&index, &db, "numbers", &filters)
+                .expect("search")
+                .into_iter()
+                .map(|result| result.email.id)
+                .collect::<Vec<_>>();
+            ids.sort();
+            ids
+        };
+        assert_eq!(
+            ids(EmailFilters {
+                has_attachment: true,
+                ..EmailFilters::default()
+            }),
+            vec!["deck", "sheet"]
+        );
+        assert_eq!(
+            ids(EmailFilters {
+                attachment_type: Some("spreadsheet".to_string()),
+                ..EmailFilters::default()
+            }),
+            vec!["sheet"]
+        );
+        assert_eq!(
+            ids(EmailFilters {
+                attachment_name: Some("*REVIEW*".to_string()),
+                ..EmailFilters::default()
+            }),
+            vec!["deck"]
+        );
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn compare_scopes_counts_each_account_type() {
+        let root = temp_root();
+        let db = Database::open(&root.join("ess.db")).expect("open db");
+        db.insert_account(&account("acc-pro", AccountType::Professional))
+            .expect("insert pro account");
+        db.insert_account(&account("acc-personal", AccountType::Personal))
+            .expect("insert personal account");
+        for (id, account_id, received_at) in [
+            ("pro-1", "acc-pro", "2026-01-10T10:00:00Z"),
+            ("pro-2", "acc-pro", "2026-02-01T10:00:00Z"),
+            ("pro-3", "acc-pro", "2026-02-05T10:00:00Z"),
+            ("home-1", "acc-personal", "2026-01-20T10:00:00Z"),
+        ] {
+            db.insert_email(&email(
+                id,
+                account_id,
+                "Offsite planning",
+                "Venue options",
+                "Alice",
+                received_at,
+            ))
+            .expect("insert email");
+        }
+
+        let mut index = EmailIndex::open(&root.join("index")).expect("open index");
+        index.reindex(&db).expect("reindex");
+
+        let comparison = compare_scopes(&index, &db, "offsite", &EmailFilters::default(), 2)
+            .expect("compare scopes");
+
+        assert_eq!(comparison.len(), 2);
+        assert_eq!(comparison[0].scope, Scope::Professional);
+        assert_eq!(comparison[0].total, 3);
+        assert_eq!(comparison[0].samples.len(), 2);
+        assert_eq!(
+            comparison[0].first_received_at.as_deref(),
+            Some("2026-01-10T10:00:00Z")
+        );
+        assert_eq!(comparison[1].scope, Scope::Personal);
+        assert_eq!(comparison[1].total, 1);
+        assert_eq!(comparison[1].samples[0].email.id, "home-1");
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn cursor_pages_cover_every_match_once() {
+        let root = temp_root();
+        let db = Database::open(&root.join("ess.db")).expect("open db");
+        db.insert_account(&account("acc-pro", AccountType::Professional))
+            .expect("insert account");
+        for n in 0..7 {
+            let body = if n % 2 == 0 {
+                "Status report attached"
+            } else {
+                "Status report, draft"
+            };
+            db.insert_email(&email(
+                &format!("report-{n}"),
+                "acc-pro",
+                "Weekly status",
+                body,
+                if n < 4 { "Alice" } else { "Bob" },
+                &format!("2026-02-0{}T10:00:00Z", n + 1),
+            ))
+            .expect("insert email");
+        }
+        let mut index = EmailIndex::open(&root.join("index")).expect("open index");
+        index.reindex(&db).expect("reindex");
+
+        let filters = EmailFilters {
+            limit: 2,
+            ..EmailFilters::default()
+        };
+        for query in ["status", "from:alice status"] {
+            let mut seen = Vec::new();
+            let mut cursor: Option<Cursor> = None;
+            loop {
+                let page = search_emails_page(&index, &db, query, &filters, cursor.take())
+                    .expect("search page");
+                // Hits the stored-email filters reject do not leave a
+                // page short.
+                if page.next_cursor.is_some() {
+                    assert_eq!(page.results.len(), 2, "{query}");
+                } else {
+                    assert!(page.results.len() <= 2);
+                }
+                seen.extend(page.results.into_iter().map(|result| result.email.id));
+                match page.next_cursor {
+                    Some(next) => cursor = Some(next.to_string().parse().expect("token")),
+                    None => break,
+                }
+            }
+            seen.sort();
+            let expected = if query == "status" { 7 } else { 4 };
+            assert_eq!(seen.len(), expected, "{query}");
+            seen.dedup();
+            assert_eq!(seen.len(), expected, "{query} repeated a result");
+        }
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn local_terms_match_notes_and_stars() {
+        let root = temp_root();
+        let db = Database::open(&root.join("ess.db")).expect("open db");
+        db.insert_account(&account("acc-pro", AccountType::Professional))
+            .expect("insert account");
+        let mut renewal = email(
+            "renewal",
+            "acc-pro",
+            "Contract renewal",
+            "Terms attached",
+            "Alice",
+            "2026-02-01T10:00:00Z",
+        );
+        renewal.conversation_id = Some("thread-renewal".to_string());
+        db.insert_email(&renewal).expect("insert email");
+        db.insert_email(&email(
+            "other",
+            "acc-pro",
+            "Contract draft",
+            "Terms attached",
+            "Bob",
+            "2026-02-02T10:00:00Z",
+        ))
+        .expect("insert email");
+        db.add_note(
+            "thread-renewal",
+            "Legal signed off; go with the 3-year term",
+        )
+        .expect("add note");
+        let mut index = EmailIndex::open(&root.join("index")).expect("open index");
+        index.reindex(&db).expect("reindex");
+
+        let ids = |query: &str| {
+            search_emails(&index, &db, query, &EmailFilters::default())
+                .expect(query)
+                .into_iter()
+                .map(|result| result.email.id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids("note:legal"), vec!["renewal"]);
+        assert_eq!(ids("contract note:\"3-year term\""), vec!["renewal"]);
+        assert_eq!(ids("contract -note:legal"), vec!["other"]);
+        assert!(ids("note:nothing").is_empty());
+
+        assert!(ids("is:starred").is_empty());
+        db.star_email("other").expect("star email");
+        assert_eq!(ids("is:starred"), vec!["other"]);
+        assert_eq!(ids("contract -is:starred"), vec!["renewal"]);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn semantic_search_fuses_keyword_and_vector_hits() {
+        let root = temp_root();
+        let db = Database::open(&root.join("ess.db")).expect("open db");
+        db.insert_account(&account("acc-pro", AccountType::Professional))
+            .expect("insert account");
+        for email in [
+            email(
+                "both",
+                "acc-pro",
+                "Budget overruns in facilities",
+                "Facilities spend is over budget again.",
+                "Alice",
+                "2026-02-01T10:00:00Z",
+            ),
+            email(
+                "vector-only",
+                "acc-pro",
+                "Q3 marketing overrun",
+                "The campaign ran 12% over budget.",
+                "Bob",
+                "2026-02-02T10:00:00Z",
+            ),
+            email(
+                "unrelated",
+                "acc-pro",
+                "Team offsite",
+                "Bring walking shoes.",
+                "Carol",
+                "2026-02-03T10:00:00Z",
+            ),
+        ] {
+            db.insert_email(&email).expect("insert email");
+        }
+        let mut index = EmailIndex::open(&root.join("index")).expect("open index");
+        index.reindex(&db).expect("reindex");
+
+        let filters = EmailFilters::default();
+        let missing = semantic_search(&index, &db, &HashingEmbedder, "budget overruns", &filters)
+            .await
+            .expect_err("no embeddings yet");
+        assert!(missing.to_string().contains("ess embed"));
+
+        for id in db
+            .email_ids_without_embedding(HASHING_MODEL, 10)
+            .expect("missing embeddings")
+        {
+            let email = db.get_email(&id).expect("load email").expect("email");
+            let vector = HashingEmbedder::embed_text(&embedding_text(&email));
+            db.upsert_embedding(&id, HASHING_MODEL, &vector)
+                .expect("store embedding");
+        }
+
+        let ids = semantic_search(&index, &db, &HashingEmbedder, "budget overruns", &filters)
+            .await
+            .expect("semantic search")
+            .into_iter()
+            .map(|result| result.email.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["both", "vector-only"]);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn search_applies_structured_query() {
+        let root = temp_root();
+        let db = Database::open(&root.join("ess.db")).expect("open db");
+        db.insert_account(&account("acc-pro", AccountType::Professional))
+            .expect("insert account");
+
+        let mut unread = email(
+            "budget-alice",
+            "acc-pro",
+            "Budget review",
+            "The quarterly budget is attached",
+            "Alice",
+            "2026-02-01T10:00:00Z",
+        );
+        unread.has_attachments = Some(true);
+        unread.set_meta("source", "graph_delta_sync");
+        db.insert_email(&unread).expect("insert alice email");
+        let mut read = email(
+            "budget-bob",
+            "acc-pro",
+            "Budget draft",
+            "Budget numbers, quarterly edition",
+            "Bob",
+            "2026-01-15T10:00:00Z",
+        );
+        read.is_read = Some(true);
+        read.set_meta("rule", "newsletters");
+        db.insert_email(&read).expect("insert bob email");
+
+        let mut index = EmailIndex::open(&root.join("index")).expect("open index");
+        index.reindex(&db).expect("reindex");
+
+        let search = |query: &str| {
+            search_emails(
+                &index,
+                &db,
+                query,
+                &EmailFilters {
+                    limit: 10,
+                    ..EmailFilters::default()
+                },
+            )
+            .expect("search")
+            .into_iter()
+            .map(|result| result.email.id)
+            .collect::<Vec<_>>()
+        };
+
+        assert_eq!(search("budget from:alice"), vec!["budget-alice"]);
+        assert_eq!(search("budget -subject:review"), vec!["budget-bob"]);
+        assert_eq!(search(r#""quarterly budget""#), vec!["budget-alice"]);
+        assert_eq!(search("budget is:read OR has:attachment").len(), 2);
+        assert_eq!(search("budget before:2026-02-01"), vec!["budget-bob"]);
+        assert_eq!(search("meta:source=graph_delta_sync"), vec!["budget-alice"]);
+        assert_eq!(search("budget meta:rule=newsletters"), vec!["budget-bob"]);
+        assert_eq!(
+            search("budget -meta:source=graph_delta_sync"),
+            vec!["budget-bob"]
+        );
+        // Input the query language rejects searches as its words.
+        assert_eq!(search("(budget").len(), 2);
+        assert_eq!(search("quarterly AND (").len(), 2);
+
         let _ = std::fs::remove_dir_all(root);
     }
 
@@ -309,7 +855,16 @@ mod tests {
             "2026-02-01T10:00:00Z",
         );
 
-        let snippet = super::build_snippet(&email, "claude");
-        assert!(snippet.is_some());
+        let field = crate::indexer::schema::build_schema()
+            .get_field("body_text")
+            .expect("body_text field");
+        let snippet = super::highlight::build_snippet(&email, &["claude"], field)
+            .expect("snippet for matching body");
+        let highlighted = snippet
+            .highlights
+            .iter()
+            .map(|range| &snippet.text[range.clone()])
+            .collect::<Vec<_>>();
+        assert_eq!(highlighted, vec!["Claude"]);
     }
 }
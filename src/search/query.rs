@@ -0,0 +1,864 @@
+//! The `ess search` query language.
+//!
+//! Free words, quoted phrases, `from:`, `to:`, `subject:`, `note:`,
+//! `has:attachment`, `is:unread|read|flagged|starred`, `before:`/`after:`,
+//! `meta:key=value` and boolean `AND`/`OR`/`NOT` (or a leading `-`) parse into a [`QueryNode`]
+//! tree. Adjacent terms are joined with AND. The tree maps onto a Tantivy
+//! query for ranking, onto SQL for database filtering, and is evaluated
+//! against each hit so predicates the index does not store stay exact.
+
+use std::collections::BTreeSet;
+use std::ops::Bound;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use tantivy::query::{
+    BooleanQuery, Occur, Query, QueryParser, RangeQuery, TermQuery, TermSetQuery,
+};
+use tantivy::schema::IndexRecordOption;
+use tantivy::{DateTime as TantivyDateTime, Index, Term};
+use thiserror::Error;
+
+use crate::db::meta::{is_meta_key, meta_path, INDEXED_META_KEYS};
+use crate::db::models::Email;
+use crate::search::filters::SqlWhereClause;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum QueryParseError {
+    #[error("unbalanced parentheses in search query")]
+    UnbalancedParens,
+
+    #[error("'{operator}' needs a term after it")]
+    DanglingOperator { operator: &'static str },
+
+    #[error("invalid date '{value}' for {field}: expected YYYY-MM-DD")]
+    InvalidDate { field: &'static str, value: String },
+
+    #[error("unsupported {field}:{value} (expected {expected})")]
+    UnsupportedValue {
+        field: &'static str,
+        value: String,
+        expected: &'static str,
+    },
+
+    #[error("search query nests more than {max} levels deep")]
+    TooDeep { max: usize },
+}
+
+/// How deep groups and negations may nest. Deeper input is searched as its
+/// words instead of recursing without bound.
+const MAX_QUERY_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextField {
+    From,
+    To,
+    Subject,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageState {
+    Unread,
+    Read,
+    Flagged,
+}
+
+/// A lookup [`QueryNode::resolve_local`] needs answered from SQLite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocalLookup<'a> {
+    /// Emails whose thread has a note containing the text.
+    Note(&'a str),
+    /// Emails starred with `ess star`.
+    Starred,
+    /// Emails the tagging rules gave this tag.
+    Tag(&'a str),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryNode {
+    /// A bare word matched against subject, sender and body.
+    Word(String),
+    /// A quoted phrase matched verbatim (case-insensitive).
+    Phrase(String),
+    Field {
+        field: TextField,
+        value: String,
+    },
+    /// A note on the email's thread contains `text`. `email_ids` is empty
+    /// until [`QueryNode::resolve_local`] looks the notes up.
+    Note {
+        text: String,
+        email_ids: BTreeSet<String>,
+    },
+    /// Starred locally with `ess star`; resolved like `Note`.
+    Starred {
+        email_ids: BTreeSet<String>,
+    },
+    /// Tagged by the rules in `rules.toml`; resolved like `Note`.
+    Tag {
+        tag: String,
+        email_ids: BTreeSet<String>,
+    },
+    HasAttachment,
+    Is(MessageState),
+    /// An `emails.metadata` key holds this value, compared as
+    /// [`crate::db::meta::meta_value_text`] renders it.
+    Meta {
+        key: String,
+        value: String,
+    },
+    /// Received strictly before the start of this day.
+    Before(NaiveDate),
+    /// Received on or after this day.
+    After(NaiveDate),
+    And(Vec<QueryNode>),
+    Or(Vec<QueryNode>),
+    Not(Box<QueryNode>),
+}
+
+/// Parses a search string. Returns `None` for an empty query.
+pub fn parse_query(input: &str) -> Result<Option<QueryNode>, QueryParseError> {
+    let tokens = tokenize(input);
+    let mut parser = Parser {
+        tokens: &tokens,
+        position: 0,
+    };
+    let node = parser.parse_or(0)?;
+    if parser.position < tokens.len() {
+        return Err(QueryParseError::UnbalancedParens);
+    }
+    Ok(node)
+}
+
+/// Parses like [`parse_query`], but searches input the query language
+/// rejects, such as `(budget` or `before:yesterday`, as its literal words
+/// rather than failing. The index, stored-email and SQL searches all go
+/// through here, so they agree on such input.
+pub fn parse_query_or_words(input: &str) -> Option<QueryNode> {
+    parse_query(input).unwrap_or_else(|_| {
+        let mut words: Vec<_> = input
+            .split_whitespace()
+            .map(|word| word.trim_matches(|ch: char| !ch.is_alphanumeric()))
+            .filter(|word| !word.is_empty() && !matches!(*word, "AND" | "OR" | "NOT"))
+            .map(|word| QueryNode::Word(word.to_string()))
+            .collect();
+        match words.len() {
+            0 => None,
+            1 => words.pop(),
+            _ => Some(QueryNode::And(words)),
+        }
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Word(String),
+    Phrase(String),
+    Field(String, String),
+}
+
+fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&ch) = chars.peek() {
+        if ch.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        match ch {
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '"' => {
+                chars.next();
+                tokens.push(Token::Phrase(read_phrase(&mut chars)));
+            }
+            '-' => {
+                chars.next();
+                if chars.peek().is_some_and(|next| !next.is_whitespace()) {
+                    tokens.push(Token::Not);
+                } else {
+                    tokens.push(Token::Word("-".to_string()));
+                }
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || next == '(' || next == ')' {
+                        break;
+                    }
+                    chars.next();
+                    if next == ':' && !word.is_empty() && is_field_name(&word) {
+                        let value = if chars.peek() == Some(&'"') {
+                            chars.next();
+                            read_phrase(&mut chars)
+                        } else {
+                            read_word(&mut chars)
+                        };
+                        if value.is_empty() {
+                            word.push(':');
+                            break;
+                        }
+                        tokens.push(Token::Field(word.to_ascii_lowercase(), value));
+                        word.clear();
+                        break;
+                    }
+                    word.push(next);
+                }
+                match word.as_str() {
+                    "" => {}
+                    "AND" => tokens.push(Token::And),
+                    "OR" => tokens.push(Token::Or),
+                    "NOT" => tokens.push(Token::Not),
+                    _ => tokens.push(Token::Word(word)),
+                }
+            }
+        }
+    }
+    tokens
+}
+
+fn is_field_name(name: &str) -> bool {
+    matches!(
+        name.to_ascii_lowercase().as_str(),
+        "from" | "to" | "subject" | "note" | "tag" | "has" | "is" | "before" | "after" | "meta"
+    )
+}
+
+fn read_phrase(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> String {
+    // An unterminated quote runs to the end of the input.
+    chars.by_ref().take_while(|&ch| ch != '"').collect()
+}
+
+fn read_word(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> String {
+    let mut word = String::new();
+    while let Some(&next) = chars.peek() {
+        if next.is_whitespace() || next == '(' || next == ')' {
+            break;
+        }
+        word.push(next);
+        chars.next();
+    }
+    word
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn parse_or(&mut self, depth: usize) -> Result<Option<QueryNode>, QueryParseError> {
+        let mut branches = Vec::new();
+        if let Some(node) = self.parse_and(depth)? {
+            branches.push(node);
+        }
+        while self.peek() == Some(&Token::Or) {
+            self.position += 1;
+            match self.parse_and(depth)? {
+                Some(node) => branches.push(node),
+                None => return Err(QueryParseError::DanglingOperator { operator: "OR" }),
+            }
+        }
+        Ok(combine(branches, QueryNode::Or))
+    }
+
+    fn parse_and(&mut self, depth: usize) -> Result<Option<QueryNode>, QueryParseError> {
+        let mut terms = Vec::new();
+        loop {
+            match self.peek() {
+                None | Some(Token::Or) | Some(Token::Close) => break,
+                Some(Token::And) => {
+                    self.position += 1;
+                    if matches!(self.peek(), None | Some(Token::Or) | Some(Token::Close)) {
+                        return Err(QueryParseError::DanglingOperator { operator: "AND" });
+                    }
+                }
+                Some(_) => terms.push(self.parse_unary(depth)?),
+            }
+        }
+        Ok(combine(terms, QueryNode::And))
+    }
+
+    fn parse_unary(&mut self, depth: usize) -> Result<QueryNode, QueryParseError> {
+        let Some(token) = self.peek().cloned() else {
+            return Err(QueryParseError::DanglingOperator { operator: "NOT" });
+        };
+        self.position += 1;
+        if matches!(token, Token::Not | Token::Open) && depth >= MAX_QUERY_DEPTH {
+            return Err(QueryParseError::TooDeep {
+                max: MAX_QUERY_DEPTH,
+            });
+        }
+        match token {
+            Token::Not => {
+                if matches!(
+                    self.peek(),
+                    None | Some(Token::Or) | Some(Token::And) | Some(Token::Close)
+                ) {
+                    return Err(QueryParseError::DanglingOperator { operator: "NOT" });
+                }
+                Ok(QueryNode::Not(Box::new(self.parse_unary(depth + 1)?)))
+            }
+            Token::Open => {
+                let inner = self.parse_or(depth + 1)?;
+                if self.peek() != Some(&Token::Close) {
+                    return Err(QueryParseError::UnbalancedParens);
+                }
+                self.position += 1;
+                Ok(inner.unwrap_or(QueryNode::And(Vec::new())))
+            }
+            Token::Word(word) => Ok(QueryNode::Word(word)),
+            Token::Phrase(phrase) => Ok(QueryNode::Phrase(phrase)),
+            Token::Field(name, value) => field_node(&name, value),
+            Token::Close => Err(QueryParseError::UnbalancedParens),
+            Token::And | Token::Or => Err(QueryParseError::DanglingOperator { operator: "AND" }),
+        }
+    }
+}
+
+fn combine(mut nodes: Vec<QueryNode>, wrap: fn(Vec<QueryNode>) -> QueryNode) -> Option<QueryNode> {
+    match nodes.len() {
+        0 => None,
+        1 => nodes.pop(),
+        _ => Some(wrap(nodes)),
+    }
+}
+
+fn field_node(name: &str, value: String) -> Result<QueryNode, QueryParseError> {
+    let text = |field| QueryNode::Field {
+        field,
+        value: value.clone(),
+    };
+    match name {
+        "from" => Ok(text(TextField::From)),
+        "to" => Ok(text(TextField::To)),
+        "subject" => Ok(text(TextField::Subject)),
+        "note" => Ok(QueryNode::Note {
+            text: value,
+            email_ids: BTreeSet::new(),
+        }),
+        "tag" => Ok(QueryNode::Tag {
+            tag: value.to_lowercase(),
+            email_ids: BTreeSet::new(),
+        }),
+        "has" => match value.to_ascii_lowercase().as_str() {
+            "attachment" | "attachments" => Ok(QueryNode::HasAttachment),
+            _ => Err(QueryParseError::UnsupportedValue {
+                field: "has",
+                value,
+                expected: "attachment",
+            }),
+        },
+        "is" => match value.to_ascii_lowercase().as_str() {
+            "unread" => Ok(QueryNode::Is(MessageState::Unread)),
+            "read" => Ok(QueryNode::Is(MessageState::Read)),
+            "flagged" => Ok(QueryNode::Is(MessageState::Flagged)),
+            "starred" => Ok(QueryNode::Starred {
+                email_ids: BTreeSet::new(),
+            }),
+            _ => Err(QueryParseError::UnsupportedValue {
+                field: "is",
+                value,
+                expected: "unread, read, flagged or starred",
+            }),
+        },
+        "meta" => match value.split_once('=') {
+            Some((key, expected)) if is_meta_key(key) => Ok(QueryNode::Meta {
+                key: key.to_string(),
+                value: expected.to_string(),
+            }),
+            _ => Err(QueryParseError::UnsupportedValue {
+                field: "meta",
+                value,
+                expected: "key=value",
+            }),
+        },
+        "before" => parse_date("before", &value).map(QueryNode::Before),
+        _ => parse_date("after", &value).map(QueryNode::After),
+    }
+}
+
+fn parse_date(field: &'static str, value: &str) -> Result<NaiveDate, QueryParseError> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(value, "%Y/%m/%d"))
+        .map_err(|_| QueryParseError::InvalidDate {
+            field,
+            value: value.to_string(),
+        })
+}
+
+impl QueryNode {
+    /// Fills in the emails each `note:`, `tag:` and `is:starred` term
+    /// matches. Notes, tags and stars live in SQLite, not in the index, so
+    /// this has to run before the query is used for search.
+    pub fn resolve_local<E>(
+        &mut self,
+        lookup: &mut impl FnMut(LocalLookup<'_>) -> Result<BTreeSet<String>, E>,
+    ) -> Result<(), E> {
+        match self {
+            Self::Note { text, email_ids } => *email_ids = lookup(LocalLookup::Note(text))?,
+            Self::Starred { email_ids } => *email_ids = lookup(LocalLookup::Starred)?,
+            Self::Tag { tag, email_ids } => *email_ids = lookup(LocalLookup::Tag(tag))?,
+            Self::And(children) | Self::Or(children) => {
+                for child in children {
+                    child.resolve_local(lookup)?;
+                }
+            }
+            Self::Not(child) => child.resolve_local(lookup)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Builds a Tantivy query that matches a superset of this node, or
+    /// `None` when the index cannot narrow it (fields it does not store,
+    /// negations). Hits are checked with [`QueryNode::matches`] afterwards.
+    pub fn to_tantivy(&self, index: &Index) -> tantivy::Result<Option<Box<dyn Query>>> {
+        let schema = index.schema();
+        let field = |name: &str| schema.get_field(name).ok();
+        let text_query = |names: &[(&str, f32)], value: &str| -> Option<Box<dyn Query>> {
+            let fields: Vec<_> = names
+                .iter()
+                .filter_map(|(name, boost)| field(name).map(|field| (field, *boost)))
+                .collect();
+            let cleaned: String = value
+                .chars()
+                .map(|ch| if ch.is_alphanumeric() { ch } else { ' ' })
+                .collect();
+            if fields.is_empty() || cleaned.trim().is_empty() {
+                return None;
+            }
+            let mut parser =
+                QueryParser::for_index(index, fields.iter().map(|(field, _)| *field).collect());
+            parser.set_conjunction_by_default();
+            for (field, boost) in &fields {
+                parser.set_field_boost(*field, *boost);
+            }
+            parser.parse_query(&cleaned).ok()
+        };
+
+        Ok(match self {
+            Self::Word(value) | Self::Phrase(value) => text_query(DEFAULT_FIELDS, value),
+            Self::Field {
+                field: TextField::Subject,
+                value,
+            } => text_query(&[("subject", 1.0), ("subject_words", 1.0)], value),
+            Self::Field {
+                field: TextField::From,
+                value,
+            } => text_query(
+                &[
+                    ("from_name", 1.0),
+                    ("from_name_words", 1.0),
+                    ("from_address", 1.0),
+                ],
+                value,
+            ),
+            Self::Field {
+                field: TextField::To,
+                ..
+            }
+            | Self::HasAttachment
+            | Self::Is(_)
+            | Self::Not(_) => None,
+            Self::Note { email_ids, .. }
+            | Self::Starred { email_ids }
+            | Self::Tag { email_ids, .. } => field("email_db_id").map(|field| {
+                Box::new(TermSetQuery::new(
+                    email_ids.iter().map(|id| Term::from_field_text(field, id)),
+                )) as Box<dyn Query>
+            }),
+            Self::Meta { key, value } => {
+                if INDEXED_META_KEYS.contains(&key.as_str()) {
+                    field("meta").map(|field| {
+                        Box::new(TermQuery::new(
+                            Term::from_field_text(field, &format!("{key}={value}")),
+                            IndexRecordOption::Basic,
+                        )) as Box<dyn Query>
+                    })
+                } else {
+                    None
+                }
+            }
+            Self::Before(date) => field("received_at")
+                .map(|_| date_range(Bound::Unbounded, Bound::Excluded(start_of_day(*date)))),
+            Self::After(date) => field("received_at")
+                .map(|_| date_range(Bound::Included(start_of_day(*date)), Bound::Unbounded)),
+            Self::And(children) => {
+                let mut clauses = Vec::new();
+                for child in children {
+                    if let Some(query) = child.to_tantivy(index)? {
+                        clauses.push((Occur::Must, query));
+                    }
+                }
+                (!clauses.is_empty())
+                    .then(|| Box::new(BooleanQuery::new(clauses)) as Box<dyn Query>)
+            }
+            Self::Or(children) => {
+                let mut clauses = Vec::new();
+                for child in children {
+                    match child.to_tantivy(index)? {
+                        Some(query) => clauses.push((Occur::Should, query)),
+                        None => return Ok(None),
+                    }
+                }
+                Some(Box::new(BooleanQuery::new(clauses)))
+            }
+        })
+    }
+
+    /// True when [`QueryNode::to_tantivy`] matches more than this node does,
+    /// so callers should over-fetch before filtering hits.
+    pub fn needs_post_filter(&self) -> bool {
+        match self {
+            Self::Word(_)
+            | Self::Note { .. }
+            | Self::Starred { .. }
+            | Self::Tag { .. }
+            | Self::Before(_)
+            | Self::After(_) => false,
+            Self::Field { field, .. } => *field == TextField::To,
+            Self::Meta { key, .. } => !INDEXED_META_KEYS.contains(&key.as_str()),
+            Self::And(children) | Self::Or(children) => {
+                children.iter().any(QueryNode::needs_post_filter)
+            }
+            _ => true,
+        }
+    }
+
+    /// Evaluates the query against a stored email.
+    pub fn matches(&self, email: &Email) -> bool {
+        match self {
+            Self::Word(value) | Self::Phrase(value) => {
+                let needle = value.to_lowercase();
+                [
+                    email.subject.as_deref(),
+                    email.from_name.as_deref(),
+                    email.from_address.as_deref(),
+                    email.body_text.as_deref().or(email.body_preview.as_deref()),
+                ]
+                .into_iter()
+                .flatten()
+                .any(|haystack| contains_ci(haystack, &needle))
+            }
+            Self::Field { field, value } => {
+                let needle = value.to_lowercase();
+                match field {
+                    TextField::Subject => email
+                        .subject
+                        .as_deref()
+                        .is_some_and(|subject| contains_ci(subject, &needle)),
+                    TextField::From => [email.from_address.as_deref(), email.from_name.as_deref()]
+                        .into_iter()
+                        .flatten()
+                        .any(|value| contains_ci(value, &needle)),
+                    TextField::To => email
+                        .to_addresses
+                        .iter()
+                        .chain(&email.cc_addresses)
+                        .chain(&email.bcc_addresses)
+                        .any(|value| contains_ci(value, &needle)),
+                }
+            }
+            Self::Note { email_ids, .. }
+            | Self::Starred { email_ids }
+            | Self::Tag { email_ids, .. } => email_ids.contains(&email.id),
+            Self::HasAttachment => email.has_attachments.unwrap_or(false),
+            Self::Is(MessageState::Unread) => !email.is_read.unwrap_or(false),
+            Self::Is(MessageState::Read) => email.is_read.unwrap_or(false),
+            Self::Is(MessageState::Flagged) => email.flag_status.as_deref() == Some("flagged"),
+            Self::Meta { key, value } => email.meta_text(key).as_deref() == Some(value.as_str()),
+            Self::Before(date) => received_date(email).is_some_and(|received| received < *date),
+            Self::After(date) => received_date(email).is_some_and(|received| received >= *date),
+            Self::And(children) => children.iter().all(|child| child.matches(email)),
+            Self::Or(children) => children.iter().any(|child| child.matches(email)),
+            Self::Not(child) => !child.matches(email),
+        }
+    }
+
+    /// SQL over the `emails` table with the same meaning as
+    /// [`QueryNode::matches`].
+    pub fn to_sql(&self) -> SqlWhereClause {
+        let like = |value: &str| format!("%{value}%");
+        match self {
+            Self::Word(value) | Self::Phrase(value) => SqlWhereClause::new(
+                "(subject LIKE ? OR body_text LIKE ? OR from_name LIKE ? OR from_address LIKE ?)",
+                vec![like(value); 4],
+            ),
+            Self::Field {
+                field: TextField::Subject,
+                value,
+            } => SqlWhereClause::new("subject LIKE ?", [like(value)]),
+            Self::Field {
+                field: TextField::From,
+                value,
+            } => SqlWhereClause::new(
+                "(LOWER(from_address) LIKE LOWER(?) OR LOWER(from_name) LIKE LOWER(?))",
+                vec![like(value); 2],
+            ),
+            Self::Field {
+                field: TextField::To,
+                value,
+            } => SqlWhereClause::new(
+                "(LOWER(to_addresses) LIKE LOWER(?) OR LOWER(cc_addresses) LIKE LOWER(?) OR LOWER(bcc_addresses) LIKE LOWER(?))",
+                vec![like(value); 3],
+            ),
+            Self::Note { text, .. } => SqlWhereClause::new(
+                "conversation_id IN (SELECT conversation_id FROM notes WHERE LOWER(body) LIKE LOWER(?))",
+                [like(text)],
+            ),
+            Self::Starred { .. } => SqlWhereClause::new("id IN (SELECT email_id FROM stars)", []),
+            Self::Tag { tag, .. } => SqlWhereClause::new(
+                "id IN (SELECT email_id FROM tags WHERE tag = ?)",
+                [tag.clone()],
+            ),
+            Self::HasAttachment => SqlWhereClause::new("COALESCE(has_attachments, 0) = 1", []),
+            Self::Is(MessageState::Unread) => SqlWhereClause::new("COALESCE(is_read, 0) = 0", []),
+            Self::Is(MessageState::Read) => SqlWhereClause::new("COALESCE(is_read, 0) = 1", []),
+            Self::Is(MessageState::Flagged) => {
+                SqlWhereClause::new("flag_status = ?", ["flagged".to_string()])
+            }
+            // Booleans need `json_type`: `json_extract` returns them as 1/0.
+            Self::Meta { key, value } => SqlWhereClause::new(
+                "CASE json_type(metadata, ?) WHEN 'true' THEN 'true' WHEN 'false' THEN 'false' \
+                 ELSE CAST(json_extract(metadata, ?) AS TEXT) END = ?",
+                [meta_path(key), meta_path(key), value.clone()],
+            ),
+            Self::Before(date) => {
+                SqlWhereClause::new("DATE(received_at) < DATE(?)", [date.to_string()])
+            }
+            Self::After(date) => {
+                SqlWhereClause::new("DATE(received_at) >= DATE(?)", [date.to_string()])
+            }
+            Self::And(children) => SqlWhereClause::all(children.iter().map(QueryNode::to_sql)),
+            Self::Or(children) => SqlWhereClause::any(children.iter().map(QueryNode::to_sql)),
+            Self::Not(child) => child.to_sql().negate(),
+        }
+    }
+
+    /// Words and phrases the query looks for, used to pick snippets.
+    pub fn positive_terms(&self) -> Vec<&str> {
+        match self {
+            Self::Word(value) | Self::Phrase(value) => vec![value.as_str()],
+            Self::And(children) | Self::Or(children) => children
+                .iter()
+                .flat_map(QueryNode::positive_terms)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+const DEFAULT_FIELDS: &[(&str, f32)] = &[
+    ("subject", crate::indexer::schema::SUBJECT_BOOST),
+    ("subject_words", crate::indexer::schema::SUBJECT_BOOST),
+    ("from_name", crate::indexer::schema::FROM_NAME_BOOST),
+    ("from_name_words", crate::indexer::schema::FROM_NAME_BOOST),
+    ("body_text", crate::indexer::schema::BODY_BOOST),
+    ("body_words", crate::indexer::schema::BODY_BOOST),
+];
+
+fn contains_ci(haystack: &str, needle_lower: &str) -> bool {
+    haystack.to_lowercase().contains(needle_lower)
+}
+
+fn received_date(email: &Email) -> Option<NaiveDate> {
+    DateTime::parse_from_rfc3339(&email.received_at)
+        .map(|received| received.with_timezone(&Utc).date_naive())
+        .ok()
+        .or_else(|| NaiveDate::parse_from_str(email.received_at.get(..10)?, "%Y-%m-%d").ok())
+}
+
+fn start_of_day(date: NaiveDate) -> TantivyDateTime {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+    TantivyDateTime::from_timestamp_micros(midnight.timestamp_micros())
+}
+
+fn date_range(lower: Bound<TantivyDateTime>, upper: Bound<TantivyDateTime>) -> Box<dyn Query> {
+    Box::new(RangeQuery::new_date_bounds(
+        "received_at".to_string(),
+        lower,
+        upper,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::{
+        parse_query, parse_query_or_words, MessageState, QueryNode, QueryParseError, TextField,
+    };
+
+    #[test]
+    fn parses_fields_phrases_and_boolean_operators() {
+        let parsed = parse_query(
+            r#""quarterly report" from:alice (has:attachment OR is:unread) -subject:draft after:2026/01/01"#,
+        )
+        .expect("parse query")
+        .expect("non-empty query");
+
+        assert_eq!(
+            parsed,
+            QueryNode::And(vec![
+                QueryNode::Phrase("quarterly report".to_string()),
+                QueryNode::Field {
+                    field: TextField::From,
+                    value: "alice".to_string(),
+                },
+                QueryNode::Or(vec![
+                    QueryNode::HasAttachment,
+                    QueryNode::Is(MessageState::Unread),
+                ]),
+                QueryNode::Not(Box::new(QueryNode::Field {
+                    field: TextField::Subject,
+                    value: "draft".to_string(),
+                })),
+                QueryNode::After(NaiveDate::from_ymd_opt(2026, 1, 1).expect("valid date")),
+            ])
+        );
+        assert_eq!(
+            parsed.positive_terms(),
+            vec!["quarterly report"],
+            "negated and field terms are not snippet candidates"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_queries() {
+        assert_eq!(parse_query("   ").expect("empty query"), None);
+        assert_eq!(
+            parse_query("(budget"),
+            Err(QueryParseError::UnbalancedParens)
+        );
+        assert_eq!(
+            parse_query("budget OR"),
+            Err(QueryParseError::DanglingOperator { operator: "OR" })
+        );
+        assert!(matches!(
+            parse_query("before:yesterday"),
+            Err(QueryParseError::InvalidDate { .. })
+        ));
+        // Unknown prefixes such as URLs stay plain words.
+        assert_eq!(
+            parse_query("https://example.com").expect("parse url"),
+            Some(QueryNode::Word("https://example.com".to_string()))
+        );
+        // Searches fall back to the words of a rejected query.
+        assert_eq!(
+            parse_query_or_words("(budget OR \"draft"),
+            Some(QueryNode::And(vec![
+                QueryNode::Word("budget".to_string()),
+                QueryNode::Word("draft".to_string()),
+            ]))
+        );
+        assert_eq!(
+            parse_query_or_words("before:yesterday"),
+            Some(QueryNode::Word("before:yesterday".to_string()))
+        );
+        assert_eq!(parse_query_or_words("( ("), None);
+    }
+
+    #[test]
+    fn deep_nesting_falls_back_to_words() {
+        let nested = format!("{}x{}", "(".repeat(20_000), ")".repeat(20_000));
+        let negated = format!("{}x", "-".repeat(20_000));
+        for query in [&nested, &negated] {
+            assert!(matches!(
+                parse_query(query),
+                Err(QueryParseError::TooDeep { .. })
+            ));
+            assert_eq!(
+                parse_query_or_words(query),
+                Some(QueryNode::Word("x".to_string()))
+            );
+        }
+
+        let shallow = format!("{}x{}", "(".repeat(64), ")".repeat(64));
+        assert_eq!(
+            parse_query(&shallow).expect("parse nested query"),
+            Some(QueryNode::Word("x".to_string()))
+        );
+    }
+
+    #[test]
+    fn sql_mirrors_query_structure() {
+        let parsed = parse_query("to:bob NOT is:read")
+            .expect("parse query")
+            .expect("non-empty query");
+        let sql = parsed.to_sql();
+        assert_eq!(
+            sql.clause,
+            "((LOWER(to_addresses) LIKE LOWER(?) OR LOWER(cc_addresses) LIKE LOWER(?) OR LOWER(bcc_addresses) LIKE LOWER(?)) AND NOT (COALESCE(is_read, 0) = 1))"
+        );
+        assert_eq!(sql.params, vec!["%bob%"; 3]);
+    }
+
+    #[test]
+    fn meta_terms_match_in_memory_and_in_sql() {
+        use crate::db::models::Email;
+        use crate::db::Database;
+
+        assert_eq!(
+            parse_query("meta:source=graph_delta_sync").expect("parse meta"),
+            Some(QueryNode::Meta {
+                key: "source".to_string(),
+                value: "graph_delta_sync".to_string(),
+            })
+        );
+        assert!(matches!(
+            parse_query("meta:source"),
+            Err(QueryParseError::UnsupportedValue { field: "meta", .. })
+        ));
+
+        let path = std::env::temp_dir().join(format!("ess-query-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        let mut email = Email {
+            received_at: "2026-02-01T10:00:00Z".to_string(),
+            ..Email::fixture("e1")
+        };
+        email.set_meta("source", "graph_delta_sync");
+        email.set_meta("triaged", true);
+        email.set_meta("priority", 2);
+        db.insert_email(&email).expect("insert email");
+
+        for (query, expected) in [
+            ("meta:source=graph_delta_sync", true),
+            ("meta:triaged=true", true),
+            ("meta:priority=2", true),
+            ("meta:priority=3", false),
+            ("meta:missing=x", false),
+            ("-meta:source=gmail_sync", true),
+        ] {
+            let node = parse_query(query).expect("parse").expect("non-empty");
+            assert_eq!(node.matches(&email), expected, "{query} in memory");
+            let sql = node.to_sql();
+            let count: i64 = db
+                .conn()
+                .query_row(
+                    &format!("SELECT COUNT(*) FROM emails WHERE {}", sql.clause),
+                    rusqlite::params_from_iter(sql.params.iter()),
+                    |row| row.get(0),
+                )
+                .expect("run sql");
+            assert_eq!(count == 1, expected, "{query} in sql");
+        }
+
+        let _ = std::fs::remove_file(path);
+    }
+}
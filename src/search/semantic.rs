@@ -0,0 +1,119 @@
+//! Hybrid ranking for `ess search --semantic`: BM25 hits from the index and
+//! nearest neighbours among stored embeddings, fused by reciprocal rank.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::db::Database;
+use crate::embeddings::{similarity, Embedder};
+use crate::indexer::EmailIndex;
+use crate::search::filters::EmailFilters;
+use crate::search::{highlight, parse_query_or_words, search_emails, QueryNode, SearchResult};
+
+/// Reciprocal rank fusion constant; larger values flatten the advantage of
+/// the top few ranks.
+pub const RRF_K: f32 = 60.0;
+/// Candidates drawn from each ranking per requested result.
+const CANDIDATES_PER_RESULT: usize = 4;
+const MIN_CANDIDATES: usize = 50;
+
+/// Ranks emails by `1/(RRF_K + rank)` summed over the keyword ranking and
+/// the vector-similarity ranking, so a message near the top of either list
+/// surfaces and one near the top of both wins. `filters` apply to both
+/// sides; `score` in the results is the fused score.
+pub async fn semantic_search(
+    index: &EmailIndex,
+    db: &Database,
+    embedder: &dyn Embedder,
+    query: &str,
+    filters: &EmailFilters,
+) -> Result<Vec<SearchResult>> {
+    let query = query.trim();
+    if query.is_empty() {
+        bail!("semantic search needs a query");
+    }
+    let model = embedder.model();
+    if db.count_embeddings(model)? == 0 {
+        bail!("no embeddings stored for model '{model}'; run `ess embed` first");
+    }
+
+    let wanted = filters.limit.max(1).saturating_add(filters.offset);
+    let candidates = wanted
+        .saturating_mul(CANDIDATES_PER_RESULT)
+        .max(MIN_CANDIDATES);
+
+    let query_vector = embedder
+        .embed(&[query.to_string()])
+        .await?
+        .pop()
+        .ok_or_else(|| anyhow!("embedding model returned no vector for the query"))?;
+    let mut vector_filters = filters.clone();
+    vector_filters.query = None;
+    let predicate = vector_filters.to_sql_where();
+    let mut nearest = db
+        .embeddings_matching(model, &predicate.clause, &predicate.params)?
+        .into_iter()
+        .map(|(id, vector)| (similarity(&query_vector, &vector), id))
+        .filter(|(score, _)| *score > 0.0)
+        .collect::<Vec<_>>();
+    nearest.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    nearest.truncate(candidates);
+
+    // Natural-language questions occasionally trip the query syntax (a
+    // stray `:` or quote); those search as their words.
+    let parsed = parse_query_or_words(query);
+    let keyword_hits = if parsed.is_some() {
+        let mut keyword_filters = filters.clone();
+        keyword_filters.limit = candidates;
+        keyword_filters.offset = 0;
+        search_emails(index, db, query, &keyword_filters)?
+    } else {
+        Vec::new()
+    };
+
+    let mut fused: HashMap<String, (f32, Option<SearchResult>)> = HashMap::new();
+    for (rank, hit) in keyword_hits.into_iter().enumerate() {
+        let entry = fused.entry(hit.email.id.clone()).or_default();
+        entry.0 += reciprocal_rank(rank);
+        entry.1 = Some(hit);
+    }
+    for (rank, (_, id)) in nearest.into_iter().enumerate() {
+        fused.entry(id).or_default().0 += reciprocal_rank(rank);
+    }
+
+    let mut ranked = fused.into_iter().collect::<Vec<_>>();
+    ranked.sort_by(|a, b| b.1 .0.total_cmp(&a.1 .0).then_with(|| a.0.cmp(&b.0)));
+
+    let snippet_terms = parsed
+        .as_ref()
+        .map(QueryNode::positive_terms)
+        .unwrap_or_default();
+    let body_field = index.tantivy_index().schema().get_field("body_text")?;
+    let mut results = Vec::with_capacity(filters.limit);
+    for (id, (score, hit)) in ranked
+        .into_iter()
+        .skip(filters.offset)
+        .take(filters.limit.max(1))
+    {
+        let result = match hit {
+            Some(hit) => SearchResult { score, ..hit },
+            None => {
+                let Some(email) = db.get_email(&id)? else {
+                    continue;
+                };
+                SearchResult {
+                    snippet: highlight::build_snippet(&email, &snippet_terms, body_field),
+                    email,
+                    score,
+                }
+            }
+        };
+        results.push(result);
+    }
+    Ok(results)
+}
+
+fn reciprocal_rank(rank: usize) -> f32 {
+    1.0 / (RRF_K + rank as f32 + 1.0)
+}
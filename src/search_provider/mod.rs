@@ -0,0 +1,341 @@
+//! GNOME Shell search provider: `ess search-provider run` owns a name on the
+//! session bus and answers `org.gnome.Shell.SearchProvider2`, so the Shell's
+//! overview search lists matching emails. `ess search-provider install`
+//! registers it; D-Bus then starts the process on the first search.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use futures_util::StreamExt;
+use zbus::zvariant::Value;
+use zbus::{connection, fdo, interface, MessageStream};
+
+use crate::db::Database;
+use crate::env;
+use crate::indexer::EmailIndex;
+use crate::search::filters::EmailFilters;
+use crate::search::search_emails;
+
+pub const BUS_NAME: &str = "io.github.krasmussen37.Ess.SearchProvider";
+pub const OBJECT_PATH: &str = "/io/github/krasmussen37/Ess/SearchProvider";
+pub const INTERFACE: &str = "org.gnome.Shell.SearchProvider2";
+/// The `.desktop` file the Shell shows the results under.
+pub const DESKTOP_ID: &str = "ess-search-provider.desktop";
+
+/// The Shell shows a handful of results per provider; more are wasted work.
+const MAX_RESULTS: usize = 10;
+const SUBJECT_FALLBACK: &str = "(no subject)";
+
+/// Answers provider calls from the index and database.
+pub struct SearchProvider {
+    index: EmailIndex,
+    /// The connection is not `Sync`, and zbus may call in from any thread.
+    db: Mutex<Database>,
+}
+
+impl SearchProvider {
+    pub fn new(index: EmailIndex, db: Database) -> Self {
+        Self {
+            index,
+            db: Mutex::new(db),
+        }
+    }
+
+    fn db(&self) -> std::sync::MutexGuard<'_, Database> {
+        self.db
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Ids of the best matches for the typed terms, which are joined into
+    /// one `ess search` query, so `from:` and friends work too.
+    pub fn result_ids(&self, terms: &[String]) -> Result<Vec<String>> {
+        let query = terms.join(" ");
+        if query.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        let filters = EmailFilters {
+            limit: MAX_RESULTS,
+            ..EmailFilters::default()
+        };
+        Ok(search_emails(&self.index, &self.db(), &query, &filters)?
+            .into_iter()
+            .map(|result| result.email.id)
+            .collect())
+    }
+
+    /// Subject as the name, sender and day as the description; ids no
+    /// longer stored are left out.
+    pub fn result_metas(&self, ids: &[String]) -> Result<Vec<HashMap<String, Value<'static>>>> {
+        let db = self.db();
+        let mut metas = Vec::with_capacity(ids.len());
+        for id in ids {
+            let Some(email) = db.get_email(id)? else {
+                continue;
+            };
+            let from = email
+                .from_name
+                .as_deref()
+                .or(email.from_address.as_deref())
+                .unwrap_or("(unknown)");
+            let day = email.received_at.get(..10).unwrap_or(&email.received_at);
+            let name = email.subject.as_deref().unwrap_or(SUBJECT_FALLBACK);
+            metas.push(HashMap::from([
+                ("id".to_string(), Value::from(id.clone())),
+                ("name".to_string(), Value::from(name.to_string())),
+                (
+                    "description".to_string(),
+                    Value::from(format!("{from} · {day}")),
+                ),
+            ]));
+        }
+        Ok(metas)
+    }
+
+    /// Opens the email in the provider's web client, when it has a link.
+    pub fn activate(&self, id: &str) -> Result<()> {
+        let Some(link) = self.db().get_email(id)?.and_then(|email| email.web_link) else {
+            tracing::warn!("email {id} has no web link to open");
+            return Ok(());
+        };
+        std::process::Command::new("xdg-open")
+            .arg(&link)
+            .spawn()
+            .with_context(|| format!("run xdg-open for email {id}"))?;
+        Ok(())
+    }
+}
+
+/// `org.gnome.Shell.SearchProvider2`; zbus adds the standard `Peer`,
+/// `Introspectable` and `Properties` interfaces.
+#[interface(name = "org.gnome.Shell.SearchProvider2")]
+impl SearchProvider {
+    fn get_initial_result_set(&self, terms: Vec<String>) -> fdo::Result<Vec<String>> {
+        self.result_ids(&terms).map_err(failed)
+    }
+
+    fn get_subsearch_result_set(
+        &self,
+        _previous_results: Vec<String>,
+        terms: Vec<String>,
+    ) -> fdo::Result<Vec<String>> {
+        self.result_ids(&terms).map_err(failed)
+    }
+
+    fn get_result_metas(
+        &self,
+        identifiers: Vec<String>,
+    ) -> fdo::Result<Vec<HashMap<String, Value<'static>>>> {
+        self.result_metas(&identifiers).map_err(failed)
+    }
+
+    fn activate_result(
+        &self,
+        identifier: String,
+        _terms: Vec<String>,
+        _timestamp: u32,
+    ) -> fdo::Result<()> {
+        self.activate(&identifier).map_err(failed)
+    }
+
+    fn launch_search(&self, _terms: Vec<String>, _timestamp: u32) {}
+}
+
+fn failed(error: anyhow::Error) -> fdo::Error {
+    fdo::Error::Failed(format!("{error:#}"))
+}
+
+/// Connects to the session bus, claims `BUS_NAME` and serves calls until
+/// the bus connection closes.
+pub async fn run(index: EmailIndex, db: Database) -> Result<()> {
+    let connection = session_bus()?
+        .serve_at(OBJECT_PATH, SearchProvider::new(index, db))?
+        .build()
+        .await
+        .context("connect to the session bus")?;
+    // Fail rather than queue behind another owner; zbus's builder queues.
+    connection
+        .request_name_with_flags(BUS_NAME, fdo::RequestNameFlags::DoNotQueue.into())
+        .await
+        .with_context(|| format!("claim {BUS_NAME}; is another provider running?"))?;
+    tracing::info!("serving {INTERFACE} as {BUS_NAME}");
+
+    // Calls are answered by zbus's own tasks; the stream only ends once the
+    // connection does.
+    let mut messages = MessageStream::from(&connection);
+    while let Some(message) = messages.next().await {
+        if let Err(error) = message {
+            tracing::info!("session bus connection closed: {error}");
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// The bus named by `DBUS_SESSION_BUS_ADDRESS`, else the socket in
+/// `$XDG_RUNTIME_DIR`, else wherever zbus finds one on this platform.
+fn session_bus() -> Result<connection::Builder<'static>> {
+    let address = env::var(env::DBUS_SESSION_BUS_ADDRESS)
+        .or_else(|| env::var(env::XDG_RUNTIME_DIR).map(|dir| format!("unix:path={dir}/bus")));
+    Ok(match address {
+        Some(address) => connection::Builder::address(address.as_str())
+            .with_context(|| format!("parse session bus address {address}"))?,
+        None => connection::Builder::session().context("find the session bus")?,
+    })
+}
+
+/// The files `install` writes under the XDG data directory, with contents.
+pub fn registration_files(data_dir: &Path, exe: &Path) -> Vec<(PathBuf, String)> {
+    let exe = exe.display();
+    vec![
+        (
+            data_dir.join("applications").join(DESKTOP_ID),
+            format!(
+                "[Desktop Entry]\n\
+                 Type=Application\n\
+                 Name=ESS Mail\n\
+                 Comment=Search indexed email\n\
+                 Icon=mail-message-new\n\
+                 Exec=\"{exe}\" search-provider run\n\
+                 NoDisplay=true\n"
+            ),
+        ),
+        (
+            data_dir
+                .join("gnome-shell")
+                .join("search-providers")
+                .join("ess-search-provider.ini"),
+            format!(
+                "[Shell Search Provider]\n\
+                 DesktopId={DESKTOP_ID}\n\
+                 BusName={BUS_NAME}\n\
+                 ObjectPath={OBJECT_PATH}\n\
+                 Version=2\n"
+            ),
+        ),
+        (
+            data_dir
+                .join("dbus-1")
+                .join("services")
+                .join(format!("{BUS_NAME}.service")),
+            format!(
+                "[D-BUS Service]\n\
+                 Name={BUS_NAME}\n\
+                 Exec=\"{exe}\" search-provider run\n"
+            ),
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use zbus::zvariant::Value;
+    use zbus::{connection, Guid};
+
+    use super::{registration_files, SearchProvider, BUS_NAME, INTERFACE, OBJECT_PATH};
+    use crate::db::models::{Account, AccountType, Email};
+    use crate::db::Database;
+    use crate::indexer::EmailIndex;
+
+    #[tokio::test]
+    async fn answers_shell_search_calls() {
+        let root = std::env::temp_dir().join(format!("ess-provider-test-{}", uuid::Uuid::new_v4()));
+        let db = Database::open(&root.join("ess.db")).expect("open db");
+        db.insert_account(&Account {
+            account_id: "acc".to_string(),
+            email_address: "me@example.com".to_string(),
+            display_name: None,
+            tenant_id: None,
+            account_type: AccountType::Professional,
+            enabled: true,
+            last_sync: None,
+            config: None,
+        })
+        .expect("insert account");
+        db.insert_email(&Email {
+            account_id: Some("acc".to_string()),
+            subject: Some("Quarterly budget".to_string()),
+            from_address: Some("alice@example.com".to_string()),
+            from_name: Some("Alice".to_string()),
+            body_text: Some("Numbers for Q3".to_string()),
+            received_at: "2026-02-01T10:00:00Z".to_string(),
+            is_read: Some(false),
+            has_attachments: Some(false),
+            folder: Some("inbox".to_string()),
+            ..Email::fixture("e1")
+        })
+        .expect("insert email");
+        let mut index = EmailIndex::open(&root.join("index")).expect("open index");
+        index.reindex(&db).expect("reindex");
+
+        // A peer-to-peer connection stands in for the session bus.
+        let (server, client) = tokio::net::UnixStream::pair().expect("socket pair");
+        let server = connection::Builder::unix_stream(server)
+            .server(Guid::generate())
+            .expect("server guid")
+            .p2p()
+            .serve_at(OBJECT_PATH, SearchProvider::new(index, db))
+            .expect("serve provider")
+            .build();
+        let client = connection::Builder::unix_stream(client).p2p().build();
+        let (_server, client) = tokio::try_join!(server, client).expect("connect");
+        let call = |member: &'static str, terms: Vec<&'static str>| {
+            let client = client.clone();
+            async move {
+                client
+                    .call_method(
+                        Some(BUS_NAME),
+                        OBJECT_PATH,
+                        Some(INTERFACE),
+                        member,
+                        &(terms,),
+                    )
+                    .await
+            }
+        };
+
+        let reply = call("GetInitialResultSet", vec!["quarterly", "budget"])
+            .await
+            .expect("initial results");
+        let ids: Vec<String> = reply.body().deserialize().expect("result ids");
+        assert_eq!(ids, vec!["e1".to_string()]);
+
+        let reply = call("GetResultMetas", vec!["e1", "missing"])
+            .await
+            .expect("result metas");
+        let body = reply.body();
+        let metas: Vec<HashMap<String, Value>> = body.deserialize().expect("metas");
+        assert_eq!(
+            metas,
+            vec![HashMap::from([
+                ("id".to_string(), Value::from("e1")),
+                ("name".to_string(), Value::from("Quarterly budget")),
+                ("description".to_string(), Value::from("Alice · 2026-02-01")),
+            ])]
+        );
+
+        assert!(call("Unknown", Vec::new()).await.is_err());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn registration_points_the_shell_at_the_bus_name() {
+        let files = registration_files(
+            std::path::Path::new("/home/me/.local/share"),
+            std::path::Path::new("/usr/bin/ess"),
+        );
+        let (ini_path, ini) = &files[1];
+        assert!(ini_path.ends_with("gnome-shell/search-providers/ess-search-provider.ini"));
+        assert!(ini.contains("BusName=io.github.krasmussen37.Ess.SearchProvider\n"));
+        assert!(ini.contains("DesktopId=ess-search-provider.desktop\n"));
+        let (service_path, service) = &files[2];
+        assert!(service_path
+            .ends_with("dbus-1/services/io.github.krasmussen37.Ess.SearchProvider.service"));
+        assert!(service.contains("Exec=\"/usr/bin/ess\" search-provider run\n"));
+    }
+}
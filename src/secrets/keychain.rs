@@ -0,0 +1,90 @@
+//! The OS keychain as a store for credentials, through the `keyring`
+//! crate: the macOS login keychain, the Secret Service on Linux and BSD (as
+//! provided by GNOME Keyring or KeePassXC), and the Windows Credential
+//! Manager.
+//!
+//! Every entry lives under service `ess` and is named
+//! `<account_id>:<key>` for connector credentials, e.g.
+//! `you@company.com:client_secret`, or by its purpose alone, like the
+//! SQLCipher `database-key`. Where no keychain is reachable an entry reads
+//! as absent, and `ESS_KEYCHAIN=0` turns lookups off altogether.
+
+use keyring::Entry;
+
+use crate::connectors::conditions::parse_flag;
+use crate::env;
+use crate::secrets::SecretsError;
+
+pub const SERVICE: &str = "ess";
+
+/// Where credentials are kept besides the environment and account config.
+pub trait SecretStore {
+    fn get(&self, name: &str) -> Option<String>;
+    fn set(&self, name: &str, value: &str) -> Result<(), SecretsError>;
+    /// Whether there was an entry to delete.
+    fn delete(&self, name: &str) -> Result<bool, SecretsError>;
+}
+
+/// The entry holding `key` of `account_id`.
+pub fn entry_name(account_id: &str, key: &str) -> String {
+    format!("{account_id}:{key}")
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Keychain;
+
+impl Keychain {
+    /// Unless `ESS_KEYCHAIN` turns it off.
+    pub fn enabled() -> bool {
+        env::var(env::KEYCHAIN)
+            .as_deref()
+            .and_then(parse_flag)
+            .unwrap_or(true)
+    }
+}
+
+impl SecretStore for Keychain {
+    fn get(&self, name: &str) -> Option<String> {
+        if !Self::enabled() {
+            return None;
+        }
+        entry(name)
+            .ok()?
+            .get_password()
+            .ok()
+            .filter(|value| !value.is_empty())
+    }
+
+    fn set(&self, name: &str, value: &str) -> Result<(), SecretsError> {
+        entry(name)?.set_password(value).map_err(keychain_error)
+    }
+
+    fn delete(&self, name: &str) -> Result<bool, SecretsError> {
+        match entry(name)?.delete_credential() {
+            Ok(()) => Ok(true),
+            Err(keyring::Error::NoEntry) => Ok(false),
+            Err(error) => Err(keychain_error(error)),
+        }
+    }
+}
+
+fn entry(name: &str) -> Result<Entry, SecretsError> {
+    Entry::new(SERVICE, name).map_err(keychain_error)
+}
+
+fn keychain_error(error: keyring::Error) -> SecretsError {
+    SecretsError::Keychain(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::entry_name;
+
+    #[test]
+    fn names() {
+        assert_eq!(
+            entry_name("you@company.com", "client_secret"),
+            "you@company.com:client_secret"
+        );
+    }
+}
@@ -0,0 +1,594 @@
+//! Credential lookup and token-cache encryption shared by the connectors.
+//!
+//! A connector credential comes from its environment variable, else from
+//! the OS keychain (see [`keychain`]), else from the account's config JSON.
+//! `ess secrets migrate` moves credentials out of the config into the
+//! keychain.
+//!
+//! Access tokens cached in `sync_state` are sealed with AES-256-GCM under
+//! `ESS_TOKEN_CACHE_KEY`. During a key rollover `ESS_TOKEN_CACHE_KEY_PREVIOUS`
+//! stays readable so existing entries keep working until
+//! `ess secrets rotate-key` (or the next token read) re-seals them under the
+//! current key.
+//!
+//! [`TokenRefreshPolicy`] decides when a cached token is too close to expiry
+//! to hand out for another page of sync work.
+
+use chrono::{DateTime, Duration, Utc};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::db::models::Account;
+use crate::db::{Database, DbError};
+use crate::env;
+
+use self::keychain::{entry_name, Keychain, SecretStore};
+
+pub mod keychain;
+
+pub const TOKEN_CACHE_KEY_BYTES: usize = 32;
+const TOKEN_CACHE_NONCE_BYTES: usize = 12;
+const TOKEN_CACHE_ENVELOPE_VERSION: u8 = 1;
+
+/// `sync_state` key prefixes whose values are sealed access tokens.
+pub const TOKEN_CACHE_PREFIXES: &[&str] = &["graph_api_token:", "gmail_access_token:"];
+
+/// Account config keys holding OAuth credentials.
+pub const CREDENTIAL_KEYS: &[&str] = &["client_id", "client_secret", "refresh_token"];
+
+#[derive(Debug, Error)]
+pub enum SecretsError {
+    #[error("{name} must be 64 hex characters (32 bytes)")]
+    InvalidKey { name: &'static str },
+
+    #[error("{name} must be a whole number of seconds")]
+    InvalidSeconds { name: &'static str },
+
+    #[error("token envelope: {0}")]
+    Envelope(String),
+
+    #[error("{0}")]
+    Crypto(&'static str),
+
+    #[error("keychain: {0}")]
+    Keychain(String),
+
+    #[error(transparent)]
+    Db(#[from] DbError),
+}
+
+/// Reads a credential from its environment variable, else the account's
+/// entry in the OS keychain, else the matching string in its config JSON.
+pub fn credential(env_name: &str, account: &Account, key: &str) -> Option<String> {
+    credential_in(&Keychain, env_name, account, key)
+}
+
+pub fn credential_in(
+    store: &dyn SecretStore,
+    env_name: &str,
+    account: &Account,
+    key: &str,
+) -> Option<String> {
+    env::var(env_name)
+        .or_else(|| store.get(&entry_name(&account.account_id, key)))
+        .or_else(|| config_string(account, key))
+}
+
+/// Moves the credentials in `account`'s config JSON into `store` and drops
+/// them from the stored config, returning the keys moved.
+pub fn migrate_credentials(
+    db: &Database,
+    store: &dyn SecretStore,
+    account: &Account,
+) -> Result<Vec<String>, SecretsError> {
+    let mut account = account.clone();
+    let mut moved = Vec::new();
+    for key in CREDENTIAL_KEYS {
+        if let Some(value) = config_string(&account, key) {
+            store.set(&entry_name(&account.account_id, key), &value)?;
+            moved.push(key.to_string());
+        }
+    }
+    if let Some(config) = account.config.as_mut().and_then(|c| c.as_object_mut()) {
+        for key in &moved {
+            config.remove(key);
+        }
+    }
+    if !moved.is_empty() {
+        db.insert_account(&account)?;
+    }
+    Ok(moved)
+}
+
+pub fn config_string(account: &Account, key: &str) -> Option<String> {
+    account
+        .config
+        .as_ref()
+        .and_then(|config| config.get(key))
+        .and_then(|value| value.as_str())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+/// Default allowance for the local clock running ahead of the provider's.
+pub const DEFAULT_TOKEN_SKEW_SECONDS: u64 = 60;
+/// Default look-ahead covering one page of sync work, so a token never
+/// expires while a slow folder page is still being fetched.
+pub const DEFAULT_TOKEN_REFRESH_WINDOW_SECONDS: u64 = 300;
+
+/// When to stop reusing a cached access token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenRefreshPolicy {
+    pub skew_seconds: u64,
+    pub window_seconds: u64,
+}
+
+impl Default for TokenRefreshPolicy {
+    fn default() -> Self {
+        Self {
+            skew_seconds: DEFAULT_TOKEN_SKEW_SECONDS,
+            window_seconds: DEFAULT_TOKEN_REFRESH_WINDOW_SECONDS,
+        }
+    }
+}
+
+impl TokenRefreshPolicy {
+    /// Reads `ESS_TOKEN_SKEW_SECONDS` and `ESS_TOKEN_REFRESH_WINDOW_SECONDS`,
+    /// keeping the defaults for whichever is unset.
+    pub fn from_env() -> Result<Self, SecretsError> {
+        let defaults = Self::default();
+        Ok(Self {
+            skew_seconds: seconds_from_env(env::TOKEN_SKEW_SECONDS)?
+                .unwrap_or(defaults.skew_seconds),
+            window_seconds: seconds_from_env(env::TOKEN_REFRESH_WINDOW_SECONDS)?
+                .unwrap_or(defaults.window_seconds),
+        })
+    }
+
+    /// True when a token expiring at `expires_at` could lapse before the
+    /// next page of work completes, allowing for clock skew. A margin
+    /// reaching past what a timestamp can hold always refreshes.
+    pub fn needs_refresh(&self, expires_at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        let margin = self.skew_seconds.saturating_add(self.window_seconds);
+        i64::try_from(margin)
+            .ok()
+            .and_then(Duration::try_seconds)
+            .and_then(|margin| now.checked_add_signed(margin))
+            .is_none_or(|limit| expires_at <= limit)
+    }
+}
+
+fn seconds_from_env(name: &'static str) -> Result<Option<u64>, SecretsError> {
+    env::var(name)
+        .map(|raw| {
+            raw.parse::<u64>()
+                .map_err(|_| SecretsError::InvalidSeconds { name })
+        })
+        .transpose()
+}
+
+/// The current token-cache key plus, during a rollover, the previous one.
+#[derive(Clone)]
+pub struct TokenCacheKeys {
+    current: [u8; TOKEN_CACHE_KEY_BYTES],
+    previous: Option<[u8; TOKEN_CACHE_KEY_BYTES]>,
+}
+
+impl std::fmt::Debug for TokenCacheKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TokenCacheKeys")
+            .field("previous", &self.previous.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Plaintext recovered from an envelope.
+#[derive(Debug)]
+pub struct Unsealed {
+    pub plaintext: Vec<u8>,
+    /// True when only the previous key could open the envelope; the caller
+    /// should seal it again under the current key.
+    pub needs_reseal: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct EncryptedTokenEnvelope {
+    version: u8,
+    nonce_hex: String,
+    ciphertext_hex: String,
+}
+
+impl TokenCacheKeys {
+    pub fn new(
+        current: [u8; TOKEN_CACHE_KEY_BYTES],
+        previous: Option<[u8; TOKEN_CACHE_KEY_BYTES]>,
+    ) -> Self {
+        Self { current, previous }
+    }
+
+    /// Loads the keys from the environment. Returns `None` when no current
+    /// key is configured, in which case tokens must not be cached at rest.
+    pub fn from_env() -> Result<Option<Self>, SecretsError> {
+        let Some(current) = env::var(env::TOKEN_CACHE_KEY) else {
+            return Ok(None);
+        };
+        let current = parse_key_hex(&current).ok_or(SecretsError::InvalidKey {
+            name: env::TOKEN_CACHE_KEY,
+        })?;
+        let previous = env::var(env::TOKEN_CACHE_KEY_PREVIOUS)
+            .map(|raw| {
+                parse_key_hex(&raw).ok_or(SecretsError::InvalidKey {
+                    name: env::TOKEN_CACHE_KEY_PREVIOUS,
+                })
+            })
+            .transpose()?;
+        Ok(Some(Self::new(current, previous)))
+    }
+
+    pub fn has_previous(&self) -> bool {
+        self.previous.is_some()
+    }
+
+    /// Encrypts `plaintext` under the current key into a JSON envelope.
+    pub fn seal(&self, plaintext: &[u8]) -> Result<String, SecretsError> {
+        let key = aead_key(&self.current)?;
+        let mut nonce_bytes = [0u8; TOKEN_CACHE_NONCE_BYTES];
+        SystemRandom::new().fill(&mut nonce_bytes).map_err(|_| {
+            SecretsError::Crypto("generate random nonce for token cache encryption")
+        })?;
+
+        let mut ciphertext = plaintext.to_vec();
+        key.seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce_bytes),
+            Aad::empty(),
+            &mut ciphertext,
+        )
+        .map_err(|_| SecretsError::Crypto("encrypt token cache"))?;
+
+        let envelope = EncryptedTokenEnvelope {
+            version: TOKEN_CACHE_ENVELOPE_VERSION,
+            nonce_hex: hex_encode(&nonce_bytes),
+            ciphertext_hex: hex_encode(&ciphertext),
+        };
+        serde_json::to_string(&envelope).map_err(|error| SecretsError::Envelope(error.to_string()))
+    }
+
+    /// Decrypts an envelope with the current key, falling back to the
+    /// previous key during a rollover.
+    pub fn open(&self, raw: &str) -> Result<Unsealed, SecretsError> {
+        let envelope: EncryptedTokenEnvelope =
+            serde_json::from_str(raw).map_err(|error| SecretsError::Envelope(error.to_string()))?;
+        if envelope.version != TOKEN_CACHE_ENVELOPE_VERSION {
+            return Err(SecretsError::Envelope(format!(
+                "unsupported version {}",
+                envelope.version
+            )));
+        }
+
+        let nonce_bytes: [u8; TOKEN_CACHE_NONCE_BYTES] = hex_decode(&envelope.nonce_hex)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| SecretsError::Envelope("invalid nonce".to_string()))?;
+        let ciphertext = hex_decode(&envelope.ciphertext_hex)
+            .ok_or_else(|| SecretsError::Envelope("invalid ciphertext hex".to_string()))?;
+
+        if let Some(plaintext) = open_with(&self.current, nonce_bytes, &ciphertext)? {
+            return Ok(Unsealed {
+                plaintext,
+                needs_reseal: false,
+            });
+        }
+        if let Some(previous) = &self.previous {
+            if let Some(plaintext) = open_with(previous, nonce_bytes, &ciphertext)? {
+                return Ok(Unsealed {
+                    plaintext,
+                    needs_reseal: true,
+                });
+            }
+        }
+        Err(SecretsError::Crypto(
+            "decrypt token cache: no configured key matches",
+        ))
+    }
+}
+
+fn aead_key(bytes: &[u8; TOKEN_CACHE_KEY_BYTES]) -> Result<LessSafeKey, SecretsError> {
+    let unbound = UnboundKey::new(&AES_256_GCM, bytes)
+        .map_err(|_| SecretsError::Crypto("construct AES-256-GCM key"))?;
+    Ok(LessSafeKey::new(unbound))
+}
+
+fn open_with(
+    key: &[u8; TOKEN_CACHE_KEY_BYTES],
+    nonce: [u8; TOKEN_CACHE_NONCE_BYTES],
+    ciphertext: &[u8],
+) -> Result<Option<Vec<u8>>, SecretsError> {
+    let key = aead_key(key)?;
+    let mut buffer = ciphertext.to_vec();
+    Ok(key
+        .open_in_place(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::empty(),
+            &mut buffer,
+        )
+        .ok()
+        .map(|plaintext| plaintext.to_vec()))
+}
+
+/// Outcome of `ess secrets rotate-key`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RotationReport {
+    /// Entries re-sealed under the current key.
+    pub reencrypted: usize,
+    /// Entries that were already sealed under the current key.
+    pub unchanged: usize,
+    /// Entries no configured key could open; removed so the next sync
+    /// fetches a fresh token.
+    pub discarded: usize,
+}
+
+/// Re-seals every cached access token under the current key. Plaintext
+/// entries written before encryption existed are sealed as well.
+pub fn rotate_token_cache(
+    db: &Database,
+    keys: &TokenCacheKeys,
+) -> Result<RotationReport, SecretsError> {
+    let mut report = RotationReport::default();
+    for prefix in TOKEN_CACHE_PREFIXES {
+        for state in db.list_sync_state(prefix)? {
+            let Some(raw) = state.value else {
+                continue;
+            };
+            match keys.open(&raw) {
+                Ok(unsealed) if !unsealed.needs_reseal => report.unchanged += 1,
+                Ok(unsealed) => {
+                    db.set_sync_state(&state.key, &keys.seal(&unsealed.plaintext)?)?;
+                    report.reencrypted += 1;
+                }
+                Err(_) if is_plaintext_token(&raw) => {
+                    db.set_sync_state(&state.key, &keys.seal(raw.as_bytes())?)?;
+                    report.reencrypted += 1;
+                }
+                Err(_) => {
+                    db.delete_sync_state(&state.key)?;
+                    report.discarded += 1;
+                }
+            }
+        }
+    }
+    Ok(report)
+}
+
+fn is_plaintext_token(raw: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(raw)
+        .ok()
+        .is_some_and(|value| value.get("access_token").is_some())
+}
+
+/// Generates a random key in the hex form `ESS_TOKEN_CACHE_KEY` expects.
+pub fn generate_key_hex() -> Result<String, SecretsError> {
+    let mut bytes = [0u8; TOKEN_CACHE_KEY_BYTES];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| SecretsError::Crypto("generate random token cache key"))?;
+    Ok(hex_encode(&bytes))
+}
+
+pub fn parse_key_hex(raw: &str) -> Option<[u8; TOKEN_CACHE_KEY_BYTES]> {
+    hex_decode(raw)?.try_into().ok()
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        out.push(HEX[(byte >> 4) as usize] as char);
+        out.push(HEX[(byte & 0x0f) as usize] as char);
+    }
+    out
+}
+
+fn hex_decode(raw: &str) -> Option<Vec<u8>> {
+    let value = raw.trim();
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+
+    value
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| Some((decode_hex_nibble(pair[0])? << 4) | decode_hex_nibble(pair[1])?))
+        .collect()
+}
+
+fn decode_hex_nibble(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => Some(byte - b'0'),
+        b'a'..=b'f' => Some(byte - b'a' + 10),
+        b'A'..=b'F' => Some(byte - b'A' + 10),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use chrono::{Duration, Utc};
+
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::keychain::SecretStore;
+    use super::{
+        credential_in, migrate_credentials, parse_key_hex, rotate_token_cache, RotationReport,
+        SecretsError, TokenCacheKeys, TokenRefreshPolicy,
+    };
+    use crate::db::models::{Account, AccountType};
+    use crate::db::Database;
+
+    #[derive(Default)]
+    struct MemoryStore(RefCell<HashMap<String, String>>);
+
+    impl SecretStore for MemoryStore {
+        fn get(&self, name: &str) -> Option<String> {
+            self.0.borrow().get(name).cloned()
+        }
+
+        fn set(&self, name: &str, value: &str) -> Result<(), SecretsError> {
+            self.0
+                .borrow_mut()
+                .insert(name.to_string(), value.to_string());
+            Ok(())
+        }
+
+        fn delete(&self, name: &str) -> Result<bool, SecretsError> {
+            Ok(self.0.borrow_mut().remove(name).is_some())
+        }
+    }
+
+    #[test]
+    fn credentials_move_from_config_to_the_keychain() {
+        let path = std::env::temp_dir().join(format!("ess-secrets-{}.db", Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        let account = Account {
+            account_id: "me@gmail.com".to_string(),
+            email_address: "me@gmail.com".to_string(),
+            display_name: None,
+            tenant_id: None,
+            account_type: AccountType::Personal,
+            enabled: true,
+            last_sync: None,
+            config: Some(json!({
+                "connector": "gmail_api",
+                "client_id": "config-id",
+                "refresh_token": "config-token",
+            })),
+        };
+        db.insert_account(&account).expect("insert account");
+        let store = MemoryStore::default();
+        store
+            .set("me@gmail.com:client_id", "keychain-id")
+            .expect("set");
+        let lookup = |account: &Account, key| {
+            credential_in(&store, crate::env::GMAIL_CLIENT_ID, account, key)
+        };
+        assert_eq!(
+            lookup(&account, "client_id").as_deref(),
+            Some("keychain-id")
+        );
+        assert_eq!(
+            lookup(&account, "refresh_token").as_deref(),
+            Some("config-token")
+        );
+
+        let moved = migrate_credentials(&db, &store, &account).expect("migrate");
+        assert_eq!(moved, ["client_id", "refresh_token"]);
+        let stored = db
+            .get_account("me@gmail.com")
+            .expect("get")
+            .expect("account");
+        assert_eq!(stored.config, Some(json!({"connector": "gmail_api"})));
+        assert_eq!(lookup(&stored, "client_id").as_deref(), Some("config-id"));
+        assert_eq!(
+            lookup(&stored, "refresh_token").as_deref(),
+            Some("config-token")
+        );
+        assert_eq!(lookup(&stored, "client_secret"), None);
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    const OLD_KEY: &str = "00112233445566778899aabbccddeeff00112233445566778899aabbccddeeff";
+    const NEW_KEY: &str = "ffeeddccbbaa99887766554433221100ffeeddccbbaa99887766554433221100";
+
+    fn key(hex: &str) -> [u8; 32] {
+        parse_key_hex(hex).expect("valid test key")
+    }
+
+    #[test]
+    fn previous_key_opens_during_rollover() {
+        let old = TokenCacheKeys::new(key(OLD_KEY), None);
+        let sealed = old.seal(b"secret").expect("seal");
+
+        let rolled = TokenCacheKeys::new(key(NEW_KEY), Some(key(OLD_KEY)));
+        let unsealed = rolled.open(&sealed).expect("open with previous key");
+        assert_eq!(unsealed.plaintext, b"secret");
+        assert!(unsealed.needs_reseal);
+
+        let new_only = TokenCacheKeys::new(key(NEW_KEY), None);
+        assert!(new_only.open(&sealed).is_err());
+    }
+
+    #[test]
+    fn refresh_policy_covers_skew_and_window() {
+        let policy = TokenRefreshPolicy {
+            skew_seconds: 60,
+            window_seconds: 300,
+        };
+        let now = Utc::now();
+        assert!(policy.needs_refresh(now + Duration::seconds(359), now));
+        assert!(!policy.needs_refresh(now + Duration::seconds(361), now));
+
+        let lenient = TokenRefreshPolicy {
+            skew_seconds: 600,
+            window_seconds: 0,
+        };
+        assert!(lenient.needs_refresh(now + Duration::minutes(9), now));
+
+        let huge = TokenRefreshPolicy {
+            skew_seconds: 60,
+            window_seconds: 10_000_000_000_000,
+        };
+        assert!(huge.needs_refresh(now + Duration::days(365), now));
+        let overflowing = TokenRefreshPolicy {
+            skew_seconds: u64::MAX,
+            window_seconds: u64::MAX,
+        };
+        assert!(overflowing.needs_refresh(now, now));
+    }
+
+    #[test]
+    fn rotate_reseals_cached_tokens() {
+        let path = std::env::temp_dir().join(format!("ess-secrets-test-{}.db", Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        let old = TokenCacheKeys::new(key(OLD_KEY), None);
+        db.set_sync_state("graph_api_token:a", &old.seal(b"{}").expect("seal"))
+            .expect("seed sealed token");
+        db.set_sync_state(
+            "gmail_access_token:b",
+            r#"{"access_token":"plain","expires_at":"2030-01-01T00:00:00Z"}"#,
+        )
+        .expect("seed plaintext token");
+        db.set_sync_state("gmail_access_token:c", "garbage")
+            .expect("seed unreadable token");
+
+        let rolled = TokenCacheKeys::new(key(NEW_KEY), Some(key(OLD_KEY)));
+        let report = rotate_token_cache(&db, &rolled).expect("rotate");
+        assert_eq!(
+            report,
+            RotationReport {
+                reencrypted: 2,
+                unchanged: 0,
+                discarded: 1,
+            }
+        );
+
+        let new_only = TokenCacheKeys::new(key(NEW_KEY), None);
+        let resealed = db
+            .get_sync_state("graph_api_token:a")
+            .expect("read state")
+            .and_then(|state| state.value)
+            .expect("value");
+        assert!(!new_only.open(&resealed).expect("open").needs_reseal);
+        assert!(db
+            .get_sync_state("gmail_access_token:c")
+            .expect("read state")
+            .is_none());
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,456 @@
+//! Conversation summaries for `ess summarize` and `ess_summarize`.
+//!
+//! A thread's messages are sent to a chat model behind [`Summarizer`]: any
+//! OpenAI-compatible `/chat/completions` endpoint, or Ollama's own
+//! `/api/chat`, selected with `ESS_SUMMARY_URL` and `ESS_SUMMARY_BACKEND`.
+//! The summary is stored per conversation with the number of messages it
+//! covers, so it is only written again once the thread changes.
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use chrono::{SecondsFormat, Utc};
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use serde_json::json;
+use thiserror::Error;
+
+use crate::db::models::Email;
+use crate::db::summaries::latest_received_at;
+use crate::db::{ConversationSummary, Database};
+use crate::env;
+
+pub const DEFAULT_OPENAI_MODEL: &str = "gpt-4o-mini";
+pub const DEFAULT_OLLAMA_MODEL: &str = "llama3.2";
+/// Characters of thread text sent per request; the newest messages are
+/// kept when a thread is longer.
+const MAX_PROMPT_CHARS: usize = 24_000;
+/// Characters of one message's body, so a single long message does not
+/// crowd out the rest of the thread.
+const MAX_MESSAGE_CHARS: usize = 4_000;
+
+const SYSTEM_PROMPT: &str = "You summarize email threads for the person who received them. \
+Write three to six sentences of plain text: what the thread is about, what was decided, \
+and any open questions, requests or deadlines, naming who owns each. Do not invent details.";
+
+#[derive(Debug, Error)]
+pub enum SummaryError {
+    #[error("summary request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("summary API returned {status}: {body}")]
+    Api { status: u16, body: String },
+
+    #[error("summary API returned no text")]
+    Empty,
+
+    #[error("{0}")]
+    Config(String),
+}
+
+#[async_trait(?Send)]
+pub trait Summarizer {
+    /// Name stored with each summary.
+    fn model(&self) -> &str;
+
+    /// The model's answer to `prompt` under the `system` instructions.
+    async fn complete(&self, system: &str, prompt: &str) -> Result<String, SummaryError>;
+}
+
+/// The API `ESS_SUMMARY_URL` speaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    OpenAi,
+    Ollama,
+}
+
+impl FromStr for Backend {
+    type Err = SummaryError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "openai" => Ok(Self::OpenAi),
+            "ollama" => Ok(Self::Ollama),
+            other => Err(SummaryError::Config(format!(
+                "unknown summary backend '{other}'; expected openai or ollama"
+            ))),
+        }
+    }
+}
+
+/// The summarizer configured by `ESS_SUMMARY_*`. Unlike embeddings there is
+/// no built-in fallback, so `ESS_SUMMARY_URL` must be set.
+pub fn from_env() -> Result<Box<dyn Summarizer>, SummaryError> {
+    let base_url = env::var(env::SUMMARY_URL).ok_or_else(|| {
+        SummaryError::Config(format!(
+            "{} is not set; point it at an OpenAI-compatible API (e.g. https://api.openai.com/v1) \
+             or an Ollama server (http://localhost:11434 with {}=ollama)",
+            env::SUMMARY_URL,
+            env::SUMMARY_BACKEND
+        ))
+    })?;
+    let backend = env::var(env::SUMMARY_BACKEND)
+        .map(|value| value.parse())
+        .transpose()?
+        .unwrap_or(Backend::OpenAi);
+    let model = env::var(env::SUMMARY_MODEL);
+    Ok(match backend {
+        Backend::OpenAi => Box::new(OpenAiSummarizer::new(
+            &base_url,
+            model.as_deref().unwrap_or(DEFAULT_OPENAI_MODEL),
+            env::var(env::SUMMARY_API_KEY),
+        )?),
+        Backend::Ollama => Box::new(OllamaSummarizer::new(
+            &base_url,
+            model.as_deref().unwrap_or(DEFAULT_OLLAMA_MODEL),
+        )?),
+    })
+}
+
+/// The conversation `id` names: the thread of the email with that id, or
+/// the conversation itself.
+pub fn resolve_conversation(db: &Database, id: &str) -> Result<String> {
+    if let Some(email) = db.get_email(id)? {
+        return email
+            .conversation_id
+            .ok_or_else(|| anyhow!("email '{id}' is not part of a conversation"));
+    }
+    if db.get_emails_by_conversation(id)?.is_empty() {
+        return Err(anyhow!("no email or conversation found for id '{id}'"));
+    }
+    Ok(id.to_string())
+}
+
+/// The stored summary of a conversation, if it still covers every message.
+pub fn current_summary(
+    db: &Database,
+    conversation_id: &str,
+) -> Result<Option<ConversationSummary>> {
+    let Some(summary) = db.summary(conversation_id)? else {
+        return Ok(None);
+    };
+    let emails = db.get_emails_by_conversation(conversation_id)?;
+    Ok(summary.covers(&emails).then_some(summary))
+}
+
+/// The summary of the conversation `id` names (see [`resolve_conversation`]):
+/// the stored one while it still covers the thread, unless `refresh`, or
+/// else a new one from the model configured by [`from_env`]. The flag is
+/// true for a stored summary.
+pub async fn summarize(
+    db: &Database,
+    id: &str,
+    refresh: bool,
+) -> Result<(ConversationSummary, bool)> {
+    let conversation_id = resolve_conversation(db, id)?;
+    if !refresh {
+        if let Some(summary) = current_summary(db, &conversation_id)? {
+            return Ok((summary, true));
+        }
+    }
+    let summarizer = from_env().context("configure summary model")?;
+    let summary = summarize_conversation(db, summarizer.as_ref(), &conversation_id).await?;
+    Ok((summary, false))
+}
+
+/// Asks `summarizer` for a summary of the conversation and stores it.
+pub async fn summarize_conversation(
+    db: &Database,
+    summarizer: &dyn Summarizer,
+    conversation_id: &str,
+) -> Result<ConversationSummary> {
+    let emails = db.get_emails_by_conversation(conversation_id)?;
+    if emails.is_empty() {
+        return Err(anyhow!("conversation '{conversation_id}' has no messages"));
+    }
+    let text = summarizer
+        .complete(SYSTEM_PROMPT, &thread_prompt(&emails))
+        .await
+        .with_context(|| format!("summarize conversation {conversation_id}"))?;
+    let summary = ConversationSummary {
+        conversation_id: conversation_id.to_string(),
+        summary: text.trim().to_string(),
+        model: summarizer.model().to_string(),
+        message_count: emails.len() as i64,
+        last_received_at: latest_received_at(&emails).map(str::to_string),
+        created_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+    };
+    db.store_summary(&summary)?;
+    Ok(summary)
+}
+
+/// The thread as the model reads it: the subject, then each message with
+/// its sender and date, oldest first. Older messages are left out, with a
+/// note saying so, when the whole thread exceeds `MAX_PROMPT_CHARS`.
+pub fn thread_prompt(emails: &[Email]) -> String {
+    let mut blocks = emails
+        .iter()
+        .rev()
+        .map(|email| {
+            let sender = match (&email.from_name, &email.from_address) {
+                (Some(name), Some(address)) => format!("{name} <{address}>"),
+                (Some(name), None) => name.clone(),
+                (None, Some(address)) => address.clone(),
+                (None, None) => "(unknown sender)".to_string(),
+            };
+            let body = email
+                .body_text
+                .as_deref()
+                .or(email.body_preview.as_deref())
+                .unwrap_or_default()
+                .trim();
+            let body = match body.char_indices().nth(MAX_MESSAGE_CHARS) {
+                Some((cut, _)) => format!("{} [...]", &body[..cut]),
+                None => body.to_string(),
+            };
+            format!(
+                "From: {sender}\nDate: {}\n\n{body}\n",
+                email.sent_at.as_deref().unwrap_or(&email.received_at)
+            )
+        })
+        .scan(0, |total, block| {
+            *total += block.chars().count();
+            (*total <= MAX_PROMPT_CHARS).then_some(block)
+        })
+        .collect::<Vec<_>>();
+    let omitted = emails.len() - blocks.len();
+    blocks.reverse();
+
+    let subject = emails
+        .first()
+        .and_then(|email| email.subject.as_deref())
+        .unwrap_or("(no subject)");
+    let mut prompt = format!("Subject: {subject}\n\n");
+    if omitted > 0 {
+        prompt.push_str(&format!("[{omitted} earlier messages omitted]\n\n"));
+    }
+    prompt.push_str(&blocks.join("\n---\n\n"));
+    prompt
+}
+
+/// Client for an OpenAI-compatible `POST {base}/chat/completions` endpoint.
+#[derive(Debug, Clone)]
+pub struct OpenAiSummarizer {
+    client: Client,
+    endpoint: Url,
+    model: String,
+    api_key: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatCompletion {
+    choices: Vec<ChatChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatMessage {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+impl OpenAiSummarizer {
+    pub fn new(base_url: &str, model: &str, api_key: Option<String>) -> Result<Self, SummaryError> {
+        Ok(Self {
+            client: Client::new(),
+            endpoint: endpoint(base_url, "chat/completions")?,
+            model: model.to_string(),
+            api_key,
+        })
+    }
+}
+
+#[async_trait(?Send)]
+impl Summarizer for OpenAiSummarizer {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn complete(&self, system: &str, prompt: &str) -> Result<String, SummaryError> {
+        let mut request = self.client.post(self.endpoint.clone()).json(&json!({
+            "model": self.model,
+            "messages": messages(system, prompt),
+            "temperature": 0.2,
+        }));
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        let completion: ChatCompletion = send(request).await?;
+        completion
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .filter(|content| !content.trim().is_empty())
+            .ok_or(SummaryError::Empty)
+    }
+}
+
+/// Client for Ollama's `POST {base}/api/chat`.
+#[derive(Debug, Clone)]
+pub struct OllamaSummarizer {
+    client: Client,
+    endpoint: Url,
+    model: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaChat {
+    message: ChatMessage,
+}
+
+impl OllamaSummarizer {
+    pub fn new(base_url: &str, model: &str) -> Result<Self, SummaryError> {
+        Ok(Self {
+            client: Client::new(),
+            endpoint: endpoint(base_url, "api/chat")?,
+            model: model.to_string(),
+        })
+    }
+}
+
+#[async_trait(?Send)]
+impl Summarizer for OllamaSummarizer {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn complete(&self, system: &str, prompt: &str) -> Result<String, SummaryError> {
+        let request = self.client.post(self.endpoint.clone()).json(&json!({
+            "model": self.model,
+            "messages": messages(system, prompt),
+            "stream": false,
+        }));
+        let chat: OllamaChat = send(request).await?;
+        chat.message
+            .content
+            .filter(|content| !content.trim().is_empty())
+            .ok_or(SummaryError::Empty)
+    }
+}
+
+fn endpoint(base_url: &str, path: &str) -> Result<Url, SummaryError> {
+    Url::parse(&format!("{}/{path}", base_url.trim_end_matches('/')))
+        .map_err(|error| SummaryError::Config(format!("invalid summary URL '{base_url}': {error}")))
+}
+
+fn messages(system: &str, prompt: &str) -> serde_json::Value {
+    json!([
+        {"role": "system", "content": system},
+        {"role": "user", "content": prompt},
+    ])
+}
+
+async fn send<T: for<'de> Deserialize<'de>>(
+    request: reqwest::RequestBuilder,
+) -> Result<T, SummaryError> {
+    let response = request.send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(SummaryError::Api {
+            status: status.as_u16(),
+            body: body.chars().take(500).collect(),
+        });
+    }
+    Ok(response.json().await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+
+    use super::{
+        current_summary, resolve_conversation, summarize_conversation, thread_prompt, Summarizer,
+        SummaryError, MAX_PROMPT_CHARS,
+    };
+    use crate::db::models::Email;
+    use crate::db::Database;
+
+    /// Echoes how many messages the prompt held.
+    struct Counting;
+
+    #[async_trait(?Send)]
+    impl Summarizer for Counting {
+        fn model(&self) -> &str {
+            "counting"
+        }
+
+        async fn complete(&self, _system: &str, prompt: &str) -> Result<String, SummaryError> {
+            Ok(format!(" {} messages \n", prompt.matches("From: ").count()))
+        }
+    }
+
+    fn email(id: &str, received_at: &str, body: &str) -> Email {
+        Email {
+            conversation_id: Some("t1".to_string()),
+            subject: Some("Q3 budget".to_string()),
+            from_address: Some("alice@example.com".to_string()),
+            from_name: Some("Alice".to_string()),
+            to_addresses: vec!["me@example.com".to_string()],
+            body_text: Some(body.to_string()),
+            received_at: received_at.to_string(),
+            ..Email::fixture(id)
+        }
+    }
+
+    #[test]
+    fn prompts_keep_the_newest_messages_of_long_threads() {
+        let short = thread_prompt(&[
+            email("m1", "2026-02-01T10:00:00Z", "Numbers attached."),
+            email("m2", "2026-02-02T10:00:00Z", "Looks good."),
+        ]);
+        assert!(short.starts_with("Subject: Q3 budget\n\nFrom: Alice <alice@example.com>"));
+        assert!(short.find("Numbers attached.") < short.find("Looks good."));
+
+        let long = (0..20)
+            .map(|n| {
+                email(
+                    &format!("m{n}"),
+                    &format!("2026-02-{:02}T10:00:00Z", n + 1),
+                    &"x".repeat(3_000),
+                )
+            })
+            .collect::<Vec<_>>();
+        let prompt = thread_prompt(&long);
+        assert!(prompt.chars().count() <= MAX_PROMPT_CHARS + 100);
+        assert!(prompt.contains("earlier messages omitted"));
+        assert!(prompt.contains("2026-02-20T10:00:00Z"));
+        assert!(!prompt.contains("2026-02-01T10:00:00Z"));
+    }
+
+    #[tokio::test]
+    async fn summaries_are_stored_until_the_thread_changes() {
+        let path = std::env::temp_dir().join(format!("ess-summarize-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        db.insert_email(&email("m1", "2026-02-01T10:00:00Z", "Numbers attached."))
+            .expect("insert m1");
+
+        assert_eq!(resolve_conversation(&db, "m1").expect("by email"), "t1");
+        assert_eq!(resolve_conversation(&db, "t1").expect("by thread"), "t1");
+        assert!(resolve_conversation(&db, "missing").is_err());
+        assert!(current_summary(&db, "t1").expect("current").is_none());
+
+        let summary = summarize_conversation(&db, &Counting, "t1")
+            .await
+            .expect("summarize");
+        assert_eq!(summary.summary, "1 messages");
+        assert_eq!(summary.model, "counting");
+        assert_eq!(current_summary(&db, "t1").expect("current"), Some(summary));
+
+        db.insert_email(&email("m2", "2026-02-02T10:00:00Z", "Looks good."))
+            .expect("insert m2");
+        assert!(current_summary(&db, "t1").expect("current").is_none());
+        let refreshed = summarize_conversation(&db, &Counting, "t1")
+            .await
+            .expect("summarize again");
+        assert_eq!(refreshed.summary, "2 messages");
+
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,578 @@
+//! Automatic tags such as `invoice`, `newsletter` or `travel`.
+//!
+//! Rules in `$XDG_CONFIG_HOME/ess/rules.toml` match on the sender address,
+//! keywords in the subject or body, and message headers. An optional
+//! `[model]` endpoint gets each email and the rule tags and may add tags of
+//! its own. `ess sync` tags every email that was added or changed since the
+//! last run, and every email again whenever the rules file changes; the
+//! tags land in the `tags` table for `--tag`, `tag:` and `ess stats`.
+//!
+//! ```toml
+//! [[rule]]
+//! tag = "invoice"
+//! from = ["billing@*", "*@stripe.com"]
+//! keywords = ["invoice", "receipt"]
+//!
+//! [[rule]]
+//! tag = "newsletter"
+//! headers = { "List-Unsubscribe" = "*" }
+//!
+//! [model]
+//! url = "http://127.0.0.1:8000/classify"
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use mail_parser::MessageParser;
+use reqwest::{Client, Url};
+use ring::digest::{digest, SHA256};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use thiserror::Error;
+
+use crate::db::models::Email;
+use crate::db::{Database, EmailTag, TaggedEmail};
+use crate::paths;
+use crate::raw::RawStore;
+
+pub const RULES_FILE: &str = "rules.toml";
+
+pub const RULE_SOURCE: &str = "rule";
+pub const MODEL_SOURCE: &str = "model";
+
+/// Emails tagged per database transaction.
+const BATCH_SIZE: usize = 500;
+/// Characters of the body sent to the model.
+const MAX_MODEL_BODY_CHARS: usize = 4_000;
+const DEFAULT_MODEL_TIMEOUT_SECS: u64 = 10;
+
+#[derive(Debug, Error)]
+pub enum TagModelError {
+    #[error("tag model request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("tag model returned {status}: {body}")]
+    Api { status: u16, body: String },
+}
+
+/// Tags an email beyond what the rules find, e.g. a local classifier.
+#[async_trait(?Send)]
+pub trait TagModel {
+    /// Extra tags for `email`, given the ones the rules assigned.
+    async fn tags(&self, email: &Email, rule_tags: &[String])
+        -> Result<Vec<String>, TagModelError>;
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    #[serde(default, rename = "rule")]
+    rules: Vec<Rule>,
+    model: Option<ModelConfig>,
+}
+
+/// One `[[rule]]`. Every criterion it sets must match; within `from` and
+/// `keywords` one entry is enough.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    pub tag: String,
+    /// Case-insensitive globs over the sender address.
+    #[serde(default)]
+    pub from: Vec<String>,
+    /// Case-insensitive text looked for in the subject and body.
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// Header name to a case-insensitive glob over its value.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ModelConfig {
+    url: String,
+    timeout_secs: Option<u64>,
+}
+
+/// A parsed `rules.toml`.
+pub struct TagRules {
+    rules: Vec<Rule>,
+    model: Option<Box<dyn TagModel>>,
+    hash: String,
+}
+
+impl std::fmt::Debug for TagRules {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TagRules")
+            .field("rules", &self.rules)
+            .field("model", &self.model.is_some())
+            .field("hash", &self.hash)
+            .finish()
+    }
+}
+
+/// What [`tag_pending`] did.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TagReport {
+    /// Emails (re)tagged.
+    pub emails: usize,
+    /// Of those, emails that got at least one tag.
+    pub tagged: usize,
+    /// Emails tagged by the rules alone because the model failed; they are
+    /// tagged again on the next run.
+    pub model_skipped: usize,
+}
+
+/// `$XDG_CONFIG_HOME/ess/rules.toml`.
+pub fn default_rules_path() -> Result<PathBuf> {
+    Ok(paths::config_dir()
+        .context("resolve ESS config directory")?
+        .join(RULES_FILE))
+}
+
+impl TagRules {
+    pub fn parse(raw: &str) -> Result<Self> {
+        let file: RulesFile = toml::from_str(raw)?;
+        let mut rules = file.rules;
+        for (position, rule) in rules.iter_mut().enumerate() {
+            rule.tag = normalize_tag(&rule.tag)
+                .ok_or_else(|| anyhow!("rule {}: invalid tag '{}'", position + 1, rule.tag))?;
+            if rule.from.is_empty() && rule.keywords.is_empty() && rule.headers.is_empty() {
+                return Err(anyhow!(
+                    "rule {} ({}): set at least one of from, keywords or headers",
+                    position + 1,
+                    rule.tag
+                ));
+            }
+        }
+        let model = file
+            .model
+            .map(|model| -> Result<Box<dyn TagModel>> {
+                Ok(Box::new(HttpTagModel::new(
+                    &model.url,
+                    Duration::from_secs(model.timeout_secs.unwrap_or(DEFAULT_MODEL_TIMEOUT_SECS)),
+                )?))
+            })
+            .transpose()?;
+        let hash = digest(&SHA256, raw.as_bytes())
+            .as_ref()
+            .iter()
+            .take(8)
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        Ok(Self { rules, model, hash })
+    }
+
+    /// The rules at `path`, or `None` when there is no such file.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        match std::fs::read_to_string(path) {
+            Ok(raw) => Self::parse(&raw)
+                .map(Some)
+                .with_context(|| format!("parse tagging rules at {}", path.display())),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(error) => {
+                Err(error).with_context(|| format!("read tagging rules at {}", path.display()))
+            }
+        }
+    }
+
+    pub fn load_default() -> Result<Option<Self>> {
+        Self::load(&default_rules_path()?)
+    }
+
+    /// Replaces the `[model]` endpoint, or adds one.
+    pub fn with_model(mut self, model: Box<dyn TagModel>) -> Self {
+        self.model = Some(model);
+        self
+    }
+
+    /// Identifies this version of the rules file.
+    pub fn hash(&self) -> &str {
+        &self.hash
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Tags of every rule that matches. `raw` supplies headers ESS does
+    /// not keep as fields, for emails whose source is stored.
+    pub fn rule_tags(&self, email: &Email, raw: Option<&RawStore>) -> Vec<String> {
+        let needs_raw = self.rules.iter().any(|rule| {
+            rule.headers
+                .keys()
+                .any(|name| stored_header(email, name).is_none())
+        });
+        let raw_source = needs_raw
+            .then(|| raw.and_then(|store| store.get(&email.id).ok().flatten()))
+            .flatten();
+        let parsed = raw_source
+            .as_deref()
+            .and_then(|bytes| MessageParser::default().parse(bytes));
+        let raw_headers: Vec<(&str, &str)> = parsed
+            .as_ref()
+            .map(|message| message.headers_raw().collect())
+            .unwrap_or_default();
+
+        let mut tags: Vec<String> = Vec::new();
+        for rule in &self.rules {
+            if !tags.contains(&rule.tag) && rule.matches(email, &raw_headers) {
+                tags.push(rule.tag.clone());
+            }
+        }
+        tags
+    }
+}
+
+impl Rule {
+    fn matches(&self, email: &Email, raw_headers: &[(&str, &str)]) -> bool {
+        let from_matches = self.from.is_empty()
+            || email.from_address.as_deref().is_some_and(|address| {
+                self.from
+                    .iter()
+                    .any(|pattern| glob_matches(pattern, address))
+            });
+        let keywords_match = self.keywords.is_empty() || {
+            let text = [
+                email.subject.as_deref(),
+                email.body_text.as_deref().or(email.body_preview.as_deref()),
+            ]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join("\n")
+            .to_lowercase();
+            self.keywords
+                .iter()
+                .any(|keyword| text.contains(&keyword.to_lowercase()))
+        };
+        let headers_match = self.headers.iter().all(|(name, pattern)| {
+            let values = stored_header(email, name).unwrap_or_else(|| {
+                raw_headers
+                    .iter()
+                    .filter(|(header, _)| header.eq_ignore_ascii_case(name))
+                    .map(|(_, value)| value.trim().to_string())
+                    .collect()
+            });
+            values.iter().any(|value| glob_matches(pattern, value))
+        });
+        from_matches && keywords_match && headers_match
+    }
+}
+
+/// Values of the headers ESS stores as fields, or `None` for any other
+/// header.
+fn stored_header(email: &Email, name: &str) -> Option<Vec<String>> {
+    let single = |value: Option<&String>| value.into_iter().cloned().collect();
+    Some(match name.to_ascii_lowercase().as_str() {
+        "from" => single(email.from_address.as_ref()),
+        "to" => email.to_addresses.clone(),
+        "cc" => email.cc_addresses.clone(),
+        "subject" => single(email.subject.as_ref()),
+        "importance" => single(email.importance.as_ref()),
+        "message-id" => single(email.internet_message_id.as_ref()),
+        _ => return None,
+    })
+}
+
+/// Lowercased and trimmed, or `None` unless made of letters, digits and
+/// `-`, `_`, `.`, `/`.
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.trim().to_lowercase();
+    (!tag.is_empty()
+        && tag
+            .chars()
+            .all(|ch| ch.is_alphanumeric() || matches!(ch, '-' | '_' | '.' | '/')))
+    .then_some(tag)
+}
+
+/// Case-insensitive glob with `*` (any run of characters) and `?` (one).
+fn glob_matches(pattern: &str, value: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let value: Vec<char> = value.to_lowercase().chars().collect();
+    let (mut p, mut v) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while v < value.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, v));
+                p += 1;
+            }
+            Some(&ch) if ch == '?' || ch == value[v] => {
+                p += 1;
+                v += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    v = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&ch| ch == '*')
+}
+
+/// Tags every email not yet tagged under the current rules, in batches.
+/// After the first model failure the rest are tagged by the rules alone
+/// and left for the next run to complete.
+pub async fn tag_pending(
+    db: &Database,
+    rules: &TagRules,
+    raw: Option<&RawStore>,
+) -> Result<TagReport> {
+    let mut report = TagReport::default();
+    let partial_hash = format!("{}+partial", rules.hash);
+    let mut model_failed = false;
+    let mut after: Option<String> = None;
+    loop {
+        let batch = db.emails_to_tag(&rules.hash, after.as_deref(), BATCH_SIZE)?;
+        let Some(last) = batch.last() else {
+            break;
+        };
+        after = Some(last.id.clone());
+
+        let mut tagged = Vec::with_capacity(batch.len());
+        for email in &batch {
+            let rule_tags = rules.rule_tags(email, raw);
+            let mut tags: Vec<EmailTag> = rule_tags
+                .iter()
+                .map(|tag| EmailTag {
+                    tag: tag.clone(),
+                    source: RULE_SOURCE.to_string(),
+                })
+                .collect();
+            let mut rules_hash = rules.hash.clone();
+            match rules.model.as_deref() {
+                Some(_) if model_failed => {
+                    rules_hash = partial_hash.clone();
+                    report.model_skipped += 1;
+                }
+                Some(model) => match model.tags(email, &rule_tags).await {
+                    Ok(model_tags) => {
+                        for tag in model_tags.iter().filter_map(|tag| normalize_tag(tag)) {
+                            if !tags.iter().any(|existing| existing.tag == tag) {
+                                tags.push(EmailTag {
+                                    tag,
+                                    source: MODEL_SOURCE.to_string(),
+                                });
+                            }
+                        }
+                    }
+                    Err(error) => {
+                        tracing::warn!(
+                            "tag model failed on {}: {error}; tagging by rules only",
+                            email.id
+                        );
+                        model_failed = true;
+                        rules_hash = partial_hash.clone();
+                        report.model_skipped += 1;
+                    }
+                },
+                None => {}
+            }
+            report.emails += 1;
+            if !tags.is_empty() {
+                report.tagged += 1;
+            }
+            tagged.push(TaggedEmail {
+                email_id: email.id.clone(),
+                tags,
+                rules_hash,
+            });
+        }
+        db.store_email_tags(&tagged)?;
+    }
+    Ok(report)
+}
+
+/// `POST {url}` with the email as JSON, answered by `{"tags": [...]}`.
+#[derive(Debug, Clone)]
+pub struct HttpTagModel {
+    client: Client,
+    url: Url,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelResponse {
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+impl HttpTagModel {
+    pub fn new(url: &str, timeout: Duration) -> Result<Self> {
+        Ok(Self {
+            client: Client::builder()
+                .timeout(timeout)
+                .build()
+                .context("build tag model client")?,
+            url: Url::parse(url).with_context(|| format!("invalid tag model URL '{url}'"))?,
+        })
+    }
+}
+
+#[async_trait(?Send)]
+impl TagModel for HttpTagModel {
+    async fn tags(
+        &self,
+        email: &Email,
+        rule_tags: &[String],
+    ) -> Result<Vec<String>, TagModelError> {
+        let body: String = email
+            .body_text
+            .as_deref()
+            .or(email.body_preview.as_deref())
+            .unwrap_or_default()
+            .chars()
+            .take(MAX_MODEL_BODY_CHARS)
+            .collect();
+        let response = self
+            .client
+            .post(self.url.clone())
+            .json(&json!({
+                "id": email.id,
+                "subject": email.subject,
+                "from": email.from_address,
+                "to": email.to_addresses,
+                "body": body,
+                "tags": rule_tags,
+            }))
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(TagModelError::Api {
+                status: status.as_u16(),
+                body: body.chars().take(500).collect(),
+            });
+        }
+        Ok(response.json::<ModelResponse>().await?.tags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+
+    use super::{glob_matches, tag_pending, TagModel, TagModelError, TagRules};
+    use crate::db::models::Email;
+    use crate::db::Database;
+
+    const RULES: &str = r#"
+        [[rule]]
+        tag = "Invoice"
+        from = ["billing@*"]
+        keywords = ["invoice", "receipt"]
+
+        [[rule]]
+        tag = "newsletter"
+        headers = { "List-Unsubscribe" = "*" }
+
+        [[rule]]
+        tag = "travel"
+        keywords = ["boarding pass"]
+    "#;
+
+    fn email(id: &str, from: &str, subject: &str) -> Email {
+        Email {
+            subject: Some(subject.to_string()),
+            from_address: Some(from.to_string()),
+            body_text: Some("Details inside.".to_string()),
+            received_at: "2026-02-01T10:00:00Z".to_string(),
+            ..Email::fixture(id)
+        }
+    }
+
+    struct Fails;
+
+    #[async_trait(?Send)]
+    impl TagModel for Fails {
+        async fn tags(&self, _: &Email, _: &[String]) -> Result<Vec<String>, TagModelError> {
+            Err(TagModelError::Api {
+                status: 503,
+                body: "down".to_string(),
+            })
+        }
+    }
+
+    struct Finance;
+
+    #[async_trait(?Send)]
+    impl TagModel for Finance {
+        async fn tags(
+            &self,
+            _: &Email,
+            rule_tags: &[String],
+        ) -> Result<Vec<String>, TagModelError> {
+            Ok(if rule_tags.iter().any(|tag| tag == "invoice") {
+                vec!["Finance".to_string(), "invoice".to_string()]
+            } else {
+                vec!["not a tag!".to_string()]
+            })
+        }
+    }
+
+    #[test]
+    fn rules_match_senders_keywords_and_headers() {
+        let rules = TagRules::parse(RULES).expect("parse rules");
+        let invoice = email("a", "Billing@Shop.example", "Your receipt");
+        assert_eq!(rules.rule_tags(&invoice, None), ["invoice"]);
+        let not_billing = email("b", "alice@example.com", "Invoice attached");
+        assert!(rules.rule_tags(&not_billing, None).is_empty());
+        let trip = email("c", "alice@example.com", "Your Boarding Pass");
+        assert_eq!(rules.rule_tags(&trip, None), ["travel"]);
+
+        assert!(glob_matches("*@stripe.com", "receipts@STRIPE.com"));
+        assert!(glob_matches("a?c*", "abcdef"));
+        assert!(!glob_matches("*@stripe.com", "x@stripe.com.evil"));
+        assert!(TagRules::parse("[[rule]]\ntag = \"empty\"").is_err());
+        assert!(TagRules::parse("[[rule]]\ntag = \"a b\"\nkeywords = [\"x\"]").is_err());
+    }
+
+    #[tokio::test]
+    async fn model_failures_are_retried_on_the_next_run() {
+        let path = std::env::temp_dir().join(format!("ess-tagging-{}.db", uuid::Uuid::new_v4()));
+        let db = Database::open(&path).expect("open db");
+        db.insert_email(&email("a", "billing@shop.example", "Invoice 42"))
+            .expect("insert a");
+        db.insert_email(&email("b", "alice@example.com", "Lunch"))
+            .expect("insert b");
+
+        let failing = TagRules::parse(RULES)
+            .expect("parse rules")
+            .with_model(Box::new(Fails));
+        let report = tag_pending(&db, &failing, None).await.expect("tag");
+        assert_eq!(
+            (report.emails, report.tagged, report.model_skipped),
+            (2, 1, 2)
+        );
+        assert_eq!(db.email_tags("a").expect("tags")[0].tag, "invoice");
+
+        let working = TagRules::parse(RULES)
+            .expect("parse rules")
+            .with_model(Box::new(Finance));
+        let report = tag_pending(&db, &working, None).await.expect("retag");
+        assert_eq!((report.emails, report.model_skipped), (2, 0));
+        let tags = db.email_tags("a").expect("tags");
+        let names: Vec<_> = tags
+            .iter()
+            .map(|tag| (tag.tag.as_str(), tag.source.as_str()))
+            .collect();
+        assert_eq!(names, [("finance", "model"), ("invoice", "rule")]);
+        assert!(db.email_tags("b").expect("tags").is_empty());
+        assert_eq!(
+            tag_pending(&db, &working, None).await.expect("noop").emails,
+            0
+        );
+
+        let _ = std::fs::remove_file(path);
+    }
+}
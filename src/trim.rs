@@ -0,0 +1,398 @@
+//! Keeping the store under `[storage] max_size_mb`.
+//!
+//! The store is the database, the search index and the raw messages
+//! together. Once it is over the cap, the bodies of emails are dropped in
+//! the order of `[storage] eviction` until the estimate falls under it:
+//! each trimmed email gives back its text and HTML body and its raw
+//! source. Headers, the preview and the attachment list stay, and the
+//! email is indexed again without its body. Starred and flagged emails
+//! and those in a collection are never trimmed. `ess trim` trims on
+//! demand, compacting the store before and after.
+//!
+//! Sync trims after each cycle too, but compacting rewrites the whole
+//! database and index, so it leaves the space it frees for the database and
+//! index to reuse and counts it as already given back. It compacts once
+//! that space passes a twentieth of the cap, or a day after the first
+//! uncompacted trim.
+//!
+//! ```toml
+//! [storage]
+//! max_size_mb = 2000
+//! eviction = "oldest"
+//! ```
+
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+
+use crate::config::StoreCap;
+use crate::db::{Database, EvictionPolicy, TrimCandidate};
+use crate::indexer::EmailIndex;
+use crate::raw::RawStore;
+
+/// `sync_state` key holding the body bytes sync has trimmed since the store
+/// was last compacted.
+const UNCOMPACTED_BYTES_KEY: &str = "trim_uncompacted_bytes";
+/// `sync_state` key holding when sync first trimmed after the last
+/// compaction.
+const UNCOMPACTED_SINCE_KEY: &str = "trim_uncompacted_since";
+/// Sync compacts once the uncompacted bytes pass this fraction of the cap.
+const COMPACT_SHARE_OF_CAP: u64 = 20;
+/// Or once the oldest uncompacted trim is this old.
+const COMPACT_AFTER_HOURS: i64 = 24;
+
+/// Bytes on disk, by part of the store.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct StoreSize {
+    pub database: u64,
+    pub index: u64,
+    pub raw: u64,
+    pub total: u64,
+}
+
+impl StoreSize {
+    pub fn measure(db: &Database, index: &EmailIndex, raw: &RawStore) -> Result<Self> {
+        let mut database = file_size(db.path())?;
+        for suffix in ["-wal", "-shm"] {
+            let mut sidecar = db.path().as_os_str().to_os_string();
+            sidecar.push(suffix);
+            database += file_size(Path::new(&sidecar))?;
+        }
+        let index = index
+            .get_stats()
+            .context("measure ESS index")?
+            .index_size_bytes;
+        let raw = directory_size(raw.root())
+            .with_context(|| format!("measure raw store at {}", raw.root().display()))?;
+        Ok(Self {
+            database,
+            index,
+            raw,
+            total: database + index + raw,
+        })
+    }
+}
+
+/// What one trim dropped, or with `dry_run` would drop.
+#[derive(Debug, Clone, Serialize)]
+pub struct TrimReport {
+    pub max_bytes: u64,
+    pub policy: EvictionPolicy,
+    pub dry_run: bool,
+    pub size_before: StoreSize,
+    pub size_after: StoreSize,
+    /// Bodies and raw sources dropped, in bytes; the files shrink by
+    /// roughly as much.
+    pub bytes_trimmed: u64,
+    pub trimmed: Vec<TrimCandidate>,
+}
+
+/// Compacts a store over `cap`, then drops bodies until it is estimated to
+/// fit, compacts again and measures. A dry run skips the compaction, so it
+/// may list more than a real trim drops.
+pub fn trim_store(
+    db: &Database,
+    index: &mut EmailIndex,
+    raw: &RawStore,
+    cap: StoreCap,
+    dry_run: bool,
+) -> Result<TrimReport> {
+    let size_before = StoreSize::measure(db, index, raw)?;
+    let mut report = TrimReport {
+        max_bytes: cap.max_bytes,
+        policy: cap.policy,
+        dry_run,
+        size_before,
+        size_after: size_before,
+        bytes_trimmed: 0,
+        trimmed: Vec::new(),
+    };
+    if size_before.total <= cap.max_bytes {
+        return Ok(report);
+    }
+    let candidates = db.trim_candidates(cap.policy)?;
+    if candidates.is_empty() {
+        return Ok(report);
+    }
+    // Space held by deleted documents and free pages may be enough.
+    let mut size = size_before;
+    if !dry_run {
+        compact(db, index)?;
+        size = StoreSize::measure(db, index, raw)?;
+        report.size_after = size;
+        if size.total <= cap.max_bytes {
+            return Ok(report);
+        }
+    }
+
+    select(&mut report, candidates, size.total - cap.max_bytes, raw)?;
+    if dry_run || report.trimmed.is_empty() {
+        return Ok(report);
+    }
+    drop_bodies(db, index, raw, &report.trimmed)?;
+    compact(db, index)?;
+    report.size_after = StoreSize::measure(db, index, raw)?;
+    Ok(report)
+}
+
+/// Trims a store over `cap` after a sync cycle. Unlike [`trim_store`] it
+/// compacts only when enough trimmed space has built up, or it has waited a
+/// day; until then that space counts as free.
+pub fn trim_after_sync(
+    db: &Database,
+    index: &mut EmailIndex,
+    raw: &RawStore,
+    cap: StoreCap,
+) -> Result<TrimReport> {
+    let mut uncompacted = sync_state_u64(db, UNCOMPACTED_BYTES_KEY)?;
+    let size_before = StoreSize::measure(db, index, raw)?;
+    let mut report = TrimReport {
+        max_bytes: cap.max_bytes,
+        policy: cap.policy,
+        dry_run: false,
+        size_before,
+        size_after: size_before,
+        bytes_trimmed: 0,
+        trimmed: Vec::new(),
+    };
+    let live = size_before.total.saturating_sub(uncompacted);
+    if live > cap.max_bytes {
+        let candidates = db.trim_candidates(cap.policy)?;
+        select(&mut report, candidates, live - cap.max_bytes, raw)?;
+    }
+    if !report.trimmed.is_empty() {
+        drop_bodies(db, index, raw, &report.trimmed)?;
+        if uncompacted == 0 {
+            let now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+            db.set_sync_state(UNCOMPACTED_SINCE_KEY, &now)?;
+        }
+        // Raw sources are files of their own and are gone already.
+        uncompacted += report
+            .trimmed
+            .iter()
+            .map(|email| email.body_bytes)
+            .sum::<u64>();
+        db.set_sync_state(UNCOMPACTED_BYTES_KEY, &uncompacted.to_string())?;
+    }
+    if uncompacted == 0 {
+        return Ok(report);
+    }
+    let since = db
+        .get_sync_state(UNCOMPACTED_SINCE_KEY)?
+        .and_then(|state| state.value)
+        .and_then(|value| DateTime::parse_from_rfc3339(&value).ok());
+    let waited_a_day = since.is_none_or(|since| {
+        Utc::now().signed_duration_since(since) >= chrono::Duration::hours(COMPACT_AFTER_HOURS)
+    });
+    if uncompacted >= cap.max_bytes / COMPACT_SHARE_OF_CAP || waited_a_day {
+        compact(db, index)?;
+    }
+    report.size_after = StoreSize::measure(db, index, raw)?;
+    Ok(report)
+}
+
+/// Adds candidates to the report until their bodies and raw sources cover
+/// `excess` bytes.
+fn select(
+    report: &mut TrimReport,
+    candidates: Vec<TrimCandidate>,
+    excess: u64,
+    raw: &RawStore,
+) -> Result<()> {
+    for candidate in candidates {
+        if report.bytes_trimmed >= excess {
+            break;
+        }
+        report.bytes_trimmed += candidate.body_bytes + file_size(&raw.path(&candidate.id))?;
+        report.trimmed.push(candidate);
+    }
+    Ok(())
+}
+
+/// Drops the bodies and raw sources of `trimmed` and indexes them again.
+fn drop_bodies(
+    db: &Database,
+    index: &mut EmailIndex,
+    raw: &RawStore,
+    trimmed: &[TrimCandidate],
+) -> Result<()> {
+    let ids: Vec<String> = trimmed.iter().map(|email| email.id.clone()).collect();
+    let now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+    db.trim_bodies(&ids, &now)?;
+    for id in &ids {
+        raw.delete(id)
+            .with_context(|| format!("delete raw message for {id}"))?;
+    }
+    index
+        .reindex_emails(db, &ids)
+        .context("index trimmed emails")?;
+    Ok(())
+}
+
+fn sync_state_u64(db: &Database, key: &str) -> Result<u64> {
+    Ok(db
+        .get_sync_state(key)?
+        .and_then(|state| state.value)
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0))
+}
+
+/// Merges away the index's deleted documents and vacuums the database,
+/// giving back the space trims left behind.
+fn compact(db: &Database, index: &mut EmailIndex) -> Result<()> {
+    index.optimize().context("compact ESS index")?;
+    db.delete_sync_state(UNCOMPACTED_BYTES_KEY)?;
+    db.delete_sync_state(UNCOMPACTED_SINCE_KEY)?;
+    db.vacuum()?;
+    Ok(())
+}
+
+/// Zero for a missing file.
+fn file_size(path: &Path) -> io::Result<u64> {
+    match fs::metadata(path) {
+        Ok(metadata) => Ok(metadata.len()),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(0),
+        Err(error) => Err(error),
+    }
+}
+
+/// Zero for a missing directory.
+fn directory_size(path: &Path) -> io::Result<u64> {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(0),
+        Err(error) => return Err(error),
+    };
+    let mut total = 0;
+    for entry in entries {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        total += if metadata.is_dir() {
+            directory_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{trim_after_sync, trim_store, UNCOMPACTED_BYTES_KEY, UNCOMPACTED_SINCE_KEY};
+    use crate::config::StoreCap;
+    use crate::db::models::Email;
+    use crate::db::{Database, EvictionPolicy};
+    use crate::indexer::EmailIndex;
+    use crate::raw::RawStore;
+
+    #[test]
+    fn trims_oldest_bodies_until_under_the_cap() {
+        let dir = std::env::temp_dir().join(format!("ess-trim-store-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("create dir");
+        let db = Database::open(&dir.join("ess.db")).expect("open db");
+        let mut index = EmailIndex::open(&dir.join("index")).expect("open index");
+        let raw = RawStore::new(dir.join("raw"));
+        for (id, received_at) in [
+            ("old", "2026-01-01T00:00:00Z"),
+            ("new", "2026-02-01T00:00:00Z"),
+        ] {
+            let email = Email {
+                subject: Some(format!("Subject {id}")),
+                from_address: Some("alice@example.com".to_string()),
+                body_text: Some("quarterly numbers ".repeat(100)),
+                received_at: received_at.to_string(),
+                ..Email::fixture(id)
+            };
+            db.insert_email(&email).expect("insert");
+            index.add_email(&email, "personal").expect("index");
+            raw.put(id, b"Subject: hi\r\n\r\nbody").expect("put raw");
+        }
+        // Already compact, so a trim has to drop a body.
+        index.optimize().expect("optimize");
+        db.vacuum().expect("vacuum");
+
+        let cap = |max_bytes| StoreCap {
+            max_bytes,
+            policy: EvictionPolicy::Oldest,
+        };
+        let roomy = trim_store(&db, &mut index, &raw, cap(u64::MAX), false).expect("trim");
+        assert!(roomy.trimmed.is_empty());
+
+        let over = roomy.size_before.total - 1;
+        let preview = trim_store(&db, &mut index, &raw, cap(over), true).expect("dry run");
+        assert_eq!(preview.trimmed.len(), 1);
+        assert_eq!(preview.trimmed[0].id, "old");
+        assert!(raw.contains("old"));
+
+        let report = trim_store(&db, &mut index, &raw, cap(over), false).expect("trim");
+        assert_eq!(report.trimmed.len(), 1);
+        assert!(!raw.contains("old"));
+        assert!(raw.contains("new"));
+        let old = db.get_email("old").expect("get").expect("email");
+        assert_eq!(old.body_text, None);
+        assert_eq!(old.subject.as_deref(), Some("Subject old"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn sync_trims_compact_only_once_enough_has_built_up() {
+        let dir = std::env::temp_dir().join(format!("ess-trim-sync-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("create dir");
+        let db = Database::open(&dir.join("ess.db")).expect("open db");
+        let mut index = EmailIndex::open(&dir.join("index")).expect("open index");
+        let raw = RawStore::new(dir.join("raw"));
+        for (id, received_at) in [
+            ("old", "2026-01-01T00:00:00Z"),
+            ("new", "2026-02-01T00:00:00Z"),
+        ] {
+            let email = Email {
+                body_text: Some("quarterly numbers ".repeat(100)),
+                received_at: received_at.to_string(),
+                ..Email::fixture(id)
+            };
+            db.insert_email(&email).expect("insert");
+            index.add_email(&email, "personal").expect("index");
+        }
+        // Starred, so it is never trimmed but keeps the cap large next to
+        // one body.
+        raw.put("new", &vec![b'x'; 1_000_000]).expect("put raw");
+        db.star_email("new").expect("star");
+        index.optimize().expect("optimize");
+        db.vacuum().expect("vacuum");
+
+        let uncompacted = |db: &Database| {
+            db.get_sync_state(UNCOMPACTED_BYTES_KEY)
+                .expect("state")
+                .and_then(|state| state.value)
+        };
+        let cap = |max_bytes| StoreCap {
+            max_bytes,
+            policy: EvictionPolicy::Oldest,
+        };
+        let size = trim_store(&db, &mut index, &raw, cap(u64::MAX), false)
+            .expect("measure")
+            .size_before
+            .total;
+
+        let report = trim_after_sync(&db, &mut index, &raw, cap(size - 1)).expect("trim");
+        assert_eq!(report.trimmed.len(), 1);
+        assert_eq!(uncompacted(&db).as_deref(), Some("1800"));
+
+        // The trimmed body counts as free, so nothing more goes.
+        let report = trim_after_sync(&db, &mut index, &raw, cap(size - 1)).expect("trim");
+        assert!(report.trimmed.is_empty());
+        assert_eq!(uncompacted(&db).as_deref(), Some("1800"));
+
+        db.set_sync_state(UNCOMPACTED_SINCE_KEY, "2026-01-01T00:00:00Z")
+            .expect("backdate");
+        trim_after_sync(&db, &mut index, &raw, cap(size - 1)).expect("trim");
+        assert_eq!(uncompacted(&db), None);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
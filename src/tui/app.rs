@@ -0,0 +1,390 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::db::{Database, EmailSearchFilters};
+use crate::indexer::EmailIndex;
+use crate::output::{self, SearchResultItem};
+use crate::search::{self, EmailFilters, Scope};
+
+/// Rows moved by PageUp and PageDown.
+const PAGE: usize = 10;
+
+/// Where key presses go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Focus {
+    Search,
+    Results,
+    Preview,
+}
+
+/// What the event loop has to fetch after a key press.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    None,
+    /// Re-run the query with the current filters.
+    Search,
+    /// Load the thread of the newly selected result.
+    Preview,
+}
+
+/// How the browser starts.
+#[derive(Debug, Clone)]
+pub struct TuiOptions {
+    pub query: String,
+    pub scope: Scope,
+    pub folder: Option<String>,
+    pub limit: usize,
+}
+
+pub struct App {
+    pub query: String,
+    pub scope: Scope,
+    /// Every stored folder name, most emails first.
+    pub folders: Vec<String>,
+    pub folder: Option<String>,
+    pub limit: usize,
+    pub results: Vec<SearchResultItem>,
+    pub selected: usize,
+    pub focus: Focus,
+    /// The selected email's thread, rendered like `ess thread`.
+    pub preview: String,
+    pub preview_scroll: u16,
+    /// The result count, or why the last search failed.
+    pub status: String,
+    pub quit: bool,
+}
+
+impl App {
+    pub fn new(options: TuiOptions) -> Self {
+        Self {
+            focus: if options.query.is_empty() {
+                Focus::Search
+            } else {
+                Focus::Results
+            },
+            query: options.query,
+            scope: options.scope,
+            folders: Vec::new(),
+            folder: options.folder,
+            limit: options.limit.max(1),
+            results: Vec::new(),
+            selected: 0,
+            preview: String::new(),
+            preview_scroll: 0,
+            status: String::new(),
+            quit: false,
+        }
+    }
+
+    pub fn load_folders(&mut self, db: &Database) -> anyhow::Result<()> {
+        let mut counts = Vec::<(String, i64)>::new();
+        for row in db.get_stats()?.emails_by_folder {
+            let Some(folder) = row.folder else {
+                continue;
+            };
+            match counts.iter_mut().find(|(name, _)| *name == folder) {
+                Some((_, count)) => *count += row.count,
+                None => counts.push((folder, row.count)),
+            }
+        }
+        counts.sort_by(|left, right| right.1.cmp(&left.1).then_with(|| left.0.cmp(&right.0)));
+        self.folders = counts.into_iter().map(|(name, _)| name).collect();
+        Ok(())
+    }
+
+    /// Runs the query, or lists the newest emails when it is empty, and
+    /// loads the first result's thread. Failures, such as a malformed
+    /// query, end up in the status line.
+    pub fn search(&mut self, db: &Database, index: &EmailIndex) {
+        self.selected = 0;
+        match self.fetch(db, index) {
+            Ok(results) => {
+                self.status = match results.len() {
+                    1 => "1 email".to_string(),
+                    count if count == self.limit => format!("first {count} emails"),
+                    count => format!("{count} emails"),
+                };
+                self.results = results;
+            }
+            Err(error) => {
+                self.status = format!("{error:#}");
+                self.results.clear();
+            }
+        }
+        self.load_preview(db);
+    }
+
+    fn fetch(&self, db: &Database, index: &EmailIndex) -> anyhow::Result<Vec<SearchResultItem>> {
+        let query = self.query.trim();
+        let mut items = if query.is_empty() {
+            db.search_emails(EmailSearchFilters {
+                query: None,
+                account_id: None,
+                account_type: match self.scope {
+                    Scope::All => None,
+                    scope => Some(scope.as_str().to_string()),
+                },
+                folder: self.folder.clone(),
+                from_address: None,
+                from_domain: None,
+                since: None,
+                until: None,
+                tag: None,
+                needs_reply_before: None,
+                limit: self.limit,
+                offset: 0,
+                after: None,
+            })?
+            .into_iter()
+            .map(|email| SearchResultItem {
+                email,
+                score: None,
+                snippet: None,
+                preview: None,
+            })
+            .collect::<Vec<_>>()
+        } else {
+            let filters = EmailFilters {
+                scope: self.scope,
+                folder: self.folder.clone(),
+                limit: self.limit,
+                ..EmailFilters::default()
+            };
+            search::search_emails(index, db, query, &filters)?
+                .into_iter()
+                .map(|result| SearchResultItem {
+                    email: result.email,
+                    score: Some(result.score),
+                    snippet: result.snippet,
+                    preview: None,
+                })
+                .collect()
+        };
+        output::attach_previews(db, &mut items)?;
+        Ok(items)
+    }
+
+    pub fn load_preview(&mut self, db: &Database) {
+        self.preview_scroll = 0;
+        let Some(email) = self.results.get(self.selected).map(|item| &item.email) else {
+            self.preview.clear();
+            return;
+        };
+        let thread = match email.conversation_id.as_deref() {
+            Some(conversation_id) => {
+                db.get_emails_by_conversation(conversation_id)
+                    .and_then(|emails| {
+                        Ok((
+                            emails,
+                            db.notes_for_conversation(conversation_id)?,
+                            db.summary(conversation_id)?,
+                        ))
+                    })
+            }
+            None => Ok((vec![email.clone()], Vec::new(), None)),
+        };
+        self.preview = match thread {
+            Ok((emails, _, _)) if emails.is_empty() => output::table::format_email(email, false),
+            Ok((emails, notes, summary)) => {
+                output::table::format_thread(&emails, &notes, summary.as_ref(), false)
+            }
+            Err(error) => format!("failed to load thread: {error}"),
+        };
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Action {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('c') if ctrl => {
+                self.quit = true;
+                return Action::None;
+            }
+            KeyCode::Char('s') if ctrl => return self.next_scope(),
+            KeyCode::Char('f') if ctrl => return self.next_folder(),
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Focus::Search => Focus::Results,
+                    Focus::Results => Focus::Preview,
+                    Focus::Preview => Focus::Search,
+                };
+                return Action::None;
+            }
+            KeyCode::BackTab => {
+                self.focus = match self.focus {
+                    Focus::Search => Focus::Preview,
+                    Focus::Results => Focus::Search,
+                    Focus::Preview => Focus::Results,
+                };
+                return Action::None;
+            }
+            _ => {}
+        }
+        match self.focus {
+            Focus::Search => self.search_key(key, ctrl),
+            Focus::Results => self.results_key(key),
+            Focus::Preview => self.preview_key(key),
+        }
+    }
+
+    fn search_key(&mut self, key: KeyEvent, ctrl: bool) -> Action {
+        match key.code {
+            KeyCode::Enter => {
+                self.focus = Focus::Results;
+                Action::Search
+            }
+            KeyCode::Esc | KeyCode::Down => {
+                self.focus = Focus::Results;
+                Action::None
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                Action::None
+            }
+            KeyCode::Char('u') if ctrl => {
+                self.query.clear();
+                Action::None
+            }
+            KeyCode::Char(ch) if !ctrl => {
+                self.query.push(ch);
+                Action::None
+            }
+            _ => Action::None,
+        }
+    }
+
+    fn results_key(&mut self, key: KeyEvent) -> Action {
+        let last = self.results.len().saturating_sub(1);
+        let selected = match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => {
+                self.quit = true;
+                return Action::None;
+            }
+            KeyCode::Char('/') => {
+                self.focus = Focus::Search;
+                return Action::None;
+            }
+            KeyCode::Char('s') => return self.next_scope(),
+            KeyCode::Char('f') => return self.next_folder(),
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => {
+                self.focus = Focus::Preview;
+                return Action::None;
+            }
+            KeyCode::Down | KeyCode::Char('j') => (self.selected + 1).min(last),
+            KeyCode::Up | KeyCode::Char('k') => self.selected.saturating_sub(1),
+            KeyCode::PageDown => (self.selected + PAGE).min(last),
+            KeyCode::PageUp => self.selected.saturating_sub(PAGE),
+            KeyCode::Home | KeyCode::Char('g') => 0,
+            KeyCode::End | KeyCode::Char('G') => last,
+            _ => return Action::None,
+        };
+        if selected == self.selected {
+            return Action::None;
+        }
+        self.selected = selected;
+        Action::Preview
+    }
+
+    fn preview_key(&mut self, key: KeyEvent) -> Action {
+        match key.code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Esc | KeyCode::Left | KeyCode::Char('h') => self.focus = Focus::Results,
+            KeyCode::Char('/') => self.focus = Focus::Search,
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.preview_scroll = self.preview_scroll.saturating_add(1)
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.preview_scroll = self.preview_scroll.saturating_sub(1)
+            }
+            KeyCode::PageDown | KeyCode::Char(' ') => {
+                self.preview_scroll = self.preview_scroll.saturating_add(PAGE as u16)
+            }
+            KeyCode::PageUp => {
+                self.preview_scroll = self.preview_scroll.saturating_sub(PAGE as u16)
+            }
+            KeyCode::Home | KeyCode::Char('g') => self.preview_scroll = 0,
+            _ => {}
+        }
+        Action::None
+    }
+
+    /// All, professional, personal, and round again.
+    fn next_scope(&mut self) -> Action {
+        self.scope = match self.scope {
+            Scope::All => Scope::Professional,
+            Scope::Professional => Scope::Personal,
+            Scope::Personal => Scope::All,
+        };
+        Action::Search
+    }
+
+    /// Every folder in turn, then no folder filter.
+    fn next_folder(&mut self) -> Action {
+        let position = self
+            .folder
+            .as_ref()
+            .and_then(|current| self.folders.iter().position(|name| name == current));
+        self.folder = match position {
+            Some(idx) => self.folders.get(idx + 1).cloned(),
+            None if self.folder.is_some() => None,
+            None => self.folders.first().cloned(),
+        };
+        Action::Search
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use super::{Action, App, Focus, TuiOptions};
+    use crate::search::Scope;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn keys_edit_the_query_move_focus_and_cycle_filters() {
+        let mut app = App::new(TuiOptions {
+            query: String::new(),
+            scope: Scope::All,
+            folder: None,
+            limit: 50,
+        });
+        app.folders = vec!["inbox".to_string(), "archive".to_string()];
+        assert_eq!(app.focus, Focus::Search);
+
+        for ch in "q budget".chars() {
+            assert_eq!(app.handle_key(key(KeyCode::Char(ch))), Action::None);
+        }
+        app.handle_key(key(KeyCode::Backspace));
+        assert_eq!(app.query, "q budge");
+        assert!(!app.quit);
+        assert_eq!(app.handle_key(key(KeyCode::Enter)), Action::Search);
+        assert_eq!(app.focus, Focus::Results);
+
+        assert_eq!(
+            app.handle_key(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL)),
+            Action::Search
+        );
+        assert_eq!(app.scope, Scope::Professional);
+        let folders = (0..3)
+            .map(|_| {
+                app.handle_key(key(KeyCode::Char('f')));
+                app.folder.clone()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            folders,
+            [Some("inbox".to_string()), Some("archive".to_string()), None]
+        );
+
+        // Nothing to select in an empty result list.
+        assert_eq!(app.handle_key(key(KeyCode::Char('j'))), Action::None);
+        app.handle_key(key(KeyCode::Tab));
+        assert_eq!(app.focus, Focus::Preview);
+        app.handle_key(key(KeyCode::PageDown));
+        assert_eq!(app.preview_scroll, 10);
+        app.handle_key(key(KeyCode::Char('q')));
+        assert!(app.quit);
+    }
+}
@@ -0,0 +1,57 @@
+//! `ess tui`: an interactive three-pane mail browser.
+//!
+//! A search box sits above the result list and a preview of the selected
+//! email's thread. A query runs through [`crate::search::search_emails`],
+//! exactly as `ess search` would; an empty query lists the newest emails
+//! like `ess list`. The scope and folder filters apply to both and are
+//! cycled from the keyboard. The preview is the whole conversation from
+//! `Database::get_emails_by_conversation`, with its notes.
+
+mod app;
+mod ui;
+
+pub use app::TuiOptions;
+
+use anyhow::Result;
+use ratatui::crossterm::event::{self, Event, KeyEventKind};
+use ratatui::DefaultTerminal;
+
+use crate::db::Database;
+use crate::indexer::EmailIndex;
+use crate::tui::app::{Action, App};
+
+/// Takes over the terminal until the user quits. The terminal is restored
+/// on return and, through ratatui's panic hook, on a panic.
+pub fn run(db: &Database, index: &EmailIndex, options: TuiOptions) -> Result<()> {
+    let mut app = App::new(options);
+    app.load_folders(db)?;
+    app.search(db, index);
+
+    let mut terminal = ratatui::init();
+    let outcome = event_loop(&mut terminal, &mut app, db, index);
+    ratatui::restore();
+    outcome
+}
+
+fn event_loop(
+    terminal: &mut DefaultTerminal,
+    app: &mut App,
+    db: &Database,
+    index: &EmailIndex,
+) -> Result<()> {
+    while !app.quit {
+        terminal.draw(|frame| ui::draw(frame, app))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match app.handle_key(key) {
+            Action::Search => app.search(db, index),
+            Action::Preview => app.load_preview(db),
+            Action::None => {}
+        }
+    }
+    Ok(())
+}
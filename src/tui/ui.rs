@@ -0,0 +1,169 @@
+use chrono::{DateTime, Local};
+use ratatui::layout::{Constraint, Layout, Position, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::Frame;
+use unicode_width::UnicodeWidthStr;
+
+use crate::output::SearchResultItem;
+use crate::tui::app::{App, Focus};
+
+const KEY_HELP: &str = "Enter search  / query  Tab pane  j/k move  ^S scope  ^F folder  q quit";
+
+pub fn draw(frame: &mut Frame, app: &App) {
+    let [search_area, body_area, status_area] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(3),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [results_area, preview_area] =
+        Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .areas(body_area);
+
+    draw_search(frame, app, search_area);
+    draw_results(frame, app, results_area);
+
+    let preview = Paragraph::new(app.preview.as_str())
+        .block(pane("Thread", app.focus == Focus::Preview))
+        .wrap(Wrap { trim: false })
+        .scroll((app.preview_scroll, 0));
+    frame.render_widget(preview, preview_area);
+
+    let status = Line::from(vec![
+        Span::styled(
+            format!(" {} ", app.status),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(KEY_HELP, Style::default().add_modifier(Modifier::DIM)),
+    ]);
+    frame.render_widget(Paragraph::new(status), status_area);
+}
+
+fn draw_search(frame: &mut Frame, app: &App, area: Rect) {
+    let title = format!(
+        "Search (scope: {}, folder: {})",
+        app.scope.as_str(),
+        app.folder.as_deref().unwrap_or("all")
+    );
+    let focused = app.focus == Focus::Search;
+    frame.render_widget(
+        Paragraph::new(app.query.as_str()).block(pane(&title, focused)),
+        area,
+    );
+    if focused {
+        let column = area.x + 1 + app.query.width().min(u16::MAX as usize) as u16;
+        frame.set_cursor_position(Position::new(
+            column.min(area.right().saturating_sub(2)),
+            area.y + 1,
+        ));
+    }
+}
+
+fn draw_results(frame: &mut Frame, app: &App, area: Rect) {
+    let items = app.results.iter().map(result_item).collect::<Vec<_>>();
+    let list = List::new(items)
+        .block(pane("Results", app.focus == Focus::Results))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default();
+    if !app.results.is_empty() {
+        state.select(Some(app.selected));
+    }
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+/// Date and sender on the first line, subject on the second; unread
+/// emails in bold.
+fn result_item(item: &SearchResultItem) -> ListItem<'static> {
+    let email = &item.email;
+    let date = DateTime::parse_from_rfc3339(&email.received_at)
+        .map(|date| date.with_timezone(&Local).format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|_| email.received_at.chars().take(10).collect());
+    let sender = email
+        .from_name
+        .clone()
+        .or_else(|| email.from_address.clone())
+        .unwrap_or_else(|| "(unknown)".to_string());
+    let subject = email
+        .subject
+        .clone()
+        .unwrap_or_else(|| "(no subject)".to_string());
+    let style = if email.is_read == Some(false) {
+        Style::default().add_modifier(Modifier::BOLD)
+    } else {
+        Style::default()
+    };
+    ListItem::new(vec![
+        Line::from(vec![
+            Span::styled(date, Style::default().fg(Color::DarkGray)),
+            Span::raw(" "),
+            Span::styled(sender, style),
+        ]),
+        Line::from(Span::styled(format!("  {subject}"), style)),
+    ])
+}
+
+fn pane(title: &str, focused: bool) -> Block<'static> {
+    let block = Block::bordered().title(format!(" {title} "));
+    if focused {
+        block.border_style(Style::default().fg(Color::Cyan))
+    } else {
+        block
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    use super::draw;
+    use crate::db::models::Email;
+    use crate::output::SearchResultItem;
+    use crate::search::Scope;
+    use crate::tui::app::{App, TuiOptions};
+
+    #[test]
+    fn draws_search_box_results_and_thread() {
+        let mut app = App::new(TuiOptions {
+            query: "budget".to_string(),
+            scope: Scope::Professional,
+            folder: Some("inbox".to_string()),
+            limit: 50,
+        });
+        app.results = vec![SearchResultItem {
+            email: Email {
+                subject: Some("Q3 budget".to_string()),
+                from_address: Some("alice@example.com".to_string()),
+                from_name: Some("Alice".to_string()),
+                received_at: "2026-02-01T10:00:00Z".to_string(),
+                is_read: Some(false),
+                ..Email::fixture("e1")
+            },
+            score: Some(1.0),
+            snippet: None,
+            preview: None,
+        }];
+        app.preview = "Subject: Q3 budget\n\nNumbers attached.".to_string();
+        app.status = "1 email".to_string();
+
+        let mut terminal = Terminal::new(TestBackend::new(80, 12)).expect("terminal");
+        terminal.draw(|frame| draw(frame, &app)).expect("draw");
+        let screen = terminal
+            .backend()
+            .buffer()
+            .content()
+            .chunks(80)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect::<Vec<_>>();
+
+        assert!(screen[0].contains("Search (scope: professional, folder: inbox)"));
+        assert!(screen[1].contains("budget"));
+        assert!(screen[3].contains(" Results ") && screen[3].contains(" Thread "));
+        assert!(screen[4].contains("Alice"));
+        assert!(screen[5].contains("  Q3 budget"));
+        assert!(screen[6].contains("Numbers attached."));
+        assert!(screen[11].starts_with(" 1 email "));
+    }
+}
@@ -0,0 +1,241 @@
+//! Graph and Gmail syncs replayed from the recorded HTTP fixtures in
+//! `tests/fixtures/http/` (see `ess sync --record`). Each test owns the
+//! environment variables of one connector, so the two run side by side.
+
+use std::path::{Path, PathBuf};
+
+use ess::connectors::recording::HttpFixture;
+use ess::connectors::{ConnectorError, EmailConnector, GmailApiConnector, GraphApiConnector};
+use ess::db::models::{Account, AccountType};
+use ess::db::Database;
+use ess::env;
+use ess::indexer::EmailIndex;
+use serde_json::json;
+use uuid::Uuid;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn temp_root() -> PathBuf {
+    let root = std::env::temp_dir().join(format!("ess-http-fixtures-it-{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&root).expect("create temp test root");
+    root
+}
+
+fn fixture(name: &str) -> HttpFixture {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/http")
+        .join(format!("{name}.json"));
+    HttpFixture::load(&path).expect("load HTTP fixture")
+}
+
+/// Serves each exchange exactly once, in fixture order; the server panics
+/// on drop if one was never requested.
+async fn replay(server: &MockServer, fixture: &HttpFixture) {
+    for exchange in &fixture.exchanges {
+        let mut mock =
+            Mock::given(method(exchange.method.as_str())).and(path(exchange.path.as_str()));
+        for (name, value) in exchange.query_pairs() {
+            mock = mock.and(query_param(name, value));
+        }
+        let body = exchange.body_for(&server.uri());
+        let mut response = match exchange.headers.get("content-type") {
+            Some(content_type) => {
+                ResponseTemplate::new(exchange.status).set_body_raw(body, content_type)
+            }
+            None => ResponseTemplate::new(exchange.status).set_body_string(body),
+        };
+        for (name, value) in &exchange.headers {
+            if name != "content-type" {
+                response = response.insert_header(name.as_str(), value.as_str());
+            }
+        }
+        mock.respond_with(response)
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(server)
+            .await;
+    }
+}
+
+async fn mount_token_endpoint(server: &MockServer) {
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "access_token": "fixture-token",
+            "token_type": "Bearer",
+            "expires_in": 3600
+        })))
+        .mount(server)
+        .await;
+}
+
+fn account(id: &str, config: serde_json::Value) -> Account {
+    Account {
+        account_id: id.to_string(),
+        email_address: "me@example.com".to_string(),
+        display_name: None,
+        tenant_id: Some("fixture-tenant".to_string()),
+        account_type: AccountType::Professional,
+        enabled: true,
+        last_sync: None,
+        config: Some(config),
+    }
+}
+
+#[tokio::test]
+async fn graph_sync_pages_through_throttling_and_delta_rounds() {
+    let server = MockServer::start().await;
+    mount_token_endpoint(&server).await;
+    std::env::set_var(env::GRAPH_API_BASE, format!("{}/graph", server.uri()));
+    std::env::set_var(env::GRAPH_TOKEN_URL, format!("{}/token", server.uri()));
+
+    let root = temp_root();
+    let db = Database::open(&root.join("ess.db")).expect("open db");
+    let mut index = EmailIndex::open(&root.join("index")).expect("open index");
+    let account = account(
+        "graph-acc",
+        json!({"client_id": "fixture-client", "client_secret": "fixture-secret"}),
+    );
+    let connector = GraphApiConnector::new();
+    let delta_key = "graph_delta_link:graph-acc:inbox-id";
+
+    replay(&server, &fixture("graph_initial_sync")).await;
+    let report = connector
+        .sync(&db, &mut index, &account)
+        .await
+        .expect("initial sync");
+    assert_eq!(report.emails_added, 2, "{:?}", report.errors);
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    let delta_link = db
+        .get_sync_state(delta_key)
+        .expect("read delta link")
+        .and_then(|state| state.value)
+        .expect("delta link stored");
+    assert!(delta_link.ends_with("/delta?$deltatoken=round1"));
+    let folders = db
+        .discovered_folders(Some("graph-acc"))
+        .expect("discovered folders");
+    let decisions: Vec<_> = folders
+        .iter()
+        .map(|folder| {
+            (
+                folder.path.as_str(),
+                folder.label.as_deref(),
+                folder.included,
+            )
+        })
+        .collect();
+    assert_eq!(
+        decisions,
+        [("Inbox", Some("inbox"), true), ("Sync Issues", None, false)]
+    );
+    assert_eq!(folders[0].stored_emails, 2);
+    assert!(folders[1].hidden);
+    // Every fixture exchange, throttled ones included, plus the token
+    // requests.
+    let usage = connector.take_usage();
+    assert!(usage.requests > 7 && usage.bytes > 0, "{usage:?}");
+    assert!(connector.take_usage().is_empty());
+
+    replay(&server, &fixture("graph_delta_sync")).await;
+    let report = connector
+        .sync(&db, &mut index, &account)
+        .await
+        .expect("delta sync");
+    assert_eq!(
+        (report.emails_added, report.emails_updated),
+        (1, 2),
+        "{:?}",
+        report.errors
+    );
+    assert!(db.get_email("m1").expect("get m1").is_none());
+    let budget = db.get_email("m2").expect("get m2").expect("m2 stored");
+    assert_eq!(budget.subject.as_deref(), Some("Budget review"));
+    assert_eq!(budget.is_read, Some(true));
+    assert_eq!(
+        db.get_email("m3")
+            .expect("get m3")
+            .and_then(|email| email.folder)
+            .as_deref(),
+        Some("inbox")
+    );
+    let delta_link = db
+        .get_sync_state(delta_key)
+        .expect("read delta link")
+        .and_then(|state| state.value)
+        .expect("delta link stored");
+    assert!(delta_link.ends_with("/delta?$deltatoken=round2"));
+    let states = db
+        .folder_sync_states(Some("graph-acc"))
+        .expect("folder sync state");
+    assert_eq!(states.len(), 1);
+    assert_eq!(states[0].folder, "inbox");
+    assert_eq!((states[0].emails_added, states[0].emails_updated), (3, 2));
+    assert!(states[0].last_delta_at.is_some());
+    assert_eq!(states[0].last_error, None);
+
+    let _ = std::fs::remove_dir_all(root);
+}
+
+#[tokio::test]
+async fn gmail_expired_history_falls_back_to_a_batched_full_sync() {
+    let server = MockServer::start().await;
+    mount_token_endpoint(&server).await;
+    std::env::set_var(env::GMAIL_API_BASE, format!("{}/gmail", server.uri()));
+    std::env::set_var(
+        env::GMAIL_BATCH_URL,
+        format!("{}/gmail-batch", server.uri()),
+    );
+    std::env::set_var(env::GMAIL_TOKEN_URL, format!("{}/token", server.uri()));
+
+    let root = temp_root();
+    let db = Database::open(&root.join("ess.db")).expect("open db");
+    let mut index = EmailIndex::open(&root.join("index")).expect("open index");
+    let account = account(
+        "gmail-acc",
+        json!({
+            "client_id": "fixture-client",
+            "client_secret": "fixture-secret",
+            "refresh_token": "fixture-refresh"
+        }),
+    );
+    db.insert_account(&account).expect("insert account");
+    db.set_sync_state("gmail_history_id:gmail-acc", "100")
+        .expect("store expired history id");
+
+    replay(&server, &fixture("gmail_history_expired")).await;
+    let report = GmailApiConnector::new()
+        .sync(&db, &mut index, &account)
+        .await
+        .expect("sync");
+
+    assert_eq!(report.emails_added, 1, "{:?}", report.errors);
+    assert_eq!(report.errors.len(), 2, "{:?}", report.errors);
+    assert!(report
+        .errors
+        .iter()
+        .any(|error| error.to_string().contains("quarantined as #")));
+    assert!(report
+        .errors
+        .iter()
+        .any(|error| matches!(error, ConnectorError::NotFound { .. })));
+    let lunch = db.get_email("g1").expect("get g1").expect("g1 stored");
+    assert_eq!(lunch.subject.as_deref(), Some("Lunch on Friday"));
+    assert_eq!(lunch.from_address.as_deref(), Some("bob@example.com"));
+    assert_eq!(
+        lunch.body_text.as_deref(),
+        Some("Lunch on Friday, see you there.")
+    );
+    let quarantined = db.quarantined(Some("gmail-acc"), None).expect("quarantine");
+    assert_eq!(quarantined.len(), 1);
+    assert_eq!(quarantined[0].message_id.as_deref(), Some("g2"));
+    assert_eq!(
+        db.get_sync_state("gmail_history_id:gmail-acc")
+            .expect("read history id")
+            .and_then(|state| state.value)
+            .as_deref(),
+        Some("250")
+    );
+
+    let _ = std::fs::remove_dir_all(root);
+}
@@ -41,10 +41,7 @@ fn email(
         from_address: Some(from_address.to_string()),
         from_name: Some(from_name.to_string()),
         to_addresses: vec!["owner@example.com".to_string()],
-        cc_addresses: vec![],
-        bcc_addresses: vec![],
         body_text: Some("Kickoff agenda and notes".to_string()),
-        body_html: None,
         body_preview: Some("Kickoff agenda".to_string()),
         received_at: received_at.to_string(),
         sent_at: Some(received_at.to_string()),
@@ -53,9 +50,7 @@ fn email(
         has_attachments: Some(false),
         folder: Some("inbox".to_string()),
         categories: vec!["work".to_string()],
-        flag_status: None,
-        web_link: None,
-        metadata: None,
+        ..Email::default()
     }
 }
 